@@ -0,0 +1,20 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use symor::performance::IncrementalSync;
+use std::path::PathBuf;
+/// Block hashing below and well above the size where it moves to the thread pool, to
+/// catch the parallel path costing more than it saves
+fn block_hashing(c: &mut Criterion) {
+    let mut group = c.benchmark_group("calculate_blocks");
+    for size in [64 * 1024, 64 * 1024 * 1024] {
+        let content: Vec<u8> = (0..size).map(|i| (i % 251) as u8).collect();
+        group.throughput(Throughput::Bytes(size as u64));
+        group.sample_size(10);
+        group.bench_with_input(BenchmarkId::from_parameter(size), &content, |b, content| {
+            let mut sync = IncrementalSync::new(4096);
+            b.iter(|| sync.store_blocks(PathBuf::from("bench"), content));
+        });
+    }
+    group.finish();
+}
+criterion_group!(benches, block_hashing);
+criterion_main!(benches);
\ No newline at end of file
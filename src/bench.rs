@@ -0,0 +1,280 @@
+use crate::{
+    errors::{Context, ErrorCode, Result, SymorError},
+    performance::{memory, ParallelProcessor},
+    versioning::filter::{parse_quantity, SIZE_UNITS},
+    SymorManager,
+};
+use flate2::{write::GzEncoder, Compression};
+use std::{
+    fs, io::Write, path::{Path, PathBuf}, time::{Duration, Instant},
+};
+/// Sample size `sym bench` uses by default
+pub const DEFAULT_SAMPLE_SIZE: usize = 16 * 1024 * 1024;
+/// Smallest sample that takes long enough to time
+const MIN_SAMPLE_SIZE: usize = 64 * 1024;
+/// Chunk each benchmark worker hashes and compresses at a time
+const WORKER_CHUNK: usize = 1024 * 1024;
+/// Small writes timed for the version store latency
+const STORE_WRITES: usize = 32;
+/// Levels within this share of the smallest output count as compressing as well
+const LEVEL_TOLERANCE: f64 = 0.02;
+/// Worker counts reaching this share of the best throughput count as fast enough
+const WORKER_TOLERANCE: f64 = 0.9;
+/// Gzip output of the sample at one level
+#[derive(Debug, Clone, PartialEq)]
+pub struct LevelResult {
+    pub level: u8,
+    pub compressed: u64,
+    /// Bytes of input per second
+    pub throughput: f64,
+}
+/// Hashing and compressing the sample with some workers at once
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorkerResult {
+    pub workers: usize,
+    pub throughput: f64,
+}
+/// Writing the sample into the directory of a mirror target
+#[derive(Debug, Clone)]
+pub struct CopyResult {
+    pub target: PathBuf,
+    pub throughput: Result<f64>,
+}
+/// Time to write one small version file into the version store
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StoreLatency {
+    pub median: Duration,
+    pub max: Duration,
+}
+/// What `SymorManager::benchmark` measured on this machine
+#[derive(Debug, Clone)]
+pub struct BenchReport {
+    pub sample_size: u64,
+    /// MD5 bytes per second on one thread
+    pub hash_throughput: f64,
+    pub levels: Vec<LevelResult>,
+    pub workers: Vec<WorkerResult>,
+    pub copies: Vec<CopyResult>,
+    pub store: Result<StoreLatency>,
+}
+impl BenchReport {
+    /// Lowest level whose output is within 2% of the smallest
+    pub fn recommended_level(&self) -> Option<u8> {
+        let smallest = self.levels.iter().map(|l| l.compressed).min()? as f64;
+        self.levels
+            .iter()
+            .find(|l| l.compressed as f64 <= smallest * (1.0 + LEVEL_TOLERANCE))
+            .map(|l| l.level)
+    }
+    /// Fewest workers reaching 90% of the best throughput
+    pub fn recommended_workers(&self) -> Option<usize> {
+        let best = self.workers.iter().map(|w| w.throughput).fold(0.0, f64::max);
+        self.workers
+            .iter()
+            .find(|w| w.throughput >= best * WORKER_TOLERANCE)
+            .map(|w| w.workers)
+    }
+}
+/// Sample size given as `--size`, like `64M`
+pub fn parse_sample_size(text: &str) -> Result<usize> {
+    match parse_quantity(text.trim(), SIZE_UNITS) {
+        Some(size) if size >= MIN_SAMPLE_SIZE as u64 => Ok(size as usize),
+        _ => {
+            Err(
+                SymorError::new(
+                    ErrorCode::InvalidConfiguration,
+                    format!("Invalid sample size: {}", text),
+                )
+                    .with_suggestion("Use a size of at least 64K, like '64M'".to_string()),
+            )
+        }
+    }
+}
+fn throughput(bytes: usize, elapsed: Duration) -> f64 {
+    bytes as f64 / elapsed.as_secs_f64().max(1e-9)
+}
+/// Text-like content that compresses about as well as source code and documents
+fn sample(size: usize) -> Vec<u8> {
+    const WORDS: &[&str] = &[
+        "the", "file", "version", "mirror", "config", "let", "value", "return", "error", "sync",
+        "target", "path", "fn", "struct", "impl", "self", "data", "size", "time", "result",
+    ];
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    let mut data = Vec::with_capacity(size + 16);
+    while data.len() < size {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        match state % 8 {
+            0 => data.extend_from_slice(format!("{} ", state % 100_000).as_bytes()),
+            1 => data.push(b'\n'),
+            _ => {
+                data.extend_from_slice(WORDS[(state >> 8) as usize % WORDS.len()].as_bytes());
+                data.push(b' ');
+            }
+        }
+    }
+    data.truncate(size);
+    data
+}
+fn gzip_len(data: &[u8], level: u8) -> Result<u64> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::new(level as u32));
+    encoder.write_all(data)?;
+    Ok(encoder.finish().context("Failed to compress data")?.len() as u64)
+}
+/// 1, 2, 4, ... up to the CPU count, as far as the memory budget allows
+fn worker_counts() -> Vec<usize> {
+    let most = memory::budget()
+        .concurrency(ParallelProcessor::get_optimal_concurrency(), 2 * WORKER_CHUNK as u64);
+    let mut counts: Vec<usize> = (0..)
+        .map(|shift| 1 << shift)
+        .take_while(|&n| n < most)
+        .collect();
+    counts.push(most);
+    counts
+}
+/// Writes `data` to a new file in `dir` and syncs it to disk, then removes it
+fn timed_write(dir: &Path, data: &[u8]) -> Result<Duration> {
+    let path = dir.join(format!(".symor-bench-{}.tmp", std::process::id()));
+    let start = Instant::now();
+    let written = fs::File::create(&path)
+        .and_then(|mut file| file.write_all(data).and_then(|_| file.sync_all()));
+    let elapsed = start.elapsed();
+    let _ = fs::remove_file(&path);
+    written.with_context(|| format!("cannot write to {:?}", dir))?;
+    Ok(elapsed)
+}
+impl SymorManager {
+    /// Measures hashing, compression at each level, parallel workers, copies to each
+    /// mirror target directory and version store writes on a `sample_size` byte sample
+    pub fn benchmark(&self, sample_size: usize) -> Result<BenchReport> {
+        let data = sample(sample_size);
+        let start = Instant::now();
+        std::hint::black_box(md5::compute(&data));
+        let hash_throughput = throughput(data.len(), start.elapsed());
+        let mut levels = Vec::new();
+        for level in 1..=9 {
+            let start = Instant::now();
+            let compressed = gzip_len(&data, level)?;
+            let throughput = throughput(data.len(), start.elapsed());
+            levels.push(LevelResult { level, compressed, throughput });
+        }
+        let chunks: Vec<&[u8]> = data.chunks(WORKER_CHUNK).collect();
+        let level = self.version_storage.compression_level();
+        let mut workers = Vec::new();
+        for count in worker_counts() {
+            let start = Instant::now();
+            let results = ParallelProcessor::new(count)
+                .map_parallel(
+                    &chunks,
+                    |chunk| {
+                        std::hint::black_box(md5::compute(chunk));
+                        gzip_len(chunk, level)
+                    },
+                );
+            results.into_iter().collect::<Result<Vec<_>>>()?;
+            let throughput = throughput(data.len(), start.elapsed());
+            workers.push(WorkerResult { workers: count, throughput });
+        }
+        let mut dirs: Vec<PathBuf> = Vec::new();
+        for target in self.mirrors().iter().flat_map(|m| &m.targets) {
+            let target = crate::dated::expand(target, std::time::SystemTime::now())?;
+            let dir = match target.parent() {
+                Some(parent) if !target.is_dir() => parent.to_path_buf(),
+                _ => target,
+            };
+            if !dirs.contains(&dir) {
+                dirs.push(dir);
+            }
+        }
+        let copies = dirs
+            .into_iter()
+            .map(|dir| {
+                let throughput = timed_write(&dir, &data)
+                    .map(|elapsed| throughput(data.len(), elapsed));
+                CopyResult { target: dir, throughput }
+            })
+            .collect();
+        Ok(BenchReport {
+            sample_size: data.len() as u64,
+            hash_throughput,
+            levels,
+            workers,
+            copies,
+            store: self.store_latency(&data[..data.len().min(4096)]),
+        })
+    }
+    fn store_latency(&self, data: &[u8]) -> Result<StoreLatency> {
+        self.ensure_writable("benchmark the version store")?;
+        let dir = self.version_storage.storage_path();
+        fs::create_dir_all(dir)?;
+        let mut times = (0..STORE_WRITES)
+            .map(|_| timed_write(dir, data))
+            .collect::<Result<Vec<_>>>()?;
+        times.sort();
+        Ok(StoreLatency { median: times[times.len() / 2], max: times[times.len() - 1] })
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+    #[test]
+    fn test_benchmark() {
+        assert_eq!(parse_sample_size("2m").unwrap(), 2 << 20);
+        assert!(parse_sample_size("1k").is_err());
+        let data = sample(64 * 1024);
+        assert_eq!(data, sample(64 * 1024));
+        let ratio = gzip_len(&data, 6).unwrap() as f64 / data.len() as f64;
+        assert!(ratio > 0.1 && ratio < 0.6, "sample compresses to {}", ratio);
+        let temp_dir = tempdir().unwrap();
+        let mut manager = SymorManager::new().unwrap();
+        manager.config.home_dir = temp_dir.path().join("home");
+        manager.version_storage = crate::versioning::storage::VersionStorage::with_config(
+            crate::versioning::storage::StorageConfig {
+                storage_path: temp_dir.path().join("home/versions"),
+                ..Default::default()
+            },
+        );
+        let target = temp_dir.path().join("target");
+        fs::create_dir_all(&target).unwrap();
+        manager.mirrors = vec![crate::MirrorSpec {
+            source: temp_dir.path().join("source"),
+            targets: vec![
+                target.clone(),
+                target.join("file.txt"),
+                temp_dir.path().join("gone/x"),
+            ],
+            bidirectional: false,
+            only: Vec::new(),
+            skip_hidden: false,
+            paused: false,
+            storm_threshold: None,
+            rollover: false,
+            keep_rotations: None,
+            link_rotations: false,
+            symlinks: Default::default(),
+        }];
+        let report = manager.benchmark(256 * 1024).unwrap();
+        assert_eq!(report.sample_size, 256 * 1024);
+        assert_eq!(report.levels.len(), 9);
+        assert_eq!(report.workers[0].workers, 1);
+        assert_eq!(report.copies.len(), 2);
+        assert!(report.copies[0].throughput.is_ok());
+        assert!(report.copies[1].throughput.is_err());
+        assert_eq!(fs::read_dir(&target).unwrap().count(), 0);
+        let store = report.store.clone().unwrap();
+        assert!(store.median <= store.max);
+        let report = BenchReport {
+            levels: [(1, 130), (6, 101), (9, 100)]
+                .map(|(level, compressed)| LevelResult { level, compressed, throughput: 1.0 })
+                .to_vec(),
+            workers: [(1, 100.0), (2, 190.0), (4, 200.0)]
+                .map(|(workers, throughput)| WorkerResult { workers, throughput })
+                .to_vec(),
+            ..report
+        };
+        assert_eq!(report.recommended_level(), Some(6));
+        assert_eq!(report.recommended_workers(), Some(2));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,431 @@
+use crate::{
+    case::CaseCollisions, errors::{Context, ErrorCode, ErrorRecovery, Result, SymorError},
+    events::EventHub, file_rules::FileRules, linking::LinkType, ownership::OwnerMap, storm,
+    symlinks::SymlinkMode, Mirror, PathFilter, SymorConfig, DEBOUNCE_DELAY,
+};
+use notify::{Config, RecommendedWatcher, RecursiveMode, Watcher};
+use std::{
+    path::PathBuf, sync::{atomic::AtomicBool, mpsc, Arc},
+    time::Duration,
+};
+/// What a directory sync does with target files the source no longer has
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DeletePolicy {
+    /// Remove them, so targets match the source exactly (excluded paths are kept)
+    #[default]
+    Mirror,
+    /// Leave them in place; files are only ever added or updated
+    Keep,
+}
+/// Options for a `Mirror`, started with `Mirror::builder`
+#[derive(Debug, Clone)]
+pub struct MirrorBuilder {
+    src: PathBuf,
+    targets: Vec<PathBuf>,
+    bidirectional: bool,
+    link_type: LinkType,
+    shadow_copy: bool,
+    metrics_home: Option<PathBuf>,
+    excludes: Vec<String>,
+    only: Vec<String>,
+    skip_hidden: bool,
+    debounce: Duration,
+    verify: bool,
+    delete_policy: DeletePolicy,
+    trash: bool,
+    max_syncs_per_minute: u32,
+    storm_threshold: u32,
+    rollover: bool,
+    keep_rotations: usize,
+    link_rotations: bool,
+    symlinks: SymlinkMode,
+    case_collisions: CaseCollisions,
+    normalize_unicode: bool,
+    owners: Option<OwnerMap>,
+    recovery: ErrorRecovery,
+    reclaim_config: Option<SymorConfig>,
+    file_rules: FileRules,
+    #[cfg(feature = "scripting")]
+    rules: Option<Arc<crate::rules::SyncRules>>,
+    events: EventHub,
+    paused: Arc<AtomicBool>,
+}
+impl MirrorBuilder {
+    pub fn new(src: impl Into<PathBuf>) -> Self {
+        Self {
+            src: src.into(),
+            targets: Vec::new(),
+            bidirectional: false,
+            link_type: LinkType::Copy,
+            shadow_copy: false,
+            metrics_home: None,
+            excludes: Vec::new(),
+            only: Vec::new(),
+            skip_hidden: false,
+            debounce: DEBOUNCE_DELAY,
+            verify: false,
+            delete_policy: DeletePolicy::default(),
+            trash: false,
+            max_syncs_per_minute: 0,
+            storm_threshold: storm::DEFAULT_THRESHOLD,
+            rollover: false,
+            keep_rotations: 0,
+            link_rotations: false,
+            symlinks: SymlinkMode::default(),
+            case_collisions: CaseCollisions::default(),
+            normalize_unicode: true,
+            owners: None,
+            recovery: ErrorRecovery::default(),
+            reclaim_config: None,
+            file_rules: FileRules::default(),
+            #[cfg(feature = "scripting")]
+            rules: None,
+            events: EventHub::default(),
+            paused: Arc::default(),
+        }
+    }
+    pub fn target(mut self, target: impl Into<PathBuf>) -> Self {
+        self.targets.push(target.into());
+        self
+    }
+    pub fn targets<I, P>(mut self, targets: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<PathBuf>,
+    {
+        self.targets.extend(targets.into_iter().map(Into::into));
+        self
+    }
+    /// Also watch the targets and copy their changes back to the source
+    pub fn bidirectional(mut self, bidirectional: bool) -> Self {
+        self.bidirectional = bidirectional;
+        self
+    }
+    /// Attach targets as links of this kind instead of copies
+    pub fn link_type(mut self, link_type: LinkType) -> Self {
+        self.link_type = link_type;
+        self
+    }
+    /// Read a locked source through a shadow copy instead of failing (Windows only)
+    pub fn shadow_copy(mut self, shadow_copy: bool) -> Self {
+        self.shadow_copy = shadow_copy;
+        self
+    }
+    /// Record syncs in the metrics store and status files under `home_dir`
+    pub fn metrics(mut self, home_dir: impl Into<PathBuf>) -> Self {
+        self.metrics_home = Some(home_dir.into());
+        self
+    }
+    /// Skip paths matching `pattern`, a glob matched against the path relative to the
+    /// mirror root and against the file name (`*.tmp`, `build/**`)
+    pub fn exclude(mut self, pattern: impl Into<String>) -> Self {
+        self.excludes.push(pattern.into());
+        self
+    }
+    /// Copy only files matching `pattern` (or another `only` pattern), matched like
+    /// excludes; directories are always walked
+    pub fn only(mut self, pattern: impl Into<String>) -> Self {
+        self.only.push(pattern.into());
+        self
+    }
+    /// Leave hidden files and directories inside the source out of the mirror
+    pub fn skip_hidden(mut self, skip_hidden: bool) -> Self {
+        self.skip_hidden = skip_hidden;
+        self
+    }
+    /// Wait this many milliseconds after the last change before syncing
+    pub fn debounce(mut self, millis: u64) -> Self {
+        self.debounce = Duration::from_millis(millis);
+        self
+    }
+    /// Read every copy back and compare it with the source
+    pub fn verify(mut self, verify: bool) -> Self {
+        self.verify = verify;
+        self
+    }
+    pub fn delete_policy(mut self, delete_policy: DeletePolicy) -> Self {
+        self.delete_policy = delete_policy;
+        self
+    }
+    /// Move target files that a sync replaces or deletes to the OS trash
+    pub fn trash(mut self, trash: bool) -> Self {
+        self.trash = trash;
+        self
+    }
+    /// Run at most this many syncs in any minute (0, the default, for no limit); changes
+    /// beyond it are coalesced into one later sync and a `RateLimited` event is sent
+    pub fn max_syncs_per_minute(mut self, max: u32) -> Self {
+        self.max_syncs_per_minute = max;
+        self
+    }
+    /// Treat more than this many change events in a second (200 by default, 0 never) as
+    /// a storm, such as a `git checkout` in the source: its events are not handled one
+    /// by one, and a single full rescan runs once they stop, sending `StormDetected`
+    pub fn storm_threshold(mut self, events_per_second: u32) -> Self {
+        self.storm_threshold = events_per_second;
+        self
+    }
+    /// Check every minute whether dated targets such as `/backup/%F/etc`, which are
+    /// expanded when the mirror is built, expand to a new path, and sync into it if so
+    pub fn rollover(mut self, rollover: bool) -> Self {
+        self.rollover = rollover;
+        self
+    }
+    /// Keep only the newest `keep` dated copies of each dated target, such as the last
+    /// seven of `/backup/%F/etc`, removing older ones when the mirror starts and rolls
+    /// over; 0, the default, keeps them all. The date has to sit in one path component.
+    pub fn keep_rotations(mut self, keep: usize) -> Self {
+        self.keep_rotations = keep;
+        self
+    }
+    /// Hard link files that did not change since the previous dated copy of a dated
+    /// target instead of copying them, so daily copies of a mostly unchanged tree take
+    /// little more space than one. Linked files are unlinked before the mirror rewrites
+    /// them, leaving earlier copies as they were.
+    pub fn link_rotations(mut self, link: bool) -> Self {
+        self.link_rotations = link;
+        self
+    }
+    /// What directory syncs do with symlinks inside the source: copy what they point to
+    /// (the default), recreate them on the targets, or leave them out
+    pub fn symlinks(mut self, mode: SymlinkMode) -> Self {
+        self.symlinks = mode;
+        self
+    }
+    /// What directory syncs do with source names that differ only in case, like
+    /// `Readme.md` and `README.md`, on a target that does not tell them apart: leave all
+    /// but the first out (the default) or rename them. Either way each is reported once
+    /// with a `CaseCollision` conflict.
+    pub fn case_collisions(mut self, policy: CaseCollisions) -> Self {
+        self.case_collisions = policy;
+        self
+    }
+    /// Match source and target names that differ only in Unicode normal form, such as a
+    /// decomposed (NFD) `café` from macOS and a composed (NFC) one from Linux, instead of
+    /// taking one for a new file and the other for a deleted one (on by default). A
+    /// matched target entry is written under the name it already has.
+    pub fn normalize_unicode(mut self, normalize: bool) -> Self {
+        self.normalize_unicode = normalize;
+        self
+    }
+    /// Give each copied file and directory the owner of its source, changed by `map`'s
+    /// rules; `None`, the default, leaves copies owned by the user running the mirror
+    pub fn owners(mut self, map: Option<OwnerMap>) -> Self {
+        self.owners = map;
+        self
+    }
+    /// Retry or skip failed file copies as these strategies say, like `SymorManager` does
+    /// for backups
+    pub fn recovery(mut self, recovery: ErrorRecovery) -> Self {
+        self.recovery = recovery;
+        self
+    }
+    /// When a write to a target hits a full disk, free space in the version store and home
+    /// directory `config` describes, then try the write once more
+    pub fn reclaim_space(mut self, config: SymorConfig) -> Self {
+        self.reclaim_config = Some(config);
+        self
+    }
+    /// Skip the files `rules` decide not to mirror
+    pub fn file_rules(mut self, rules: FileRules) -> Self {
+        self.file_rules = rules;
+        self
+    }
+    /// Skip files the rules' `include` rejects and settle two-way edit conflicts with
+    /// their `resolve`; see `SyncRules`
+    #[cfg(feature = "scripting")]
+    pub fn rules(mut self, rules: Arc<crate::rules::SyncRules>) -> Self {
+        self.rules = Some(rules);
+        self
+    }
+    /// Deliver sync events to `events`, e.g. one hub shared by several mirrors
+    pub fn events(mut self, events: EventHub) -> Self {
+        self.events = events;
+        self
+    }
+    /// Hold syncs while `paused` is set; changes seen meanwhile sync once it is cleared
+    pub fn pause_flag(mut self, paused: Arc<AtomicBool>) -> Self {
+        self.paused = paused;
+        self
+    }
+    /// Validates the options and starts watching the source (and targets, when
+    /// bidirectional); nothing is copied until `Mirror::run`
+    pub fn build(self) -> Result<Mirror> {
+        if self.targets.is_empty() {
+            return Err(
+                SymorError::new(
+                        ErrorCode::InvalidPath,
+                        format!("No targets given for {:?}", self.src),
+                    )
+                    .with_suggestion("Add at least one target with .target(path)".to_string()),
+            );
+        }
+        crate::dated::check_targets(&self.src, &self.targets, self.bidirectional)?;
+        if self.keep_rotations > 0 || self.link_rotations {
+            crate::dated::check_rotations(&self.src, &self.targets)?;
+        }
+        let dated = self.targets.iter().any(|target| crate::dated::is_dated(target));
+        let now = std::time::SystemTime::now();
+        let targets = self
+            .targets
+            .iter()
+            .map(|target| crate::dated::expand(target, now))
+            .collect::<Result<Vec<_>>>()?;
+        let mut previous_targets = Vec::new();
+        if self.link_rotations {
+            for (template, target) in self.targets.iter().zip(&targets) {
+                previous_targets.push(crate::dated::previous_rotation(template, target)?);
+            }
+        }
+        let excludes = self
+            .excludes
+            .iter()
+            .map(|pattern| {
+                glob::Pattern::new(pattern)
+                    .map_err(|e| {
+                        SymorError::new(
+                                ErrorCode::InvalidConfiguration,
+                                format!("Invalid exclude pattern {:?}: {}", pattern, e),
+                            )
+                            .with_suggestion(
+                                "Use glob syntax such as *.tmp or build/**".to_string(),
+                            )
+                    })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let filter = PathFilter::new(&self.only)?.skip_hidden(self.skip_hidden);
+        let (tx, rx) = mpsc::channel();
+        let mut watcher = RecommendedWatcher::new(tx, Config::default())
+            .context("failed to initialise file‑watcher")?;
+        let recursive_mode = if self.src.is_dir() {
+            RecursiveMode::Recursive
+        } else {
+            RecursiveMode::NonRecursive
+        };
+        watcher
+            .watch(&self.src, recursive_mode)
+            .with_context(|| format!("cannot watch source {:?}", self.src))?;
+        if self.bidirectional {
+            for target in &targets {
+                if target.exists() {
+                    let target_recursive_mode = if target.is_dir() {
+                        RecursiveMode::Recursive
+                    } else {
+                        RecursiveMode::NonRecursive
+                    };
+                    watcher
+                        .watch(target, target_recursive_mode)
+                        .with_context(|| format!("cannot watch target {:?}", target))?;
+                    println!("Target watcher created successfully");
+                } else {
+                    println!(
+                        "Target does not exist, skipping bidirectional watch: {:?}",
+                        target
+                    );
+                }
+            }
+        }
+        Ok(Mirror {
+            src: self.src,
+            targets,
+            target_templates: self.targets,
+            rollover: self.rollover && dated,
+            keep_rotations: self.keep_rotations,
+            link_rotations: self.link_rotations,
+            symlinks: self.symlinks,
+            following: Default::default(),
+            previous_targets,
+            rx,
+            _watcher: watcher,
+            link_type: self.link_type,
+            shadow_copy: self.shadow_copy,
+            bidirectional: self.bidirectional,
+            metrics_home: self.metrics_home,
+            excludes,
+            filter,
+            debounce: self.debounce,
+            verify: self.verify,
+            delete_policy: self.delete_policy,
+            trash: self.trash,
+            max_syncs_per_minute: self.max_syncs_per_minute,
+            storm_threshold: self.storm_threshold,
+            file_rules: self.file_rules,
+            #[cfg(feature = "scripting")]
+            rules: self.rules,
+            events: self.events,
+            paused: self.paused,
+            copied: Default::default(),
+            held: Default::default(),
+            case_collisions: self.case_collisions,
+            normalize_unicode: self.normalize_unicode,
+            folds_case: Default::default(),
+            case_reported: Default::default(),
+            owners: self.owners,
+            owners_denied: Default::default(),
+            recovery: self.recovery,
+            reclaim_config: self.reclaim_config,
+        })
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+    #[test]
+    fn test_builder_options() {
+        let temp_dir = tempdir().unwrap();
+        let source = temp_dir.path().join("src");
+        let target = temp_dir.path().join("dst");
+        fs::create_dir_all(source.join("build")).unwrap();
+        fs::write(source.join("keep.txt"), "keep").unwrap();
+        fs::write(source.join("scratch.tmp"), "skip").unwrap();
+        fs::write(source.join("build").join("out.o"), "skip").unwrap();
+        fs::create_dir_all(&target).unwrap();
+        fs::write(target.join("stale.txt"), "old").unwrap();
+        fs::write(target.join("local.tmp"), "excluded, so kept").unwrap();
+        assert!(Mirror::builder(& source).build().is_err());
+        assert!(Mirror::builder(& source).target(& target).exclude("[").build().is_err());
+        let mirror = Mirror::builder(&source)
+            .target(&target)
+            .exclude("*.tmp")
+            .exclude("build")
+            .debounce(20)
+            .verify(true)
+            .max_syncs_per_minute(30)
+            .build()
+            .unwrap();
+        assert_eq!(mirror.debounce, Duration::from_millis(20));
+        assert_eq!(mirror.max_syncs_per_minute, 30);
+        mirror.sync_once().unwrap();
+        assert!(target.join("keep.txt").exists());
+        assert!(! target.join("scratch.tmp").exists());
+        assert!(! target.join("build").exists());
+        assert!(! target.join("stale.txt").exists());
+        assert!(target.join("local.tmp").exists());
+        fs::write(target.join("stale.txt"), "old").unwrap();
+        let keeping = Mirror::builder(&source)
+            .target(&target)
+            .delete_policy(DeletePolicy::Keep)
+            .build()
+            .unwrap();
+        keeping.sync_once().unwrap();
+        assert!(target.join("stale.txt").exists());
+        assert!(target.join("build").join("out.o").exists());
+        let filtered = temp_dir.path().join("filtered");
+        let only = Mirror::builder(&source).target(&filtered).only("*.txt").build().unwrap();
+        only.sync_once().unwrap();
+        assert!(filtered.join("keep.txt").exists());
+        assert!(! filtered.join("scratch.tmp").exists());
+        assert!(! filtered.join("build").join("out.o").exists());
+        fs::create_dir_all(source.join(".cache")).unwrap();
+        fs::write(source.join(".cache").join("blob"), "skip").unwrap();
+        fs::write(source.join(".env"), "skip").unwrap();
+        let visible = temp_dir.path().join("visible");
+        let mirror = Mirror::builder(&source).target(&visible).skip_hidden(true).build().unwrap();
+        mirror.sync_once().unwrap();
+        assert!(visible.join("keep.txt").exists());
+        assert!(! visible.join(".cache").exists());
+        assert!(! visible.join(".env").exists());
+    }
+}
\ No newline at end of file
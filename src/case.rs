@@ -0,0 +1,166 @@
+use crate::errors::{ErrorCode, Result, SymorError};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashSet}, ffi::{OsStr, OsString}, fmt, fs, path::Path,
+    str::FromStr,
+};
+/// What a sync does with source names that differ only in case, such as `Readme.md` and
+/// `README.md`, when the target's file system folds case (macOS, Windows) and one would
+/// overwrite the other
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CaseCollisions {
+    /// Mirror the first name in byte order and leave the others out
+    #[default]
+    Skip,
+    /// Mirror the others too, numbered like `Readme (2).md`
+    Rename,
+}
+impl FromStr for CaseCollisions {
+    type Err = SymorError;
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "skip" => Ok(CaseCollisions::Skip),
+            "rename" => Ok(CaseCollisions::Rename),
+            other => {
+                Err(
+                    SymorError::new(
+                        ErrorCode::InvalidConfiguration,
+                        format!("Unknown case collision policy: {}", other),
+                    )
+                        .with_suggestion("Use 'skip' or 'rename'".to_string()),
+                )
+            }
+        }
+    }
+}
+impl fmt::Display for CaseCollisions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CaseCollisions::Skip => write!(f, "skip"),
+            CaseCollisions::Rename => write!(f, "rename"),
+        }
+    }
+}
+fn swap_case(text: &str) -> String {
+    text.chars()
+        .map(|c| {
+            if c.is_lowercase() {
+                c.to_uppercase().next().unwrap_or(c)
+            } else {
+                c.to_lowercase().next().unwrap_or(c)
+            }
+        })
+        .collect()
+}
+/// Whether the file system holding directory `dir` treats names that differ only in
+/// case as the same entry. Looks one of its entries up with the case swapped, or
+/// creates a probe file when it has none to try.
+pub fn folds_case(dir: &Path) -> bool {
+    let dir = crate::long_path::extend(dir);
+    let Ok(entries) = fs::read_dir(&dir) else {
+        return false;
+    };
+    let names: HashSet<OsString> = entries.flatten().map(|entry| entry.file_name()).collect();
+    for name in names.iter().filter_map(|name| name.to_str()) {
+        let swapped = swap_case(name);
+        if swapped != name && !names.contains(OsStr::new(&swapped)) {
+            return dir.join(swapped).exists();
+        }
+    }
+    let probe = format!(".symor-case-probe-{}", std::process::id());
+    if fs::write(dir.join(&probe), b"").is_err() {
+        return false;
+    }
+    let folds = dir.join(swap_case(&probe)).exists();
+    let _ = fs::remove_file(dir.join(&probe));
+    folds
+}
+/// Groups of `names` that differ only in case, each sorted with the name that keeps its
+/// place first
+pub fn collisions<'a>(names: impl IntoIterator<Item = &'a OsStr>) -> Vec<Vec<OsString>> {
+    let mut folded: BTreeMap<String, Vec<OsString>> = BTreeMap::new();
+    for name in names {
+        folded.entry(name.to_string_lossy().to_lowercase()).or_default().push(name.into());
+    }
+    folded
+        .into_values()
+        .filter(|group| group.len() > 1)
+        .map(|mut group| {
+            group.sort();
+            group
+        })
+        .collect()
+}
+/// `name` numbered before its extension, like `Readme (2).md`
+pub fn numbered(name: &OsStr, n: usize) -> OsString {
+    let path = Path::new(name);
+    let mut numbered = path.file_stem().unwrap_or(name).to_os_string();
+    numbered.push(format!(" ({})", n));
+    if let Some(extension) = path.extension() {
+        numbered.push(".");
+        numbered.push(extension);
+    }
+    numbered
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_case_collisions() {
+        let names = ["Readme.md", "b.txt", "README.md", "readme.md", "Docs", "docs"];
+        let groups = collisions(names.map(OsStr::new));
+        assert_eq!(groups[0], ["Docs", "docs"]);
+        assert_eq!(groups[1], ["README.md", "Readme.md", "readme.md"]);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(numbered(OsStr::new("Readme.md"), 2), "Readme (2).md");
+        assert_eq!(numbered(OsStr::new(".bashrc"), 3), ".bashrc (3)");
+        assert_eq!("Rename".parse::<CaseCollisions>().unwrap(), CaseCollisions::Rename);
+        assert!("merge".parse::<CaseCollisions>().is_err());
+        let temp_dir = tempfile::tempdir().unwrap();
+        let folds = folds_case(temp_dir.path());
+        assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 0);
+        fs::write(temp_dir.path().join("Name"), "").unwrap();
+        assert_eq!(folds_case(temp_dir.path()), folds);
+        assert_eq!(folds, cfg!(any(windows, target_os = "macos")));
+        let source = temp_dir.path().join("src");
+        fs::create_dir_all(&source).unwrap();
+        for (name, content) in [("README.md", "upper"), ("Readme.md", "mixed"), ("b.txt", "b")] {
+            fs::write(source.join(name), content).unwrap();
+        }
+        let mut listed = Vec::new();
+        for policy in [CaseCollisions::Rename, CaseCollisions::Skip] {
+            let target = temp_dir.path().join(policy.to_string());
+            let mirror = crate::Mirror::builder(&source)
+                .target(&target)
+                .case_collisions(policy)
+                .build()
+                .unwrap();
+            mirror.folds_case.borrow_mut().insert(target.clone(), true);
+            let events = mirror.subscribe();
+            mirror.sync_once().unwrap();
+            mirror.sync_once().unwrap();
+            let conflicts: Vec<_> = events
+                .try_iter()
+                .filter_map(|event| match event {
+                    crate::SyncEvent::ConflictDetected(conflict) => Some(conflict),
+                    _ => None,
+                })
+                .collect();
+            assert_eq!(conflicts.len(), 1);
+            assert_eq!(conflicts[0].kind, crate::conflicts::ConflictKind::CaseCollision);
+            assert_eq!(conflicts[0].source, source.join("Readme.md"));
+            assert_eq!(conflicts[0].target, Some(target.join("README.md")));
+            let mut names: Vec<_> = fs::read_dir(&target)
+                .unwrap()
+                .map(|entry| entry.unwrap().file_name())
+                .collect();
+            names.sort();
+            listed.push(names);
+        }
+        assert_eq!(listed[0], ["README.md", "Readme (2).md", "b.txt"]);
+        assert_eq!(listed[1], ["README.md", "b.txt"]);
+        let renamed = temp_dir.path().join("rename/Readme (2).md");
+        assert_eq!(fs::read_to_string(renamed).unwrap(), "mixed");
+    }
+}
\ No newline at end of file
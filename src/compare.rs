@@ -0,0 +1,170 @@
+use crate::{
+    conflicts::relative_files,
+    errors::{Context, ErrorCode, Result, SymorError},
+    linking, long_path::extend, performance::ParallelProcessor, SymorManager,
+};
+use std::{collections::BTreeSet, fs, path::{Path, PathBuf}};
+/// How two directory trees differ, with paths relative to their roots
+#[derive(Debug, Clone, Default)]
+pub struct TreeComparison {
+    pub only_a: Vec<PathBuf>,
+    pub only_b: Vec<PathBuf>,
+    /// Files in both trees with different contents
+    pub differing: Vec<PathBuf>,
+    pub identical: usize,
+    /// Files in both trees that could not be read, so were not compared
+    pub unreadable: Vec<(PathBuf, SymorError)>,
+}
+impl TreeComparison {
+    pub fn is_identical(&self) -> bool {
+        self.only_a.is_empty()
+            && self.only_b.is_empty()
+            && self.differing.is_empty()
+            && self.unreadable.is_empty()
+    }
+}
+/// What `SymorManager::fix_tree` changed in the second tree
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TreeFix {
+    pub copied: usize,
+    pub removed: usize,
+}
+fn ensure_dir(path: &Path) -> Result<()> {
+    if path.is_dir() {
+        return Ok(());
+    }
+    Err(
+        SymorError::new(ErrorCode::InvalidPath, format!("Not a directory: {:?}", path))
+            .with_suggestion("Compare two existing directories".to_string()),
+    )
+}
+enum Outcome {
+    Same,
+    Differs,
+    Unreadable(SymorError),
+}
+impl SymorManager {
+    /// Walks both trees at once and compares the files they share, by size and then by
+    /// the change detector's hash, on up to `jobs` threads
+    pub fn compare_trees(&self, a: &Path, b: &Path, jobs: usize) -> Result<TreeComparison> {
+        ensure_dir(a)?;
+        ensure_dir(b)?;
+        let walks = ParallelProcessor::new(2)
+            .map_parallel(
+                &[a, b],
+                |root| {
+                    let mut files = BTreeSet::new();
+                    relative_files(root, root, &mut files);
+                    files
+                },
+            );
+        let [files_a, files_b]: [BTreeSet<PathBuf>; 2] = walks.try_into().expect("two walks");
+        let mut comparison = TreeComparison {
+            only_a: files_a.difference(&files_b).cloned().collect(),
+            only_b: files_b.difference(&files_a).cloned().collect(),
+            ..Default::default()
+        };
+        let shared: Vec<&PathBuf> = files_a.intersection(&files_b).collect();
+        let detector = &self.change_detector;
+        let outcomes = ParallelProcessor::new(jobs)
+            .map_parallel(
+                &shared,
+                |relative| {
+                    let (file_a, file_b) = (a.join(relative), b.join(relative));
+                    let sizes = fs::metadata(extend(&file_a))
+                        .and_then(|ma| Ok((ma.len(), fs::metadata(extend(&file_b))?.len())));
+                    let hashes = match sizes {
+                        Ok((size_a, size_b)) if size_a != size_b => return Outcome::Differs,
+                        Ok(_) => {
+                            detector
+                                .calculate_file_hash(&file_a)
+                                .and_then(|hash| {
+                                    Ok((hash, detector.calculate_file_hash(&file_b)?))
+                                })
+                        }
+                        Err(e) => Err(e.into()),
+                    };
+                    match hashes {
+                        Ok((hash_a, hash_b)) if hash_a == hash_b => Outcome::Same,
+                        Ok(_) => Outcome::Differs,
+                        Err(e) => Outcome::Unreadable(e),
+                    }
+                },
+            );
+        for (relative, outcome) in shared.into_iter().zip(outcomes) {
+            match outcome {
+                Outcome::Same => comparison.identical += 1,
+                Outcome::Differs => comparison.differing.push(relative.clone()),
+                Outcome::Unreadable(e) => comparison.unreadable.push((relative.clone(), e)),
+            }
+        }
+        Ok(comparison)
+    }
+    /// Makes `b` match `a` where `comparison` found them apart: copies files missing
+    /// from or differing in `b` and removes files only `b` has, through the trash when
+    /// `linking.trash` is on
+    pub fn fix_tree(&self, a: &Path, b: &Path, comparison: &TreeComparison) -> Result<TreeFix> {
+        let trash = self.config.linking.trash;
+        let mut fix = TreeFix::default();
+        for relative in comparison.only_a.iter().chain(&comparison.differing) {
+            let (from, to) = (a.join(relative), b.join(relative));
+            if let Some(parent) = to.parent() {
+                fs::create_dir_all(extend(parent))
+                    .with_context(|| format!("cannot create directory {:?}", parent))?;
+            }
+            if trash && extend(&to).exists() {
+                linking::discard_path(&to, true)?;
+            }
+            fs::copy(extend(&from), extend(&to))
+                .with_context(|| format!("cannot copy file {:?} to {:?}", from, to))?;
+            fix.copied += 1;
+        }
+        for relative in &comparison.only_b {
+            linking::discard_path(&b.join(relative), trash)?;
+            fix.removed += 1;
+        }
+        Ok(fix)
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+    #[test]
+    fn test_compare_and_fix_trees() {
+        let temp_dir = tempdir().unwrap();
+        let (a, b) = (temp_dir.path().join("a"), temp_dir.path().join("b"));
+        for (path, content) in [
+            ("a/same.txt", "same"),
+            ("b/same.txt", "same"),
+            ("a/sub/edited.txt", "new text"),
+            ("b/sub/edited.txt", "old text"),
+            ("a/sub/resized.txt", "longer"),
+            ("b/sub/resized.txt", "short"),
+            ("a/deep/new.txt", "new"),
+            ("b/stale.txt", "stale"),
+        ] {
+            let path = temp_dir.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        let mut manager = SymorManager::new().unwrap();
+        manager.config.linking.trash = false;
+        let comparison = manager.compare_trees(&a, &b, 2).unwrap();
+        assert_eq!(comparison.only_a, vec![PathBuf::from("deep/new.txt")]);
+        assert_eq!(comparison.only_b, vec![PathBuf::from("stale.txt")]);
+        assert_eq!(
+            comparison.differing,
+            vec![PathBuf::from("sub/edited.txt"), PathBuf::from("sub/resized.txt")]
+        );
+        assert_eq!(comparison.identical, 1);
+        assert!(!comparison.is_identical());
+        let fix = manager.fix_tree(&a, &b, &comparison).unwrap();
+        assert_eq!(fix, TreeFix { copied: 3, removed: 1 });
+        let comparison = manager.compare_trees(&a, &b, 2).unwrap();
+        assert!(comparison.is_identical());
+        assert_eq!(comparison.identical, 4);
+        let error = manager.compare_trees(&a, &a.join("same.txt"), 2).unwrap_err();
+        assert_eq!(error.code, ErrorCode::InvalidPath);
+    }
+}
\ No newline at end of file
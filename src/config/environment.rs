@@ -0,0 +1,244 @@
+use super::{ConfigFormat, ConfigTemplate, EnvironmentConfig};
+use crate::errors::{Context, ErrorCode, Result, SymorError};
+use std::{collections::HashMap, fs, path::{Path, PathBuf}};
+/// Environment variable naming the active environment explicitly
+pub const ENVIRONMENT_VAR: &str = "SYMOR_ENV";
+/// Marker file whose contents name the environment for a directory tree
+pub const ENVIRONMENT_MARKER: &str = ".symor-env";
+/// How the active environment was chosen
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EnvironmentSource {
+    EnvVar,
+    Marker(PathBuf),
+    Ci,
+}
+pub fn load_environments(path: &Path) -> Result<Vec<EnvironmentConfig>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let data = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read environments file {:?}", path))?;
+    ConfigFormat::from_path(path).deserialize(&data)
+}
+pub fn save_environments(path: &Path, environments: &[EnvironmentConfig]) -> Result<()> {
+    fs::write(path, ConfigFormat::from_path(path).serialize(&environments)?)?;
+    Ok(())
+}
+/// Picks the active environment
+///
+/// `SYMOR_ENV` always wins. A `.symor-env` marker in `cwd` or one of its parents,
+/// or an environment named `ci` when `CI` is set, only applies to environments
+/// with `auto_switch` enabled.
+pub fn detect_environment<'a>(
+    environments: &'a [EnvironmentConfig],
+    cwd: &Path,
+) -> Option<(&'a EnvironmentConfig, EnvironmentSource)> {
+    let find = |name: &str| environments.iter().find(|env| env.name == name);
+    if let Ok(name) = std::env::var(ENVIRONMENT_VAR) {
+        return find(name.trim()).map(|env| (env, EnvironmentSource::EnvVar));
+    }
+    for dir in cwd.ancestors() {
+        let marker = dir.join(ENVIRONMENT_MARKER);
+        if let Ok(name) = fs::read_to_string(&marker) {
+            return find(name.trim())
+                .filter(|env| env.auto_switch)
+                .map(|env| (env, EnvironmentSource::Marker(marker)));
+        }
+    }
+    if std::env::var_os("CI").is_some() {
+        return find("ci")
+            .filter(|env| env.auto_switch)
+            .map(|env| (env, EnvironmentSource::Ci));
+    }
+    None
+}
+impl EnvironmentConfig {
+    /// Config file path, relative paths being taken from the symor home
+    pub fn resolved_config_path(&self, home_dir: &Path) -> PathBuf {
+        if self.config_path.is_absolute() {
+            self.config_path.clone()
+        } else {
+            home_dir.join(&self.config_path)
+        }
+    }
+    /// Loads the environment's config file as a layer over `base`: the fields it sets
+    /// replace those of `base`, section by section, and the rest are kept, except that
+    /// the symor home stays `base`'s. `{{name}}` placeholders are filled from `variables`.
+    /// Unlike `config.json`, the file may not set fields symor does not know
+    pub fn load_config(&self, base: &crate::SymorConfig) -> Result<crate::SymorConfig> {
+        let path = self.resolved_config_path(&base.home_dir);
+        let data = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read config file {:?}", path))?;
+        let layer: serde_json::Value = ConfigFormat::from_path(&path).deserialize(&data)?;
+        let invalid = |message: String| {
+            let name = &self.name;
+            SymorError::new(
+                ErrorCode::InvalidConfiguration,
+                format!("Invalid config {:?} for environment '{}': {}", path, name, message),
+            )
+        };
+        let mut value = serde_json::to_value(base)?;
+        merge_layer(&mut value, layer.clone());
+        let config = serde_json::from_value(value).map_err(|e| invalid(e.to_string()))?;
+        let unknown = unknown_fields(&layer, &config)?;
+        if !unknown.is_empty() {
+            return Err(
+                invalid(format!("unknown setting(s) {}", unknown.join(", ")))
+                    .with_suggestion(
+                        "Check the spelling and that each setting sits under its section, \
+                         like max_versions under versioning"
+                            .to_string(),
+                    ),
+            );
+        }
+        let template = ConfigTemplate {
+            name: self.name.clone(),
+            description: String::new(),
+            config,
+            patterns: Vec::new(),
+            variables: self.variables.clone(),
+        };
+        let mut config = template.resolve_config(&HashMap::new())?;
+        config.home_dir = base.home_dir.clone();
+        Ok(config)
+    }
+}
+/// Dotted paths of the fields in `given` that `config`, the config read from it, has no
+/// place for, so were ignored. Fields with an empty value (null, false, "", [] or {})
+/// are let through, as those are left out when a config is written
+pub fn unknown_fields(
+    given: &serde_json::Value,
+    config: &crate::SymorConfig,
+) -> Result<Vec<String>> {
+    use serde_json::Value;
+    fn walk(given: &Value, known: &Value, path: &str, out: &mut Vec<String>) {
+        match (given, known) {
+            (Value::Object(given), Value::Object(known)) => {
+                for (key, value) in given {
+                    let field = match path {
+                        "" => key.clone(),
+                        _ => format!("{}.{}", path, key),
+                    };
+                    match known.get(key) {
+                        Some(known) => walk(value, known, &field, out),
+                        None if !is_empty(value) => out.push(field),
+                        None => {}
+                    }
+                }
+            }
+            (Value::Array(given), Value::Array(known)) => {
+                for (i, (value, known)) in given.iter().zip(known).enumerate() {
+                    walk(value, known, &format!("{}[{}]", path, i), out);
+                }
+            }
+            _ => {}
+        }
+    }
+    fn is_empty(value: &Value) -> bool {
+        match value {
+            Value::Null | Value::Bool(false) => true,
+            Value::String(s) => s.is_empty(),
+            Value::Array(items) => items.is_empty(),
+            Value::Object(fields) => fields.is_empty(),
+            Value::Number(_) | Value::Bool(true) => false,
+        }
+    }
+    let mut unknown = Vec::new();
+    walk(given, &serde_json::to_value(config)?, "", &mut unknown);
+    Ok(unknown)
+}
+/// Sets each field of `layer` on `base`, merging sections field by field
+fn merge_layer(base: &mut serde_json::Value, layer: serde_json::Value) {
+    match (base, layer) {
+        (serde_json::Value::Object(base), serde_json::Value::Object(layer)) => {
+            for (key, value) in layer {
+                match base.get_mut(&key) {
+                    Some(field) => merge_layer(field, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, layer) => *base = layer,
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+    #[test]
+    fn test_marker_selects_auto_switch_environment() {
+        let temp_dir = tempdir().unwrap();
+        let project = temp_dir.path().join("project");
+        let nested = project.join("src");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(project.join(ENVIRONMENT_MARKER), "staging\n").unwrap();
+        let mut environments = vec![
+            EnvironmentConfig { name : "staging".to_string(), config_path :
+            PathBuf::from("staging.yaml"), auto_switch : true, variables :
+            HashMap::new(), }
+        ];
+        if std::env::var_os(ENVIRONMENT_VAR).is_none() {
+            let marker = project.join(ENVIRONMENT_MARKER);
+            let (env, source) = detect_environment(&environments, &nested).unwrap();
+            assert_eq!(env.name, "staging");
+            assert_eq!(source, EnvironmentSource::Marker(marker));
+            environments[0].auto_switch = false;
+            assert!(detect_environment(&environments, &nested).is_none());
+        }
+        let path = temp_dir.path().join("environments.json");
+        save_environments(&path, &environments).unwrap();
+        let loaded = load_environments(&path).unwrap();
+        assert_eq!(loaded[0].config_path, PathBuf::from("staging.yaml"));
+    }
+    #[test]
+    fn test_environment_config_is_layered() {
+        let temp_dir = tempdir().unwrap();
+        let base = crate::SymorConfig {
+            home_dir: temp_dir.path().to_path_buf(),
+            versioning: crate::VersioningConfig {
+                compression: 9,
+                ..Default::default()
+            },
+            linking: crate::LinkingConfig {
+                trash: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let env = EnvironmentConfig {
+            name: "ci".to_string(),
+            config_path: PathBuf::from("ci.yaml"),
+            auto_switch: true,
+            variables: HashMap::from([("keep".to_string(), "2".to_string())]),
+        };
+        fs::write(
+                temp_dir.path().join("ci.yaml"),
+                "home_dir: /elsewhere\nversioning:\n  max_versions: 2\nhooks:\n  \
+                 on_version_created: [\"echo {{keep}}\"]\n",
+            )
+            .unwrap();
+        let config = env.load_config(&base).unwrap();
+        assert_eq!(config.versioning.max_versions, 2);
+        assert_eq!(config.versioning.compression, 9);
+        assert!(config.versioning.enabled && config.linking.trash);
+        assert_eq!(config.hooks.on_version_created, vec!["echo 2".to_string()]);
+        assert_eq!(config.home_dir, base.home_dir);
+        fs::write(temp_dir.path().join("ci.yaml"), "max_versions: 2\n").unwrap();
+        let error = env.load_config(&base).unwrap_err();
+        assert_eq!(error.code, ErrorCode::InvalidConfiguration);
+        assert!(error.message.contains("max_versions"));
+        fs::write(
+                temp_dir.path().join("ci.yaml"),
+                "file_rules:\n- paths: ['*.log']\n  skip: false\n  mirror: false\n",
+            )
+            .unwrap();
+        assert_eq!(env.load_config(&base).unwrap().file_rules.len(), 1);
+        let json = r#"{"versioning": {"max_versions": 3, "from_newer_release": true}}"#;
+        let config: crate::SymorConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(config.versioning.max_versions, 3);
+        let given = serde_json::from_str(json).unwrap();
+        assert_eq!(unknown_fields(&given, &config).unwrap(), ["versioning.from_newer_release"]);
+    }
+}
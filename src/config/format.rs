@@ -0,0 +1,233 @@
+use crate::errors::{Context, ErrorCode, Result, SymorError};
+use super::ConfigTemplate;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{fmt, fs, path::Path, str::FromStr};
+/// Serialization format for config files
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConfigFormat {
+    #[default]
+    Json,
+    Yaml,
+}
+impl ConfigFormat {
+    /// Picks the format from a file extension, falling back to JSON
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("yaml") | Some("yml") => ConfigFormat::Yaml,
+            _ => ConfigFormat::Json,
+        }
+    }
+}
+impl FromStr for ConfigFormat {
+    type Err = SymorError;
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "json" => Ok(ConfigFormat::Json),
+            "yaml" | "yml" => Ok(ConfigFormat::Yaml),
+            other => {
+                Err(
+                    SymorError::new(
+                        ErrorCode::InvalidConfiguration,
+                        format!("Unknown config format: {}", other),
+                    )
+                        .with_suggestion("Use 'json' or 'yaml'".to_string()),
+                )
+            }
+        }
+    }
+}
+impl fmt::Display for ConfigFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigFormat::Json => write!(f, "json"),
+            ConfigFormat::Yaml => write!(f, "yaml"),
+        }
+    }
+}
+impl ConfigFormat {
+    pub fn deserialize<T: DeserializeOwned>(&self, data: &str) -> Result<T> {
+        let parsed = match self {
+            ConfigFormat::Json => serde_json::from_str(data).map_err(|e| e.to_string()),
+            ConfigFormat::Yaml => serde_yaml::from_str(data).map_err(|e| e.to_string()),
+        };
+        parsed
+            .map_err(|e| {
+                SymorError::new(
+                    ErrorCode::InvalidConfiguration,
+                    format!("Invalid {} config: {}", self, e),
+                )
+            })
+    }
+    pub fn serialize<T: Serialize>(&self, value: &T) -> Result<String> {
+        match self {
+            ConfigFormat::Json => Ok(serde_json::to_string_pretty(value)?),
+            ConfigFormat::Yaml => {
+                serde_yaml::to_string(value)
+                    .map_err(|e| {
+                        SymorError::new(ErrorCode::SerializationError, e.to_string())
+                    })
+            }
+        }
+    }
+}
+/// Encoding for metadata symor writes often and nobody edits by hand: the watched
+/// item files and the change detector's hash cache
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MetadataFormat {
+    #[default]
+    Json,
+    /// MessagePack: smaller and faster to parse than JSON
+    Msgpack,
+}
+impl MetadataFormat {
+    pub const ALL: [MetadataFormat; 2] = [MetadataFormat::Json, MetadataFormat::Msgpack];
+    pub fn extension(&self) -> &'static str {
+        match self {
+            MetadataFormat::Json => "json",
+            MetadataFormat::Msgpack => "msgpack",
+        }
+    }
+    /// The format a metadata file was written in, by its extension
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?;
+        Self::ALL.into_iter().find(|format| format.extension() == ext)
+    }
+    pub fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>> {
+        match self {
+            MetadataFormat::Json => Ok(serde_json::to_vec_pretty(value)?),
+            MetadataFormat::Msgpack => {
+                rmp_serde::to_vec_named(value)
+                    .map_err(|e| {
+                        SymorError::new(ErrorCode::SerializationError, e.to_string())
+                    })
+            }
+        }
+    }
+    pub fn decode<T: DeserializeOwned>(&self, data: &[u8]) -> Result<T> {
+        match self {
+            MetadataFormat::Json => Ok(serde_json::from_slice(data)?),
+            MetadataFormat::Msgpack => {
+                rmp_serde::from_slice(data)
+                    .map_err(|e| {
+                        SymorError::new(
+                            ErrorCode::SerializationError,
+                            format!("Invalid msgpack metadata: {}", e),
+                        )
+                    })
+            }
+        }
+    }
+}
+impl FromStr for MetadataFormat {
+    type Err = SymorError;
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "json" => Ok(MetadataFormat::Json),
+            "msgpack" | "messagepack" => Ok(MetadataFormat::Msgpack),
+            other => {
+                Err(
+                    SymorError::new(
+                        ErrorCode::InvalidConfiguration,
+                        format!("Unknown metadata format: {}", other),
+                    )
+                        .with_suggestion("Use 'json' or 'msgpack'".to_string()),
+                )
+            }
+        }
+    }
+}
+impl fmt::Display for MetadataFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.extension())
+    }
+}
+impl crate::SymorConfig {
+    pub fn parse(data: &str, format: ConfigFormat) -> Result<Self> {
+        format.deserialize(data)
+    }
+    pub fn to_string_as(&self, format: ConfigFormat) -> Result<String> {
+        format.serialize(self)
+    }
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let data = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file {:?}", path))?;
+        Self::parse(&data, ConfigFormat::from_path(path))
+    }
+    pub fn write_to_file(&self, path: &Path) -> Result<()> {
+        fs::write(path, self.to_string_as(ConfigFormat::from_path(path))?)?;
+        Ok(())
+    }
+}
+/// Full settings snapshot used by `sym settings export` and `sym settings import`
+///
+/// The config fields sit at the top level, so a bundle without templates is also a plain
+/// config file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettingsBundle {
+    #[serde(flatten)]
+    pub config: crate::SymorConfig,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub templates: Vec<ConfigTemplate>,
+}
+impl SettingsBundle {
+    pub fn from_file(path: &Path, format: Option<ConfigFormat>) -> Result<Self> {
+        let data = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read settings file {:?}", path))?;
+        format.unwrap_or_else(|| ConfigFormat::from_path(path)).deserialize(&data)
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SymorConfig;
+    #[test]
+    fn test_yaml_round_trip() {
+        let mut config = SymorConfig::default();
+        config.versioning.max_versions = 42;
+        let yaml = config.to_string_as(ConfigFormat::Yaml).unwrap();
+        assert!(yaml.contains("max_versions: 42"));
+        let parsed = SymorConfig::parse(&yaml, ConfigFormat::Yaml).unwrap();
+        assert_eq!(parsed.versioning.max_versions, 42);
+        assert_eq!(parsed.recovery, config.recovery);
+    }
+    #[test]
+    fn test_format_from_path() {
+        assert_eq!(ConfigFormat::from_path(Path::new("a.yml")), ConfigFormat::Yaml);
+        assert_eq!(ConfigFormat::from_path(Path::new("a.json")), ConfigFormat::Json);
+        assert!("toml".parse::<ConfigFormat>().is_err());
+    }
+    #[test]
+    fn test_metadata_formats_round_trip() {
+        let mut config = SymorConfig::default();
+        config.versioning.metadata_format = MetadataFormat::Msgpack;
+        for format in MetadataFormat::ALL {
+            let data = format.encode(&config).unwrap();
+            let parsed: SymorConfig = format.decode(&data).unwrap();
+            assert_eq!(parsed.versioning.metadata_format, MetadataFormat::Msgpack);
+            let path = Path::new("cache").join(format!("hashes.{}", format.extension()));
+            assert_eq!(MetadataFormat::from_path(&path), Some(format));
+        }
+        let (json, msgpack) = (
+            MetadataFormat::Json.encode(&config).unwrap(),
+            MetadataFormat::Msgpack.encode(&config).unwrap(),
+        );
+        assert!(msgpack.len() < json.len());
+        assert!(MetadataFormat::Msgpack.decode::<SymorConfig>(b"{}").is_err());
+        assert_eq!("MessagePack".parse::<MetadataFormat>().unwrap(), MetadataFormat::Msgpack);
+    }
+    #[test]
+    fn test_bundle_is_a_plain_config() {
+        let mut bundle = SettingsBundle {
+            config: SymorConfig::default(),
+            templates: Vec::new(),
+        };
+        bundle.config.linking.link_type = "hard".to_string();
+        let json = ConfigFormat::Json.serialize(&bundle).unwrap();
+        assert!(! json.contains("templates"));
+        let config = SymorConfig::parse(&json, ConfigFormat::Json).unwrap();
+        assert_eq!(config.linking.link_type, "hard");
+        let parsed: SettingsBundle = ConfigFormat::Json.deserialize(&json).unwrap();
+        assert!(parsed.templates.is_empty());
+    }
+}
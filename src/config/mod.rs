@@ -1,4 +1,9 @@
+pub mod environment;
+pub mod format;
 pub mod templates;
+pub mod topology;
 pub mod validation;
-pub use templates::{ConfigTemplate, TemplateManager, EnvironmentConfig};
-pub use validation::{ConfigValidator, ValidationResult, ValidationError};
\ No newline at end of file
+pub use templates::{ConfigTemplate, ConfigOverrides, TemplateManager, EnvironmentConfig};
+pub use validation::{ConfigValidator, ValidationResult, ValidationError};
+pub use format::{ConfigFormat, MetadataFormat, SettingsBundle};
+pub use topology::{Topology, TopologyChange};
\ No newline at end of file
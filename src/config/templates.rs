@@ -1,4 +1,4 @@
-use anyhow::Result;
+use crate::errors::{ErrorCode, Result, SymorError};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::PathBuf;
@@ -8,6 +8,9 @@ pub struct ConfigTemplate {
     pub description: String,
     pub config: crate::SymorConfig,
     pub patterns: Vec<String>,
+    /// Default values for `{{name}}` placeholders used in this template
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub variables: HashMap<String, String>,
 }
 pub struct TemplateManager {
     templates: HashMap<String, ConfigTemplate>,
@@ -15,9 +18,12 @@ pub struct TemplateManager {
 }
 impl TemplateManager {
     pub fn new() -> Self {
+        Self::with_path(PathBuf::from(".symor/templates"))
+    }
+    pub fn with_path(custom_templates_path: PathBuf) -> Self {
         Self {
             templates: HashMap::new(),
-            custom_templates_path: PathBuf::from(".symor/templates"),
+            custom_templates_path,
         }
     }
     pub fn load_builtin_templates(&mut self) -> Result<()> {
@@ -30,13 +36,27 @@ impl TemplateManager {
                     enabled: true,
                     max_versions: 50,
                     compression: 3,
+                    shadow_copy: false,
+                    pre_restore_days: 7,
+                    metadata_format: Default::default(),
+                    retention_lock_days: 0,
+                    secret_scan: Default::default(),
+                    symlinks: crate::symlinks::SymlinkMode::Follow,
                 },
                 linking: crate::LinkingConfig {
                     link_type: "copy".to_string(),
                     preserve_permissions: true,
+                    preserve_ownership: false,
+                    owner_map: Vec::new(),
+                    trash: false,
+                    max_syncs_per_minute: 0,
+                    case_collisions: Default::default(),
+                    normalize_unicode: true,
                 },
+                ..crate::SymorConfig::default()
             },
             patterns: vec!["*.rs".to_string(), "*.toml".to_string()],
+            variables: HashMap::new(),
         };
         let prod_template = ConfigTemplate {
             name: "production".to_string(),
@@ -47,13 +67,27 @@ impl TemplateManager {
                     enabled: true,
                     max_versions: 20,
                     compression: 9,
+                    shadow_copy: false,
+                    pre_restore_days: 7,
+                    metadata_format: Default::default(),
+                    retention_lock_days: 0,
+                    secret_scan: Default::default(),
+                    symlinks: crate::symlinks::SymlinkMode::Follow,
                 },
                 linking: crate::LinkingConfig {
                     link_type: "hard".to_string(),
                     preserve_permissions: true,
+                    preserve_ownership: false,
+                    owner_map: Vec::new(),
+                    trash: false,
+                    max_syncs_per_minute: 0,
+                    case_collisions: Default::default(),
+                    normalize_unicode: true,
                 },
+                ..crate::SymorConfig::default()
             },
             patterns: vec!["*.txt".to_string(), "*.md".to_string()],
+            variables: HashMap::new(),
         };
         let backup_template = ConfigTemplate {
             name: "backup".to_string(),
@@ -64,13 +98,27 @@ impl TemplateManager {
                     enabled: true,
                     max_versions: 100,
                     compression: 6,
+                    shadow_copy: false,
+                    pre_restore_days: 7,
+                    metadata_format: Default::default(),
+                    retention_lock_days: 0,
+                    secret_scan: Default::default(),
+                    symlinks: crate::symlinks::SymlinkMode::Follow,
                 },
                 linking: crate::LinkingConfig {
                     link_type: "copy".to_string(),
                     preserve_permissions: true,
+                    preserve_ownership: false,
+                    owner_map: Vec::new(),
+                    trash: false,
+                    max_syncs_per_minute: 0,
+                    case_collisions: Default::default(),
+                    normalize_unicode: true,
                 },
+                ..crate::SymorConfig::default()
             },
             patterns: vec!["*".to_string()],
+            variables: HashMap::new(),
         };
         self.templates.insert(dev_template.name.clone(), dev_template);
         self.templates.insert(prod_template.name.clone(), prod_template);
@@ -90,8 +138,17 @@ impl TemplateManager {
     ) -> Result<crate::SymorConfig> {
         let template = self
             .get_template(template_name)
-            .ok_or_else(|| anyhow::anyhow!("Template '{}' not found", template_name))?;
-        let mut config = template.config.clone();
+            .ok_or_else(|| {
+                SymorError::new(
+                        ErrorCode::MissingConfiguration,
+                        format!("Template '{}' not found", template_name),
+                    )
+                    .with_suggestion(
+                        "Use 'sym settings template list' to see available templates"
+                            .to_string(),
+                    )
+            })?;
+        let mut config = template.resolve_config(&overrides.variables)?;
         if let Some(max_versions) = overrides.max_versions {
             config.versioning.max_versions = max_versions;
         }
@@ -108,16 +165,30 @@ impl TemplateManager {
         name: String,
         config: crate::SymorConfig,
     ) -> Result<()> {
-        use std::fs;
         let template = ConfigTemplate {
             name: name.clone(),
             description: format!("Custom template: {}", name),
             config,
             patterns: vec!["*".to_string()],
+            variables: HashMap::new(),
         };
-        let custom_path = self.custom_templates_path.join(format!("{}.json", name));
+        self.save_template(&template)
+    }
+    pub fn save_template(&self, template: &ConfigTemplate) -> Result<()> {
+        use std::fs;
+        if !is_valid_template_name(&template.name) {
+            return Err(
+                SymorError::new(
+                    ErrorCode::InvalidConfiguration,
+                    format!("Invalid template name: {:?}", template.name),
+                ),
+            );
+        }
+        let custom_path = self
+            .custom_templates_path
+            .join(format!("{}.json", template.name));
         fs::create_dir_all(&self.custom_templates_path)?;
-        let json_data = serde_json::to_string_pretty(&template)?;
+        let json_data = serde_json::to_string_pretty(template)?;
         fs::write(custom_path, json_data)?;
         Ok(())
     }
@@ -138,16 +209,192 @@ impl TemplateManager {
         Ok(())
     }
 }
+/// Template names double as file names, so path separators and leading dots are rejected
+pub fn is_valid_template_name(name: &str) -> bool {
+    !name.is_empty() && !name.starts_with('.') && !name.contains(['/', '\\'])
+}
 #[derive(Debug, Clone, Default)]
 pub struct ConfigOverrides {
     pub max_versions: Option<usize>,
     pub compression: Option<u8>,
     pub link_type: Option<String>,
+    pub variables: HashMap<String, String>,
+}
+impl ConfigTemplate {
+    /// Returns the template config with every `{{name}}` placeholder substituted
+    ///
+    /// Values come from `variables`, then `SYMOR_VAR_<NAME>` environment variables,
+    /// then the built-ins (`hostname`, `user`, `home`, `project_dir`), then the
+    /// template's own defaults.
+    pub fn resolve_config(
+        &self,
+        variables: &HashMap<String, String>,
+    ) -> Result<crate::SymorConfig> {
+        let builtins = builtin_variables();
+        let lookup = |name: &str| {
+            variables
+                .get(name)
+                .cloned()
+                .or_else(|| {
+                    std::env::var(format!("SYMOR_VAR_{}", name.to_uppercase())).ok()
+                })
+                .or_else(|| builtins.get(name).cloned())
+                .or_else(|| self.variables.get(name).cloned())
+        };
+        let mut value = serde_json::to_value(&self.config)?;
+        let mut missing = Vec::new();
+        interpolate_value(&mut value, &lookup, &mut missing);
+        if !missing.is_empty() {
+            missing.sort();
+            missing.dedup();
+            return Err(
+                SymorError::new(
+                        ErrorCode::MissingConfiguration,
+                        format!(
+                            "Template '{}' needs values for: {}", self.name, missing
+                            .join(", ")
+                        ),
+                    )
+                    .with_suggestion(
+                        "Pass them with --var NAME=VALUE or set SYMOR_VAR_<NAME>"
+                            .to_string(),
+                    ),
+            );
+        }
+        Ok(serde_json::from_value(value)?)
+    }
+    /// Names of all placeholders used by the template config
+    pub fn variable_names(&self) -> Vec<String> {
+        let mut names = serde_json::to_string(&self.config)
+            .map(|json| placeholders(&json))
+            .unwrap_or_default();
+        names.sort();
+        names.dedup();
+        names
+    }
+}
+pub fn builtin_variables() -> HashMap<String, String> {
+    let mut variables = HashMap::new();
+    if let Ok(hostname) = hostname::get() {
+        variables.insert("hostname".to_string(), hostname.to_string_lossy().into_owned());
+    }
+    if let Ok(user) = std::env::var("USER").or_else(|_| std::env::var("USERNAME")) {
+        variables.insert("user".to_string(), user);
+    }
+    if let Ok(home) = std::env::var("HOME").or_else(|_| std::env::var("USERPROFILE")) {
+        variables.insert("home".to_string(), home);
+    }
+    if let Ok(cwd) = std::env::current_dir() {
+        variables.insert("project_dir".to_string(), cwd.display().to_string());
+    }
+    variables
+}
+fn placeholders(input: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut rest = input;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        names.push(rest[start + 2..start + 2 + end].trim().to_string());
+        rest = &rest[start + 2 + end + 2..];
+    }
+    names
+}
+fn interpolate(
+    input: &str,
+    lookup: &dyn Fn(&str) -> Option<String>,
+    missing: &mut Vec<String>,
+) -> String {
+    let mut output = String::with_capacity(input.len());
+    let mut rest = input;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        let name = rest[start + 2..start + 2 + end].trim();
+        output.push_str(&rest[..start]);
+        match lookup(name) {
+            Some(value) => output.push_str(&value),
+            None => missing.push(name.to_string()),
+        }
+        rest = &rest[start + 2 + end + 2..];
+    }
+    output.push_str(rest);
+    output
+}
+fn interpolate_value(
+    value: &mut serde_json::Value,
+    lookup: &dyn Fn(&str) -> Option<String>,
+    missing: &mut Vec<String>,
+) {
+    match value {
+        serde_json::Value::String(s) if s.contains("{{") => {
+            *s = interpolate(s, lookup, missing);
+        }
+        serde_json::Value::Array(items) => {
+            for item in items {
+                interpolate_value(item, lookup, missing);
+            }
+        }
+        serde_json::Value::Object(map) => {
+            for item in map.values_mut() {
+                interpolate_value(item, lookup, missing);
+            }
+        }
+        _ => {}
+    }
 }
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EnvironmentConfig {
     pub name: String,
     pub config_path: PathBuf,
     pub auto_switch: bool,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
     pub variables: HashMap<String, String>,
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+    #[test]
+    fn test_apply_and_save_templates() {
+        let temp_dir = tempdir().unwrap();
+        let mut manager = TemplateManager::with_path(temp_dir.path().join("templates"));
+        manager.load_builtin_templates().unwrap();
+        let overrides = ConfigOverrides {
+            max_versions: Some(7),
+            ..Default::default()
+        };
+        let config = manager.create_from_template("production", &overrides).unwrap();
+        assert_eq!(config.versioning.max_versions, 7);
+        assert_eq!(config.linking.link_type, "hard");
+        assert!(manager.create_from_template("missing", &overrides).is_err());
+        manager.save_custom_template("mine".to_string(), config).unwrap();
+        assert!(
+            manager.save_custom_template("../escape".to_string(), crate::SymorConfig::default())
+            .is_err()
+        );
+        let mut reloaded = TemplateManager::with_path(temp_dir.path().join("templates"));
+        reloaded.load_custom_templates().unwrap();
+        assert_eq!(reloaded.list_templates().len(), 1);
+        let saved = reloaded.get_template("mine").unwrap();
+        assert_eq!(saved.config.versioning.max_versions, 7);
+    }
+    #[test]
+    fn test_template_variables() {
+        let mut template = TemplateManager::new();
+        template.load_builtin_templates().unwrap();
+        let mut template = template.get_template("backup").unwrap().clone();
+        template.config.home_dir = PathBuf::from("{{project_dir}}/{{ site }}/.symor");
+        template.variables.insert("site".to_string(), "default-site".to_string());
+        assert_eq!(template.variable_names(), vec!["project_dir", "site"]);
+        let mut variables = HashMap::new();
+        variables.insert("project_dir".to_string(), "/srv/app".to_string());
+        let config = template.resolve_config(&variables).unwrap();
+        assert_eq!(config.home_dir, PathBuf::from("/srv/app/default-site/.symor"));
+        template.config.linking.link_type = "{{undefined_var}}".to_string();
+        let err = template.resolve_config(&variables).unwrap_err();
+        assert!(err.message.contains("undefined_var"));
+    }
+}
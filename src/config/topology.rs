@@ -0,0 +1,363 @@
+use crate::{
+    errors::{Context, ErrorCode, Result, SymorError},
+    MirrorSpec, PathFilter, SymorManager,
+};
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::{fmt, fs, path::{Path, PathBuf}};
+/// Topology file names looked for in the symor home, first match wins
+pub const TOPOLOGY_FILES: [&str; 2] = ["symor.toml", "mirrors.toml"];
+/// A `[[mirror]]` table
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MirrorEntry {
+    pub source: PathBuf,
+    pub targets: Vec<PathBuf>,
+    #[serde(default)]
+    pub bidirectional: bool,
+    #[serde(default)]
+    pub only: Vec<String>,
+    /// Falls back to the `skip_hidden` setting
+    #[serde(default)]
+    pub skip_hidden: Option<bool>,
+    /// Change events per second treated as a storm; see `MirrorSpec::storm_threshold`
+    #[serde(default)]
+    pub storm_threshold: Option<u32>,
+    /// See `MirrorSpec::rollover`
+    #[serde(default)]
+    pub rollover: bool,
+    /// See `MirrorSpec::keep_rotations`
+    #[serde(default)]
+    pub keep_rotations: Option<usize>,
+    /// See `MirrorSpec::link_rotations`
+    #[serde(default)]
+    pub link_rotations: bool,
+    /// See `MirrorSpec::symlinks`
+    #[serde(default)]
+    pub symlinks: crate::symlinks::SymlinkMode,
+}
+/// A `[[watch]]` table
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WatchEntry {
+    pub path: PathBuf,
+    #[serde(default)]
+    pub recursive: bool,
+    #[serde(default)]
+    pub only: Vec<String>,
+    /// Falls back to the `skip_hidden` setting
+    #[serde(default)]
+    pub skip_hidden: Option<bool>,
+}
+/// Mirrors and watched paths declared in a `symor.toml`, applied on top of those added
+/// from the command line
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Topology {
+    #[serde(default, rename = "mirror")]
+    pub mirrors: Vec<MirrorEntry>,
+    #[serde(default, rename = "watch")]
+    pub watches: Vec<WatchEntry>,
+}
+/// One step that brings the registry in line with a topology
+#[derive(Debug, Clone, PartialEq)]
+pub enum TopologyChange {
+    AddMirror(MirrorSpec),
+    /// Replaces the mirror with the same source
+    UpdateMirror(MirrorSpec),
+    /// Source of a mirror the topology no longer declares
+    RemoveMirror(PathBuf),
+    AddWatch(WatchEntry),
+    /// Resolved options for the watched item with this ID
+    UpdateWatch(String, WatchEntry),
+    /// ID and path of a watched item the topology no longer declares
+    RemoveWatch(String, PathBuf),
+    /// A declared watch path that does not exist yet, so it is left out
+    MissingWatch(PathBuf),
+}
+impl fmt::Display for TopologyChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let targets = |spec: &MirrorSpec| {
+            spec.targets.iter().map(|t| t.display().to_string()).collect::<Vec<_>>().join(", ")
+        };
+        match self {
+            Self::AddMirror(spec) => {
+                write!(f, "+ mirror {} -> {}", spec.source.display(), targets(spec))
+            }
+            Self::UpdateMirror(spec) => {
+                write!(f, "~ mirror {} -> {}", spec.source.display(), targets(spec))
+            }
+            Self::RemoveMirror(source) => write!(f, "- mirror {}", source.display()),
+            Self::AddWatch(entry) => write!(f, "+ watch {}", entry.path.display()),
+            Self::UpdateWatch(_, entry) => write!(f, "~ watch {}", entry.path.display()),
+            Self::RemoveWatch(_, path) => write!(f, "- watch {}", path.display()),
+            Self::MissingWatch(path) => write!(f, "! watch {} (path does not exist)", path.display()),
+        }
+    }
+}
+/// `~/` is the user's home; other relative paths are taken from `base`
+fn resolve(base: &Path, path: &Path) -> PathBuf {
+    if let (Ok(rest), Some(home)) = (path.strip_prefix("~"), std::env::var_os("HOME")) {
+        return PathBuf::from(home).join(rest);
+    }
+    base.join(path)
+}
+/// The topology file in `home_dir`, if there is one
+pub fn find_topology(home_dir: &Path) -> Option<PathBuf> {
+    TOPOLOGY_FILES.iter().map(|name| home_dir.join(name)).find(|path| path.is_file())
+}
+impl Topology {
+    pub fn parse(data: &str) -> Result<Self> {
+        let topology: Self = toml::from_str(data)
+            .map_err(|e| {
+                SymorError::new(
+                        ErrorCode::InvalidConfiguration,
+                        format!("Invalid topology file: {}", e),
+                    )
+                    .with_suggestion(
+                        "Declare [[mirror]] tables (source, targets) and [[watch]] tables \
+                         (path)"
+                            .to_string(),
+                    )
+            })?;
+        for only in topology
+            .mirrors
+            .iter()
+            .map(|m| &m.only)
+            .chain(topology.watches.iter().map(|w| &w.only))
+        {
+            PathFilter::new(only)?;
+        }
+        Ok(topology)
+    }
+    /// Reads `path`, resolving relative paths in it against the file's directory
+    pub fn load(path: &Path) -> Result<Self> {
+        let data = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read topology file {:?}", path))?;
+        let mut topology = Self::parse(&data)?;
+        let base = std::path::absolute(path)?
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        for mirror in &mut topology.mirrors {
+            mirror.source = resolve(&base, &mirror.source);
+            for target in &mut mirror.targets {
+                *target = resolve(&base, target);
+            }
+        }
+        for watch in &mut topology.watches {
+            watch.path = resolve(&base, &watch.path);
+        }
+        Ok(topology)
+    }
+}
+impl SymorManager {
+    /// Steps that make the registry match `topology`: declared mirrors and watches are
+    /// added, or updated when one exists for the same source or path, and with `prune`
+    /// the ones it does not declare are removed
+    pub fn plan_topology(&self, topology: &Topology, prune: bool) -> Vec<TopologyChange> {
+        let mut plan = Vec::new();
+        for entry in &topology.mirrors {
+            let existing = self.mirrors.iter().find(|m| m.source == entry.source);
+            let spec = MirrorSpec {
+                source: entry.source.clone(),
+                targets: entry.targets.clone(),
+                bidirectional: entry.bidirectional,
+                only: entry.only.clone(),
+                skip_hidden: entry.skip_hidden.unwrap_or(self.config.skip_hidden),
+                paused: existing.is_some_and(|m| m.paused),
+                storm_threshold: entry.storm_threshold,
+                rollover: entry.rollover,
+                keep_rotations: entry.keep_rotations,
+                link_rotations: entry.link_rotations,
+                symlinks: entry.symlinks,
+            };
+            match existing {
+                Some(existing) if *existing == spec => {}
+                Some(_) => plan.push(TopologyChange::UpdateMirror(spec)),
+                None => plan.push(TopologyChange::AddMirror(spec)),
+            }
+        }
+        for entry in &topology.watches {
+            let entry = WatchEntry {
+                skip_hidden: Some(entry.skip_hidden.unwrap_or(self.config.skip_hidden)),
+                ..entry.clone()
+            };
+            match self.watched_items.values().find(|item| item.path == entry.path) {
+                Some(item) => {
+                    if (item.recursive, &item.only, Some(item.skip_hidden))
+                        != (entry.recursive, &entry.only, entry.skip_hidden)
+                    {
+                        plan.push(TopologyChange::UpdateWatch(item.id.clone(), entry));
+                    }
+                }
+                None if !entry.path.exists() => {
+                    plan.push(TopologyChange::MissingWatch(entry.path))
+                }
+                None => plan.push(TopologyChange::AddWatch(entry)),
+            }
+        }
+        if prune {
+            for spec in &self.mirrors {
+                if !topology.mirrors.iter().any(|m| m.source == spec.source) {
+                    plan.push(TopologyChange::RemoveMirror(spec.source.clone()));
+                }
+            }
+            let mut undeclared: Vec<_> = self
+                .watched_items
+                .values()
+                .filter(|item| !topology.watches.iter().any(|w| w.path == item.path))
+                .collect();
+            undeclared.sort_by(|a, b| a.path.cmp(&b.path));
+            for item in undeclared {
+                plan.push(TopologyChange::RemoveWatch(item.id.clone(), item.path.clone()));
+            }
+        }
+        plan
+    }
+    /// Carries out `plan_topology`, returning the steps taken; removed watches keep
+    /// their stored versions
+    pub fn apply_topology(
+        &mut self,
+        topology: &Topology,
+        prune: bool,
+    ) -> Result<Vec<TopologyChange>> {
+        let plan = self.plan_topology(topology, prune);
+        let (mut mirrors_changed, mut watches_changed) = (false, false);
+        for change in &plan {
+            match change {
+                TopologyChange::AddMirror(spec) => {
+                    self.mirrors.push(spec.clone());
+                    mirrors_changed = true;
+                }
+                TopologyChange::UpdateMirror(spec) => {
+                    for existing in self.mirrors.iter_mut().filter(|m| m.source == spec.source) {
+                        *existing = spec.clone();
+                    }
+                    mirrors_changed = true;
+                }
+                TopologyChange::RemoveMirror(source) => {
+                    self.mirrors.retain(|m| &m.source != source);
+                    mirrors_changed = true;
+                }
+                TopologyChange::AddWatch(entry) => {
+                    self.watch_only(
+                        entry.path.clone(),
+                        entry.recursive,
+                        entry.only.clone(),
+                        entry.skip_hidden.unwrap_or(self.config.skip_hidden),
+                    )?;
+                }
+                TopologyChange::UpdateWatch(id, entry) => {
+                    if let Some(item) = self.watched_items.get_mut(id) {
+                        item.recursive = entry.recursive;
+                        item.only = entry.only.clone();
+                        item.skip_hidden = entry.skip_hidden.unwrap_or(item.skip_hidden);
+                        watches_changed = true;
+                    }
+                }
+                TopologyChange::RemoveWatch(id, _) => {
+                    watches_changed |= self.watched_items.remove(id).is_some();
+                }
+                TopologyChange::MissingWatch(_) => {}
+            }
+        }
+        if mirrors_changed {
+            self.save_mirrors()?;
+        }
+        if watches_changed {
+            self.save_watched_items()?;
+        }
+        Ok(plan)
+    }
+    /// Loads and applies the topology file in the symor home, if there is one, without
+    /// removing anything it does not declare
+    pub fn apply_topology_file(&mut self) -> Result<Option<(PathBuf, Vec<TopologyChange>)>> {
+        let Some(path) = find_topology(&self.config.home_dir) else {
+            return Ok(None);
+        };
+        let changes = self.apply_topology(&Topology::load(&path)?, false)?;
+        for change in &changes {
+            info!("Topology {:?}: {}", path, change);
+        }
+        Ok(Some((path, changes)))
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+    #[test]
+    fn test_apply_topology() {
+        let temp_dir = tempdir().unwrap();
+        let home = temp_dir.path().join("home");
+        fs::create_dir_all(temp_dir.path().join("etc")).unwrap();
+        fs::create_dir_all(&home).unwrap();
+        fs::write(
+                home.join("symor.toml"),
+                r#"
+[[mirror]]
+source = "../etc"
+targets = ["/backup/etc", "copy"]
+only = ["*.conf"]
+storm_threshold = 50
+rollover = true
+keep_rotations = 7
+link_rotations = true
+symlinks = "preserve"
+
+[[watch]]
+path = "../etc"
+recursive = true
+skip_hidden = true
+
+[[watch]]
+path = "../later"
+"#,
+            )
+            .unwrap();
+        let mut manager = SymorManager::new().unwrap();
+        manager.config.home_dir = home.clone();
+        manager.config.versioning.enabled = false;
+        let (path, changes) = manager.apply_topology_file().unwrap().unwrap();
+        assert_eq!(path, home.join("symor.toml"));
+        assert!(matches!(changes[0], TopologyChange::AddMirror(_)));
+        assert!(matches!(changes[1], TopologyChange::AddWatch(_)));
+        assert_eq!(changes[2], TopologyChange::MissingWatch(home.join("../later")));
+        let spec = &manager.mirrors()[0];
+        assert_eq!(spec.source, home.join("../etc"));
+        assert_eq!(spec.targets, [PathBuf::from("/backup/etc"), home.join("copy")]);
+        assert_eq!(spec.storm_threshold, Some(50));
+        assert!(spec.rollover);
+        assert_eq!(spec.keep_rotations, Some(7));
+        assert!(spec.link_rotations);
+        assert_eq!(spec.symlinks, crate::symlinks::SymlinkMode::Preserve);
+        let item = manager.watched_index().next().unwrap();
+        assert!(item.recursive && item.skip_hidden);
+        let changes = manager.apply_topology_file().unwrap().unwrap().1;
+        assert_eq!(changes.len(), 1);
+        let mut topology = Topology::load(&home.join("symor.toml")).unwrap();
+        topology.watches[0].recursive = false;
+        topology.mirrors[0].bidirectional = true;
+        let changes = manager.apply_topology(&topology, false).unwrap();
+        assert!(matches!(changes[0], TopologyChange::UpdateMirror(_)));
+        assert!(matches!(changes[1], TopologyChange::UpdateWatch(..)));
+        manager.load_mirrors().unwrap();
+        assert!(manager.mirrors()[0].bidirectional);
+        assert!(!manager.watched_index().next().unwrap().recursive);
+        let emptied = Topology::default();
+        let plan = manager.plan_topology(&emptied, true);
+        assert_eq!(plan.len(), 2);
+        assert_eq!(manager.mirrors().len(), 1);
+        assert_eq!(plan[0].to_string(), format!("- mirror {}", home.join("../etc").display()));
+        manager.apply_topology(&emptied, true).unwrap();
+        manager.load_mirrors().unwrap();
+        manager.load_watched_items().unwrap();
+        assert!(manager.mirrors().is_empty() && manager.watched_index().len() == 0);
+        assert!(manager.apply_topology(&emptied, true).unwrap().is_empty());
+        assert!(Topology::parse("[[mirror]]\nsource = \"a\"").is_err());
+        assert!(Topology::parse("[[watch]]\npath = \"a\"\nonly = [\"[\"]").is_err());
+        assert!(Topology::parse("[[watches]]\npath = \"a\"").is_err());
+        assert_eq!(Topology::parse("").unwrap(), Topology::default());
+    }
+}
\ No newline at end of file
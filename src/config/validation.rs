@@ -1,4 +1,4 @@
-use anyhow::Result;
+use crate::errors::Result;
 #[derive(Debug, Clone)]
 pub struct ValidationResult {
     pub is_valid: bool,
@@ -28,6 +28,25 @@ impl ConfigValidator {
         self.validate_versioning_config(&config.versioning, &mut errors, &mut warnings);
         self.validate_linking_config(&config.linking, &mut errors, &mut warnings);
         self.validate_home_directory(&config.home_dir, &mut errors, &mut warnings);
+        self.validate_recovery_config(&config.recovery, &mut errors, &mut warnings);
+        if let Err(e) = crate::file_rules::FileRules::new(&config.file_rules) {
+            errors
+                .push(ValidationError {
+                    field: "file_rules".to_string(),
+                    message: e.message,
+                    suggestion: e.recovery_suggestion,
+                });
+        }
+        if let Err(e) = config.performance.max_memory_bytes() {
+            errors
+                .push(ValidationError {
+                    field: "performance.max_memory".to_string(),
+                    message: e.message,
+                    suggestion: e.recovery_suggestion,
+                });
+        }
+        #[cfg(feature = "tui")]
+        self.validate_tui_config(&config.tui, &mut errors);
         ValidationResult {
             is_valid: errors.is_empty(),
             errors,
@@ -77,7 +96,7 @@ impl ConfigValidator {
         errors: &mut Vec<ValidationError>,
         _warnings: &mut Vec<ValidationWarning>,
     ) {
-        let valid_link_types = ["hard", "soft", "copy"];
+        let valid_link_types = crate::linking::LinkType::NAMES;
         if !valid_link_types.contains(&config.link_type.as_str()) {
             errors
                 .push(ValidationError {
@@ -86,6 +105,14 @@ impl ConfigValidator {
                     suggestion: Some(format!("Use one of: {:?}", valid_link_types)),
                 });
         }
+        if let Err(e) = crate::ownership::OwnerMap::parse(&config.owner_map) {
+            errors
+                .push(ValidationError {
+                    field: "linking.owner_map".to_string(),
+                    message: e.message,
+                    suggestion: e.recovery_suggestion,
+                });
+        }
     }
     fn validate_home_directory(
         &self,
@@ -114,6 +141,78 @@ impl ConfigValidator {
                 });
         }
     }
+    fn validate_recovery_config(
+        &self,
+        config: &crate::errors::RecoveryConfig,
+        errors: &mut Vec<ValidationError>,
+        _warnings: &mut Vec<ValidationWarning>,
+    ) {
+        for (code, strategy) in &config.strategies {
+            let field = format!("recovery.{}", code);
+            if serde_json::from_value::<crate::errors::ErrorCode>(
+                    serde_json::Value::String(code.clone()),
+                )
+                .is_err()
+            {
+                errors
+                    .push(ValidationError {
+                        field: field.clone(),
+                        message: format!("Unknown error code: {}", code),
+                        suggestion: Some(
+                            "Use an ErrorCode name such as NetworkError or FileNotFound"
+                                .to_string(),
+                        ),
+                    });
+            }
+            if let crate::errors::RecoveryStrategy::Retry { max_attempts: 0, .. } = strategy {
+                errors
+                    .push(ValidationError {
+                        field,
+                        message: "Retry strategy needs at least one attempt".to_string(),
+                        suggestion: Some(
+                            "Set max_attempts to a value greater than 0".to_string(),
+                        ),
+                    });
+            }
+        }
+    }
+    #[cfg(feature = "tui")]
+    fn validate_tui_config(
+        &self,
+        config: &crate::tui::TuiConfig,
+        errors: &mut Vec<ValidationError>,
+    ) {
+        for (action, spec) in &config.keys {
+            let problem = action
+                .parse::<crate::tui::keys::Action>()
+                .err()
+                .or_else(|| {
+                    spec.names()
+                        .into_iter()
+                        .find_map(|name| crate::tui::keys::parse_key(name).err())
+                });
+            if let Some(e) = problem {
+                errors
+                    .push(ValidationError {
+                        field: format!("tui.keys.{}", action),
+                        message: e.message,
+                        suggestion: e.recovery_suggestion,
+                    });
+            }
+        }
+        let theme_error = match crate::tui::theme::Theme::named(&config.theme) {
+            Ok(theme) => theme.with_palette(&config.colors).err().map(|e| ("tui.colors", e)),
+            Err(e) => Some(("tui.theme", e)),
+        };
+        if let Some((field, e)) = theme_error {
+            errors
+                .push(ValidationError {
+                    field: field.to_string(),
+                    message: e.message,
+                    suggestion: e.recovery_suggestion,
+                });
+        }
+    }
     pub fn validate_and_fix_config(
         &self,
         config: &mut crate::SymorConfig,
@@ -141,11 +240,24 @@ mod tests {
                 enabled: true,
                 max_versions: 0,
                 compression: 10,
+                shadow_copy: false,
+                pre_restore_days: 7,
+                metadata_format: Default::default(),
+                retention_lock_days: 0,
+                secret_scan: Default::default(),
+                symlinks: crate::symlinks::SymlinkMode::Follow,
             },
             linking: crate::LinkingConfig {
                 link_type: "invalid".to_string(),
                 preserve_permissions: true,
+                preserve_ownership: false,
+                owner_map: Vec::new(),
+                trash: false,
+                max_syncs_per_minute: 0,
+                case_collisions: Default::default(),
+                normalize_unicode: true,
             },
+            ..crate::SymorConfig::default()
         };
         let result = validator.validate_config(&config);
         assert!(! result.is_valid);
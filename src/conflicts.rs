@@ -0,0 +1,428 @@
+use crate::{
+    case, errors::{Context, ErrorCode, Result, SymorError}, linking, long_path,
+    merge::LineMerge, normalize,
+    sync_state::SyncState, MirrorSpec,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet}, fs, path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+/// Why a path needs attention
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictKind {
+    /// Source and target both exist with different contents
+    Diverged,
+    /// The source is gone; for a mirror the target still has a copy
+    MissingSource,
+    /// A watched file with no stored versions
+    NoVersions,
+    /// The source has another name differing only in case, which the target's file
+    /// system stores as the same file
+    CaseCollision,
+}
+/// Which sides of a diverged pair changed since the mirror last synced it; an edit to
+/// the source alone is not a conflict
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditedSide {
+    Target,
+    Both,
+}
+/// How to settle a conflict
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Resolution {
+    KeepSource,
+    KeepTarget,
+    KeepBoth,
+}
+/// A mirror pair or watched item found by `SymorManager::find_conflicts`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conflict {
+    pub kind: ConflictKind,
+    pub source: PathBuf,
+    /// Mirror target; `None` for findings about a watched item alone
+    pub target: Option<PathBuf>,
+    /// Watched item whose path is `source`, if any
+    pub item_id: Option<String>,
+    /// Which side was edited since the last sync, when the mirror's sync state knows
+    pub edited: Option<EditedSide>,
+}
+impl Conflict {
+    /// Resolutions that apply to this conflict, with what each one does.
+    /// For a watched item on its own, the "target" is its latest stored version.
+    pub fn resolutions(&self) -> Vec<(Resolution, &'static str)> {
+        match (self.kind, self.target.is_some()) {
+            (ConflictKind::Diverged, true) => {
+                vec![
+                    (Resolution::KeepSource, "Keep source"), (Resolution::KeepTarget,
+                    "Keep target"), (Resolution::KeepBoth, "Keep both"),
+                ]
+            }
+            (ConflictKind::MissingSource, true) => {
+                vec![(Resolution::KeepTarget, "Restore source from target")]
+            }
+            (ConflictKind::MissingSource, false) => {
+                vec![
+                    (Resolution::KeepTarget, "Restore latest version"),
+                    (Resolution::KeepSource, "Unwatch"),
+                ]
+            }
+            (ConflictKind::NoVersions, _) => {
+                vec![(Resolution::KeepSource, "Create a version")]
+            }
+            (ConflictKind::Diverged, false) | (ConflictKind::CaseCollision, _) => Vec::new(),
+        }
+    }
+    /// The resolution most likely wanted, with the reason for it
+    pub fn suggestion(&self) -> Option<(Resolution, &'static str)> {
+        let newer_target = || {
+            self.target.as_deref().and_then(modified) > modified(&self.source)
+        };
+        match (self.kind, self.target.is_some(), self.edited) {
+            (ConflictKind::Diverged, true, Some(EditedSide::Both)) => {
+                Some((Resolution::KeepBoth, "both sides were edited since the last sync"))
+            }
+            (ConflictKind::Diverged, true, Some(EditedSide::Target)) => {
+                Some((Resolution::KeepBoth, "the next sync would overwrite the target's edits"))
+            }
+            (ConflictKind::Diverged, true, _) if newer_target() => {
+                Some((Resolution::KeepTarget, "the target was modified more recently"))
+            }
+            (ConflictKind::Diverged, true, _) => {
+                Some((Resolution::KeepSource, "the source was modified more recently"))
+            }
+            (ConflictKind::MissingSource, true, _) => {
+                Some((Resolution::KeepTarget, "the target still has the only copy"))
+            }
+            (ConflictKind::MissingSource, false, _) => {
+                Some((Resolution::KeepTarget, "its latest stored version can bring it back"))
+            }
+            (ConflictKind::NoVersions, _, _) => {
+                Some((Resolution::KeepSource, "nothing could be restored if it changed"))
+            }
+            (ConflictKind::Diverged, false, _) | (ConflictKind::CaseCollision, _, _) => None,
+        }
+    }
+    /// One-line description, e.g. for `sym conflicts`
+    pub fn summary(&self) -> String {
+        match (self.kind, &self.target) {
+            (ConflictKind::Diverged, Some(target)) => {
+                format!("{} ≠ {}", self.source.display(), target.display())
+            }
+            (ConflictKind::MissingSource, Some(target)) => {
+                format!("{} missing, {} still exists", self.source.display(), target.display())
+            }
+            (ConflictKind::MissingSource, None) => {
+                format!("{} not found", self.source.display())
+            }
+            (ConflictKind::NoVersions, _) => {
+                format!("{} has no version history", self.source.display())
+            }
+            (ConflictKind::CaseCollision, Some(target)) => {
+                format!("{} collides with {} by case", self.source.display(), target.display())
+            }
+            (ConflictKind::Diverged | ConflictKind::CaseCollision, None) => {
+                self.source.display().to_string()
+            }
+        }
+    }
+}
+/// Files below `dir`, relative to `root`, not following links
+pub(crate) fn relative_files(root: &Path, dir: &Path, files: &mut BTreeSet<PathBuf>) {
+    let Ok(entries) = fs::read_dir(long_path::extend(dir)) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = dir.join(entry.file_name());
+        if linking::is_reparse_point(&path) {
+            continue;
+        }
+        if long_path::extend(&path).is_dir() {
+            relative_files(root, &path, files);
+        } else if let Ok(relative) = path.strip_prefix(root) {
+            files.insert(relative.to_path_buf());
+        }
+    }
+}
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(long_path::extend(path)).and_then(|m| m.modified()).ok()
+}
+pub(crate) fn same_contents(a: &Path, b: &Path) -> bool {
+    let (a, b) = (long_path::extend(a), long_path::extend(b));
+    match (fs::metadata(&a), fs::metadata(&b)) {
+        (Ok(ma), Ok(mb)) if ma.len() != mb.len() => false,
+        _ => matches!((fs::read(&a), fs::read(&b)), (Ok(da), Ok(db)) if da == db),
+    }
+}
+/// Files that differ between a mirror's source and its targets. With the mirror's
+/// `state`, a pair only counts as diverged when the target was edited since the last
+/// sync and, for a two-way mirror, the source was too and their edits overlap; an
+/// edit on one side is just what the next sync carries over, and the next sync merges
+/// the rest. Without it, targets of one-way mirrors count when edited after the source.
+pub fn mirror_conflicts(spec: &MirrorSpec, state: Option<&SyncState>) -> Vec<Conflict> {
+    let mut conflicts = Vec::new();
+    let only = crate::PathFilter::new(&spec.only)
+        .unwrap_or_default()
+        .skip_hidden(spec.skip_hidden);
+    for target in &spec.targets {
+        let Ok(target) = &crate::dated::expand(target, SystemTime::now()) else {
+            continue;
+        };
+        if !long_path::extend(target).exists() || linking::is_linked(&spec.source, target) {
+            continue;
+        }
+        let pairs = if long_path::extend(&spec.source).is_dir()
+            || long_path::extend(target).is_dir()
+        {
+            let mut files = BTreeSet::new();
+            relative_files(&spec.source, &spec.source, &mut files);
+            files.retain(|rel| only.includes(rel));
+            let groups = case::collisions(files.iter().map(|rel| rel.as_os_str()));
+            let mut target_files = BTreeSet::new();
+            relative_files(target, target, &mut target_files);
+            if !groups.is_empty() && case::folds_case(target) {
+                for group in groups {
+                    for (n, rel) in group.iter().map(Path::new).enumerate() {
+                        files.remove(rel);
+                        target_files.remove(rel);
+                        let Some(name) = rel.file_name().filter(|_| n > 0) else {
+                            continue;
+                        };
+                        target_files.remove(&rel.with_file_name(case::numbered(name, n + 1)));
+                        conflicts
+                            .push(Conflict {
+                                kind: ConflictKind::CaseCollision,
+                                source: spec.source.join(rel),
+                                target: Some(target.join(&group[0])),
+                                item_id: None,
+                                edited: None,
+                            });
+                    }
+                }
+            }
+            // Target files named in another Unicode normal form than their source file
+            let by_key: BTreeMap<_, _> = files
+                .iter()
+                .filter(|rel| normalize::may_differ(rel.as_os_str()))
+                .map(|rel| (normalize::key(rel), rel))
+                .collect();
+            let respelled: BTreeMap<PathBuf, PathBuf> = target_files
+                .iter()
+                .filter(|rel| !files.contains(*rel) && normalize::may_differ(rel.as_os_str()))
+                .filter_map(|rel| {
+                    let source_rel = by_key.get(&normalize::key(rel))?;
+                    Some((source_rel.to_path_buf(), rel.clone()))
+                })
+                .collect();
+            for rel in respelled.values() {
+                target_files.remove(rel);
+            }
+            files
+                .iter()
+                .chain(&target_files)
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .filter(|rel| only.includes(rel))
+                .map(|rel| {
+                    let target_rel = respelled.get(rel).unwrap_or(rel);
+                    (spec.source.join(rel), target.join(target_rel))
+                })
+                .collect()
+        } else {
+            vec![(spec.source.clone(), target.clone())]
+        };
+        for (source, target) in pairs {
+            let edits = state.and_then(|state| state.edits(&source, &target));
+            let (kind, edited) = match (
+                long_path::extend(&source).exists(),
+                long_path::extend(&target).exists(),
+                edits,
+            ) {
+                (false, true, _) => (ConflictKind::MissingSource, None),
+                (true, true, _) if same_contents(&source, &target) => continue,
+                (true, true, Some((true, true))) => {
+                    let merges = spec.bidirectional
+                        && state
+                            .and_then(|state| state.merge(&source, &target))
+                            .is_some_and(|merge| matches!(merge, LineMerge::Clean(_)));
+                    if merges {
+                        continue;
+                    }
+                    (ConflictKind::Diverged, Some(EditedSide::Both))
+                }
+                (true, true, Some((false, true))) if !spec.bidirectional => {
+                    (ConflictKind::Diverged, Some(EditedSide::Target))
+                }
+                (true, true, Some((false, false))) => (ConflictKind::Diverged, None),
+                (true, true, Some(_)) => continue,
+                (true, true, None) => {
+                    if !spec.bidirectional && modified(&target) <= modified(&source) {
+                        continue;
+                    }
+                    (ConflictKind::Diverged, None)
+                }
+                _ => continue,
+            };
+            conflicts
+                .push(Conflict {
+                    kind,
+                    source,
+                    target: Some(target),
+                    item_id: None,
+                    edited,
+                });
+        }
+    }
+    conflicts
+}
+/// Free name beside `path` for a kept-both copy: `name.conflict-<secs>.ext`
+pub fn conflict_copy_path(path: &Path) -> PathBuf {
+    let secs = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let stem = path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    let ext = path
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+    let mut candidate = path.with_file_name(format!("{}.conflict-{}{}", stem, secs, ext));
+    let mut n = 1;
+    while long_path::extend(&candidate).exists() {
+        candidate = path.with_file_name(format!("{}.conflict-{}-{}{}", stem, secs, n, ext));
+        n += 1;
+    }
+    candidate
+}
+/// Replaces `to` with a copy of `from` through a temporary file
+fn replace_file(from: &Path, to: &Path) -> Result<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(long_path::extend(parent))
+            .with_context(|| format!("cannot create directory {:?}", parent))?;
+    }
+    let tmp = to.with_extension("tmp-sync");
+    fs::copy(long_path::extend(from), long_path::extend(&tmp))
+        .with_context(|| format!("cannot copy {:?} to {:?}", from, tmp))?;
+    fs::rename(long_path::extend(&tmp), long_path::extend(to))
+        .with_context(|| format!("cannot atomically replace {:?}", to))?;
+    Ok(())
+}
+/// Applies `resolution` to a mirror conflict on disk and describes what changed
+pub fn resolve_files(conflict: &Conflict, resolution: Resolution) -> Result<String> {
+    let Some(target) = &conflict.target else {
+        return Err(
+            SymorError::new(
+                ErrorCode::InvalidPath,
+                format!("No mirror target for {:?}", conflict.source),
+            ),
+        );
+    };
+    match (conflict.kind, resolution) {
+        (ConflictKind::Diverged, Resolution::KeepSource) => {
+            replace_file(&conflict.source, target)?;
+            Ok(format!("Copied {} over {}", conflict.source.display(), target.display()))
+        }
+        (ConflictKind::Diverged | ConflictKind::MissingSource, Resolution::KeepTarget) => {
+            replace_file(target, &conflict.source)?;
+            Ok(format!("Copied {} over {}", target.display(), conflict.source.display()))
+        }
+        (ConflictKind::Diverged, Resolution::KeepBoth) => {
+            let copy = conflict_copy_path(&conflict.source);
+            fs::copy(long_path::extend(target), long_path::extend(&copy))
+                .with_context(|| format!("cannot copy {:?} to {:?}", target, copy))?;
+            replace_file(&conflict.source, target)?;
+            Ok(format!("Kept both; the target's version is now {}", copy.display()))
+        }
+        _ => {
+            Err(
+                SymorError::new(
+                    ErrorCode::InternalError,
+                    format!("{:?} does not apply to {}", resolution, conflict.summary()),
+                ),
+            )
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+    #[test]
+    fn test_mirror_conflicts_and_resolution() {
+        let temp_dir = tempdir().unwrap();
+        let source = temp_dir.path().join("src");
+        let target = temp_dir.path().join("dst");
+        fs::create_dir_all(&source).unwrap();
+        fs::create_dir_all(&target).unwrap();
+        fs::write(source.join("same.txt"), "same").unwrap();
+        fs::write(target.join("same.txt"), "same").unwrap();
+        fs::write(source.join("a.txt"), "source edit").unwrap();
+        fs::write(target.join("a.txt"), "target edit").unwrap();
+        fs::write(target.join("only.txt"), "only in target").unwrap();
+        let spec = MirrorSpec {
+            source: source.clone(),
+            targets: vec![target.clone()],
+            bidirectional: true,
+            only: Vec::new(),
+            skip_hidden: false,
+            paused: false,
+            storm_threshold: None,
+            rollover: false,
+            keep_rotations: None,
+            link_rotations: false,
+            symlinks: Default::default(),
+        };
+        let conflicts = mirror_conflicts(&spec, None);
+        assert_eq!(conflicts.len(), 2);
+        assert_eq!(conflicts[0].kind, ConflictKind::Diverged);
+        assert_eq!(conflicts[0].source, source.join("a.txt"));
+        assert_eq!(conflicts[1].kind, ConflictKind::MissingSource);
+        assert_eq!(conflicts[1].resolutions().len(), 1);
+        resolve_files(&conflicts[0], Resolution::KeepBoth).unwrap();
+        assert_eq!(fs::read_to_string(target.join("a.txt")).unwrap(), "source edit");
+        let copies: Vec<_> = fs::read_dir(&source)
+            .unwrap()
+            .flatten()
+            .filter(|e| e.file_name().to_string_lossy().starts_with("a.conflict-"))
+            .collect();
+        assert_eq!(copies.len(), 1);
+        assert_eq!(fs::read_to_string(copies[0].path()).unwrap(), "target edit");
+        resolve_files(&conflicts[1], Resolution::KeepTarget).unwrap();
+        assert_eq!(fs::read_to_string(source.join("only.txt")).unwrap(), "only in target");
+        assert!(resolve_files(& conflicts[1], Resolution::KeepBoth).is_err());
+        assert!(mirror_conflicts(& spec, None).is_empty());
+    }
+    #[test]
+    fn test_mirror_conflicts_from_sync_state() {
+        let temp_dir = tempdir().unwrap();
+        let (source, target) = (temp_dir.path().join("a.txt"), temp_dir.path().join("b.txt"));
+        fs::write(&source, "synced").unwrap();
+        fs::write(&target, "synced").unwrap();
+        let mut spec = MirrorSpec {
+            source: source.clone(),
+            targets: vec![target.clone()],
+            bidirectional: true,
+            only: Vec::new(),
+            skip_hidden: false,
+            paused: false,
+            storm_threshold: None,
+            rollover: false,
+            keep_rotations: None,
+            link_rotations: false,
+            symlinks: Default::default(),
+        };
+        let mut state = SyncState::new(&source);
+        state.record(&source, &target);
+        fs::write(&target, "target edit").unwrap();
+        assert!(mirror_conflicts(& spec, Some(& state)).is_empty());
+        spec.bidirectional = false;
+        let conflicts = mirror_conflicts(&spec, Some(&state));
+        assert_eq!(conflicts[0].edited, Some(EditedSide::Target));
+        spec.bidirectional = true;
+        let later = SystemTime::now() + std::time::Duration::from_secs(5);
+        fs::write(&source, "source edit").unwrap();
+        fs::File::options().write(true).open(&source).unwrap().set_modified(later).unwrap();
+        let conflicts = mirror_conflicts(&spec, Some(&state));
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].edited, Some(EditedSide::Both));
+        assert_eq!(conflicts[0].suggestion().unwrap().0, Resolution::KeepBoth);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,427 @@
+use crate::errors::{ErrorCode, Result, SymorError};
+use serde_json::{json, Value};
+use std::{
+    fs, io::{Read, Write}, net::{SocketAddr, TcpStream},
+    path::{Path, PathBuf}, sync::mpsc::Sender, time::Duration,
+};
+#[cfg(feature = "api")]
+pub use server::ApiServer;
+/// Default address of the control API; it only listens on loopback unless told otherwise
+pub const DEFAULT_ADDR: &str = "127.0.0.1:7437";
+const NO_DAEMON_HINT: &str = "Retry with --no-daemon to work on the state files directly";
+/// Where to listen and the bearer token clients must send
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiOptions {
+    pub addr: SocketAddr,
+    pub token: String,
+}
+/// Something a control API client asked the daemon to do
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ApiCommand {
+    /// Liveness probe: answered without a token while the daemon loop responds
+    Health,
+    /// Readiness probe: like `Health`, but fails while syncing is paused or a mirror
+    /// stopped
+    Ready,
+    Status,
+    Items,
+    /// Version changed watched files now, or just the item with this path or ID; `force`
+    /// versions them even when unchanged
+    Sync {
+        item: Option<String>,
+        force: bool,
+    },
+    /// Start watching `path`; `skip_hidden` defaults to the daemon's setting
+    Watch {
+        path: PathBuf,
+        recursive: bool,
+        only: Vec<String>,
+        skip_hidden: Option<bool>,
+        /// Alias for the new item
+        name: Option<String>,
+    },
+    /// Hold all syncing, or just the watched item or mirror source with this path or ID;
+    /// the pause is remembered across restarts
+    Pause(Option<String>),
+    Resume(Option<String>),
+    Restore {
+        item: String,
+        /// Any version selector, e.g. an ID or `@latest~1`
+        version: String,
+        /// Defaults to the item's own path
+        target: Option<PathBuf>,
+    },
+}
+/// A command with the channel its HTTP status and JSON body go back on
+#[derive(Debug)]
+pub struct ApiRequest {
+    pub command: ApiCommand,
+    pub reply: Sender<(u16, Value)>,
+}
+pub fn token_file(home_dir: &Path) -> PathBuf {
+    home_dir.join("api-token")
+}
+/// Where a running daemon records the address its API actually listens on
+pub fn addr_file(home_dir: &Path) -> PathBuf {
+    home_dir.join("api-addr")
+}
+/// The API token in `home_dir`, creating a random one from OS entropy if there is none
+/// yet. The new token is written to a temporary file that is readable only by the owner
+/// from the start, then renamed into place.
+pub fn load_or_create_token(home_dir: &Path) -> Result<String> {
+    let path = token_file(home_dir);
+    if let Ok(token) = fs::read_to_string(&path) {
+        if !token.trim().is_empty() {
+            return Ok(token.trim().to_string());
+        }
+    }
+    let mut bytes = [0u8; 16];
+    getrandom::fill(&mut bytes).map_err(|e| {
+        SymorError::new(ErrorCode::InternalError, format!("Cannot generate an API token: {}", e))
+    })?;
+    let token: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+    fs::create_dir_all(home_dir)?;
+    let tmp = path.with_extension(format!("{}.tmp", std::process::id()));
+    let _ = fs::remove_file(&tmp);
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let written = options.open(&tmp).and_then(|mut file| file.write_all(token.as_bytes()));
+    if let Err(e) = written.and_then(|_| fs::rename(&tmp, &path)) {
+        let _ = fs::remove_file(&tmp);
+        return Err(e.into());
+    }
+    Ok(token)
+}
+fn error_body(code: u16, message: &str) -> (u16, Value) {
+    (code, json!({ "error" : message }))
+}
+/// Maps a method and URL to a command, or the error response for a bad request
+pub fn route(method: &str, url: &str, body: &str) -> std::result::Result<ApiCommand, (u16, Value)> {
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    let param = |name: &str| {
+        query
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| percent_decode(value))
+    };
+    match (method, path.trim_end_matches('/')) {
+        ("GET", "/healthz") => Ok(ApiCommand::Health),
+        ("GET", "/readyz") => Ok(ApiCommand::Ready),
+        ("GET", "/v1/status") => Ok(ApiCommand::Status),
+        ("GET", "/v1/items") => Ok(ApiCommand::Items),
+        ("POST", "/v1/sync") => {
+            Ok(ApiCommand::Sync {
+                item: param("item"),
+                force: param("force").is_some_and(|force| force == "true" || force == "1"),
+            })
+        }
+        ("POST", "/v1/pause") => Ok(ApiCommand::Pause(param("item"))),
+        ("POST", "/v1/resume") => Ok(ApiCommand::Resume(param("item"))),
+        ("POST", "/v1/watch") => {
+            let body = parse_body(body)?;
+            let Some(path) = body.get("path").and_then(Value::as_str) else {
+                return Err(error_body(400, "Watch needs \"path\""));
+            };
+            let only = body
+                .get("only")
+                .and_then(Value::as_array)
+                .map(|only| only.iter().filter_map(Value::as_str).map(str::to_string).collect())
+                .unwrap_or_default();
+            Ok(ApiCommand::Watch {
+                path: PathBuf::from(path),
+                recursive: body.get("recursive").and_then(Value::as_bool).unwrap_or(false),
+                only,
+                skip_hidden: body.get("skip_hidden").and_then(Value::as_bool),
+                name: body.get("name").and_then(Value::as_str).map(str::to_string),
+            })
+        }
+        ("POST", "/v1/restore") => {
+            let body = parse_body(body)?;
+            let field = |name: &str| body.get(name).and_then(Value::as_str).map(str::to_string);
+            match (field("item"), field("version")) {
+                (Some(item), Some(version)) => {
+                    Ok(ApiCommand::Restore {
+                        item,
+                        version,
+                        target: field("target").map(PathBuf::from),
+                    })
+                }
+                _ => Err(error_body(400, "Restore needs \"item\" and \"version\"")),
+            }
+        }
+        (_, "/healthz" | "/readyz") => Err(error_body(405, "Method not allowed")),
+        (_, "/v1/status" | "/v1/items" | "/v1/sync" | "/v1/watch" | "/v1/pause"
+        | "/v1/resume" | "/v1/restore") => Err(error_body(405, "Method not allowed")),
+        _ => Err(error_body(404, "Not found")),
+    }
+}
+/// Whether a request is a health probe, which orchestrators send without a token
+pub fn is_probe(method: &str, url: &str) -> bool {
+    let path = url.split_once('?').map_or(url, |(path, _)| path);
+    method == "GET" && matches!(path.trim_end_matches('/'), "/healthz" | "/readyz")
+}
+fn parse_body(body: &str) -> std::result::Result<Value, (u16, Value)> {
+    serde_json::from_str(body).map_err(|e| error_body(400, &format!("Invalid JSON body: {}", e)))
+}
+/// Escapes everything but unreserved characters for use in a query string
+pub fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3).and_then(|h| std::str::from_utf8(h).ok());
+        match (bytes[i], hex.and_then(|h| u8::from_str_radix(h, 16).ok())) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (b'+', _) => {
+                decoded.push(b' ');
+                i += 1;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+/// Talks to the control API of the daemon running on this machine, so CLI commands
+/// go through it instead of racing it for the state files
+#[derive(Debug, Clone)]
+pub struct ApiClient {
+    pub pid: u32,
+    addr: SocketAddr,
+    token: String,
+}
+impl ApiClient {
+    /// A client for the running daemon, or `None` when no daemon with the API enabled
+    /// runs for `home_dir`
+    pub fn connect(home_dir: &Path) -> Option<Self> {
+        let pid = super::running_pid(home_dir)?;
+        let addr = fs::read_to_string(addr_file(home_dir)).ok()?.trim().parse().ok()?;
+        let token = fs::read_to_string(token_file(home_dir)).ok()?.trim().to_string();
+        Some(Self { pid, addr, token })
+    }
+    /// Sends a request and returns the JSON body of a successful response
+    pub fn call(&self, method: &str, path: &str, body: Option<&Value>) -> Result<Value> {
+        let unreachable = |e: std::io::Error| {
+            SymorError::new(
+                    ErrorCode::NetworkError,
+                    format!("Cannot reach the daemon at {}: {}", self.addr, e),
+                )
+                .with_suggestion(NO_DAEMON_HINT.to_string())
+        };
+        let body = body.map(Value::to_string).unwrap_or_default();
+        let mut stream = TcpStream::connect_timeout(&self.addr, Duration::from_secs(5))
+            .map_err(unreachable)?;
+        stream.set_read_timeout(Some(Duration::from_secs(90))).map_err(unreachable)?;
+        write!(
+            stream,
+            "{} {} HTTP/1.0\r\nHost: {}\r\nAuthorization: Bearer {}\r\n\
+             Content-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            method, path, self.addr, self.token, body.len(), body
+        )
+            .map_err(unreachable)?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response).map_err(unreachable)?;
+        let (head, body) = response.split_once("\r\n\r\n").unwrap_or((&response, ""));
+        let status: u16 = head
+            .split_whitespace()
+            .nth(1)
+            .and_then(|code| code.parse().ok())
+            .unwrap_or(0);
+        let value: Value = serde_json::from_str(body).unwrap_or(Value::Null);
+        if (200..300).contains(&status) {
+            return Ok(value);
+        }
+        let message = value["error"].as_str().unwrap_or("no response").to_string();
+        let code = serde_json::from_value(value["code"].clone())
+            .unwrap_or(match status {
+                400 => ErrorCode::InvalidConfiguration,
+                401 => ErrorCode::PermissionDenied,
+                404 => ErrorCode::NotWatched,
+                _ => ErrorCode::InternalError,
+            });
+        let suggestion = value["suggestion"].as_str().unwrap_or(NO_DAEMON_HINT);
+        Err(
+            SymorError::new(code, format!("Daemon (pid {}): {}", self.pid, message))
+                .with_suggestion(suggestion.to_string()),
+        )
+    }
+}
+#[cfg(feature = "api")]
+mod server {
+    use super::{error_body, is_probe, route, ApiCommand, ApiOptions, ApiRequest};
+    use crate::errors::{ErrorCode, Result, SymorError};
+    use serde_json::Value;
+    use std::{net::SocketAddr, sync::{mpsc::{self, Sender}, Arc}, thread, time::Duration};
+    /// How long a request waits for the daemon loop, which may be in the middle of a scan
+    const REPLY_TIMEOUT: Duration = Duration::from_secs(60);
+    /// How long a health probe waits; a loop stuck for longer counts as unhealthy
+    const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+    /// A running control API server; dropping it stops the server
+    pub struct ApiServer {
+        server: Arc<tiny_http::Server>,
+        thread: Option<thread::JoinHandle<()>>,
+    }
+    impl ApiServer {
+        /// Listens on `options.addr` and forwards authorized requests to `requests`
+        pub fn start(options: ApiOptions, requests: Sender<ApiRequest>) -> Result<Self> {
+            let server = tiny_http::Server::http(options.addr)
+                .map_err(|e| {
+                    SymorError::new(
+                            ErrorCode::NetworkError,
+                            format!("Cannot listen on {}: {}", options.addr, e),
+                        )
+                        .with_suggestion("Pick another address with --api <ADDR>".to_string())
+                })?;
+            let server = Arc::new(server);
+            let listener = server.clone();
+            let thread = thread::spawn(move || {
+                for request in listener.incoming_requests() {
+                    handle(request, &options.token, &requests);
+                }
+            });
+            Ok(Self { server, thread: Some(thread) })
+        }
+        /// Address actually bound, useful when listening on port 0
+        pub fn addr(&self) -> Option<SocketAddr> {
+            self.server.server_addr().to_ip()
+        }
+    }
+    impl Drop for ApiServer {
+        fn drop(&mut self) {
+            self.server.unblock();
+            if let Some(thread) = self.thread.take() {
+                let _ = thread.join();
+            }
+        }
+    }
+    /// Whether `given` equals `expected`, taking as long wherever they first differ so
+    /// response times do not reveal how much of a guessed token was right
+    pub(super) fn same_secret(given: &[u8], expected: &[u8]) -> bool {
+        given.len() == expected.len()
+            && given.iter().zip(expected).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+    }
+    fn handle(mut request: tiny_http::Request, token: &str, requests: &Sender<ApiRequest>) {
+        let expected = format!("Bearer {}", token);
+        let probe = is_probe(&request.method().to_string(), request.url());
+        let authorized = probe
+            || request.headers().iter().any(|h| {
+                h.field.equiv("Authorization")
+                    && same_secret(h.value.as_str().as_bytes(), expected.as_bytes())
+            });
+        let mut body = String::new();
+        let (code, value) = if !authorized {
+            error_body(401, "Missing or wrong bearer token")
+        } else if std::io::Read::read_to_string(request.as_reader(), &mut body).is_err() {
+            error_body(400, "Unreadable request body")
+        } else {
+            match route(&request.method().to_string(), request.url(), &body) {
+                Ok(command) => dispatch(command, requests),
+                Err(response) => response,
+            }
+        };
+        let header = tiny_http::Header::from_bytes("Content-Type", "application/json")
+            .expect("static header is valid");
+        let response = tiny_http::Response::from_string(value.to_string())
+            .with_status_code(code)
+            .with_header(header);
+        let _ = request.respond(response);
+    }
+    fn dispatch(command: ApiCommand, requests: &Sender<ApiRequest>) -> (u16, Value) {
+        let probe = matches!(command, ApiCommand::Health | ApiCommand::Ready);
+        let (reply, replies) = mpsc::channel();
+        if requests.send(ApiRequest { command, reply }).is_err() {
+            return error_body(503, "Daemon is shutting down");
+        }
+        if probe {
+            return replies
+                .recv_timeout(PROBE_TIMEOUT)
+                .unwrap_or_else(|_| error_body(503, "Daemon loop is not responding"));
+        }
+        replies.recv_timeout(REPLY_TIMEOUT).unwrap_or_else(|_| error_body(504, "Daemon is busy"))
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+    #[test]
+    fn test_route_and_token() {
+        assert_eq!(route("GET", "/v1/status", ""), Ok(ApiCommand::Status));
+        assert_eq!(route("GET", "/v1/items/", ""), Ok(ApiCommand::Items));
+        assert_eq!(
+            route("POST", "/v1/sync", ""),
+            Ok(ApiCommand::Sync { item: None, force: false })
+        );
+        let hosts = format!("/v1/sync?item={}&force=true", percent_encode("/etc/my hosts"));
+        assert_eq!(
+            route("POST", &hosts, ""),
+            Ok(ApiCommand::Sync { item: Some("/etc/my hosts".to_string()), force: true })
+        );
+        assert_eq!(
+            route(
+                "POST",
+                "/v1/watch",
+                r#"{"path": "/srv", "recursive": true, "only": ["*.conf"], "name": "srv"}"#,
+            ),
+            Ok(ApiCommand::Watch {
+                path: PathBuf::from("/srv"),
+                recursive: true,
+                only: vec!["*.conf".to_string()],
+                skip_hidden: None,
+                name: Some("srv".to_string()),
+            })
+        );
+        assert_eq!(
+            route("POST", "/v1/restore", r#"{"item": "a.txt", "version": "@latest~1"}"#),
+            Ok(ApiCommand::Restore {
+                item: "a.txt".to_string(),
+                version: "@latest~1".to_string(),
+                target: None,
+            })
+        );
+        assert_eq!(route("POST", "/v1/restore", "{}").unwrap_err().0, 400);
+        assert_eq!(route("GET", "/v1/pause", "").unwrap_err().0, 405);
+        assert_eq!(route("GET", "/v2/status", "").unwrap_err().0, 404);
+        assert_eq!(route("GET", "/healthz", ""), Ok(ApiCommand::Health));
+        assert_eq!(route("GET", "/readyz/", ""), Ok(ApiCommand::Ready));
+        assert_eq!(route("POST", "/healthz", "").unwrap_err().0, 405);
+        assert!(is_probe("GET", "/readyz?verbose=1") && !is_probe("GET", "/v1/status"));
+        let temp_dir = tempdir().unwrap();
+        let token = load_or_create_token(temp_dir.path()).unwrap();
+        assert_eq!(token.len(), 32);
+        assert!(token.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_eq!(load_or_create_token(temp_dir.path()).unwrap(), token);
+        assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 1);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(token_file(temp_dir.path())).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        #[cfg(feature = "api")]
+        {
+            assert!(server::same_secret(b"Bearer abc", b"Bearer abc"));
+            assert!(!server::same_secret(b"Bearer abd", b"Bearer abc"));
+            assert!(!server::same_secret(b"Bearer ab", b"Bearer abc"));
+        }
+    }
+}
\ No newline at end of file
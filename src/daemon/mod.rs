@@ -0,0 +1,710 @@
+pub mod api;
+pub mod service;
+#[cfg(windows)]
+pub mod windows;
+use crate::errors::{ErrorCode, Result, SymorError};
+use crate::{
+    events::{EventHub, SyncEvent}, file_rules::FileRules, linking::LinkType,
+    monitoring::{
+        summary::{self, ActivityTally, SummaryPeriod}, CommandSubscriber, NotificationSystem,
+    },
+    symlinks::SymlinkMode, Mirror, MirrorBuilder, MirrorSpec, SymorManager, WatchedItem,
+};
+use log::{error, info, warn};
+use api::{ApiCommand, ApiOptions, ApiRequest};
+use serde_json::{json, Value};
+use std::{
+    fs, path::{Path, PathBuf},
+    sync::{atomic::{AtomicBool, Ordering}, mpsc, Arc},
+    thread, time::{Duration, Instant, SystemTime},
+};
+#[derive(Debug, Clone)]
+pub struct DaemonOptions {
+    /// How often watched files are checked for changes
+    pub scan_interval: Duration,
+    /// How often old restore temp files and `.pre-restore` copies are cleaned up
+    pub cleanup_interval: Duration,
+    /// Serve the local control API (see `api`) while running
+    pub api: Option<ApiOptions>,
+}
+impl Default for DaemonOptions {
+    fn default() -> Self {
+        Self {
+            scan_interval: Duration::from_secs(2),
+            cleanup_interval: Duration::from_secs(3600),
+            api: None,
+        }
+    }
+}
+pub fn pid_file(home_dir: &Path) -> PathBuf {
+    home_dir.join("daemon.pid")
+}
+/// Locked by the running daemon for as long as it runs
+pub fn lock_file(home_dir: &Path) -> PathBuf {
+    home_dir.join("daemon.lock")
+}
+pub fn log_file(home_dir: &Path) -> PathBuf {
+    home_dir.join("logs").join("daemon.log")
+}
+/// PID of the running daemon, ignoring stale pid files
+pub fn running_pid(home_dir: &Path) -> Option<u32> {
+    let pid: u32 = fs::read_to_string(pid_file(home_dir)).ok()?.trim().parse().ok()?;
+    if process_alive(pid) { Some(pid) } else { None }
+}
+/// Whether a process with `pid` exists
+#[cfg(unix)]
+pub fn process_alive(pid: u32) -> bool {
+    let Ok(pid) = i32::try_from(pid) else {
+        return false;
+    };
+    let alive = unsafe { libc::kill(pid, 0) == 0 };
+    alive || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+#[cfg(windows)]
+pub fn process_alive(pid: u32) -> bool {
+    use windows_sys::Win32::{
+        Foundation::{CloseHandle, STILL_ACTIVE},
+        System::Threading::{GetExitCodeProcess, OpenProcess, PROCESS_QUERY_LIMITED_INFORMATION},
+    };
+    unsafe {
+        let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+        if handle.is_null() {
+            return false;
+        }
+        let mut exit_code = 0u32;
+        let ok = GetExitCodeProcess(handle, &mut exit_code) != 0;
+        CloseHandle(handle);
+        ok && exit_code == STILL_ACTIVE as u32
+    }
+}
+#[cfg(not(any(unix, windows)))]
+pub fn process_alive(_pid: u32) -> bool {
+    true
+}
+fn already_running(pid: Option<u32>) -> SymorError {
+    let message = match pid {
+        Some(pid) => format!("Daemon is already running (pid {})", pid),
+        None => "Daemon is already running".to_string(),
+    };
+    SymorError::new(ErrorCode::InternalError, message)
+        .with_suggestion("Use 'sym daemon status' to inspect it".to_string())
+}
+/// Takes the daemon lock and records this process in the pid file. The lock is held
+/// until the returned file is dropped, so of two daemons started together only one gets
+/// past here; a pid file left by a daemon that died is replaced.
+fn lock_daemon(home_dir: &Path) -> Result<fs::File> {
+    let lock = fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(lock_file(home_dir))?;
+    match lock.try_lock() {
+        Ok(()) => {}
+        Err(fs::TryLockError::WouldBlock) => return Err(already_running(running_pid(home_dir))),
+        Err(fs::TryLockError::Error(e)) => return Err(e.into()),
+    }
+    if let Some(pid) = running_pid(home_dir).filter(|pid| *pid != std::process::id()) {
+        return Err(already_running(Some(pid)));
+    }
+    fs::write(pid_file(home_dir), std::process::id().to_string())?;
+    Ok(lock)
+}
+/// Removes the pid file (or API address file) when the daemon stops
+struct PidGuard(PathBuf);
+impl Drop for PidGuard {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+/// Background process that keeps mirrors in sync and versions watched files
+pub struct Daemon {
+    manager: SymorManager,
+    options: DaemonOptions,
+    events: EventHub,
+    /// Whether `sym pause --all` holds scans and mirror syncs
+    paused: Arc<AtomicBool>,
+    /// Pause flag of each running mirror, by source
+    mirror_pauses: Vec<(PathBuf, Arc<AtomicBool>)>,
+    /// Thread of each running mirror, by source
+    mirror_threads: Vec<(PathBuf, thread::JoinHandle<Result<()>>)>,
+    /// Sources of mirrors whose thread ended, which only happens when they fail
+    stopped_mirrors: Vec<PathBuf>,
+    last_scan: Option<SystemTime>,
+    /// Latest scan, backup or mirror failure, for the health probes
+    last_error: Option<(SystemTime, String)>,
+    /// Activity since the last summary
+    tally: ActivityTally,
+    /// Where summaries go, set up from the `notifications` config section
+    notifications: NotificationSystem,
+    /// `file_rules` of the config, compiled when the daemon starts
+    file_rules: FileRules,
+    /// Sync rules from the home directory, loaded when the daemon starts
+    #[cfg(feature = "scripting")]
+    rules: Option<Arc<crate::rules::SyncRules>>,
+}
+impl Daemon {
+    pub fn new(manager: SymorManager, options: DaemonOptions) -> Self {
+        Self {
+            manager,
+            options,
+            events: EventHub::new(),
+            paused: Arc::default(),
+            mirror_pauses: Vec::new(),
+            mirror_threads: Vec::new(),
+            stopped_mirrors: Vec::new(),
+            last_scan: None,
+            last_error: None,
+            tally: ActivityTally::new(),
+            notifications: NotificationSystem::new(),
+            file_rules: FileRules::default(),
+            #[cfg(feature = "scripting")]
+            rules: None,
+        }
+    }
+    /// Receiver for the sync events of every mirror the daemon runs
+    pub fn subscribe(&self) -> std::sync::mpsc::Receiver<SyncEvent> {
+        self.events.subscribe()
+    }
+    /// Calls `callback` on the mirror threads for each sync event
+    pub fn on_event(&self, callback: impl Fn(&SyncEvent) + Send + 'static) {
+        self.events.on_event(callback);
+    }
+    pub fn run(self) -> Result<()> {
+        self.run_until(&AtomicBool::new(false))
+    }
+    /// Runs until `stop` is set, checking it between scans
+    pub fn run_until(mut self, stop: &AtomicBool) -> Result<()> {
+        let home_dir = self.manager.config().home_dir.clone();
+        let _lock = lock_daemon(&home_dir)?;
+        let _guard = PidGuard(pid_file(&home_dir));
+        if let Some((path, _)) = self.manager.apply_topology_file()? {
+            info!("Loaded topology from {:?}", path);
+        }
+        info!(
+            "Daemon started (pid {}, {} mirror(s), {} watched item(s))", std::process::id(),
+            self.manager.mirrors().len(), self.manager.watched_index().len()
+        );
+        self.file_rules = self.manager.file_rules()?;
+        if self.manager.config().performance.background_priority {
+            match crate::priority::enter_background() {
+                Ok(()) => info!("Running syncs and backups at background priority"),
+                Err(e) => warn!("Cannot lower the daemon's priority: {}", e),
+            }
+        }
+        #[cfg(feature = "scripting")]
+        {
+            self.rules = self.manager.load_sync_rules()?.map(Arc::new);
+            if self.rules.is_some() {
+                info!("Loaded sync rules from {:?}", crate::rules::rules_file(&home_dir));
+            }
+        }
+        let summary_period = self.manager.config().notifications.summary;
+        let mut summary_from = summary_period.map(|period| self.start_summaries(period));
+        let link_type = self.manager.config().linking.link_type.parse()?;
+        self.paused.store(self.manager.is_paused(), Ordering::SeqCst);
+        for spec in self.manager.mirrors().to_vec() {
+            let held = self.manager.is_paused() || self.manager.in_maintenance();
+            let paused = Arc::new(AtomicBool::new(held || spec.paused));
+            self.mirror_pauses.push((spec.source.clone(), paused.clone()));
+            let source = spec.source.clone();
+            let builder = self.mirror_builder(&source, link_type);
+            let thread = spawn_mirror(spec, builder, self.events.clone(), paused);
+            self.mirror_threads.push((source, thread));
+        }
+        let (api_tx, api_rx) = mpsc::channel();
+        let _api_server = self.start_api(api_tx)?;
+        let _addr_guard = PidGuard(api::addr_file(&home_dir));
+        let mut last_cleanup: Option<Instant> = None;
+        while !stop.load(Ordering::SeqCst) {
+            self.refresh_pauses();
+            let maintenance = self.manager.in_maintenance();
+            if !self.paused.load(Ordering::SeqCst) && !maintenance {
+                if let Err(e) = self.scan_once() {
+                    error!("Scan failed: {}", e);
+                    self.record_error(format!("Scan failed: {}", e));
+                }
+            }
+            let cleanup_due =
+                last_cleanup.is_none_or(|at| at.elapsed() >= self.options.cleanup_interval);
+            if cleanup_due && !maintenance {
+                match self.manager.cleanup_restore_files() {
+                    Ok(0) => {}
+                    Ok(removed) => info!("Removed {} old restore files", removed),
+                    Err(e) => warn!("Restore file cleanup failed: {}", e),
+                }
+                last_cleanup = Some(Instant::now());
+            }
+            if let (Some(period), Some(from)) = (summary_period, summary_from) {
+                if from.elapsed().unwrap_or_default() >= period.duration() {
+                    summary_from = Some(self.send_summary(period, from));
+                }
+            }
+            let next_scan = Instant::now() + self.options.scan_interval;
+            while let Some(wait) = next_scan.checked_duration_since(Instant::now()) {
+                match api_rx.recv_timeout(wait) {
+                    Ok(request) => {
+                        let _ = request.reply.send(self.handle_api(request.command));
+                    }
+                    Err(mpsc::RecvTimeoutError::Timeout) => break,
+                    Err(mpsc::RecvTimeoutError::Disconnected) => thread::sleep(wait),
+                }
+            }
+        }
+        info!("Daemon stopped");
+        Ok(())
+    }
+    fn record_error(&mut self, message: String) {
+        self.tally.record_error(message.clone());
+        self.last_error = Some((SystemTime::now(), message));
+    }
+    /// Starts counting activity for summaries and subscribes the configured commands;
+    /// returns when the current period started, which survives restarts, though the
+    /// counts only cover the time the daemon ran
+    fn start_summaries(&mut self, period: SummaryPeriod) -> SystemTime {
+        let tally = self.tally.clone();
+        self.events.on_event(move |event| tally.record_sync(event));
+        let tally = self.tally.clone();
+        self.manager.on_version_event(move |event| tally.record_version(event));
+        let tally = self.tally.clone();
+        self.manager.on_secret_detected(move |warning| tally.record_error(warning.to_string()));
+        let config = self.manager.config();
+        for command in &config.notifications.summary_commands {
+            let subscriber = CommandSubscriber::new(command.clone(), &config.home_dir);
+            self.notifications.subscribe(Box::new(subscriber));
+        }
+        let from = summary::last_summary(&config.home_dir).unwrap_or_else(|| {
+            let now = SystemTime::now();
+            if let Err(e) = summary::record_summary(&config.home_dir, now) {
+                warn!("Cannot record the summary period: {}", e);
+            }
+            now
+        });
+        info!(
+            "Sending {} summaries to {} command(s)", period,
+            config.notifications.summary_commands.len()
+        );
+        from
+    }
+    /// Sends the summary of the period that started at `from`; returns when the next one
+    /// starts
+    fn send_summary(&mut self, period: SummaryPeriod, from: SystemTime) -> SystemTime {
+        let summary = match self.manager.activity_summary(period, from, &self.tally) {
+            Ok(summary) => summary,
+            Err(e) => {
+                warn!("Cannot build the {} summary: {}", period, e);
+                return SystemTime::now();
+            }
+        };
+        info!("{}", summary.title());
+        if let Err(e) = self.notifications.notify_summary(&summary) {
+            warn!("Cannot send the {} summary: {}", period, e);
+        }
+        let home_dir = &self.manager.config().home_dir;
+        if let Err(e) = summary::record_summary(home_dir, summary.to) {
+            warn!("Cannot record the summary period: {}", e);
+        }
+        summary.to
+    }
+    /// Builder for a mirror of `source` with the daemon's settings and rules
+    fn mirror_builder(&self, source: &Path, link_type: LinkType) -> MirrorBuilder {
+        let config = self.manager.config();
+        let builder = Mirror::builder(source)
+            .link_type(link_type)
+            .shadow_copy(config.versioning.shadow_copy)
+            .trash(config.linking.trash)
+            .max_syncs_per_minute(config.linking.max_syncs_per_minute)
+            .case_collisions(config.linking.case_collisions)
+            .normalize_unicode(config.linking.normalize_unicode)
+            .owners(
+                self.manager
+                    .owner_map()
+                    .unwrap_or_else(|e| {
+                        warn!("Ignoring owner mapping rules: {}", e);
+                        None
+                    }),
+            )
+            .recovery(self.manager.error_recovery())
+            .reclaim_space(config.clone())
+            .metrics(&config.home_dir)
+            .file_rules(self.file_rules.clone());
+        #[cfg(feature = "scripting")]
+        if let Some(rules) = &self.rules {
+            return builder.rules(rules.clone());
+        }
+        builder
+    }
+    #[cfg(feature = "api")]
+    fn start_api(&self, requests: mpsc::Sender<ApiRequest>) -> Result<Option<api::ApiServer>> {
+        let Some(options) = self.options.api.clone() else {
+            return Ok(None);
+        };
+        let server = api::ApiServer::start(options.clone(), requests)?;
+        let addr = server.addr().unwrap_or(options.addr);
+        fs::write(api::addr_file(&self.manager.config().home_dir), addr.to_string())?;
+        info!("Control API listening on http://{}", addr);
+        Ok(Some(server))
+    }
+    #[cfg(not(feature = "api"))]
+    fn start_api(&self, _requests: mpsc::Sender<ApiRequest>) -> Result<Option<()>> {
+        if self.options.api.is_some() {
+            warn!("Built without the api feature; the control API is not available");
+        }
+        Ok(None)
+    }
+    /// Runs a control API command on the daemon thread, returning the HTTP status and
+    /// JSON body to answer with
+    pub fn handle_api(&mut self, command: ApiCommand) -> (u16, Value) {
+        let failed = |e: SymorError| {
+            let status = match e.code() {
+                ErrorCode::NotWatched | ErrorCode::VersionNotFound | ErrorCode::FileNotFound => {
+                    404
+                }
+                ErrorCode::InvalidPath | ErrorCode::InvalidConfiguration => 400,
+                ErrorCode::MaintenanceMode => 503,
+                ErrorCode::RetentionLocked => 409,
+                ErrorCode::SecretDetected => 422,
+                _ => 500,
+            };
+            (status, json!({ "error" : e.message, "code" : e.code, "suggestion" : e
+            .recovery_suggestion }))
+        };
+        let secs = |time: SystemTime| {
+            time.duration_since(SystemTime::UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+        };
+        match command {
+            ApiCommand::Health => (200, self.health()),
+            ApiCommand::Ready => {
+                let health = self.health();
+                let ready = health["ready"].as_bool().unwrap_or(false);
+                (if ready { 200 } else { 503 }, health)
+            }
+            ApiCommand::Status => {
+                let mirrors: Vec<Value> = self
+                    .manager
+                    .mirrors()
+                    .iter()
+                    .map(|spec| {
+                        json!({ "source" : spec.source, "targets" : spec.targets,
+                        "bidirectional" : spec.bidirectional })
+                    })
+                    .collect();
+                (200, json!({ "pid" : std::process::id(), "version" :
+                env!("CARGO_PKG_VERSION"), "paused" : self.paused.load(Ordering::SeqCst),
+                "watched_items" : self.manager.watched_index().len(), "mirrors" : mirrors,
+                "last_scan" : self.last_scan.map(secs) }))
+            }
+            ApiCommand::Items => {
+                if let Err(e) = self.manager.load_watched_items() {
+                    return failed(e);
+                }
+                let mut items: Vec<_> = self.manager.watched_index().collect();
+                items.sort_by(|a, b| a.path.cmp(&b.path));
+                let items: Vec<Value> = items
+                    .into_iter()
+                    .map(|item| {
+                        json!({ "id" : item.id, "path" : item.path, "directory" :
+                        item.is_directory, "paused" : item.paused, "versions" : item.versions
+                        .len(), "last_version" : item.versions.last().map(| v | secs(v
+                        .timestamp)) })
+                    })
+                    .collect();
+                (200, json!({ "items" : items }))
+            }
+            ApiCommand::Sync { item, force } => {
+                if let Err(e) = self.manager.load_watched_items() {
+                    return failed(e);
+                }
+                let shadow_copy = self.manager.config().versioning.shadow_copy;
+                let symlinks = self.manager.config().versioning.symlinks;
+                let ids: Vec<String> = match item {
+                    Some(item) => {
+                        match self.manager.resolve_item(&item) {
+                            Some(found)
+                                if force || needs_version(found, shadow_copy, symlinks) => {
+                                vec![found.id.clone()]
+                            }
+                            Some(_) => Vec::new(),
+                            None => return failed(SymorError::not_watched(&item)),
+                        }
+                    }
+                    None => {
+                        self.last_scan = Some(SystemTime::now());
+                        self.manager
+                            .watched_index()
+                            .filter(|item| !item.is_directory && !item.paused && item.path.is_file())
+                            .filter(|item| self.rules_include(item))
+                            .filter(|item| force || needs_version(item, shadow_copy, symlinks))
+                            .map(|item| item.id.clone())
+                            .collect()
+                    }
+                };
+                let jobs = crate::performance::ParallelProcessor::get_optimal_concurrency();
+                let results = match self.manager.backup_items(&ids, jobs) {
+                    Ok(results) => results,
+                    Err(e) => return failed(e),
+                };
+                let (mut versioned, mut errors) = (Vec::new(), Vec::new());
+                for (id, result) in results {
+                    let path = self.manager.resolve_item(&id).map(|item| item.path.clone());
+                    match result {
+                        Ok(()) => versioned.push(json!({ "id" : id, "path" : path })),
+                        Err(e) => {
+                            errors.push(json!({ "id" : id, "path" : path, "error" : e.to_string() }))
+                        }
+                    }
+                }
+                (200, json!({ "versioned" : versioned, "failed" : errors }))
+            }
+            ApiCommand::Watch { path, recursive, only, skip_hidden, name } => {
+                if let Err(e) = self.manager.load_watched_items() {
+                    return failed(e);
+                }
+                if !path.exists() {
+                    let message = format!("File does not exist: {:?}", path);
+                    return failed(SymorError::new(ErrorCode::FileNotFound, message));
+                }
+                if let Some(found) = self.manager.resolve_item(&path.to_string_lossy()) {
+                    let message = format!("Already watched: {:?} (ID {})", path, found.id);
+                    return failed(SymorError::new(ErrorCode::InvalidPath, message));
+                }
+                let skip_hidden = skip_hidden.unwrap_or(self.manager.config().skip_hidden);
+                match self.manager.watch_named(path, recursive, only, skip_hidden, name) {
+                    Ok(id) => (200, json!({ "id" : id })),
+                    Err(e) => failed(e),
+                }
+            }
+            ApiCommand::Pause(item) => {
+                match self.set_paused(item.as_deref(), true) {
+                    Ok(path) => (200, json!({ "paused" : true, "path" : path })),
+                    Err(e) => failed(e),
+                }
+            }
+            ApiCommand::Resume(item) => {
+                match self.set_paused(item.as_deref(), false) {
+                    Ok(path) => (200, json!({ "paused" : false, "path" : path })),
+                    Err(e) => failed(e),
+                }
+            }
+            ApiCommand::Restore { item, version, target } => {
+                let (id, path) = match self.manager.resolve_item(&item) {
+                    Some(found) => (found.id.clone(), found.path.clone()),
+                    None => return failed(SymorError::not_watched(&item)),
+                };
+                let target = target.unwrap_or(path);
+                let restored = self
+                    .manager
+                    .resolve_version(&id, &version)
+                    .and_then(|version_id| {
+                        let result = self.manager.restore_file(&id, &version_id, &target)?;
+                        Ok((version_id, result))
+                    });
+                match restored {
+                    Ok((version_id, result)) => {
+                        (200, json!({ "item" : id, "version" : version_id, "target" : target,
+                        "verified" : result.verified, "backup_created" : result.backup_created
+                        }))
+                    }
+                    Err(e) => failed(e),
+                }
+            }
+        }
+    }
+    /// Watcher state, queue depth and last error, as the health probes report them
+    fn health(&mut self) -> Value {
+        let secs = |time: SystemTime| {
+            time.duration_since(SystemTime::UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+        };
+        self.reap_mirrors();
+        let paused = self.paused.load(Ordering::SeqCst);
+        let home_dir = &self.manager.config().home_dir;
+        let statuses: Vec<_> = crate::monitoring::status::MirrorStatus::load_all(home_dir)
+            .into_iter()
+            .filter(|status| status.pid == std::process::id())
+            .collect();
+        let mut last_error = self.last_error.clone();
+        for status in &statuses {
+            if let (Some(at), Some(message)) = (status.last_error_at, &status.last_error) {
+                if last_error.as_ref().is_none_or(|(latest, _)| at > *latest) {
+                    let message = format!("{}: {}", status.source.display(), message);
+                    last_error = Some((at, message));
+                }
+            }
+        }
+        let maintenance = self.manager.maintenance();
+        let ready = !paused && maintenance.is_none() && self.stopped_mirrors.is_empty();
+        json!({ "status" : if self.stopped_mirrors.is_empty() { "ok" } else { "degraded" },
+        "ready" : ready, "pid" : std::process::id(), "watchers" : json!({ "mirrors" : self
+        .mirror_threads.len() + self.stopped_mirrors.len(), "running" : self.mirror_threads
+        .len(), "stopped" : self.stopped_mirrors, "watched_items" : self.manager
+        .watched_index().len(), "paused" : paused }), "maintenance" : maintenance.map(| m |
+        json!({ "since" : secs(m.since), "reason" : m.reason })), "queue_depth" : statuses.iter().map(|
+        status | status.queued).sum::< usize > (), "last_scan" : self.last_scan.map(secs),
+        "last_error" : last_error.map(| (at, message) | json!({ "message" : message, "at" :
+        secs(at) })) })
+    }
+    /// Moves mirrors whose thread ended to `stopped_mirrors`, keeping their error
+    fn reap_mirrors(&mut self) {
+        let (ended, running) = std::mem::take(&mut self.mirror_threads)
+            .into_iter()
+            .partition(|(_, thread)| thread.is_finished());
+        self.mirror_threads = running;
+        for (source, thread) in ended {
+            let message = match thread.join() {
+                Ok(Err(e)) => format!("Mirror for {} stopped: {}", source.display(), e),
+                Ok(Ok(())) => format!("Mirror for {} stopped", source.display()),
+                Err(_) => format!("Mirror for {} panicked", source.display()),
+            };
+            self.record_error(message);
+            self.stopped_mirrors.push(source);
+        }
+    }
+    /// Pauses or resumes one item or mirror source, or everything for `None`; returns the
+    /// path of the one
+    fn set_paused(&mut self, item: Option<&str>, paused: bool) -> Result<Option<PathBuf>> {
+        self.manager.load_watched_items()?;
+        self.manager.load_mirrors()?;
+        let path = match item {
+            Some(item) => Some(self.manager.set_paused(item, paused)?),
+            None => {
+                self.manager.set_all_paused(paused)?;
+                None
+            }
+        };
+        self.refresh_pauses();
+        let what = path.as_ref().map_or("Syncing".to_string(), |p| format!("{:?}", p));
+        info!("{} {} through the control API", what, if paused { "paused" } else { "resumed" });
+        Ok(path)
+    }
+    /// Picks up pauses made with `sym pause` since the last check; maintenance mode
+    /// holds every mirror too
+    fn refresh_pauses(&mut self) {
+        if let Err(e) = self.manager.load_mirrors() {
+            warn!("Cannot reload mirrors: {}", e);
+        }
+        let all = self.manager.is_paused();
+        self.paused.store(all, Ordering::SeqCst);
+        let all = all || self.manager.in_maintenance();
+        for (source, flag) in &self.mirror_pauses {
+            let paused = self.manager.mirrors().iter().any(|m| &m.source == source && m.paused);
+            flag.store(all || paused, Ordering::SeqCst);
+        }
+    }
+    /// Whether the file rules and sync rules let the daemon version `item`
+    fn rules_include(&self, item: &WatchedItem) -> bool {
+        let name = item.path.file_name().map(Path::new).unwrap_or(&item.path);
+        if !self.file_rules.decide(&item.path, name).version {
+            return false;
+        }
+        #[cfg(feature = "scripting")]
+        if let Some(rules) = &self.rules {
+            return rules.includes(&item.path, name);
+        }
+        true
+    }
+    /// Creates a version for every watched file whose content changed; returns their ids
+    pub fn scan_once(&mut self) -> Result<Vec<String>> {
+        self.manager.load_watched_items()?;
+        self.last_scan = Some(SystemTime::now());
+        let shadow_copy = self.manager.config().versioning.shadow_copy;
+        let symlinks = self.manager.config().versioning.symlinks;
+        let candidates: Vec<String> = self
+            .manager
+            .watched_index()
+            .filter(|item| !item.is_directory && !item.paused && item.path.is_file())
+            .filter(|item| self.rules_include(item) && needs_version(item, shadow_copy, symlinks))
+            .map(|item| item.id.clone())
+            .collect();
+        for id in &candidates {
+            match self.manager.create_backup(id) {
+                Ok(()) => info!("Versioned changed item {}", id),
+                Err(e) => {
+                    warn!("Backup of {} failed: {}", id, e);
+                    self.record_error(format!("Backup of {} failed: {}", id, e));
+                    crate::monitoring::metrics::record(
+                        &self.manager.config().home_dir,
+                        |bucket| bucket.errors += 1,
+                    );
+                }
+            }
+        }
+        Ok(candidates)
+    }
+}
+/// Whether a watched file changed since its last version (or is locked, when shadow
+/// copies can still read it). With `symlinks` preserved, a watched link changes when it
+/// is pointed somewhere else.
+fn needs_version(item: &WatchedItem, shadow_copy: bool, symlinks: SymlinkMode) -> bool {
+    let link = match symlinks {
+        SymlinkMode::Follow => None,
+        SymlinkMode::Preserve | SymlinkMode::Skip => crate::symlinks::link_target(&item.path),
+    };
+    if link.is_some() && symlinks == SymlinkMode::Skip {
+        return false;
+    }
+    let metadata = match link {
+        Some(_) => fs::symlink_metadata(&item.path),
+        None => fs::metadata(&item.path),
+    };
+    let modified = metadata.and_then(|m| m.modified()).unwrap_or(SystemTime::now());
+    if !item.versions.is_empty() && modified <= item.last_modified {
+        return false;
+    }
+    let current = match &link {
+        Some(target) => Ok(crate::symlinks::target_hash(target)),
+        None => crate::performance::memory::hash_file(&item.path),
+    };
+    match (current, item.versions.last()) {
+        (Ok(hash), Some(last)) => hash != last.hash,
+        (Ok(_), None) => true,
+        (Err(e), _) => shadow_copy && crate::shadow_copy::is_sharing_violation(&e),
+    }
+}
+/// Runs the mirror for `spec` on its own thread, configured from `builder`
+fn spawn_mirror(
+    spec: MirrorSpec,
+    builder: MirrorBuilder,
+    events: EventHub,
+    paused: Arc<AtomicBool>,
+) -> thread::JoinHandle<Result<()>> {
+    thread::spawn(move || {
+        let source = spec.source.clone();
+        let builder = builder
+            .targets(spec.targets)
+            .bidirectional(spec.bidirectional)
+            .skip_hidden(spec.skip_hidden)
+            .storm_threshold(spec.storm_threshold.unwrap_or(crate::storm::DEFAULT_THRESHOLD))
+            .rollover(spec.rollover)
+            .keep_rotations(spec.keep_rotations.unwrap_or(0))
+            .link_rotations(spec.link_rotations)
+            .symlinks(spec.symlinks)
+            .events(events)
+            .pause_flag(paused);
+        let result = spec
+            .only
+            .into_iter()
+            .fold(builder, MirrorBuilder::only)
+            .build()
+            .and_then(Mirror::run);
+        if let Err(e) = &result {
+            error!("Mirror for {:?} stopped: {}", source, e);
+        }
+        result
+    })
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_daemon_lock() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let home_dir = temp_dir.path();
+        fs::write(pid_file(home_dir), u32::MAX.to_string()).unwrap();
+        let lock = lock_daemon(home_dir).unwrap();
+        let recorded = fs::read_to_string(pid_file(home_dir)).unwrap();
+        assert_eq!(recorded, std::process::id().to_string());
+        let error = lock_daemon(home_dir).unwrap_err();
+        assert!(error.message.contains("already running"), "{}", error.message);
+        drop(lock);
+        assert!(lock_daemon(home_dir).is_ok());
+    }
+}
@@ -0,0 +1,480 @@
+use crate::errors::{ErrorCode, Result, SymorError};
+use log::warn;
+use std::{fs, path::{Path, PathBuf}, process::Command};
+pub const SERVICE_NAME: &str = "symor";
+/// Whether the service runs for the current user or system-wide
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServiceScope {
+    User,
+    System,
+}
+/// Everything needed to describe how the service starts the daemon
+#[derive(Debug, Clone)]
+pub struct ServiceSpec {
+    pub scope: ServiceScope,
+    pub executable: PathBuf,
+    pub home_dir: PathBuf,
+    /// Passed as `--portable=<home_dir>` when the home directory is overridden (by
+    /// `--home`, `--portable` or `SYMOR_HOME`), and always on Windows where services
+    /// run under a different account
+    pub portable: bool,
+    pub user: Option<String>,
+}
+impl ServiceSpec {
+    pub fn for_current_process(scope: ServiceScope, home_dir: &Path) -> Result<Self> {
+        Ok(Self {
+            scope,
+            executable: std::env::current_exe()?,
+            home_dir: home_dir.to_path_buf(),
+            portable: crate::home_override().is_some() || cfg!(windows),
+            user: std::env::var("USER").ok(),
+        })
+    }
+    /// Arguments after the executable that start the daemon in the foreground
+    pub fn daemon_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if self.portable {
+            args.push(format!("--portable={}", self.home_dir.display()));
+        }
+        args.push("daemon".to_string());
+        args.push("run".to_string());
+        args
+    }
+}
+pub fn systemd_unit_path(scope: ServiceScope) -> Result<PathBuf> {
+    match scope {
+        ServiceScope::User => {
+            let config_dir = std::env::var("XDG_CONFIG_HOME")
+                .map(PathBuf::from)
+                .or_else(|_| {
+                    std::env::var("HOME").map(|home| PathBuf::from(home).join(".config"))
+                })
+                .map_err(|_| {
+                    SymorError::new(
+                        ErrorCode::MissingConfiguration,
+                        "Cannot determine the systemd user unit directory".to_string(),
+                    )
+                })?;
+            Ok(
+                config_dir
+                    .join("systemd")
+                    .join("user")
+                    .join(format!("{}.service", SERVICE_NAME)),
+            )
+        }
+        ServiceScope::System => {
+            Ok(PathBuf::from(format!("/etc/systemd/system/{}.service", SERVICE_NAME)))
+        }
+    }
+}
+/// Joins arguments into one command line, quoting those that contain spaces
+fn command_line(args: impl Iterator<Item = String>) -> String {
+    args.map(|arg| if arg.contains(' ') { format!("\"{}\"", arg) } else { arg })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+pub fn systemd_unit(spec: &ServiceSpec) -> String {
+    let exec_start = command_line(
+        std::iter::once(spec.executable.display().to_string()).chain(spec.daemon_args()),
+    );
+    let mut unit = String::new();
+    unit.push_str("[Unit]\n");
+    unit.push_str("Description=Symor file mirroring and versioning daemon\n");
+    unit.push_str("After=network.target\n\n");
+    unit.push_str("[Service]\n");
+    unit.push_str("Type=simple\n");
+    unit.push_str(&format!("ExecStart={}\n", exec_start));
+    unit.push_str("Restart=on-failure\n");
+    unit.push_str("RestartSec=5\n");
+    if spec.scope == ServiceScope::System {
+        if let Some(user) = &spec.user {
+            unit.push_str(&format!("User={}\n", user));
+        }
+    }
+    unit.push_str("\n[Install]\n");
+    match spec.scope {
+        ServiceScope::User => unit.push_str("WantedBy=default.target\n"),
+        ServiceScope::System => unit.push_str("WantedBy=multi-user.target\n"),
+    }
+    unit
+}
+fn systemctl(scope: ServiceScope, args: &[&str]) -> Result<String> {
+    let mut command = Command::new("systemctl");
+    if scope == ServiceScope::User {
+        command.arg("--user");
+    }
+    let output = command
+        .args(args)
+        .output()
+        .map_err(|e| {
+            SymorError::new(
+                    ErrorCode::InternalError,
+                    format!("Failed to run systemctl: {}", e),
+                )
+                .with_suggestion("systemd is required for service management".to_string())
+        })?;
+    let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+    if !output.status.success() && args.first() != Some(&"status") {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(
+            SymorError::new(
+                ErrorCode::InternalError,
+                format!("systemctl {} failed: {}", args.join(" "), stderr.trim()),
+            ),
+        );
+    }
+    Ok(stdout)
+}
+/// Asks systemd to pick up unit changes; without a running systemd the unit is still written
+fn reload(scope: ServiceScope) {
+    if let Err(e) = systemctl(scope, &["daemon-reload"]) {
+        warn!("Could not reload systemd units: {}", e);
+    }
+}
+pub const LAUNCHD_LABEL: &str = "com.symor.daemon";
+pub fn launchd_plist_path(scope: ServiceScope) -> Result<PathBuf> {
+    let file_name = format!("{}.plist", LAUNCHD_LABEL);
+    match scope {
+        ServiceScope::User => {
+            let home = std::env::var("HOME")
+                .map_err(|_| {
+                    SymorError::new(
+                        ErrorCode::MissingConfiguration,
+                        "Cannot determine the LaunchAgents directory".to_string(),
+                    )
+                })?;
+            Ok(PathBuf::from(home).join("Library").join("LaunchAgents").join(file_name))
+        }
+        ServiceScope::System => Ok(PathBuf::from("/Library/LaunchDaemons").join(file_name)),
+    }
+}
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+/// launchd property list that keeps the daemon alive and sends its output to the symor logs
+pub fn launchd_plist(spec: &ServiceSpec) -> String {
+    let logs_dir = spec.home_dir.join("logs");
+    let mut plist = String::new();
+    plist.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    plist.push_str(
+        "<!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n",
+    );
+    plist.push_str("<plist version=\"1.0\">\n<dict>\n");
+    plist.push_str(&format!("  <key>Label</key>\n  <string>{}</string>\n", LAUNCHD_LABEL));
+    plist.push_str("  <key>ProgramArguments</key>\n  <array>\n");
+    for arg in std::iter::once(spec.executable.display().to_string())
+        .chain(spec.daemon_args())
+    {
+        plist.push_str(&format!("    <string>{}</string>\n", xml_escape(&arg)));
+    }
+    plist.push_str("  </array>\n");
+    plist.push_str("  <key>RunAtLoad</key>\n  <true/>\n");
+    plist.push_str("  <key>KeepAlive</key>\n  <dict>\n");
+    plist.push_str("    <key>SuccessfulExit</key>\n    <false/>\n  </dict>\n");
+    plist.push_str("  <key>ThrottleInterval</key>\n  <integer>5</integer>\n");
+    plist.push_str(
+        &format!(
+            "  <key>StandardOutPath</key>\n  <string>{}</string>\n",
+            xml_escape(&logs_dir.join("daemon.out.log").display().to_string())
+        ),
+    );
+    plist.push_str(
+        &format!(
+            "  <key>StandardErrorPath</key>\n  <string>{}</string>\n",
+            xml_escape(&logs_dir.join("daemon.err.log").display().to_string())
+        ),
+    );
+    if spec.scope == ServiceScope::System {
+        if let Some(user) = &spec.user {
+            plist.push_str(
+                &format!("  <key>UserName</key>\n  <string>{}</string>\n", xml_escape(user)),
+            );
+        }
+    }
+    plist.push_str("</dict>\n</plist>\n");
+    plist
+}
+fn launchctl(args: &[&str]) -> Result<String> {
+    let output = Command::new("launchctl")
+        .args(args)
+        .output()
+        .map_err(|e| {
+            SymorError::new(
+                ErrorCode::InternalError,
+                format!("Failed to run launchctl: {}", e),
+            )
+        })?;
+    if !output.status.success() && args.first() != Some(&"list") {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(
+            SymorError::new(
+                ErrorCode::InternalError,
+                format!("launchctl {} failed: {}", args.join(" "), stderr.trim()),
+            ),
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+/// Command line the Windows Service Control Manager uses to start the daemon
+pub fn windows_command_line(spec: &ServiceSpec) -> String {
+    command_line(
+        std::iter::once(spec.executable.display().to_string())
+            .chain(spec.daemon_args())
+            .chain(std::iter::once("--service".to_string())),
+    )
+}
+fn sc(args: &[&str]) -> Result<String> {
+    let output = Command::new("sc.exe")
+        .args(args)
+        .output()
+        .map_err(|e| {
+            SymorError::new(
+                ErrorCode::InternalError,
+                format!("Failed to run sc.exe: {}", e),
+            )
+        })?;
+    let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+    if !output.status.success() && args.first() != Some(&"query") {
+        let mut err = SymorError::new(
+            ErrorCode::InternalError,
+            format!("sc.exe {} failed: {}", args.join(" "), stdout.trim()),
+        );
+        if stdout.contains("Access is denied") {
+            err = err
+                .with_suggestion(
+                    "Run the command from an elevated (Administrator) prompt".to_string(),
+                );
+        }
+        return Err(err);
+    }
+    Ok(stdout)
+}
+/// Init system that supervises the daemon on this platform
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServiceBackend {
+    Systemd,
+    Launchd,
+    /// Windows Service Control Manager; services are always system-wide
+    WindowsService,
+}
+impl ServiceBackend {
+    pub fn current() -> Self {
+        if cfg!(target_os = "macos") {
+            ServiceBackend::Launchd
+        } else if cfg!(windows) {
+            ServiceBackend::WindowsService
+        } else {
+            ServiceBackend::Systemd
+        }
+    }
+    /// Where the service is registered, for display
+    pub fn location(&self, scope: ServiceScope) -> Result<String> {
+        match self {
+            ServiceBackend::Systemd => Ok(systemd_unit_path(scope)?.display().to_string()),
+            ServiceBackend::Launchd => Ok(launchd_plist_path(scope)?.display().to_string()),
+            ServiceBackend::WindowsService => Ok(format!("Windows service '{}'", SERVICE_NAME)),
+        }
+    }
+    pub fn is_installed(&self, scope: ServiceScope) -> Result<bool> {
+        match self {
+            ServiceBackend::Systemd => Ok(systemd_unit_path(scope)?.exists()),
+            ServiceBackend::Launchd => Ok(launchd_plist_path(scope)?.exists()),
+            ServiceBackend::WindowsService => {
+                Ok(sc(&["query", SERVICE_NAME]).is_ok_and(|out| out.contains("STATE")))
+            }
+        }
+    }
+    /// Registers the service with the service manager; returns where it was installed
+    pub fn install(&self, spec: &ServiceSpec, force: bool) -> Result<String> {
+        let location = self.location(spec.scope)?;
+        if self.is_installed(spec.scope)? {
+            if !force {
+                return Err(
+                    SymorError::new(
+                            ErrorCode::InvalidConfiguration,
+                            format!("Service already installed at {}", location),
+                        )
+                        .with_suggestion("Use --force to overwrite it".to_string()),
+                );
+            }
+            if *self == ServiceBackend::WindowsService {
+                self.uninstall(spec.scope)?;
+            }
+        }
+        match self {
+            ServiceBackend::Systemd => {
+                let path = systemd_unit_path(spec.scope)?;
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(&path, systemd_unit(spec))?;
+                reload(spec.scope);
+            }
+            ServiceBackend::Launchd => {
+                let path = launchd_plist_path(spec.scope)?;
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::create_dir_all(spec.home_dir.join("logs"))?;
+                fs::write(&path, launchd_plist(spec))?;
+            }
+            ServiceBackend::WindowsService => {
+                let bin_path = windows_command_line(spec);
+                sc(
+                    &[
+                        "create",
+                        SERVICE_NAME,
+                        "binPath=",
+                        &bin_path,
+                        "start=",
+                        "demand",
+                        "DisplayName=",
+                        "Symor Daemon",
+                    ],
+                )?;
+                sc(
+                    &[
+                        "description",
+                        SERVICE_NAME,
+                        "Symor file mirroring and versioning daemon",
+                    ],
+                )?;
+                sc(
+                    &[
+                        "failure",
+                        SERVICE_NAME,
+                        "reset=",
+                        "86400",
+                        "actions=",
+                        "restart/5000/restart/5000/restart/5000",
+                    ],
+                )?;
+            }
+        }
+        Ok(location)
+    }
+    pub fn uninstall(&self, scope: ServiceScope) -> Result<bool> {
+        if !self.is_installed(scope)? {
+            return Ok(false);
+        }
+        let _ = self.disable(scope);
+        match self {
+            ServiceBackend::Systemd => {
+                fs::remove_file(systemd_unit_path(scope)?)?;
+                reload(scope);
+            }
+            ServiceBackend::Launchd => fs::remove_file(launchd_plist_path(scope)?)?,
+            ServiceBackend::WindowsService => {
+                sc(&["delete", SERVICE_NAME])?;
+            }
+        }
+        Ok(true)
+    }
+    /// Starts the daemon now and on every boot or login
+    pub fn enable(&self, scope: ServiceScope) -> Result<()> {
+        match self {
+            ServiceBackend::Systemd => {
+                systemctl(scope, &["enable", "--now", SERVICE_NAME])?;
+            }
+            ServiceBackend::Launchd => {
+                let path = launchd_plist_path(scope)?;
+                launchctl(&["load", "-w", &path.to_string_lossy()])?;
+            }
+            ServiceBackend::WindowsService => {
+                sc(&["config", SERVICE_NAME, "start=", "auto"])?;
+                sc(&["start", SERVICE_NAME])?;
+            }
+        }
+        Ok(())
+    }
+    pub fn disable(&self, scope: ServiceScope) -> Result<()> {
+        match self {
+            ServiceBackend::Systemd => {
+                systemctl(scope, &["disable", "--now", SERVICE_NAME])?;
+            }
+            ServiceBackend::Launchd => {
+                let path = launchd_plist_path(scope)?;
+                launchctl(&["unload", "-w", &path.to_string_lossy()])?;
+            }
+            ServiceBackend::WindowsService => {
+                let _ = sc(&["stop", SERVICE_NAME]);
+                sc(&["config", SERVICE_NAME, "start=", "demand"])?;
+            }
+        }
+        Ok(())
+    }
+    /// Human-readable service status from the service manager
+    pub fn status(&self, scope: ServiceScope) -> Result<String> {
+        match self {
+            ServiceBackend::Systemd => {
+                systemctl(scope, &["status", "--no-pager", SERVICE_NAME])
+            }
+            ServiceBackend::Launchd => launchctl(&["list", LAUNCHD_LABEL]),
+            ServiceBackend::WindowsService => sc(&["query", SERVICE_NAME]),
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_systemd_unit_contents() {
+        let spec = ServiceSpec {
+            scope: ServiceScope::System,
+            executable: PathBuf::from("/usr/local/bin/sym"),
+            home_dir: PathBuf::from("/srv/symor data"),
+            portable: true,
+            user: Some("backup".to_string()),
+        };
+        let unit = systemd_unit(&spec);
+        assert!(
+            unit.contains("ExecStart=/usr/local/bin/sym \"--portable=/srv/symor data\" daemon run")
+        );
+        assert!(unit.contains("User=backup"));
+        assert!(unit.contains("WantedBy=multi-user.target"));
+        let user_spec = ServiceSpec {
+            scope: ServiceScope::User,
+            portable: false,
+            ..spec
+        };
+        let unit = systemd_unit(&user_spec);
+        assert!(unit.contains("ExecStart=/usr/local/bin/sym daemon run\n"));
+        assert!(! unit.contains("User="));
+        assert!(unit.contains("WantedBy=default.target"));
+    }
+    #[test]
+    fn test_launchd_plist_contents() {
+        let spec = ServiceSpec {
+            scope: ServiceScope::User,
+            executable: PathBuf::from("/usr/local/bin/sym"),
+            home_dir: PathBuf::from("/Users/me/.symor"),
+            portable: false,
+            user: Some("me".to_string()),
+        };
+        let plist = launchd_plist(&spec);
+        assert!(
+            plist.contains("<string>/usr/local/bin/sym</string>\n    <string>daemon</string>\n    <string>run</string>")
+        );
+        assert!(plist.contains("<key>SuccessfulExit</key>\n    <false/>"));
+        assert!(plist.contains("<string>/Users/me/.symor/logs/daemon.err.log</string>"));
+        assert!(! plist.contains("UserName"));
+    }
+    #[test]
+    fn test_windows_command_line() {
+        let spec = ServiceSpec {
+            scope: ServiceScope::System,
+            executable: PathBuf::from("C:\\Program Files\\symor\\sym.exe"),
+            home_dir: PathBuf::from("C:\\Users\\me\\.symor"),
+            portable: true,
+            user: None,
+        };
+        assert_eq!(
+            windows_command_line(& spec),
+            "\"C:\\Program Files\\symor\\sym.exe\" --portable=C:\\Users\\me\\.symor daemon run --service"
+        );
+    }
+}
@@ -0,0 +1,149 @@
+use super::{service::SERVICE_NAME, Daemon};
+use crate::errors::{ErrorCode, Result, SymorError};
+use log::{error, Level, Log, Metadata, Record};
+use std::{
+    ffi::OsString, os::windows::ffi::OsStrExt,
+    sync::{atomic::{AtomicBool, Ordering}, Arc, Mutex},
+    time::Duration,
+};
+use windows_service::{
+    define_windows_service,
+    service::{
+        ServiceControl, ServiceControlAccept, ServiceExitCode, ServiceState,
+        ServiceStatus, ServiceType,
+    },
+    service_control_handler::{self, ServiceControlHandlerResult},
+    service_dispatcher,
+};
+use windows_sys::Win32::System::EventLog::{
+    DeregisterEventSource, RegisterEventSourceW, ReportEventW, EVENTLOG_ERROR_TYPE,
+    EVENTLOG_INFORMATION_TYPE, EVENTLOG_WARNING_TYPE,
+};
+/// Daemon handed from `run_service` to the service main function
+static PENDING_DAEMON: Mutex<Option<Daemon>> = Mutex::new(None);
+define_windows_service!(ffi_service_main, service_main);
+/// Hands control to the Service Control Manager and runs `daemon` until the service stops
+pub fn run_service(daemon: Daemon) -> Result<()> {
+    *PENDING_DAEMON.lock().unwrap_or_else(|e| e.into_inner()) = Some(daemon);
+    service_dispatcher::start(SERVICE_NAME, ffi_service_main)
+        .map_err(|e| {
+            SymorError::new(
+                    ErrorCode::InternalError,
+                    format!("Failed to start the service dispatcher: {}", e),
+                )
+                .with_suggestion(
+                    "'sym daemon run --service' is only meant to be started by Windows; use 'sym daemon run' in a console"
+                        .to_string(),
+                )
+        })
+}
+fn service_main(_arguments: Vec<OsString>) {
+    if let Err(e) = run_service_main() {
+        error!("Service failed: {}", e);
+    }
+}
+fn service_status(state: ServiceState, exit_code: u32) -> ServiceStatus {
+    ServiceStatus {
+        service_type: ServiceType::OWN_PROCESS,
+        current_state: state,
+        controls_accepted: if state == ServiceState::Running {
+            ServiceControlAccept::STOP | ServiceControlAccept::SHUTDOWN
+        } else {
+            ServiceControlAccept::empty()
+        },
+        exit_code: ServiceExitCode::Win32(exit_code),
+        checkpoint: 0,
+        wait_hint: Duration::default(),
+        process_id: None,
+    }
+}
+fn run_service_main() -> windows_service::Result<()> {
+    let stop = Arc::new(AtomicBool::new(false));
+    let handler_stop = stop.clone();
+    let status_handle = service_control_handler::register(
+        SERVICE_NAME,
+        move |control| match control {
+            ServiceControl::Stop | ServiceControl::Shutdown => {
+                handler_stop.store(true, Ordering::SeqCst);
+                ServiceControlHandlerResult::NoError
+            }
+            ServiceControl::Interrogate => ServiceControlHandlerResult::NoError,
+            _ => ServiceControlHandlerResult::NotImplemented,
+        },
+    )?;
+    status_handle.set_service_status(service_status(ServiceState::Running, 0))?;
+    let daemon = PENDING_DAEMON.lock().unwrap_or_else(|e| e.into_inner()).take();
+    let exit_code = match daemon.map(|daemon| daemon.run_until(&stop)) {
+        Some(Ok(())) => 0,
+        Some(Err(e)) => {
+            error!("Daemon stopped: {}", e);
+            1
+        }
+        None => 1,
+    };
+    status_handle.set_service_status(service_status(ServiceState::Stopped, exit_code))?;
+    Ok(())
+}
+fn wide(value: &str) -> Vec<u16> {
+    std::ffi::OsStr::new(value).encode_wide().chain(std::iter::once(0)).collect()
+}
+/// `log` backend writing to the Windows Application event log
+pub struct EventLogLogger {
+    handle: usize,
+    level: Level,
+}
+impl EventLogLogger {
+    pub fn new(level: Level) -> Result<Self> {
+        let source = wide(SERVICE_NAME);
+        let handle = unsafe { RegisterEventSourceW(std::ptr::null(), source.as_ptr()) };
+        if handle.is_null() {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        Ok(Self { handle: handle as usize, level })
+    }
+    /// Installs this logger as the global `log` backend
+    pub fn init(level: Level) -> Result<()> {
+        let logger = Self::new(level)?;
+        log::set_max_level(level.to_level_filter());
+        log::set_boxed_logger(Box::new(logger))
+            .map_err(|e| SymorError::new(ErrorCode::InternalError, e.to_string()))
+    }
+}
+impl Log for EventLogLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let event_type = match record.level() {
+            Level::Error => EVENTLOG_ERROR_TYPE,
+            Level::Warn => EVENTLOG_WARNING_TYPE,
+            _ => EVENTLOG_INFORMATION_TYPE,
+        };
+        let message = wide(&format!("{}: {}", record.target(), record.args()));
+        let strings = [message.as_ptr()];
+        unsafe {
+            ReportEventW(
+                self.handle as _,
+                event_type,
+                0,
+                0,
+                std::ptr::null_mut(),
+                1,
+                0,
+                strings.as_ptr(),
+                std::ptr::null(),
+            );
+        }
+    }
+    fn flush(&self) {}
+}
+impl Drop for EventLogLogger {
+    fn drop(&mut self) {
+        unsafe {
+            DeregisterEventSource(self.handle as _);
+        }
+    }
+}
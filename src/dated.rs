@@ -0,0 +1,372 @@
+use crate::{
+    errors::{ErrorCode, Result, SymorError},
+    tui::format::{civil_from_days, epoch_secs},
+};
+use regex::Regex;
+use std::{fs, path::{Component, Path, PathBuf}, time::{Duration, SystemTime}};
+/// How often a mirror with rollover checks whether its dated targets moved on
+pub const ROLLOVER_CHECK: Duration = Duration::from_secs(60);
+const TOKENS: &str = "%Y, %y, %m, %d, %F, %H, %M, %S, %j or %%";
+/// Whether `target` holds strftime tokens such as `%Y` to expand at sync time
+pub fn is_dated(target: &Path) -> bool {
+    target.to_string_lossy().contains('%')
+}
+/// Calendar fields of a time, `yday` counting from 1
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Fields {
+    year: i64,
+    month: i64,
+    day: i64,
+    hour: i64,
+    minute: i64,
+    second: i64,
+    yday: i64,
+}
+fn utc_fields(time: SystemTime) -> Fields {
+    let secs = epoch_secs(time);
+    let days = secs.div_euclid(86_400);
+    let (year, month, day) = civil_from_days(days);
+    let mut first = days - 365;
+    while civil_from_days(first).0 < year {
+        first += 1;
+    }
+    let of_day = secs.rem_euclid(86_400);
+    Fields {
+        year,
+        month,
+        day,
+        hour: of_day / 3600,
+        minute: of_day % 3600 / 60,
+        second: of_day % 60,
+        yday: days - first + 1,
+    }
+}
+#[cfg(unix)]
+fn local_fields(time: SystemTime) -> Fields {
+    let secs = epoch_secs(time) as libc::time_t;
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    if unsafe { libc::localtime_r(&secs, &mut tm) }.is_null() {
+        return utc_fields(time);
+    }
+    Fields {
+        year: i64::from(tm.tm_year) + 1900,
+        month: i64::from(tm.tm_mon) + 1,
+        day: i64::from(tm.tm_mday),
+        hour: i64::from(tm.tm_hour),
+        minute: i64::from(tm.tm_min),
+        second: i64::from(tm.tm_sec),
+        yday: i64::from(tm.tm_yday) + 1,
+    }
+}
+#[cfg(not(unix))]
+fn local_fields(time: SystemTime) -> Fields {
+    utc_fields(time)
+}
+fn render(target: &Path, fields: Fields) -> Result<PathBuf> {
+    let text = target.to_string_lossy();
+    let mut expanded = String::with_capacity(text.len() + 8);
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            expanded.push(c);
+            continue;
+        }
+        let Fields { year, month, day, hour, minute, second, yday } = fields;
+        let token = chars.next();
+        let value = match token {
+            Some('Y') => format!("{:04}", year),
+            Some('y') => format!("{:02}", year.rem_euclid(100)),
+            Some('m') => format!("{:02}", month),
+            Some('d') => format!("{:02}", day),
+            Some('F') => format!("{:04}-{:02}-{:02}", year, month, day),
+            Some('H') => format!("{:02}", hour),
+            Some('M') => format!("{:02}", minute),
+            Some('S') => format!("{:02}", second),
+            Some('j') => format!("{:03}", yday),
+            Some('%') => "%".to_string(),
+            _ => {
+                let token = token.map(|c| format!("%{}", c)).unwrap_or_else(|| "%".into());
+                return Err(
+                    SymorError::new(
+                        ErrorCode::InvalidPath,
+                        format!("Unknown date token {} in target {:?}", token, target),
+                    )
+                        .with_suggestion(format!("Use {}", TOKENS)),
+                );
+            }
+        };
+        expanded.push_str(&value);
+    }
+    Ok(PathBuf::from(expanded))
+}
+/// Fails when `targets` of `source` hold unknown tokens, or are dated while the mirror
+/// is two-way, which would sync past days' copies back
+pub(crate) fn check_targets(
+    source: &Path,
+    targets: &[PathBuf],
+    bidirectional: bool,
+) -> Result<()> {
+    let now = SystemTime::now();
+    for target in targets {
+        expand(target, now)?;
+    }
+    if bidirectional && targets.iter().any(|target| is_dated(target)) {
+        return Err(
+            SymorError::new(
+                ErrorCode::InvalidConfiguration,
+                format!("Dated targets of {:?} cannot sync back to it", source),
+            )
+                .with_suggestion(
+                    "Mirror into dated targets one way, without --bidirectional".to_string(),
+                ),
+        );
+    }
+    Ok(())
+}
+/// Where the copies of a dated target pile up: the directory holding them and what the
+/// name of each looks like
+struct Rotation {
+    parent: PathBuf,
+    /// Index of the dated component in the target
+    depth: usize,
+    pattern: Regex,
+    /// Field each capture group of `pattern` holds, as a token letter
+    fields: Vec<char>,
+}
+impl Rotation {
+    /// `None` for a target without tokens; fails when tokens sit in more than one
+    /// component, as in `/backup/%Y/%m`
+    fn of(template: &Path) -> Result<Option<Rotation>> {
+        render(template, utc_fields(SystemTime::now()))?;
+        let components: Vec<Component> = template.components().collect();
+        let mut dated = components
+            .iter()
+            .enumerate()
+            .filter(|(_, component)| component.as_os_str().to_string_lossy().contains('%'));
+        let Some((depth, name)) = dated.next() else {
+            return Ok(None);
+        };
+        if dated.next().is_some() {
+            return Err(
+                SymorError::new(
+                    ErrorCode::InvalidConfiguration,
+                    format!("Cannot rotate {:?}: the date spans several directories", template),
+                )
+                    .with_suggestion(
+                        "Put the whole date in one directory name, e.g. /backup/%F/etc"
+                            .to_string(),
+                    ),
+            );
+        }
+        let mut pattern = String::from("^");
+        let mut fields = Vec::new();
+        let name = name.as_os_str().to_string_lossy();
+        let mut chars = name.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                pattern.push_str(&regex::escape(&c.to_string()));
+                continue;
+            }
+            let token = chars.next();
+            let (group, letters) = match token {
+                Some('Y') => (r"(\d{4})", "Y"),
+                Some('F') => (r"(\d{4})-(\d{2})-(\d{2})", "Ymd"),
+                Some('j') => (r"(\d{3})", "j"),
+                Some('%') => ("%", ""),
+                Some(letter @ ('y' | 'm' | 'd' | 'H' | 'M' | 'S')) => {
+                    fields.push(letter);
+                    pattern.push_str(r"(\d{2})");
+                    continue;
+                }
+                _ => unreachable!("rendered above"),
+            };
+            fields.extend(letters.chars());
+            pattern.push_str(group);
+        }
+        pattern.push('$');
+        Ok(Some(Rotation {
+            parent: components[..depth].iter().collect(),
+            depth,
+            pattern: Regex::new(&pattern).expect("escaped rotation pattern"),
+            fields,
+        }))
+    }
+    /// Sort key of a copy's name: its year, month, day, day of the year, hour, minute
+    /// and second, where the name has them
+    fn key(&self, name: &str) -> Option<[i64; 7]> {
+        let captures = self.pattern.captures(name)?;
+        let mut key = [0; 7];
+        for (field, capture) in self.fields.iter().zip(captures.iter().skip(1)) {
+            let value: i64 = capture?.as_str().parse().ok()?;
+            let (slot, value) = match field {
+                'Y' => (0, value),
+                'y' => (0, 2000 + value),
+                'm' => (1, value),
+                'd' => (2, value),
+                'j' => (3, value),
+                'H' => (4, value),
+                'M' => (5, value),
+                _ => (6, value),
+            };
+            key[slot] = value;
+        }
+        Some(key)
+    }
+    /// Other copies beside the one `current`, a target expanded from the template, falls
+    /// in, newest first
+    fn earlier_copies(&self, current: &Path) -> Vec<PathBuf> {
+        let current: PathBuf = current.components().take(self.depth + 1).collect();
+        let Ok(entries) = fs::read_dir(&self.parent) else {
+            return Vec::new();
+        };
+        let mut copies: Vec<([i64; 7], PathBuf)> = entries
+            .flatten()
+            .filter(|entry| entry.path() != current)
+            .filter_map(|entry| Some((self.key(entry.file_name().to_str()?)?, entry.path())))
+            .collect();
+        copies.sort_by_key(|(key, _)| std::cmp::Reverse(*key));
+        copies.into_iter().map(|(_, path)| path).collect()
+    }
+}
+/// Fails unless some of `targets` is dated and every dated one can be rotated
+pub(crate) fn check_rotations(source: &Path, targets: &[PathBuf]) -> Result<()> {
+    let mut dated = 0;
+    for target in targets {
+        dated += usize::from(Rotation::of(target)?.is_some());
+    }
+    if dated == 0 {
+        return Err(
+            SymorError::new(
+                ErrorCode::InvalidConfiguration,
+                format!("No dated target of {:?} to rotate", source),
+            )
+                .with_suggestion("Add a date to a target, e.g. /backup/%F/etc".to_string()),
+        );
+    }
+    Ok(())
+}
+/// Earlier copies of the dated `template` beyond the `keep` newest, counting `current`
+/// (its expansion now) as one of them even before it exists
+pub(crate) fn stale_rotations(
+    template: &Path,
+    current: &Path,
+    keep: usize,
+) -> Result<Vec<PathBuf>> {
+    let Some(rotation) = Rotation::of(template)? else {
+        return Ok(Vec::new());
+    };
+    Ok(rotation.earlier_copies(current).into_iter().skip(keep.saturating_sub(1)).collect())
+}
+/// What `current`, the dated `template` expanded now, was in the newest earlier copy
+/// still on disk
+pub(crate) fn previous_rotation(template: &Path, current: &Path) -> Result<Option<PathBuf>> {
+    let Some(rotation) = Rotation::of(template)? else {
+        return Ok(None);
+    };
+    let rest: PathBuf = current.components().skip(rotation.depth + 1).collect();
+    let previous = rotation.earlier_copies(current).into_iter().next();
+    Ok(previous.map(|dir| if rest.as_os_str().is_empty() { dir } else { dir.join(rest) }))
+}
+/// `target` with its strftime tokens replaced by `time` in local time (UTC where the
+/// platform offers no time zone); targets without tokens come back unchanged
+pub fn expand(target: &Path, time: SystemTime) -> Result<PathBuf> {
+    if !is_dated(target) {
+        return Ok(target.to_path_buf());
+    }
+    render(target, local_fields(time))
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::UNIX_EPOCH;
+    #[test]
+    fn test_dated_targets() {
+        let time = UNIX_EPOCH + Duration::from_secs(1_709_642_096);
+        let fields = utc_fields(time);
+        assert_eq!(fields, Fields {
+            year: 2024, month: 3, day: 5, hour: 12, minute: 34, second: 56, yday: 65 });
+        assert_eq!(utc_fields(UNIX_EPOCH).yday, 1);
+        let target = Path::new("/backup/%Y/%F_%H%M%S-%j-%y/100%%/etc");
+        assert_eq!(
+            render(target, fields).unwrap(),
+            PathBuf::from("/backup/2024/2024-03-05_123456-065-24/100%/etc")
+        );
+        let plain = Path::new("/backup/etc");
+        assert!(!is_dated(plain));
+        assert_eq!(expand(plain, time).unwrap(), plain);
+        let today = expand(Path::new("%Y-%m-%d"), time).unwrap();
+        assert_eq!(today, expand(Path::new("%F"), time).unwrap());
+        assert_eq!(today.to_string_lossy().len(), 10);
+        for bad in ["/backup/%Q", "/backup/50%"] {
+            let error = expand(Path::new(bad), time).unwrap_err();
+            assert_eq!(error.code, ErrorCode::InvalidPath);
+        }
+        let temp_dir = tempfile::tempdir().unwrap();
+        let source = temp_dir.path().join("src");
+        std::fs::create_dir_all(&source).unwrap();
+        let template = temp_dir.path().join("%Y/etc");
+        let builder = crate::Mirror::builder(&source).target(&template);
+        let mut mirror = builder.clone().rollover(true).build().unwrap();
+        let year = expand(&template, SystemTime::now()).unwrap();
+        assert_eq!(mirror.targets, std::slice::from_ref(&year));
+        assert!(!mirror.roll_over().unwrap());
+        mirror.targets = vec![temp_dir.path().join("1999/etc")];
+        assert!(mirror.roll_over().unwrap());
+        assert_eq!(mirror.targets, [year]);
+        let error = builder.bidirectional(true).build().err().unwrap();
+        assert_eq!(error.code, ErrorCode::InvalidConfiguration);
+        let rotations = temp_dir.path().join("rot");
+        let names = ["2024-02-28", "2024-03-01", "2024-03-02", "2024-03-05", "2024-3-9", "notes"];
+        for name in names {
+            std::fs::create_dir_all(rotations.join(name)).unwrap();
+        }
+        let template = rotations.join("%F/etc");
+        let stale = stale_rotations(&template, &rotations.join("2024-03-05/etc"), 3).unwrap();
+        assert_eq!(stale, [rotations.join("2024-02-28")]);
+        let rotation = Rotation::of(Path::new("/b/%d.%m.%y")).unwrap().unwrap();
+        assert!(rotation.key("05.03.24") > rotation.key("28.02.24"));
+        assert_eq!(rotation.key("28.02"), None);
+        for bad in [vec![PathBuf::from("/b/%Y/%m")], vec![PathBuf::from("/b/etc")]] {
+            let error = check_rotations(&source, &bad).unwrap_err();
+            assert_eq!(error.code, ErrorCode::InvalidConfiguration);
+        }
+        let mirror = crate::Mirror::builder(&source).target(&template).keep_rotations(2);
+        mirror.build().unwrap().rotate();
+        let mut left: Vec<_> = std::fs::read_dir(&rotations)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        left.sort();
+        assert_eq!(left, ["2024-03-05", "2024-3-9", "notes"]);
+        let tree = temp_dir.path().join("tree");
+        let old = temp_dir.path().join("snap/2024-03-05");
+        for dir in [&tree, &old] {
+            std::fs::create_dir_all(dir).unwrap();
+            std::fs::write(dir.join("same.txt"), "same").unwrap();
+        }
+        std::fs::write(tree.join("edited.txt"), "new").unwrap();
+        std::fs::write(old.join("edited.txt"), "old").unwrap();
+        let mirror = crate::Mirror::builder(&tree)
+            .target(temp_dir.path().join("snap/%F"))
+            .link_rotations(true)
+            .build()
+            .unwrap();
+        assert_eq!(mirror.previous_targets, [Some(old.clone())]);
+        let today = mirror.targets[0].clone();
+        for _ in 0..2 {
+            mirror.sync_once().unwrap();
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::MetadataExt;
+                let inode = |path: PathBuf| std::fs::metadata(path).unwrap().ino();
+                assert_eq!(inode(today.join("same.txt")), inode(old.join("same.txt")));
+                assert_ne!(inode(today.join("edited.txt")), inode(old.join("edited.txt")));
+            }
+        }
+        std::fs::write(tree.join("same.txt"), "changed").unwrap();
+        mirror.sync_once().unwrap();
+        assert_eq!(std::fs::read_to_string(today.join("same.txt")).unwrap(), "changed");
+        assert_eq!(std::fs::read_to_string(old.join("same.txt")).unwrap(), "same");
+        assert_eq!(std::fs::read_to_string(today.join("edited.txt")).unwrap(), "new");
+    }
+}
\ No newline at end of file
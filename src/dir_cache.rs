@@ -0,0 +1,150 @@
+use crate::{errors::Result, PathFilter};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs, path::{Path, PathBuf}, time::SystemTime};
+/// Names in one directory as of its modification time, which changes whenever an
+/// entry is added, removed or renamed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedDir {
+    modified: SystemTime,
+    files: Vec<PathBuf>,
+    dirs: Vec<PathBuf>,
+}
+/// Directory listings kept between runs of `sym list`, so walking a watched tree
+/// only reads the directories that changed since
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct DirCache {
+    dirs: BTreeMap<PathBuf, CachedDir>,
+    #[serde(skip)]
+    changed: bool,
+}
+fn cache_file(home_dir: &Path) -> PathBuf {
+    home_dir.join("cache").join("dirs.json")
+}
+impl DirCache {
+    pub fn load(home_dir: &Path) -> Self {
+        fs::read_to_string(cache_file(home_dir))
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+    /// Writes the cache back when a walk changed it
+    pub fn save(&self, home_dir: &Path) -> Result<()> {
+        if !self.changed {
+            return Ok(());
+        }
+        let path = cache_file(home_dir);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+    /// Entries of `dir`, read again only when its modification time moved. Directories
+    /// whose path or entry names are not valid UTF-8 are listed but never cached, as the
+    /// cache file cannot hold such names
+    fn entries(&mut self, dir: &Path) -> Result<CachedDir> {
+        let modified = fs::metadata(dir)?.modified()?;
+        if let Some(cached) = self.dirs.get(dir).filter(|cached| cached.modified == modified) {
+            return Ok(cached.clone());
+        }
+        let mut cached = CachedDir {
+            modified,
+            files: Vec::new(),
+            dirs: Vec::new(),
+        };
+        let mut cacheable = dir.to_str().is_some();
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let (path, name) = (entry.path(), PathBuf::from(entry.file_name()));
+            cacheable &= name.to_str().is_some();
+            if path.is_file() {
+                cached.files.push(name);
+            } else if path.is_dir() {
+                cached.dirs.push(name);
+            }
+        }
+        cached.files.sort();
+        cached.dirs.sort();
+        if !cacheable {
+            self.changed |= self.dirs.remove(dir).is_some();
+            return Ok(cached);
+        }
+        if let Some(old) = self.dirs.get(dir) {
+            let gone: Vec<PathBuf> = old
+                .dirs
+                .iter()
+                .filter(|name| !cached.dirs.contains(name))
+                .map(|name| dir.join(name))
+                .collect();
+            self.dirs.retain(|path, _| !gone.iter().any(|gone| path.starts_with(gone)));
+        }
+        self.dirs.insert(dir.to_path_buf(), cached.clone());
+        self.changed = true;
+        Ok(cached)
+    }
+    /// Files below `root` that `filter` lets through, in path order
+    pub fn files(&mut self, root: &Path, filter: &PathFilter) -> Result<Vec<PathBuf>> {
+        let mut files = Vec::new();
+        let mut pending = vec![root.to_path_buf()];
+        while let Some(dir) = pending.pop() {
+            let Ok(entries) = self.entries(&dir) else {
+                continue;
+            };
+            for name in entries.files {
+                let path = dir.join(name);
+                if filter.includes(path.strip_prefix(root).unwrap_or(&path)) {
+                    files.push(path);
+                }
+            }
+            for name in entries.dirs.into_iter().rev() {
+                let path = dir.join(name);
+                if filter.walks(path.strip_prefix(root).unwrap_or(&path)) {
+                    pending.push(path);
+                }
+            }
+        }
+        files.sort();
+        Ok(files)
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+    #[test]
+    fn test_dir_cache() {
+        let temp_dir = tempdir().unwrap();
+        let (home, root) = (temp_dir.path().join("home"), temp_dir.path().join("root"));
+        fs::create_dir_all(root.join("sub/deep")).unwrap();
+        fs::write(root.join("a.txt"), "a").unwrap();
+        fs::write(root.join("sub/b.log"), "b").unwrap();
+        fs::write(root.join("sub/deep/c.txt"), "c").unwrap();
+        let mut cache = DirCache::load(&home);
+        let all = cache.files(&root, &PathFilter::default()).unwrap();
+        assert_eq!(all, vec![root.join("a.txt"), root.join("sub/b.log"), root.join("sub/deep/c.txt")]);
+        let only_txt = PathFilter::new(&["*.txt".to_string()]).unwrap();
+        assert_eq!(cache.files(&root, &only_txt).unwrap().len(), 2);
+        cache.save(&home).unwrap();
+        let mut cache = DirCache::load(&home);
+        assert_eq!(cache.dirs.len(), 3);
+        assert_eq!(cache.files(&root, &PathFilter::default()).unwrap(), all);
+        assert!(!cache.changed);
+        fs::remove_dir_all(root.join("sub")).unwrap();
+        fs::write(root.join("d.txt"), "d").unwrap();
+        let later = SystemTime::now() + std::time::Duration::from_secs(5);
+        fs::File::open(&root).unwrap().set_modified(later).unwrap();
+        assert_eq!(cache.files(&root, &PathFilter::default()).unwrap(), vec![root.join("a.txt"), root.join("d.txt")]);
+        assert_eq!(cache.dirs.len(), 1);
+        #[cfg(unix)]
+        {
+            use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+            let odd = root.join(OsStr::from_bytes(b"caf\xe9.txt"));
+            fs::write(&odd, "e").unwrap();
+            let later = later + std::time::Duration::from_secs(5);
+            fs::File::open(&root).unwrap().set_modified(later).unwrap();
+            assert!(cache.files(&root, &PathFilter::default()).unwrap().contains(&odd));
+            assert!(cache.dirs.is_empty());
+            cache.save(&home).unwrap();
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,373 @@
+use crate::{config::validation::ConfigValidator, daemon, SymorManager};
+use notify::{RecursiveMode, Watcher};
+use std::{collections::HashSet, fs, path::Path};
+/// How serious a finding is, from best to worst
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Ok,
+    Warning,
+    Error,
+}
+/// Outcome of one `sym doctor` check
+#[derive(Debug, Clone)]
+pub struct Finding {
+    pub check: &'static str,
+    pub severity: Severity,
+    pub message: String,
+    pub suggestion: Option<String>,
+}
+impl Finding {
+    fn ok(check: &'static str, message: String) -> Self {
+        Self {
+            check,
+            severity: Severity::Ok,
+            message,
+            suggestion: None,
+        }
+    }
+    fn warning(check: &'static str, message: String, suggestion: &str) -> Self {
+        Self {
+            check,
+            severity: Severity::Warning,
+            message,
+            suggestion: Some(suggestion.to_string()),
+        }
+    }
+    fn error(check: &'static str, message: String, suggestion: &str) -> Self {
+        Self {
+            check,
+            severity: Severity::Error,
+            message,
+            suggestion: Some(suggestion.to_string()),
+        }
+    }
+}
+#[derive(Debug, Clone, Default)]
+pub struct Report {
+    pub findings: Vec<Finding>,
+}
+impl Report {
+    /// The worst severity found
+    pub fn verdict(&self) -> Severity {
+        self.findings.iter().map(|f| f.severity).max().unwrap_or(Severity::Ok)
+    }
+    pub fn count(&self, severity: Severity) -> usize {
+        self.findings.iter().filter(|f| f.severity == severity).count()
+    }
+}
+/// Free space below 100 MiB is an error, below 1 GiB a warning
+const DISK_ERROR_BYTES: u64 = 100 * 1024 * 1024;
+const DISK_WARNING_BYTES: u64 = 1024 * 1024 * 1024;
+/// Runs every check against `manager`. `load_errors` are failures from loading its
+/// state, keyed by the check they belong to (`config`, `state`)
+pub fn diagnose(manager: &SymorManager, load_errors: &[(&'static str, String)]) -> Report {
+    let home_dir = &manager.config().home_dir;
+    let load_error = |check: &str| {
+        load_errors.iter().find(|(c, _)| *c == check).map(|(_, e)| e.clone())
+    };
+    let mut findings = vec![check_home(home_dir)];
+    findings
+        .push(
+            match load_error("config") {
+                Some(e) => {
+                    Finding::error(
+                        "Config",
+                        format!("cannot be loaded: {}", e),
+                        "Fix the file or reset it with 'sym settings init'",
+                    )
+                }
+                None => check_config(manager),
+            },
+        );
+    findings
+        .push(
+            match load_error("state") {
+                Some(e) => {
+                    Finding::error(
+                        "State files",
+                        format!("items/ or mirrors.json cannot be read: {}", e),
+                        "Restore the file from a backup or move it aside to start afresh",
+                    )
+                }
+                None => check_mirrors(manager),
+            },
+        );
+    findings.push(check_versions(manager));
+    findings.extend(check_inotify(manager));
+    findings.push(check_disk(home_dir));
+    findings.push(check_daemon(manager));
+    findings.push(check_watcher(home_dir));
+    Report { findings }
+}
+fn check_home(home_dir: &Path) -> Finding {
+    if !home_dir.is_dir() {
+        return Finding::error(
+            "Home directory",
+            format!("{} does not exist", home_dir.display()),
+            "Create it or point 'sym settings home' at an existing directory",
+        );
+    }
+    let probe = home_dir.join(".doctor-probe");
+    match fs::write(&probe, b"ok") {
+        Ok(()) => {
+            let _ = fs::remove_file(&probe);
+            Finding::ok("Home directory", format!("{} is writable", home_dir.display()))
+        }
+        Err(e) => {
+            Finding::error(
+                "Home directory",
+                format!("{} is not writable: {}", home_dir.display(), e),
+                "Fix its ownership and permissions (it should be owned by you, mode 700)",
+            )
+        }
+    }
+}
+fn check_config(manager: &SymorManager) -> Finding {
+    let result = ConfigValidator::new().validate_config(manager.config());
+    let home_config = manager.config().home_dir.join("config.json");
+    let source = match manager.config_source() {
+        Some(path) => path.display().to_string(),
+        None if home_config.exists() => home_config.display().to_string(),
+        None => "the built-in defaults".to_string(),
+    };
+    if let Some(error) = result.errors.first() {
+        return Finding::error(
+            "Config",
+            format!(
+                "{} error(s) in {}; first: {}: {}", result.errors.len(), source, error
+                .field, error.message
+            ),
+            error.suggestion.as_deref().unwrap_or("Run 'sym settings show' to review it"),
+        );
+    }
+    if let Some(warning) = result.warnings.first() {
+        return Finding::warning(
+            "Config",
+            format!(
+                "{} warning(s) in {}; first: {}: {}", result.warnings.len(), source,
+                warning.field, warning.message
+            ),
+            warning.suggestion.as_deref().unwrap_or("Run 'sym settings show' to review it"),
+        );
+    }
+    Finding::ok("Config", format!("{} is valid", source))
+}
+fn check_mirrors(manager: &SymorManager) -> Finding {
+    let missing: Vec<String> = manager
+        .mirrors()
+        .iter()
+        .filter(|spec| !spec.source.exists())
+        .map(|spec| spec.source.display().to_string())
+        .collect();
+    if let Some(first) = missing.first() {
+        return Finding::warning(
+            "State files",
+            format!("{} mirror source(s) no longer exist, e.g. {}", missing.len(), first),
+            "Remove stale mirrors with 'sym unmirror <source>'",
+        );
+    }
+    Finding::ok(
+        "State files",
+        format!(
+            "{} watched item(s) and {} mirror(s) loaded", manager.watched_index().len(),
+            manager.mirrors().len()
+        ),
+    )
+}
+fn check_versions(manager: &SymorManager) -> Finding {
+    let storage = manager.version_storage();
+    let mut referenced = HashSet::new();
+    let mut missing = 0;
+    for item in manager.watched_index() {
+        for version in &item.versions {
+            referenced.insert(version.id.clone());
+            let legacy = version
+                .backup_path
+                .as_ref()
+                .is_some_and(|path| path.is_absolute() && path.exists());
+            if !storage.contains(&version.id) && !legacy {
+                missing += 1;
+            }
+        }
+    }
+    let orphaned = storage
+        .version_ids()
+        .map(|ids| ids.iter().filter(|id| !referenced.contains(*id)).count())
+        .unwrap_or(0);
+    if missing > 0 {
+        return Finding::error(
+            "Version store",
+            format!(
+                "{} of {} recorded version(s) are missing from storage", missing, referenced
+                .len()
+            ),
+            "Restore the versions directory from a backup",
+        );
+    }
+    if orphaned > 0 {
+        return Finding::warning(
+            "Version store",
+            format!("{} stored version(s) are not referenced by any watched item", orphaned),
+            "They belong to unwatched files; delete them from the versions directory",
+        );
+    }
+    Finding::ok("Version store", format!("{} version(s), all present", referenced.len()))
+}
+/// Directories below `path` (1 for a file), each needing its own inotify watch
+fn watch_count(path: &Path, recursive: bool) -> u64 {
+    if !recursive || !path.is_dir() || crate::linking::is_reparse_point(path) {
+        return 1;
+    }
+    1 + fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter(|entry| entry.file_type().is_ok_and(|t| t.is_dir()))
+                .map(|entry| watch_count(&entry.path(), true))
+                .sum()
+        })
+        .unwrap_or(0)
+}
+#[cfg(target_os = "linux")]
+fn check_inotify(manager: &SymorManager) -> Option<Finding> {
+    let limit: u64 = fs::read_to_string("/proc/sys/fs/inotify/max_user_watches")
+        .ok()?
+        .trim()
+        .parse()
+        .ok()?;
+    let items: u64 = manager
+        .watched_index()
+        .map(|item| watch_count(&item.path, item.recursive))
+        .sum();
+    let mirrors: u64 = manager
+        .mirrors()
+        .iter()
+        .map(|spec| {
+            let targets = if spec.bidirectional { spec.targets.len() as u64 } else { 0 };
+            watch_count(&spec.source, true) + targets
+        })
+        .sum();
+    let needed = items + mirrors;
+    let message = format!("about {} of {} watches needed", needed, limit);
+    let suggestion = "Raise it with 'sudo sysctl fs.inotify.max_user_watches=524288' \
+                      (add it to /etc/sysctl.conf to keep it)";
+    Some(
+        if needed > limit {
+            Finding::error("inotify limit", message, suggestion)
+        } else if needed * 5 > limit * 4 {
+            Finding::warning("inotify limit", message, suggestion)
+        } else {
+            Finding::ok("inotify limit", message)
+        },
+    )
+}
+#[cfg(not(target_os = "linux"))]
+fn check_inotify(_manager: &SymorManager) -> Option<Finding> {
+    None
+}
+#[cfg(unix)]
+fn available_space(path: &Path) -> Option<u64> {
+    use std::os::unix::ffi::OsStrExt;
+    let c_path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    let bytes = stat.f_bavail as u128 * stat.f_frsize as u128;
+    Some(bytes.min(u64::MAX as u128) as u64)
+}
+#[cfg(not(unix))]
+fn available_space(_path: &Path) -> Option<u64> {
+    None
+}
+fn check_disk(home_dir: &Path) -> Finding {
+    let Some(free) = available_space(home_dir) else {
+        return Finding::ok("Disk space", "not checked on this platform".to_string());
+    };
+    let message = format!("{} MiB free for {}", free / (1024 * 1024), home_dir.display());
+    let suggestion = "Free some space, lower versioning.max_versions or run 'sym clean'";
+    if free < DISK_ERROR_BYTES {
+        Finding::error("Disk space", message, suggestion)
+    } else if free < DISK_WARNING_BYTES {
+        Finding::warning("Disk space", message, suggestion)
+    } else {
+        Finding::ok("Disk space", message)
+    }
+}
+fn check_daemon(manager: &SymorManager) -> Finding {
+    let home_dir = &manager.config().home_dir;
+    if let Some(pid) = daemon::running_pid(home_dir) {
+        return Finding::ok("Daemon", format!("running (pid {})", pid));
+    }
+    if daemon::pid_file(home_dir).exists() {
+        return Finding::warning(
+            "Daemon",
+            "not running, but a stale pid file was left behind".to_string(),
+            "Start it with 'sym daemon run' or 'sym daemon enable'; the stale file is replaced",
+        );
+    }
+    if manager.mirrors().is_empty() {
+        return Finding::ok("Daemon", "not running (no mirrors need it)".to_string());
+    }
+    Finding::warning(
+        "Daemon",
+        format!("not running, so {} mirror(s) are not being synced", manager.mirrors().len()),
+        "Start it with 'sym daemon run' or install it with 'sym daemon install-service'",
+    )
+}
+fn check_watcher(home_dir: &Path) -> Finding {
+    let kind = notify::RecommendedWatcher::kind();
+    let result = notify::RecommendedWatcher::new(
+            |_: notify::Result<notify::Event>| {},
+            notify::Config::default(),
+        )
+        .and_then(|mut watcher| watcher.watch(home_dir, RecursiveMode::NonRecursive));
+    match result {
+        Ok(()) => Finding::ok("Watcher backend", format!("{:?} is working", kind)),
+        Err(e) => {
+            Finding::error(
+                "Watcher backend",
+                format!("{:?} cannot watch {}: {}", kind, home_dir.display(), e),
+                "Check the inotify limits and that the home directory is on a local filesystem",
+            )
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+    #[test]
+    fn test_diagnose_reports_problems() {
+        let temp_dir = tempdir().unwrap();
+        let mut manager = SymorManager::new().unwrap();
+        manager.config.home_dir = temp_dir.path().to_path_buf();
+        manager.mirrors.push(crate::MirrorSpec {
+            source: temp_dir.path().join("gone"),
+            targets: vec![temp_dir.path().join("copy")],
+            bidirectional: false,
+            only: Vec::new(),
+            skip_hidden: false,
+            paused: false,
+            storm_threshold: None,
+            rollover: false,
+            keep_rotations: None,
+            link_rotations: false,
+            symlinks: Default::default(),
+        });
+        let report = diagnose(&manager, &[]);
+        let finding = |check: &str| {
+            report.findings.iter().find(|f| f.check == check).unwrap().severity
+        };
+        assert_eq!(finding("Home directory"), Severity::Ok);
+        assert_eq!(finding("State files"), Severity::Warning);
+        assert_eq!(finding("Daemon"), Severity::Warning);
+        assert_eq!(finding("Watcher backend"), Severity::Ok);
+        assert!(report.verdict() >= Severity::Warning);
+        let report = diagnose(&manager, &[("config", "bad json".to_string())]);
+        assert_eq!(report.verdict(), Severity::Error);
+        assert_eq!(report.count(Severity::Error), 1);
+        manager.config.home_dir = temp_dir.path().join("missing");
+        assert_eq!(check_home(& manager.config.home_dir).severity, Severity::Error);
+    }
+}
\ No newline at end of file
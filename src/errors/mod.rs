@@ -1,4 +1,4 @@
 pub mod types;
 pub mod recovery;
-pub use types::{SymorError, ErrorCode, ErrorContext};
-pub use recovery::{ErrorRecovery, RecoveryStrategy, RecoveryResult};
\ No newline at end of file
+pub use types::{SymorError, ErrorCode, ErrorContext, Context, Result};
+pub use recovery::{ErrorRecovery, RecoveryConfig, RecoveryStrategy, RecoveryResult};
\ No newline at end of file
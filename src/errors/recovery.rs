@@ -1,18 +1,43 @@
-use anyhow::Result;
-use std::time::Duration;
-#[derive(Debug, Clone)]
+use super::{ErrorCode, Result, SymorError};
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, time::Duration};
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "strategy", rename_all = "snake_case")]
 pub enum RecoveryStrategy {
-    Retry { max_attempts: u32, delay: Duration },
+    Retry {
+        max_attempts: u32,
+        #[serde(rename = "delay_ms", with = "duration_ms")]
+        delay: Duration,
+    },
     Fallback { alternative_action: String },
     Skip,
     Fail,
 }
-pub struct ErrorRecovery {
-    strategies: std::collections::HashMap<String, RecoveryStrategy>,
+mod duration_ms {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+    pub fn serialize<S: Serializer>(
+        delay: &Duration,
+        s: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        s.serialize_u64(delay.as_millis() as u64)
+    }
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        d: D,
+    ) -> std::result::Result<Duration, D::Error> {
+        Ok(Duration::from_millis(u64::deserialize(d)?))
+    }
 }
-impl ErrorRecovery {
-    pub fn new() -> Self {
-        let mut strategies = std::collections::HashMap::new();
+/// Per-`ErrorCode` recovery strategies, stored in the `recovery` section of the config
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct RecoveryConfig {
+    pub strategies: HashMap<String, RecoveryStrategy>,
+}
+impl Default for RecoveryConfig {
+    fn default() -> Self {
+        let mut strategies = HashMap::new();
         strategies
             .insert(
                 "FileNotFound".to_string(),
@@ -38,12 +63,87 @@ impl ErrorRecovery {
             );
         Self { strategies }
     }
+}
+fn recovery_error(message: &str) -> SymorError {
+    SymorError::new(ErrorCode::InternalError, message.to_string())
+}
+/// Runs `write` and, when it fails because the disk is full, runs `reclaim` to free space
+/// and, if it freed any, `write` once more. Both get `state`, so they can share a borrow
+pub(crate) fn retry_when_full<S, T>(
+    state: &mut S,
+    mut write: impl FnMut(&mut S) -> Result<T>,
+    reclaim: impl FnOnce(&mut S) -> Result<bool>,
+) -> Result<T> {
+    match write(state) {
+        Err(e) if e.code == ErrorCode::DiskFull => {
+            warn!("{}; freeing space and trying once more", e.message);
+            if !reclaim(state)? {
+                return Err(e);
+            }
+            write(state)
+        }
+        result => result,
+    }
+}
+/// What to do about each kind of error, as the `recovery` section of the config says
+#[derive(Debug, Clone)]
+pub struct ErrorRecovery {
+    strategies: HashMap<String, RecoveryStrategy>,
+}
+impl Default for ErrorRecovery {
+    fn default() -> Self {
+        Self::new(&RecoveryConfig::default())
+    }
+}
+impl ErrorRecovery {
+    /// The strategies of `config`; error codes it has no entry for fail right away
+    pub fn new(config: &RecoveryConfig) -> Self {
+        Self {
+            strategies: config.strategies.clone(),
+        }
+    }
     pub fn get_strategy(&self, error_code: &str) -> RecoveryStrategy {
         self.strategies.get(error_code).cloned().unwrap_or(RecoveryStrategy::Fail)
     }
+    pub fn strategy_for(&self, error_code: &super::ErrorCode) -> RecoveryStrategy {
+        self.get_strategy(&format!("{:?}", error_code))
+    }
     pub fn set_strategy(&mut self, error_code: String, strategy: RecoveryStrategy) {
         self.strategies.insert(error_code, strategy);
     }
+    /// Runs `operation` and, when it fails, follows the strategy for its error's code:
+    /// a retry runs it again after the delay until the attempts are used up, a fallback
+    /// suggests its alternative action, and anything else returns the error as it is
+    pub fn run<T>(&self, mut operation: impl FnMut() -> Result<T>) -> Result<T> {
+        let mut attempt = 1;
+        loop {
+            let error = match operation() {
+                Ok(value) => return Ok(value),
+                Err(error) => error,
+            };
+            match self.strategy_for(&error.code) {
+                RecoveryStrategy::Retry { max_attempts, delay } if attempt < max_attempts => {
+                    debug!(
+                        "{} (attempt {} of {}), retrying in {:?}", error.message, attempt,
+                        max_attempts, delay
+                    );
+                    std::thread::sleep(delay);
+                    attempt += 1;
+                }
+                RecoveryStrategy::Fallback { alternative_action }
+                    if error.recovery_suggestion.is_none() =>
+                {
+                    return Err(error.with_suggestion(alternative_action));
+                }
+                _ => return Err(error),
+            }
+        }
+    }
+    /// Whether errors like `error` are to be skipped, leaving the file they happened on
+    /// out so the rest of a sync goes on
+    pub fn skips(&self, error: &SymorError) -> bool {
+        self.strategy_for(&error.code) == RecoveryStrategy::Skip
+    }
     pub async fn execute_recovery<T, F>(
         &self,
         error_code: &str,
@@ -59,13 +159,11 @@ impl ErrorRecovery {
                 self.execute_retry(operation, max_attempts, delay).await
             }
             RecoveryStrategy::Fallback { alternative_action } => {
-                Err(anyhow::anyhow!("Fallback required: {}", alternative_action))
-            }
-            RecoveryStrategy::Skip => {
-                Err(anyhow::anyhow!("Operation skipped due to error"))
+                Err(recovery_error(&format!("Fallback required: {}", alternative_action)))
             }
+            RecoveryStrategy::Skip => Err(recovery_error("Operation skipped due to error")),
             RecoveryStrategy::Fail => {
-                Err(anyhow::anyhow!("Operation failed without recovery option"))
+                Err(recovery_error("Operation failed without recovery option"))
             }
         }
     }
@@ -86,12 +184,15 @@ impl ErrorRecovery {
                 Err(e) => {
                     last_error = Some(e);
                     if attempt < max_attempts {
+                        #[cfg(feature = "tokio")]
                         tokio::time::sleep(delay).await;
+                        #[cfg(not(feature = "tokio"))]
+                        std::thread::sleep(delay);
                     }
                 }
             }
         }
-        Err(last_error.unwrap_or_else(|| anyhow::anyhow!("All retry attempts failed")))
+        Err(last_error.unwrap_or_else(|| recovery_error("All retry attempts failed")))
     }
 }
 #[derive(Debug, Clone)]
@@ -125,10 +226,10 @@ pub struct AutoRecovery {
 }
 impl AutoRecovery {
     pub fn new() -> Self {
-        Self {
-            error_recovery: ErrorRecovery::new(),
-            enabled: true,
-        }
+        Self::with_recovery(ErrorRecovery::default())
+    }
+    pub fn with_recovery(error_recovery: ErrorRecovery) -> Self {
+        Self { error_recovery, enabled: true }
     }
     pub fn set_enabled(&mut self, enabled: bool) {
         self.enabled = enabled;
@@ -153,15 +254,15 @@ mod tests {
     use std::sync::atomic::{AtomicU32, Ordering};
     #[tokio::test]
     async fn test_retry_recovery() {
-        let recovery = ErrorRecovery::new();
+        let recovery = ErrorRecovery::default();
         let attempt_count = AtomicU32::new(0);
-        let result: Result<String, _> = recovery
+        let result: Result<String> = recovery
             .execute_recovery(
                 "FileNotFound",
                 || {
                     let count = attempt_count.fetch_add(1, Ordering::SeqCst);
                     if count < 2 {
-                        Err(anyhow::anyhow!("File not found"))
+                        Err(SymorError::new(ErrorCode::FileNotFound, "missing".to_string()))
                     } else {
                         Ok("success".to_string())
                     }
@@ -174,14 +275,70 @@ mod tests {
     }
     #[tokio::test]
     async fn test_fallback_recovery() {
-        let recovery = ErrorRecovery::new();
-        let result: Result<String, _> = recovery
+        let recovery = ErrorRecovery::default();
+        let result: Result<String> = recovery
             .execute_recovery(
                 "PermissionDenied",
-                || Err(anyhow::anyhow!("Permission denied")),
+                || Err(SymorError::new(ErrorCode::PermissionDenied, "denied".to_string())),
             )
             .await;
         assert!(result.is_err());
         assert!(result.unwrap_err().to_string().contains("Fallback required"));
     }
+    #[test]
+    fn test_strategies_from_config() {
+        let json = r#"{
+            "NetworkError": { "strategy": "retry", "max_attempts": 10, "delay_ms": 250 },
+            "DiskFull": { "strategy": "skip" }
+        }"#;
+        let config: RecoveryConfig = serde_json::from_str(json).unwrap();
+        let recovery = ErrorRecovery::new(&config);
+        assert_eq!(
+            recovery.get_strategy("NetworkError"), RecoveryStrategy::Retry { max_attempts
+            : 10, delay : Duration::from_millis(250) }
+        );
+        assert_eq!(
+            recovery.strategy_for(& super::super::ErrorCode::DiskFull),
+            RecoveryStrategy::Skip
+        );
+        assert_eq!(recovery.get_strategy("FileNotFound"), RecoveryStrategy::Fail);
+    }
+    #[test]
+    fn test_configured_retries() {
+        let attempts = AtomicU32::new(0);
+        let flaky = || {
+            let attempt = attempts.fetch_add(1, Ordering::SeqCst) + 1;
+            if attempt < 3 {
+                Err(SymorError::new(ErrorCode::NetworkError, "mount went away".to_string()))
+            } else {
+                Ok(attempt)
+            }
+        };
+        let retries = |max_attempts| {
+            let delay = Duration::from_millis(1);
+            let strategy = RecoveryStrategy::Retry { max_attempts, delay };
+            let strategies = HashMap::from([("NetworkError".to_string(), strategy)]);
+            ErrorRecovery::new(&RecoveryConfig { strategies })
+        };
+        assert_eq!(retries(3).run(flaky).unwrap(), 3);
+        attempts.store(0, Ordering::SeqCst);
+        assert_eq!(retries(2).run(flaky).unwrap_err().code, ErrorCode::NetworkError);
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+        attempts.store(0, Ordering::SeqCst);
+        let config: RecoveryConfig = serde_json::from_str(
+            r#"{"PermissionDenied": {"strategy": "fallback", "alternative_action": "Run as root"},
+                "NetworkError": {"strategy": "skip"}}"#,
+        )
+        .unwrap();
+        let recovery = ErrorRecovery::new(&config);
+        let error = recovery.run(flaky).unwrap_err();
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+        assert!(recovery.skips(&error));
+        let denied = || -> Result<()> {
+            Err(SymorError::new(ErrorCode::PermissionDenied, "denied".to_string()))
+        };
+        let error = recovery.run(denied).unwrap_err();
+        assert_eq!(error.recovery_suggestion.as_deref(), Some("Run as root"));
+        assert!(!recovery.skips(&error));
+    }
 }
\ No newline at end of file
@@ -1,6 +1,8 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::time::SystemTime;
+/// Result type returned by the symor library API
+pub type Result<T, E = SymorError> = std::result::Result<T, E>;
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SymorError {
     pub code: ErrorCode,
@@ -27,6 +29,59 @@ impl SymorError {
         self.recovery_suggestion = Some(suggestion);
         self
     }
+    pub fn not_watched(item: &str) -> Self {
+        SymorError::new(ErrorCode::NotWatched, format!("Watched item not found: {}", item))
+            .with_suggestion("Use 'sym list' to see watched items".to_string())
+    }
+    pub fn code(&self) -> &ErrorCode {
+        &self.code
+    }
+    fn wrap(mut self, context: String) -> Self {
+        self.message = format!("{}: {}", context, self.message);
+        self
+    }
+}
+impl From<std::io::Error> for SymorError {
+    fn from(error: std::io::Error) -> Self {
+        SymorError::new(ErrorCode::from_io_error(&error), error.to_string())
+            .with_context("io_kind", &format!("{:?}", error.kind()))
+    }
+}
+impl From<serde_json::Error> for SymorError {
+    fn from(error: serde_json::Error) -> Self {
+        SymorError::new(ErrorCode::SerializationError, error.to_string())
+    }
+}
+impl From<notify::Error> for SymorError {
+    fn from(error: notify::Error) -> Self {
+        match error.kind {
+            notify::ErrorKind::Io(io_error) => io_error.into(),
+            notify::ErrorKind::PathNotFound => {
+                SymorError::new(ErrorCode::FileNotFound, "path not found".to_string())
+            }
+            other => SymorError::new(ErrorCode::WatcherError, format!("{:?}", other)),
+        }
+    }
+}
+/// Attach human readable context to a failing result, keeping its error code
+pub trait Context<T> {
+    fn context<C: std::fmt::Display>(self, context: C) -> Result<T>;
+    fn with_context<C, F>(self, f: F) -> Result<T>
+    where
+        C: std::fmt::Display,
+        F: FnOnce() -> C;
+}
+impl<T, E: Into<SymorError>> Context<T> for std::result::Result<T, E> {
+    fn context<C: std::fmt::Display>(self, context: C) -> Result<T> {
+        self.map_err(|e| e.into().wrap(context.to_string()))
+    }
+    fn with_context<C, F>(self, f: F) -> Result<T>
+    where
+        C: std::fmt::Display,
+        F: FnOnce() -> C,
+    {
+        self.map_err(|e| e.into().wrap(f().to_string()))
+    }
 }
 impl std::fmt::Display for SymorError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -48,8 +103,62 @@ pub enum ErrorCode {
     MissingConfiguration,
     NetworkError,
     ConnectionTimeout,
+    NotWatched,
+    SerializationError,
+    WatcherError,
     InternalError,
     UnknownError,
+    /// Refused because `sym maintenance on` is in effect
+    MaintenanceMode,
+    /// Refused because the versions are under the compliance retention lock
+    RetentionLocked,
+    /// Refused to version a file holding secrets under `versioning.secret_scan` strict
+    SecretDetected,
+}
+impl ErrorCode {
+    /// Process exit status used by the `sym` binary for this error category
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            ErrorCode::NotWatched => 2,
+            ErrorCode::VersionNotFound => 3,
+            ErrorCode::VersionCorrupted | ErrorCode::SerializationError => 4,
+            ErrorCode::PermissionDenied => 5,
+            ErrorCode::FileNotFound | ErrorCode::InvalidPath => 6,
+            ErrorCode::DiskFull | ErrorCode::StorageFull => 7,
+            ErrorCode::InvalidConfiguration | ErrorCode::MissingConfiguration => 8,
+            ErrorCode::NetworkError | ErrorCode::ConnectionTimeout => 9,
+            ErrorCode::MaintenanceMode => 10,
+            ErrorCode::RetentionLocked => 11,
+            ErrorCode::SecretDetected => 12,
+            ErrorCode::WatcherError
+            | ErrorCode::InternalError
+            | ErrorCode::UnknownError => 1,
+        }
+    }
+    pub fn from_io_error(error: &std::io::Error) -> Self {
+        use std::io::ErrorKind;
+        if matches!(error.raw_os_error(), Some(28) | Some(112)) {
+            return ErrorCode::DiskFull;
+        }
+        match error.kind() {
+            ErrorKind::NotFound => ErrorCode::FileNotFound,
+            ErrorKind::PermissionDenied | ErrorKind::ReadOnlyFilesystem => {
+                ErrorCode::PermissionDenied
+            }
+            ErrorKind::StorageFull => ErrorCode::DiskFull,
+            ErrorKind::QuotaExceeded | ErrorKind::FileTooLarge => ErrorCode::StorageFull,
+            ErrorKind::InvalidInput | ErrorKind::NotADirectory | ErrorKind::IsADirectory => {
+                ErrorCode::InvalidPath
+            }
+            ErrorKind::TimedOut => ErrorCode::ConnectionTimeout,
+            ErrorKind::ConnectionRefused
+            | ErrorKind::ConnectionReset
+            | ErrorKind::ConnectionAborted
+            | ErrorKind::NotConnected
+            | ErrorKind::BrokenPipe => ErrorCode::NetworkError,
+            _ => ErrorCode::UnknownError,
+        }
+    }
 }
 /// Error context information
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -74,4 +183,38 @@ impl ErrorContext {
         self.additional_info.insert(key.to_string(), value.to_string());
         self
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_io_error_mapping() {
+        let not_found = std::io::Error::new(std::io::ErrorKind::NotFound, "missing");
+        let error: SymorError = not_found.into();
+        assert_eq!(error.code, ErrorCode::FileNotFound);
+        let denied = std::io::Error::new(std::io::ErrorKind::PermissionDenied, "denied");
+        assert_eq!(SymorError::from(denied).code, ErrorCode::PermissionDenied);
+        let full = std::io::Error::from_raw_os_error(28);
+        assert_eq!(SymorError::from(full).code, ErrorCode::DiskFull);
+    }
+    #[test]
+    fn test_exit_codes_are_distinct_per_category() {
+        assert_eq!(ErrorCode::NotWatched.exit_code(), 2);
+        assert_eq!(ErrorCode::VersionNotFound.exit_code(), 3);
+        assert_eq!(ErrorCode::VersionCorrupted.exit_code(), 4);
+        assert_eq!(ErrorCode::PermissionDenied.exit_code(), 5);
+        assert_eq!(ErrorCode::UnknownError.exit_code(), 1);
+        assert_eq!(ErrorCode::MaintenanceMode.exit_code(), 10);
+        assert_eq!(ErrorCode::RetentionLocked.exit_code(), 11);
+        assert_eq!(ErrorCode::SecretDetected.exit_code(), 12);
+    }
+    #[test]
+    fn test_context_keeps_error_code() {
+        let result: std::result::Result<(), std::io::Error> = Err(
+            std::io::Error::new(std::io::ErrorKind::NotFound, "missing"),
+        );
+        let error = result.with_context(|| "cannot read config").unwrap_err();
+        assert_eq!(error.code, ErrorCode::FileNotFound);
+        assert!(error.to_string().starts_with("[FileNotFound] cannot read config: "));
+    }
+}
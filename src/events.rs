@@ -0,0 +1,180 @@
+use crate::conflicts::Conflict;
+use std::{
+    path::PathBuf, sync::{mpsc::{self, Receiver, Sender}, Arc, Mutex},
+    time::Duration,
+};
+/// Sync activity reported to subscribers of a `Mirror` or `Daemon`
+#[derive(Debug, Clone, PartialEq)]
+pub enum SyncEvent {
+    SyncStarted { source: PathBuf },
+    /// A file was written to `path`, a target (or the source, when syncing back)
+    FileCopied { path: PathBuf, bytes: u64 },
+    SyncCompleted { source: PathBuf, files: usize, bytes: u64, duration: Duration },
+    SyncFailed { source: PathBuf, error: String },
+    /// Found when a mirror starts, before the initial sync overwrites the target
+    ConflictDetected(Conflict),
+    /// The source changed faster than `max_per_minute` syncs allow; later changes are
+    /// coalesced into one sync when the limit frees up
+    RateLimited { source: PathBuf, max_per_minute: u32 },
+    /// More than `threshold` changes arrived within a second; the mirror waits for them
+    /// to stop and then rescans once
+    StormDetected { source: PathBuf, threshold: u32 },
+}
+impl SyncEvent {
+    /// Mirror source the event belongs to, if it names one
+    pub fn source(&self) -> Option<&PathBuf> {
+        match self {
+            Self::SyncStarted { source }
+            | Self::SyncCompleted { source, .. }
+            | Self::SyncFailed { source, .. }
+            | Self::RateLimited { source, .. }
+            | Self::StormDetected { source, .. } => Some(source),
+            Self::ConflictDetected(conflict) => Some(&conflict.source),
+            Self::FileCopied { .. } => None,
+        }
+    }
+}
+enum Listener {
+    Channel(Sender<SyncEvent>),
+    Callback(Box<dyn Fn(&SyncEvent) + Send>),
+}
+/// Subscribers shared between mirrors; clones deliver to the same listeners
+#[derive(Clone, Default)]
+pub struct EventHub {
+    listeners: Arc<Mutex<Vec<Listener>>>,
+}
+impl std::fmt::Debug for EventHub {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EventHub").field("listeners", &self.len()).finish()
+    }
+}
+impl EventHub {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<Listener>> {
+        self.listeners.lock().unwrap_or_else(|e| e.into_inner())
+    }
+    fn len(&self) -> usize {
+        self.lock().len()
+    }
+    /// Receiver for every later event; dropping it unsubscribes
+    pub fn subscribe(&self) -> Receiver<SyncEvent> {
+        let (tx, rx) = mpsc::channel();
+        self.lock().push(Listener::Channel(tx));
+        rx
+    }
+    /// Calls `callback` on the syncing thread for every later event
+    pub fn on_event(&self, callback: impl Fn(&SyncEvent) + Send + 'static) {
+        self.lock().push(Listener::Callback(Box::new(callback)));
+    }
+    pub fn has_listeners(&self) -> bool {
+        self.len() > 0
+    }
+    pub fn emit(&self, event: SyncEvent) {
+        self.lock()
+            .retain(|listener| match listener {
+                Listener::Channel(tx) => tx.send(event.clone()).is_ok(),
+                Listener::Callback(callback) => {
+                    callback(&event);
+                    true
+                }
+            });
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Mirror;
+    use std::{fs, sync::atomic::{AtomicUsize, Ordering}};
+    use tempfile::tempdir;
+    #[test]
+    fn test_sync_events() {
+        let temp_dir = tempdir().unwrap();
+        let source = temp_dir.path().join("src");
+        let target = temp_dir.path().join("dst");
+        fs::create_dir_all(&source).unwrap();
+        fs::write(source.join("a.txt"), "hello").unwrap();
+        fs::write(source.join("b.txt"), "world!").unwrap();
+        let mirror = Mirror::builder(&source).target(&target).build().unwrap();
+        let rx = mirror.subscribe();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        mirror.on_event(move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+        mirror.run_sync(Mirror::sync_once).unwrap();
+        let events: Vec<_> = rx.try_iter().collect();
+        assert_eq!(events.len(), 4);
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+        assert_eq!(events[0], SyncEvent::SyncStarted { source : source.clone() });
+        assert!(
+            events.contains(& SyncEvent::FileCopied { path : target.join("b.txt"), bytes
+            : 6 })
+        );
+        match &events[3] {
+            SyncEvent::SyncCompleted { files, bytes, .. } => {
+                assert_eq!((* files, * bytes), (2, 11))
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+        drop(rx);
+        fs::remove_dir_all(&source).unwrap();
+        assert!(mirror.run_sync(Mirror::sync_once).is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 6);
+        assert!(mirror.events.has_listeners());
+    }
+    #[test]
+    fn test_large_targets_are_patched() {
+        let temp_dir = tempdir().unwrap();
+        let source = temp_dir.path().join("disk.img");
+        let target = temp_dir.path().join("copy.img");
+        let mut data: Vec<u8> = (0..crate::DELTA_MIN_SIZE).map(|i| (i % 253) as u8).collect();
+        fs::write(&source, &data).unwrap();
+        let mirror = Mirror::builder(&source).target(&target).build().unwrap();
+        mirror.run_sync(Mirror::sync_once).unwrap();
+        data[1000] ^= 0xff;
+        data[5_000_000] ^= 0xff;
+        data.extend_from_slice(b"appended");
+        fs::write(&source, &data).unwrap();
+        let rx = mirror.subscribe();
+        mirror.run_sync(Mirror::sync_once).unwrap();
+        assert_eq!(fs::read(&target).unwrap(), data);
+        let written = rx
+            .try_iter()
+            .find_map(|event| match event {
+                SyncEvent::FileCopied { bytes, .. } => Some(bytes),
+                _ => None,
+            })
+            .unwrap();
+        assert!(written < 4 * crate::DELTA_BLOCK_SIZE as u64, "{} bytes written", written);
+    }
+    #[test]
+    fn test_change_storm_rescans_once() {
+        let temp_dir = tempdir().unwrap();
+        let source = temp_dir.path().join("src");
+        let target = temp_dir.path().join("dst");
+        fs::create_dir_all(&source).unwrap();
+        let mirror = Mirror::builder(&source)
+            .target(&target)
+            .debounce(20)
+            .storm_threshold(20)
+            .build()
+            .unwrap();
+        let rx = mirror.subscribe();
+        std::thread::spawn(move || mirror.run());
+        let next = |timeout| rx.recv_timeout(Duration::from_secs(timeout)).ok();
+        let wait = |until: fn(&SyncEvent) -> bool| {
+            std::iter::from_fn(|| next(15)).any(|event| until(&event))
+        };
+        assert!(wait(|event| matches!(event, SyncEvent::SyncCompleted { .. })));
+        for i in 0..200 {
+            fs::write(source.join(format!("{}.txt", i)), "x").unwrap();
+        }
+        assert!(wait(|event| matches!(event, SyncEvent::StormDetected { threshold: 20, .. })));
+        let events: Vec<_> = std::iter::from_fn(|| next(4)).collect();
+        let syncs = events.iter().filter(|e| matches!(e, SyncEvent::SyncStarted { .. })).count();
+        assert_eq!(syncs, 1, "{:?}", events);
+        assert_eq!(fs::read_dir(&target).unwrap().count(), 200);
+    }
+}
\ No newline at end of file
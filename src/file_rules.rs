@@ -0,0 +1,271 @@
+use crate::{
+    errors::{ErrorCode, Result, SymorError},
+    versioning::filter::{parse_quantity, AGE_UNITS, SIZE_UNITS},
+    SymorManager,
+};
+use serde::{Deserialize, Serialize};
+use std::{fmt, fs, path::Path, time::{Duration, SystemTime}};
+/// One entry of the `file_rules` config section: conditions on a file's path, extension,
+/// size and age, and what to do with the files that meet all of them. Sizes take
+/// B, KB, MB or GB and ages s, m, h, d, w or y, as in `--where`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FileRule {
+    /// Globs matched against the path inside the mirror or watch, or the file name
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub paths: Vec<String>,
+    /// Extensions without the dot, compared case-insensitively
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub extensions: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_size: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_size: Option<String>,
+    /// Time since the file was last modified
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub older_than: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub newer_than: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mirror: Option<bool>,
+    /// Whether versions are stored compressed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compress: Option<bool>,
+    /// Neither version nor mirror
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub skip: bool,
+}
+/// What the file rules decided for one file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileDecision {
+    pub version: bool,
+    pub mirror: bool,
+    pub compress: bool,
+}
+impl Default for FileDecision {
+    fn default() -> Self {
+        Self {
+            version: true,
+            mirror: true,
+            compress: true,
+        }
+    }
+}
+impl fmt::Display for FileDecision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        if self.version {
+            parts.push(if self.compress { "versioned" } else { "versioned uncompressed" });
+        }
+        if self.mirror {
+            parts.push("mirrored");
+        }
+        match parts.as_slice() {
+            [] => f.write_str("skipped"),
+            [only] => write!(f, "{} only", only),
+            _ => f.write_str(&parts.join(", ")),
+        }
+    }
+}
+#[derive(Debug, Clone)]
+struct CompiledRule {
+    paths: Vec<glob::Pattern>,
+    extensions: Vec<String>,
+    size: (Option<u64>, Option<u64>),
+    /// Least and greatest age
+    age: (Option<Duration>, Option<Duration>),
+    rule: FileRule,
+}
+fn invalid(index: usize, message: String) -> SymorError {
+    let message = format!("{} in file rule {}", message, index + 1);
+    SymorError::new(ErrorCode::InvalidConfiguration, message)
+        .with_suggestion(
+            "Sizes take B, KB, MB or GB (\"100MB\") and ages s, m, h, d, w or y (\"30d\")"
+                .to_string(),
+        )
+}
+impl CompiledRule {
+    fn new(index: usize, rule: &FileRule) -> Result<Self> {
+        let paths = rule
+            .paths
+            .iter()
+            .map(|pattern| {
+                glob::Pattern::new(pattern)
+                    .map_err(|e| invalid(index, format!("Invalid glob {:?} ({})", pattern, e)))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let quantity = |value: &Option<String>, units| {
+            value
+                .as_deref()
+                .map(|text| {
+                    parse_quantity(text.trim(), units)
+                        .ok_or_else(|| invalid(index, format!("Invalid quantity {:?}", text)))
+                })
+                .transpose()
+        };
+        let age = |value| quantity(value, AGE_UNITS).map(|secs| secs.map(Duration::from_secs));
+        Ok(Self {
+            paths,
+            extensions: rule
+                .extensions
+                .iter()
+                .map(|ext| ext.trim_start_matches('.').to_lowercase())
+                .collect(),
+            size: (
+                quantity(&rule.min_size, SIZE_UNITS)?,
+                quantity(&rule.max_size, SIZE_UNITS)?,
+            ),
+            age: (age(&rule.older_than)?, age(&rule.newer_than)?),
+            rule: rule.clone(),
+        })
+    }
+    fn matches(&self, path: &Path, relative: &Path, now: SystemTime) -> bool {
+        let name = path.file_name().map(|n| n.to_string_lossy().into_owned());
+        let path_matches = self.paths.is_empty()
+            || self
+                .paths
+                .iter()
+                .any(|pattern| {
+                    pattern.matches_path(relative)
+                        || name.as_deref().is_some_and(|n| pattern.matches(n))
+                });
+        let ext = path.extension().map(|e| e.to_string_lossy().to_lowercase());
+        let ext_matches = self.extensions.is_empty()
+            || ext.is_some_and(|ext| self.extensions.contains(&ext));
+        if !path_matches || !ext_matches {
+            return false;
+        }
+        if self.size == (None, None) && self.age == (None, None) {
+            return true;
+        }
+        let Ok(metadata) = fs::metadata(path) else {
+            return false;
+        };
+        let size = metadata.len();
+        let age = metadata
+            .modified()
+            .map(|modified| now.duration_since(modified).unwrap_or_default());
+        self.size.0.is_none_or(|min| size >= min) && self.size.1.is_none_or(|max| size <= max)
+            && match age {
+                Ok(age) => {
+                    self.age.0.is_none_or(|min| age >= min)
+                        && self.age.1.is_none_or(|max| age <= max)
+                }
+                Err(_) => self.age == (None, None),
+            }
+    }
+}
+/// The compiled `file_rules`; every rule a file meets applies in order, so later rules
+/// override what earlier ones set
+#[derive(Debug, Clone, Default)]
+pub struct FileRules {
+    rules: Vec<CompiledRule>,
+}
+impl FileRules {
+    pub fn new(rules: &[FileRule]) -> Result<Self> {
+        let rules = rules
+            .iter()
+            .enumerate()
+            .map(|(index, rule)| CompiledRule::new(index, rule))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { rules })
+    }
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+    /// What to do with the file at `path`; `relative` is its path inside the mirrored
+    /// or watched root
+    pub fn decide(&self, path: &Path, relative: &Path) -> FileDecision {
+        let mut decision = FileDecision::default();
+        if self.rules.is_empty() {
+            return decision;
+        }
+        let now = SystemTime::now();
+        for compiled in self.rules.iter().filter(|rule| rule.matches(path, relative, now)) {
+            let rule = &compiled.rule;
+            if rule.skip {
+                decision.version = false;
+                decision.mirror = false;
+            }
+            decision.version = rule.version.unwrap_or(decision.version);
+            decision.mirror = rule.mirror.unwrap_or(decision.mirror);
+            decision.compress = rule.compress.unwrap_or(decision.compress);
+        }
+        decision
+    }
+}
+impl SymorManager {
+    /// The `file_rules` of the config, compiled
+    pub fn file_rules(&self) -> Result<FileRules> {
+        FileRules::new(&self.config.file_rules)
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+    #[test]
+    fn test_file_rules() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path();
+        let (image, photo, log) = (root.join("disk.ISO"), root.join("a.jpg"), root.join("x.log"));
+        fs::File::create(&image).unwrap().set_len(2 << 20).unwrap();
+        fs::write(&photo, "jpeg").unwrap();
+        fs::write(&log, "log").unwrap();
+        let rules: Vec<FileRule> = serde_json::from_str(
+                r#"[
+                    {"extensions": ["iso"], "min_size": "1MB", "version": false},
+                    {"extensions": [".JPG", "png"], "compress": false},
+                    {"paths": ["logs/**", "*.log"], "skip": true},
+                    {"paths": ["keep/*.log"], "mirror": true},
+                    {"extensions": ["jpg"], "older_than": "1d", "skip": true}
+                ]"#,
+            )
+            .unwrap();
+        let rules = FileRules::new(&rules).unwrap();
+        let decide = |path: &Path, relative: &str| rules.decide(path, Path::new(relative));
+        assert_eq!(decide(&image, "disk.ISO").to_string(), "mirrored only");
+        assert_eq!(decide(&photo, "a.jpg").to_string(), "versioned uncompressed, mirrored");
+        assert_eq!(decide(&log, "x.log").to_string(), "skipped");
+        assert_eq!(decide(&log, "keep/x.log").to_string(), "mirrored only");
+        assert_eq!(decide(&root.join("b.txt"), "b.txt"), FileDecision::default());
+        fs::File::create(&image).unwrap().set_len(10).unwrap();
+        assert!(decide(&image, "disk.ISO").version);
+        let bad = FileRule {
+            max_size: Some("lots".to_string()),
+            ..Default::default()
+        };
+        let error = FileRules::new(&[FileRule::default(), bad]).unwrap_err();
+        assert!(error.message.ends_with("file rule 2"), "{}", error.message);
+        let target = root.join("copy");
+        let mirror = crate::Mirror::builder(root)
+            .target(&target)
+            .exclude("copy")
+            .file_rules(rules)
+            .build()
+            .unwrap();
+        mirror.sync_once().unwrap();
+        assert!(target.join("disk.ISO").exists() && !target.join("x.log").exists());
+        let mut manager = SymorManager::new().unwrap();
+        manager.config.home_dir = root.join("home");
+        manager.version_storage = crate::versioning::storage::VersionStorage::with_config(
+            crate::versioning::storage::StorageConfig {
+                storage_path: root.join("home/versions"),
+                ..Default::default()
+            },
+        );
+        manager.config.file_rules = serde_json::from_str(
+                r#"[{"extensions": ["jpg"], "compress": false},
+                    {"paths": ["*.log"], "skip": true}]"#,
+            )
+            .unwrap();
+        let photo_id = manager.watch_only(photo, false, Vec::new(), false).unwrap();
+        let log_id = manager.watch_only(log, false, Vec::new(), false).unwrap();
+        let version = &manager.watched_item(&photo_id).unwrap().versions[0];
+        let metadata = manager.version_storage.load_metadata(&version.id).unwrap();
+        assert_eq!(metadata.compression_level, 0);
+        assert!(manager.watched_item(&log_id).unwrap().versions.is_empty());
+    }
+}
\ No newline at end of file
@@ -0,0 +1,206 @@
+use crate::{FileVersion, SymorManager};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt, path::{Path, PathBuf}, process::{Command, Stdio},
+    sync::{Arc, Mutex},
+};
+/// What happened to a version
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VersionAction {
+    Created,
+    Restored,
+    /// Deleted by the version limit, `sym clean`, a purge or to free disk space
+    Pruned,
+}
+impl fmt::Display for VersionAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(
+            match self {
+                Self::Created => "created",
+                Self::Restored => "restored",
+                Self::Pruned => "pruned",
+            },
+        )
+    }
+}
+/// A version lifecycle change, handed to callbacks and to hook commands through
+/// `SYMOR_*` environment variables
+#[derive(Debug, Clone, PartialEq)]
+pub struct VersionEvent {
+    pub action: VersionAction,
+    pub item_id: String,
+    pub item_path: PathBuf,
+    pub version_id: String,
+    pub hash: String,
+    pub size: u64,
+    /// Where a restore wrote the version
+    pub target: Option<PathBuf>,
+}
+impl VersionEvent {
+    pub fn new(action: VersionAction, item_id: &str, version: &FileVersion) -> Self {
+        Self {
+            action,
+            item_id: item_id.to_string(),
+            item_path: version.path.clone(),
+            version_id: version.id.clone(),
+            hash: version.hash.clone(),
+            size: version.size,
+            target: None,
+        }
+    }
+    /// Environment a hook command runs with
+    pub fn env(&self) -> Vec<(&'static str, String)> {
+        let mut env = vec![
+            ("SYMOR_EVENT", self.action.to_string()), ("SYMOR_ITEM_ID", self.item_id.clone()),
+            ("SYMOR_ITEM_PATH", self.item_path.to_string_lossy().into_owned()),
+            ("SYMOR_VERSION_ID", self.version_id.clone()), ("SYMOR_VERSION_HASH", self.hash
+            .clone()), ("SYMOR_VERSION_SIZE", self.size.to_string()),
+        ];
+        if let Some(target) = &self.target {
+            env.push(("SYMOR_RESTORE_TARGET", target.to_string_lossy().into_owned()));
+        }
+        env
+    }
+}
+/// Shell commands run for each version lifecycle change, in the `hooks` config section
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HooksConfig {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub on_version_created: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub on_version_restored: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub on_version_pruned: Vec<String>,
+}
+impl HooksConfig {
+    pub fn commands(&self, action: VersionAction) -> &[String] {
+        match action {
+            VersionAction::Created => &self.on_version_created,
+            VersionAction::Restored => &self.on_version_restored,
+            VersionAction::Pruned => &self.on_version_pruned,
+        }
+    }
+    pub fn is_empty(&self) -> bool {
+        self.on_version_created.is_empty() && self.on_version_restored.is_empty()
+            && self.on_version_pruned.is_empty()
+    }
+}
+type Callback = Arc<dyn Fn(&VersionEvent) + Send + Sync>;
+/// Callbacks registered with `SymorManager::on_version_event`
+#[derive(Clone, Default)]
+pub(crate) struct VersionHooks {
+    callbacks: Arc<Mutex<Vec<Callback>>>,
+}
+impl fmt::Debug for VersionHooks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let callbacks = self.callbacks.lock().map(|c| c.len()).unwrap_or_default();
+        f.debug_struct("VersionHooks").field("callbacks", &callbacks).finish()
+    }
+}
+/// `command` run through `sh -c`, or `cmd /C` on Windows
+pub(crate) fn shell_command(command: &str) -> Command {
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    shell.arg(command);
+    shell
+}
+/// Starts `command` through the shell with the event's environment, without waiting
+/// for it; a failure is logged once it exits
+fn spawn_command(command: &str, event: &VersionEvent, cwd: &Path) {
+    let mut shell = shell_command(command);
+    shell.envs(event.env()).current_dir(cwd).stdin(Stdio::null());
+    match shell.spawn() {
+        Ok(mut child) => {
+            let command = command.to_string();
+            std::thread::spawn(move || match child.wait() {
+                Ok(status) if !status.success() => {
+                    warn!("Version hook {:?} exited with {}", command, status)
+                }
+                Ok(_) => {}
+                Err(e) => warn!("Version hook {:?} failed: {}", command, e),
+            });
+        }
+        Err(e) => warn!("Cannot run version hook {:?}: {}", command, e),
+    }
+}
+impl SymorManager {
+    /// Calls `callback` for every version this manager creates, restores or prunes
+    pub fn on_version_event(&self, callback: impl Fn(&VersionEvent) + Send + Sync + 'static) {
+        self.version_hooks
+            .callbacks
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(Arc::new(callback));
+    }
+    /// Runs the registered callbacks and the configured hook commands for `event`
+    pub fn run_version_hooks(&self, event: &VersionEvent) {
+        let callbacks = self.version_hooks.callbacks.lock().unwrap_or_else(|e| e.into_inner());
+        let callbacks = callbacks.clone();
+        for callback in callbacks {
+            callback(event);
+        }
+        for command in self.config.hooks.commands(event.action) {
+            info!("Running {} hook for version {}", event.action, event.version_id);
+            spawn_command(command, event, &self.config.home_dir);
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{fs, time::Duration};
+    use tempfile::tempdir;
+    #[test]
+    fn test_version_hooks() {
+        let temp_dir = tempdir().unwrap();
+        let mut manager = SymorManager::new().unwrap();
+        manager.config.home_dir = temp_dir.path().join("home");
+        fs::create_dir_all(&manager.config.home_dir).unwrap();
+        manager.version_storage = crate::versioning::storage::VersionStorage::with_config(
+            crate::versioning::storage::StorageConfig {
+                storage_path: temp_dir.path().join("home/versions"),
+                ..Default::default()
+            },
+        );
+        manager.config.versioning.max_versions = 1;
+        let log = temp_dir.path().join("hooks.log");
+        let line = format!("echo \"$SYMOR_EVENT $SYMOR_VERSION_HASH\" >> {:?}", log);
+        manager.config.hooks.on_version_created.push(line);
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let recorder = seen.clone();
+        manager.on_version_event(move |event| recorder.lock().unwrap().push(event.clone()));
+        let path = temp_dir.path().join("notes.txt");
+        fs::write(&path, "first").unwrap();
+        let id = manager.watch_only(path.clone(), false, Vec::new(), false).unwrap();
+        fs::write(&path, "second").unwrap();
+        manager.create_backup(&id).unwrap();
+        let versions = manager.watched_item(&id).unwrap().versions.to_vec();
+        let restored = temp_dir.path().join("restored.txt");
+        manager.restore_file(&id, &versions[0].id, &restored).unwrap();
+        let actions: Vec<_> = seen.lock().unwrap().iter().map(|event| event.action).collect();
+        use VersionAction::*;
+        assert_eq!(actions, [Created, Created, Pruned, Restored]);
+        let restore = seen.lock().unwrap()[3].clone();
+        assert_eq!(restore.target.as_deref(), Some(restored.as_path()));
+        assert!(restore.env().contains(&("SYMOR_VERSION_HASH", versions[0].hash.clone())));
+        if cfg!(unix) {
+            for _ in 0..50 {
+                if fs::read_to_string(&log).is_ok_and(|log| log.lines().count() == 2) {
+                    break;
+                }
+                std::thread::sleep(Duration::from_millis(100));
+            }
+            let log = fs::read_to_string(&log).unwrap();
+            assert!(log.contains(&format!("created {}", versions[0].hash)), "{}", log);
+        }
+    }
+}
\ No newline at end of file
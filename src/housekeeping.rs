@@ -0,0 +1,221 @@
+use crate::{errors::Result, SymorManager};
+use std::{fs, path::{Path, PathBuf}, time::{Duration, SystemTime}};
+/// Temp files younger than this may still belong to a running sync or restore
+const TEMP_AGE: Duration = Duration::from_secs(3600);
+/// Rotated logs (`daemon.log.1`, `daemon.log.2.gz`) are kept for a week
+const LOG_AGE: Duration = Duration::from_secs(7 * 86_400);
+/// Kind of leftover removed by `SymorManager::clean_home`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Leftover {
+    /// A `groups/` entry whose directory no longer exists
+    StaleGroup,
+    /// An expired file or directory under `temp/`
+    TempFile,
+    /// A rotated log under `logs/` past its retention
+    RotatedLog,
+    /// A `.tmp-sync` file an interrupted mirror left next to its source or targets
+    SyncTemp,
+}
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LeftoverPath {
+    pub kind: Leftover,
+    pub path: PathBuf,
+    /// Bytes on disk, including everything under a directory
+    pub size: u64,
+}
+/// What `SymorManager::clean_home` removed, or would remove on a dry run
+#[derive(Debug, Clone, Default)]
+pub struct HomeCleanup {
+    pub removed: Vec<LeftoverPath>,
+}
+impl HomeCleanup {
+    pub fn count(&self, kind: Leftover) -> usize {
+        self.removed.iter().filter(|leftover| leftover.kind == kind).count()
+    }
+    pub fn bytes(&self) -> u64 {
+        self.removed.iter().map(|leftover| leftover.size).sum()
+    }
+}
+fn older_than(path: &Path, age: Duration, now: SystemTime) -> bool {
+    fs::symlink_metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .is_ok_and(|modified| now.duration_since(modified).unwrap_or_default() > age)
+}
+fn disk_size(path: &Path) -> u64 {
+    let Ok(metadata) = fs::symlink_metadata(path) else {
+        return 0;
+    };
+    if !metadata.is_dir() {
+        return metadata.len();
+    }
+    fs::read_dir(path)
+        .map(|entries| entries.flatten().map(|entry| disk_size(&entry.path())).sum())
+        .unwrap_or(0)
+}
+/// Group directories under `home_dir/groups` whose index points at a path that is gone
+pub(crate) fn stale_groups(home_dir: &Path) -> Result<Vec<(PathBuf, String)>> {
+    let groups_dir = home_dir.join("groups");
+    if !groups_dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut stale = Vec::new();
+    for entry in fs::read_dir(&groups_dir)? {
+        let group_subdir = entry?.path();
+        let Ok(index_content) = fs::read_to_string(group_subdir.join("index.json")) else {
+            continue;
+        };
+        let group_path = serde_json::from_str::<serde_json::Value>(&index_content)
+            .ok()
+            .and_then(|index| index.get("group_path")?.as_str().map(str::to_string));
+        if let Some(group_path) = group_path {
+            if !Path::new(&group_path).exists() {
+                stale.push((group_subdir, group_path));
+            }
+        }
+    }
+    Ok(stale)
+}
+/// `.tmp-sync` files at or below `path` last written before `now - TEMP_AGE`
+fn sync_temps(path: &Path, now: SystemTime, found: &mut Vec<PathBuf>) {
+    let Ok(metadata) = fs::symlink_metadata(path) else {
+        return;
+    };
+    if metadata.is_dir() {
+        for entry in fs::read_dir(path).into_iter().flatten().flatten() {
+            sync_temps(&entry.path(), now, found);
+        }
+    } else if path.extension().is_some_and(|ext| ext == "tmp-sync")
+        && older_than(path, TEMP_AGE, now)
+    {
+        found.push(path.to_path_buf());
+    }
+}
+impl SymorManager {
+    /// Leftovers in the home directory and around mirrors, as of `now`
+    fn home_leftovers(&self, now: SystemTime) -> Result<Vec<LeftoverPath>> {
+        let home_dir = &self.config.home_dir;
+        let mut found: Vec<(Leftover, PathBuf)> = stale_groups(home_dir)?
+            .into_iter()
+            .map(|(dir, _)| (Leftover::StaleGroup, dir))
+            .collect();
+        for entry in fs::read_dir(home_dir.join("temp")).into_iter().flatten().flatten() {
+            if older_than(&entry.path(), TEMP_AGE, now) {
+                found.push((Leftover::TempFile, entry.path()));
+            }
+        }
+        for entry in fs::read_dir(home_dir.join("logs")).into_iter().flatten().flatten() {
+            let rotated = entry.file_name().to_string_lossy().contains(".log.");
+            if rotated && older_than(&entry.path(), LOG_AGE, now) {
+                found.push((Leftover::RotatedLog, entry.path()));
+            }
+        }
+        let mut temps = Vec::new();
+        for spec in &self.mirrors {
+            for root in std::iter::once(&spec.source).chain(&spec.targets) {
+                if root.is_dir() {
+                    sync_temps(root, now, &mut temps);
+                } else {
+                    sync_temps(&root.with_extension("tmp-sync"), now, &mut temps);
+                }
+            }
+        }
+        temps.sort();
+        temps.dedup();
+        found.extend(temps.into_iter().map(|path| (Leftover::SyncTemp, path)));
+        Ok(
+            found
+                .into_iter()
+                .map(|(kind, path)| LeftoverPath {
+                    size: disk_size(&path),
+                    kind,
+                    path,
+                })
+                .collect(),
+        )
+    }
+    /// Removes stale group directories, expired files under `temp/`, old rotated logs
+    /// and orphaned `.tmp-sync` files from interrupted mirrors; with `dry_run` only
+    /// reports them
+    pub fn clean_home(&self, dry_run: bool) -> Result<HomeCleanup> {
+        if !dry_run {
+            self.ensure_writable("clean the home directory")?;
+        }
+        let removed = self.home_leftovers(SystemTime::now())?;
+        if !dry_run {
+            for leftover in &removed {
+                if fs::symlink_metadata(&leftover.path)?.is_dir() {
+                    fs::remove_dir_all(&leftover.path)?;
+                } else {
+                    fs::remove_file(&leftover.path)?;
+                }
+            }
+        }
+        Ok(HomeCleanup { removed })
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MirrorSpec;
+    use std::fs::File;
+    use tempfile::tempdir;
+    fn age(path: &Path, secs: u64) {
+        let modified = SystemTime::now() - Duration::from_secs(secs);
+        File::options().write(true).open(path).unwrap().set_modified(modified).unwrap();
+    }
+    #[test]
+    fn test_clean_home() {
+        let temp_dir = tempdir().unwrap();
+        let home = temp_dir.path().join("home");
+        let mut manager = SymorManager::new().unwrap();
+        manager.config.home_dir = home.clone();
+        SymorManager::setup_directory_structure(&home).unwrap();
+        let group = home.join("groups").join("abc");
+        fs::create_dir_all(&group).unwrap();
+        fs::write(group.join("index.json"), r#"{"group_path": "/no/such/dir"}"#).unwrap();
+        let live_group = home.join("groups").join("def");
+        fs::create_dir_all(&live_group).unwrap();
+        let index = serde_json::json!({ "group_path": temp_dir.path() });
+        fs::write(live_group.join("index.json"), index.to_string()).unwrap();
+        let (old_temp, new_temp) = (home.join("temp").join("old"), home.join("temp").join("new"));
+        fs::write(&old_temp, "xx").unwrap();
+        fs::write(&new_temp, "xx").unwrap();
+        age(&old_temp, 2 * 3600);
+        let (rotated, current) = (home.join("logs/daemon.log.1"), home.join("logs/daemon.log"));
+        for log in [&rotated, &current] {
+            fs::write(log, "log").unwrap();
+            age(log, 8 * 86_400);
+        }
+        let target = temp_dir.path().join("mirror");
+        fs::create_dir_all(target.join("nested")).unwrap();
+        let (orphan, in_flight) = (target.join("nested/a.tmp-sync"), target.join("b.tmp-sync"));
+        fs::write(&orphan, "partial").unwrap();
+        fs::write(&in_flight, "partial").unwrap();
+        age(&orphan, 2 * 3600);
+        manager.mirrors.push(MirrorSpec {
+            source: temp_dir.path().join("source"),
+            targets: vec![target],
+            bidirectional: false,
+            only: Vec::new(),
+            skip_hidden: false,
+            paused: false,
+            storm_threshold: None,
+            rollover: false,
+            keep_rotations: None,
+            link_rotations: false,
+            symlinks: Default::default(),
+        });
+        let preview = manager.clean_home(true).unwrap();
+        assert_eq!(preview.removed.len(), 4);
+        assert!(group.exists() && old_temp.exists());
+        let kinds = [Leftover::StaleGroup, Leftover::TempFile, Leftover::RotatedLog, Leftover::SyncTemp];
+        for kind in kinds {
+            assert_eq!(preview.count(kind), 1, "{:?}", kind);
+        }
+        assert!(preview.bytes() >= 2 + 3 + 7);
+        manager.clean_home(false).unwrap();
+        assert!(! group.exists() && ! old_temp.exists() && ! rotated.exists() && ! orphan.exists());
+        assert!(live_group.exists() && new_temp.exists() && current.exists() && in_flight.exists());
+        assert!(manager.clean_home(false).unwrap().removed.is_empty());
+    }
+}
\ No newline at end of file
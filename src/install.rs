@@ -0,0 +1,102 @@
+use crate::errors::{ErrorCode, Result, SymorError};
+use std::{fs, io::Write, path::{Path, PathBuf}};
+/// File name of the installed binary on this platform
+pub fn binary_name() -> String {
+    format!("sym{}", std::env::consts::EXE_SUFFIX)
+}
+/// System-wide install directory (may need elevated permissions)
+pub fn system_install_dir() -> Result<PathBuf> {
+    if cfg!(target_os = "linux") || cfg!(target_os = "macos") {
+        Ok(PathBuf::from("/usr/local/bin"))
+    } else if cfg!(target_os = "windows") {
+        Ok(
+            std::env::var("USERPROFILE")
+                .map(|p| PathBuf::from(p).join("bin"))
+                .unwrap_or_else(|_| PathBuf::from("C:\\bin")),
+        )
+    } else {
+        Err(
+            SymorError::new(
+                ErrorCode::InternalError,
+                "Unsupported platform for installation".to_string(),
+            ),
+        )
+    }
+}
+/// Per-user install directory: `~/.local/bin`, or `%LOCALAPPDATA%\symor\bin` on Windows
+pub fn user_install_dir() -> Result<PathBuf> {
+    let dir = if cfg!(target_os = "windows") {
+        std::env::var("LOCALAPPDATA").map(|p| PathBuf::from(p).join("symor").join("bin"))
+    } else {
+        std::env::var("HOME").map(|p| PathBuf::from(p).join(".local").join("bin"))
+    };
+    dir.map_err(|_| {
+        SymorError::new(
+            ErrorCode::MissingConfiguration,
+            "Cannot determine the user install directory".to_string(),
+        )
+    })
+}
+pub fn is_on_path(dir: &Path) -> bool {
+    std::env::var_os("PATH")
+        .map(|paths| std::env::split_paths(&paths).any(|p| p == dir))
+        .unwrap_or(false)
+}
+/// Startup file of the user's login shell, based on `$SHELL`
+pub fn shell_profile() -> Option<PathBuf> {
+    let home = PathBuf::from(std::env::var_os("HOME")?);
+    let shell = std::env::var("SHELL").unwrap_or_default();
+    let profile = if shell.ends_with("zsh") {
+        home.join(".zshrc")
+    } else if shell.ends_with("fish") {
+        home.join(".config").join("fish").join("config.fish")
+    } else if shell.ends_with("bash") {
+        if cfg!(target_os = "macos") {
+            home.join(".bash_profile")
+        } else {
+            home.join(".bashrc")
+        }
+    } else {
+        home.join(".profile")
+    };
+    Some(profile)
+}
+/// Appends a PATH entry for `dir` to `profile`; returns false if it was already there
+pub fn add_to_shell_profile(profile: &Path, dir: &Path) -> Result<bool> {
+    let line = if profile.extension().and_then(|e| e.to_str()) == Some("fish") {
+        format!("fish_add_path \"{}\"", dir.display())
+    } else {
+        format!("export PATH=\"{}:$PATH\"", dir.display())
+    };
+    let existing = fs::read_to_string(profile).unwrap_or_default();
+    if existing.lines().any(|l| l.trim() == line) {
+        return Ok(false);
+    }
+    if let Some(parent) = profile.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = fs::OpenOptions::new().create(true).append(true).open(profile)?;
+    if !existing.is_empty() && !existing.ends_with('\n') {
+        writeln!(file)?;
+    }
+    writeln!(file, "# Added by symor")?;
+    writeln!(file, "{}", line)?;
+    Ok(true)
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+    #[test]
+    fn test_add_to_shell_profile_once() {
+        let temp_dir = tempdir().unwrap();
+        let profile = temp_dir.path().join(".bashrc");
+        fs::write(&profile, "alias ll='ls -l'").unwrap();
+        let dir = Path::new("/home/me/.local/bin");
+        assert!(add_to_shell_profile(&profile, dir).unwrap());
+        assert!(! add_to_shell_profile(&profile, dir).unwrap());
+        let content = fs::read_to_string(&profile).unwrap();
+        assert!(content.starts_with("alias ll='ls -l'\n# Added by symor\n"));
+        assert!(content.contains("export PATH=\"/home/me/.local/bin:$PATH\""));
+    }
+}
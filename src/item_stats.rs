@@ -0,0 +1,98 @@
+use crate::{errors::Result, SymorManager, WatchedItem};
+use std::{fs, time::SystemTime};
+/// What the stored versions of one watched item add up to, for `sym stats --item`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ItemStats {
+    pub versions: usize,
+    pub first_backup: Option<SystemTime>,
+    pub last_backup: Option<SystemTime>,
+    /// Versions per day between the first and last backup, taken over at least a day;
+    /// `None` with fewer than two
+    pub backups_per_day: Option<f64>,
+    pub average_size: u64,
+    /// Original and compressed bytes of the versions whose metadata is still stored
+    pub original_bytes: u64,
+    pub stored_bytes: u64,
+    /// Bytes per day the item grew (or shrank) from its first version to its latest
+    pub growth_per_day: Option<f64>,
+    /// Latest of the last backup and the item's own modification time
+    pub last_activity: Option<SystemTime>,
+}
+impl ItemStats {
+    /// Stored bytes per original byte, as in `StorageStats`
+    pub fn compression_ratio(&self) -> Option<f64> {
+        (self.original_bytes > 0).then(|| self.stored_bytes as f64 / self.original_bytes as f64)
+    }
+}
+fn days_between(from: SystemTime, to: SystemTime) -> f64 {
+    to.duration_since(from).unwrap_or_default().as_secs_f64() / 86_400.0
+}
+impl SymorManager {
+    /// Statistics for `item` from its versions and their stored metadata
+    pub fn item_stats(&self, item: &WatchedItem) -> Result<ItemStats> {
+        let mut versions: Vec<_> = item.versions.iter().collect();
+        versions.sort_by_key(|version| version.timestamp);
+        let (first, last) = (versions.first(), versions.last());
+        let span = first
+            .zip(last)
+            .filter(|_| versions.len() > 1)
+            .map(|(first, last)| days_between(first.timestamp, last.timestamp).max(1.0));
+        let (mut original_bytes, mut stored_bytes) = (0, 0);
+        for version in &versions {
+            if let Ok(metadata) = self.version_storage.load_metadata(&version.id) {
+                original_bytes += metadata.size;
+                stored_bytes += metadata.compressed_size;
+            }
+        }
+        let total: u64 = versions.iter().map(|version| version.size).sum();
+        let modified = fs::metadata(&item.path).and_then(|m| m.modified()).ok();
+        Ok(ItemStats {
+            versions: versions.len(),
+            first_backup: first.map(|version| version.timestamp),
+            last_backup: last.map(|version| version.timestamp),
+            backups_per_day: span.map(|days| (versions.len() - 1) as f64 / days),
+            average_size: total.checked_div(versions.len() as u64).unwrap_or(0),
+            original_bytes,
+            stored_bytes,
+            growth_per_day: first
+                .zip(last)
+                .zip(span)
+                .map(|((first, last), days)| (last.size as f64 - first.size as f64) / days),
+            last_activity: last.map(|version| version.timestamp).max(modified),
+        })
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tempfile::tempdir;
+    #[test]
+    fn test_item_stats() {
+        let temp_dir = tempdir().unwrap();
+        let mut manager = SymorManager::new().unwrap();
+        manager.config.home_dir = temp_dir.path().join("home");
+        manager.version_storage = crate::versioning::storage::VersionStorage::with_config(
+            crate::versioning::storage::StorageConfig {
+                storage_path: temp_dir.path().join("home/versions"),
+                ..Default::default()
+            },
+        );
+        let path = temp_dir.path().join("notes.txt");
+        fs::write(&path, "a".repeat(100)).unwrap();
+        let id = manager.watch_only(path.clone(), false, Vec::new(), false).unwrap();
+        fs::write(&path, "a".repeat(300)).unwrap();
+        manager.create_backup(&id).unwrap();
+        let item = manager.watched_item_mut(&id).unwrap();
+        item.versions[0].timestamp -= Duration::from_secs(2 * 86_400);
+        let stats = manager.item_stats(manager.watched_item(&id).unwrap()).unwrap();
+        assert_eq!(stats.versions, 2);
+        assert_eq!(stats.average_size, 200);
+        assert_eq!(stats.original_bytes, 400);
+        assert!(stats.compression_ratio().unwrap() < 1.0);
+        let per_day = stats.backups_per_day.unwrap();
+        assert!((per_day - 0.5).abs() < 0.01, "{}", per_day);
+        assert!((stats.growth_per_day.unwrap() - 100.0).abs() < 1.0);
+        assert!(stats.last_activity >= stats.last_backup);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,431 @@
+use crate::{
+    config::MetadataFormat, errors::{Context, Result}, sync_state::FileStamp, FileVersion,
+    WatchedItem,
+};
+use log::{error, info};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+    collections::HashMap, fs, ops::{Deref, DerefMut}, path::{Path, PathBuf},
+    sync::{Mutex, OnceLock},
+};
+/// What this process last read from or wrote to an item or versions file
+#[derive(Debug, Clone)]
+struct KnownFile {
+    id: String,
+    stamp: Option<FileStamp>,
+    /// `None` for a versions file not read through this store
+    digest: Option<md5::Digest>,
+}
+/// Watched items kept one per file under `items/` in the home directory, each with its
+/// versions in a file of its own under `items/versions/`. Saving after a backup rewrites
+/// only the items that changed, reloading only re-reads files another process touched,
+/// and a version list is only read once something uses it
+#[derive(Debug, Default)]
+pub(crate) struct ItemStore {
+    known: Mutex<HashMap<PathBuf, KnownFile>>,
+}
+/// The versions of a watched item. Items loaded from `items/` start with only the path of
+/// their versions file, which is read on first access
+#[derive(Debug, Clone)]
+pub struct Versions {
+    list: OnceLock<Vec<FileVersion>>,
+    /// Versions file the list is read from, with a digest of what was read
+    file: Option<(PathBuf, OnceLock<md5::Digest>)>,
+}
+static NO_VERSIONS: Vec<FileVersion> = Vec::new();
+impl Versions {
+    fn stored_in(path: PathBuf) -> Self {
+        Self {
+            list: OnceLock::new(),
+            file: Some((path, OnceLock::new())),
+        }
+    }
+    /// Left out of an item file, as the list has a file of its own
+    fn separate() -> Self {
+        Self { list: OnceLock::new(), file: None }
+    }
+    pub(crate) fn is_separate(&self) -> bool {
+        self.list.get().is_none() && self.file.is_none()
+    }
+    /// The list, first read from the versions file if it was not yet
+    pub fn load(&self) -> Result<&Vec<FileVersion>> {
+        if let Some(list) = self.list.get() {
+            return Ok(list);
+        }
+        let Some((path, digest)) = &self.file else {
+            return Ok(self.list.get_or_init(Vec::new));
+        };
+        let data = fs::read(path).with_context(|| format!("cannot read versions {:?}", path))?;
+        let format = MetadataFormat::from_path(path).unwrap_or_default();
+        let list = format
+            .decode(&data)
+            .with_context(|| format!("cannot read versions {:?}", path))?;
+        let _ = digest.set(md5::compute(&data));
+        Ok(self.list.get_or_init(|| list))
+    }
+    /// Whether the list is in memory, so using it does not read a file
+    pub fn is_loaded(&self) -> bool {
+        self.list.get().is_some()
+    }
+    /// The list to write to the versions file; `None` while it is unread, or when its
+    /// file could not be read, so a partial list never replaces the stored one
+    fn to_save(&self) -> Option<&Vec<FileVersion>> {
+        match &self.file {
+            Some((_, digest)) if digest.get().is_none() => None,
+            _ => Some(self.list.get().unwrap_or(&NO_VERSIONS)),
+        }
+    }
+    /// Digest of the versions file `path` as this list was read from it
+    fn read_digest(&self, path: &Path) -> Option<md5::Digest> {
+        let (_, digest) = self.file.as_ref().filter(|(file, _)| file == path)?;
+        digest.get().copied()
+    }
+}
+impl Default for Versions {
+    fn default() -> Self {
+        Vec::new().into()
+    }
+}
+impl From<Vec<FileVersion>> for Versions {
+    fn from(list: Vec<FileVersion>) -> Self {
+        Self { list: OnceLock::from(list), file: None }
+    }
+}
+/// Reads the list on first use; a file that cannot be read counts as no versions
+impl Deref for Versions {
+    type Target = Vec<FileVersion>;
+    fn deref(&self) -> &Vec<FileVersion> {
+        self.load().unwrap_or_else(|e| {
+            error!("{}", e);
+            &NO_VERSIONS
+        })
+    }
+}
+/// Like `deref`, except that a list whose file cannot be read is never saved
+impl DerefMut for Versions {
+    fn deref_mut(&mut self) -> &mut Vec<FileVersion> {
+        if let Err(e) = self.load() {
+            error!("{}; changes to these versions will not be saved", e);
+            let _ = self.list.set(Vec::new());
+        }
+        self.list.get_mut().expect("versions are loaded")
+    }
+}
+impl<'a> IntoIterator for &'a Versions {
+    type Item = &'a FileVersion;
+    type IntoIter = std::slice::Iter<'a, FileVersion>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+impl<'a> IntoIterator for &'a mut Versions {
+    type Item = &'a mut FileVersion;
+    type IntoIter = std::slice::IterMut<'a, FileVersion>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+impl Serialize for Versions {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        self.load().map_err(serde::ser::Error::custom)?.serialize(serializer)
+    }
+}
+impl<'de> Deserialize<'de> for Versions {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        Vec::deserialize(deserializer).map(Self::from)
+    }
+}
+pub fn items_dir(home_dir: &Path) -> PathBuf {
+    home_dir.join("items")
+}
+/// `<id>.json` or `<id>.msgpack`, or a hash of the id when it is not safe as a file name
+fn item_file(home_dir: &Path, id: &str, format: MetadataFormat) -> PathBuf {
+    let safe = !id.is_empty() && !id.starts_with('.')
+        && id.chars().all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c));
+    let name = if safe { id.to_string() } else { format!("{:x}", md5::compute(id)) };
+    items_dir(home_dir).join(format!("{}.{}", name, format.extension()))
+}
+/// File under `items/versions/` holding the versions of the item in `item_file`
+fn versions_file(item_file: &Path) -> PathBuf {
+    let dir = item_file.parent().unwrap_or(Path::new("")).join("versions");
+    dir.join(item_file.file_name().unwrap_or_default())
+}
+/// `item` without its versions, as its item file holds it
+fn header(item: &WatchedItem) -> WatchedItem {
+    WatchedItem {
+        id: item.id.clone(),
+        path: item.path.clone(),
+        is_directory: item.is_directory,
+        recursive: item.recursive,
+        versions: Versions::separate(),
+        created_at: item.created_at,
+        last_modified: item.last_modified,
+        only: item.only.clone(),
+        skip_hidden: item.skip_hidden,
+        paused: item.paused,
+        name: item.name.clone(),
+        description: item.description.clone(),
+    }
+}
+/// Writes `data` to `path` through a temporary file, readable only by the owner
+fn write_private(path: &Path, data: &[u8]) -> Result<()> {
+    #[cfg(unix)]
+    use std::os::unix::fs::PermissionsExt;
+    let ext = path.extension().map(|ext| ext.to_string_lossy().into_owned()).unwrap_or_default();
+    let tmp = path.with_extension(format!("{}.tmp", ext));
+    fs::write(&tmp, data)?;
+    let mut perms = fs::metadata(&tmp)?.permissions();
+    #[cfg(unix)] perms.set_mode(0o600);
+    fs::set_permissions(&tmp, perms)?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+impl ItemStore {
+    /// Brings `items` up to date with the item files, first moving the items of a
+    /// single `mirror.json` from older versions into files of their own. Only item files
+    /// whose stamp changed are read; version lists are left to be read on first use, and
+    /// one another process changed is dropped so it is read again
+    pub fn load(&self, home_dir: &Path, items: &mut HashMap<String, WatchedItem>) -> Result<()> {
+        let legacy = home_dir.join("mirror.json");
+        if legacy.exists() {
+            let migrated: HashMap<String, WatchedItem> = serde_json::from_str(
+                &fs::read_to_string(&legacy)?,
+            )?;
+            fs::create_dir_all(items_dir(home_dir).join("versions"))?;
+            for (id, item) in &migrated {
+                let saved = MetadataFormat::ALL
+                    .iter()
+                    .any(|format| item_file(home_dir, id, *format).exists());
+                if !saved {
+                    let path = item_file(home_dir, id, MetadataFormat::Json);
+                    let versions = serde_json::to_vec_pretty(&item.versions)?;
+                    write_private(&versions_file(&path), &versions)?;
+                    write_private(&path, &serde_json::to_vec_pretty(&header(item))?)?;
+                }
+            }
+            fs::rename(&legacy, home_dir.join("mirror.json.migrated"))?;
+            info!("Moved {} watched item(s) from {:?} to {:?}", migrated.len(), legacy, items_dir(home_dir));
+        }
+        if !items_dir(home_dir).exists() {
+            return Ok(());
+        }
+        let mut known = self.known.lock().unwrap_or_else(|e| e.into_inner());
+        let mut present = HashMap::new();
+        for entry in fs::read_dir(items_dir(home_dir))? {
+            let path = entry?.path();
+            let Some(format) = MetadataFormat::from_path(&path) else {
+                continue;
+            };
+            let stamp = FileStamp::of(&path);
+            let versions_path = versions_file(&path);
+            let versions_stamp = FileStamp::of(&versions_path);
+            let cached = known
+                .get(&path)
+                .filter(|file| {
+                    file.stamp.is_some() && file.stamp == stamp && items.contains_key(&file.id)
+                })
+                .map(|file| file.id.clone());
+            if let Some(id) = cached {
+                let seen = known.get(&versions_path).map(|file| file.stamp);
+                if versions_stamp.is_some() && seen != Some(versions_stamp) {
+                    let item = items.get_mut(&id).expect("cached item is loaded");
+                    item.versions = Versions::stored_in(versions_path.clone());
+                    let file = KnownFile { id: id.clone(), stamp: versions_stamp, digest: None };
+                    known.insert(versions_path, file);
+                }
+                present.insert(id, path);
+                continue;
+            }
+            let data = fs::read(&path)?;
+            let mut item: WatchedItem = format.decode(&data)?;
+            if versions_stamp.is_some() {
+                item.versions = Versions::stored_in(versions_path.clone());
+                let file = KnownFile { id: item.id.clone(), stamp: versions_stamp, digest: None };
+                known.insert(versions_path, file);
+            }
+            let file = KnownFile {
+                id: item.id.clone(),
+                stamp,
+                digest: Some(md5::compute(&data)),
+            };
+            present.insert(item.id.clone(), path.clone());
+            known.insert(path, file);
+            items.insert(item.id.clone(), item);
+        }
+        items.retain(|id, _| present.contains_key(id));
+        known.retain(|path, file| {
+            present
+                .get(&file.id)
+                .is_some_and(|item_path| item_path == path || versions_file(item_path) == *path)
+        });
+        Ok(())
+    }
+    /// Writes the items and read version lists whose contents changed since they were
+    /// last read or written, in `format`, and removes the files of items this process
+    /// knew about that are gone. Files other processes added in the meantime are left
+    /// alone.
+    pub fn save(
+        &self,
+        home_dir: &Path,
+        items: &HashMap<String, WatchedItem>,
+        format: MetadataFormat,
+    ) -> Result<()> {
+        fs::create_dir_all(items_dir(home_dir).join("versions"))?;
+        let mut known = self.known.lock().unwrap_or_else(|e| e.into_inner());
+        for (id, item) in items {
+            let path = item_file(home_dir, id, format);
+            let data = format.encode(&header(item))?;
+            Self::write_changed(&mut known, id, &path, &data, None)?;
+            let versions_path = versions_file(&path);
+            let list = match (item.versions.to_save(), &item.versions.file) {
+                (Some(list), _) => Some(list),
+                (None, Some((file, _))) if *file != versions_path => {
+                    (!item.versions.is_loaded()).then(|| item.versions.load()).transpose()?
+                }
+                (None, _) => None,
+            };
+            if let Some(list) = list {
+                let data = format.encode(list)?;
+                let read = item.versions.read_digest(&versions_path);
+                Self::write_changed(&mut known, id, &versions_path, &data, read)?;
+            }
+            for other in MetadataFormat::ALL.into_iter().filter(|other| *other != format) {
+                let stale = item_file(home_dir, id, other);
+                let stale_versions = versions_file(&stale);
+                if list.is_some() && stale_versions.exists() {
+                    fs::remove_file(&stale_versions)?;
+                    known.remove(&stale_versions);
+                }
+                if stale.exists() {
+                    fs::remove_file(&stale)?;
+                }
+                known.remove(&stale);
+            }
+        }
+        let gone: Vec<PathBuf> = known
+            .iter()
+            .filter(|(_, file)| !items.contains_key(&file.id))
+            .map(|(path, _)| path.clone())
+            .collect();
+        for path in gone {
+            if path.exists() {
+                fs::remove_file(&path)?;
+            }
+            known.remove(&path);
+        }
+        Ok(())
+    }
+    /// Writes `data` to `path` unless it already holds it, as last read (`read`) or written
+    fn write_changed(
+        known: &mut HashMap<PathBuf, KnownFile>,
+        id: &str,
+        path: &Path,
+        data: &[u8],
+        read: Option<md5::Digest>,
+    ) -> Result<()> {
+        let digest = md5::compute(data);
+        let last = known.get(path).and_then(|file| file.digest).or(read);
+        if last == Some(digest) && path.exists() {
+            return Ok(());
+        }
+        write_private(path, data)?;
+        let file = KnownFile {
+            id: id.to_string(),
+            stamp: FileStamp::of(path),
+            digest: Some(digest),
+        };
+        known.insert(path.to_path_buf(), file);
+        Ok(())
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::SystemTime;
+    use tempfile::tempdir;
+    fn item(id: &str) -> WatchedItem {
+        WatchedItem {
+            id: id.to_string(),
+            path: PathBuf::from(format!("/data/{}", id)),
+            is_directory: false,
+            recursive: false,
+            versions: Default::default(),
+            created_at: SystemTime::now(),
+            last_modified: SystemTime::now(),
+            only: Vec::new(),
+            skip_hidden: false,
+            paused: false,
+            name: None,
+            description: None,
+        }
+    }
+    #[test]
+    fn test_item_store() {
+        let temp_dir = tempdir().unwrap();
+        let home = temp_dir.path();
+        let legacy: HashMap<String, WatchedItem> = ["a", "b/c"]
+            .into_iter()
+            .map(|id| (id.to_string(), item(id)))
+            .collect();
+        fs::write(home.join("mirror.json"), serde_json::to_string(&legacy).unwrap()).unwrap();
+        let store = ItemStore::default();
+        let mut items = HashMap::new();
+        store.load(home, &mut items).unwrap();
+        assert_eq!(items.len(), 2);
+        assert!(!home.join("mirror.json").exists() && home.join("mirror.json.migrated").exists());
+        let json = MetadataFormat::Json;
+        assert!(item_file(home, "a", json).ends_with("items/a.json"));
+        assert!(!item_file(home, "b/c", json).ends_with("b/c.json"));
+        let unchanged = FileStamp::of(&item_file(home, "a", json));
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        store.save(home, &items, json).unwrap();
+        assert_eq!(FileStamp::of(&item_file(home, "a", json)), unchanged);
+        items.get_mut("a").unwrap().paused = true;
+        items.remove("b/c");
+        let other = ItemStore::default();
+        other.save(home, &HashMap::from([("d".to_string(), item("d"))]), json).unwrap();
+        store.save(home, &items, json).unwrap();
+        assert!(!item_file(home, "b/c", json).exists() && item_file(home, "d", json).exists());
+        assert!(!versions_file(&item_file(home, "b/c", json)).exists());
+        let mut reloaded = HashMap::new();
+        ItemStore::default().load(home, &mut reloaded).unwrap();
+        assert_eq!(reloaded.len(), 2);
+        assert!(reloaded["a"].paused);
+        store.load(home, &mut items).unwrap();
+        assert!(items.contains_key("d"));
+        let msgpack = MetadataFormat::Msgpack;
+        store.save(home, &items, msgpack).unwrap();
+        assert!(item_file(home, "a", msgpack).exists() && !item_file(home, "a", json).exists());
+        let mut reloaded = HashMap::new();
+        ItemStore::default().load(home, &mut reloaded).unwrap();
+        assert_eq!(reloaded.len(), 2);
+        assert!(reloaded["a"].paused);
+        let version = |id: &str| FileVersion {
+            id: id.to_string(),
+            timestamp: SystemTime::now(),
+            size: 1,
+            hash: String::new(),
+            path: PathBuf::from("/data/a"),
+            backup_path: None,
+        };
+        items.get_mut("a").unwrap().versions.push(version("v1"));
+        store.save(home, &items, msgpack).unwrap();
+        let reader = ItemStore::default();
+        let mut lazy = HashMap::new();
+        reader.load(home, &mut lazy).unwrap();
+        assert!(!lazy["a"].versions.is_loaded());
+        assert_eq!(lazy["a"].versions.len(), 1);
+        let header_stamp = FileStamp::of(&item_file(home, "a", msgpack));
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        reader.save(home, &lazy, msgpack).unwrap();
+        assert_eq!(FileStamp::of(&item_file(home, "a", msgpack)), header_stamp);
+        items.get_mut("a").unwrap().versions.push(version("v2"));
+        store.save(home, &items, msgpack).unwrap();
+        reader.load(home, &mut lazy).unwrap();
+        assert!(!lazy["a"].versions.is_loaded() && !lazy["d"].versions.is_loaded());
+        assert_eq!(lazy["a"].versions.len(), 2);
+        reader.save(home, &lazy, json).unwrap();
+        let d_versions = versions_file(&item_file(home, "d", json));
+        assert!(d_versions.exists() && !versions_file(&item_file(home, "d", msgpack)).exists());
+    }
+}
\ No newline at end of file
@@ -1,12 +1,12 @@
-use anyhow::{Context, Result};
+use errors::{Context, ErrorCode, Result, SymorError};
+use hooks::{VersionAction, VersionEvent};
 use log::{debug, error, info, warn};
-use notify::{
-    Config, Event, EventKind, RecommendedWatcher, RecursiveMode, Result as NotifyResult,
-    Watcher,
-};
+use notify::{Event, EventKind, RecommendedWatcher, Result as NotifyResult};
 use serde::{Deserialize, Serialize};
+use symlinks::SymlinkMode;
 use std::{
-    collections::HashMap, fs, path::{Path, PathBuf},
+    cell::{Cell, RefCell}, collections::{BTreeSet, HashMap}, ffi::OsString, fs,
+    path::{Path, PathBuf},
     sync::mpsc::{self, Receiver},
     time::{Duration, Instant, SystemTime},
 };
@@ -16,20 +16,66 @@ pub mod config;
 pub mod errors;
 pub mod performance;
 pub mod tui;
+pub mod install;
+pub mod daemon;
+pub mod linking;
+pub mod shadow_copy;
+pub mod conflicts;
+pub mod doctor;
+pub mod builder;
+pub mod events;
+pub mod path_filter;
+pub mod housekeeping;
+pub mod rate_limit;
+pub mod storm;
+pub mod sync_state;
+pub mod merge;
+pub mod quarantine;
+pub mod dir_cache;
+pub mod item_store;
+pub mod item_stats;
+pub mod hooks;
+pub mod file_rules;
+pub mod maintenance;
+pub mod report;
+pub mod retention;
+pub mod secrets;
+pub mod priority;
+pub mod bench;
+pub mod compare;
+pub mod dated;
+pub mod case;
+pub mod normalize;
+pub mod long_path;
+pub mod symlinks;
+pub mod ownership;
+#[cfg(feature = "scripting")]
+pub mod rules;
+pub use builder::{DeletePolicy, MirrorBuilder};
+pub use events::{EventHub, SyncEvent};
+pub use item_store::Versions;
+pub use path_filter::PathFilter;
 fn copy_dir_all(src: &Path, dst: &Path) -> Result<()> {
-    if !src.is_dir() {
-        return Err(anyhow::anyhow!("Source is not a directory: {:?}", src));
+    if !long_path::extend(src).is_dir() {
+        return Err(
+            SymorError::new(
+                ErrorCode::InvalidPath,
+                format!("Source is not a directory: {:?}", src),
+            ),
+        );
     }
-    fs::create_dir_all(dst)
+    fs::create_dir_all(long_path::extend(dst))
         .with_context(|| format!("cannot create destination directory {:?}", dst))?;
-    for entry in fs::read_dir(src)
+    for entry in fs::read_dir(long_path::extend(src))
         .with_context(|| format!("cannot read source directory {:?}", src))?
     {
         let entry = entry
             .with_context(|| format!("cannot read directory entry in {:?}", src))?;
-        let src_path = entry.path();
+        let src_path = src.join(entry.file_name());
         let dst_path = dst.join(entry.file_name());
-        if src_path.is_dir() {
+        if linking::is_reparse_point(&src_path) {
+            linking::copy_link(&src_path, &dst_path)?;
+        } else if long_path::extend(&src_path).is_dir() {
             copy_dir_all(&src_path, &dst_path)
                 .with_context(|| {
                     format!("cannot copy subdirectory {:?} to {:?}", src_path, dst_path)
@@ -43,49 +89,284 @@ fn copy_dir_all(src: &Path, dst: &Path) -> Result<()> {
     }
     Ok(())
 }
+/// Total size of the files under `path`, not following links
+fn tree_size(path: &Path) -> u64 {
+    let path = long_path::extend(path);
+    match fs::symlink_metadata(&path) {
+        Ok(metadata) if metadata.is_dir() => {
+            fs::read_dir(&path)
+                .map(|entries| entries.flatten().map(|e| tree_size(&e.path())).sum())
+                .unwrap_or(0)
+        }
+        Ok(metadata) if metadata.is_file() => metadata.len(),
+        _ => 0,
+    }
+}
 #[cfg(test)]
 mod tests;
 const DEBOUNCE_DELAY: Duration = Duration::from_millis(100);
+/// Mirror targets at least this large are patched with a block delta rather than
+/// rewritten, which for databases and disk images saves most of the writes
+const DELTA_MIN_SIZE: u64 = 8 * 1024 * 1024;
+const DELTA_BLOCK_SIZE: usize = 64 * 1024;
+/// Whether the files at `a` and `b` have the same size and hash
+fn same_contents(a: &Path, b: &Path) -> bool {
+    let size = |path: &Path| fs::metadata(long_path::extend(path)).map(|m| m.len()).ok();
+    let hash = |path: &Path| performance::memory::hash_file(path).ok();
+    size(a).is_some() && size(a) == size(b) && hash(a).is_some() && hash(a) == hash(b)
+}
+/// Whether `a` and `b`, or their nearest existing ancestors, are on the same volume
+fn same_volume(a: &Path, b: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        let device = |path: &Path| {
+            path.ancestors().find_map(|dir| fs::metadata(dir).ok()).map(|m| m.dev())
+        };
+        device(a).is_some() && device(a) == device(b)
+    }
+    #[cfg(not(unix))]
+    {
+        a.components().next() == b.components().next()
+    }
+}
+/// Whether `path` is large enough for block deltas, which also read it through a map
+fn is_large(path: &Path) -> bool {
+    fs::metadata(long_path::extend(path)).is_ok_and(|m| m.len() >= DELTA_MIN_SIZE)
+}
 pub struct Mirror {
     src: PathBuf,
+    /// `target_templates` expanded for the current date
     targets: Vec<PathBuf>,
+    /// Targets as given, possibly with strftime tokens
+    target_templates: Vec<PathBuf>,
+    /// Re-expand dated targets while running, so syncs move on to the new date
+    rollover: bool,
+    /// Dated copies of each dated target to keep, 0 for all
+    keep_rotations: usize,
+    /// Hard link files unchanged since the previous dated copy instead of copying them
+    link_rotations: bool,
+    symlinks: symlinks::SymlinkMode,
+    /// Directories entered through followed links in the sync in progress, to stop loops
+    following: RefCell<Vec<PathBuf>>,
+    /// Previous dated copy of each of `targets`, where there is one to link from
+    previous_targets: Vec<Option<PathBuf>>,
     rx: Receiver<NotifyResult<Event>>,
     _watcher: RecommendedWatcher,
+    link_type: linking::LinkType,
+    shadow_copy: bool,
     bidirectional: bool,
+    metrics_home: Option<PathBuf>,
+    excludes: Vec<glob::Pattern>,
+    filter: PathFilter,
+    debounce: Duration,
+    verify: bool,
+    delete_policy: DeletePolicy,
+    /// Move replaced and deleted target files to the OS trash instead of deleting them
+    trash: bool,
+    /// 0 for no limit
+    max_syncs_per_minute: u32,
+    /// Change events per second that count as a storm; 0 never does
+    storm_threshold: u32,
+    file_rules: file_rules::FileRules,
+    #[cfg(feature = "scripting")]
+    rules: Option<std::sync::Arc<rules::SyncRules>>,
+    events: EventHub,
+    /// Set while syncs are on hold, e.g. by the daemon's control API
+    paused: std::sync::Arc<std::sync::atomic::AtomicBool>,
+    /// Files and bytes written by the sync in progress
+    copied: Cell<(usize, u64)>,
+    /// Absolute paths of two-way mirror files whose edits overlap, left alone until
+    /// the conflict is resolved
+    held: RefCell<BTreeSet<PathBuf>>,
+    case_collisions: case::CaseCollisions,
+    /// Match names that differ only in Unicode normal form (NFC, NFD) between source and
+    /// targets, keeping the form each side already has
+    normalize_unicode: bool,
+    /// Whether the source and each target fold case, once a sync had to know
+    folds_case: RefCell<HashMap<PathBuf, bool>>,
+    /// Source paths whose case collisions were already reported
+    case_reported: RefCell<BTreeSet<PathBuf>>,
+    /// With ownership preserved, the rules mapping source owners to target owners
+    owners: Option<ownership::OwnerMap>,
+    /// Set once changing an owner was refused, so the sync stops trying
+    owners_denied: Cell<bool>,
+    /// What to do when copying a file fails: retry it, skip it or give up
+    recovery: errors::ErrorRecovery,
+    /// Config of the version store and home to free space in when a target's disk is full
+    reclaim_config: Option<SymorConfig>,
 }
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct SymorConfig {
     pub home_dir: PathBuf,
     pub versioning: VersioningConfig,
     pub linking: LinkingConfig,
+    #[serde(default)]
+    pub recovery: errors::RecoveryConfig,
+    #[serde(default)]
+    pub tui: tui::TuiConfig,
+    /// Default for new watches and mirrors: ignore dot-files and dot-directories
+    /// (`.cache`, `.venv`, editor swap files) inside them
+    #[serde(default)]
+    pub skip_hidden: bool,
+    /// Commands run when a version is created, restored or pruned
+    #[serde(default, skip_serializing_if = "hooks::HooksConfig::is_empty")]
+    pub hooks: hooks::HooksConfig,
+    /// Per-file decisions to version, mirror, compress or skip by path, extension, size
+    /// and age
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub file_rules: Vec<file_rules::FileRule>,
+    /// Daily or weekly activity summaries from the daemon and where they are sent
+    #[serde(default, skip_serializing_if = "monitoring::summary::NotificationsConfig::is_empty")]
+    pub notifications: monitoring::summary::NotificationsConfig,
+    #[serde(default)]
+    pub performance: performance::PerformanceConfig,
 }
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct VersioningConfig {
     pub enabled: bool,
     pub max_versions: usize,
     pub compression: u8,
+    /// Read files locked by other processes from a Volume Shadow Copy (Windows only)
+    #[serde(default)]
+    pub shadow_copy: bool,
+    /// Days to keep the `.pre-restore` copies that restores leave next to files; 0
+    /// keeps them until removed by hand
+    #[serde(default = "default_pre_restore_days")]
+    pub pre_restore_days: u64,
+    /// Encoding of the watched item files and the hash cache; config stays JSON or YAML
+    #[serde(default)]
+    pub metadata_format: config::MetadataFormat,
+    /// Days during which a version cannot be deleted by any command; 0 for no lock
+    #[serde(default)]
+    pub retention_lock_days: u64,
+    /// Look for private keys and access tokens in files before versioning them
+    #[serde(default)]
+    pub secret_scan: secrets::SecretScan,
+    /// Whether a watched symlink is versioned as the file it points to (the default), as
+    /// the link itself so restores recreate it, or not at all
+    #[serde(default)]
+    pub symlinks: symlinks::SymlinkMode,
+}
+fn default_normalize_unicode() -> bool {
+    true
+}
+fn default_pre_restore_days() -> u64 {
+    7
 }
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct LinkingConfig {
     pub link_type: String,
     pub preserve_permissions: bool,
+    /// Give restored files back their owner from backup time, and mirrored files the
+    /// owner of their source (needs root)
+    #[serde(default)]
+    pub preserve_ownership: bool,
+    /// `ownership::OwnerMap` rules, like `1000->33`, changing the owners preserved
+    /// ownership gives, for restores and mirrors run as another user or in a container
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub owner_map: Vec<String>,
+    /// Move target files that mirroring replaces or deletes, and purged versions, to
+    /// the OS trash
+    #[serde(default)]
+    pub trash: bool,
+    /// Most syncs a mirror runs in any minute, so a source that never settles (log
+    /// files, build output) cannot keep it re-syncing; 0 for no limit
+    #[serde(default)]
+    pub max_syncs_per_minute: u32,
+    /// What directory syncs do with source names that differ only in case when the
+    /// target's file system does not tell them apart
+    #[serde(default)]
+    pub case_collisions: case::CaseCollisions,
+    /// Treat file names that differ only in Unicode normal form, as macOS (NFD) and
+    /// Linux (NFC) write them, as the same name when syncing and detecting changes
+    #[serde(default = "default_normalize_unicode")]
+    pub normalize_unicode: bool,
+}
+impl Default for VersioningConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_versions: 10,
+            compression: 6,
+            shadow_copy: false,
+            pre_restore_days: default_pre_restore_days(),
+            metadata_format: Default::default(),
+            retention_lock_days: 0,
+            secret_scan: Default::default(),
+            symlinks: Default::default(),
+        }
+    }
+}
+impl Default for LinkingConfig {
+    fn default() -> Self {
+        Self {
+            link_type: "copy".to_string(),
+            preserve_permissions: true,
+            preserve_ownership: false,
+            owner_map: Vec::new(),
+            trash: false,
+            max_syncs_per_minute: 0,
+            case_collisions: Default::default(),
+            normalize_unicode: default_normalize_unicode(),
+        }
+    }
 }
 impl Default for SymorConfig {
     fn default() -> Self {
         Self {
             home_dir: get_default_home_dir(),
-            versioning: VersioningConfig {
-                enabled: true,
-                max_versions: 10,
-                compression: 6,
-            },
-            linking: LinkingConfig {
-                link_type: "copy".to_string(),
-                preserve_permissions: true,
-            },
+            versioning: VersioningConfig::default(),
+            linking: LinkingConfig::default(),
+            recovery: errors::RecoveryConfig::default(),
+            tui: tui::TuiConfig::default(),
+            skip_hidden: false,
+            hooks: hooks::HooksConfig::default(),
+            file_rules: Vec::new(),
+            notifications: Default::default(),
+            performance: Default::default(),
         }
     }
 }
+/// A persisted mirror relationship, run in the background by the daemon
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MirrorSpec {
+    pub source: PathBuf,
+    pub targets: Vec<PathBuf>,
+    #[serde(default)]
+    pub bidirectional: bool,
+    /// `--only` patterns; files inside a mirrored directory that match none are skipped
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub only: Vec<String>,
+    /// Leave hidden files and directories inside the source out of the mirror
+    #[serde(default)]
+    pub skip_hidden: bool,
+    /// Held by `sym pause`; the daemon does not sync it until resumed
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub paused: bool,
+    /// Re-expand dated targets (`/backup/%F/etc`) when the date changes while the daemon
+    /// runs, instead of only when the mirror starts
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub rollover: bool,
+    /// Dated copies of each dated target to keep; older ones are removed as the date
+    /// moves on. `None` keeps them all
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keep_rotations: Option<usize>,
+    /// Hard link files of a new dated copy that did not change since the previous copy
+    /// instead of copying them, as rsnapshot does
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub link_rotations: bool,
+    /// Whether symlinks inside the source are recreated, followed or left out
+    #[serde(default, skip_serializing_if = "symlinks::SymlinkMode::is_default")]
+    pub symlinks: symlinks::SymlinkMode,
+    /// Change events per second after which the mirror waits for the burst to settle
+    /// and rescans once; `None` for the default, 0 to handle every event
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub storm_threshold: Option<u32>,
+}
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileVersion {
     pub id: String,
@@ -102,9 +383,39 @@ pub struct WatchedItem {
     pub path: PathBuf,
     pub is_directory: bool,
     pub recursive: bool,
-    pub versions: Vec<FileVersion>,
+    /// Read from the item's versions file on first access
+    #[serde(default, skip_serializing_if = "Versions::is_separate")]
+    pub versions: Versions,
     pub created_at: SystemTime,
     pub last_modified: SystemTime,
+    /// `--only` patterns limiting which files of a watched directory are tracked
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub only: Vec<String>,
+    /// Ignore hidden files and directories inside a watched directory
+    #[serde(default)]
+    pub skip_hidden: bool,
+    /// Held by `sym pause`; the daemon does not version it until resumed
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub paused: bool,
+    /// Alias set with `sym watch --name`, accepted wherever an ID or path is
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Free-text note set with `sym annotate`, e.g. who owns the file and why it is watched
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+/// Which part of the watched items `SymorManager::list_watched_page` prints
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ListPage {
+    /// 1-based
+    pub page: usize,
+    /// Items per page; 0 lists them all
+    pub per_page: usize,
+}
+impl Default for ListPage {
+    fn default() -> Self {
+        Self { page: 1, per_page: 0 }
+    }
 }
 pub struct SymorManager {
     config: SymorConfig,
@@ -112,9 +423,55 @@ pub struct SymorManager {
     change_detector: versioning::detector::ChangeDetector,
     version_storage: versioning::storage::VersionStorage,
     restore_engine: versioning::restore::RestoreEngine,
+    validate_on_load: bool,
+    config_source: Option<PathBuf>,
+    active_environment: Option<String>,
+    mirrors: Vec<MirrorSpec>,
+    item_store: item_store::ItemStore,
+    version_hooks: hooks::VersionHooks,
+    secret_hooks: secrets::SecretHooks,
+    /// Cleanup epoch of the version store when the watched items were last loaded
+    store_epoch: u64,
+    /// Versions pruned from the watched items while the store was held shared, with
+    /// their item IDs; deleted by `delete_pruned` once it can be held exclusively
+    pruned: Vec<(String, FileVersion)>,
+}
+static HOME_OVERRIDE: std::sync::RwLock<Option<PathBuf>> = std::sync::RwLock::new(None);
+/// Marker file that switches a binary's install directory into portable mode
+pub const PORTABLE_MARKER: &str = "symor.portable";
+/// Environment variable that points symor at another home directory, like `--home`
+pub const HOME_ENV: &str = "SYMOR_HOME";
+/// Redirects all symor state to `home_dir` for the rest of the process
+pub fn set_home_override(home_dir: Option<PathBuf>) {
+    *HOME_OVERRIDE.write().unwrap_or_else(|e| e.into_inner()) = home_dir;
+}
+/// Home directory set with `set_home_override`, or else through `SYMOR_HOME`
+pub fn home_override() -> Option<PathBuf> {
+    let home_dir = HOME_OVERRIDE.read().unwrap_or_else(|e| e.into_inner()).clone();
+    home_dir.or_else(|| {
+        let dir = std::env::var_os(HOME_ENV).filter(|dir| !dir.is_empty())?;
+        std::path::absolute(dir).ok()
+    })
+}
+/// State directory next to the running binary, used by portable mode
+pub fn portable_home_dir() -> Option<PathBuf> {
+    let exe = std::env::current_exe().ok()?;
+    Some(exe.parent()?.join(".symor"))
+}
+/// Portable state directory if a marker file sits next to the binary
+pub fn detect_portable_home() -> Option<PathBuf> {
+    let exe = std::env::current_exe().ok()?;
+    let exe_dir = exe.parent()?;
+    if exe_dir.join(PORTABLE_MARKER).is_file() {
+        Some(exe_dir.join(".symor"))
+    } else {
+        None
+    }
 }
 pub fn get_default_home_dir() -> PathBuf {
-    if let Ok(home) = std::env::var("HOME") {
+    if let Some(home_dir) = home_override() {
+        home_dir
+    } else if let Ok(home) = std::env::var("HOME") {
         PathBuf::from(home).join(".symor")
     } else if let Ok(user) = std::env::var("USERPROFILE") {
         PathBuf::from(user).join(".symor")
@@ -136,286 +493,957 @@ impl Mirror {
         targets: Vec<PathBuf>,
         bidirectional: bool,
     ) -> Result<Self> {
-        let src = src.into();
-        let (tx, rx) = mpsc::channel();
-        let mut watcher = RecommendedWatcher::new(tx, Config::default())
-            .context("failed to initialise file‑watcher")?;
-        let recursive_mode = if src.is_dir() {
-            RecursiveMode::Recursive
-        } else {
-            RecursiveMode::NonRecursive
+        Self::builder(src).targets(targets).bidirectional(bidirectional).build()
+    }
+    /// Starts configuring a mirror of `src`; see `MirrorBuilder`
+    pub fn builder(src: impl Into<PathBuf>) -> MirrorBuilder {
+        MirrorBuilder::new(src)
+    }
+    /// Attaches targets as links of this kind instead of copies
+    pub fn with_link_type(mut self, link_type: linking::LinkType) -> Self {
+        self.link_type = link_type;
+        self
+    }
+    /// Reads a locked source through a shadow copy instead of failing (Windows only)
+    pub fn with_shadow_copy(mut self, shadow_copy: bool) -> Self {
+        self.shadow_copy = shadow_copy;
+        self
+    }
+    /// Counts syncs, bytes copied and failures in the metrics store under `home_dir`
+    pub fn with_metrics(mut self, home_dir: impl Into<PathBuf>) -> Self {
+        self.metrics_home = Some(home_dir.into());
+        self
+    }
+    /// Receiver for this mirror's sync events; subscribe before calling `run`
+    pub fn subscribe(&self) -> std::sync::mpsc::Receiver<SyncEvent> {
+        self.events.subscribe()
+    }
+    /// Calls `callback` on the mirror's thread for each sync event
+    pub fn on_event(&self, callback: impl Fn(&SyncEvent) + Send + 'static) {
+        self.events.on_event(callback);
+    }
+    fn file_copied(&self, path: &Path, bytes: u64) {
+        let (files, total) = self.copied.get();
+        self.copied.set((files + 1, total + bytes));
+        if self.events.has_listeners() {
+            self.events
+                .emit(SyncEvent::FileCopied {
+                    path: path.to_path_buf(),
+                    bytes,
+                });
+        }
+    }
+    /// Runs `sync` between SyncStarted and SyncCompleted/SyncFailed events, then
+    /// records it in the metrics
+    fn run_sync(&self, sync: impl FnOnce(&Self) -> Result<()>) -> Result<()> {
+        let started = Instant::now();
+        self.copied.set((0, 0));
+        self.events
+            .emit(SyncEvent::SyncStarted {
+                source: self.src.clone(),
+            });
+        if self.bidirectional {
+            self.merge_edits();
+        }
+        let result = sync(self);
+        match &result {
+            Ok(()) => {
+                self.record_sync_state();
+                let (files, bytes) = self.copied.get();
+                self.events
+                    .emit(SyncEvent::SyncCompleted {
+                        source: self.src.clone(),
+                        files,
+                        bytes,
+                        duration: started.elapsed(),
+                    });
+            }
+            Err(e) => {
+                self.events
+                    .emit(SyncEvent::SyncFailed {
+                        source: self.src.clone(),
+                        error: e.to_string(),
+                    })
+            }
+        }
+        self.record_sync(result)
+    }
+    /// Remembers each copied file's size and mtime on both sides, so `find_conflicts`
+    /// can tell later edits apart, and for a two-way mirror the synced text as the base
+    /// for merging them; kept alongside the metrics
+    fn record_sync_state(&self) {
+        let Some(home_dir) = &self.metrics_home else {
+            return;
         };
-        watcher
-            .watch(&src, recursive_mode)
-            .with_context(|| format!("cannot watch source {:?}", src))?;
-        if bidirectional {
-            for target in &targets {
-                if target.exists() {
-                    let target_recursive_mode = if target.is_dir() {
-                        RecursiveMode::Recursive
-                    } else {
-                        RecursiveMode::NonRecursive
-                    };
-                    watcher
-                        .watch(target, target_recursive_mode)
-                        .with_context(|| format!("cannot watch target {:?}", target))?;
-                    println!("Target watcher created successfully");
-                } else {
-                    println!(
-                        "Target does not exist, skipping bidirectional watch: {:?}",
-                        target
-                    );
+        if self.link_type != linking::LinkType::Copy {
+            return;
+        }
+        // Keyed by absolute paths, as the registry stores them
+        let absolute = |path: &PathBuf| std::path::absolute(path).unwrap_or_else(|_| path.clone());
+        let src = absolute(&self.src);
+        let mut pairs = Vec::new();
+        for target in self.targets.iter().map(absolute) {
+            if src.is_dir() {
+                let mut files = BTreeSet::new();
+                conflicts::relative_files(&src, &src, &mut files);
+                for relative in files {
+                    if !self.is_excluded(&self.src.join(&relative)) {
+                        pairs.push((src.join(&relative), target.join(relative)));
+                    }
                 }
+            } else {
+                pairs.push((src.clone(), target));
             }
         }
-        Ok(Self {
-            src,
-            targets,
-            rx,
-            _watcher: watcher,
-            bidirectional,
-        })
-    }
-    fn sync_once(&self) -> Result<()> {
-        if self.src.is_dir() {
-            for tgt in &self.targets {
-                if let Some(parent) = tgt.parent() {
-                    fs::create_dir_all(parent)
-                        .with_context(|| {
-                            format!("cannot create directory {:?}", parent)
-                        })?;
+        let previous = sync_state::SyncState::load(home_dir, &src).unwrap_or_default();
+        let mut state = sync_state::SyncState::new(&src);
+        state.synced_at = Some(SystemTime::now());
+        for (source, target) in pairs {
+            let synced = previous.files.get(&target);
+            if self.is_held(&target) {
+                if let Some(pair) = synced {
+                    state.files.insert(target, pair.clone());
                 }
-                if tgt.exists() {
-                    let metadata = fs::metadata(tgt)
-                        .with_context(|| format!("cannot get metadata for {:?}", tgt))?;
-                    if metadata.is_dir() {
-                        fs::remove_dir_all(tgt)
-                            .with_context(|| {
-                                format!("cannot remove existing directory {:?}", tgt)
-                            })?;
-                    } else {
-                        fs::remove_file(tgt)
-                            .with_context(|| {
-                                format!("cannot remove existing file {:?}", tgt)
-                            })?;
-                    }
+                continue;
+            }
+            state.record(&source, &target);
+            if self.bidirectional && state.files.get(&target) != synced {
+                if let Err(e) = sync_state::save_base(home_dir, &target) {
+                    warn!("cannot keep merge base for {:?}: {}", target, e);
                 }
-                fs::create_dir_all(tgt)
-                    .with_context(|| {
-                        format!("cannot create target directory {:?}", tgt)
-                    })?;
-                for entry in fs::read_dir(&self.src)
-                    .with_context(|| {
-                        format!("cannot read source directory {:?}", self.src)
-                    })?
-                {
-                    let entry = entry
-                        .with_context(|| {
-                            format!("cannot read directory entry in {:?}", self.src)
-                        })?;
-                    let src_path = entry.path();
-                    let file_name = entry.file_name();
-                    let dst_path = tgt.join(file_name);
-                    if src_path.is_dir() {
-                        copy_dir_all(&src_path, &dst_path)
-                            .with_context(|| {
-                                format!(
-                                    "cannot copy subdirectory {:?} to {:?}", src_path, dst_path
-                                )
-                            })?;
-                    } else {
-                        fs::copy(&src_path, &dst_path)
-                            .with_context(|| {
-                                format!("cannot copy file {:?} to {:?}", src_path, dst_path)
-                            })?;
+            }
+        }
+        if let Err(e) = state.save(home_dir) {
+            warn!("cannot save sync state for {:?}: {}", self.src, e);
+        }
+    }
+    /// For a two-way mirror, merges files both sides edited since the last sync when
+    /// their edits do not overlap, and holds back the others so neither side's edits
+    /// are overwritten
+    fn merge_edits(&self) {
+        self.held.borrow_mut().clear();
+        let Some(home_dir) = &self.metrics_home else {
+            return;
+        };
+        let src = std::path::absolute(&self.src).unwrap_or_else(|_| self.src.clone());
+        let state = match sync_state::SyncState::load(home_dir, &src) {
+            Some(state) if self.link_type == linking::LinkType::Copy => state,
+            _ => return,
+        };
+        let mut held = BTreeSet::new();
+        for (target, pair) in &state.files {
+            let source = &pair.source;
+            if state.edits(source, target) != Some((true, true))
+                || conflicts::same_contents(source, target)
+            {
+                continue;
+            }
+            if let Some(merge::LineMerge::Clean(merged)) = state.merge(source, target) {
+                let written = self
+                    .write_file(merged.as_bytes(), source)
+                    .and_then(|_| self.write_file(merged.as_bytes(), target));
+                match written {
+                    Ok(()) => {
+                        info!("merged edits to {:?} and {:?}", source, target);
+                        continue;
                     }
+                    Err(e) => warn!("cannot merge {:?} and {:?}: {}", source, target, e),
                 }
             }
+            #[cfg(feature = "scripting")]
+            if self.resolve_by_rules(source, target) {
+                continue;
+            }
+            match quarantine::quarantine(home_dir, source, target) {
+                Ok(Some(entry)) => {
+                    warn!(
+                        "{:?} and {:?} were both edited since the last sync; copies are in {:?}, settle it with `sym conflicts resolve {}`",
+                        source, target, entry.dir, entry.id
+                    )
+                }
+                Ok(None) => {}
+                Err(e) => warn!("cannot quarantine {:?} and {:?}: {}", source, target, e),
+            }
+            held.insert(source.clone());
+            held.insert(target.clone());
+            self.events
+                .emit(
+                    SyncEvent::ConflictDetected(conflicts::Conflict {
+                        kind: conflicts::ConflictKind::Diverged,
+                        source: source.clone(),
+                        target: Some(target.clone()),
+                        item_id: None,
+                        edited: Some(conflicts::EditedSide::Both),
+                    }),
+                );
+        }
+        *self.held.borrow_mut() = held;
+    }
+    /// Settles an edit conflict with the sync rules' `resolve`, if they pick a resolution
+    #[cfg(feature = "scripting")]
+    fn resolve_by_rules(&self, source: &Path, target: &Path) -> bool {
+        let Some(rules) = &self.rules else {
+            return false;
+        };
+        let conflict = conflicts::Conflict {
+            kind: conflicts::ConflictKind::Diverged,
+            source: source.to_path_buf(),
+            target: Some(target.to_path_buf()),
+            item_id: None,
+            edited: Some(conflicts::EditedSide::Both),
+        };
+        let Some(resolution) = rules.resolution(&conflict) else {
+            return false;
+        };
+        match conflicts::resolve_files(&conflict, resolution) {
+            Ok(message) => {
+                info!("sync rules settled a conflict: {}", message);
+                true
+            }
+            Err(e) => {
+                warn!("cannot apply {:?} from the sync rules: {}", resolution, e);
+                false
+            }
+        }
+    }
+    /// True when `path` is a file `merge_edits` held back from this sync
+    fn is_held(&self, path: &Path) -> bool {
+        let held = self.held.borrow();
+        !held.is_empty()
+            && std::path::absolute(path).is_ok_and(|path| held.contains(&path))
+    }
+    /// Reports targets that differ from the source before the initial sync replaces them
+    fn detect_conflicts(&self) {
+        if !self.events.has_listeners() || self.link_type != linking::LinkType::Copy {
+            return;
+        }
+        let absolute = |path: &PathBuf| std::path::absolute(path).unwrap_or_else(|_| path.clone());
+        let spec = MirrorSpec {
+            source: absolute(&self.src),
+            targets: self.targets.iter().map(absolute).collect(),
+            bidirectional: self.bidirectional,
+            only: Vec::new(),
+            skip_hidden: false,
+            paused: false,
+            storm_threshold: None,
+            rollover: false,
+            keep_rotations: None,
+            link_rotations: false,
+            symlinks: Default::default(),
+        };
+        let state = self
+            .metrics_home
+            .as_deref()
+            .and_then(|home_dir| sync_state::SyncState::load(home_dir, &spec.source));
+        for conflict in conflicts::mirror_conflicts(&spec, state.as_ref()) {
+            let relative = conflict.source.strip_prefix(&spec.source).unwrap_or(Path::new(""));
+            if !self.is_excluded(&self.src.join(relative)) {
+                self.events.emit(SyncEvent::ConflictDetected(conflict));
+            }
+        }
+    }
+    /// Updates this mirror's status file for `sym top`, when metrics are enabled
+    fn record_status(&self, update: impl FnOnce(&mut monitoring::status::MirrorStatus)) {
+        if let Some(home_dir) = &self.metrics_home {
+            monitoring::status::update(home_dir, &self.src, update);
+        }
+    }
+    fn record_sync(&self, result: Result<()>) -> Result<()> {
+        let Some(home_dir) = &self.metrics_home else {
+            return result;
+        };
+        self.record_status(|status| {
+            status.queued = 0;
+            match &result {
+                Ok(()) => {
+                    status.syncs += 1;
+                    status.last_sync = Some(SystemTime::now());
+                }
+                Err(e) => {
+                    status.errors += 1;
+                    status.last_error = Some(e.to_string());
+                    status.last_error_at = Some(SystemTime::now());
+                }
+            }
+        });
+        let copied = if result.is_ok() && self.link_type == linking::LinkType::Copy {
+            tree_size(&self.src) * self.targets.len() as u64
         } else {
-            let data = fs::read(&self.src)
-                .with_context(|| format!("cannot read source file {:?}", self.src))?;
-            for tgt in &self.targets {
-                if let Some(parent) = tgt.parent() {
-                    fs::create_dir_all(parent)
-                        .with_context(|| {
-                            format!("cannot create directory {:?}", parent)
-                        })?;
+            0
+        };
+        monitoring::metrics::record(
+            home_dir,
+            |bucket| {
+                if result.is_ok() {
+                    bucket.syncs += 1;
+                    bucket.bytes_synced += copied;
+                } else {
+                    bucket.errors += 1;
                 }
-                if tgt.exists() {
-                    let metadata = fs::metadata(tgt)
-                        .with_context(|| format!("cannot get metadata for {:?}", tgt))?;
-                    if metadata.is_dir() {
-                        fs::remove_dir_all(tgt)
-                            .with_context(|| {
-                                format!("cannot remove existing directory {:?}", tgt)
-                            })?;
+            },
+        );
+        result
+    }
+    fn link_targets(&self) -> Result<()> {
+        for tgt in &self.targets {
+            if linking::is_linked(&self.src, tgt) {
+                continue;
+            }
+            if let Some(parent) = tgt.parent() {
+                fs::create_dir_all(long_path::extend(parent))
+                    .with_context(|| format!("cannot create directory {:?}", parent))?;
+            }
+            self.discard(tgt)?;
+            let created = linking::create_link(&self.src, tgt, self.link_type)?;
+            info!("Linked {:?} → {:?} ({})", tgt, self.src, created);
+        }
+        Ok(())
+    }
+    /// True when `path`, relative to the source or a target, or its file name matches
+    /// an exclude pattern, or when it is inside the tree and the filter leaves it out
+    fn is_excluded(&self, path: &Path) -> bool {
+        let is_dir = || long_path::extend(path).is_dir();
+        if !self.file_rules.is_empty() && !is_dir() {
+            let skipped = std::iter::once(&self.src)
+                .chain(&self.targets)
+                .filter_map(|root| path.strip_prefix(root).ok())
+                .any(|relative| {
+                    !relative.as_os_str().is_empty()
+                        && !self.file_rules.decide(path, relative).mirror
+                });
+            if skipped {
+                return true;
+            }
+        }
+        #[cfg(feature = "scripting")]
+        if let Some(rules) = self.rules.as_ref().filter(|_| !is_dir()) {
+            let skipped = std::iter::once(&self.src)
+                .chain(&self.targets)
+                .filter_map(|root| path.strip_prefix(root).ok())
+                .any(|relative| {
+                    !relative.as_os_str().is_empty() && !rules.includes(path, relative)
+                });
+            if skipped {
+                return true;
+            }
+        }
+        if self.excludes.is_empty() && self.filter.is_empty() {
+            return false;
+        }
+        let name = path.file_name().map(|n| n.to_string_lossy().into_owned());
+        std::iter::once(&self.src)
+            .chain(&self.targets)
+            .filter_map(|root| path.strip_prefix(root).ok())
+            .any(|relative| {
+                self.excludes
+                    .iter()
+                    .any(|pattern| {
+                        pattern.matches_path(relative)
+                            || name.as_deref().is_some_and(|n| pattern.matches(n))
+                    })
+                    || (!relative.as_os_str().is_empty()
+                        && if is_dir() {
+                            !self.filter.walks(relative)
+                        } else {
+                            !self.filter.includes(relative)
+                        })
+            })
+    }
+    /// Checks that `copy` has the same content as `original` when verification is on
+    fn verify_copy(&self, original: &[u8], copy: &Path) -> Result<()> {
+        if !self.verify {
+            return Ok(());
+        }
+        let written = performance::MappedFile::open(copy)
+            .with_context(|| format!("cannot verify {:?}", copy))?;
+        if md5::compute(&written[..]) != md5::compute(original) {
+            return Err(
+                SymorError::new(
+                    ErrorCode::InternalError,
+                    format!("verification failed: {:?} does not match its source", copy),
+                ),
+            );
+        }
+        Ok(())
+    }
+    /// Deletes a target path, or moves it to the trash when that is enabled
+    fn discard(&self, path: &Path) -> Result<()> {
+        linking::discard_path(path, self.trash)
+    }
+    /// With trash enabled, moves the file at `to` to the trash before it is overwritten
+    /// with different content
+    fn trash_if_changed(&self, to: &Path, data: &[u8]) -> Result<()> {
+        let long_to = long_path::extend(to);
+        if self.trash && long_to.is_file() && fs::read(&long_to).is_ok_and(|old| old != data) {
+            self.discard(to)?;
+        }
+        Ok(())
+    }
+    /// The copy of target file `to` in the previous dated copy of its target
+    fn previous_copy(&self, to: &Path) -> Option<PathBuf> {
+        self.targets
+            .iter()
+            .zip(&self.previous_targets)
+            .find_map(|(target, previous)| {
+                let relative = to.strip_prefix(target).ok()?;
+                let previous = previous.as_ref()?;
+                Some(
+                    if relative.as_os_str().is_empty() {
+                        previous.clone()
                     } else {
-                        fs::remove_file(tgt)
-                            .with_context(|| {
-                                format!("cannot remove existing file {:?}", tgt)
-                            })?;
-                    }
-                }
-                let tmp = tgt.with_extension("tmp-sync");
-                fs::write(&tmp, &data)
-                    .with_context(|| format!("cannot write temporary file {:?}", tmp))?;
-                fs::rename(&tmp, tgt)
-                    .with_context(|| format!("cannot atomically replace {:?}", tgt))?;
+                        previous.join(relative)
+                    },
+                )
+            })
+    }
+    /// With linked rotations, hard links the new target file `to` to its copy in the
+    /// previous dated copy when `unchanged` finds that copy still matches; true if linked
+    fn link_rotation(&self, to: &Path, unchanged: impl FnOnce(&Path) -> bool) -> bool {
+        if !self.link_rotations || fs::symlink_metadata(long_path::extend(to)).is_ok() {
+            return false;
+        }
+        let previous = self.previous_copy(to);
+        let Some(previous) = previous.filter(|p| long_path::extend(p).is_file()) else {
+            return false;
+        };
+        if !unchanged(&previous) {
+            return false;
+        }
+        if let Some(parent) = to.parent() {
+            let _ = fs::create_dir_all(long_path::extend(parent));
+        }
+        match linking::create_link(&previous, to, linking::LinkType::Hard) {
+            Ok(_) => {
+                debug!("Linked {:?} to unchanged {:?}", to, previous);
+                true
+            }
+            Err(e) => {
+                debug!("cannot link {:?} to {:?}, copying: {e}", to, previous);
+                false
             }
         }
+    }
+    /// With linked rotations, checks a target file `to` that earlier dated copies may
+    /// share: true when `unchanged` finds it up to date, otherwise it is removed so the
+    /// sync does not write into those copies
+    fn unshare(&self, to: &Path, unchanged: impl FnOnce(&Path) -> bool) -> Result<bool> {
+        let long_to = long_path::extend(to);
+        if !self.link_rotations || !long_to.is_file() {
+            return Ok(false);
+        }
+        #[cfg(unix)]
+        let shared = {
+            use std::os::unix::fs::MetadataExt;
+            fs::metadata(&long_to).is_ok_and(|m| m.nlink() > 1)
+        };
+        #[cfg(not(unix))]
+        let shared = true;
+        if !shared {
+            return Ok(false);
+        }
+        if unchanged(to) {
+            return Ok(true);
+        }
+        fs::remove_file(&long_to).with_context(|| format!("cannot unlink {:?}", to))?;
+        Ok(false)
+    }
+    /// Copies `from` over `to`, freeing space and trying once more if the disk is full
+    fn copy_file(&self, from: &Path, to: &Path) -> Result<()> {
+        errors::recovery::retry_when_full(
+            &mut (),
+            |_| self.copy_contents(from, to),
+            |_| Ok(self.free_space(to)),
+        )
+    }
+    fn copy_contents(&self, from: &Path, to: &Path) -> Result<()> {
+        if self.is_held(to) {
+            return Ok(());
+        }
+        let unchanged = |copy: &Path| same_contents(from, copy);
+        if self.link_rotation(to, unchanged) || self.unshare(to, unchanged)? {
+            return Ok(());
+        }
+        let (long_from, long_to) = (long_path::extend(from), long_path::extend(to));
+        if is_large(from) && !self.trash && long_to.is_file() {
+            let data = performance::MappedFile::open(from)
+                .with_context(|| format!("cannot read file {:?}", from))?;
+            self.write_contents(&data, to)?;
+            self.copy_owner(from, to);
+            return Ok(());
+        }
+        if self.trash {
+            let data = fs::read(&long_from)
+                .with_context(|| format!("cannot read file {:?}", from))?;
+            self.trash_if_changed(to, &data)?;
+        }
+        let bytes = fs::copy(&long_from, &long_to)
+            .with_context(|| format!("cannot copy file {:?} to {:?}", from, to))?;
+        if self.verify {
+            let original = fs::read(&long_from)
+                .with_context(|| format!("cannot read file {:?}", from))?;
+            self.verify_copy(&original, to)?;
+        }
+        self.copy_owner(from, to);
+        self.file_copied(to, bytes);
         Ok(())
     }
-    fn sync_from_target(&self, target_path: &Path) -> Result<()> {
-        if target_path.is_dir() {
-            if self.src.exists() {
-                if self.src.is_dir() {
-                    fs::remove_dir_all(&self.src)
-                        .with_context(|| {
-                            format!(
-                                "cannot remove existing source directory {:?}", self.src
-                            )
-                        })?;
-                } else {
-                    fs::remove_file(&self.src)
-                        .with_context(|| {
-                            format!("cannot remove existing source file {:?}", self.src)
-                        })?;
+    /// With ownership preserved, gives `to` the owner of `from` as the owner map maps it.
+    /// A refusal is reported once, as later files would be refused too.
+    fn copy_owner(&self, from: &Path, to: &Path) {
+        let Some(owners) = &self.owners else {
+            return;
+        };
+        if self.owners_denied.get() {
+            return;
+        }
+        let Some(owner) = versioning::storage::file_owner(&long_path::extend(from)) else {
+            return;
+        };
+        match ownership::set_owner(&long_path::extend(to), owners.map(owner)) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => {
+                warn!(
+                    "cannot give mirrored files the owners of their sources ({}): run as root \
+                     or map owners with 'sym settings linking --owner-map'", e
+                );
+                self.owners_denied.set(true);
+            }
+            Err(e) => debug!("cannot set the owner of {:?}: {}", to, e),
+        }
+    }
+    /// Writes `data` over `to`, freeing space and trying once more if the disk is full
+    fn write_file(&self, data: &[u8], to: &Path) -> Result<()> {
+        errors::recovery::retry_when_full(
+            &mut (),
+            |_| self.write_contents(data, to),
+            |_| Ok(self.free_space(to)),
+        )
+    }
+    /// Frees space for a write to `to` that hit a full disk, through a manager for the
+    /// config given to `MirrorBuilder::reclaim_space`; returns whether anything was freed
+    fn free_space(&self, to: &Path) -> bool {
+        let Some(config) = &self.reclaim_config else {
+            return false;
+        };
+        let freed = SymorManager::new().and_then(|mut manager| {
+            manager.config = config.clone();
+            manager.apply_loaded_config();
+            manager.load_watched_items()?;
+            manager.load_mirrors()?;
+            manager.reclaim_space_for(to)
+        });
+        freed.unwrap_or_else(|e| {
+            warn!("Cannot free space for {:?}: {}", to, e);
+            false
+        })
+    }
+    /// Writes `data` over `to` through a temporary file
+    fn write_contents(&self, data: &[u8], to: &Path) -> Result<()> {
+        if self.is_held(to) {
+            return Ok(());
+        }
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(long_path::extend(parent))
+                .with_context(|| format!("cannot create directory {:?}", parent))?;
+        }
+        let long_to = long_path::extend(to);
+        if long_to.is_dir() {
+            self.discard(to)?;
+        }
+        let unchanged = |copy: &Path| {
+            fs::metadata(long_path::extend(copy)).is_ok_and(|m| m.len() == data.len() as u64)
+                && performance::memory::hash_file(copy)
+                    .is_ok_and(|hash| hash == format!("{:x}", md5::compute(data)))
+        };
+        if self.link_rotation(to, unchanged) || self.unshare(to, unchanged)? {
+            return Ok(());
+        }
+        if !self.trash && data.len() as u64 >= DELTA_MIN_SIZE && long_to.is_file() {
+            let written = self.patch_file(data, to)?;
+            self.verify_copy(data, to)?;
+            self.file_copied(to, written);
+            return Ok(());
+        }
+        self.trash_if_changed(to, data)?;
+        let tmp = to.with_extension("tmp-sync");
+        if let Err(e) = fs::write(long_path::extend(&tmp), data) {
+            let _ = fs::remove_file(long_path::extend(&tmp));
+            return Err(e).with_context(|| format!("cannot write temporary file {:?}", tmp));
+        }
+        fs::rename(long_path::extend(&tmp), &long_to)
+            .with_context(|| format!("cannot atomically replace {:?}", to))?;
+        self.verify_copy(data, to)?;
+        self.file_copied(to, data.len() as u64);
+        Ok(())
+    }
+    /// Brings the existing file `to` up to `data` by writing only the blocks a delta
+    /// against it finds changed or moved; returns the bytes written
+    fn patch_file(&self, data: &[u8], to: &Path) -> Result<u64> {
+        let block_size = performance::memory::budget()
+            .delta_block_size(data.len() as u64, DELTA_BLOCK_SIZE);
+        performance::IncrementalSync::new(block_size)
+            .patch_file(to, data)
+            .map_err(|e| {
+                SymorError::new(ErrorCode::InternalError, format!("cannot patch {:?}: {}", to, e))
+            })
+    }
+    /// Whether the source or target tree holding `path` folds case, probed once
+    fn folds_case(&self, path: &Path) -> bool {
+        let root = std::iter::once(&self.src)
+            .chain(&self.targets)
+            .filter(|root| path.starts_with(root))
+            .max_by_key(|root| root.as_os_str().len())
+            .map_or(path, PathBuf::as_path);
+        *self
+            .folds_case
+            .borrow_mut()
+            .entry(root.to_path_buf())
+            .or_insert_with(|| case::folds_case(path))
+    }
+    /// Names in `to` for the entries of `from` that differ only in case, when `to` folds
+    /// case: `None` to leave one out, or the name `case_collisions` gives it. Reports
+    /// each collision once, as a conflict.
+    fn case_names(
+        &self,
+        from: &Path,
+        to: &Path,
+        names: &[OsString],
+    ) -> HashMap<OsString, Option<OsString>> {
+        let groups = case::collisions(names.iter().map(OsString::as_os_str));
+        let mut case_names = HashMap::new();
+        if groups.is_empty() || !self.folds_case(to) {
+            return case_names;
+        }
+        for group in groups {
+            for (n, name) in group.iter().enumerate().skip(1) {
+                let source = from.join(name);
+                if self.case_reported.borrow_mut().insert(source.clone()) {
+                    warn!(
+                        "{:?} and {:?} differ only in case, which {:?} cannot tell apart ({})",
+                        source, from.join(&group[0]), to, self.case_collisions
+                    );
+                    self.events
+                        .emit(
+                            SyncEvent::ConflictDetected(conflicts::Conflict {
+                                kind: conflicts::ConflictKind::CaseCollision,
+                                source,
+                                target: Some(to.join(&group[0])),
+                                item_id: None,
+                                edited: None,
+                            }),
+                        );
                 }
+                let renamed = match self.case_collisions {
+                    case::CaseCollisions::Skip => None,
+                    case::CaseCollisions::Rename => Some(case::numbered(name, n + 1)),
+                };
+                case_names.insert(name.clone(), renamed);
             }
-            if let Some(parent) = self.src.parent() {
-                fs::create_dir_all(parent)
-                    .with_context(|| {
-                        format!("cannot create source parent directory {:?}", parent)
-                    })?;
+        }
+        case_names
+    }
+    /// Entries of `from` that are not excluded
+    fn included_entries(&self, from: &Path) -> Result<Vec<fs::DirEntry>> {
+        let mut entries = Vec::new();
+        for entry in fs::read_dir(long_path::extend(from))
+            .with_context(|| format!("cannot read directory {:?}", from))?
+        {
+            let entry = entry
+                .with_context(|| format!("cannot read directory entry in {:?}", from))?;
+            if !self.is_excluded(&from.join(entry.file_name())) {
+                entries.push(entry);
             }
-            fs::create_dir_all(&self.src)
-                .with_context(|| {
-                    format!("cannot create source directory {:?}", self.src)
-                })?;
-            for entry in fs::read_dir(target_path)
-                .with_context(|| {
-                    format!("cannot read target directory {:?}", target_path)
-                })?
-            {
-                let entry = entry
-                    .with_context(|| {
-                        format!("cannot read directory entry in {:?}", target_path)
-                    })?;
-                let src_path = entry.path();
-                let file_name = entry.file_name();
-                let dst_path = self.src.join(file_name);
-                if src_path.is_dir() {
-                    copy_dir_all(&src_path, &dst_path)
-                        .with_context(|| {
-                            format!(
-                                "cannot copy subdirectory {:?} to {:?}", src_path, dst_path
-                            )
-                        })?;
-                } else {
-                    fs::copy(&src_path, &dst_path)
-                        .with_context(|| {
-                            format!("cannot copy file {:?} to {:?}", src_path, dst_path)
-                        })?;
-                }
+        }
+        Ok(entries)
+    }
+    /// Removes entries of `to` that `from` no longer has, keeping excluded ones
+    fn prune_tree(&self, from: &Path, to: &Path) -> Result<()> {
+        // Source names of entries renamed for a case collision, by their name in `to`
+        let mut renamed: Option<HashMap<OsString, OsString>> = None;
+        let mut respelled: Option<HashMap<PathBuf, OsString>> = None;
+        for entry in fs::read_dir(long_path::extend(to))
+            .with_context(|| format!("cannot read directory {:?}", to))?
+        {
+            let entry = entry
+                .with_context(|| format!("cannot read directory entry in {:?}", to))?;
+            let dst_path = to.join(entry.file_name());
+            if self.is_excluded(&dst_path) {
+                continue;
             }
-            for tgt in &self.targets {
-                if tgt != target_path {
-                    if let Some(parent) = tgt.parent() {
-                        fs::create_dir_all(parent)
-                            .with_context(|| {
-                                format!("cannot create directory {:?}", parent)
-                            })?;
+            let mut src_path = from.join(entry.file_name());
+            if fs::symlink_metadata(long_path::extend(&src_path)).is_err() {
+                let renamed = match &mut renamed {
+                    Some(renamed) => renamed,
+                    None => {
+                        let names: Vec<OsString> = self
+                            .included_entries(from)?
+                            .iter()
+                            .map(fs::DirEntry::file_name)
+                            .collect();
+                        renamed.insert(
+                            self.case_names(from, to, &names)
+                                .into_iter()
+                                .filter_map(|(name, renamed)| Some((renamed?, name)))
+                                .collect(),
+                        )
                     }
-                    if tgt.exists() {
-                        if tgt.is_dir() {
-                            fs::remove_dir_all(tgt)
-                                .with_context(|| {
-                                    format!("cannot remove existing directory {:?}", tgt)
-                                })?;
-                        } else {
-                            fs::remove_file(tgt)
-                                .with_context(|| {
-                                    format!("cannot remove existing file {:?}", tgt)
-                                })?;
+                };
+                if let Some(name) = renamed.get(&entry.file_name()) {
+                    src_path = from.join(name);
+                }
+            }
+            let name = entry.file_name();
+            let missing = fs::symlink_metadata(long_path::extend(&src_path)).is_err();
+            if missing && self.normalize_unicode && normalize::may_differ(&name) {
+                let respelled = respelled.get_or_insert_with(|| normalize::names_by_key(from));
+                if let Some(name) = respelled.get(normalize::key(Path::new(&name)).as_ref()) {
+                    src_path = from.join(name);
+                }
+            }
+            let is_dir = entry.file_type().is_ok_and(|t| t.is_dir());
+            let source_kind = match self.symlinks {
+                SymlinkMode::Follow => fs::metadata(long_path::extend(&src_path)),
+                _ => fs::symlink_metadata(long_path::extend(&src_path)),
+            };
+            let skipped = self.symlinks == SymlinkMode::Skip
+                && linking::is_reparse_point(&src_path);
+            let same_kind = !skipped && source_kind.is_ok_and(|m| m.is_dir() == is_dir);
+            if !same_kind {
+                self.discard(&dst_path)?;
+            } else if is_dir {
+                self.prune_tree(&src_path, &dst_path)?;
+            }
+        }
+        Ok(())
+    }
+    /// Copies the tree at `from` into `to`, skipping excluded paths
+    fn copy_tree(&self, from: &Path, to: &Path) -> Result<()> {
+        fs::create_dir_all(long_path::extend(to))
+            .with_context(|| format!("cannot create directory {:?}", to))?;
+        self.copy_owner(from, to);
+        let entries = self.included_entries(from)?;
+        let names: Vec<OsString> = entries.iter().map(fs::DirEntry::file_name).collect();
+        let case_names = self.case_names(from, to, &names);
+        // Entries of `to` whose names another Unicode normal form of a source name spells
+        let respell = self.normalize_unicode && names.iter().any(|n| normalize::may_differ(n));
+        let respelled = if respell {
+            normalize::names_by_key(to)
+        } else {
+            HashMap::new()
+        };
+        for (entry, name) in entries.into_iter().zip(names) {
+            let src_path = from.join(entry.file_name());
+            let dst_path = match case_names.get(&name) {
+                Some(None) => continue,
+                Some(Some(renamed)) => to.join(renamed),
+                None => {
+                    let exact = to.join(&name);
+                    match respelled.get(normalize::key(Path::new(&name)).as_ref()) {
+                        Some(existing)
+                            if fs::symlink_metadata(long_path::extend(&exact)).is_err() => {
+                            to.join(existing)
                         }
+                        _ => exact,
                     }
-                    fs::create_dir_all(tgt)
-                        .with_context(|| {
-                            format!("cannot create target directory {:?}", tgt)
-                        })?;
-                    for entry in fs::read_dir(&self.src)
-                        .with_context(|| {
-                            format!("cannot read source directory {:?}", self.src)
-                        })?
-                    {
-                        let entry = entry
-                            .with_context(|| {
-                                format!("cannot read directory entry in {:?}", self.src)
-                            })?;
-                        let src_path = entry.path();
-                        let file_name = entry.file_name();
-                        let dst_path = tgt.join(file_name);
-                        if src_path.is_dir() {
-                            copy_dir_all(&src_path, &dst_path)
-                                .with_context(|| {
-                                    format!(
-                                        "cannot copy subdirectory {:?} to {:?}", src_path, dst_path
-                                    )
-                                })?;
-                        } else {
-                            fs::copy(&src_path, &dst_path)
-                                .with_context(|| {
-                                    format!("cannot copy file {:?} to {:?}", src_path, dst_path)
-                                })?;
-                        }
+                }
+            };
+            let is_link = linking::is_reparse_point(&src_path);
+            if is_link {
+                match self.symlinks {
+                    SymlinkMode::Preserve => {
+                        self.copy_symlink(&src_path, &dst_path)?;
+                        continue;
+                    }
+                    SymlinkMode::Skip => continue,
+                    SymlinkMode::Follow if !long_path::extend(&src_path).exists() => {
+                        debug!("Skipping dangling link {:?}", src_path);
+                        continue;
                     }
+                    SymlinkMode::Follow if linking::is_reparse_point(&dst_path) => {
+                        self.discard(&dst_path)?;
+                    }
+                    SymlinkMode::Follow => {}
                 }
             }
-        } else {
-            let data = fs::read(target_path)
-                .with_context(|| format!("cannot read target file {:?}", target_path))?;
-            if let Some(parent) = self.src.parent() {
-                fs::create_dir_all(parent)
+            if long_path::extend(&src_path).is_dir() {
+                let entered = is_link && self.enter_link(from, &src_path);
+                if is_link && !entered {
+                    continue;
+                }
+                let copied = self
+                    .copy_tree(&src_path, &dst_path)
                     .with_context(|| {
-                        format!("cannot create source parent directory {:?}", parent)
-                    })?;
-            }
-            let tmp = self.src.with_extension("tmp-sync");
-            fs::write(&tmp, &data)
-                .with_context(|| format!("cannot write temporary file {:?}", tmp))?;
-            fs::rename(&tmp, &self.src)
-                .with_context(|| format!("cannot atomically replace {:?}", self.src))?;
-            for tgt in &self.targets {
-                if tgt != target_path {
-                    if let Some(parent) = tgt.parent() {
-                        fs::create_dir_all(parent)
-                            .with_context(|| {
-                                format!("cannot create directory {:?}", parent)
-                            })?;
+                        format!("cannot copy subdirectory {:?} to {:?}", src_path, dst_path)
+                    });
+                if entered {
+                    self.following.borrow_mut().pop();
+                }
+                copied?;
+            } else {
+                match self.recovery.run(|| self.copy_file(&src_path, &dst_path)) {
+                    Err(e) if self.recovery.skips(&e) => {
+                        warn!("skipping {:?}, cannot copy it: {}", src_path, e)
                     }
-                    let tmp = tgt.with_extension("tmp-sync");
-                    fs::write(&tmp, &data)
-                        .with_context(|| {
-                            format!("cannot write temporary file {:?}", tmp)
-                        })?;
-                    fs::rename(&tmp, tgt)
-                        .with_context(|| {
-                            format!("cannot atomically replace {:?}", tgt)
-                        })?;
+                    copied => copied?,
                 }
             }
         }
         Ok(())
     }
-    pub fn run(self) -> Result<()> {
-        self.sync_once().with_context(|| "initial sync failed")?;
+    /// Recreates the link `from` as `to`, replacing whatever `to` holds unless it is
+    /// already a link to the same place
+    fn copy_symlink(&self, from: &Path, to: &Path) -> Result<()> {
+        let (long_from, long_to) = (long_path::extend(from), long_path::extend(to));
+        if let Ok(existing) = fs::read_link(&long_to) {
+            if fs::read_link(&long_from).is_ok_and(|destination| destination == existing) {
+                return Ok(());
+            }
+        }
+        if fs::symlink_metadata(&long_to).is_ok() {
+            self.discard(to)?;
+        }
+        linking::copy_link(from, to)
+    }
+    /// With links followed, notes that the sync enters the directory the link `path` in
+    /// `from` leads to; false, leaving it out, when the sync is already inside it
+    fn enter_link(&self, from: &Path, path: &Path) -> bool {
+        let dir = fs::canonicalize(long_path::extend(path));
+        let (Ok(dir), Ok(parent)) = (dir, fs::canonicalize(long_path::extend(from))) else {
+            return false;
+        };
+        let mut following = self.following.borrow_mut();
+        if parent.starts_with(&dir) || following.contains(&dir) {
+            warn!("Not following {:?}, which leads back into {:?}", path, dir);
+            return false;
+        }
+        following.push(dir);
+        true
+    }
+    /// Makes the directory `to` a copy of `from` under the delete policy
+    fn mirror_tree(&self, from: &Path, to: &Path) -> Result<()> {
+        let long_to = long_path::extend(to);
+        if long_to.exists() && !long_to.is_dir() {
+            self.discard(to)?;
+        }
+        if long_to.is_dir() && self.delete_policy == DeletePolicy::Mirror {
+            self.prune_tree(from, to)?;
+        }
+        self.copy_tree(from, to)
+    }
+    fn sync_once(&self) -> Result<()> {
+        if self.link_type != linking::LinkType::Copy {
+            return self.link_targets();
+        }
+        if long_path::extend(&self.src).is_dir() {
+            for tgt in &self.targets {
+                self.mirror_tree(&self.src, tgt)?;
+            }
+        } else if !self.shadow_copy && is_large(&self.src) {
+            let data = performance::MappedFile::open(&self.src)
+                .with_context(|| format!("cannot read source file {:?}", self.src))?;
+            for tgt in &self.targets {
+                self.recovery.run(|| self.write_file(&data, tgt))?;
+                self.copy_owner(&self.src, tgt);
+            }
+        } else {
+            let data = shadow_copy::read_file(&self.src, self.shadow_copy)
+                .with_context(|| format!("cannot read source file {:?}", self.src))?;
+            for tgt in &self.targets {
+                self.recovery.run(|| self.write_file(&data, tgt))?;
+                self.copy_owner(&self.src, tgt);
+            }
+        }
+        Ok(())
+    }
+    fn sync_from_target(&self, target_path: &Path) -> Result<()> {
+        let others = self.targets.iter().filter(|tgt| *tgt != target_path);
+        if long_path::extend(target_path).is_dir() {
+            self.mirror_tree(target_path, &self.src)?;
+            for tgt in others {
+                self.mirror_tree(&self.src, tgt)?;
+            }
+        } else {
+            let data = fs::read(long_path::extend(target_path))
+                .with_context(|| format!("cannot read target file {:?}", target_path))?;
+            self.recovery.run(|| self.write_file(&data, &self.src))?;
+            self.copy_owner(target_path, &self.src);
+            for tgt in others {
+                self.recovery.run(|| self.write_file(&data, tgt))?;
+                self.copy_owner(target_path, tgt);
+            }
+        }
+        Ok(())
+    }
+    /// With rollover, expands the target templates for now and switches to the new
+    /// paths when they differ; true when they did
+    fn roll_over(&mut self) -> Result<bool> {
+        if !self.rollover {
+            return Ok(false);
+        }
+        let now = SystemTime::now();
+        let targets = self
+            .target_templates
+            .iter()
+            .map(|target| dated::expand(target, now))
+            .collect::<Result<Vec<_>>>()?;
+        if targets == self.targets {
+            return Ok(false);
+        }
+        info!("{:?} rolls over to {:?}", self.src, targets);
+        if self.link_rotations {
+            self.previous_targets = self
+                .targets
+                .iter()
+                .zip(&targets)
+                .map(|(old, new)| (old != new).then(|| old.clone()))
+                .collect();
+        }
+        self.targets = targets;
+        self.rotate();
+        Ok(true)
+    }
+    /// Removes the dated copies of each target beyond the newest `keep_rotations`,
+    /// counting the current one, through the trash when that is enabled
+    fn rotate(&self) {
+        if self.keep_rotations == 0 {
+            return;
+        }
+        for (template, target) in self.target_templates.iter().zip(&self.targets) {
+            let stale = match dated::stale_rotations(template, target, self.keep_rotations) {
+                Ok(stale) => stale,
+                Err(e) => {
+                    warn!("cannot rotate {:?}: {e}", template);
+                    continue;
+                }
+            };
+            for path in stale {
+                match self.discard(&path) {
+                    Ok(()) => info!("Removed old rotation {:?}", path),
+                    Err(e) => warn!("cannot remove old rotation {:?}: {e}", path),
+                }
+            }
+        }
+    }
+    pub fn run(mut self) -> Result<()> {
+        self.record_status(|status| status.targets = self.targets.len());
+        self.detect_conflicts();
+        self.run_sync(Self::sync_once).with_context(|| "initial sync failed")?;
+        self.rotate();
         info!("Watching {:?} → {} target(s)", self.src, self.targets.len());
+        let mut queued = 0;
         let mut pending = false;
         let mut last_event: Option<Event> = None;
         let mut debounce_deadline = Instant::now();
+        let mut limiter = rate_limit::SyncRateLimiter::new(self.max_syncs_per_minute);
+        let mut limited = false;
+        let mut storm = storm::StormDetector::new(self.storm_threshold);
+        // Source or targets the storm's events were under, to pick what to rescan from
+        let mut storm_roots: Vec<PathBuf> = Vec::new();
         loop {
             let timeout = if pending {
                 debounce_deadline.checked_duration_since(Instant::now())
+            } else if self.rollover {
+                Some(dated::ROLLOVER_CHECK)
             } else {
                 None
             };
@@ -425,52 +1453,123 @@ impl Mirror {
             {
                 Ok(Ok(ev)) => {
                     debug!("raw notify event: {:?}", ev);
-                    if Self::is_interesting(&ev) {
+                    if self.is_interesting(&ev) {
+                        let now = Instant::now();
+                        if storm.record(now) {
+                            warn!(
+                                "{:?} got more than {} changes in a second; rescanning once they stop",
+                                self.src, storm.threshold()
+                            );
+                            self.events
+                                .emit(SyncEvent::StormDetected {
+                                    source: self.src.clone(),
+                                    threshold: storm.threshold(),
+                                });
+                        }
+                        queued += 1;
                         pending = true;
-                        last_event = Some(ev);
-                        debounce_deadline = Instant::now() + DEBOUNCE_DELAY;
+                        if storm.in_storm() {
+                            for path in &ev.paths {
+                                let root = std::iter::once(&self.src)
+                                    .chain(&self.targets)
+                                    .find(|root| path.starts_with(root));
+                                if let Some(root) = root.filter(|root| !storm_roots.contains(root)) {
+                                    storm_roots.push(root.clone());
+                                }
+                            }
+                            if queued % 1000 == 0 {
+                                self.record_status(|status| status.queued = queued);
+                            }
+                            debounce_deadline = now + self.debounce.max(storm::SETTLE);
+                        } else {
+                            self.record_status(|status| status.queued = queued);
+                            last_event = Some(ev);
+                            debounce_deadline = now + self.debounce;
+                        }
                     }
                 }
                 Ok(Err(e)) => {
                     warn!("watcher error: {e:?}");
                 }
                 Err(mpsc::RecvTimeoutError::Timeout) => {
-                    if pending {
+                    match self.roll_over() {
+                        Ok(rolled_over) => pending |= rolled_over,
+                        Err(e) => error!("cannot expand dated targets: {e}"),
+                    }
+                    if pending && self.paused.load(std::sync::atomic::Ordering::SeqCst) {
+                        debounce_deadline = Instant::now() + self.debounce;
+                    } else if let (true, Err(retry_at)) = (pending, limiter.acquire(Instant::now())) {
+                        debounce_deadline = retry_at;
+                        if !limited {
+                            limited = true;
+                            warn!(
+                                "{:?} changes more than {} times a minute; coalescing changes for {}s",
+                                self.src, self.max_syncs_per_minute, retry_at
+                                .saturating_duration_since(Instant::now()).as_secs()
+                            );
+                            self.events
+                                .emit(SyncEvent::RateLimited {
+                                    source: self.src.clone(),
+                                    max_per_minute: self.max_syncs_per_minute,
+                                });
+                        }
+                    } else if pending && storm.in_storm() {
+                        let events = storm.finish().unwrap_or_default();
+                        let from_target = match storm_roots.as_slice() {
+                            [root] if self.bidirectional && root != &self.src => Some(root.clone()),
+                            _ => None,
+                        };
+                        let result = match &from_target {
+                            Some(target) => self.run_sync(|mirror| mirror.sync_from_target(target)),
+                            None => self.run_sync(Self::sync_once),
+                        };
+                        match result {
+                            Ok(_) => info!("rescanned {:?} after a storm of {} changes", self.src, events),
+                            Err(e) => error!("rescan after change storm failed: {e}"),
+                        }
+                        limited = false;
+                        pending = false;
+                        last_event = None;
+                        queued = 0;
+                        storm_roots.clear();
+                    } else if pending {
+                        limited = false;
                         if let Some(ev) = &last_event {
                             if self.bidirectional {
                                 let changed_path = &ev.paths[0];
                                 if changed_path == &self.src {
-                                    match self.sync_once() {
+                                    match self.run_sync(Self::sync_once) {
                                         Ok(_) => {
                                             info!("synced source to targets after {:?}", ev.kind)
                                         }
-                                        Err(e) => error!("sync failed: {e:?}"),
+                                        Err(e) => error!("sync failed: {e}"),
                                     }
                                 } else if self.targets.contains(changed_path) {
-                                    match self.sync_from_target(changed_path) {
+                                    match self.run_sync(|mirror| mirror.sync_from_target(changed_path)) {
                                         Ok(_) => {
                                             info!(
                                                 "synced target to source and other targets after {:?}", ev
                                                 .kind
                                             )
                                         }
-                                        Err(e) => error!("bidirectional sync failed: {e:?}"),
+                                        Err(e) => error!("bidirectional sync failed: {e}"),
                                     }
                                 }
                             } else {
-                                match self.sync_once() {
+                                match self.run_sync(Self::sync_once) {
                                     Ok(_) => info!("synced after {:?}", ev.kind),
-                                    Err(e) => error!("sync failed: {e:?}"),
+                                    Err(e) => error!("sync failed: {e}"),
                                 }
                             }
                         } else {
-                            match self.sync_once() {
+                            match self.run_sync(Self::sync_once) {
                                 Ok(_) => info!("synced"),
-                                Err(e) => error!("sync failed: {e:?}"),
+                                Err(e) => error!("sync failed: {e}"),
                             }
                         }
                         pending = false;
                         last_event = None;
+                        queued = 0;
                     }
                 }
                 Err(mpsc::RecvTimeoutError::Disconnected) => {
@@ -481,11 +1580,12 @@ impl Mirror {
         }
         Ok(())
     }
-    fn is_interesting(event: &Event) -> bool {
+    /// Changes worth a sync: not access events, and not only excluded paths
+    fn is_interesting(&self, event: &Event) -> bool {
         matches!(
             event.kind, EventKind::Modify(_) | EventKind::Create(_) |
             EventKind::Remove(_) | EventKind::Any
-        )
+        ) && (event.paths.is_empty() || !event.paths.iter().all(|p| self.is_excluded(p)))
     }
 }
 impl SymorManager {
@@ -498,20 +1598,51 @@ impl SymorManager {
             compression_level: 6,
             max_versions_per_file: 10,
             storage_path: config.home_dir.join("versions"),
+            compression_threads: config.performance.compression_threads(),
         };
         let version_storage = versioning::storage::VersionStorage::with_config(
             storage_config,
         );
+        match version_storage.migrate_flat_blobs() {
+            Ok(0) => {}
+            Ok(moved) => info!("Moved {} version file(s) into shard directories", moved),
+            Err(e) => warn!("Could not move version files into shard directories: {}", e),
+        }
         let restore_engine = versioning::restore::RestoreEngine::new()?;
-        let manager = Self {
+        let mut manager = Self {
             config,
             watched_items,
             change_detector,
             version_storage,
             restore_engine,
+            validate_on_load: true,
+            config_source: None,
+            active_environment: None,
+            mirrors: Vec::new(),
+            item_store: Default::default(),
+            version_hooks: Default::default(),
+            secret_hooks: Default::default(),
+            store_epoch: 0,
+            pruned: Vec::new(),
         };
+        manager.track_restore_backups();
         Ok(manager)
     }
+    /// Points the restore engine's backup log and retention at the current config
+    fn track_restore_backups(&mut self) {
+        let retention = match self.config.versioning.pre_restore_days {
+            0 => None,
+            days => Some(Duration::from_secs(days * 86_400)),
+        };
+        self.restore_engine
+            .track_backups(self.config.home_dir.join("restore-backups.json"), retention);
+    }
+    /// Deletes leftover restore temp files and `.pre-restore` copies past their retention,
+    /// returning how many were removed
+    pub fn cleanup_restore_files(&self) -> Result<usize> {
+        self.ensure_writable("remove old restore files")?;
+        self.restore_engine.cleanup_temp_files()
+    }
     pub fn setup_directory_structure(home_dir: &Path) -> Result<()> {
         #[cfg(unix)]
         use std::os::unix::fs::PermissionsExt;
@@ -540,96 +1671,493 @@ impl SymorManager {
             #[cfg(unix)] config_perms.set_mode(0o600);
             fs::set_permissions(&config_path, config_perms)?;
         }
-        let mirror_path = home_dir.join("mirror.json");
-        if mirror_path.exists() {
-            let mut mirror_perms = fs::metadata(&mirror_path)?.permissions();
-            #[cfg(unix)] mirror_perms.set_mode(0o600);
-            fs::set_permissions(&mirror_path, mirror_perms)?;
-        }
+        let items_dir = item_store::items_dir(home_dir);
+        fs::create_dir_all(&items_dir)?;
+        let mut items_perms = fs::metadata(&items_dir)?.permissions();
+        #[cfg(unix)] items_perms.set_mode(0o700);
+        fs::set_permissions(&items_dir, items_perms)?;
         info!(
             "Created symor directory structure with secure permissions at {:?}", home_dir
         );
         Ok(())
     }
     pub fn load_config(&mut self) -> Result<()> {
+        let environments = config::environment::load_environments(
+            &self.environments_path(),
+        )?;
         let config_path = self.config.home_dir.join("config.json");
         if config_path.exists() {
             let config_data = fs::read_to_string(&config_path)?;
             let loaded_config: SymorConfig = serde_json::from_str(&config_data)?;
+            let given: serde_json::Value = serde_json::from_str(&config_data)?;
+            for field in config::environment::unknown_fields(&given, &loaded_config)? {
+                warn!("Ignoring unknown setting '{}' in {:?}", field, config_path);
+            }
             self.config = loaded_config;
         }
+        let cwd = std::env::current_dir().unwrap_or_default();
+        if let Some((env, source)) = config::environment::detect_environment(
+            &environments,
+            &cwd,
+        ) {
+            self.config = env.load_config(&self.config)?;
+            self.config_source = Some(env.resolved_config_path(&self.config.home_dir));
+            self.active_environment = Some(env.name.clone());
+            info!("Using environment '{}' ({:?})", env.name, source);
+        } else if let Ok(name) = std::env::var(config::environment::ENVIRONMENT_VAR) {
+            warn!(
+                "{} names unknown environment '{}'", config::environment::ENVIRONMENT_VAR,
+                name
+            );
+        }
+        self.apply_loaded_config();
+        self.validate_loaded_config()
+    }
+    pub fn load_config_from(&mut self, path: &Path) -> Result<()> {
+        self.config = SymorConfig::from_file(path)?;
+        self.config_source = Some(path.to_path_buf());
+        self.apply_loaded_config();
+        self.validate_loaded_config()
+    }
+    pub fn environments_path(&self) -> PathBuf {
+        self.config.home_dir.join("environments.json")
+    }
+    /// Name of the environment whose config was loaded, if any
+    pub fn active_environment(&self) -> Option<&str> {
+        self.active_environment.as_deref()
+    }
+    /// File the config was loaded from when it is not the home `config.json`
+    pub fn config_source(&self) -> Option<&Path> {
+        self.config_source.as_deref()
+    }
+    /// Skips validation in `load_config`, for repairing a broken config
+    pub fn set_validate_on_load(&mut self, validate: bool) {
+        self.validate_on_load = validate;
+    }
+    fn validate_loaded_config(&mut self) -> Result<()> {
+        if !self.validate_on_load {
+            return Ok(());
+        }
+        let validator = config::ConfigValidator::new();
+        let original = validator.validate_and_fix_config(&mut self.config)?;
+        let result = validator.validate_config(&self.config);
+        for warning in &result.warnings {
+            warn!("Config {}: {}", warning.field, warning.message);
+        }
+        if original.errors.len() > result.errors.len() {
+            warn!(
+                "Auto-corrected {} invalid config setting(s)", original.errors.len() -
+                result.errors.len()
+            );
+        }
+        if let Some(error) = result.errors.first() {
+            let mut message = format!("{}: {}", error.field, error.message);
+            if result.errors.len() > 1 {
+                message.push_str(&format!(" (and {} more)", result.errors.len() - 1));
+            }
+            return Err(
+                SymorError::new(ErrorCode::InvalidConfiguration, message)
+                    .with_context("field", &error.field)
+                    .with_suggestion(
+                        match &error.suggestion {
+                            Some(suggestion) => {
+                                format!("{}, or rerun with --no-validate", suggestion)
+                            }
+                            None => {
+                                "Fix the config file or rerun with --no-validate"
+                                    .to_string()
+                            }
+                        },
+                    ),
+            );
+        }
+        Ok(())
+    }
+    /// Applies what a loaded config implies outside `config`: the portable home override,
+    /// the change detector's hidden-file rule, the memory budget and restore backup tracking
+    fn apply_loaded_config(&mut self) {
+        if let Some(home_dir) = home_override() {
+            self.config.home_dir = home_dir;
+        }
+        self.change_detector.set_skip_hidden(self.config.skip_hidden);
+        self.change_detector.set_normalize_unicode(self.config.linking.normalize_unicode);
+        self.version_storage
+            .set_compression_threads(self.config.performance.compression_threads());
+        match self.config.performance.max_memory_bytes() {
+            Ok(bytes) => performance::memory::budget().set_limit(bytes),
+            Err(e) => warn!("ignoring performance.max_memory: {}", e),
+        }
+        self.track_restore_backups();
+    }
+    pub fn save_config(&self) -> Result<()> {
+        #[cfg(unix)]
+        use std::os::unix::fs::PermissionsExt;
+        let config_path = match &self.config_source {
+            Some(path) => {
+                self.config.write_to_file(path)?;
+                path.clone()
+            }
+            None => {
+                let config_path = get_default_home_dir().join("config.json");
+                let config_data = serde_json::to_string_pretty(&self.config)?;
+                fs::write(&config_path, config_data)?;
+                config_path
+            }
+        };
+        let mut perms = fs::metadata(&config_path)?.permissions();
+        #[cfg(unix)] perms.set_mode(0o600);
+        fs::set_permissions(&config_path, perms)?;
         Ok(())
     }
-    pub fn save_config(&self) -> Result<()> {
-        #[cfg(unix)]
-        use std::os::unix::fs::PermissionsExt;
-        let config_path = self.config.home_dir.join("config.json");
-        let config_data = serde_json::to_string_pretty(&self.config)?;
-        fs::write(&config_path, config_data)?;
-        let mut perms = fs::metadata(&config_path)?.permissions();
-        #[cfg(unix)] perms.set_mode(0o600);
-        fs::set_permissions(&config_path, perms)?;
+    pub fn watch(&mut self, path: PathBuf, recursive: bool) -> Result<String> {
+        let skip_hidden = self.config.skip_hidden;
+        self.watch_only(path, recursive, Vec::new(), skip_hidden)
+    }
+    /// `watch`, tracking only the files of a directory that match `only` and, with
+    /// `skip_hidden`, are not hidden (see `PathFilter`)
+    pub fn watch_only(
+        &mut self,
+        path: PathBuf,
+        recursive: bool,
+        only: Vec<String>,
+        skip_hidden: bool,
+    ) -> Result<String> {
+        self.watch_named(path, recursive, only, skip_hidden, None)
+    }
+    /// `watch_only`, giving the item a `name` to use in place of its ID or path
+    pub fn watch_named(
+        &mut self,
+        path: PathBuf,
+        recursive: bool,
+        only: Vec<String>,
+        skip_hidden: bool,
+        name: Option<String>,
+    ) -> Result<String> {
+        self.ensure_writable("watch files")?;
+        PathFilter::new(&only)?;
+        if let Some(name) = &name {
+            self.check_item_name(name)?;
+        }
+        let id = generate_id();
+        let is_directory = path.is_dir();
+        let watched_item = WatchedItem {
+            id: id.clone(),
+            path: path.clone(),
+            is_directory,
+            recursive,
+            versions: Default::default(),
+            created_at: SystemTime::now(),
+            last_modified: SystemTime::now(),
+            only,
+            skip_hidden,
+            paused: false,
+            name,
+            description: None,
+        };
+        self.watched_items.insert(id.clone(), watched_item);
+        self.save_watched_items()?;
+        if self.config.versioning.enabled {
+            self.create_backup(&id)?;
+        }
+        if let Some(item) = self.watched_items.get(&id) {
+            if item.path.exists() {
+                self.change_detector.scan_file(&item.path)?;
+            }
+        }
+        info!("Now watching: {:?} (ID: {})", path, id);
+        Ok(id)
+    }
+    /// Watches every path in `paths` with the same options, writing the watched items once
+    /// and taking the initial backups in parallel. Returns each path with its new ID, or
+    /// why it was skipped (missing, listed twice or already watched); a failed initial
+    /// backup is only logged
+    pub fn watch_many(
+        &mut self,
+        paths: Vec<PathBuf>,
+        recursive: bool,
+        only: Vec<String>,
+        skip_hidden: bool,
+    ) -> Result<Vec<(PathBuf, Result<String>)>> {
+        self.ensure_writable("watch files")?;
+        PathFilter::new(&only)?;
+        let _lock = self.lock_store()?;
+        let mut results = Vec::with_capacity(paths.len());
+        let mut added = Vec::new();
+        for path in paths {
+            if !path.exists() {
+                let error = SymorError::new(
+                    ErrorCode::FileNotFound,
+                    format!("File does not exist: {:?}", path),
+                );
+                results.push((path, Err(error)));
+                continue;
+            }
+            if self.watched_items.values().any(|item| item.path == path) {
+                let error = SymorError::new(
+                    ErrorCode::InvalidPath,
+                    format!("Already watched: {:?}", path),
+                );
+                results.push((path, Err(error)));
+                continue;
+            }
+            let mut id = generate_id();
+            while self.watched_items.contains_key(&id) {
+                id = generate_id();
+            }
+            let watched_item = WatchedItem {
+                id: id.clone(),
+                path: path.clone(),
+                is_directory: path.is_dir(),
+                recursive,
+                versions: Default::default(),
+                created_at: SystemTime::now(),
+                last_modified: SystemTime::now(),
+                only: only.clone(),
+                skip_hidden,
+                paused: false,
+                name: None,
+                description: None,
+            };
+            self.watched_items.insert(id.clone(), watched_item);
+            if !path.is_dir() {
+                added.push((id.clone(), path.clone()));
+            }
+            results.push((path, Ok(id)));
+        }
+        if self.config.versioning.enabled {
+            let ids: Vec<String> = added.iter().map(|(id, _)| id.clone()).collect();
+            let jobs = performance::ParallelProcessor::get_optimal_concurrency();
+            for ((_, path), (_, result)) in added.iter().zip(self.store_versions(&ids, jobs)) {
+                if let Err(e) = result {
+                    warn!("Initial backup of {:?} failed: {}", path, e);
+                }
+            }
+        }
+        self.save_watched_items()?;
+        for (_, path) in &added {
+            self.change_detector.scan_file(path)?;
+        }
+        info!("Now watching {} new path(s)", results.iter().filter(|(_, r)| r.is_ok()).count());
+        Ok(results)
+    }
+    /// Watched item named by an id, its `--name` alias or its path (as given, or
+    /// canonicalized)
+    pub fn resolve_item(&self, path_or_id: &str) -> Option<&WatchedItem> {
+        if let Some(item) = self.watched_items.get(path_or_id) {
+            return Some(item);
+        }
+        if let Some(item) = self
+            .watched_items
+            .values()
+            .find(|item| item.name.as_deref() == Some(path_or_id))
+        {
+            return Some(item);
+        }
+        let path = Path::new(path_or_id);
+        let canonical = fs::canonicalize(path).ok();
+        self.watched_items
+            .values()
+            .find(|item| {
+                item.path == path
+                    || canonical
+                        .as_ref()
+                        .is_some_and(|c| fs::canonicalize(&item.path).is_ok_and(|p| &p == c))
+            })
+    }
+    /// Fails when `name` cannot be an item alias: it is empty, looks like a path or is
+    /// already the ID or alias of a watched item
+    pub fn check_item_name(&self, name: &str) -> Result<()> {
+        let problem = if name.trim().is_empty() {
+            "must not be empty"
+        } else if name.contains(['/', '\\']) || name.starts_with('.') {
+            "must not look like a path"
+        } else if self.watched_items.contains_key(name)
+            || self.watched_items.values().any(|item| item.name.as_deref() == Some(name))
+        {
+            "is already taken"
+        } else {
+            return Ok(());
+        };
+        Err(
+            SymorError::new(ErrorCode::InvalidPath, format!("Name {:?} {}", name, problem))
+                .with_suggestion("Pick another name, e.g. nginx-conf".to_string()),
+        )
+    }
+    /// Deletes the stored versions of an unwatched `item` (to the trash when the linking
+    /// `trash` option is on), returning how many were removed
+    pub fn purge_versions(&self, item: &WatchedItem) -> Result<usize> {
+        self.ensure_writable("delete versions")?;
+        self.ensure_deletable(&item.versions, "purge versions")?;
+        let _lock = self.version_storage.lock_exclusive()?;
+        let to_trash = self.config.linking.trash;
+        for version in &item.versions {
+            self.version_storage.discard_version(&version.id, to_trash)?;
+            if let Some(backup_path) = &version.backup_path {
+                if backup_path.is_absolute() {
+                    linking::discard_path(backup_path, to_trash)?;
+                }
+            }
+            self.run_version_hooks(&VersionEvent::new(VersionAction::Pruned, &item.id, version));
+        }
+        Ok(item.versions.len())
+    }
+    fn pause_marker(&self) -> PathBuf {
+        self.config.home_dir.join("paused")
+    }
+    /// Whether `sym pause --all` holds every mirror and watched item
+    pub fn is_paused(&self) -> bool {
+        self.pause_marker().exists()
+    }
+    /// Pauses everything, or on resume lifts the global pause together with every
+    /// per-item one
+    pub fn set_all_paused(&mut self, paused: bool) -> Result<()> {
+        if paused {
+            fs::write(self.pause_marker(), "")?;
+            return Ok(());
+        }
+        if self.is_paused() {
+            fs::remove_file(self.pause_marker())?;
+        }
+        if self.mirrors.iter().any(|spec| spec.paused) {
+            self.mirrors.iter_mut().for_each(|spec| spec.paused = false);
+            self.save_mirrors()?;
+        }
+        if self.watched_items.values().any(|item| item.paused) {
+            self.watched_items.values_mut().for_each(|item| item.paused = false);
+            self.save_watched_items()?;
+        }
         Ok(())
     }
-    pub fn watch(&mut self, path: PathBuf, recursive: bool) -> Result<String> {
-        let id = generate_id();
-        let is_directory = path.is_dir();
-        let watched_item = WatchedItem {
-            id: id.clone(),
-            path: path.clone(),
-            is_directory,
-            recursive,
-            versions: Vec::new(),
-            created_at: SystemTime::now(),
-            last_modified: SystemTime::now(),
-        };
-        self.watched_items.insert(id.clone(), watched_item);
+    /// Sets the description of the watched item (path, ID or name) named by `path_or_id`,
+    /// or clears it for `None` or blank text; returns the item's path
+    pub fn annotate(&mut self, path_or_id: &str, description: Option<String>) -> Result<PathBuf> {
+        let id = self
+            .resolve_item(path_or_id)
+            .map(|item| item.id.clone())
+            .ok_or_else(|| SymorError::not_watched(path_or_id))?;
+        let item = self.watched_items.get_mut(&id).expect("resolved item exists");
+        item.description = description
+            .map(|text| text.trim().to_string())
+            .filter(|text| !text.is_empty());
+        let path = item.path.clone();
         self.save_watched_items()?;
-        if self.config.versioning.enabled {
-            self.create_backup(&id)?;
+        Ok(path)
+    }
+    /// Pauses or resumes the watched item (path or ID) or mirror (source path) named
+    /// by `path_or_id`, returning its path
+    pub fn set_paused(&mut self, path_or_id: &str, paused: bool) -> Result<PathBuf> {
+        if let Some(id) = self.resolve_item(path_or_id).map(|item| item.id.clone()) {
+            let item = self.watched_items.get_mut(&id).expect("resolved item exists");
+            item.paused = paused;
+            let path = item.path.clone();
+            self.save_watched_items()?;
+            return Ok(path);
         }
-        if let Some(item) = self.watched_items.get(&id) {
-            if item.path.exists() {
-                self.change_detector.scan_file(&item.path)?;
-            }
+        let canonical = fs::canonicalize(path_or_id).ok();
+        let spec = self
+            .mirrors
+            .iter_mut()
+            .find(|spec| {
+                spec.source == Path::new(path_or_id)
+                    || canonical
+                        .as_ref()
+                        .is_some_and(|c| fs::canonicalize(&spec.source).is_ok_and(|p| &p == c))
+            })
+            .ok_or_else(|| SymorError::not_watched(path_or_id))?;
+        spec.paused = paused;
+        let source = spec.source.clone();
+        self.save_mirrors()?;
+        Ok(source)
+    }
+    /// Stops watching the item with `id`, returning it with its versions read in; stored
+    /// versions are kept
+    pub fn unwatch(&mut self, id: &str) -> Result<Option<WatchedItem>> {
+        self.ensure_writable("stop watching files")?;
+        if let Some(item) = self.watched_items.get(id) {
+            item.versions.load()?;
         }
-        info!("Now watching: {:?} (ID: {})", path, id);
-        Ok(id)
+        let removed = self.watched_items.remove(id);
+        if removed.is_some() {
+            self.save_watched_items()?;
+            info!("Stopped watching: {}", id);
+        }
+        Ok(removed)
     }
     pub fn list_watched(&self, detailed: bool) -> Result<()> {
+        self.list_watched_page(detailed, ListPage::default())
+    }
+    /// Prints one page of the watched items in path order. Directory contents come
+    /// from a cache that only re-reads directories changed since the last listing;
+    /// file names are printed with `detailed`, and file groups are refreshed when the
+    /// page covers every item.
+    pub fn list_watched_page(&self, detailed: bool, page: ListPage) -> Result<()> {
         if self.watched_items.is_empty() {
             println!("No files or directories are currently being watched.");
             return Ok(());
         }
+        let mut items: Vec<&WatchedItem> = self.watched_items.values().collect();
+        items.sort_by(|a, b| a.path.cmp(&b.path));
+        let per_page = if page.per_page == 0 { items.len() } else { page.per_page };
+        let pages = items.len().div_ceil(per_page);
+        let first = (page.page.max(1) - 1) * per_page;
+        if first >= items.len() {
+            println!("Page {} is empty; there are {} page(s) of up to {} items", page.page, pages, per_page);
+            return Ok(());
+        }
         println!("📋 Watched Items Summary");
         println!("========================");
-        println!("Total watched roots: {}", self.watched_items.len());
+        println!("Total watched roots: {}", items.len());
+        if pages > 1 {
+            println!(
+                "Showing {}-{} (page {} of {})", (first + 1).min(items.len()), (first +
+                per_page).min(items.len()), page.page.max(1), pages
+            );
+        }
         println!();
+        let mut cache = dir_cache::DirCache::load(&self.config.home_dir);
         let mut total_files = 0;
         let mut total_dirs = 0;
         let mut all_files = Vec::new();
-        for (id, item) in &self.watched_items {
+        for item in items.iter().skip(first).take(per_page) {
+            let id = match &item.name {
+                Some(name) => format!("{} (name: {})", item.id, name),
+                None => item.id.clone(),
+            };
+            let description = item.description.as_deref().filter(|_| detailed);
             if item.is_directory && item.recursive {
-                let files_in_dir = self.collect_files_recursive(&item.path)?;
+                let filter = PathFilter::new(&item.only)?.skip_hidden(item.skip_hidden);
+                let files_in_dir = cache.files(&item.path, &filter)?;
                 total_files += files_in_dir.len();
                 total_dirs += 1;
                 println!("📁 Directory: {:?}", item.path);
                 println!("   ID: {}", id);
+                if let Some(description) = description {
+                    println!("   Description: {}", description);
+                }
                 println!("   Files within: {}", files_in_dir.len());
+                if !item.only.is_empty() {
+                    println!("   Only: {}", item.only.join(", "));
+                }
+                if item.skip_hidden {
+                    println!("   Hidden files: skipped");
+                }
                 if detailed {
                     println!("   Created: {:?}", item.created_at);
                     println!("   Last Modified: {:?}", item.last_modified);
                     println!("   Versions: {}", item.versions.len());
+                    for file_path in &files_in_dir {
+                        println!("   📄 {}", file_path.display());
+                    }
                 }
-                for file_path in &files_in_dir {
-                    println!("   📄 {}", file_path.display());
-                    all_files.push(file_path.clone());
-                }
+                all_files.extend(files_in_dir);
                 println!();
             } else if item.is_directory {
                 total_dirs += 1;
                 println!("📁 Directory (non-recursive): {:?}", item.path);
                 println!("   ID: {}", id);
+                if let Some(description) = description {
+                    println!("   Description: {}", description);
+                }
                 if detailed {
                     println!("   Created: {:?}", item.created_at);
                     println!("   Versions: {}", item.versions.len());
@@ -639,6 +2167,9 @@ impl SymorManager {
                 total_files += 1;
                 println!("📄 File: {:?}", item.path);
                 println!("   ID: {}", id);
+                if let Some(description) = description {
+                    println!("   Description: {}", description);
+                }
                 if detailed {
                     println!("   Created: {:?}", item.created_at);
                     println!("   Last Modified: {:?}", item.last_modified);
@@ -652,31 +2183,20 @@ impl SymorManager {
                 println!();
             }
         }
+        if let Err(e) = cache.save(&self.config.home_dir) {
+            warn!("cannot save directory cache: {}", e);
+        }
         println!("📊 Summary:");
         println!("  Directories: {}", total_dirs);
         println!("  Files: {}", total_files);
         println!("  Total items: {}", total_files + total_dirs);
-        self.save_file_groups(&all_files)?;
-        Ok(())
-    }
-    fn collect_files_recursive(&self, dir_path: &Path) -> Result<Vec<PathBuf>> {
-        let mut files = Vec::new();
-        fn collect_recursive(path: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
-            if path.is_dir() {
-                for entry in fs::read_dir(path)? {
-                    let entry = entry?;
-                    let entry_path = entry.path();
-                    if entry_path.is_file() {
-                        files.push(entry_path);
-                    } else if entry_path.is_dir() {
-                        collect_recursive(&entry_path, files)?;
-                    }
-                }
-            }
-            Ok(())
+        if page.page.max(1) < pages {
+            println!("  Next page: sym list --page {} --per-page {}", page.page.max(1) + 1, per_page);
         }
-        collect_recursive(dir_path, &mut files)?;
-        Ok(files)
+        if pages == 1 {
+            self.save_file_groups(&all_files)?;
+        }
+        Ok(())
     }
     fn save_file_groups(&self, files: &[PathBuf]) -> Result<()> {
         use serde_json::json;
@@ -728,7 +2248,7 @@ impl SymorManager {
             let group_index_json = serde_json::to_string_pretty(&group_index_data)?;
             fs::write(&group_index_file, group_index_json)?;
             println!(
-                "💾 Group '{}' saved to: ~/.symor/groups/{}/", folder_name, group_id
+                "💾 Group '{}' saved to: {}/", folder_name, group_subdir.display()
             );
             println!("   📄 {}.json", folder_name);
             println!("   📄 index.json");
@@ -747,8 +2267,8 @@ impl SymorManager {
         );
         let master_index_file = groups_dir.join("index.json");
         let master_index_json = serde_json::to_string_pretty(&master_index_data)?;
-        fs::write(master_index_file, master_index_json)?;
-        println!("📋 Master index saved to: ~/.symor/groups/index.json");
+        fs::write(&master_index_file, master_index_json)?;
+        println!("📋 Master index saved to: {}", master_index_file.display());
         println!(
             "📁 Created {} group directories with individual management",
             total_groups_created
@@ -757,39 +2277,11 @@ impl SymorManager {
         Ok(())
     }
     fn cleanup_stale_groups(&self) -> Result<()> {
-        let groups_dir = self.config.home_dir.join("groups");
-        if !groups_dir.exists() {
-            return Ok(());
-        }
         let mut cleaned_count = 0;
-        for entry in fs::read_dir(&groups_dir)? {
-            let entry = entry?;
-            let group_subdir = entry.path();
-            if group_subdir.is_file() {
-                continue;
-            }
-            let group_index_file = group_subdir.join("index.json");
-            if !group_index_file.exists() {
-                continue;
-            }
-            let index_content = fs::read_to_string(&group_index_file)?;
-            if let Ok(index_data) = serde_json::from_str::<
-                serde_json::Value,
-            >(&index_content) {
-                if let Some(group_path) = index_data
-                    .get("group_path")
-                    .and_then(|p| p.as_str())
-                {
-                    if !PathBuf::from(group_path).exists() {
-                        println!(
-                            "🗑️  Removing stale group: {} (path no longer exists)",
-                            group_path
-                        );
-                        fs::remove_dir_all(&group_subdir)?;
-                        cleaned_count += 1;
-                    }
-                }
-            }
+        for (group_subdir, group_path) in housekeeping::stale_groups(&self.config.home_dir)? {
+            println!("🗑️  Removing stale group: {} (path no longer exists)", group_path);
+            fs::remove_dir_all(&group_subdir)?;
+            cleaned_count += 1;
         }
         if cleaned_count > 0 {
             println!("🧹 Cleaned up {} stale group directories", cleaned_count);
@@ -813,45 +2305,196 @@ impl SymorManager {
         }
         Ok(())
     }
+    /// Writes the watched items that changed to their files under `items/`
     fn save_watched_items(&self) -> Result<()> {
-        #[cfg(unix)]
-        use std::os::unix::fs::PermissionsExt;
-        let mirror_path = self.config.home_dir.join("mirror.json");
-        let mirror_data = serde_json::to_string_pretty(&self.watched_items)?;
-        fs::write(&mirror_path, mirror_data)?;
-        let mut perms = fs::metadata(&mirror_path)?.permissions();
-        #[cfg(unix)] perms.set_mode(0o600);
-        fs::set_permissions(&mirror_path, perms)?;
-        Ok(())
+        self.item_store.save(
+            &self.config.home_dir,
+            &self.watched_items,
+            self.config.versioning.metadata_format,
+        )
     }
+    /// Loads the watched items from `items/`, re-reading only files changed since the
+    /// last load and leaving version lists to be read on first use; a `mirror.json` from
+    /// older versions is migrated on first use
     pub fn load_watched_items(&mut self) -> Result<()> {
-        let mirror_path = self.config.home_dir.join("mirror.json");
-        if mirror_path.exists() {
-            let mirror_data = fs::read_to_string(mirror_path)?;
-            self.watched_items = serde_json::from_str(&mirror_data)?;
+        self.store_epoch = self.version_storage.epoch();
+        self.item_store.load(&self.config.home_dir, &mut self.watched_items)
+    }
+    pub fn load_mirrors(&mut self) -> Result<()> {
+        let mirrors_path = self.config.home_dir.join("mirrors.json");
+        if mirrors_path.exists() {
+            let mirrors_data = fs::read_to_string(mirrors_path)?;
+            self.mirrors = serde_json::from_str(&mirrors_data)?;
         }
         Ok(())
     }
-    pub fn install_binary(&self, force: bool) -> Result<()> {
+    fn save_mirrors(&self) -> Result<()> {
+        let mirrors_path = self.config.home_dir.join("mirrors.json");
+        fs::write(mirrors_path, serde_json::to_string_pretty(&self.mirrors)?)?;
+        Ok(())
+    }
+    pub fn mirrors(&self) -> &[MirrorSpec] {
+        &self.mirrors
+    }
+    /// Records a mirror relationship, merging targets into an existing one for `source`;
+    /// non-empty `only` patterns and a given `skip_hidden` or `storm_threshold` replace
+    /// the existing ones, and a new mirror without `skip_hidden` takes the config default
+    pub fn add_mirror(
+        &mut self,
+        source: &Path,
+        targets: &[PathBuf],
+        bidirectional: bool,
+        only: &[String],
+        skip_hidden: Option<bool>,
+        storm_threshold: Option<u32>,
+    ) -> Result<()> {
+        let source = std::path::absolute(source)?;
+        let targets = targets
+            .iter()
+            .map(std::path::absolute)
+            .collect::<std::io::Result<Vec<_>>>()?;
+        let existing = self.mirrors.iter().find(|m| m.source == source);
+        let all_targets: Vec<PathBuf> = existing
+            .map(|spec| spec.targets.clone())
+            .unwrap_or_default()
+            .into_iter()
+            .chain(targets.iter().cloned())
+            .collect();
+        let two_way = bidirectional || existing.is_some_and(|spec| spec.bidirectional);
+        dated::check_targets(&source, &all_targets, two_way)?;
+        match self.mirrors.iter_mut().find(|m| m.source == source) {
+            Some(spec) => {
+                for target in targets {
+                    if !spec.targets.contains(&target) {
+                        spec.targets.push(target);
+                    }
+                }
+                spec.bidirectional |= bidirectional;
+                if !only.is_empty() {
+                    spec.only = only.to_vec();
+                }
+                if let Some(skip_hidden) = skip_hidden {
+                    spec.skip_hidden = skip_hidden;
+                }
+                if storm_threshold.is_some() {
+                    spec.storm_threshold = storm_threshold;
+                }
+            }
+            None => {
+                self.mirrors
+                    .push(MirrorSpec {
+                        source,
+                        targets,
+                        bidirectional,
+                        only: only.to_vec(),
+                        skip_hidden: skip_hidden.unwrap_or(self.config.skip_hidden),
+                        paused: false,
+                        storm_threshold,
+                        rollover: false,
+                        keep_rotations: None,
+                        link_rotations: false,
+                        symlinks: Default::default(),
+                    })
+            }
+        }
+        self.save_mirrors()
+    }
+    /// Turns re-expanding dated targets on date changes on or off for the mirror of
+    /// `source`; false when there is no such mirror
+    pub fn set_mirror_rollover(&mut self, source: &Path, rollover: bool) -> Result<bool> {
+        let source = std::path::absolute(source)?;
+        let Some(spec) = self.mirrors.iter_mut().find(|m| m.source == source) else {
+            return Ok(false);
+        };
+        spec.rollover = rollover;
+        self.save_mirrors()?;
+        Ok(true)
+    }
+    /// Sets how many dated copies of each dated target the mirror of `source` keeps,
+    /// `None` for all; false when there is no such mirror
+    pub fn set_mirror_rotations(&mut self, source: &Path, keep: Option<usize>) -> Result<bool> {
+        let source = std::path::absolute(source)?;
+        let Some(spec) = self.mirrors.iter_mut().find(|m| m.source == source) else {
+            return Ok(false);
+        };
+        if keep.is_some() {
+            dated::check_rotations(&spec.source, &spec.targets)?;
+        }
+        spec.keep_rotations = keep;
+        self.save_mirrors()?;
+        Ok(true)
+    }
+    /// Turns hard linking unchanged files from the previous dated copy on or off for the
+    /// mirror of `source`; false when there is no such mirror
+    pub fn set_mirror_link_rotations(&mut self, source: &Path, link: bool) -> Result<bool> {
+        let source = std::path::absolute(source)?;
+        let Some(spec) = self.mirrors.iter_mut().find(|m| m.source == source) else {
+            return Ok(false);
+        };
+        if link {
+            dated::check_rotations(&spec.source, &spec.targets)?;
+        }
+        spec.link_rotations = link;
+        self.save_mirrors()?;
+        Ok(true)
+    }
+    /// Sets how the mirror of `source` syncs symlinks; false when there is no such mirror
+    pub fn set_mirror_symlinks(&mut self, source: &Path, mode: SymlinkMode) -> Result<bool> {
+        let source = std::path::absolute(source)?;
+        let Some(spec) = self.mirrors.iter_mut().find(|m| m.source == source) else {
+            return Ok(false);
+        };
+        spec.symlinks = mode;
+        self.save_mirrors()?;
+        Ok(true)
+    }
+    /// Removes one target, or the whole relationship when `target` is `None`
+    pub fn remove_mirror(&mut self, source: &Path, target: Option<&Path>) -> Result<bool> {
+        let source = std::path::absolute(source)?;
+        let before = self.mirrors.iter().map(|m| m.targets.len()).sum::<usize>();
+        match target {
+            Some(target) => {
+                let target = std::path::absolute(target)?;
+                for spec in self.mirrors.iter_mut().filter(|m| m.source == source) {
+                    spec.targets.retain(|t| t != &target);
+                }
+                self.mirrors.retain(|m| !m.targets.is_empty());
+            }
+            None => self.mirrors.retain(|m| m.source != source),
+        }
+        let removed = self.mirrors.iter().map(|m| m.targets.len()).sum::<usize>()
+            < before;
+        if removed {
+            self.save_mirrors()?;
+        }
+        Ok(removed)
+    }
+    /// Copies the running binary into the system (or, with `user`, per-user) bin directory
+    pub fn install_binary(&self, force: bool, user: bool) -> Result<PathBuf> {
         let current_exe = std::env::current_exe()?;
-        let bin_name = "sym";
-        let install_dir = if cfg!(target_os = "linux") || cfg!(target_os = "macos") {
-            PathBuf::from("/usr/local/bin")
-        } else if cfg!(target_os = "windows") {
-            std::env::var("USERPROFILE")
-                .map(|p| PathBuf::from(p).join("bin"))
-                .unwrap_or_else(|_| PathBuf::from("C:\\bin"))
+        let install_dir = if user {
+            install::user_install_dir()?
         } else {
-            return Err(anyhow::anyhow!("Unsupported platform for installation"));
+            install::system_install_dir()?
         };
-        let install_path = install_dir.join(bin_name);
+        let install_path = install_dir.join(install::binary_name());
         if install_path.exists() && !force {
             println!("sym is already installed at {:?}", install_path);
             println!("Use --force to overwrite existing installation");
-            return Ok(());
+            return Ok(install_path);
         }
-        fs::create_dir_all(&install_dir)?;
-        fs::copy(&current_exe, &install_path)?;
+        let suggest_user = |e: std::io::Error| {
+            let err = SymorError::from(e);
+            if err.code() == &ErrorCode::PermissionDenied && !user {
+                err.with_suggestion(
+                    "Use 'sym install --user' to install without sudo".to_string(),
+                )
+            } else {
+                err
+            }
+        };
+        fs::create_dir_all(&install_dir).map_err(suggest_user)?;
+        fs::copy(&current_exe, &install_path).map_err(suggest_user)?;
         #[cfg(unix)]
         {
             use std::os::unix::fs::PermissionsExt;
@@ -860,16 +2503,19 @@ impl SymorManager {
             fs::set_permissions(&install_path, perms)?;
         }
         println!("Successfully installed sym to {:?}", install_path);
-        Ok(())
+        Ok(install_path)
     }
     pub fn uninstall_binary(&self) -> Result<()> {
-        let bin_name = "sym";
-        let possible_paths = vec![
-            PathBuf::from("/usr/local/bin").join(bin_name), PathBuf::from("/usr/bin")
-            .join(bin_name), std::env::var("CARGO_HOME").map(| p | PathBuf::from(p)
-            .join("bin").join(bin_name)).unwrap_or_else(| _ |
-            PathBuf::from("~/.cargo/bin").join(bin_name)),
+        let bin_name = install::binary_name();
+        let mut possible_paths = vec![
+            PathBuf::from("/usr/local/bin").join(& bin_name), PathBuf::from("/usr/bin")
+            .join(& bin_name), std::env::var("CARGO_HOME").map(| p | PathBuf::from(p)
+            .join("bin").join(& bin_name)).unwrap_or_else(| _ |
+            PathBuf::from("~/.cargo/bin").join(& bin_name)),
         ];
+        if let Ok(dir) = install::user_install_dir() {
+            possible_paths.push(dir.join(&bin_name));
+        }
         let mut uninstalled = false;
         for path in possible_paths {
             if path.exists() {
@@ -893,11 +2539,21 @@ impl SymorManager {
     pub fn config(&self) -> &SymorConfig {
         &self.config
     }
-    pub fn watched_items(&self) -> &HashMap<String, WatchedItem> {
-        &self.watched_items
+    /// Every watched item, from the headers of the item files; an item's versions are
+    /// read when first used, or up front by `watched_item`
+    pub fn watched_index(&self) -> impl ExactSizeIterator<Item = &WatchedItem> + '_ {
+        self.watched_items.values()
+    }
+    /// The watched item with `id`, its versions read in
+    pub fn watched_item(&self, id: &str) -> Result<&WatchedItem> {
+        let item = self.watched_items.get(id).ok_or_else(|| SymorError::not_watched(id))?;
+        item.versions.load()?;
+        Ok(item)
     }
-    pub fn watched_items_mut(&mut self) -> &mut HashMap<String, WatchedItem> {
-        &mut self.watched_items
+    pub fn watched_item_mut(&mut self, id: &str) -> Result<&mut WatchedItem> {
+        let item = self.watched_items.get_mut(id).ok_or_else(|| SymorError::not_watched(id))?;
+        item.versions.load()?;
+        Ok(item)
     }
     pub fn change_detector(&self) -> &versioning::detector::ChangeDetector {
         &self.change_detector
@@ -905,12 +2561,60 @@ impl SymorManager {
     pub fn change_detector_mut(&mut self) -> &mut versioning::detector::ChangeDetector {
         &mut self.change_detector
     }
+    fn hash_cache_file(&self, format: config::MetadataFormat) -> PathBuf {
+        self.config.home_dir.join("cache").join(format!("hashes.{}", format.extension()))
+    }
+    /// Gives the change detector the file hashes the last `save_hash_cache` wrote, in
+    /// whichever metadata format it used; an unreadable cache is only logged
+    pub fn load_hash_cache(&mut self) {
+        for format in config::MetadataFormat::ALL {
+            let path = self.hash_cache_file(format);
+            let Ok(data) = fs::read(&path) else {
+                continue;
+            };
+            match format.decode(&data) {
+                Ok(hashes) => return self.change_detector.set_hashes(hashes),
+                Err(e) => warn!("Ignoring hash cache {:?}: {}", path, e),
+            }
+        }
+    }
+    /// Writes the change detector's hashes of watched paths in the configured metadata
+    /// format, so the next run only reports files that changed since this one
+    pub fn save_hash_cache(&self) -> Result<()> {
+        let format = self.config.versioning.metadata_format;
+        let watched: std::collections::HashSet<std::borrow::Cow<Path>> = self
+            .watched_items
+            .values()
+            .map(|item| self.change_detector.key(&item.path))
+            .collect();
+        let hashes: HashMap<&PathBuf, &String> = self
+            .change_detector
+            .hashes()
+            .iter()
+            .filter(|(path, _)| watched.contains(path.as_path()))
+            .collect();
+        let path = self.hash_cache_file(format);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, format.encode(&hashes)?)?;
+        for other in config::MetadataFormat::ALL.into_iter().filter(|other| *other != format) {
+            let stale = self.hash_cache_file(other);
+            if stale.exists() {
+                fs::remove_file(stale)?;
+            }
+        }
+        Ok(())
+    }
     pub fn version_storage(&self) -> &versioning::storage::VersionStorage {
         &self.version_storage
     }
     pub fn restore_engine(&self) -> &versioning::restore::RestoreEngine {
         &self.restore_engine
     }
+    pub fn error_recovery(&self) -> errors::ErrorRecovery {
+        errors::ErrorRecovery::new(&self.config.recovery)
+    }
     pub fn save_watched_items_public(&self) -> Result<()> {
         self.save_watched_items()
     }
@@ -918,108 +2622,609 @@ impl SymorManager {
     where
         F: FnOnce(&mut SymorConfig),
     {
-        updater(&mut self.config);
+        let mut config = self.config.clone();
+        updater(&mut config);
+        self.check_retention_change(config.versioning.retention_lock_days)?;
+        self.config = config;
         self.save_config()?;
         Ok(())
     }
     pub fn create_backup(&mut self, item_id: &str) -> Result<()> {
+        self.ensure_writable("create backups")?;
+        let stored = self.store_backup(item_id);
+        self.delete_pruned()?;
+        stored
+    }
+    /// `create_backup` up to recording the version, holding the store shared
+    fn store_backup(&mut self, item_id: &str) -> Result<()> {
+        let lock = self.lock_store()?;
+        let Some((path, level)) = self.backup_path(item_id)? else {
+            return Ok(());
+        };
+        if let Some(target) = self.preserved_link(&path) {
+            let metadata = self.version_storage.store_link(&path, &target, &generate_id())?;
+            return self.record_backup(item_id, metadata);
+        }
+        let _memory = performance::memory::budget().reserve_file(&path);
+        let recovery = self.error_recovery();
+        let shadow = self.config.versioning.shadow_copy;
+        let content = recovery.run(|| shadow_copy::read_file(&path, shadow))?;
+        let findings = secrets::screen(&path, &content, self.config.versioning.secret_scan)?;
+        let version_id = generate_id();
+        let mut held = (&mut *self, Some(lock));
+        let metadata = errors::recovery::retry_when_full(
+            &mut held,
+            |(manager, _)| {
+                let storage = &manager.version_storage;
+                recovery.run(|| storage.store_version_at(&path, &content, &version_id, level))
+            },
+            |(manager, lock)| manager.reclaim_for_retry(lock),
+        )?;
+        let _lock = held.1;
+        self.warn_secrets(item_id, &path, findings);
+        self.record_backup(item_id, metadata)
+    }
+    /// `create_backup` for several items, reading and storing up to `jobs` files at once
+    /// and saving the watched items once. Returns each ID with its own outcome, so one
+    /// failure does not stop the rest
+    pub fn backup_items(
+        &mut self,
+        ids: &[String],
+        jobs: usize,
+    ) -> Result<Vec<(String, Result<()>)>> {
+        self.ensure_writable("create backups")?;
+        let lock = self.lock_store()?;
+        let mut results: Vec<(String, Result<()>)> =
+            ids.iter().map(|id| (id.clone(), Ok(()))).collect();
+        let mut pending: Vec<usize> = (0..ids.len()).collect();
+        let mut held = (&mut *self, Some(lock));
+        let stored = errors::recovery::retry_when_full(
+            &mut held,
+            |(manager, _)| {
+                let batch: Vec<String> = pending.iter().map(|&i| ids[i].clone()).collect();
+                let mut full = Ok(());
+                let mut still_full = Vec::new();
+                let stored = manager.store_versions(&batch, jobs);
+                for (&i, (_, result)) in pending.iter().zip(stored) {
+                    if matches!(&result, Err(e) if e.code() == &ErrorCode::DiskFull) {
+                        still_full.push(i);
+                        if full.is_ok() {
+                            full = result.clone();
+                        }
+                    }
+                    results[i].1 = result;
+                }
+                pending = still_full;
+                full
+            },
+            |(manager, lock)| {
+                manager.save_watched_items()?;
+                manager.reclaim_for_retry(lock)
+            },
+        );
+        if let Err(e) = stored {
+            if e.code() != &ErrorCode::DiskFull {
+                return Err(e);
+            }
+        }
+        let lock = held.1;
+        self.save_watched_items()?;
+        drop(lock);
+        self.delete_pruned()?;
+        Ok(results)
+    }
+    /// Frees version space for one more try after a store hit a full disk, releasing
+    /// `lock`, the caller's shared hold on the store, meanwhile. Returns whether anything
+    /// was freed
+    fn reclaim_for_retry(
+        &mut self,
+        lock: &mut Option<versioning::lock::StoreLock>,
+    ) -> Result<bool> {
+        *lock = None;
+        let freed = self.reclaim_version_space()? > 0;
+        *lock = Some(self.lock_store()?);
+        Ok(freed)
+    }
+    /// Holds the version store shared while versions are stored and recorded, first
+    /// reloading the watched items when a cleanup finished since they were loaded, so
+    /// they do not list versions it deleted
+    fn lock_store(&mut self) -> Result<versioning::lock::StoreLock> {
+        let lock = self.version_storage.lock_shared()?;
+        self.reload_after_cleanup()?;
+        Ok(lock)
+    }
+    fn reload_after_cleanup(&mut self) -> Result<()> {
+        if self.version_storage.epoch() != self.store_epoch {
+            debug!("Version store was cleaned up, reloading watched items");
+            self.load_watched_items()?;
+        }
+        Ok(())
+    }
+    /// `backup_items` without saving the watched items
+    fn store_versions(&mut self, ids: &[String], jobs: usize) -> Vec<(String, Result<()>)> {
+        let mut version_ids: Vec<String> = Vec::with_capacity(ids.len());
+        let mut work = Vec::with_capacity(ids.len());
+        for id in ids {
+            let mut version_id = generate_id();
+            while version_ids.contains(&version_id) {
+                version_id = generate_id();
+            }
+            version_ids.push(version_id.clone());
+            let job = self.backup_path(id);
+            work.push(job.map(|job| job.map(|(path, level)| (path, level, version_id))));
+        }
+        let recovery = self.error_recovery();
+        let storage = &self.version_storage;
+        let shadow = self.config.versioning.shadow_copy;
+        let secret_scan = self.config.versioning.secret_scan;
+        let preserve = self.config.versioning.symlinks == SymlinkMode::Preserve;
+        let stored = performance::ParallelProcessor::new(jobs)
+            .map_parallel(
+                &work,
+                |job| match job {
+                    Ok(Some((path, level, version_id))) => {
+                        if let Some(target) = symlinks::link_target(path).filter(|_| preserve) {
+                            return storage
+                                .store_link(path, &target, version_id)
+                                .map(|metadata| Some((metadata, Vec::new())));
+                        }
+                        let _memory = performance::memory::budget().reserve_file(path);
+                        recovery
+                            .run(|| shadow_copy::read_file(path, shadow))
+                            .and_then(|content| {
+                                let findings = secrets::screen(path, &content, secret_scan)?;
+                                let level = *level;
+                                let stored = recovery.run(|| {
+                                    storage.store_version_at(path, &content, version_id, level)
+                                });
+                                stored.map(|metadata| (metadata, findings))
+                            })
+                            .map(Some)
+                    }
+                    Ok(None) => Ok(None),
+                    Err(e) => Err(e.clone()),
+                },
+            );
+        let mut results = Vec::with_capacity(ids.len());
+        for (id, stored) in ids.iter().zip(stored) {
+            let result = match stored {
+                Ok(Some((metadata, findings))) => {
+                    self.warn_secrets(id, &metadata.original_path, findings);
+                    self.push_version(id, metadata)
+                }
+                Ok(None) => Ok(()),
+                Err(e) => Err(e),
+            };
+            results.push((id.clone(), result));
+        }
+        results
+    }
+    /// File to version for `item_id` and the compression level to store it at, or `None`
+    /// for a directory, which is tracked only, or a file the file rules do not version
+    fn backup_path(&self, item_id: &str) -> Result<Option<(PathBuf, u8)>> {
         let item = self
             .watched_items
-            .get_mut(item_id)
-            .ok_or_else(|| anyhow::anyhow!("Watched item not found: {}", item_id))?;
+            .get(item_id)
+            .ok_or_else(|| SymorError::not_watched(item_id))?;
+        let is_link = symlinks::link_target(&item.path).is_some();
+        match self.config.versioning.symlinks {
+            SymlinkMode::Skip if is_link => {
+                info!("Leaving symlink {:?} unversioned", item.path);
+                return Ok(None);
+            }
+            SymlinkMode::Preserve if is_link => {
+                return Ok(Some((item.path.clone(), 0)));
+            }
+            _ => {}
+        }
         if !item.path.exists() {
-            return Err(anyhow::anyhow!("File does not exist: {:?}", item.path));
+            return Err(
+                SymorError::new(
+                    ErrorCode::FileNotFound,
+                    format!("File does not exist: {:?}", item.path),
+                ),
+            );
         }
         if item.path.is_dir() {
             println!("📁 Directory tracked (not versioned): {:?}", item.path);
-            return Ok(());
+            return Ok(None);
         }
-        let content = fs::read(&item.path)?;
-        let size = content.len() as u64;
-        let hash = format!("{:x}", md5::compute(& content));
-        let version_id = generate_id();
-        let metadata = self
-            .version_storage
-            .store_version(&item.path, &content, &version_id)?;
+        let name = item.path.file_name().map(Path::new).unwrap_or(&item.path);
+        let decision = self.file_rules()?.decide(&item.path, name);
+        if !decision.version {
+            info!("File rules leave {:?} unversioned", item.path);
+            return Ok(None);
+        }
+        let level = if decision.compress { self.version_storage.compression_level() } else { 0 };
+        Ok(Some((item.path.clone(), level)))
+    }
+    /// Where the watched symlink `path` points, when links are versioned as themselves
+    fn preserved_link(&self, path: &Path) -> Option<PathBuf> {
+        symlinks::link_target(path)
+            .filter(|_| self.config.versioning.symlinks == SymlinkMode::Preserve)
+    }
+    /// Adds a stored version to the item's history and prunes versions over the limit,
+    /// except those under the retention lock, leaving their deletion to `delete_pruned`
+    fn record_backup(&mut self, item_id: &str, metadata: versioning::VersionMetadata) -> Result<()> {
+        self.push_version(item_id, metadata)?;
+        self.save_watched_items()
+    }
+    /// `record_backup` without saving the watched items
+    fn push_version(&mut self, item_id: &str, metadata: versioning::VersionMetadata) -> Result<()> {
+        let version_id = metadata.id.clone();
+        let window = self.retention_window();
+        let item = self
+            .watched_items
+            .get_mut(item_id)
+            .ok_or_else(|| SymorError::not_watched(item_id))?;
+        item.versions.load()?;
         let version = FileVersion {
             id: version_id.clone(),
             timestamp: SystemTime::now(),
-            size,
-            hash,
+            size: metadata.size,
+            hash: metadata.hash.clone(),
             path: item.path.clone(),
             backup_path: Some(metadata.id.clone().into()),
         };
+        let event = VersionEvent::new(VersionAction::Created, item_id, &version);
         item.versions.push(version);
         if item.versions.len() > self.config.versioning.max_versions {
             let to_remove = item.versions.len() - self.config.versioning.max_versions;
-            for version in item.versions.drain(0..to_remove) {
-                let _ = self.version_storage.delete_version(&version.id);
-            }
+            let pruned = retention::take_oldest(&mut item.versions, to_remove, window);
+            self.pruned.extend(pruned.into_iter().map(|version| (item_id.to_string(), version)));
         }
         item.last_modified = SystemTime::now();
-        self.save_watched_items()?;
+        monitoring::metrics::record(
+            &self.config.home_dir,
+            |bucket| {
+                bucket.versions_created += 1;
+                bucket.bytes_stored += metadata.compressed_size;
+            },
+        );
         info!("Created backup for file (version: {})", version_id);
+        self.run_version_hooks(&event);
+        Ok(())
+    }
+    /// Deletes the versions `push_version` pruned. Callers release their shared hold on
+    /// the store first: the exclusive one waits for restores in other processes, so none
+    /// has the version it is reading deleted under it
+    fn delete_pruned(&mut self) -> Result<()> {
+        if self.pruned.is_empty() {
+            return Ok(());
+        }
+        let lock = self.version_storage.lock_exclusive()?;
+        self.delete_pruned_locked(lock);
         Ok(())
     }
+    /// `delete_pruned` with the store already held exclusively
+    fn delete_pruned_locked(&mut self, _lock: versioning::lock::StoreLock) {
+        for (item_id, version) in std::mem::take(&mut self.pruned) {
+            if self.version_storage.delete_version(&version.id).is_ok() {
+                let event = VersionEvent::new(VersionAction::Pruned, &item_id, &version);
+                self.run_version_hooks(&event);
+            }
+        }
+    }
+    /// Frees version storage after a disk-full error; returns the number of versions deleted.
+    /// Holds the store exclusively, so callers must release their own hold first
+    pub fn reclaim_version_space(&mut self) -> Result<usize> {
+        self.ensure_writable("delete versions")?;
+        let _lock = self.version_storage.lock_exclusive()?;
+        self.reload_after_cleanup()?;
+        let max_versions = self.config.versioning.max_versions.max(1);
+        let window = self.retention_window();
+        let mut pruned = Vec::new();
+        for item in self.watched_items.values_mut() {
+            if item.versions.len() > max_versions {
+                let to_remove = item.versions.len() - max_versions;
+                for version in retention::take_oldest(&mut item.versions, to_remove, window) {
+                    self.version_storage.delete_version(&version.id)?;
+                    pruned.push(VersionEvent::new(VersionAction::Pruned, &item.id, &version));
+                }
+            }
+        }
+        if pruned.is_empty() {
+            let oldest = self
+                .watched_items
+                .values_mut()
+                .filter(|item| {
+                    item.versions.len() > 1 && !retention::is_retained(&item.versions[0], window)
+                })
+                .min_by_key(|item| item.versions[0].timestamp);
+            if let Some(item) = oldest {
+                let version = item.versions.remove(0);
+                self.version_storage.delete_version(&version.id)?;
+                pruned.push(VersionEvent::new(VersionAction::Pruned, &item.id, &version));
+            }
+        }
+        let deleted = pruned.len();
+        for event in &pruned {
+            self.run_version_hooks(event);
+        }
+        if deleted > 0 {
+            self.save_watched_items()?;
+            info!("Reclaimed space by deleting {} old version(s)", deleted);
+        }
+        Ok(deleted)
+    }
+    /// Frees space for a write to `path` that hit a full disk: old versions when the
+    /// version store is on the same volume, and the leftovers `clean_home` removes.
+    /// Returns whether anything was freed
+    pub fn reclaim_space_for(&mut self, path: &Path) -> Result<bool> {
+        let mut freed = false;
+        if same_volume(path, self.version_storage.storage_path()) {
+            freed = self.reclaim_version_space()? > 0;
+        }
+        Ok(self.clean_home(false)?.bytes() > 0 || freed)
+    }
+    /// Writes a version to `target_path` and checks the result against the version's hash
     pub fn restore_file(
         &self,
         file_id: &str,
         version_id: &str,
         target_path: &Path,
-    ) -> Result<()> {
+    ) -> Result<versioning::restore::RestoreResult> {
+        self.ensure_writable("restore files")?;
+        let _lock = self.version_storage.lock_shared()?;
+        let version = self.find_version(file_id, version_id)?;
+        let result = match self.version_storage.retrieve_version(version_id) {
+            Ok((content, metadata)) => {
+                let options = self.restore_options(&metadata.hash, metadata.owner)?;
+                let result = match &metadata.link_target {
+                    Some(link) => self.restore_engine.restore_link(target_path, link, &options)?,
+                    None => self.restore_engine.restore_file(target_path, &content, &options)?,
+                };
+                info!("Successfully restored file using version storage system");
+                result
+            }
+            Err(_) => {
+                let content = fs::read(Self::legacy_backup_path(version)?)?;
+                let options = self.restore_options(&version.hash, None)?;
+                let result = self.restore_engine.restore_file(target_path, &content, &options)?;
+                info!("Successfully restored file using legacy backup system");
+                result
+            }
+        };
+        info!("Restored {:?} to {:?}", version.path, target_path);
+        let mut event = VersionEvent::new(VersionAction::Restored, file_id, version);
+        event.target = Some(target_path.to_path_buf());
+        self.run_version_hooks(&event);
+        if let Err(e) = self.cleanup_restore_files() {
+            warn!("Cleaning up old restore backups failed: {}", e);
+        }
+        Ok(result)
+    }
+    /// ID of the version of `item_id` named by `spec`: an ID, `@latest~N` or `@{when}`
+    pub fn resolve_version(&self, item_id: &str, spec: &str) -> Result<String> {
+        let item = self
+            .watched_items
+            .get(item_id)
+            .ok_or_else(|| SymorError::not_watched(item_id))?;
+        let selector: versioning::VersionSelector = spec.parse()?;
+        selector
+            .select(&item.versions)
+            .map(|version| version.id.clone())
+            .ok_or_else(|| {
+                SymorError::new(
+                        ErrorCode::VersionNotFound,
+                        format!("No version of {:?} matches {}", item.path, spec),
+                    )
+                    .with_suggestion(format!("Use 'sym history {}' to list versions", item_id))
+            })
+    }
+    /// Decompressed content of a stored version, e.g. to print instead of restoring
+    pub fn version_content(&self, file_id: &str, version_id: &str) -> Result<Vec<u8>> {
+        let version = self.find_version(file_id, version_id)?;
+        match self.version_storage.retrieve_version(version_id) {
+            Ok((content, _)) => Ok(content),
+            Err(_) => Ok(fs::read(Self::legacy_backup_path(version)?)?),
+        }
+    }
+    fn find_version(&self, file_id: &str, version_id: &str) -> Result<&FileVersion> {
         let item = self
             .watched_items
             .get(file_id)
-            .ok_or_else(|| anyhow::anyhow!("Watched item not found: {}", file_id))?;
-        let version = item
-            .versions
+            .ok_or_else(|| SymorError::not_watched(file_id))?;
+        item.versions
             .iter()
             .find(|v| v.id == version_id)
-            .ok_or_else(|| anyhow::anyhow!("Version not found: {}", version_id))?;
-        match self.version_storage.retrieve_version(version_id) {
-            Ok((content, _)) => {
-                let options = versioning::restore::RestoreOptions {
-                    preserve_permissions: self.config.linking.preserve_permissions,
-                    create_backup: true,
-                    backup_suffix: ".pre-restore".to_string(),
-                    atomic_restore: true,
-                };
-                self.restore_engine.restore_file(target_path, &content, &options)?;
-                info!("Successfully restored file using version storage system");
+            .ok_or_else(|| {
+                SymorError::new(
+                    ErrorCode::VersionNotFound,
+                    format!("Version not found: {}", version_id),
+                )
+            })
+    }
+    /// Owner rules restores and mirrors apply, when ownership is preserved
+    pub fn owner_map(&self) -> Result<Option<ownership::OwnerMap>> {
+        if !self.config.linking.preserve_ownership {
+            return Ok(None);
+        }
+        ownership::OwnerMap::parse(&self.config.linking.owner_map).map(Some)
+    }
+    /// Options for restores, which keep the replaced file as `.pre-restore` and verify
+    /// the result against `expected_hash`, and give it `owner` through the owner map
+    fn restore_options(
+        &self,
+        expected_hash: &str,
+        owner: Option<(u32, u32)>,
+    ) -> Result<versioning::restore::RestoreOptions> {
+        let owner_map = self.owner_map()?;
+        let owner = owner.map(|owner| owner_map.as_ref().map_or(owner, |map| map.map(owner)));
+        Ok(versioning::restore::RestoreOptions {
+            preserve_permissions: self.config.linking.preserve_permissions,
+            preserve_ownership: self.config.linking.preserve_ownership,
+            owner,
+            create_backup: true,
+            backup_suffix: ".pre-restore".to_string(),
+            atomic_restore: true,
+            expected_hash: Some(expected_hash.to_string()),
+        })
+    }
+    /// Backup file of a version saved before the version store existed
+    fn legacy_backup_path(version: &FileVersion) -> Result<&PathBuf> {
+        let backup_path = version
+            .backup_path
+            .as_ref()
+            .ok_or_else(|| {
+                SymorError::new(
+                    ErrorCode::VersionNotFound,
+                    format!("No backup path available for version: {}", version.id),
+                )
+            })?;
+        if !backup_path.exists() {
+            return Err(
+                SymorError::new(
+                    ErrorCode::VersionCorrupted,
+                    format!("Backup file not found: {:?}", backup_path),
+                ),
+            );
+        }
+        Ok(backup_path)
+    }
+    /// Diverged mirror files plus watched items that are missing or have no versions
+    pub fn find_conflicts(&self) -> Vec<conflicts::Conflict> {
+        let mut found: Vec<conflicts::Conflict> = self
+            .mirrors
+            .iter()
+            .flat_map(|spec| {
+                let state = sync_state::SyncState::load(&self.config.home_dir, &spec.source);
+                conflicts::mirror_conflicts(spec, state.as_ref())
+            })
+            .collect();
+        let mut items: Vec<&WatchedItem> = self.watched_items.values().collect();
+        items.sort_by(|a, b| a.path.cmp(&b.path));
+        for item in items {
+            let finding = |kind| conflicts::Conflict {
+                kind,
+                source: item.path.clone(),
+                target: None,
+                item_id: Some(item.id.clone()),
+                edited: None,
+            };
+            if !item.path.exists() {
+                found.push(finding(conflicts::ConflictKind::MissingSource));
             }
-            Err(_) => {
-                let backup_path = version
-                    .backup_path
-                    .as_ref()
+            if item.versions.is_empty() {
+                found.push(finding(conflicts::ConflictKind::NoVersions));
+            }
+        }
+        for conflict in &mut found {
+            if conflict.item_id.is_none() {
+                conflict.item_id = self
+                    .watched_items
+                    .values()
+                    .find(|item| item.path == conflict.source)
+                    .map(|item| item.id.clone());
+            }
+        }
+        found
+    }
+    /// Marks a settled mirror conflict as in sync, so the fix is not itself taken for an
+    /// edit on both sides
+    fn record_resolution(&self, conflict: &conflicts::Conflict) {
+        let Some(target) = &conflict.target else {
+            return;
+        };
+        let home_dir = &self.config.home_dir;
+        let spec = self
+            .mirrors
+            .iter()
+            .find(|spec| {
+                conflict.source.starts_with(&spec.source)
+                    && spec.targets.iter().any(|t| target.starts_with(t))
+            });
+        let Some(spec) = spec else {
+            return;
+        };
+        if let Some(mut state) = sync_state::SyncState::load(home_dir, &spec.source) {
+            state.record(&conflict.source, target);
+            if let Err(e) = state.save(home_dir) {
+                warn!("cannot save sync state for {:?}: {}", state.source, e);
+            }
+            if spec.bidirectional {
+                if let Err(e) = sync_state::save_base(home_dir, target) {
+                    warn!("cannot keep merge base for {:?}: {}", target, e);
+                }
+            }
+        }
+    }
+    /// Settles a conflict from `find_conflicts`; a watched file about to be
+    /// overwritten gets a version first
+    pub fn resolve_conflict(
+        &mut self,
+        conflict: &conflicts::Conflict,
+        resolution: conflicts::Resolution,
+    ) -> Result<String> {
+        self.ensure_writable("resolve conflicts")?;
+        use conflicts::{ConflictKind, Resolution};
+        if conflict.target.is_some() {
+            let overwritten = match resolution {
+                Resolution::KeepTarget => Some(conflict.source.clone()),
+                _ => conflict.target.clone(),
+            };
+            let item_id = overwritten
+                .filter(|path| path.is_file())
+                .and_then(|path| {
+                    self.watched_items
+                        .values()
+                        .find(|item| item.path == path)
+                        .map(|item| item.id.clone())
+                });
+            if let Some(id) = item_id {
+                self.create_backup(&id)?;
+            }
+            let message = conflicts::resolve_files(conflict, resolution)?;
+            self.record_resolution(conflict);
+            return Ok(message);
+        }
+        let id = conflict
+            .item_id
+            .clone()
+            .ok_or_else(|| SymorError::not_watched(&conflict.source.to_string_lossy()))?;
+        match (conflict.kind, resolution) {
+            (ConflictKind::MissingSource, Resolution::KeepTarget) => {
+                let version_id = self
+                    .watched_items
+                    .get(&id)
+                    .and_then(|item| item.versions.last())
+                    .map(|version| version.id.clone())
                     .ok_or_else(|| {
-                        anyhow::anyhow!(
-                            "No backup path available for version: {}", version_id
+                        SymorError::new(
+                            ErrorCode::VersionNotFound,
+                            format!("No versions stored for {:?}", conflict.source),
                         )
                     })?;
-                if !backup_path.exists() {
-                    return Err(
-                        anyhow::anyhow!("Backup file not found: {:?}", backup_path),
-                    );
-                }
-                let content = fs::read(backup_path)?;
-                let options = versioning::restore::RestoreOptions {
-                    preserve_permissions: self.config.linking.preserve_permissions,
-                    create_backup: true,
-                    backup_suffix: ".pre-restore".to_string(),
-                    atomic_restore: true,
-                };
-                self.restore_engine.restore_file(target_path, &content, &options)?;
-                info!("Successfully restored file using legacy backup system");
+                self.restore_file(&id, &version_id, &conflict.source)?;
+                Ok(
+                    format!(
+                        "Restored {} from version {}", conflict.source.display(), version_id
+                    ),
+                )
+            }
+            (ConflictKind::MissingSource, Resolution::KeepSource) => {
+                self.unwatch(&id)?;
+                Ok(format!("Stopped watching {}", conflict.source.display()))
+            }
+            (ConflictKind::NoVersions, Resolution::KeepSource) => {
+                self.create_backup(&id)?;
+                Ok(format!("Created a version of {}", conflict.source.display()))
+            }
+            _ => {
+                Err(
+                    SymorError::new(
+                        ErrorCode::InternalError,
+                        format!("{:?} does not apply to {}", resolution, conflict.summary()),
+                    ),
+                )
             }
         }
-        info!("Restored {:?} to {:?}", version.path, target_path);
-        Ok(())
     }
     pub fn list_versions(&self, item_id: &str) -> Result<()> {
         let item = self
             .watched_items
             .get(item_id)
-            .ok_or_else(|| anyhow::anyhow!("Watched item not found: {}", item_id))?;
+            .ok_or_else(|| SymorError::not_watched(item_id))?;
         if item.versions.is_empty() {
             println!("No versions found for item: {}", item_id);
             return Ok(());
@@ -0,0 +1,267 @@
+use crate::{errors::{Context, ErrorCode, Result, SymorError}, long_path::extend};
+use log::warn;
+use std::{fmt, fs, io, path::Path, str::FromStr};
+/// How a mirror target is attached to its source
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LinkType {
+    #[default]
+    Copy,
+    Hard,
+    Soft,
+    /// NTFS directory junction; behaves like `Soft` on other platforms
+    Junction,
+}
+impl LinkType {
+    pub const NAMES: [&'static str; 4] = ["copy", "hard", "soft", "junction"];
+}
+impl fmt::Display for LinkType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            LinkType::Copy => "copy",
+            LinkType::Hard => "hard",
+            LinkType::Soft => "soft",
+            LinkType::Junction => "junction",
+        };
+        f.write_str(name)
+    }
+}
+impl FromStr for LinkType {
+    type Err = SymorError;
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "copy" => Ok(LinkType::Copy),
+            "hard" => Ok(LinkType::Hard),
+            "soft" => Ok(LinkType::Soft),
+            "junction" => Ok(LinkType::Junction),
+            other => {
+                Err(
+                    SymorError::new(
+                            ErrorCode::InvalidConfiguration,
+                            format!("Invalid link type: {}", other),
+                        )
+                        .with_suggestion(format!("Use one of: {:?}", LinkType::NAMES)),
+                )
+            }
+        }
+    }
+}
+/// Windows error returned when the process may not create symlinks
+const ERROR_PRIVILEGE_NOT_HELD: i32 = 1314;
+/// Cross-volume hard link errors: EXDEV on Unix, ERROR_NOT_SAME_DEVICE on Windows
+fn is_cross_device(error: &io::Error) -> bool {
+    if cfg!(windows) {
+        error.raw_os_error() == Some(17)
+    } else {
+        error.raw_os_error() == Some(18)
+    }
+}
+/// True for symlinks and, on Windows, any reparse point such as a junction
+pub fn is_reparse_point(path: &Path) -> bool {
+    let Ok(metadata) = fs::symlink_metadata(extend(path)) else {
+        return false;
+    };
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::MetadataExt;
+        const FILE_ATTRIBUTE_REPARSE_POINT: u32 = 0x400;
+        metadata.file_attributes() & FILE_ATTRIBUTE_REPARSE_POINT != 0
+    }
+    #[cfg(not(windows))]
+    {
+        metadata.file_type().is_symlink()
+    }
+}
+/// True when `target` is already a link of some kind pointing at `source`
+pub fn is_linked(source: &Path, target: &Path) -> bool {
+    if is_reparse_point(target) {
+        return fs::canonicalize(extend(target)).ok() == fs::canonicalize(extend(source)).ok();
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        if let (Ok(a), Ok(b)) = (fs::metadata(extend(source)), fs::metadata(extend(target))) {
+            return a.is_file() && a.dev() == b.dev() && a.ino() == b.ino();
+        }
+    }
+    false
+}
+pub(crate) fn symlink(source: &Path, target: &Path) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink(source, extend(target))
+    }
+    #[cfg(windows)]
+    {
+        if extend(source).is_dir() {
+            std::os::windows::fs::symlink_dir(source, extend(target))
+        } else {
+            std::os::windows::fs::symlink_file(source, extend(target))
+        }
+    }
+}
+#[cfg(windows)]
+fn junction(source: &Path, target: &Path) -> io::Result<()> {
+    let output = std::process::Command::new("cmd")
+        .arg("/C")
+        .arg("mklink")
+        .arg("/J")
+        .arg(target)
+        .arg(source)
+        .output()?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(String::from_utf8_lossy(&output.stderr).trim().to_string()))
+    }
+}
+#[cfg(not(windows))]
+fn junction(source: &Path, target: &Path) -> io::Result<()> {
+    symlink(source, target)
+}
+fn copy(source: &Path, target: &Path) -> Result<LinkType> {
+    if extend(source).is_dir() {
+        crate::copy_dir_all(source, target)?;
+    } else {
+        fs::copy(extend(source), extend(target))
+            .with_context(|| format!("cannot copy {:?} to {:?}", source, target))?;
+    }
+    Ok(LinkType::Copy)
+}
+/// Creates `target` as a `link_type` link to `source`, falling back when the platform refuses
+///
+/// Hard links fall back to a symlink (or junction) for directories and to a copy across
+/// volumes; symlinks without the Windows symlink privilege fall back to a junction for
+/// directories and a hard link or copy for files. Returns the kind actually created.
+pub fn create_link(source: &Path, target: &Path, link_type: LinkType) -> Result<LinkType> {
+    let source = std::path::absolute(source)?;
+    let source = source.as_path();
+    match link_type {
+        LinkType::Copy => copy(source, target),
+        LinkType::Hard if extend(source).is_dir() => {
+            warn!("Directories cannot be hard linked; linking {:?} with a symlink", source);
+            let fallback = if cfg!(windows) { LinkType::Junction } else { LinkType::Soft };
+            create_link(source, target, fallback)
+        }
+        LinkType::Hard => {
+            match fs::hard_link(extend(source), extend(target)) {
+                Ok(()) => Ok(LinkType::Hard),
+                Err(e) if is_cross_device(&e) => {
+                    warn!("{:?} is on another volume; copying instead of hard linking", target);
+                    copy(source, target)
+                }
+                Err(e) => {
+                    Err(e)
+                        .with_context(|| {
+                            format!("cannot hard link {:?} to {:?}", target, source)
+                        })
+                }
+            }
+        }
+        LinkType::Soft => {
+            match symlink(source, target) {
+                Ok(()) => Ok(LinkType::Soft),
+                Err(e) if e.raw_os_error() == Some(ERROR_PRIVILEGE_NOT_HELD) => {
+                    warn!(
+                        "Creating symlinks needs Developer Mode or administrator rights; linking {:?} another way",
+                        target
+                    );
+                    let fallback = if extend(source).is_dir() {
+                        LinkType::Junction
+                    } else {
+                        LinkType::Hard
+                    };
+                    create_link(source, target, fallback)
+                }
+                Err(e) => {
+                    Err(e)
+                        .with_context(|| format!("cannot symlink {:?} to {:?}", target, source))
+                }
+            }
+        }
+        LinkType::Junction if !extend(source).is_dir() => {
+            warn!("Junctions only work for directories; hard linking {:?}", source);
+            create_link(source, target, LinkType::Hard)
+        }
+        LinkType::Junction => {
+            junction(source, target)
+                .with_context(|| {
+                    format!("cannot create junction {:?} to {:?}", target, source)
+                })?;
+            Ok(LinkType::Junction)
+        }
+    }
+}
+/// Removes a file, directory tree or link at `path` without following links
+pub fn remove_path(path: &Path) -> Result<()> {
+    let long = extend(path);
+    let Ok(metadata) = fs::symlink_metadata(&long) else {
+        return Ok(());
+    };
+    let result = if is_reparse_point(path) {
+        fs::remove_file(&long).or_else(|_| fs::remove_dir(&long))
+    } else if metadata.is_dir() {
+        fs::remove_dir_all(&long)
+    } else {
+        fs::remove_file(&long)
+    };
+    result.with_context(|| format!("cannot remove existing {:?}", path))
+}
+/// Removes `path`, or with `to_trash` moves it to the OS trash so it can be recovered
+pub fn discard_path(path: &Path, to_trash: bool) -> Result<()> {
+    if !to_trash || fs::symlink_metadata(extend(path)).is_err() {
+        return remove_path(path);
+    }
+    trash::delete(path)
+        .map_err(|e| {
+            SymorError::new(
+                    ErrorCode::PermissionDenied,
+                    format!("cannot move {:?} to the trash: {}", path, e),
+                )
+                .with_suggestion(
+                    "Turn trash off with 'sym settings linking --trash false' to delete \
+                     instead"
+                        .to_string(),
+                )
+        })
+}
+/// Recreates the link at `source` as `target` instead of following it, so that junction
+/// loops and links to other volumes are not copied
+pub fn copy_link(source: &Path, target: &Path) -> Result<()> {
+    let destination = fs::read_link(extend(source))
+        .with_context(|| format!("cannot read link {:?}", source))?;
+    let result = if fs::metadata(extend(source)).is_ok_and(|m| m.is_dir()) && cfg!(windows) {
+        junction(&destination, target)
+    } else {
+        symlink(&destination, target)
+    };
+    if let Err(e) = result {
+        warn!("Skipping link {:?} that could not be recreated: {}", source, e);
+    }
+    Ok(())
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+    #[test]
+    fn test_link_types() {
+        assert_eq!("Soft".parse::<LinkType>().unwrap(), LinkType::Soft);
+        assert!("bogus".parse::<LinkType>().is_err());
+        let temp_dir = tempdir().unwrap();
+        let source = temp_dir.path().join("source.txt");
+        fs::write(&source, "data").unwrap();
+        let hard = temp_dir.path().join("hard.txt");
+        assert_eq!(create_link(&source, &hard, LinkType::Hard).unwrap(), LinkType::Hard);
+        assert!(is_linked(&source, &hard));
+        let dir = temp_dir.path().join("dir");
+        fs::create_dir(&dir).unwrap();
+        let linked_dir = temp_dir.path().join("linked_dir");
+        create_link(&dir, &linked_dir, LinkType::Junction).unwrap();
+        assert!(is_reparse_point(&linked_dir));
+        assert!(is_linked(&dir, &linked_dir));
+        assert!(! is_linked(&source, &temp_dir.path().join("missing")));
+        discard_path(&hard, false).unwrap();
+        assert!(! hard.exists());
+        discard_path(&temp_dir.path().join("missing"), true).unwrap();
+    }
+}
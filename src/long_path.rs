@@ -0,0 +1,71 @@
+use std::{borrow::Cow, path::Path};
+/// Longest path Windows file APIs take without the `\\?\` prefix; creating a directory
+/// leaves room for an 8.3 file name in it, so this is 12 short of `MAX_PATH` (260)
+#[cfg(windows)]
+const MAX_PLAIN_PATH: usize = 248;
+/// `path` in the extended-length form Windows file APIs need past 260 characters:
+/// absolute, with backslashes and the `\\?\` prefix. Shorter paths, paths that already
+/// have a prefix and every path on other platforms come back as they are. Keep the
+/// original for messages and comparisons, and extend it only where it reaches `std::fs`.
+pub fn extend(path: &Path) -> Cow<'_, Path> {
+    #[cfg(windows)]
+    {
+        let Ok(absolute) = std::path::absolute(path) else {
+            return Cow::Borrowed(path);
+        };
+        if absolute.as_os_str().len() >= MAX_PLAIN_PATH {
+            if let Some(extended) = absolute.to_str().and_then(verbatim) {
+                return Cow::Owned(extended.into());
+            }
+        }
+    }
+    Cow::Borrowed(path)
+}
+/// Absolute `path` with the prefix that lifts the Windows length limit: `\\?\C:\...` for
+/// drive paths and `\\?\UNC\server\share\...` for network shares. `None` for paths that
+/// already have a prefix, or have neither a drive nor a share to put it on.
+#[cfg(any(windows, test))]
+fn verbatim(path: &str) -> Option<String> {
+    if path.starts_with(r"\\?\") || path.starts_with(r"\\.\") {
+        return None;
+    }
+    let path = path.replace('/', r"\");
+    if let Some(share) = path.strip_prefix(r"\\") {
+        return Some(format!(r"\\?\UNC\{}", share));
+    }
+    let drive = path.as_bytes();
+    let has_drive = drive.len() >= 3 && drive[0].is_ascii_alphabetic() && drive[1] == b':'
+        && drive[2] == b'\\';
+    has_drive.then(|| format!(r"\\?\{}", path))
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    #[test]
+    fn test_long_paths() {
+        assert_eq!(verbatim(r"C:\Users\me\file.txt").unwrap(), r"\\?\C:\Users\me\file.txt");
+        assert_eq!(verbatim("d:/deep/tree").unwrap(), r"\\?\d:\deep\tree");
+        assert_eq!(verbatim(r"\\server\share\dir").unwrap(), r"\\?\UNC\server\share\dir");
+        assert_eq!(verbatim(r"\\?\C:\already"), None);
+        assert_eq!(verbatim(r"\\.\pipe\name"), None);
+        assert_eq!(verbatim("relative/path"), None);
+        assert!(matches!(extend(Path::new("short.txt")), Cow::Borrowed(_)));
+        let temp_dir = tempfile::tempdir().unwrap();
+        let source = temp_dir.path().join("src");
+        let mut deep = source.clone();
+        while deep.as_os_str().len() < 300 {
+            deep.push("a-fairly-long-directory-name");
+        }
+        fs::create_dir_all(extend(&deep)).unwrap();
+        fs::write(extend(&deep.join("file.txt")), "deep").unwrap();
+        let target = temp_dir.path().join("dst");
+        let mirror = crate::Mirror::builder(&source).target(&target).build().unwrap();
+        mirror.sync_once().unwrap();
+        let copy = target.join(deep.strip_prefix(&source).unwrap()).join("file.txt");
+        assert_eq!(fs::read_to_string(extend(&copy)).unwrap(), "deep");
+        fs::remove_file(extend(&deep.join("file.txt"))).unwrap();
+        mirror.sync_once().unwrap();
+        assert!(!extend(&copy).exists());
+    }
+}
\ No newline at end of file
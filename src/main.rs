@@ -1,9 +1,27 @@
 use anyhow::Result;
-use clap::{Parser, Subcommand, ValueHint};
+use clap::{Args, CommandFactory, Parser, Subcommand, ValueHint};
 use env_logger::Env;
 use log::LevelFilter;
 use std::path::{Path, PathBuf};
-use symor::{Mirror, SymorManager};
+use std::process::ExitCode;
+use std::sync::OnceLock;
+use symor::errors::{ErrorCode, RecoveryStrategy, SymorError};
+use symor::config::{
+    ConfigFormat, ConfigOverrides, ConfigTemplate, ConfigValidator, EnvironmentConfig,
+    MetadataFormat, SettingsBundle, TemplateManager,
+};
+use symor::daemon::api::{percent_encode, ApiClient};
+use symor::hooks::{VersionAction, VersionEvent};
+use symor::tui::preview::DiffMode;
+use symor::report::ReportFormat;
+use symor::secrets::SecretScan;
+use symor::symlinks::SymlinkMode;
+use symor::monitoring::summary::SummaryPeriod;
+use symor::performance::PerformanceConfig;
+use symor::{
+    conflicts::{ConflictKind, EditedSide, Resolution}, versioning::VersionFilter, FileVersion, ListPage, Mirror, SymorManager,
+    PathFilter, SymorConfig, WatchedItem,
+};
 #[derive(Parser, Debug)]
 #[command(
     name = "sym",
@@ -42,12 +60,34 @@ EXAMPLES:
   sym unwatch /path/to/file              # Stop watching a file
   sym sync --force                       # Force sync all watched files
   sym stats --detailed --period 60       # Show detailed stats for last 60 seconds
+  sym report --format html -o r.html     # Backup health report for tickets or mail
+  sym dictionary train                   # Compress small similar files with a dictionary
   sym tui --refresh-rate 5               # Start interactive UI with 5s refresh
+  sym top --interval 5                   # Live mirror health table, refreshed every 5s
   sym check /path/to/file                # Check file integrity/status
+  sym cmp ~/docs /mnt/backup/docs        # Verify a mirror after the fact
+  sym bench --size 64M                   # Measure hashing, compression and disk speed
+  sym doctor                             # Diagnose the installation and environment
   sym conflicts                          # Show file conflicts
   sym add-target source.txt dest2.txt    # Add a new target to a source
   sym settings show                      # Display current configuration
 
+EXIT CODES:
+  0  Success
+  1  General failure
+  2  Path or ID is not being watched
+  3  Version not found
+  4  Version storage or state file is corrupt
+  5  Permission denied
+  6  File not found or invalid path
+  7  Disk or storage full
+  8  Invalid configuration
+  9  Network error or timeout
+  10 Refused in maintenance mode
+  11 Versions are under the retention lock
+  12 Refused to version a file that contains secrets
+  64 Invalid command-line usage
+
 For more information on any command, use: sym <command> --help
     "#
 )]
@@ -56,6 +96,40 @@ struct Opt {
     command: Option<Commands>,
     #[arg(short, long, action = clap::ArgAction::Count)]
     verbose: u8,
+    #[arg(
+        long,
+        global = true,
+        value_name = "FILE",
+        value_hint = ValueHint::FilePath,
+        help = "Read settings from a JSON or YAML file instead of the symor home"
+    )]
+    config: Option<PathBuf>,
+    #[arg(
+        long,
+        global = true,
+        value_name = "DIR",
+        value_hint = ValueHint::DirPath,
+        conflicts_with = "portable",
+        help = "Use DIR as the symor home instead of ~/.symor (or set SYMOR_HOME)"
+    )]
+    home: Option<PathBuf>,
+    #[arg(
+        long,
+        global = true,
+        value_name = "DIR",
+        value_hint = ValueHint::DirPath,
+        require_equals = true,
+        help = "Keep all state in DIR (default: .symor next to the binary) instead of the home directory"
+    )]
+    portable: Option<Option<PathBuf>>,
+    #[arg(long, global = true, help = "Skip config validation when loading settings")]
+    no_validate: bool,
+    #[arg(
+        long,
+        global = true,
+        help = "Work on the state files directly even when the daemon is running"
+    )]
+    no_daemon: bool,
     #[arg(
         value_name = "SOURCE",
         value_hint = ValueHint::FilePath,
@@ -100,6 +174,30 @@ enum Commands {
                         true bidirectional sync where any file can be the source of truth."
         )]
         bidirectional: bool,
+        #[arg(
+            long,
+            value_name = "PATTERNS",
+            help = "Only copy files matching these patterns",
+            long_help = "Comma-separated globs or extensions, e.g. '*.conf,*.toml' or \
+                        'conf,toml', matched against each file's path inside the \
+                        directory and its name. Other files are neither copied \
+                        nor deleted from the targets."
+        )]
+        only: Option<String>,
+        #[command(flatten)]
+        tree: TreeOptions,
+        #[arg(
+            long,
+            value_name = "EVENTS",
+            help = "Changes per second that count as a bulk-change storm (default 200, 0 = never)",
+            long_help = "When more than this many changes arrive within a second, as during a \
+                        git checkout or npm install, stop handling them one by one and rescan \
+                        the source once they have settled for two seconds. 0 handles every \
+                        change as it comes. Remembered for the daemon."
+        )]
+        storm_threshold: Option<u32>,
+        #[command(flatten)]
+        dated: DatedTargets,
     },
     List {
         #[arg(
@@ -108,9 +206,19 @@ enum Commands {
             help = "Display comprehensive details for each watched item",
             long_help = "When enabled, shows additional information including: \
                         file sizes, last modification times, total versions stored, \
-                        compression ratios, and monitoring status."
+                        compression ratios, monitoring status, and the files inside \
+                        watched directories."
         )]
         detailed: bool,
+        #[arg(
+            long,
+            default_value_t = 1,
+            value_parser = clap::value_parser!(u32).range(1..),
+            help = "Page of watched items to show, in path order"
+        )]
+        page: u32,
+        #[arg(long, default_value_t = 50, help = "Watched items per page (0 shows all)")]
+        per_page: usize,
     },
     Info {
         #[arg(
@@ -131,17 +239,38 @@ enum Commands {
                         installation of sym in the system PATH."
         )]
         force: bool,
+        #[arg(
+            long,
+            help = "Install into ~/.local/bin (%LOCALAPPDATA%\\symor\\bin on Windows)",
+            long_help = "Install for the current user only, without sudo. If the directory \
+                        is not on PATH, sym offers to add it to your shell profile."
+        )]
+        user: bool,
     },
     Watch {
         #[arg(
             value_name = "PATH",
             value_hint = ValueHint::AnyPath,
-            help = "File or directory to add to version control",
+            required_unless_present = "from_file",
+            help = "File or directory to add to version control ('-' reads a list from stdin)",
             long_help = "The file or directory that will be continuously monitored \
                         for changes. Symor will automatically create versions \
-                        whenever modifications are detected."
+                        whenever modifications are detected. Pass '-' to read \
+                        newline-separated paths from standard input instead."
         )]
-        path: PathBuf,
+        path: Option<PathBuf>,
+        #[arg(
+            long,
+            value_name = "FILE",
+            value_hint = ValueHint::FilePath,
+            conflicts_with = "path",
+            help = "Watch every path listed in FILE, one per line",
+            long_help = "Read newline-separated paths from FILE ('-' for standard \
+                        input) and watch them all at once, writing the watch list \
+                        a single time and taking the initial backups in parallel. \
+                        Blank lines and lines starting with '#' are ignored."
+        )]
+        from_file: Option<PathBuf>,
         #[arg(
             short,
             long,
@@ -151,31 +280,109 @@ enum Commands {
                         control system for entire directory trees."
         )]
         recursive: bool,
+        #[arg(
+            long,
+            value_name = "PATTERNS",
+            help = "Only track files matching these patterns",
+            long_help = "Comma-separated globs or extensions, e.g. '*.conf,*.toml' or \
+                        'conf,toml', matched against each file's path inside the \
+                        directory and its name. Other files in a watched \
+                        directory are ignored."
+        )]
+        only: Option<String>,
+        #[arg(
+            long,
+            value_name = "BOOL",
+            num_args = 0..=1,
+            require_equals = true,
+            default_missing_value = "true",
+            help = "Don't track hidden files and directories",
+            long_help = "Leave out dot-files and everything under dot-directories such \
+                        as .cache or .venv. Defaults to the skip_hidden setting; use \
+                        --skip-hidden=false to include them anyway."
+        )]
+        skip_hidden: Option<bool>,
+        #[arg(
+            long,
+            value_name = "NAME",
+            conflicts_with = "from_file",
+            help = "Alias to use instead of the ID or path, e.g. nginx-conf",
+            long_help = "A short name for the watched item that history, restore, sync, \
+                        status, pause and unwatch accept wherever they take an ID or \
+                        path, and that listings show next to the ID. It must be unique \
+                        and must not contain a slash."
+        )]
+        name: Option<String>,
     },
     Restore {
         #[arg(
-            help = "File ID from 'sym list' command",
-            long_help = "The unique identifier for the watched file, as shown \
-                        in the output of 'sym list'. This identifies which \
-                        file's history to restore from."
+            value_name = "PATH|ID",
+            help = "Watched file path, ID or name from 'sym list'",
+            long_help = "The watched file whose history to restore from, given \
+                        by its path, by the unique identifier shown in the output \
+                        of 'sym list' or by the name given with 'sym watch --name'."
         )]
         file_id: String,
         #[arg(
-            help = "Version ID to restore from history",
+            value_name = "VERSION",
+            help = "Version ID to restore, or @latest, @latest~N, @{yesterday}",
             long_help = "The version identifier to restore, as shown in \
-                        'sym list --detailed'. Use the most recent version \
-                        or a specific historical version."
+                        'sym history'. Relative selectors also work: @latest \
+                        is the newest version, @latest~2 the one two before it, \
+                        and @{yesterday}, @{3 days ago} or @{2026-01-31 12:00} \
+                        (UTC) the newest version stored by then."
         )]
         version_id: String,
         #[arg(
             value_name = "TARGET",
             value_hint = ValueHint::AnyPath,
-            help = "Location to save the restored file",
+            required_unless_present = "stdout",
+            help = "Location to save the restored file, or - for standard output",
             long_help = "The file path where the restored version will be saved. \
                         This can be the original location or a different path \
-                        to preserve the current version."
+                        to preserve the current version. Use - to write the \
+                        content to standard output instead."
         )]
-        target: PathBuf,
+        target: Option<PathBuf>,
+        #[arg(
+            long,
+            conflicts_with = "target",
+            help = "Write the version's content to standard output",
+            long_help = "Print the decompressed content of the version instead of \
+                        writing a file, e.g. 'sym restore cfg <version> --stdout | \
+                        diff - /etc/cfg'. Same as giving - as the target."
+        )]
+        stdout: bool,
+        #[arg(
+            short,
+            long,
+            conflicts_with = "stdout",
+            help = "Show what would change and ask before restoring",
+            long_help = "Print a diff between the target's current content and \
+                        the version, then ask for confirmation before overwriting \
+                        the target."
+        )]
+        preview: bool,
+        #[arg(
+            long,
+            value_name = "MODE",
+            default_value_t = DiffMode::Auto,
+            requires = "preview",
+            help = "How --preview shows changes: auto, line, word or binary",
+            long_help = "line compares whole lines, word marks the changed words of \
+                        each line as [-removed-]{+added+}, and binary compares \
+                        hexdump rows. auto uses binary for binary content, word for \
+                        prose and config files and line for everything else."
+        )]
+        diff: DiffMode,
+        #[arg(
+            short,
+            long,
+            help = "Restore without asking after --preview",
+            long_help = "Answer yes to the --preview confirmation, so the diff is \
+                        printed but the restore goes ahead, e.g. in scripts."
+        )]
+        yes: bool,
     },
     Settings { #[command(subcommand)] action: SettingsCommand },
     Stats {
@@ -197,6 +404,12 @@ enum Commands {
                         time period in seconds. Default is since startup."
         )]
         period: Option<u64>,
+        #[arg(
+            long,
+            value_name = "PATH|ID",
+            help = "Backup statistics for one watched item from its version history"
+        )]
+        item: Option<String>,
     },
     Tui {
         #[arg(
@@ -211,6 +424,58 @@ enum Commands {
         )]
         refresh_rate: u64,
     },
+    Top {
+        #[arg(
+            short,
+            long,
+            value_name = "SECONDS",
+            default_value = "2",
+            help = "Seconds between refreshes",
+            long_help = "How often the mirror table is redrawn. The table is \
+                        updated in place, so it works over SSH and in terminals \
+                        where the full TUI does not."
+        )]
+        interval: u64,
+        #[arg(
+            long,
+            help = "Print the table once and exit",
+            long_help = "Print a single snapshot instead of refreshing, for \
+                        scripts and health checks."
+        )]
+        once: bool,
+    },
+    Cmp {
+        #[arg(value_name = "DIR_A", value_hint = ValueHint::DirPath)]
+        a: PathBuf,
+        #[arg(value_name = "DIR_B", value_hint = ValueHint::DirPath)]
+        b: PathBuf,
+        #[arg(
+            long,
+            help = "Make DIR_B match DIR_A",
+            long_help = "Copy files missing from or differing in DIR_B over from DIR_A and \
+                        remove files only DIR_B has (to the trash when linking.trash is on)."
+        )]
+        fix: bool,
+        #[arg(
+            short,
+            long,
+            value_name = "N",
+            value_parser = clap::value_parser!(u16).range(1..),
+            help = "Hash up to N files at once (default: based on CPU count)"
+        )]
+        jobs: Option<u16>,
+    },
+    Bench {
+        #[arg(
+            long,
+            value_name = "SIZE",
+            help = "Size of the generated sample data (default: 16M)",
+            long_help = "Size of the text-like sample that is hashed, compressed and \
+                        copied. Larger samples give steadier numbers on fast disks."
+        )]
+        size: Option<String>,
+    },
+    Doctor,
     Check {
         #[arg(
             value_name = "PATH",
@@ -220,8 +485,54 @@ enum Commands {
                         If not provided, verifies all watched items."
         )]
         path: Option<PathBuf>,
+        #[arg(
+            long = "where",
+            value_name = "EXPR",
+            help = "Also verify the stored content of versions matching this filter",
+            long_help = "Terms joined with &&, e.g. 'size>1MB && age<7d'. Fields are \
+                        size (B, KB, MB, GB), age (s, m, h, d, w, y) and hash \
+                        (prefix, = or !=); comparisons are < <= > >= = !=."
+        )]
+        filter: Option<String>,
+    },
+    Conflicts { #[command(subcommand)] action: Option<ConflictsCommand> },
+    #[command(
+        long_about = "Checks the file_rules of the config and the Rhai sync rules in \
+                      rules.rhai in the home directory, and shows what they decide for the \
+                      given files. \
+                      fn include(file) gets a map with path, relative, name, ext, size and \
+                      modified, and returns false for files that mirrors skip and the daemon \
+                      does not version. fn resolve(conflict) gets source, target and their \
+                      sizes and modification times, and returns \"keep-source\", \
+                      \"keep-target\" or \"keep-both\" to settle a two-way edit conflict, or \
+                      nothing to quarantine it as usual."
+    )]
+    Rules {
+        #[arg(
+            value_name = "PATH",
+            value_hint = ValueHint::AnyPath,
+            help = "Files to run the rules on"
+        )]
+        paths: Vec<PathBuf>,
+    },
+    Apply {
+        #[arg(
+            value_name = "FILE",
+            value_hint = ValueHint::FilePath,
+            help = "Topology file to apply (default: symor.toml or mirrors.toml in the home)",
+            long_help = "A TOML file of [[mirror]] tables (source, targets, \
+                        bidirectional, only, skip_hidden) and [[watch]] tables (path, \
+                        recursive, only, skip_hidden). Declared mirrors and watches \
+                        are added, or updated when one exists for the same source or \
+                        path, and mirrors and watches it does not declare are removed \
+                        (stored versions are kept). Applying the same file twice \
+                        changes nothing. The daemon adds what the file in the home \
+                        directory declares when it starts, without removing anything."
+        )]
+        file: Option<PathBuf>,
+        #[arg(long, help = "Show the changes without making them")]
+        dry_run: bool,
     },
-    Conflicts,
     AddTarget {
         #[arg(
             value_name = "SOURCE",
@@ -244,7 +555,7 @@ enum Commands {
         #[arg(
             value_name = "PATH",
             value_hint = ValueHint::AnyPath,
-            help = "Specific path to check status for",
+            help = "Specific path, ID or name to check status for",
             long_help = "Check status for a specific file or directory. \
                         If not provided, shows status for all watched items."
         )]
@@ -278,10 +589,10 @@ enum Commands {
     },
     History {
         #[arg(
-            help = "File ID from 'sym list' command",
+            help = "File ID, name or path from 'sym list' command",
             long_help = "The unique identifier for the watched file, as shown \
-                        in the output of 'sym list'. Shows the complete version \
-                        history for this file."
+                        in the output of 'sym list', its 'sym watch --name' alias \
+                        or its path. Shows the complete version history for this file."
         )]
         file_id: String,
         #[arg(
@@ -292,7 +603,63 @@ enum Commands {
             long_help = "Limit the number of versions shown in the history. \
                         Useful for large histories. Shows most recent versions first."
         )]
-        limit: Option<usize>,
+        limit: Option<usize>,        #[arg(
+            long = "where",
+            value_name = "EXPR",
+            help = "Only show versions matching this filter",
+            long_help = "Terms joined with &&, e.g. 'size>1MB && age<7d'. Fields are \
+                        size (B, KB, MB, GB), age (s, m, h, d, w, y) and hash \
+                        (prefix, = or !=); comparisons are < <= > >= = !=."
+        )]
+        filter: Option<String>,
+        #[arg(
+            long,
+            help = "Draw a timeline of when versions were stored",
+            long_help = "Show one row per hour, day, week, month or year (whichever \
+                        fits the history in 30 rows) with a bar for the versions \
+                        stored then and the size of the newest, so the backup cadence \
+                        and the file's growth are visible at a glance. Times are UTC."
+        )]
+        graph: bool,
+    },
+    Search {
+        #[arg(
+            help = "Regular expression to look for",
+            long_help = "A regular expression matched against each line of every stored \
+                        version, e.g. 'max_connections\\s*=' to find which versions \
+                        still contained a setting."
+        )]
+        pattern: String,
+        #[arg(
+            long,
+            value_name = "GLOB",
+            help = "Only search files whose path matches this glob",
+            long_help = "Limit the search to watched files whose path matches the glob, \
+                        e.g. '*.conf' or '/etc/**'."
+        )]
+        path: Option<String>,
+        #[arg(
+            long,
+            value_name = "WHEN",
+            help = "Only search versions stored since this time",
+            long_help = "Skip versions stored before this time: yesterday, '3 days ago' \
+                        or a UTC date such as 2026-01-31 or '2026-01-31 12:00'."
+        )]
+        since: Option<String>,
+        #[arg(short, long, help = "Match case-insensitively")]
+        ignore_case: bool,
+    },
+    #[command(name = "find-hash")]
+    FindHash {
+        #[arg(
+            value_name = "HASH|FILE",
+            help = "Content hash (or a prefix of it) to look up, or a file to hash",
+            long_help = "An MD5 content hash as shown by 'sym history', or its first \
+                        few characters. When a file path is given, its content is \
+                        hashed and looked up instead, answering whether any watched \
+                        file has a stored copy of it."
+        )]
+        hash: String,
     },
     Clean {
         #[arg(
@@ -322,22 +689,49 @@ enum Commands {
                         even if they would otherwise be cleaned up."
         )]
         keep: usize,
+        #[arg(
+            long = "where",
+            value_name = "EXPR",
+            help = "Only remove versions matching this filter",
+            long_help = "Terms joined with &&, e.g. 'size>1MB && age<7d'. Fields are \
+                        size (B, KB, MB, GB), age (s, m, h, d, w, y) and hash \
+                        (prefix, = or !=); comparisons are < <= > >= = !=."
+        )]
+        filter: Option<String>,
+        #[arg(
+            long,
+            conflicts_with_all = ["file", "filter"],
+            help = "Tidy the symor home directory instead of pruning versions",
+            long_help = "Remove stale group directories, temp files older than an hour, \
+                        rotated logs older than a week and .tmp-sync files left \
+                        next to mirrors by interrupted syncs, and report the space \
+                        reclaimed. Combine with --dry-run to only list them."
+        )]
+        tidy_home: bool,
     },
     Unwatch {
         #[arg(
             value_name = "PATH",
             value_hint = ValueHint::AnyPath,
-            help = "File or directory to stop watching",
+            help = "File or directory (or its ID or name) to stop watching",
             long_help = "Remove the specified file or directory from version control monitoring. \
                         No new versions will be created for this path."
         )]
         path: PathBuf,
+        #[arg(
+            long,
+            help = "Also delete the stored versions",
+            long_help = "Delete the stored versions of this path as well, freeing their \
+                        space. They go to the OS trash instead when the linking trash \
+                        setting is on."
+        )]
+        purge: bool,
     },
     Sync {
         #[arg(
             value_name = "PATH",
             value_hint = ValueHint::AnyPath,
-            help = "Specific path to sync",
+            help = "Specific path, ID or name to sync",
             long_help = "Sync only the specified file or directory. \
                         If not provided, syncs all watched items."
         )]
@@ -350,7 +744,109 @@ enum Commands {
                         Useful for ensuring consistency or after manual file modifications."
         )]
         force: bool,
+        #[arg(
+            short,
+            long,
+            value_name = "N",
+            value_parser = clap::value_parser!(u16).range(1..),
+            help = "Back up up to N items at once (default: based on CPU count)"
+        )]
+        jobs: Option<u16>,
+    },
+    Pause {
+        #[arg(
+            value_name = "PATH",
+            value_hint = ValueHint::AnyPath,
+            required_unless_present = "all",
+            help = "Watched item (path, ID or name) or mirror source to pause",
+            long_help = "Stop the daemon from versioning this watched item, or from syncing \
+                        the mirror with this source, until 'sym resume'. Handy while a large \
+                        refactor or package upgrade churns through files."
+        )]
+        path: Option<PathBuf>,
+        #[arg(long, conflicts_with = "path", help = "Pause every mirror and watched item")]
+        all: bool,
+    },
+    Resume {
+        #[arg(
+            value_name = "PATH",
+            value_hint = ValueHint::AnyPath,
+            required_unless_present = "all",
+            help = "Watched item (path, ID or name) or mirror source to resume"
+        )]
+        path: Option<PathBuf>,
+        #[arg(
+            long,
+            conflicts_with = "path",
+            help = "Lift the global pause and every per-item one"
+        )]
+        all: bool,
+    },
+    Annotate {
+        #[arg(
+            value_name = "PATH|ID",
+            help = "Watched item (path, ID or name) to describe"
+        )]
+        item: String,
+        #[arg(
+            value_name = "TEXT",
+            help = "Description to store, e.g. \"prod nginx config, owned by ops\"",
+            long_help = "Free text kept with the watched item and shown by 'sym list \
+                        --detailed', 'sym status' and the TUI, so everyone on a shared \
+                        machine knows why it is watched. Without TEXT the current \
+                        description is printed."
+        )]
+        description: Option<String>,
+        #[arg(long, conflicts_with = "description", help = "Remove the description")]
+        clear: bool,
+    },
+    Report {
+        #[arg(
+            short,
+            long,
+            value_name = "FORMAT",
+            default_value = "md",
+            help = "Report format: md, csv or html",
+            long_help = "md prints a Markdown summary with tables, ready to paste into a \
+                        ticket; html a standalone page for mail; csv one row per watched \
+                        item with raw byte counts, for spreadsheets."
+        )]
+        format: ReportFormat,
+        #[arg(
+            long,
+            value_name = "AGE",
+            default_value = "7d",
+            help = "Flag items without a backup this recent, e.g. 36h or 2w",
+            long_help = "Watched items whose newest version is older than AGE are listed \
+                        as stale; mirror errors older than AGE are left out. Takes s, m, h, \
+                        d, w or y."
+        )]
+        stale: String,
+        #[arg(
+            short,
+            long,
+            value_name = "FILE",
+            value_hint = ValueHint::FilePath,
+            help = "Write the report to FILE instead of standard output"
+        )]
+        output: Option<PathBuf>,
     },
+    #[command(
+        long_about = "Shows or switches maintenance mode. While it is on, backups, cleans, \
+                      restores and other changes to the version store are refused by the CLI \
+                      and the daemon, which also holds its scans and mirrors; use it during \
+                      store migrations, verification runs or restores from an external backup."
+    )]
+    Maintenance { #[command(subcommand)] action: Option<MaintenanceCommand> },
+    #[command(
+        long_about = "Shows or trains the compression dictionary. Many small, similar files \
+                      (config files, manifests) compress poorly one by one; 'sym dictionary \
+                      train' builds a shared dictionary from the newest version of each small \
+                      file in the store, and new versions of files up to 128 KiB are then \
+                      compressed against it. Versions keep the dictionary they were stored \
+                      with, so retraining or switching it off never affects them."
+    )]
+    Dictionary { #[command(subcommand)] action: Option<DictionaryCommand> },
     Rip {
         #[arg(
             long,
@@ -361,6 +857,170 @@ enum Commands {
         )]
         keep_data: bool,
     },
+    Man {
+        #[arg(
+            long,
+            value_name = "PATH",
+            value_hint = ValueHint::DirPath,
+            help = "Write one page per command into PATH",
+            long_help = "Write sym.1 plus a sym-<command>.1 page for every subcommand \
+                        into PATH. Without this option the main page is printed to stdout."
+        )]
+        dir: Option<PathBuf>,
+    },
+    Init {
+        #[arg(
+            long,
+            help = "Accept every default without prompting",
+            long_help = "Write the default configuration without asking any questions. \
+                        No binary or daemon service is installed. Useful for scripted \
+                        installs."
+        )]
+        defaults: bool,
+    },
+    Daemon { #[command(subcommand)] action: DaemonCommand },
+}
+#[derive(Args, Debug)]
+struct TreeOptions {
+    #[arg(
+        long,
+        value_name = "BOOL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true",
+        help = "Don't mirror hidden files and directories",
+        long_help = "Leave out dot-files and everything under dot-directories such \
+                    as .cache or .venv. Defaults to the skip_hidden setting; use \
+                    --skip-hidden=false to include them anyway."
+    )]
+    skip_hidden: Option<bool>,
+    #[arg(
+        long,
+        value_name = "MODE",
+        help = "Symlinks inside the source: follow, preserve or skip",
+        long_help = "follow (the default) copies what each link points to, leaving out \
+                    dangling links and links that lead back into a directory being \
+                    copied; preserve recreates each link on the targets, pointing where \
+                    the original points; skip leaves links out and removes copies of them \
+                    from the targets. Remembered for the daemon."
+    )]
+    symlinks: Option<SymlinkMode>,
+}
+#[derive(Args, Debug)]
+struct DatedTargets {
+    #[arg(
+        long,
+        help = "Move dated targets on to the new date while running",
+        long_help = "Targets may contain strftime tokens (%Y, %y, %m, %d, %F, %H, %M, %S, \
+                    %j), e.g. /backup/%F/etc, expanded in local time when the mirror \
+                    starts. With --rollover the daemon re-expands them every minute and \
+                    syncs into the new path once the date changes. Remembered for the \
+                    daemon."
+    )]
+    rollover: bool,
+    #[arg(
+        long,
+        value_name = "N",
+        value_parser = clap::value_parser!(u32).range(1..),
+        help = "Keep only the newest N dated copies of each dated target",
+        long_help = "Remove the oldest dated copies, e.g. /backup/2024-03-01, so that N \
+                    remain, counting today's. Runs when the mirror starts and, with \
+                    --rollover, whenever it moves on to a new date. The date has to sit in \
+                    one directory name. Removed copies go to the trash when linking.trash \
+                    is on. Remembered for the daemon."
+    )]
+    keep_rotations: Option<u32>,
+    #[arg(
+        long,
+        help = "Hard link files unchanged since the previous dated copy",
+        long_help = "When a dated target starts a new copy, hard link the files that did \
+                    not change since the previous copy instead of copying them, as \
+                    rsnapshot does, so N daily copies of a mostly static tree cost little \
+                    more than one. Files are unlinked before they are rewritten, so \
+                    earlier copies keep their contents. Remembered for the daemon."
+    )]
+    link_rotations: bool,
+}
+#[derive(Subcommand, Debug)]
+enum ConflictsCommand {
+    List,
+    Resolve {
+        #[arg(value_name = "ID", help = "Quarantine entry as ITEM/TIMESTAMP, or ITEM for its latest")]
+        id: String,
+        #[arg(
+            long,
+            value_name = "SIDE",
+            value_parser = parse_resolution,
+            help = "Version to keep: source, target or both (the target's copy is saved beside the source)"
+        )]
+        keep: Resolution,
+    },
+}
+#[derive(Subcommand, Debug)]
+enum MaintenanceCommand {
+    On {
+        #[arg(long, value_name = "TEXT", help = "Why, shown when an operation is refused")]
+        reason: Option<String>,
+    },
+    Off,
+}
+#[derive(Subcommand, Debug)]
+enum DictionaryCommand {
+    Train,
+    Off,
+}
+#[derive(Subcommand, Debug)]
+enum DaemonCommand {
+    Run {
+        #[arg(
+            long,
+            value_name = "SECS",
+            default_value_t = 2,
+            help = "Seconds between scans of watched files"
+        )]
+        interval: u64,
+        #[arg(
+            long,
+            value_name = "ADDR",
+            num_args = 0..=1,
+            default_missing_value = symor::daemon::api::DEFAULT_ADDR,
+            help = "Serve the HTTP control API on a fixed address (default 127.0.0.1:7437)",
+            long_help = "Serve the HTTP control API on a fixed address (default 127.0.0.1:7437). \
+                        Without it the API listens on a random loopback port, recorded in \
+                        the home directory, so CLI commands can still reach the daemon."
+        )]
+        api: Option<std::net::SocketAddr>,
+        #[arg(long, conflicts_with = "api", help = "Do not serve the control API at all")]
+        no_api: bool,
+        #[arg(long, hide = true, help = "Run under the Windows Service Control Manager")]
+        service: bool,
+    },
+    ApiToken {
+        #[arg(long, help = "Replace the token with a new one")]
+        rotate: bool,
+    },
+    InstallService {
+        #[arg(long, help = "Install a system-wide unit instead of a user unit")]
+        system: bool,
+        #[arg(long, help = "Overwrite an existing unit")]
+        force: bool,
+    },
+    UninstallService {
+        #[arg(long, help = "Remove the system-wide unit")]
+        system: bool,
+    },
+    Enable {
+        #[arg(long, help = "Enable the system-wide unit")]
+        system: bool,
+    },
+    Disable {
+        #[arg(long, help = "Disable the system-wide unit")]
+        system: bool,
+    },
+    Status {
+        #[arg(long, help = "Query the system-wide unit")]
+        system: bool,
+    },
 }
 #[derive(Subcommand, Debug)]
 enum SettingsCommand {
@@ -372,36 +1032,347 @@ enum SettingsCommand {
         max_versions: Option<usize>,
         #[arg(long)]
         compression: Option<u8>,
+        #[arg(long, help = "Back up locked files from a Volume Shadow Copy (Windows)")]
+        shadow_copy: Option<bool>,
+        #[arg(long, help = "Days to keep .pre-restore copies (0 keeps them)")]
+        pre_restore_days: Option<u64>,
+        #[arg(long, help = "Encoding of item files and the hash cache: json or msgpack")]
+        metadata_format: Option<MetadataFormat>,
+        #[arg(
+            long,
+            value_name = "DAYS",
+            help = "Keep every version at least DAYS days; no command can delete it sooner",
+            long_help = "Compliance retention lock: versions younger than DAYS are kept by \
+                        'sym clean', the version limit and disk-full cleanup, and 'sym \
+                        unwatch --purge' is refused. The lock can be lengthened at any \
+                        time but not shortened or switched off (0) while that would release \
+                        versions it holds."
+        )]
+        retention_lock_days: Option<u64>,
+        #[arg(
+            long,
+            value_name = "MODE",
+            help = "Check files for secrets before versioning them: off, warn or strict",
+            long_help = "Look for obvious secrets (private keys, AWS keys, GitHub and Slack \
+                        tokens) in files before versioning them, as versions are stored \
+                        unencrypted. 'warn' logs a warning and stores the version, 'strict' \
+                        refuses to store it, 'off' does not scan."
+        )]
+        secret_scan: Option<SecretScan>,
+        #[arg(
+            long,
+            value_name = "MODE",
+            help = "Watched symlinks: follow, preserve or skip",
+            long_help = "follow (the default) versions the file a watched symlink points \
+                        to; preserve versions the link itself, recording where it points, \
+                        so restoring the version recreates the link; skip leaves watched \
+                        links unversioned."
+        )]
+        symlinks: Option<SymlinkMode>,
     },
     Linking {
         #[arg(long)]
         link_type: Option<String>,
         #[arg(long)]
         preserve_permissions: Option<bool>,
+        #[arg(
+            long,
+            help = "Give restored files their owner from backup time, and mirrored files \
+                    their source's owner (needs root)"
+        )]
+        preserve_ownership: Option<bool>,
+        #[arg(
+            long,
+            value_name = "RULES",
+            help = "Owner mapping rules for preserved ownership, like '1000->33,root->current'",
+            long_help = "Comma-separated rules changing the owners that preserved ownership \
+                        gives restored and mirrored files, for copies made as another user or \
+                        into a container. Each rule is FROM->TO with IDs, user or group \
+                        names, or 'current' for the user running sym; '*' as FROM matches any \
+                        owner. Rules map user and group IDs alike unless prefixed with 'uid:' \
+                        or 'gid:'. The first matching rule wins. An empty list clears them."
+        )]
+        owner_map: Option<String>,
+        #[arg(long, help = "Move replaced or deleted targets and purged versions to the trash")]
+        trash: Option<bool>,
+        #[arg(
+            long,
+            value_name = "N",
+            help = "Sync each mirror at most N times a minute, coalescing extra changes (0: no limit)"
+        )]
+        max_syncs_per_minute: Option<u32>,
+        #[arg(
+            long,
+            value_name = "POLICY",
+            help = "Names differing only in case on a case-insensitive target: skip or rename",
+            long_help = "What directory syncs do when the source has names that differ only \
+                        in case, like Readme.md and README.md, and the target's file system \
+                        (macOS, Windows) would store them as one file. skip mirrors the \
+                        first in byte order and leaves the others out; rename mirrors them \
+                        as Readme (2).md and so on. Both report each collision as a \
+                        conflict."
+        )]
+        case_collisions: Option<symor::case::CaseCollisions>,
+        #[arg(
+            long,
+            help = "Treat names differing only in Unicode normal form (NFC/NFD) as the same"
+        )]
+        normalize_unicode: Option<bool>,
+    },
+    Files {
+        #[arg(long, help = "Skip hidden files and directories in new watches and mirrors")]
+        skip_hidden: Option<bool>,
+    },
+    #[command(
+        long_about = "Commands run through the shell when a version is created, restored or \
+                      pruned, e.g. to index or replicate new versions. They get SYMOR_EVENT, \
+                      SYMOR_ITEM_ID, SYMOR_ITEM_PATH, SYMOR_VERSION_ID, SYMOR_VERSION_HASH and \
+                      SYMOR_VERSION_SIZE in the environment, plus SYMOR_RESTORE_TARGET for \
+                      restores."
+    )]
+    Hooks {
+        #[arg(long, value_name = "COMMAND", help = "Run COMMAND after a version is created")]
+        on_created: Vec<String>,
+        #[arg(long, value_name = "COMMAND", help = "Run COMMAND after a version is restored")]
+        on_restored: Vec<String>,
+        #[arg(long, value_name = "COMMAND", help = "Run COMMAND after a version is pruned")]
+        on_pruned: Vec<String>,
+        #[arg(long, help = "Remove all hook commands before adding the given ones")]
+        clear: bool,
+    },
+    Notifications {
+        #[arg(
+            long,
+            value_name = "PERIOD",
+            help = "Have the daemon send a daily or weekly activity summary"
+        )]
+        summary: Option<SummaryPeriod>,
+        #[arg(
+            long = "command",
+            value_name = "COMMAND",
+            help = "Pipe each summary into COMMAND, e.g. 'mail -s Symor ops@example.com'"
+        )]
+        commands: Vec<String>,
+        #[arg(long, help = "Turn summaries off and drop their commands before applying the rest")]
+        clear: bool,
+    },
+    Performance {
+        #[arg(
+            long,
+            value_name = "N",
+            help = "Threads compressing versions over 4 MiB in parallel chunks (0 for auto)"
+        )]
+        compression_threads: Option<usize>,
+        #[arg(
+            long,
+            value_name = "BOOL",
+            help = "Run the daemon's syncs and backups at low CPU and IO priority"
+        )]
+        background_priority: Option<bool>,
+        #[arg(
+            long,
+            value_name = "SIZE",
+            help = "Memory budget for hashing, compression and backups, e.g. 512M (0 for none)"
+        )]
+        max_memory: Option<String>,
     },
     Home { #[arg(value_name = "PATH", value_hint = ValueHint::DirPath)] path: PathBuf },
     Init,
+    Export {
+        #[arg(value_name = "FILE", value_hint = ValueHint::FilePath)]
+        file: Option<PathBuf>,
+        #[arg(long, help = "Output format: json or yaml (defaults to the file extension)")]
+        format: Option<ConfigFormat>,
+        #[arg(long, help = "Include custom config templates")]
+        templates: bool,
+    },
+    Import {
+        #[arg(value_name = "FILE", value_hint = ValueHint::FilePath)]
+        file: PathBuf,
+        #[arg(long, help = "Input format: json or yaml (defaults to the file extension)")]
+        format: Option<ConfigFormat>,
+    },
+    Template { #[command(subcommand)] action: TemplateCommand },
+    Env { #[command(subcommand)] action: EnvCommand },
 }
-fn main() -> Result<()> {
-    let opt = Opt::parse();
-    let log_level = match opt.verbose {
-        0 => LevelFilter::Warn,
-        1 => LevelFilter::Info,
-        2 => LevelFilter::Debug,
-        _ => LevelFilter::Trace,
-    };
-    env_logger::Builder::from_env(
-            Env::default().default_filter_or(log_level.to_string()),
-        )
-        .init();
-    match opt.command {
-        Some(Commands::Mirror { source, targets, bidirectional }) => {
-            handle_mirror(source, targets, bidirectional)?;
+#[derive(Subcommand, Debug)]
+enum EnvCommand {
+    List,
+    Add {
+        name: String,
+        #[arg(value_name = "CONFIG_FILE", value_hint = ValueHint::FilePath)]
+        config_path: PathBuf,
+        #[arg(long, help = "Only select this environment through SYMOR_ENV")]
+        no_auto_switch: bool,
+        #[arg(
+            long = "var",
+            value_name = "NAME=VALUE",
+            value_parser = parse_variable,
+            help = "Value for a {{NAME}} placeholder in the environment's config"
+        )]
+        vars: Vec<(String, String)>,
+    },
+    Remove { name: String },
+}
+#[derive(Subcommand, Debug)]
+enum TemplateCommand {
+    List,
+    Show { name: String },
+    Apply {
+        name: String,
+        #[arg(long)]
+        max_versions: Option<usize>,
+        #[arg(long)]
+        compression: Option<u8>,
+        #[arg(long)]
+        link_type: Option<String>,
+        #[arg(
+            long = "var",
+            value_name = "NAME=VALUE",
+            value_parser = parse_variable,
+            help = "Value for a {{NAME}} placeholder in the template"
+        )]
+        vars: Vec<(String, String)>,
+    },
+    Save {
+        name: String,
+        #[arg(long, help = "Template description")]
+        description: Option<String>,
+    },
+}
+fn main() -> ExitCode {
+    let opt = match Opt::try_parse() {
+        Ok(opt) => opt,
+        Err(e) if e.use_stderr() => {
+            let _ = e.print();
+            return ExitCode::from(64);
+        }
+        Err(e) => e.exit(),
+    };
+    match run(opt) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {:#}", e);
+            let code = e
+                .downcast_ref::<SymorError>()
+                .map(|err| {
+                    if let Some(suggestion) = &err.recovery_suggestion {
+                        eprintln!("Hint: {}", suggestion);
+                    }
+                    err.code.exit_code()
+                })
+                .unwrap_or(1);
+            ExitCode::from(code)
+        }
+    }
+}
+#[derive(Debug, Default)]
+struct GlobalOptions {
+    config: Option<PathBuf>,
+    no_validate: bool,
+    no_daemon: bool,
+    portable: bool,
+}
+static GLOBAL_OPTIONS: OnceLock<GlobalOptions> = OnceLock::new();
+fn load_config(manager: &mut SymorManager) -> Result<()> {
+    let options = GLOBAL_OPTIONS.get_or_init(GlobalOptions::default);
+    manager.set_validate_on_load(!options.no_validate);
+    match &options.config {
+        Some(path) => manager.load_config_from(path)?,
+        None => manager.load_config()?,
+    }
+    Ok(())
+}
+fn open_manager() -> Result<SymorManager> {
+    let mut manager = SymorManager::new()?;
+    load_config(&mut manager)?;
+    manager.load_watched_items()?;
+    manager.load_mirrors()?;
+    Ok(manager)
+}
+/// The running daemon's control API, which state-changing commands go through so they
+/// don't race it; `None` with `--no-daemon` or when no daemon serves the API
+fn daemon_client() -> Result<Option<ApiClient>> {
+    if GLOBAL_OPTIONS.get_or_init(GlobalOptions::default).no_daemon {
+        return Ok(None);
+    }
+    let mut manager = SymorManager::new()?;
+    load_config(&mut manager)?;
+    Ok(ApiClient::connect(&manager.config().home_dir))
+}
+fn not_watched(path: &Path) -> anyhow::Error {
+    SymorError::not_watched(&path.display().to_string()).into()
+}
+fn parse_filter(filter: Option<String>) -> Result<Option<VersionFilter>> {
+    Ok(filter.map(|expression| expression.parse()).transpose()?)
+}
+fn run(opt: Opt) -> Result<()> {
+    let log_level = match opt.verbose {
+        0 => LevelFilter::Warn,
+        1 => LevelFilter::Info,
+        2 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    };
+    let daemon_run = matches!(
+        opt.command, Some(Commands::Daemon { action : DaemonCommand::Run { .. } })
+    );
+    if !daemon_run {
+        env_logger::Builder::from_env(
+                Env::default().default_filter_or(log_level.to_string()),
+            )
+            .init();
+    }
+    let portable_home = match opt.portable {
+        Some(Some(dir)) => Some(std::path::absolute(dir)?),
+        Some(None) => {
+            let home_dir = symor::portable_home_dir()
+                .ok_or_else(|| {
+                    SymorError::new(
+                        ErrorCode::InvalidPath,
+                        "Cannot locate the sym binary for portable mode".to_string(),
+                    )
+                })?;
+            Some(home_dir)
+        }
+        None if opt.home.is_some() || symor::home_override().is_some() => None,
+        None => symor::detect_portable_home(),
+    };
+    let _ = GLOBAL_OPTIONS
+        .set(GlobalOptions {
+            config: opt.config,
+            no_validate: opt.no_validate,
+            no_daemon: opt.no_daemon,
+            portable: portable_home.is_some(),
+        });
+    if let Some(home_dir) = opt.home {
+        symor::set_home_override(Some(std::path::absolute(home_dir)?));
+    } else if portable_home.is_some() {
+        symor::set_home_override(portable_home);
+    }
+    match opt.command {
+        Some(
+            Commands::Mirror {
+                source,
+                targets,
+                bidirectional,
+                only,
+                tree,
+                storm_threshold,
+                dated,
+            },
+        ) => {
+            handle_mirror(source, targets, bidirectional, only, tree, storm_threshold, dated)?;
         }
         None => {
             if let Some(source) = opt.source {
                 if !opt.targets.is_empty() {
-                    handle_mirror(source, opt.targets, false)?;
+                    let dated = DatedTargets {
+                        rollover: false,
+                        keep_rotations: None,
+                        link_rotations: false,
+                    };
+                    let tree = TreeOptions { skip_hidden: None, symlinks: None };
+                    handle_mirror(source, opt.targets, false, None, tree, None, dated)?;
                 } else {
                     Opt::parse_from(&["sym", "--help"]);
                 }
@@ -409,8 +1380,8 @@ fn main() -> Result<()> {
                 Opt::parse_from(&["sym", "--help"]);
             }
         }
-        Some(Commands::List { detailed }) => {
-            handle_list(detailed)?;
+        Some(Commands::List { detailed, page, per_page }) => {
+            handle_list(detailed, page as usize, per_page)?;
         }
         Some(Commands::AddTarget { source, target }) => {
             handle_add_target(source, target)?;
@@ -418,14 +1389,25 @@ fn main() -> Result<()> {
         Some(Commands::Info { path }) => {
             handle_info(path)?;
         }
-        Some(Commands::Install { force }) => {
-            handle_install(force)?;
+        Some(Commands::Install { force, user }) => {
+            handle_install(force, user)?;
         }
-        Some(Commands::Watch { path, recursive }) => {
-            handle_watch(path, recursive)?;
+        Some(Commands::Watch { path, from_file, recursive, only, skip_hidden, name }) => {
+            let list = from_file.or(path.clone().filter(|p| p.as_os_str() == "-"));
+            match (list, path) {
+                (Some(_), _) if name.is_some() => {
+                    return Err(anyhow::anyhow!("--name only works when watching a single path"));
+                }
+                (Some(list), _) => handle_watch_list(list, recursive, only, skip_hidden)?,
+                (None, Some(path)) => {
+                    handle_watch(path, recursive, only, skip_hidden, name)?;
+                }
+                (None, None) => unreachable!("clap requires a path or --from-file"),
+            }
         }
-        Some(Commands::Restore { file_id, version_id, target }) => {
-            handle_restore(file_id, version_id, target)?;
+        Some(Commands::Restore { file_id, version_id, target, stdout, preview, diff, yes }) => {
+            let target = target.filter(|t| !stdout && t.as_os_str() != "-");
+            handle_restore(file_id, version_id, target, preview.then_some(diff), yes)?;
         }
         Some(Commands::Settings { action }) => {
             handle_settings(action)?;
@@ -433,17 +1415,50 @@ fn main() -> Result<()> {
         Some(Commands::Rip { keep_data }) => {
             handle_rip(keep_data)?;
         }
-        Some(Commands::Stats { detailed, period }) => {
+        Some(Commands::Init { defaults }) => {
+            handle_init(defaults)?;
+        }
+        Some(Commands::Man { dir }) => {
+            handle_man(dir)?;
+        }
+        Some(Commands::Daemon { action }) => {
+            handle_daemon(action)?;
+        }
+        Some(Commands::Stats { item: Some(item), .. }) => {
+            handle_item_stats(&item)?;
+        }
+        Some(Commands::Stats { detailed, period, item: None }) => {
             handle_stats(detailed, period)?;
         }
         Some(Commands::Tui { refresh_rate }) => {
             handle_tui(refresh_rate)?;
         }
-        Some(Commands::Conflicts) => {
+        Some(Commands::Top { interval, once }) => {
+            handle_top(interval, once)?;
+        }
+        Some(Commands::Cmp { a, b, fix, jobs }) => {
+            handle_cmp(&a, &b, fix, jobs.map(usize::from))?;
+        }
+        Some(Commands::Bench { size }) => {
+            handle_bench(size.as_deref())?;
+        }
+        Some(Commands::Doctor) => {
+            handle_doctor()?;
+        }
+        Some(Commands::Apply { file, dry_run }) => {
+            handle_apply(file, dry_run)?;
+        }
+        Some(Commands::Conflicts { action: None | Some(ConflictsCommand::List) }) => {
             handle_conflicts()?;
         }
-        Some(Commands::Check { path }) => {
-            handle_check(path)?;
+        Some(Commands::Conflicts { action: Some(ConflictsCommand::Resolve { id, keep }) }) => {
+            handle_resolve_conflict(&id, keep)?;
+        }
+        Some(Commands::Rules { paths }) => {
+            handle_rules(&paths)?;
+        }
+        Some(Commands::Check { path, filter }) => {
+            handle_check(path, parse_filter(filter)?)?;
         }
         Some(Commands::Status { path, verbose }) => {
             handle_status(path, verbose)?;
@@ -451,17 +1466,44 @@ fn main() -> Result<()> {
         Some(Commands::Unmirror { source, target }) => {
             handle_unmirror(source, target)?;
         }
-        Some(Commands::History { file_id, limit }) => {
-            handle_history(file_id, limit)?;
+        Some(Commands::History { file_id, limit, filter, graph }) => {
+            handle_history(file_id, limit, parse_filter(filter)?, graph)?;
+        }
+        Some(Commands::Search { pattern, path, since, ignore_case }) => {
+            handle_search(pattern, path, since, ignore_case)?;
+        }
+        Some(Commands::FindHash { hash }) => {
+            handle_find_hash(hash)?;
+        }
+        Some(Commands::Clean { dry_run, tidy_home: true, .. }) => {
+            handle_clean_home(dry_run)?;
+        }
+        Some(Commands::Clean { dry_run, file, keep, filter, .. }) => {
+            handle_clean(dry_run, file, keep, parse_filter(filter)?)?;
+        }
+        Some(Commands::Unwatch { path, purge }) => {
+            handle_unwatch(path, purge)?;
         }
-        Some(Commands::Clean { dry_run, file, keep }) => {
-            handle_clean(dry_run, file, keep)?;
+        Some(Commands::Pause { path, .. }) => {
+            handle_pause(path, true)?;
         }
-        Some(Commands::Unwatch { path }) => {
-            handle_unwatch(path)?;
+        Some(Commands::Resume { path, .. }) => {
+            handle_pause(path, false)?;
         }
-        Some(Commands::Sync { path, force }) => {
-            handle_sync(path, force)?;
+        Some(Commands::Annotate { item, description, clear }) => {
+            handle_annotate(item, description, clear)?;
+        }
+        Some(Commands::Report { format, stale, output }) => {
+            handle_report(format, &stale, output)?;
+        }
+        Some(Commands::Maintenance { action }) => {
+            handle_maintenance(action)?;
+        }
+        Some(Commands::Dictionary { action }) => {
+            handle_dictionary(action)?;
+        }
+        Some(Commands::Sync { path, force, jobs }) => {
+            handle_sync(path, force, jobs.map(usize::from))?;
         }
     }
     Ok(())
@@ -470,7 +1512,13 @@ fn handle_mirror(
     source: PathBuf,
     targets: Vec<PathBuf>,
     bidirectional: bool,
+    only: Option<String>,
+    tree: TreeOptions,
+    storm_threshold: Option<u32>,
+    dated: DatedTargets,
 ) -> Result<()> {
+    let only = only.as_deref().map(PathFilter::split_list).unwrap_or_default();
+    PathFilter::new(&only)?;
     println!("Symor Mirror");
     println!("============");
     println!("");
@@ -495,6 +1543,7 @@ fn handle_mirror(
         }
     }
     for target in &targets {
+        let target = &symor::dated::expand(target, std::time::SystemTime::now())?;
         if !target.exists() {
             if source.is_dir() {
                 println!(
@@ -515,15 +1564,57 @@ fn handle_mirror(
             }
         }
     }
-    let mut manager = SymorManager::new()?;
-    manager.load_config()?;
-    manager.load_watched_items()?;
+    let mut manager = open_manager()?;
     manager.watch(source.clone(), false)?;
-    let mirror = Mirror::new_with_bidirectional(
-        source.clone(),
-        targets.clone(),
-        bidirectional,
-    )?;
+    manager
+        .add_mirror(&source, &targets, bidirectional, &only, tree.skip_hidden, storm_threshold)?;
+    if dated.rollover {
+        manager.set_mirror_rollover(&source, true)?;
+    }
+    if dated.keep_rotations.is_some() {
+        manager.set_mirror_rotations(&source, dated.keep_rotations.map(|n| n as usize))?;
+    }
+    if dated.link_rotations {
+        manager.set_mirror_link_rotations(&source, true)?;
+    }
+    if let Some(mode) = tree.symlinks {
+        manager.set_mirror_symlinks(&source, mode)?;
+    }
+    let source_path = std::path::absolute(&source)?;
+    let spec = manager.mirrors().iter().find(|spec| spec.source == source_path);
+    let rollover = spec.is_some_and(|spec| spec.rollover);
+    let keep_rotations = spec.and_then(|spec| spec.keep_rotations).unwrap_or(0);
+    let link_rotations = spec.is_some_and(|spec| spec.link_rotations);
+    let symlinks = spec.map_or(SymlinkMode::default(), |spec| spec.symlinks);
+    let skip_hidden = tree.skip_hidden.unwrap_or(manager.config().skip_hidden);
+    let link_type: symor::linking::LinkType = manager.config().linking.link_type.parse()?;
+    let mut builder = Mirror::builder(source.clone())
+        .targets(targets.clone())
+        .bidirectional(bidirectional)
+        .link_type(link_type)
+        .shadow_copy(manager.config().versioning.shadow_copy)
+        .metrics(&manager.config().home_dir)
+        .trash(manager.config().linking.trash)
+        .max_syncs_per_minute(manager.config().linking.max_syncs_per_minute)
+        .case_collisions(manager.config().linking.case_collisions)
+        .normalize_unicode(manager.config().linking.normalize_unicode)
+        .owners(manager.owner_map()?)
+        .recovery(manager.error_recovery())
+        .reclaim_space(manager.config().clone())
+        .storm_threshold(storm_threshold.unwrap_or(symor::storm::DEFAULT_THRESHOLD))
+        .rollover(rollover)
+        .keep_rotations(keep_rotations)
+        .link_rotations(link_rotations)
+        .symlinks(symlinks)
+        .file_rules(manager.file_rules()?)
+        .skip_hidden(skip_hidden);
+    for pattern in only {
+        builder = builder.only(pattern);
+    }
+    if let Some(rules) = manager.load_sync_rules()? {
+        builder = builder.rules(std::sync::Arc::new(rules));
+    }
+    let mirror = builder.build()?;
     mirror.run()?;
     println!("✓ Mirror setup complete!");
     println!("  Source: {}", source.display());
@@ -539,57 +1630,288 @@ fn handle_mirror(
     println!("Use 'sym status' to check mirror status.");
     Ok(())
 }
-fn handle_list(detailed: bool) -> Result<()> {
-    let mut manager = symor::SymorManager::new()?;
-    manager.load_config()?;
-    manager.load_watched_items()?;
-    manager.list_watched(detailed)?;
+fn handle_list(detailed: bool, page: usize, per_page: usize) -> Result<()> {
+    let manager = open_manager()?;
+    manager.list_watched_page(detailed, ListPage { page, per_page })?;
     Ok(())
 }
 fn handle_info(path: PathBuf) -> Result<()> {
-    let manager = symor::SymorManager::new()?;
+    let manager = open_manager()?;
     manager.get_info(&path)?;
     Ok(())
 }
-fn handle_install(force: bool) -> Result<()> {
+fn handle_install(force: bool, user: bool) -> Result<()> {
     let manager = symor::SymorManager::new()?;
-    manager.install_binary(force)?;
+    let install_path = manager.install_binary(force, user)?;
+    if let Some(dir) = install_path.parent() {
+        offer_path_setup(dir)?;
+    }
     Ok(())
 }
-fn handle_watch(path: PathBuf, recursive: bool) -> Result<()> {
-    let mut manager = symor::SymorManager::new()?;
-    manager.load_config()?;
-    manager.load_watched_items()?;
-    let id = manager.watch(path, recursive)?;
-    println!("Started watching with ID: {}", id);
+fn offer_path_setup(dir: &Path) -> Result<()> {
+    if symor::install::is_on_path(dir) {
+        return Ok(());
+    }
+    println!("⚠️  {} is not on your PATH", dir.display());
+    match symor::install::shell_profile() {
+        Some(profile) if cfg!(unix) => {
+            let question = format!("Add it to {}", profile.display());
+            if prompt_yes_no(&question, true)? {
+                if symor::install::add_to_shell_profile(&profile, dir)? {
+                    println!("✓ Updated {}; open a new shell to use 'sym'", profile.display());
+                } else {
+                    println!("{} already adds it; open a new shell", profile.display());
+                }
+            }
+        }
+        _ => {
+            println!("Add it to your PATH, for example:");
+            println!("  setx PATH \"%PATH%;{}\"", dir.display());
+        }
+    }
     Ok(())
 }
-fn handle_restore(file_id: String, version_id: String, target: PathBuf) -> Result<()> {
-    let mut manager = symor::SymorManager::new()?;
-    manager.load_watched_items()?;
-    manager.restore_file(&file_id, &version_id, &target)?;
-    println!(
-        "Successfully restored file {} version {} to {:?}", file_id, version_id, target
-    );
+fn handle_watch(
+    path: PathBuf,
+    recursive: bool,
+    only: Option<String>,
+    skip_hidden: Option<bool>,
+    name: Option<String>,
+) -> Result<()> {
+    let only = only.as_deref().map(PathFilter::split_list).unwrap_or_default();
+    let named = name.as_ref().map(|name| format!(" (name: {})", name)).unwrap_or_default();
+    if let Some(client) = daemon_client()? {
+        let id = watch_through(&client, &path, recursive, &only, skip_hidden, name.as_deref())?;
+        println!(
+            "Started watching with ID: {}{} (through the daemon, pid {})", id, named, client.pid
+        );
+        return Ok(());
+    }
+    let mut manager = open_manager()?;
+    let skip_hidden = skip_hidden.unwrap_or(manager.config().skip_hidden);
+    let id = manager.watch_named(path, recursive, only, skip_hidden, name)?;
+    println!("Started watching with ID: {}{}", id, named);
+    Ok(())
+}
+/// Asks the daemon to watch `path`, returning the new item's ID
+fn watch_through(
+    client: &ApiClient,
+    path: &Path,
+    recursive: bool,
+    only: &[String],
+    skip_hidden: Option<bool>,
+    name: Option<&str>,
+) -> Result<String> {
+    let body = serde_json::json!({
+        "path": std::path::absolute(path)?, "recursive": recursive, "only": only,
+        "skip_hidden": skip_hidden, "name": name
+    });
+    let reply = client.call("POST", "/v1/watch", Some(&body))?;
+    Ok(reply["id"].as_str().unwrap_or_default().to_string())
+}
+/// Watches the paths listed one per line in `list`, or on standard input for `-`
+fn handle_watch_list(
+    list: PathBuf,
+    recursive: bool,
+    only: Option<String>,
+    skip_hidden: Option<bool>,
+) -> Result<()> {
+    let text = if list.as_os_str() == "-" {
+        let mut text = String::new();
+        std::io::Read::read_to_string(&mut std::io::stdin(), &mut text)?;
+        text
+    } else {
+        std::fs::read_to_string(&list)?
+    };
+    let paths: Vec<PathBuf> = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(PathBuf::from)
+        .collect();
+    if paths.is_empty() {
+        println!("No paths to watch.");
+        return Ok(());
+    }
+    let only = only.as_deref().map(PathFilter::split_list).unwrap_or_default();
+    let results = match daemon_client()? {
+        Some(client) => {
+            println!("Watching through the daemon (pid {})...", client.pid);
+            paths
+                .into_iter()
+                .map(|path| {
+                    let result =
+                        watch_through(&client, &path, recursive, &only, skip_hidden, None);
+                    (path, result)
+                })
+                .collect::<Vec<_>>()
+        }
+        None => {
+            let mut manager = open_manager()?;
+            let skip_hidden = skip_hidden.unwrap_or(manager.config().skip_hidden);
+            manager
+                .watch_many(paths, recursive, only, skip_hidden)?
+                .into_iter()
+                .map(|(path, result)| (path, result.map_err(anyhow::Error::from)))
+                .collect()
+        }
+    };
+    let mut watched = 0;
+    for (path, result) in &results {
+        match result {
+            Ok(id) => {
+                watched += 1;
+                println!("👁️  {} ({})", path.display(), id);
+            }
+            Err(e) => println!("⚠️  Skipped {}: {}", path.display(), e),
+        }
+    }
+    println!("✅ Started watching {} of {} paths", watched, results.len());
+    Ok(())
+}
+/// Restores a version to `target`, or prints it to standard output when there is none
+fn handle_restore(
+    file_id: String,
+    version_id: String,
+    target: Option<PathBuf>,
+    preview: Option<DiffMode>,
+    yes: bool,
+) -> Result<()> {
+    let manager = open_manager()?;
+    let id = manager
+        .resolve_item(&file_id)
+        .map(|item| item.id.clone())
+        .ok_or_else(|| SymorError::not_watched(&file_id))?;
+    let version_id = manager.resolve_version(&id, &version_id)?;
+    let Some(target) = target else {
+        use std::io::Write;
+        let content = manager.version_content(&id, &version_id)?;
+        let mut stdout = std::io::stdout().lock();
+        match stdout.write_all(&content).and_then(|()| stdout.flush()) {
+            Err(e) if e.kind() != std::io::ErrorKind::BrokenPipe => return Err(e.into()),
+            _ => return Ok(()),
+        }
+    };
+    if let Some(mode) = preview {
+        let version = manager.version_content(&id, &version_id)?;
+        print_restore_diff(&target, &version, mode);
+        if !yes {
+            let question = format!("Restore version {} to {}?", version_id, target.display());
+            let answer = prompt(&question, "n")?;
+            if !matches!(answer.to_lowercase().as_str(), "y" | "yes") {
+                println!("Restore cancelled.");
+                return Ok(());
+            }
+        }
+    }
+    let result = manager.restore_file(&id, &version_id, &target)?;
+    println!("Successfully restored file {} version {} to {:?}", id, version_id, target);
+    if result.verified {
+        println!("✓ Verified: the restored file matches the version's hash");
+    }
+    if result.ownership_restored {
+        println!("✓ Owner restored from backup time");
+    }
     Ok(())
 }
 fn handle_settings(action: SettingsCommand) -> Result<()> {
     let mut manager = symor::SymorManager::new()?;
-    manager.load_config()?;
+    load_config(&mut manager)?;
     match action {
         SettingsCommand::Show => {
             let config = manager.config();
             println!("Current settings:");
             println!("Home directory: {:?}", config.home_dir);
+            if GLOBAL_OPTIONS.get_or_init(GlobalOptions::default).portable {
+                println!("Portable mode: on");
+            }
+            if let Some(env) = manager.active_environment() {
+                println!("Environment: {}", env);
+            }
+            if let Some(source) = manager.config_source() {
+                println!("Config file: {:?}", source);
+            }
             println!("Versioning:");
             println!("  Enabled: {}", config.versioning.enabled);
             println!("  Max versions: {}", config.versioning.max_versions);
             println!("  Compression: {}", config.versioning.compression);
+            println!("  Shadow copy: {}", config.versioning.shadow_copy);
+            println!("  Pre-restore days: {}", config.versioning.pre_restore_days);
+            println!("  Metadata format: {}", config.versioning.metadata_format);
+            println!("  Symlinks: {}", config.versioning.symlinks);
             println!("Linking:");
             println!("  Link type: {}", config.linking.link_type);
             println!("  Preserve permissions: {}", config.linking.preserve_permissions);
+            println!("  Preserve ownership: {}", config.linking.preserve_ownership);
+            if !config.linking.owner_map.is_empty() {
+                println!("  Owner map: {}", config.linking.owner_map.join(", "));
+            }
+            println!("  Trash: {}", config.linking.trash);
+            match config.linking.max_syncs_per_minute {
+                0 => println!("  Max syncs per minute: unlimited"),
+                max => println!("  Max syncs per minute: {}", max),
+            }
+            println!("  Case collisions: {}", config.linking.case_collisions);
+            println!("  Normalize Unicode names: {}", config.linking.normalize_unicode);
+            println!("Performance:");
+            match config.performance.compression_threads {
+                0 => {
+                    println!(
+                        "  Compression threads: auto ({})",
+                        config.performance.compression_threads()
+                    )
+                }
+                threads => println!("  Compression threads: {}", threads),
+            }
+            println!("  Background priority: {}", config.performance.background_priority);
+            match &config.performance.max_memory {
+                Some(max) => println!("  Max memory: {}", max),
+                None => println!("  Max memory: unlimited"),
+            }
+            println!("Files:");
+            println!("  Skip hidden: {}", config.skip_hidden);
+            for rule in &config.file_rules {
+                println!("  Rule: {}", serde_json::to_string(rule)?);
+            }
+            if !config.hooks.is_empty() {
+                println!("Hooks:");
+                use VersionAction::{Created, Pruned, Restored};
+                for action in [Created, Restored, Pruned] {
+                    for command in config.hooks.commands(action) {
+                        println!("  On {}: {}", action, command);
+                    }
+                }
+            }
+            println!("Recovery:");
+            let mut codes: Vec<_> = config.recovery.strategies.iter().collect();
+            codes.sort_by(|a, b| a.0.cmp(b.0));
+            for (code, strategy) in codes {
+                match strategy {
+                    RecoveryStrategy::Retry { max_attempts, delay } => {
+                        println!(
+                            "  {}: retry {} times, {}ms apart", code, max_attempts, delay
+                            .as_millis()
+                        )
+                    }
+                    RecoveryStrategy::Fallback { alternative_action } => {
+                        println!("  {}: fallback ({})", code, alternative_action)
+                    }
+                    RecoveryStrategy::Skip => println!("  {}: skip", code),
+                    RecoveryStrategy::Fail => println!("  {}: fail", code),
+                }
+            }
         }
-        SettingsCommand::Versioning { enabled, max_versions, compression } => {
+        SettingsCommand::Versioning {
+            enabled,
+            max_versions,
+            compression,
+            shadow_copy,
+            pre_restore_days,
+            metadata_format,
+            retention_lock_days,
+            secret_scan,
+            symlinks,
+        } => {
             manager
                 .update_config(|config| {
                     if let Some(e) = enabled {
@@ -601,10 +1923,55 @@ fn handle_settings(action: SettingsCommand) -> Result<()> {
                     if let Some(c) = compression {
                         config.versioning.compression = c;
                     }
+                    if let Some(sc) = shadow_copy {
+                        config.versioning.shadow_copy = sc;
+                    }
+                    if let Some(days) = pre_restore_days {
+                        config.versioning.pre_restore_days = days;
+                    }
+                    if let Some(format) = metadata_format {
+                        config.versioning.metadata_format = format;
+                    }
+                    if let Some(days) = retention_lock_days {
+                        config.versioning.retention_lock_days = days;
+                    }
+                    if let Some(mode) = secret_scan {
+                        config.versioning.secret_scan = mode;
+                    }
+                    if let Some(mode) = symlinks {
+                        config.versioning.symlinks = mode;
+                    }
                 })?;
+            if metadata_format.is_some() {
+                manager.load_watched_items()?;
+                manager.save_watched_items_public()?;
+                manager.load_hash_cache();
+                manager.save_hash_cache()?;
+            }
             println!("Versioning settings updated");
         }
-        SettingsCommand::Linking { link_type, preserve_permissions } => {
+        SettingsCommand::Linking {
+            link_type,
+            preserve_permissions,
+            preserve_ownership,
+            owner_map,
+            trash,
+            max_syncs_per_minute,
+            case_collisions,
+            normalize_unicode,
+        } => {
+            let owner_map: Option<Vec<String>> = owner_map
+                .map(|rules| {
+                    rules
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|rule| !rule.is_empty())
+                        .map(String::from)
+                        .collect()
+                });
+            if let Some(rules) = &owner_map {
+                symor::ownership::OwnerMap::parse(rules)?;
+            }
             manager
                 .update_config(|config| {
                     if let Some(lt) = link_type {
@@ -613,9 +1980,79 @@ fn handle_settings(action: SettingsCommand) -> Result<()> {
                     if let Some(pp) = preserve_permissions {
                         config.linking.preserve_permissions = pp;
                     }
+                    if let Some(po) = preserve_ownership {
+                        config.linking.preserve_ownership = po;
+                    }
+                    if let Some(rules) = owner_map {
+                        config.linking.owner_map = rules;
+                    }
+                    if let Some(t) = trash {
+                        config.linking.trash = t;
+                    }
+                    if let Some(max) = max_syncs_per_minute {
+                        config.linking.max_syncs_per_minute = max;
+                    }
+                    if let Some(policy) = case_collisions {
+                        config.linking.case_collisions = policy;
+                    }
+                    if let Some(normalize) = normalize_unicode {
+                        config.linking.normalize_unicode = normalize;
+                    }
                 })?;
             println!("Linking settings updated");
         }
+        SettingsCommand::Files { skip_hidden } => {
+            manager
+                .update_config(|config| {
+                    if let Some(sh) = skip_hidden {
+                        config.skip_hidden = sh;
+                    }
+                })?;
+            println!("File settings updated");
+        }
+        SettingsCommand::Hooks { on_created, on_restored, on_pruned, clear } => {
+            manager
+                .update_config(|config| {
+                    if clear {
+                        config.hooks = Default::default();
+                    }
+                    config.hooks.on_version_created.extend(on_created);
+                    config.hooks.on_version_restored.extend(on_restored);
+                    config.hooks.on_version_pruned.extend(on_pruned);
+                })?;
+            println!("Hook settings updated");
+        }
+        SettingsCommand::Notifications { summary, commands, clear } => {
+            manager
+                .update_config(|config| {
+                    if clear {
+                        config.notifications = Default::default();
+                    }
+                    config.notifications.summary = summary.or(config.notifications.summary);
+                    config.notifications.summary_commands.extend(commands);
+                })?;
+            println!("Notification settings updated");
+        }
+        SettingsCommand::Performance { compression_threads, background_priority, max_memory } => {
+            let max_memory = max_memory.map(|max| (max.trim() != "0").then_some(max));
+            if let Some(max) = &max_memory {
+                let limit = PerformanceConfig { max_memory: max.clone(), ..Default::default() };
+                limit.max_memory_bytes()?;
+            }
+            manager
+                .update_config(|config| {
+                    if let Some(threads) = compression_threads {
+                        config.performance.compression_threads = threads;
+                    }
+                    if let Some(background) = background_priority {
+                        config.performance.background_priority = background;
+                    }
+                    if let Some(max) = max_memory {
+                        config.performance.max_memory = max;
+                    }
+                })?;
+            println!("Performance settings updated");
+        }
         SettingsCommand::Home { path } => {
             manager
                 .update_config(|config| {
@@ -628,9 +2065,585 @@ fn handle_settings(action: SettingsCommand) -> Result<()> {
             symor::SymorManager::setup_directory_structure(&home_dir)?;
             println!("Directory structure initialized/reset with proper permissions");
         }
+        SettingsCommand::Export { file, format, templates } => {
+            let format = format
+                .or_else(|| file.as_deref().map(ConfigFormat::from_path))
+                .unwrap_or_default();
+            let mut bundle = SettingsBundle {
+                config: manager.config().clone(),
+                templates: Vec::new(),
+            };
+            if templates {
+                let mut template_manager = TemplateManager::with_path(
+                    manager.config().home_dir.join("templates"),
+                );
+                template_manager.load_custom_templates()?;
+                bundle.templates = template_manager
+                    .list_templates()
+                    .into_iter()
+                    .cloned()
+                    .collect();
+                bundle.templates.sort_by(|a, b| a.name.cmp(&b.name));
+            }
+            let data = format.serialize(&bundle)?;
+            match file {
+                Some(path) => {
+                    std::fs::write(&path, data)?;
+                    println!("Settings exported to {} ({})", path.display(), format);
+                }
+                None => println!("{}", data.trim_end()),
+            }
+        }
+        SettingsCommand::Import { file, format } => {
+            let mut bundle = SettingsBundle::from_file(&file, format)?;
+            bundle.config.home_dir = manager.config().home_dir.clone();
+            let result = ConfigValidator::new().validate_config(&bundle.config);
+            for warning in &result.warnings {
+                println!("⚠️  {}: {}", warning.field, warning.message);
+            }
+            if !result.is_valid {
+                for error in &result.errors {
+                    eprintln!("❌ {}: {}", error.field, error.message);
+                    if let Some(suggestion) = &error.suggestion {
+                        eprintln!("   {}", suggestion);
+                    }
+                }
+                return Err(
+                    SymorError::new(
+                            ErrorCode::InvalidConfiguration,
+                            format!(
+                                "{} has {} invalid setting(s), nothing was imported", file
+                                .display(), result.errors.len()
+                            ),
+                        )
+                        .into(),
+                );
+            }
+            if let Some(template) = bundle
+                .templates
+                .iter()
+                .find(|t| !symor::config::templates::is_valid_template_name(&t.name))
+            {
+                return Err(
+                    SymorError::new(
+                            ErrorCode::InvalidConfiguration,
+                            format!("Invalid template name: {:?}", template.name),
+                        )
+                        .into(),
+                );
+            }
+            let template_manager = TemplateManager::with_path(
+                bundle.config.home_dir.join("templates"),
+            );
+            for template in &bundle.templates {
+                template_manager.save_template(template)?;
+            }
+            let template_count = bundle.templates.len();
+            manager.update_config(|config| *config = bundle.config)?;
+            println!("Settings imported from {}", file.display());
+            if template_count > 0 {
+                println!("  Templates: {}", template_count);
+            }
+            println!("  Home directory kept at {:?}", manager.config().home_dir);
+        }
+        SettingsCommand::Template { action } => {
+            handle_template(&mut manager, action)?;
+        }
+        SettingsCommand::Env { action } => {
+            handle_env(&manager, action)?;
+        }
+    }
+    Ok(())
+}
+fn handle_env(manager: &SymorManager, action: EnvCommand) -> Result<()> {
+    use symor::config::environment::{load_environments, save_environments};
+    let path = manager.environments_path();
+    let mut environments = load_environments(&path)?;
+    match action {
+        EnvCommand::List => {
+            if environments.is_empty() {
+                println!("No environments configured.");
+                return Ok(());
+            }
+            println!("Environments:");
+            for env in &environments {
+                let marker = if manager.active_environment() == Some(env.name.as_str()) {
+                    "*"
+                } else {
+                    " "
+                };
+                println!(
+                    "{} {:<12} {} (auto-switch: {})", marker, env.name, env.config_path
+                    .display(), env.auto_switch
+                );
+            }
+        }
+        EnvCommand::Add { name, config_path, no_auto_switch, vars } => {
+            let config_path = if config_path.is_absolute() {
+                config_path
+            } else {
+                std::path::absolute(config_path)?
+            };
+            environments.retain(|env| env.name != name);
+            environments
+                .push(EnvironmentConfig {
+                    name: name.clone(),
+                    config_path,
+                    auto_switch: !no_auto_switch,
+                    variables: vars.into_iter().collect(),
+                });
+            save_environments(&path, &environments)?;
+            println!("Environment '{}' saved", name);
+        }
+        EnvCommand::Remove { name } => {
+            let before = environments.len();
+            environments.retain(|env| env.name != name);
+            if environments.len() == before {
+                return Err(
+                    SymorError::new(
+                            ErrorCode::MissingConfiguration,
+                            format!("Environment '{}' not found", name),
+                        )
+                        .into(),
+                );
+            }
+            save_environments(&path, &environments)?;
+            println!("Environment '{}' removed", name);
+        }
+    }
+    Ok(())
+}
+fn parse_resolution(s: &str) -> Result<Resolution, String> {
+    match s {
+        "source" => Ok(Resolution::KeepSource),
+        "target" => Ok(Resolution::KeepTarget),
+        "both" => Ok(Resolution::KeepBoth),
+        _ => Err(format!("expected source, target or both, got '{}'", s)),
+    }
+}
+fn parse_variable(s: &str) -> Result<(String, String), String> {
+    s.split_once('=')
+        .map(|(name, value)| (name.trim().to_string(), value.to_string()))
+        .ok_or_else(|| format!("expected NAME=VALUE, got '{}'", s))
+}
+fn handle_template(manager: &mut SymorManager, action: TemplateCommand) -> Result<()> {
+    let mut templates = TemplateManager::with_path(
+        manager.config().home_dir.join("templates"),
+    );
+    templates.load_builtin_templates()?;
+    templates.load_custom_templates()?;
+    match action {
+        TemplateCommand::List => {
+            let mut list = templates.list_templates();
+            list.sort_by(|a, b| a.name.cmp(&b.name));
+            println!("Available templates:");
+            for template in list {
+                println!("  {:<14} {}", template.name, template.description);
+            }
+        }
+        TemplateCommand::Show { name } => {
+            let template = templates
+                .get_template(&name)
+                .ok_or_else(|| {
+                    SymorError::new(
+                        ErrorCode::MissingConfiguration,
+                        format!("Template '{}' not found", name),
+                    )
+                })?;
+            println!("Template: {}", template.name);
+            println!("Description: {}", template.description);
+            println!("Patterns: {}", template.patterns.join(", "));
+            let variables = template.variable_names();
+            if !variables.is_empty() {
+                println!("Variables:");
+                for variable in variables {
+                    match template.variables.get(&variable) {
+                        Some(default) => println!("  {} (default: {})", variable, default),
+                        None => println!("  {}", variable),
+                    }
+                }
+            }
+            println!("Versioning:");
+            println!("  Enabled: {}", template.config.versioning.enabled);
+            println!("  Max versions: {}", template.config.versioning.max_versions);
+            println!("  Compression: {}", template.config.versioning.compression);
+            println!("Linking:");
+            println!("  Link type: {}", template.config.linking.link_type);
+            println!(
+                "  Preserve permissions: {}", template.config.linking
+                .preserve_permissions
+            );
+        }
+        TemplateCommand::Apply { name, max_versions, compression, link_type, vars } => {
+            let overrides = ConfigOverrides {
+                max_versions,
+                compression,
+                link_type,
+                variables: vars.into_iter().collect(),
+            };
+            let mut config = templates.create_from_template(&name, &overrides)?;
+            let template_sets_home = templates
+                .get_template(&name)
+                .is_some_and(|t| t.config.home_dir.to_string_lossy().contains("{{"));
+            if !template_sets_home {
+                config.home_dir = manager.config().home_dir.clone();
+            }
+            let result = ConfigValidator::new().validate_config(&config);
+            if let Some(error) = result.errors.first() {
+                return Err(
+                    SymorError::new(
+                            ErrorCode::InvalidConfiguration,
+                            format!("{}: {}", error.field, error.message),
+                        )
+                        .into(),
+                );
+            }
+            manager.update_config(|current| *current = config)?;
+            println!("Applied template '{}'", name);
+        }
+        TemplateCommand::Save { name, description } => {
+            let template = ConfigTemplate {
+                description: description
+                    .unwrap_or_else(|| format!("Custom template: {}", name)),
+                name: name.clone(),
+                config: manager.config().clone(),
+                patterns: vec!["*".to_string()],
+                variables: Default::default(),
+            };
+            templates.save_template(&template)?;
+            println!("Saved current settings as template '{}'", name);
+        }
+    }
+    Ok(())
+}
+/// Prints how restoring `version` would change the file at `target`
+fn print_restore_diff(target: &Path, version: &[u8], mode: DiffMode) {
+    use symor::tui::preview::diff;
+    let current = match std::fs::read(target) {
+        Ok(current) => current,
+        Err(_) => {
+            println!(
+                "🆕 {} does not exist; it will be created ({} bytes)", target.display(),
+                version.len()
+            );
+            return;
+        }
+    };
+    println!("🔍 Changes to {} (- current, + restored):", target.display());
+    let mode = match mode {
+        DiffMode::Auto => DiffMode::detect(&current, version),
+        mode => mode,
+    };
+    if mode == DiffMode::Binary {
+        let verdict = if current == version { "identical" } else { "different" };
+        println!(
+            "  Binary content, {}: {} bytes → {} bytes", verdict, current.len(), version
+            .len()
+        );
+    }
+    for line in diff(&current, version, mode, 3) {
+        println!("  {}", line);
+    }
+}
+fn prompt(question: &str, default: &str) -> Result<String> {
+    use std::io::Write;
+    if default.is_empty() {
+        print!("{}: ", question);
+    } else {
+        print!("{} [{}]: ", question, default);
+    }
+    std::io::stdout().flush()?;
+    let mut input = String::new();
+    if std::io::stdin().read_line(&mut input)? == 0 {
+        return Ok(default.to_string());
+    }
+    let input = input.trim();
+    Ok(if input.is_empty() { default.to_string() } else { input.to_string() })
+}
+fn prompt_parse<T: std::str::FromStr + std::fmt::Display>(
+    question: &str,
+    default: T,
+    valid: impl Fn(&T) -> bool,
+) -> Result<T> {
+    loop {
+        let answer = prompt(question, &default.to_string())?;
+        match answer.parse::<T>() {
+            Ok(value) if valid(&value) => return Ok(value),
+            _ => println!("  '{}' is not a valid answer, please try again", answer),
+        }
+    }
+}
+fn prompt_yes_no(question: &str, default: bool) -> Result<bool> {
+    loop {
+        let hint = if default { "Y/n" } else { "y/N" };
+        let answer = prompt(&format!("{} [{}]", question, hint), "")?;
+        match answer.to_lowercase().as_str() {
+            "" => return Ok(default),
+            "y" | "yes" => return Ok(true),
+            "n" | "no" => return Ok(false),
+            _ => println!("  Please answer 'y' or 'n'"),
+        }
+    }
+}
+/// Answers to the `sym init` questions; with `--defaults` none are asked
+struct InitAnswers {
+    config: SymorConfig,
+    watch_paths: Vec<PathBuf>,
+    install: bool,
+    service: bool,
+}
+fn ask_init(config: SymorConfig, defaults: bool) -> Result<InitAnswers> {
+    let mut answers = InitAnswers {
+        config,
+        watch_paths: Vec::new(),
+        install: false,
+        service: false,
+    };
+    if defaults {
+        return Ok(answers);
+    }
+    let config = &mut answers.config;
+    println!("🪞 Symor setup");
+    println!("Press Enter to keep the value in brackets.");
+    println!();
+    let home = prompt(
+        "Where should symor keep its data",
+        &config.home_dir.display().to_string(),
+    )?;
+    config.home_dir = std::path::absolute(PathBuf::from(home))?;
+    config.versioning.max_versions = prompt_parse(
+        "How many versions to keep per file",
+        config.versioning.max_versions,
+        |n| *n > 0,
+    )?;
+    config.versioning.compression = prompt_parse(
+        "Compression level (0 = fastest, 9 = smallest)",
+        config.versioning.compression,
+        |n| *n <= 9,
+    )?;
+    println!("Enter files or directories to watch, one per line (empty line to finish):");
+    loop {
+        let path = prompt("  Path", "")?;
+        if path.is_empty() {
+            break;
+        }
+        let path = std::path::absolute(PathBuf::from(path))?;
+        if path.exists() {
+            answers.watch_paths.push(path);
+        } else {
+            println!("  {} does not exist, skipping", path.display());
+        }
+    }
+    answers.install = prompt_yes_no("Install sym for your user (~/.local/bin)", false)?;
+    answers.service = prompt_yes_no("Run the daemon as a service?", false)?;
+    Ok(answers)
+}
+/// User service running the daemon on `home_dir`, passing the home along when it is not
+/// the default one, with `executable` in place of the running binary when given
+fn init_service_spec(
+    home_dir: &Path,
+    executable: Option<PathBuf>,
+) -> Result<symor::daemon::service::ServiceSpec> {
+    use symor::daemon::service::{ServiceScope, ServiceSpec};
+    let mut spec = ServiceSpec::for_current_process(ServiceScope::User, home_dir)?;
+    spec.portable |= home_dir != symor::get_default_home_dir();
+    if let Some(executable) = executable {
+        spec.executable = executable;
+    }
+    Ok(spec)
+}
+fn handle_init(defaults: bool) -> Result<()> {
+    let mut manager = SymorManager::new()?;
+    let _ = load_config(&mut manager);
+    let InitAnswers { config, watch_paths, install, service } = ask_init(
+        manager.config().clone(),
+        defaults,
+    )?;
+    let result = ConfigValidator::new().validate_config(&config);
+    if let Some(error) = result.errors.first() {
+        return Err(
+            SymorError::new(
+                    ErrorCode::InvalidConfiguration,
+                    format!("{}: {}", error.field, error.message),
+                )
+                .into(),
+        );
+    }
+    let home_dir = config.home_dir.clone();
+    SymorManager::setup_directory_structure(&home_dir)?;
+    manager.update_config(|current| *current = config)?;
+    println!("✓ Configuration written");
+    let mut manager = open_manager()?;
+    for path in watch_paths {
+        let recursive = path.is_dir();
+        let id = manager.watch(path.clone(), recursive)?;
+        println!("✓ Watching {} (ID: {})", path.display(), id);
+    }
+    let mut executable = None;
+    if install {
+        let install_path = manager.install_binary(false, true)?;
+        if let Some(dir) = install_path.parent() {
+            offer_path_setup(dir)?;
+        }
+        executable = Some(install_path);
+    }
+    if service {
+        let backend = symor::daemon::service::ServiceBackend::current();
+        let spec = init_service_spec(&home_dir, executable)?;
+        let location = backend.install(&spec, true)?;
+        backend.enable(spec.scope)?;
+        println!("✓ Daemon service installed ({}) and started", location);
+    }
+    println!();
+    println!("Symor is ready. Run 'sym settings show' to review your settings.");
+    Ok(())
+}
+fn handle_daemon(action: DaemonCommand) -> Result<()> {
+    use symor::daemon::{self, service::{ServiceBackend, ServiceScope, ServiceSpec}};
+    let backend = ServiceBackend::current();
+    let scope_for = |system: bool| {
+        if system { ServiceScope::System } else { ServiceScope::User }
+    };
+    match action {
+        DaemonCommand::Run { interval, api: api_addr, no_api, service } => {
+            let manager = open_manager()?;
+            let api = if no_api {
+                None
+            } else {
+                let token = daemon::api::load_or_create_token(&manager.config().home_dir)?;
+                let addr = api_addr.unwrap_or_else(|| ([127, 0, 0, 1], 0).into());
+                Some(daemon::api::ApiOptions { addr, token })
+            };
+            let options = daemon::DaemonOptions {
+                scan_interval: std::time::Duration::from_secs(interval.max(1)),
+                api,
+                ..Default::default()
+            };
+            if service {
+                #[cfg(windows)]
+                {
+                    daemon::windows::EventLogLogger::init(log::Level::Info)?;
+                    return Ok(daemon::windows::run_service(daemon::Daemon::new(manager, options))?);
+                }
+                #[cfg(not(windows))]
+                return Err(
+                    SymorError::new(
+                        ErrorCode::InvalidConfiguration,
+                        "--service is only supported on Windows".to_string(),
+                    )
+                        .into(),
+                );
+            }
+            let log_path = daemon::log_file(&manager.config().home_dir);
+            if let Some(parent) = log_path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            let log = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&log_path)?;
+            env_logger::Builder::from_env(Env::default().default_filter_or("info"))
+                .target(env_logger::Target::Pipe(Box::new(log)))
+                .init();
+            println!("🚀 Symor daemon running (pid {})", std::process::id());
+            println!("   Log: {}", log_path.display());
+            if let Some(addr) = api_addr {
+                println!("   API: http://{}/v1 (token: 'sym daemon api-token')", addr);
+            }
+            daemon::Daemon::new(manager, options).run()?;
+        }
+        DaemonCommand::ApiToken { rotate } => {
+            let home_dir = open_manager()?.config().home_dir.clone();
+            let path = daemon::api::token_file(&home_dir);
+            if rotate && path.exists() {
+                std::fs::remove_file(&path)?;
+            }
+            println!("{}", daemon::api::load_or_create_token(&home_dir)?);
+            if rotate {
+                eprintln!("🔑 Token rotated; restart the daemon to use it");
+            }
+        }
+        DaemonCommand::InstallService { system, force } => {
+            let home_dir = symor::get_default_home_dir();
+            let spec = ServiceSpec::for_current_process(scope_for(system), &home_dir)?;
+            let path = backend.install(&spec, force)?;
+            println!("✅ Service installed: {}", path);
+            println!(
+                "   Start it now and on every boot with 'sym daemon enable{}'", if
+                system { " --system" } else { "" }
+            );
+        }
+        DaemonCommand::UninstallService { system } => {
+            if backend.uninstall(scope_for(system))? {
+                println!("✅ Service removed");
+            } else {
+                println!("ℹ️  No symor service is installed");
+            }
+        }
+        DaemonCommand::Enable { system } => {
+            backend.enable(scope_for(system))?;
+            println!("✅ Symor daemon enabled and started");
+        }
+        DaemonCommand::Disable { system } => {
+            backend.disable(scope_for(system))?;
+            println!("✅ Symor daemon stopped and disabled");
+        }
+        DaemonCommand::Status { system } => {
+            let manager = open_manager()?;
+            let home_dir = &manager.config().home_dir;
+            match daemon::running_pid(home_dir) {
+                Some(pid) => println!("🟢 Daemon running (pid {})", pid),
+                None => println!("⚪ Daemon not running"),
+            }
+            println!("   Log: {}", daemon::log_file(home_dir).display());
+            println!("   Mirrors: {}", manager.mirrors().len());
+            for mirror in manager.mirrors() {
+                println!(
+                    "     {} {} {}", mirror.source.display(), if mirror.bidirectional {
+                    "⇄" } else { "→" }, mirror.targets.iter().map(| t | t.display()
+                    .to_string()).collect::< Vec < _ >> ().join(", ")
+                );
+            }
+            if backend.is_installed(scope_for(system))? {
+                println!("   Service: {}", backend.location(scope_for(system))?);
+                if let Ok(status) = backend.status(scope_for(system)) {
+                    for line in status.lines().take(3) {
+                        println!("     {}", line.trim());
+                    }
+                }
+            } else {
+                println!("   Service: not installed");
+            }
+        }
+    }
+    Ok(())
+}
+fn handle_man(dir: Option<PathBuf>) -> Result<()> {
+    let mut command = Opt::command();
+    command.build();
+    match dir {
+        Some(dir) => {
+            std::fs::create_dir_all(&dir)?;
+            let count = write_man_pages(&command, "sym", &dir)?;
+            println!("✓ Wrote {} man pages to {}", count, dir.display());
+        }
+        None => clap_mangen::Man::new(command).render(&mut std::io::stdout())?,
     }
     Ok(())
 }
+fn write_man_pages(command: &clap::Command, bin_name: &str, dir: &Path) -> Result<usize> {
+    let page_name = bin_name.replace(' ', "-");
+    let command = command.clone().name(page_name.clone()).bin_name(bin_name.to_string());
+    let mut page = Vec::new();
+    clap_mangen::Man::new(command.clone()).render(&mut page)?;
+    std::fs::write(dir.join(format!("{}.1", page_name)), page)?;
+    let mut count = 1;
+    for subcommand in command.get_subcommands().filter(|c| c.get_name() != "help") {
+        let sub_bin_name = format!("{} {}", bin_name, subcommand.get_name());
+        count += write_man_pages(subcommand, &sub_bin_name, dir)?;
+    }
+    Ok(count)
+}
 fn handle_rip(keep_data: bool) -> Result<()> {
     let manager = symor::SymorManager::new()?;
     println!("This will uninstall sym and remove the binary from your system.");
@@ -681,26 +2694,395 @@ fn handle_stats(detailed: bool, period: Option<u64>) -> Result<()> {
     }
     Ok(())
 }
-fn handle_tui(_refresh_rate: u64) -> Result<()> {
-    let manager = SymorManager::new()?;
-    let watched_items = manager.watched_items().values().cloned().collect::<Vec<_>>();
-    let mut tui = symor::tui::SymorTUI::new()?;
-    tui.update_state(|state| {
-        state.watched_items = watched_items;
-    });
-    tui.run()?;
-    tui.shutdown()?;
-    Ok(())
+fn handle_cmp(a: &Path, b: &Path, fix: bool, jobs: Option<usize>) -> Result<()> {
+    let manager = open_manager()?;
+    let jobs = jobs
+        .unwrap_or_else(symor::performance::ParallelProcessor::get_optimal_concurrency);
+    println!("🔍 Comparing {} with {}...", a.display(), b.display());
+    let comparison = manager.compare_trees(a, b, jobs)?;
+    for path in &comparison.only_a {
+        println!("  + only in {}: {}", a.display(), path.display());
+    }
+    for path in &comparison.only_b {
+        println!("  - only in {}: {}", b.display(), path.display());
+    }
+    for path in &comparison.differing {
+        println!("  ~ differs: {}", path.display());
+    }
+    for (path, e) in &comparison.unreadable {
+        println!("  ✗ cannot compare {}: {}", path.display(), e);
+    }
+    if comparison.is_identical() {
+        println!("✅ Directories match ({} files)", comparison.identical);
+        return Ok(());
+    }
+    println!(
+        "📊 {} identical, {} only in {}, {} only in {}, {} differing",
+        comparison.identical, comparison.only_a.len(), a.display(), comparison.only_b.len(),
+        b.display(), comparison.differing.len()
+    );
+    if !fix {
+        return Err(anyhow::anyhow!("directories differ; run with --fix to make DIR_B match"));
+    }
+    let fixed = manager.fix_tree(a, b, &comparison)?;
+    println!(
+        "🔧 Copied {} files to {} and removed {} that only it had", fixed.copied, b.display(),
+        fixed.removed
+    );
+    if !comparison.unreadable.is_empty() {
+        let unreadable = comparison.unreadable.len();
+        return Err(anyhow::anyhow!("{} files could not be compared", unreadable));
+    }
+    Ok(())
+}
+fn handle_bench(size: Option<&str>) -> Result<()> {
+    use symor::bench;
+    use symor::tui::format::format_bytes;
+    let size = size.map(bench::parse_sample_size).transpose()?;
+    let size = size.unwrap_or(bench::DEFAULT_SAMPLE_SIZE);
+    let manager = open_manager()?;
+    let rate = |bytes_per_sec: f64| format!("{}/s", format_bytes(bytes_per_sec as u64));
+    println!("⏱️  Benchmarking with a {} sample...", format_bytes(size as u64));
+    let report = manager.benchmark(size)?;
+    println!();
+    println!("Hashing (MD5): {}", rate(report.hash_throughput));
+    println!("Compression:");
+    for level in &report.levels {
+        println!(
+            "  Level {}: {}, {:.1}% of original", level.level, rate(level.throughput),
+            level.compressed as f64 * 100.0 / report.sample_size as f64
+        );
+    }
+    println!(
+        "Parallel workers (hash and compress at level {}):",
+        manager.version_storage().compression_level()
+    );
+    for workers in &report.workers {
+        println!("  {:>3}: {}", workers.workers, rate(workers.throughput));
+    }
+    if !report.copies.is_empty() {
+        println!("Copy to mirror targets:");
+        for copy in &report.copies {
+            match &copy.throughput {
+                Ok(throughput) => println!("  {}: {}", copy.target.display(), rate(*throughput)),
+                Err(e) => println!("  ✗ {}: {}", copy.target.display(), e),
+            }
+        }
+    }
+    match &report.store {
+        Ok(latency) => {
+            println!(
+                "Version store write: {:.2} ms median, {:.2} ms max",
+                latency.median.as_secs_f64() * 1000.0, latency.max.as_secs_f64() * 1000.0
+            )
+        }
+        Err(e) => println!("✗ Version store write: {}", e),
+    }
+    println!();
+    println!("💡 Recommendations:");
+    if let Some(level) = report.recommended_level() {
+        println!(
+            "  Compression level {} stores within 2% of the smallest output: \
+             sym settings versioning --compression {}", level, level
+        );
+    }
+    if let Some(workers) = report.recommended_workers() {
+        let noun = if workers == 1 { "worker reaches" } else { "workers reach" };
+        println!(
+            "  {} {} 90% of the best throughput: sym settings performance \
+             --compression-threads {}, sym sync --jobs {}", workers, noun, workers, workers
+        );
+    }
+    if let Some(slowest) = report
+        .copies
+        .iter()
+        .filter_map(|copy| Some((copy.throughput.as_ref().ok()?, &copy.target)))
+        .min_by(|a, b| a.0.total_cmp(b.0))
+        .filter(|slowest| *slowest.0 < report.hash_throughput)
+    {
+        println!(
+            "  Copies to {} ({}) are slower than hashing and bound mirror syncs",
+            slowest.1.display(), rate(*slowest.0)
+        );
+    }
+    let slow_store = std::time::Duration::from_millis(20);
+    if report.store.as_ref().is_ok_and(|latency| latency.median > slow_store) {
+        println!(
+            "  Version store writes are slow; moving the symor home to a faster disk \
+             (sym settings home PATH) speeds up backups"
+        );
+    }
+    Ok(())
+}
+fn handle_rules(paths: &[PathBuf]) -> Result<()> {
+    let manager = open_manager()?;
+    let file_rules = manager.file_rules()?;
+    if file_rules.is_empty() {
+        println!("ℹ️  No file rules in the config");
+    } else {
+        println!("✅ {} file rule(s) in the config", manager.config().file_rules.len());
+    }
+    let file = symor::rules::rules_file(&manager.config().home_dir);
+    let rules = manager.load_sync_rules()?;
+    match &rules {
+        Some(_) => println!("✅ Sync rules in {} compile", file.display()),
+        None => println!("ℹ️  No sync rules; write them to {}", file.display()),
+    }
+    for path in paths {
+        let name = path.file_name().map(Path::new).unwrap_or(path);
+        let mut decision = file_rules.decide(path, name);
+        if rules.as_ref().is_some_and(|rules| !rules.includes(path, name)) {
+            decision.version = false;
+            decision.mirror = false;
+        }
+        if decision.version || decision.mirror {
+            println!("  ✓ {}: {}", path.display(), decision);
+        } else {
+            println!("  ✗ skipped: {}", path.display());
+        }
+    }
+    Ok(())
+}
+fn handle_item_stats(path_or_id: &str) -> Result<()> {
+    use symor::tui::views::{format_age, format_bytes};
+    let manager = open_manager()?;
+    let Some(item) = manager.resolve_item(path_or_id) else {
+        return Err(not_watched(Path::new(path_or_id)));
+    };
+    let stats = manager.item_stats(item)?;
+    println!("📊 Statistics for: {}", item.path.display());
+    println!("  File ID: {}", item.id);
+    println!("  Versions: {}", stats.versions);
+    if let (Some(first), Some(last)) = (stats.first_backup, stats.last_backup) {
+        println!("  First backup: {}", format_age(first));
+        println!("  Last backup: {}", format_age(last));
+    }
+    match stats.backups_per_day {
+        Some(per_day) => println!("  Backup frequency: {:.2} per day", per_day),
+        None => println!("  Backup frequency: not enough versions"),
+    }
+    println!("  Average version size: {}", format_bytes(stats.average_size));
+    if let Some(growth) = stats.growth_per_day {
+        let sign = if growth < 0.0 { "-" } else { "+" };
+        println!("  Growth rate: {}{} per day", sign, format_bytes(growth.abs() as u64));
+    }
+    if let Some(ratio) = stats.compression_ratio() {
+        println!(
+            "  Stored: {} of {} ({:.1}% of original size)", format_bytes(stats.stored_bytes),
+            format_bytes(stats.original_bytes), ratio * 100.0
+        );
+    }
+    match stats.last_activity {
+        Some(last) => println!("  Last activity: {}", format_age(last)),
+        None => println!("  Last activity: never"),
+    }
+    Ok(())
+}
+fn handle_tui(refresh_rate: u64) -> Result<()> {
+    let manager = open_manager()?;
+    let mut tui = symor::tui::SymorTUI::new()?;
+    tui.set_refresh_interval(std::time::Duration::from_secs(refresh_rate));
+    tui.set_log_file(symor::daemon::log_file(&manager.config().home_dir));
+    tui.set_manager(manager);
+    tui.run()?;
+    tui.shutdown()?;
+    Ok(())
+}
+/// Lines of the `sym top` table, each cut to `width` characters
+fn top_lines(manager: &SymorManager, width: usize) -> Vec<String> {
+    use symor::monitoring::{metrics::MetricsStore, status::MirrorStatus};
+    use symor::tui::views::{format_age, format_bytes};
+    let home_dir = &manager.config().home_dir;
+    let daemon = match symor::daemon::running_pid(home_dir) {
+        Some(pid) => format!("daemon running (pid {})", pid),
+        None => "daemon stopped".to_string(),
+    };
+    let mut lines = vec![
+        format!(
+            "symor top - {} - {} mirror(s) - Ctrl-C to quit", daemon, manager.mirrors()
+            .len()
+        ), String::new(),
+        format!(
+            "{:<40} {:>7} {:<8} {:>5} {:>10} {:>6}  {}", "SOURCE", "TARGETS", "STATE",
+            "QUEUE", "LAST SYNC", "ERRORS", "LAST ERROR"
+        ),
+    ];
+    let statuses = MirrorStatus::load_all(home_dir);
+    for spec in manager.mirrors() {
+        let status = statuses
+            .iter()
+            .find(|status| status.source == spec.source)
+            .filter(|status| status.is_live());
+        let state = match status {
+            Some(status) if status.last_error_at > status.last_sync => "failing",
+            Some(_) => "running",
+            None => "stopped",
+        };
+        let source = spec.source.display().to_string();
+        let source = if source.chars().count() > 40 {
+            let tail: Vec<char> = source.chars().rev().take(39).collect();
+            format!("…{}", tail.into_iter().rev().collect::< String > ())
+        } else {
+            source
+        };
+        lines
+            .push(
+                format!(
+                    "{:<40} {:>7} {:<8} {:>5} {:>10} {:>6}  {}", source, spec.targets
+                    .len(), state, status.map(| s | s.queued.to_string()).unwrap_or_else(||
+                    "-".to_string()), status.and_then(| s | s.last_sync).map(format_age)
+                    .unwrap_or_else(|| "-".to_string()), status.map(| s | s.errors)
+                    .unwrap_or(0), status.and_then(| s | s.last_error.as_deref())
+                    .unwrap_or("-")
+                ),
+            );
+    }
+    if manager.mirrors().is_empty() {
+        lines.push("(no mirrors configured)".to_string());
+    }
+    let metrics = MetricsStore::load(home_dir);
+    let hour = metrics.bucket(std::time::SystemTime::now());
+    lines.push(String::new());
+    lines
+        .push(
+            format!(
+                "This hour: {} syncs, {} synced, {} versions, {} errors", hour.syncs,
+                format_bytes(hour.bytes_synced), hour.versions_created, hour.errors
+            ),
+        );
+    let mut log = symor::tui::log_tail::LogTail::new(symor::daemon::log_file(home_dir));
+    let _ = log.poll();
+    let errors: Vec<&String> = log
+        .lines()
+        .filter(|line| line.contains("ERROR") || line.contains("WARN"))
+        .collect();
+    if !errors.is_empty() {
+        lines.push("Recent errors:".to_string());
+        for line in &errors[errors.len().saturating_sub(5)..] {
+            lines.push(format!("  {}", line));
+        }
+    }
+    lines.into_iter().map(|line| line.chars().take(width).collect()).collect()
+}
+fn handle_top(interval: u64, once: bool) -> Result<()> {
+    use std::io::{IsTerminal, Write};
+    let mut manager = open_manager()?;
+    let in_place = !once && std::io::stdout().is_terminal();
+    let mut drawn = 0;
+    loop {
+        manager.load_mirrors()?;
+        let width = match crossterm::terminal::size() {
+            Ok((columns, _)) if in_place && columns > 0 => columns as usize,
+            _ => usize::MAX,
+        };
+        let lines = top_lines(&manager, width);
+        let mut stdout = std::io::stdout();
+        if in_place && drawn > 0 {
+            crossterm::execute!(
+                stdout, crossterm::cursor::MoveUp(drawn as u16),
+                crossterm::terminal::Clear(crossterm::terminal::ClearType::FromCursorDown)
+            )?;
+        } else if drawn > 0 {
+            writeln!(stdout)?;
+        }
+        for line in &lines {
+            writeln!(stdout, "{}", line)?;
+        }
+        stdout.flush()?;
+        drawn = lines.len();
+        if once {
+            return Ok(());
+        }
+        std::thread::sleep(std::time::Duration::from_secs(interval.max(1)));
+    }
+}
+fn handle_doctor() -> Result<()> {
+    use symor::doctor::Severity;
+    println!("Symor Doctor");
+    println!("============");
+    println!();
+    let mut manager = match SymorManager::new() {
+        Ok(manager) => manager,
+        Err(e) => {
+            println!("❌ Home directory: cannot be set up: {}", e);
+            println!("   → Check that your home directory exists and is writable");
+            return Err(e.into());
+        }
+    };
+    let mut load_errors = Vec::new();
+    if let Err(e) = load_config(&mut manager) {
+        load_errors.push(("config", e.to_string()));
+    }
+    if let Err(e) = manager.load_watched_items().and_then(|()| manager.load_mirrors()) {
+        load_errors.push(("state", e.to_string()));
+    }
+    let report = symor::doctor::diagnose(&manager, &load_errors);
+    for finding in &report.findings {
+        let icon = match finding.severity {
+            Severity::Ok => "✅",
+            Severity::Warning => "⚠️ ",
+            Severity::Error => "❌",
+        };
+        println!("{} {}: {}", icon, finding.check, finding.message);
+        if let Some(suggestion) = &finding.suggestion {
+            println!("   → {}", suggestion);
+        }
+    }
+    println!();
+    match report.verdict() {
+        Severity::Ok => println!("✅ Healthy"),
+        Severity::Warning => {
+            println!("⚠️  Healthy with {} warning(s)", report.count(Severity::Warning))
+        }
+        Severity::Error => {
+            println!(
+                "❌ {} problem(s) and {} warning(s) found", report.count(Severity::Error),
+                report.count(Severity::Warning)
+            );
+            return Err(anyhow::anyhow!("sym doctor found problems"));
+        }
+    }
+    Ok(())
+}
+/// Reads back each stored version of `item` that matches `filter` and compares its hash;
+/// returns how many were checked and how many failed
+fn verify_versions(
+    manager: &SymorManager,
+    item: &WatchedItem,
+    filter: &VersionFilter,
+) -> (usize, usize) {
+    let mut checked = 0;
+    let mut failed = 0;
+    for version in item.versions.iter().filter(|v| filter.matches(v)) {
+        checked += 1;
+        match manager.version_content(&item.id, &version.id) {
+            Ok(content) if format!("{:x}", md5::compute(&content)) == version.hash => {}
+            Ok(_) => {
+                failed += 1;
+                println!("✗ Version {} of {} is corrupted", version.id, item.path.display());
+            }
+            Err(e) => {
+                failed += 1;
+                println!(
+                    "✗ Version {} of {} is unreadable: {}", version.id, item.path
+                    .display(), e
+                );
+            }
+        }
+    }
+    (checked, failed)
 }
-fn handle_check(path: Option<PathBuf>) -> Result<()> {
-    let manager = SymorManager::new()?;
+fn handle_check(path: Option<PathBuf>, filter: Option<VersionFilter>) -> Result<()> {
+    let manager = open_manager()?;
     println!("Symor Integrity Check");
     println!("====================");
     println!("");
     if let Some(specific_path) = path {
         println!("Checking integrity for: {}", specific_path.display());
-        let file_id = manager.generate_file_id(&specific_path);
-        if let Some(item) = manager.watched_items().get(&file_id) {
+        if let Some(item) = manager
+            .watched_index()
+            .find(|item| item.path == specific_path)
+        {
             println!("✓ File is being watched");
             println!("  Path: {}", item.path.display());
             println!("  Last modified: {:?}", item.last_modified);
@@ -713,21 +3095,34 @@ fn handle_check(path: Option<PathBuf>) -> Result<()> {
             if let Some(latest) = item.versions.last() {
                 println!("✓ Latest version: {} ({})", latest.id, latest.size);
             }
+            if let Some(filter) = &filter {
+                let (checked, failed) = verify_versions(&manager, item, filter);
+                if failed == 0 {
+                    println!("✓ {} matching versions verified", checked);
+                }
+            }
         } else {
-            println!("✗ Path not being watched: {}", specific_path.display());
+            return Err(not_watched(&specific_path));
         }
     } else {
         println!("Checking all watched files...");
         let mut total_files = 0;
         let mut missing_files = 0;
         let mut total_versions = 0;
-        for item in manager.watched_items().values() {
+        let mut verified = 0;
+        let mut corrupted = 0;
+        for item in manager.watched_index() {
             total_files += 1;
             total_versions += item.versions.len();
             if !item.path.exists() {
                 missing_files += 1;
                 println!("✗ Missing: {}", item.path.display());
             }
+            if let Some(filter) = &filter {
+                let (checked, failed) = verify_versions(&manager, item, filter);
+                verified += checked;
+                corrupted += failed;
+            }
         }
         println!("");
         println!("Summary:");
@@ -739,59 +3134,138 @@ fn handle_check(path: Option<PathBuf>) -> Result<()> {
         } else {
             println!("⚠ {} files are missing", missing_files);
         }
+        if filter.is_some() {
+            if corrupted == 0 {
+                println!("✓ {} matching versions verified", verified);
+            } else {
+                println!("⚠ {} of {} matching versions failed verification", corrupted, verified);
+            }
+        }
     }
     println!("");
     println!("Integrity check complete.");
     Ok(())
 }
 fn handle_conflicts() -> Result<()> {
-    let manager = SymorManager::new()?;
+    let manager = open_manager()?;
     println!("Symor Conflict Detection");
     println!("=======================");
-    println!("");
-    let mut conflicts_found = 0;
-    let mut total_checked = 0;
-    let _target_map: std::collections::HashMap<PathBuf, Vec<String>> = std::collections::HashMap::new();
-    for (file_id, item) in manager.watched_items() {
-        total_checked += 1;
-        if !item.path.exists() {
-            conflicts_found += 1;
-            println!("⚠ Conflict: Missing source file");
-            println!("  File ID: {}", file_id);
-            println!("  Path: {}", item.path.display());
-            println!("  Status: Source file not found");
-            println!("");
+    println!();
+    let conflicts = manager.find_conflicts();
+    for conflict in &conflicts {
+        let (title, status) = match conflict.kind {
+            ConflictKind::Diverged => {
+                ("Mirror target differs from source", "Source and target contents differ")
+            }
+            ConflictKind::MissingSource if conflict.target.is_some() => {
+                ("Missing source file", "Only the mirror target has this file")
+            }
+            ConflictKind::MissingSource => {
+                ("Missing source file", "Source file not found")
+            }
+            ConflictKind::NoVersions => {
+                ("No versions found", "File has no version history")
+            }
+            ConflictKind::CaseCollision => {
+                (
+                    "Names differ only in case",
+                    "The target's file system stores both names as one file",
+                )
+            }
+        };
+        println!("⚠ Conflict: {}", title);
+        if let Some(id) = &conflict.item_id {
+            println!("  File ID: {}", id);
         }
-        if item.versions.is_empty() {
-            conflicts_found += 1;
-            println!("⚠ Conflict: No versions found");
-            println!("  File ID: {}", file_id);
-            println!("  Path: {}", item.path.display());
-            println!("  Status: File has no version history");
-            println!("");
+        println!("  Path: {}", conflict.source.display());
+        if let Some(target) = &conflict.target {
+            println!("  Target: {}", target.display());
+        }
+        println!("  Status: {}", status);
+        if let Some(target) = &conflict.target {
+            println!("  Source: {}", describe_conflict_side(&conflict.source));
+            println!("  Target: {}", describe_conflict_side(target));
+        }
+        match conflict.edited {
+            Some(EditedSide::Both) => println!("  Edited since last sync: source and target"),
+            Some(EditedSide::Target) => println!("  Edited since last sync: target"),
+            None => {}
+        }
+        if let Some((suggested, reason)) = conflict.suggestion() {
+            for (resolution, label) in conflict.resolutions() {
+                if resolution == suggested {
+                    println!("  Suggested: {} ({})", label, reason);
+                }
+            }
+        }
+        let options: Vec<_> = conflict
+            .resolutions()
+            .into_iter()
+            .map(|(_, label)| label)
+            .collect();
+        if !options.is_empty() {
+            println!("  Options: {}", options.join(", "));
         }
+        println!();
+    }
+    let quarantined: Vec<_> = manager
+        .quarantined_conflicts()?
+        .into_iter()
+        .filter(|entry| entry.manifest.resolution.is_none())
+        .collect();
+    for entry in &quarantined {
+        println!("🔒 Quarantined: {}", entry.id);
+        println!("  Source: {}", entry.manifest.source.display());
+        println!("  Target: {}", entry.manifest.target.display());
+        println!("  Copies: {}", entry.dir.display());
+        println!("  Resolve: sym conflicts resolve {} --keep source|target|both", entry.id);
+        println!();
     }
     println!("Conflict Detection Summary:");
-    println!("  Files checked: {}", total_checked);
-    println!("  Conflicts found: {}", conflicts_found);
-    if conflicts_found == 0 {
+    println!("  Files checked: {}", manager.watched_index().len());
+    println!("  Mirrors checked: {}", manager.mirrors().len());
+    println!("  Conflicts found: {}", conflicts.len());
+    println!("  Quarantined: {}", quarantined.len());
+    if conflicts.is_empty() && quarantined.is_empty() {
         println!("✓ No conflicts detected");
-    } else {
-        println!("⚠ {} conflicts require attention", conflicts_found);
+    } else if !conflicts.is_empty() {
+        println!("⚠ {} conflicts require attention", conflicts.len());
+        println!("  Resolve them from the Conflicts view of 'sym tui'");
     }
-    println!("");
+    println!();
     println!("Conflict detection complete.");
     Ok(())
 }
+fn handle_resolve_conflict(id: &str, resolution: Resolution) -> Result<()> {
+    let mut manager = open_manager()?;
+    let message = manager.resolve_quarantined(id, resolution)?;
+    println!("✅ {}", message);
+    Ok(())
+}
+/// Size, age and short content hash of one side of a conflict
+fn describe_conflict_side(path: &Path) -> String {
+    use symor::tui::views::{format_age, format_bytes};
+    let Ok(metadata) = std::fs::metadata(path) else {
+        return "missing".to_string();
+    };
+    let hash = std::fs::read(path)
+        .map(|data| format!("{:x}", md5::compute(data))[..8].to_string())
+        .unwrap_or_else(|_| "unreadable".to_string());
+    let age = metadata.modified().map(format_age).unwrap_or_default();
+    format!("{}, modified {}, md5 {}", format_bytes(metadata.len()), age, hash)
+}
 fn handle_add_target(source: PathBuf, target: PathBuf) -> Result<()> {
-    let manager = SymorManager::new()?;
+    let mut manager = open_manager()?;
     println!("Symor Add Target");
     println!("===============");
     println!("");
     println!("Adding target: {} -> {}", source.display(), target.display());
-    let source_id = manager.generate_file_id(&source);
-    if let Some(item) = manager.watched_items().get(&source_id) {
-        println!("✓ Source is being watched: {}", item.path.display());
+    let watched = manager
+        .watched_index()
+        .find(|item| item.path == source)
+        .map(|item| item.path.clone());
+    if let Some(item_path) = watched {
+        println!("✓ Source is being watched: {}", item_path.display());
         if target.exists() {
             println!("⚠ Target already exists: {}", target.display());
             println!("  This will overwrite the existing file.");
@@ -802,32 +3276,62 @@ fn handle_add_target(source: PathBuf, target: PathBuf) -> Result<()> {
             println!("  Source: {}", source.display());
             println!("  Target: {}", target.display());
             manager.save_watched_items_public()?;
+            manager.add_mirror(&source, std::slice::from_ref(&target), false, &[], None, None)?;
             println!("✓ Configuration updated");
         } else {
             println!("✗ Source file does not exist: {}", source.display());
         }
     } else {
-        println!("✗ Source is not being watched: {}", source.display());
-        println!("  Use 'sym watch {}' first", source.display());
+        return Err(
+            SymorError::new(
+                ErrorCode::NotWatched,
+                format!("Source is not being watched: {}", source.display()),
+            )
+                .with_suggestion(format!("Use 'sym watch {}' first", source.display()))
+                .into(),
+        );
     }
     println!("");
     println!("Add target operation complete.");
     Ok(())
 }
 fn handle_status(path: Option<PathBuf>, verbose: bool) -> Result<()> {
-    let manager = SymorManager::new()?;
+    let manager = open_manager()?;
     println!("Symor Status Report");
     println!("===================");
     println!("");
+    if let Some(client) = daemon_client()? {
+        let status = client.call("GET", "/v1/status", None)?;
+        let state = if status["paused"] == true { "paused" } else { "running" };
+        println!("Daemon: {} (pid {})", state, client.pid);
+        if let Some(last_scan) = status["last_scan"].as_u64() {
+            let at = std::time::UNIX_EPOCH + std::time::Duration::from_secs(last_scan);
+            println!("Last Scan: {}", symor::tui::views::format_age(at));
+        }
+        println!();
+    }
+    if manager.is_paused() {
+        println!("⏸️  All mirrors and watched items are paused ('sym resume --all' to continue)");
+        println!();
+    }
+    if manager.in_maintenance() {
+        println!("🚧 Maintenance mode is on ('sym maintenance off' to end it)");
+        println!();
+    }
     if let Some(specific_path) = path {
-        if let Some(item) = manager
-            .watched_items()
-            .values()
-            .find(|item| item.path == specific_path)
-        {
+        if let Some(item) = manager.resolve_item(&specific_path.to_string_lossy()) {
             println!("Path: {}", item.path.display());
+            if let Some(name) = &item.name {
+                println!("Name: {}", name);
+            }
+            if let Some(description) = &item.description {
+                println!("Description: {}", description);
+            }
             println!("Type: {}", if item.is_directory { "Directory" } else { "File" });
             println!("Recursive: {}", item.recursive);
+            if item.paused {
+                println!("Paused: yes");
+            }
             println!("Versions: {}", item.versions.len());
             println!("Last Modified: {:?}", item.last_modified);
             if verbose {
@@ -838,21 +3342,30 @@ fn handle_status(path: Option<PathBuf>, verbose: bool) -> Result<()> {
                 }
             }
         } else {
-            println!("Path not currently being watched: {}", specific_path.display());
+            return Err(not_watched(&specific_path));
         }
     } else {
-        if manager.watched_items().is_empty() {
+        if manager.watched_index().len() == 0 {
             println!("No files or directories are currently being watched.");
         } else {
-            println!("Watched Items: {}", manager.watched_items().len());
+            println!("Watched Items: {}", manager.watched_index().len());
             println!("");
-            for (id, item) in manager.watched_items() {
-                println!("ID: {}", id);
+            for item in manager.watched_index() {
+                println!("ID: {}", item.id);
+                if let Some(name) = &item.name {
+                    println!("  Name: {}", name);
+                }
                 println!("  Path: {}", item.path.display());
+                if let Some(description) = &item.description {
+                    println!("  Description: {}", description);
+                }
                 println!(
                     "  Type: {}", if item.is_directory { "Directory" } else { "File" }
                 );
                 println!("  Versions: {}", item.versions.len());
+                if item.paused {
+                    println!("  Paused: yes");
+                }
                 if verbose {
                     println!("  Last Modified: {:?}", item.last_modified);
                     println!("  Recursive: {}", item.recursive);
@@ -860,6 +3373,9 @@ fn handle_status(path: Option<PathBuf>, verbose: bool) -> Result<()> {
                 println!("");
             }
         }
+        for spec in manager.mirrors().iter().filter(|spec| spec.paused) {
+            println!("⏸️  Mirror paused: {}", spec.source.display());
+        }
     }
     if verbose {
         println!("System Information:");
@@ -874,38 +3390,86 @@ fn handle_status(path: Option<PathBuf>, verbose: bool) -> Result<()> {
     Ok(())
 }
 fn handle_unmirror(source: PathBuf, target: Option<PathBuf>) -> Result<()> {
-    println!("Unmirror command is under development.");
+    let mut manager = open_manager()?;
+    println!("Symor Unmirror");
+    println!("==============");
+    println!();
     println!("Source: {}", source.display());
-    if let Some(tgt) = target {
+    if let Some(tgt) = &target {
         println!("Target: {}", tgt.display());
     } else {
         println!("Removing all targets for source");
     }
-    println!("");
-    println!("Note: This feature will be implemented to remove mirror relationships.");
-    println!("For now, you can manually stop watching files with 'sym unwatch'");
+    if manager.remove_mirror(&source, target.as_deref())? {
+        println!("✓ Mirror relationship removed");
+        println!("  Restart the daemon for the change to take effect in the background.");
+    } else {
+        return Err(
+            SymorError::new(
+                ErrorCode::FileNotFound,
+                format!("No mirror relationship found for {}", source.display()),
+            )
+                .with_suggestion("Use 'sym daemon status' to list mirrors".to_string())
+                .into(),
+        );
+    }
     Ok(())
 }
-fn handle_history(file_id: String, limit: Option<usize>) -> Result<()> {
-    let manager = SymorManager::new()?;
-    if let Some(item) = manager.watched_items().get(&file_id) {
+/// Most rows `sym history --graph` draws
+const HISTORY_GRAPH_ROWS: usize = 30;
+fn handle_history(
+    file_id: String,
+    limit: Option<usize>,
+    filter: Option<VersionFilter>,
+    graph: bool,
+) -> Result<()> {
+    let manager = open_manager()?;
+    if let Some(item) = manager.resolve_item(&file_id) {
         println!("Version History for: {}", item.path.display());
-        println!("File ID: {}", file_id);
+        println!("File ID: {}", item.id);
         println!("Total Versions: {}", item.versions.len());
         println!("");
-        if item.versions.is_empty() {
-            println!("No versions found for this file.");
+        let versions: Vec<_> = item
+            .versions
+            .iter()
+            .filter(|v| filter.as_ref().is_none_or(|f| f.matches(v)))
+            .collect();
+        if versions.is_empty() {
+            if filter.is_some() {
+                println!("No versions match the filter.");
+            } else {
+                println!("No versions found for this file.");
+            }
             return Ok(());
         }
         let versions_to_show = if let Some(lim) = limit {
-            lim.min(item.versions.len())
+            lim.min(versions.len())
         } else {
-            item.versions.len()
+            versions.len()
         };
-        println!("Showing {} most recent versions:", versions_to_show);
+        if graph {
+            let shown = &versions[versions.len() - versions_to_show..];
+            let (unit, rows) = symor::tui::timeline::timeline(
+                shown.iter().copied(),
+                HISTORY_GRAPH_ROWS,
+            );
+            println!("📈 {} versions, one row per {} (UTC):", shown.len(), unit);
+            println!();
+            for row in rows {
+                println!("{}", row);
+            }
+            return Ok(());
+        }
+        if filter.is_some() {
+            println!(
+                "Showing {} most recent of {} matching versions:", versions_to_show,
+                versions.len()
+            );
+        } else {
+            println!("Showing {} most recent versions:", versions_to_show);
+        }
         println!("");
-        for (i, version) in item.versions.iter().rev().take(versions_to_show).enumerate()
-        {
+        for (i, version) in versions.iter().rev().take(versions_to_show).enumerate() {
             println!("Version {}: {}", i + 1, version.id);
             println!("  Timestamp: {:?}", version.timestamp);
             println!("  Size: {} bytes", version.size);
@@ -916,22 +3480,163 @@ fn handle_history(file_id: String, limit: Option<usize>) -> Result<()> {
             println!("");
         }
         if let Some(lim) = limit {
-            if lim < item.versions.len() {
+            if lim < versions.len() {
                 println!(
-                    "... and {} more versions (use --limit to see more)", item.versions
-                    .len() - lim
+                    "... and {} more versions (use --limit to see more)", versions.len()
+                    - lim
                 );
             }
         }
     } else {
+        return Err(SymorError::not_watched(&file_id).into());
+    }
+    Ok(())
+}
+fn handle_search(
+    pattern: String,
+    path: Option<String>,
+    since: Option<String>,
+    ignore_case: bool,
+) -> Result<()> {
+    let regex = regex::RegexBuilder::new(&pattern)
+        .case_insensitive(ignore_case)
+        .build()
+        .map_err(|e| {
+            SymorError::new(
+                    ErrorCode::InvalidConfiguration,
+                    format!("Invalid search pattern: {}", e),
+                )
+                .with_suggestion(
+                    "Escape regex characters such as . ( [ with a backslash".to_string(),
+                )
+        })?;
+    let options = symor::versioning::SearchOptions::parse(
+        path.as_deref(),
+        since.as_deref(),
+    )?;
+    let manager = open_manager()?;
+    let matches = manager.search_versions(&regex, &options);
+    if matches.is_empty() {
+        println!("🔍 No stored versions match '{}'", pattern);
+        return Ok(());
+    }
+    let mut current_item = "";
+    let mut current_version = "";
+    let mut versions = 0;
+    for m in &matches {
+        if m.item_id != current_item {
+            println!();
+            println!("📄 {} ({})", m.path.display(), m.item_id);
+            current_item = &m.item_id;
+            current_version = "";
+        }
+        if m.version_id != current_version {
+            println!(
+                "  {} ({})", m.version_id, symor::tui::views::format_age(m.timestamp)
+            );
+            current_version = &m.version_id;
+            versions += 1;
+        }
+        println!("    {:>5}: {}", m.line_number, m.line);
+    }
+    println!();
+    println!(
+        "🔍 {} match{} in {} version{}", matches.len(), if matches.len() == 1 { "" }
+        else { "es" }, versions, if versions == 1 { "" } else { "s" }
+    );
+    Ok(())
+}
+fn handle_find_hash(hash: String) -> Result<()> {
+    let hash = if Path::new(&hash).is_file() {
+        let content = std::fs::read(&hash)?;
+        let digest = format!("{:x}", md5::compute(content));
+        println!("🔑 {} hashes to {}", hash, digest);
+        digest
+    } else if hash.len() >= 4 && hash.chars().all(|c| c.is_ascii_hexdigit()) {
+        hash
+    } else {
+        return Err(
+            SymorError::new(
+                    ErrorCode::InvalidConfiguration,
+                    format!("Not a content hash or file: {}", hash),
+                )
+                .with_suggestion(
+                    "Give an MD5 hex digest (at least 4 characters) or the path of a file"
+                        .to_string(),
+                )
+                .into(),
+        );
+    };
+    let manager = open_manager()?;
+    let found = manager.find_by_hash(&hash);
+    if found.is_empty() {
+        println!("🔍 No stored version has content hash {}", hash);
+        return Ok(());
+    }
+    for (item, version) in &found {
+        println!("📄 {} ({})", item.path.display(), item.id);
         println!(
-            "File ID '{}' not found. Use 'sym list' to see available files.", file_id
+            "  {} {} {} ({})", version.id, version.hash, symor::tui::views::format_bytes(version
+            .size), symor::tui::views::format_age(version.timestamp)
         );
     }
+    let items = found
+        .iter()
+        .map(|(item, _)| &item.id)
+        .collect::<std::collections::HashSet<_>>()
+        .len();
+    println!();
+    println!(
+        "🔍 {} version{} across {} item{}", found.len(), if found.len() == 1 { "" } else {
+        "s" }, items, if items == 1 { "" } else { "s" }
+    );
     Ok(())
 }
-fn handle_clean(dry_run: bool, file: Option<String>, keep: usize) -> Result<()> {
-    let mut manager = SymorManager::new()?;
+/// Removes the oldest versions matching `filter` while more than `keep` remain, skipping
+/// those under the retention lock `window`; also returns how many were skipped
+fn prune_versions(
+    versions: &mut Vec<FileVersion>,
+    keep: usize,
+    filter: Option<&VersionFilter>,
+    window: Option<std::time::Duration>,
+) -> (Vec<FileVersion>, usize) {
+    let mut removed = Vec::new();
+    let mut held = 0;
+    let mut index = 0;
+    while versions.len() > keep && index < versions.len() {
+        if !filter.is_none_or(|f| f.matches(&versions[index])) {
+            index += 1;
+        } else if symor::retention::is_retained(&versions[index], window) {
+            held += 1;
+            index += 1;
+        } else {
+            removed.push(versions.remove(index));
+        }
+    }
+    (removed, held.min(versions.len().saturating_sub(keep)))
+}
+fn handle_clean(
+    dry_run: bool,
+    file: Option<String>,
+    keep: usize,
+    filter: Option<VersionFilter>,
+) -> Result<()> {
+    let mut manager = open_manager()?;
+    let _lock = if dry_run {
+        None
+    } else {
+        manager.ensure_writable("clean versions")?;
+        let storage = manager.version_storage();
+        let lock = match storage.try_lock_exclusive()? {
+            Some(lock) => lock,
+            None => {
+                println!("⏳ Waiting for running backups and restores to finish...");
+                storage.lock_exclusive()?
+            }
+        };
+        manager.load_watched_items()?;
+        Some(lock)
+    };
     println!("Symor Cleanup");
     println!("=============");
     println!("");
@@ -941,88 +3646,96 @@ fn handle_clean(dry_run: bool, file: Option<String>, keep: usize) -> Result<()>
     }
     let mut total_cleaned = 0;
     let mut total_space_freed = 0;
+    let mut total_held = 0;
+    let window = manager.retention_window();
     if let Some(file_id) = file {
-        if let Some(item) = manager.watched_items_mut().get_mut(&file_id) {
-            println!("Cleaning file: {}", item.path.display());
+        let item = manager.watched_item_mut(&file_id)?;
+        println!("Cleaning file: {}", item.path.display());
+        let original_count = item.versions.len();
+        let (versions_to_delete, held) =
+            prune_versions(&mut item.versions, keep, filter.as_ref(), window);
+        total_held += held;
+        let cleaned_count = versions_to_delete.len();
+        let space_freed: u64 = versions_to_delete.iter().map(|v| v.size).sum();
+        let _ = item;
+        if !dry_run {
+            for version in versions_to_delete {
+                if let Some(ref backup_path) = version.backup_path {
+                    let _ = std::fs::remove_file(backup_path);
+                }
+                if manager.version_storage().delete_version(&version.id).is_ok() {
+                    let event = VersionEvent::new(VersionAction::Pruned, &file_id, &version);
+                    manager.run_version_hooks(&event);
+                }
+            }
+        }
+        if cleaned_count > 0 {
+            println!(
+                "  Cleaned {} versions, freed {} bytes", cleaned_count, space_freed
+            );
+            total_cleaned += cleaned_count;
+            total_space_freed += space_freed;
+        } else {
+            println!(
+                "  No cleanup needed ({} versions, keeping {})", original_count, keep
+            );
+        }
+    } else {
+        let file_ids: Vec<String> =
+            manager.watched_index().map(|item| item.id.clone()).collect();
+        for file_id in file_ids {
+            let item = manager.watched_item_mut(&file_id)?;
+            println!("Cleaning file: {} ({})", item.path.display(), file_id);
             let original_count = item.versions.len();
-            let mut cleaned_count = 0;
-            let mut space_freed = 0;
-            let mut versions_to_delete = Vec::new();
-            while item.versions.len() > keep {
-                let version = item.versions.remove(0);
-                cleaned_count += 1;
-                space_freed += version.size;
-                versions_to_delete.push(version);
-            }
-            let _ = item;
+            let (versions_to_delete, held) = prune_versions(
+                &mut item.versions,
+                keep,
+                filter.as_ref(),
+                window,
+            );
+            total_held += held;
+            let cleaned_count = versions_to_delete.len();
+            let space_freed: u64 = versions_to_delete.iter().map(|v| v.size).sum();
+            if item.versions.is_empty() && !dry_run {
+                manager.unwatch(&file_id)?;
+            }
             if !dry_run {
                 for version in versions_to_delete {
                     if let Some(ref backup_path) = version.backup_path {
                         let _ = std::fs::remove_file(backup_path);
                     }
-                    let _ = manager.version_storage().delete_version(&version.id);
+                    if manager.version_storage().delete_version(&version.id).is_ok() {
+                        let pruned = VersionAction::Pruned;
+                        let event = VersionEvent::new(pruned, &file_id, &version);
+                        manager.run_version_hooks(&event);
+                    }
                 }
             }
             if cleaned_count > 0 {
                 println!(
-                    "  Cleaned {} versions, freed {} bytes", cleaned_count, space_freed
+                    "  Cleaned {} versions, freed {} bytes", cleaned_count,
+                    space_freed
                 );
                 total_cleaned += cleaned_count;
                 total_space_freed += space_freed;
             } else {
                 println!(
-                    "  No cleanup needed ({} versions, keeping {})", original_count, keep
+                    "  No cleanup needed ({} versions, keeping {})", original_count,
+                    keep
                 );
             }
-        } else {
-            println!("File ID '{}' not found.", file_id);
-        }
-    } else {
-        let file_ids: Vec<String> = manager.watched_items().keys().cloned().collect();
-        for file_id in file_ids {
-            if let Some(mut item) = manager.watched_items_mut().remove(&file_id) {
-                println!("Cleaning file: {} ({})", item.path.display(), file_id);
-                let original_count = item.versions.len();
-                let mut cleaned_count = 0;
-                let mut space_freed = 0;
-                let mut versions_to_delete = Vec::new();
-                while item.versions.len() > keep {
-                    let version = item.versions.remove(0);
-                    cleaned_count += 1;
-                    space_freed += version.size;
-                    versions_to_delete.push(version);
-                }
-                if !item.versions.is_empty() {
-                    manager.watched_items_mut().insert(file_id.clone(), item);
-                }
-                if !dry_run {
-                    for version in versions_to_delete {
-                        if let Some(ref backup_path) = version.backup_path {
-                            let _ = std::fs::remove_file(backup_path);
-                        }
-                        let _ = manager.version_storage().delete_version(&version.id);
-                    }
-                }
-                if cleaned_count > 0 {
-                    println!(
-                        "  Cleaned {} versions, freed {} bytes", cleaned_count,
-                        space_freed
-                    );
-                    total_cleaned += cleaned_count;
-                    total_space_freed += space_freed;
-                } else {
-                    println!(
-                        "  No cleanup needed ({} versions, keeping {})", original_count,
-                        keep
-                    );
-                }
-            }
         }
     }
     println!("");
     println!("Cleanup Summary:");
     println!("  Total versions cleaned: {}", total_cleaned);
     println!("  Total space freed: {} bytes", total_space_freed);
+    if total_held > 0 {
+        println!(
+            "  🔒 Kept under the {}-day retention lock: {}",
+            manager.config().versioning.retention_lock_days, total_held
+        );
+    }
     if dry_run {
         println!("");
         println!(
@@ -1030,78 +3743,402 @@ fn handle_clean(dry_run: bool, file: Option<String>, keep: usize) -> Result<()>
         );
     } else {
         manager.save_watched_items_public()?;
+        let expired = manager.cleanup_restore_files()?;
+        if expired > 0 {
+            println!("  Expired .pre-restore backups removed: {}", expired);
+        }
     }
     Ok(())
 }
-fn handle_unwatch(path: PathBuf) -> Result<()> {
-    let mut manager = SymorManager::new()?;
-    let item_id = manager
-        .watched_items()
+fn handle_apply(file: Option<PathBuf>, dry_run: bool) -> Result<()> {
+    use symor::config::{topology, Topology, TopologyChange};
+    let mut manager = open_manager()?;
+    let file = match file.or_else(|| topology::find_topology(&manager.config().home_dir)) {
+        Some(file) => file,
+        None => {
+            return Err(
+                SymorError::new(
+                        ErrorCode::MissingConfiguration,
+                        format!(
+                            "No {} in {}", topology::TOPOLOGY_FILES.join(" or "), manager
+                            .config().home_dir.display()
+                        ),
+                    )
+                    .with_suggestion("Pass the topology file to apply".to_string())
+                    .into(),
+            );
+        }
+    };
+    let topology = Topology::load(&file)?;
+    let changes = if dry_run {
+        manager.plan_topology(&topology, true)
+    } else {
+        manager.apply_topology(&topology, true)?
+    };
+    let applied = changes
         .iter()
-        .find(|(_, item)| item.path == path)
-        .map(|(id, _)| id.clone());
-    if let Some(id) = item_id {
-        manager.watched_items_mut().remove(&id);
-        manager.save_watched_items_public()?;
-        println!("Stopped watching: {}", path.display());
+        .filter(|change| !matches!(change, TopologyChange::MissingWatch(_)))
+        .count();
+    for change in &changes {
+        println!("  {}", change);
+    }
+    if applied == 0 {
+        println!("✅ Already up to date with {}", file.display());
+    } else if dry_run {
+        println!("📐 {} changes would be applied from {}", applied, file.display());
+    } else {
+        println!("📐 Applied {} changes from {}", applied, file.display());
+        if symor::daemon::running_pid(&manager.config().home_dir).is_some() {
+            println!("💡 Restart the daemon to pick up mirror changes");
+        }
+    }
+    Ok(())
+}
+/// Sets, clears or (with neither `description` nor `clear`) prints an item's description
+fn handle_annotate(item: String, description: Option<String>, clear: bool) -> Result<()> {
+    let mut manager = open_manager()?;
+    if description.is_none() && !clear {
+        let found = manager
+            .resolve_item(&item)
+            .ok_or_else(|| SymorError::not_watched(&item))?;
+        match &found.description {
+            Some(description) => println!("📝 {}: {}", found.path.display(), description),
+            None => println!("No description for {}", found.path.display()),
+        }
+        return Ok(());
+    }
+    let path = manager.annotate(&item, description.clone())?;
+    match description.filter(|text| !text.trim().is_empty()) {
+        Some(description) => println!("📝 {}: {}", path.display(), description.trim()),
+        None => println!("📝 Removed the description of {}", path.display()),
+    }
+    Ok(())
+}
+fn handle_report(format: ReportFormat, stale: &str, output: Option<PathBuf>) -> Result<()> {
+    let manager = open_manager()?;
+    let report = manager.backup_report(symor::report::parse_stale_after(stale)?)?;
+    let text = report.render(format);
+    let Some(output) = output else {
+        print!("{}", text);
+        return Ok(());
+    };
+    std::fs::write(&output, text)?;
+    let attention = report.needing_attention().count();
+    println!(
+        "📋 Wrote the {} report on {} items to {}", format, report.items.len(), output.display()
+    );
+    if attention > 0 || !report.errors.is_empty() {
+        println!(
+            "⚠️  {} without a recent backup, {} recent errors", attention, report.errors.len()
+        );
+    }
+    Ok(())
+}
+/// Pauses or resumes one item or mirror, or everything when `path` is `None`
+fn handle_pause(path: Option<PathBuf>, paused: bool) -> Result<()> {
+    let item = match path {
+        Some(path) if path.exists() => Some(std::path::absolute(path)?.display().to_string()),
+        Some(id) => Some(id.display().to_string()),
+        None => None,
+    };
+    let path = if let Some(client) = daemon_client()? {
+        let mut url = format!("/v1/{}", if paused { "pause" } else { "resume" });
+        if let Some(item) = &item {
+            url.push_str(&format!("?item={}", percent_encode(item)));
+        }
+        let reply = client.call("POST", &url, None)?;
+        reply["path"].as_str().map(PathBuf::from)
+    } else {
+        let mut manager = open_manager()?;
+        match &item {
+            Some(item) => Some(manager.set_paused(item, paused)?),
+            None => {
+                manager.set_all_paused(paused)?;
+                None
+            }
+        }
+    };
+    match (&path, paused) {
+        (Some(path), true) => println!("⏸️  Paused {}", path.display()),
+        (Some(path), false) => println!("▶️  Resumed {}", path.display()),
+        (None, true) => println!("⏸️  Paused all mirrors and watched items"),
+        (None, false) => println!("▶️  Resumed all mirrors and watched items"),
+    }
+    if paused {
+        let what = path.map_or("--all".to_string(), |path| path.display().to_string());
+        println!("   Run 'sym resume {}' to continue", what);
+    }
+    Ok(())
+}
+fn handle_maintenance(action: Option<MaintenanceCommand>) -> Result<()> {
+    let manager = open_manager()?;
+    match action {
+        None => {
+            match manager.maintenance() {
+                Some(maintenance) => {
+                    let since = symor::tui::views::format_age(maintenance.since);
+                    println!("🚧 Maintenance mode is on (since {})", since);
+                    if let Some(reason) = maintenance.reason {
+                        println!("  Reason: {}", reason);
+                    }
+                }
+                None => println!("✅ Maintenance mode is off"),
+            }
+        }
+        Some(MaintenanceCommand::On { reason }) => {
+            manager.start_maintenance(reason)?;
+            println!("🚧 Maintenance mode on");
+            println!("   Backups, cleans and restores are refused and the daemon holds its work");
+            println!("   Run 'sym maintenance off' when you are done");
+        }
+        Some(MaintenanceCommand::Off) => {
+            if manager.end_maintenance()? {
+                println!("✅ Maintenance mode off");
+            } else {
+                println!("ℹ️  Maintenance mode was not on");
+            }
+        }
+    }
+    Ok(())
+}
+fn handle_dictionary(action: Option<DictionaryCommand>) -> Result<()> {
+    use symor::tui::format::format_bytes;
+    let manager = open_manager()?;
+    let storage = manager.version_storage();
+    match action {
+        None => {
+            match storage.active_dictionary() {
+                Some(id) => {
+                    println!("📚 New small versions are compressed with dictionary {}", id)
+                }
+                None => println!("📚 No compression dictionary is active"),
+            }
+            let stats = storage.get_stats()?;
+            println!(
+                "  Versions: {} ({} with a dictionary)", stats.total_versions,
+                stats.dictionary_versions
+            );
+            if stats.total_original_size > 0 {
+                println!(
+                    "  Stored: {} of {} ({:.1}%)", format_bytes(stats.total_compressed_size),
+                    format_bytes(stats.total_original_size), stats.compression_ratio * 100.0
+                );
+            }
+            println!("  Dictionaries kept: {}", storage.dictionaries().len());
+        }
+        Some(DictionaryCommand::Train) => {
+            manager.ensure_writable("train a compression dictionary")?;
+            let _lock = storage.lock_exclusive()?;
+            let report = storage.train_dictionary()?;
+            println!(
+                "📚 Trained dictionary {} ({}) on {} files", report.id,
+                format_bytes(report.size as u64), report.samples
+            );
+            println!(
+                "  Those files compress to {} instead of {} with it",
+                format_bytes(report.after), format_bytes(report.before)
+            );
+            if report.removed > 0 {
+                println!("  Removed {} unused older dictionaries", report.removed);
+            }
+        }
+        Some(DictionaryCommand::Off) => {
+            manager.ensure_writable("change the compression dictionary")?;
+            storage.set_active_dictionary(None)?;
+            println!("✅ New versions are compressed without a dictionary");
+        }
+    }
+    Ok(())
+}
+fn handle_clean_home(dry_run: bool) -> Result<()> {
+    use symor::housekeeping::Leftover;
+    use symor::tui::views::format_bytes;
+    let manager = open_manager()?;
+    let cleanup = manager.clean_home(dry_run)?;
+    let verb = if dry_run { "Would remove" } else { "Removed" };
+    for leftover in &cleanup.removed {
+        println!(
+            "🗑️  {} {} ({})", verb, leftover.path.display(), format_bytes(leftover.size)
+        );
+    }
+    let kinds = [
+        (Leftover::StaleGroup, "Stale group directories"),
+        (Leftover::TempFile, "Expired temp files"),
+        (Leftover::RotatedLog, "Old rotated logs"),
+        (Leftover::SyncTemp, "Orphaned .tmp-sync files"),
+    ];
+    println!("🧹 Home directory cleanup: {}", manager.config().home_dir.display());
+    for (kind, label) in kinds {
+        println!("  {}: {}", label, cleanup.count(kind));
+    }
+    let space = format_bytes(cleanup.bytes());
+    if dry_run {
+        println!("  Space that would be reclaimed: {}", space);
+    } else {
+        println!("  Space reclaimed: {}", space);
+    }
+    Ok(())
+}
+fn handle_unwatch(path: PathBuf, purge: bool) -> Result<()> {
+    let mut manager = open_manager()?;
+    let item = manager
+        .resolve_item(&path.to_string_lossy())
+        .map(|item| (item.id.clone(), item.path.clone()));
+    if let Some((id, item_path)) = item {
+        if purge {
+            manager.ensure_deletable(&manager.watched_item(&id)?.versions, "purge versions")?;
+        }
+        let removed = manager.unwatch(&id)?;
+        println!("Stopped watching: {}", item_path.display());
         println!("File ID: {}", id);
+        if let Some(item) = removed.filter(|_| purge) {
+            let count = manager.purge_versions(&item)?;
+            if manager.config().linking.trash {
+                println!("🗑️  Moved {} stored versions to the trash", count);
+            } else {
+                println!("🗑️  Deleted {} stored versions", count);
+            }
+        }
     } else {
-        println!("Path not currently being watched: {}", path.display());
-        println!("Use 'sym list' to see currently watched files.");
+        return Err(not_watched(&path));
     }
     Ok(())
 }
-fn handle_sync(path: Option<PathBuf>, force: bool) -> Result<()> {
-    let mut manager = SymorManager::new()?;
+fn handle_sync(path: Option<PathBuf>, force: bool, jobs: Option<usize>) -> Result<()> {
+    if let Some(client) = daemon_client()? {
+        return sync_through(&client, path, force);
+    }
+    let mut manager = open_manager()?;
+    manager.load_hash_cache();
     if let Some(specific_path) = path {
-        if let Some(id) = manager
-            .watched_items()
-            .iter()
-            .find(|(_, item)| item.path == specific_path)
-            .map(|(id, _)| id.clone())
+        if let Some((id, item_path)) = manager
+            .resolve_item(&specific_path.to_string_lossy())
+            .map(|item| (item.id.clone(), item.path.clone()))
         {
-            println!("Syncing: {}", specific_path.display());
-            if force
-                || manager.change_detector_mut().scan_file(&specific_path)?.is_some()
-            {
+            println!("Syncing: {}", item_path.display());
+            if force || manager.change_detector_mut().scan_file(&item_path)?.is_some() {
                 manager.create_backup(&id)?;
-                println!("Created new version for: {}", specific_path.display());
+                println!("Created new version for: {}", item_path.display());
             } else {
-                println!("No changes detected for: {}", specific_path.display());
+                println!("No changes detected for: {}", item_path.display());
             }
+            manager.save_hash_cache()?;
         } else {
-            println!("Path not currently being watched: {}", specific_path.display());
-            println!("Use 'sym watch <path>' to start watching this file.");
+            return Err(not_watched(&specific_path));
         }
     } else {
-        println!("Syncing all watched files...");
-        let mut synced_count = 0;
-        let mut changed_count = 0;
+        let jobs = jobs
+            .unwrap_or_else(symor::performance::ParallelProcessor::get_optimal_concurrency);
+        println!("Syncing all watched files ({} jobs)...", jobs);
         let watched_items: Vec<(String, PathBuf)> = manager
-            .watched_items()
-            .iter()
-            .map(|(id, item)| (id.clone(), item.path.clone()))
+            .watched_index()
+            .filter(|item| !item.paused)
+            .map(|item| (item.id.clone(), item.path.clone()))
             .collect();
-        for (id, path) in watched_items {
-            synced_count += 1;
-            println!("Checking: {}", path.display());
-            let has_changes = if force {
-                true
-            } else {
-                manager.change_detector_mut().scan_file(&path)?.is_some()
-            };
-            if has_changes {
-                manager.create_backup(&id)?;
-                changed_count += 1;
-                println!("  ✓ Created new version");
-            } else {
-                println!("  - No changes");
+        let paused = manager.watched_index().len() - watched_items.len();
+        let mut failures = Vec::new();
+        let mut changed = Vec::new();
+        for (id, path) in &watched_items {
+            if force {
+                changed.push(id.clone());
+                continue;
+            }
+            match manager.change_detector_mut().scan_file(path) {
+                Ok(Some(_)) => changed.push(id.clone()),
+                Ok(None) => {}
+                Err(e) => failures.push((path.clone(), e.to_string())),
+            }
+        }
+        let mut changed_count = 0;
+        for (id, result) in manager.backup_items(&changed, jobs)? {
+            let path = &manager.watched_item(&id)?.path;
+            match result {
+                Ok(()) => {
+                    changed_count += 1;
+                    println!("  ✓ Created new version: {}", path.display());
+                }
+                Err(e) => failures.push((path.clone(), e.to_string())),
             }
         }
+        for (path, _) in &failures {
+            manager.change_detector_mut().forget(path);
+        }
+        manager.save_hash_cache()?;
+        for (path, error) in &failures {
+            println!("  ❌ {}: {}", path.display(), error);
+        }
         println!("");
         println!("Sync Summary:");
-        println!("  Files checked: {}", synced_count);
+        println!("  Files checked: {}", watched_items.len());
         println!("  Files with changes: {}", changed_count);
+        if paused > 0 {
+            println!("  Paused (skipped): {}", paused);
+        }
+        if !failures.is_empty() {
+            println!("  Failed: {}", failures.len());
+            return Err(
+                SymorError::new(
+                        ErrorCode::InternalError,
+                        format!(
+                            "{} of {} items failed to sync", failures.len(), watched_items
+                            .len()
+                        ),
+                    )
+                    .into(),
+            );
+        }
+    }
+    Ok(())
+}
+/// `sym sync` run by the daemon, which versions the files itself
+fn sync_through(client: &ApiClient, path: Option<PathBuf>, force: bool) -> Result<()> {
+    let mut url = format!("/v1/sync?force={}", force);
+    if let Some(path) = &path {
+        let path = std::path::absolute(path)?;
+        url.push_str(&format!("&item={}", percent_encode(&path.to_string_lossy())));
+    }
+    println!("Syncing through the daemon (pid {})...", client.pid);
+    let reply = client.call("POST", &url, None)?;
+    let entries = |key: &str| reply[key].as_array().cloned().unwrap_or_default();
+    let (versioned, failed) = (entries("versioned"), entries("failed"));
+    for entry in &versioned {
+        println!("  ✓ Created new version: {}", entry["path"].as_str().unwrap_or_default());
+    }
+    for entry in &failed {
+        println!(
+            "  ❌ {}: {}", entry["path"].as_str().unwrap_or_default(), entry["error"].as_str()
+            .unwrap_or_default()
+        );
+    }
+    if let (Some(path), true) = (&path, versioned.is_empty() && failed.is_empty()) {
+        println!("No changes detected for: {}", path.display());
+    }
+    println!("  Files with changes: {}", versioned.len());
+    if !failed.is_empty() {
+        return Err(
+            SymorError::new(
+                    ErrorCode::InternalError,
+                    format!("{} items failed to sync", failed.len()),
+                )
+                .into(),
+        );
     }
     Ok(())
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+    #[test]
+    fn test_init_defaults_asks_nothing() {
+        let config = SymorConfig::default();
+        let answers = ask_init(config.clone(), true).unwrap();
+        assert!(!answers.install && !answers.service && answers.watch_paths.is_empty());
+        assert_eq!(answers.config.home_dir, config.home_dir);
+        let home = tempdir().unwrap();
+        let executable = home.path().join("sym");
+        let spec = init_service_spec(home.path(), Some(executable.clone())).unwrap();
+        assert_eq!(spec.executable, executable);
+        assert_eq!(spec.daemon_args()[0], format!("--portable={}", home.path().display()));
+    }
 }
\ No newline at end of file
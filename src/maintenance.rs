@@ -0,0 +1,105 @@
+use crate::{
+    errors::{Context, ErrorCode, Result, SymorError},
+    SymorManager,
+};
+use serde::{Deserialize, Serialize};
+use std::{fs, path::{Path, PathBuf}, time::SystemTime};
+/// Maintenance mode, set by `sym maintenance on` for store migrations, verification
+/// runs or restores from an external backup: the daemon stands still and backups,
+/// cleans and restores are refused until it is switched off
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Maintenance {
+    pub since: SystemTime,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+pub fn maintenance_file(home_dir: &Path) -> PathBuf {
+    home_dir.join("maintenance.json")
+}
+impl SymorManager {
+    /// The maintenance mode in effect, if any; a marker that cannot be parsed still
+    /// counts
+    pub fn maintenance(&self) -> Option<Maintenance> {
+        let path = maintenance_file(&self.config.home_dir);
+        let data = fs::read_to_string(&path).ok()?;
+        let fallback = || Maintenance {
+            since: fs::metadata(&path)
+                .and_then(|m| m.modified())
+                .unwrap_or(SystemTime::UNIX_EPOCH),
+            reason: None,
+        };
+        Some(serde_json::from_str(&data).unwrap_or_else(|_| fallback()))
+    }
+    pub fn in_maintenance(&self) -> bool {
+        maintenance_file(&self.config.home_dir).exists()
+    }
+    /// Switches maintenance mode on, keeping the start time when it already was
+    pub fn start_maintenance(&self, reason: Option<String>) -> Result<Maintenance> {
+        let maintenance = Maintenance {
+            since: self.maintenance().map_or_else(SystemTime::now, |current| current.since),
+            reason,
+        };
+        let path = maintenance_file(&self.config.home_dir);
+        fs::write(&path, serde_json::to_string_pretty(&maintenance)?)
+            .with_context(|| format!("cannot write {:?}", path))?;
+        Ok(maintenance)
+    }
+    /// Switches maintenance mode off; false when it was not on
+    pub fn end_maintenance(&self) -> Result<bool> {
+        let path = maintenance_file(&self.config.home_dir);
+        if !path.exists() {
+            return Ok(false);
+        }
+        fs::remove_file(&path).with_context(|| format!("cannot remove {:?}", path))?;
+        Ok(true)
+    }
+    /// Fails with `MaintenanceMode` while maintenance mode is on, naming `operation`
+    pub fn ensure_writable(&self, operation: &str) -> Result<()> {
+        let Some(maintenance) = self.maintenance() else {
+            return Ok(());
+        };
+        let reason = maintenance.reason.map(|reason| format!(" ({})", reason));
+        let message = format!(
+            "Cannot {} in maintenance mode{}", operation, reason.unwrap_or_default()
+        );
+        let suggestion = "Run 'sym maintenance off' once the maintenance is done".to_string();
+        Err(SymorError::new(ErrorCode::MaintenanceMode, message).with_suggestion(suggestion))
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+    #[test]
+    fn test_maintenance_mode() {
+        let temp_dir = tempdir().unwrap();
+        let mut manager = SymorManager::new().unwrap();
+        manager.config.home_dir = temp_dir.path().join("home");
+        fs::create_dir_all(&manager.config.home_dir).unwrap();
+        manager.version_storage = crate::versioning::storage::VersionStorage::with_config(
+            crate::versioning::storage::StorageConfig {
+                storage_path: temp_dir.path().join("home/versions"),
+                ..Default::default()
+            },
+        );
+        let path = temp_dir.path().join("notes.txt");
+        fs::write(&path, "first").unwrap();
+        let id = manager.watch_only(path.clone(), false, Vec::new(), false).unwrap();
+        assert!(!manager.in_maintenance() && manager.ensure_writable("back up").is_ok());
+        let started = manager.start_maintenance(Some("store migration".to_string())).unwrap();
+        let again = manager.start_maintenance(None).unwrap();
+        assert_eq!(again.since, started.since);
+        manager.start_maintenance(Some("store migration".to_string())).unwrap();
+        fs::write(&path, "second").unwrap();
+        let error = manager.create_backup(&id).unwrap_err();
+        assert_eq!(error.code, ErrorCode::MaintenanceMode);
+        assert!(error.message.contains("(store migration)"), "{}", error.message);
+        let version = manager.watched_item(&id).unwrap().versions[0].id.clone();
+        let target = temp_dir.path().join("restored.txt");
+        assert!(manager.restore_file(&id, &version, &target).is_err() && !target.exists());
+        assert!(manager.unwatch(&id).is_err());
+        assert!(manager.end_maintenance().unwrap() && !manager.end_maintenance().unwrap());
+        manager.create_backup(&id).unwrap();
+        assert_eq!(manager.watched_item(&id).unwrap().versions.len(), 2);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,162 @@
+use std::ops::Range;
+/// Largest number of base lines times side lines the diff takes on; bigger changes are
+/// left for the user
+const MAX_CELLS: usize = 4_000_000;
+/// Result of `merge_lines`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LineMerge {
+    /// Both sides' edits applied
+    Clean(String),
+    /// How many regions both sides changed differently
+    Overlapping(usize),
+}
+/// Base lines one side replaced with some of its own lines
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Hunk {
+    base: Range<usize>,
+    side: Range<usize>,
+}
+/// Lines with their endings, so merged text keeps them as they were
+fn lines(text: &str) -> Vec<&str> {
+    text.split_inclusive('\n').collect()
+}
+/// Changes that turn `base` into `side`, from their longest common subsequence, or
+/// `None` when the changed middle is too large to compare
+fn diff(base: &[&str], side: &[&str]) -> Option<Vec<Hunk>> {
+    let prefix = base.iter().zip(side).take_while(|(a, b)| a == b).count();
+    let suffix = base[prefix..]
+        .iter()
+        .rev()
+        .zip(side[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (b, s) = (&base[prefix..base.len() - suffix], &side[prefix..side.len() - suffix]);
+    if b.len().saturating_mul(s.len()) > MAX_CELLS {
+        return None;
+    }
+    let width = s.len() + 1;
+    let mut common = vec![0u32; (b.len() + 1) * width];
+    for i in (0..b.len()).rev() {
+        for j in (0..s.len()).rev() {
+            common[i * width + j] = if b[i] == s[j] {
+                common[(i + 1) * width + j + 1] + 1
+            } else {
+                common[(i + 1) * width + j].max(common[i * width + j + 1])
+            };
+        }
+    }
+    let mut hunks = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    let mut open: Option<(usize, usize)> = None;
+    while i < b.len() || j < s.len() {
+        if i < b.len() && j < s.len() && b[i] == s[j] {
+            if let Some((start_i, start_j)) = open.take() {
+                hunks.push(Hunk {
+                    base: prefix + start_i..prefix + i,
+                    side: prefix + start_j..prefix + j,
+                });
+            }
+            i += 1;
+            j += 1;
+        } else {
+            open.get_or_insert((i, j));
+            let skip_side = j < s.len()
+                && (i == b.len() || common[i * width + j + 1] >= common[(i + 1) * width + j]);
+            if skip_side {
+                j += 1;
+            } else {
+                i += 1;
+            }
+        }
+    }
+    if let Some((start_i, start_j)) = open {
+        hunks.push(Hunk {
+            base: prefix + start_i..prefix + i,
+            side: prefix + start_j..prefix + j,
+        });
+    }
+    Some(hunks)
+}
+/// One side's text for `base[range]`, with its `hunks` inside that range applied
+fn apply(base: &[&str], range: Range<usize>, hunks: &[Hunk], side: &[&str]) -> String {
+    let mut text = String::new();
+    let mut at = range.start;
+    for hunk in hunks {
+        text.push_str(&base[at..hunk.base.start].concat());
+        text.push_str(&side[hunk.side.clone()].concat());
+        at = hunk.base.end;
+    }
+    text.push_str(&base[at..range.end].concat());
+    text
+}
+/// Three-way merge of `ours` and `theirs`, both edited from `base`. Edits that touch
+/// the same or adjacent base lines overlap unless both sides made the same change.
+/// `None` when the texts are too far apart to compare line by line.
+pub fn merge_lines(base: &str, ours: &str, theirs: &str) -> Option<LineMerge> {
+    let (base, ours, theirs) = (lines(base), lines(ours), lines(theirs));
+    let (our_hunks, their_hunks) = (diff(&base, &ours)?, diff(&base, &theirs)?);
+    let mut merged = String::new();
+    let mut overlaps = 0;
+    let (mut x, mut y, mut at) = (0, 0, 0);
+    while x < our_hunks.len() || y < their_hunks.len() {
+        let start = match (our_hunks.get(x), their_hunks.get(y)) {
+            (Some(a), Some(b)) => a.base.start.min(b.base.start),
+            (Some(a), None) => a.base.start,
+            (None, Some(b)) => b.base.start,
+            (None, None) => break,
+        };
+        let (first_x, first_y, mut end) = (x, y, start);
+        loop {
+            if let Some(hunk) = our_hunks.get(x).filter(|hunk| hunk.base.start <= end) {
+                end = end.max(hunk.base.end);
+                x += 1;
+            } else if let Some(hunk) = their_hunks.get(y).filter(|hunk| hunk.base.start <= end) {
+                end = end.max(hunk.base.end);
+                y += 1;
+            } else {
+                break;
+            }
+        }
+        merged.push_str(&base[at..start].concat());
+        let ours_text = apply(&base, start..end, &our_hunks[first_x..x], &ours);
+        let theirs_text = apply(&base, start..end, &their_hunks[first_y..y], &theirs);
+        if first_y == y || ours_text == theirs_text {
+            merged.push_str(&ours_text);
+        } else if first_x == x {
+            merged.push_str(&theirs_text);
+        } else {
+            overlaps += 1;
+        }
+        at = end;
+    }
+    merged.push_str(&base[at..].concat());
+    Some(if overlaps == 0 { LineMerge::Clean(merged) } else { LineMerge::Overlapping(overlaps) })
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_merge_lines() {
+        let base = "one\ntwo\nthree\nfour\nfive\n";
+        let ours = "ONE\ntwo\nthree\nfour\nfive\nsix\n";
+        let theirs = "one\ntwo\nthree\nFOUR\nfive\n";
+        assert_eq!(
+            merge_lines(base, ours, theirs),
+            Some(LineMerge::Clean("ONE\ntwo\nthree\nFOUR\nfive\nsix\n".to_string()))
+        );
+        assert_eq!(
+            merge_lines(base, "one\n2\nthree\nfour\nfive\n", "one\n2\nthree\nfour\nfive\n"),
+            Some(LineMerge::Clean("one\n2\nthree\nfour\nfive\n".to_string()))
+        );
+        assert_eq!(
+            merge_lines(base, "one\nTWO\nthree\nfour\nfive\n", "one\ntwo\nthree\nfour\nFIVE\n"),
+            Some(LineMerge::Clean("one\nTWO\nthree\nfour\nFIVE\n".to_string()))
+        );
+        assert_eq!(
+            merge_lines(base, "one\nTWO\nthree\nfour\nfive\n", "one\n2\nthree\nfour\nfive\n"),
+            Some(LineMerge::Overlapping(1))
+        );
+        assert_eq!(merge_lines(base, "", "one\n"), Some(LineMerge::Overlapping(1)));
+        assert_eq!(merge_lines("a", "a\nb", "a"), Some(LineMerge::Clean("a\nb".to_string())));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,126 @@
+use crate::errors::Result;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap, fs, path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+/// Seconds covered by one bucket
+pub const BUCKET_SECS: u64 = 3600;
+/// Buckets kept on disk (30 days of hourly counters)
+pub const RETENTION_BUCKETS: usize = 24 * 30;
+/// Activity counters for one hour
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MetricsBucket {
+    pub versions_created: u64,
+    /// Compressed bytes written to version storage
+    pub bytes_stored: u64,
+    pub syncs: u64,
+    /// Bytes copied to mirror targets
+    pub bytes_synced: u64,
+    pub errors: u64,
+}
+/// Hourly activity counters persisted in `metrics.json` under the symor home
+#[derive(Debug, Clone, Default)]
+pub struct MetricsStore {
+    path: PathBuf,
+    buckets: BTreeMap<u64, MetricsBucket>,
+}
+fn bucket_start(at: SystemTime) -> u64 {
+    let secs = at.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    secs - secs % BUCKET_SECS
+}
+impl MetricsStore {
+    pub fn file(home_dir: &Path) -> PathBuf {
+        home_dir.join("metrics.json")
+    }
+    /// Loads the store; a missing or unreadable file starts empty
+    pub fn load(home_dir: &Path) -> Self {
+        let path = Self::file(home_dir);
+        let buckets = fs::read_to_string(&path)
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default();
+        Self { path, buckets }
+    }
+    /// Writes the store, dropping buckets past the retention window
+    pub fn save(&mut self) -> Result<()> {
+        while self.buckets.len() > RETENTION_BUCKETS {
+            self.buckets.pop_first();
+        }
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let tmp = self.path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_string(&self.buckets)?)?;
+        fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+    pub fn record(&mut self, at: SystemTime, update: impl FnOnce(&mut MetricsBucket)) {
+        update(self.buckets.entry(bucket_start(at)).or_default());
+    }
+    /// Counters for the hour containing `at`
+    pub fn bucket(&self, at: SystemTime) -> MetricsBucket {
+        self.buckets.get(&bucket_start(at)).copied().unwrap_or_default()
+    }
+    /// One value per hour for the `hours` hours up to `now`, oldest first
+    pub fn series(
+        &self,
+        now: SystemTime,
+        hours: usize,
+        field: fn(&MetricsBucket) -> u64,
+    ) -> Vec<u64> {
+        let end = bucket_start(now);
+        (0..hours as u64)
+            .rev()
+            .map(|back| end.saturating_sub(back * BUCKET_SECS))
+            .map(|start| self.buckets.get(&start).map(field).unwrap_or(0))
+            .collect()
+    }
+    /// Sums of every counter over the retained history
+    pub fn totals(&self) -> MetricsBucket {
+        self.buckets
+            .values()
+            .fold(
+                MetricsBucket::default(),
+                |acc, b| MetricsBucket {
+                    versions_created: acc.versions_created + b.versions_created,
+                    bytes_stored: acc.bytes_stored + b.bytes_stored,
+                    syncs: acc.syncs + b.syncs,
+                    bytes_synced: acc.bytes_synced + b.bytes_synced,
+                    errors: acc.errors + b.errors,
+                },
+            )
+    }
+}
+/// Adds to the current hour's counters in `home_dir`; failures are logged rather than
+/// returned so that metrics never fail the operation being measured
+pub fn record(home_dir: &Path, update: impl FnOnce(&mut MetricsBucket)) {
+    let mut store = MetricsStore::load(home_dir);
+    store.record(SystemTime::now(), update);
+    if let Err(e) = store.save() {
+        warn!("Could not save metrics to {:?}: {}", store.path, e);
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tempfile::tempdir;
+    #[test]
+    fn test_metrics_series_and_persistence() {
+        let temp_dir = tempdir().unwrap();
+        let now = SystemTime::now();
+        let mut store = MetricsStore::load(temp_dir.path());
+        store.record(now, |b| b.versions_created += 2);
+        store.record(now - Duration::from_secs(2 * BUCKET_SECS), |b| b.errors += 1);
+        store.save().unwrap();
+        record(temp_dir.path(), |b| b.bytes_synced += 10);
+        let store = MetricsStore::load(temp_dir.path());
+        assert_eq!(store.series(now, 3, | b | b.versions_created), vec![0, 0, 2]);
+        assert_eq!(store.series(now, 3, | b | b.errors), vec![1, 0, 0]);
+        let totals = store.totals();
+        assert_eq!((totals.versions_created, totals.errors, totals.bytes_synced), (2, 1, 10));
+    }
+}
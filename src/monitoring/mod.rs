@@ -1,4 +1,9 @@
+pub mod metrics;
 pub mod notifications;
 pub mod progress;
-pub use notifications::{NotificationSystem, ChangeSubscriber, NotificationLevel};
+pub mod status;
+pub mod summary;
+pub use notifications::{
+    NotificationSystem, ChangeSubscriber, CommandSubscriber, NotificationLevel,
+};
 pub use progress::{ProgressTracker, ProgressEvent, OperationStatus};
\ No newline at end of file
@@ -1,6 +1,8 @@
-use anyhow::Result;
+use super::summary::ActivitySummary;
+use crate::errors::{ErrorCode, Result, SymorError};
+use log::warn;
 use std::{
-    path::Path, sync::mpsc::{self, Receiver, Sender},
+    io::Write, path::{Path, PathBuf}, process::Stdio, sync::mpsc::{self, Receiver, Sender},
     time::Duration,
 };
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -55,18 +57,32 @@ impl NotificationSystem {
         }
         Ok(())
     }
-    pub fn notify_error(&self, error: &anyhow::Error) -> Result<()> {
+    pub fn notify_error(&self, error: &SymorError) -> Result<()> {
         for subscriber in &self.subscribers {
             subscriber.on_error(error);
         }
         Ok(())
     }
+    pub fn notify_summary(&self, summary: &ActivitySummary) -> Result<()> {
+        if !self.enabled {
+            return Ok(());
+        }
+        for subscriber in &self.subscribers {
+            subscriber.on_summary(summary);
+        }
+        Ok(())
+    }
     pub fn receive_notification(&self) -> Result<Option<FileChangeNotification>> {
         match self.receiver.try_recv() {
             Ok(notification) => Ok(Some(notification)),
             Err(mpsc::TryRecvError::Empty) => Ok(None),
             Err(mpsc::TryRecvError::Disconnected) => {
-                Err(anyhow::anyhow!("Notification channel disconnected"))
+                Err(
+                    SymorError::new(
+                        ErrorCode::InternalError,
+                        "Notification channel disconnected".to_string(),
+                    ),
+                )
             }
         }
     }
@@ -80,7 +96,9 @@ impl NotificationSystem {
 pub trait ChangeSubscriber: Send + Sync {
     fn on_file_change(&self, notification: &FileChangeNotification);
     fn on_sync_complete(&self, path: &Path, duration: Duration);
-    fn on_error(&self, error: &anyhow::Error);
+    fn on_error(&self, error: &SymorError);
+    /// Periodic activity summary from the daemon
+    fn on_summary(&self, _summary: &ActivitySummary) {}
 }
 pub struct ConsoleSubscriber;
 impl ChangeSubscriber for ConsoleSubscriber {
@@ -98,7 +116,56 @@ impl ChangeSubscriber for ConsoleSubscriber {
     fn on_sync_complete(&self, path: &Path, duration: Duration) {
         println!("Sync completed for {:?} in {:.2}ms", path, duration.as_millis());
     }
-    fn on_error(&self, error: &anyhow::Error) {
+    fn on_error(&self, error: &SymorError) {
         eprintln!("Error: {}", error);
     }
+    fn on_summary(&self, summary: &ActivitySummary) {
+        print!("{}", summary);
+    }
+}
+/// Pipes each summary into a shell command, e.g. `mail` or a chat webhook script, with
+/// its figures in `SYMOR_SUMMARY_*` environment variables; other notifications are
+/// ignored
+pub struct CommandSubscriber {
+    command: String,
+    cwd: PathBuf,
+}
+impl CommandSubscriber {
+    pub fn new(command: impl Into<String>, cwd: impl Into<PathBuf>) -> Self {
+        Self {
+            command: command.into(),
+            cwd: cwd.into(),
+        }
+    }
+}
+impl ChangeSubscriber for CommandSubscriber {
+    fn on_file_change(&self, _notification: &FileChangeNotification) {}
+    fn on_sync_complete(&self, _path: &Path, _duration: Duration) {}
+    fn on_error(&self, _error: &SymorError) {}
+    fn on_summary(&self, summary: &ActivitySummary) {
+        let mut shell = crate::hooks::shell_command(&self.command);
+        shell.envs(summary.env()).current_dir(&self.cwd).stdin(Stdio::piped());
+        let mut child = match shell.spawn() {
+            Ok(child) => child,
+            Err(e) => {
+                warn!("Cannot run summary command {:?}: {}", self.command, e);
+                return;
+            }
+        };
+        let text = summary.to_string();
+        let command = self.command.clone();
+        let stdin = child.stdin.take();
+        std::thread::spawn(move || {
+            if let Some(mut stdin) = stdin {
+                let _ = stdin.write_all(text.as_bytes());
+            }
+            match child.wait() {
+                Ok(status) if !status.success() => {
+                    warn!("Summary command {:?} exited with {}", command, status)
+                }
+                Ok(_) => {}
+                Err(e) => warn!("Summary command {:?} failed: {}", command, e),
+            }
+        });
+    }
 }
\ No newline at end of file
@@ -0,0 +1,99 @@
+use crate::errors::Result;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::{fs, path::{Path, PathBuf}, time::SystemTime};
+/// Live state of one running mirror, written by the mirror for `sym top`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MirrorStatus {
+    pub source: PathBuf,
+    pub targets: usize,
+    /// Process running the mirror
+    pub pid: u32,
+    /// Change events received since the last sync
+    pub queued: usize,
+    pub syncs: u64,
+    pub errors: u64,
+    pub last_sync: Option<SystemTime>,
+    pub last_error: Option<String>,
+    pub last_error_at: Option<SystemTime>,
+}
+/// Directory holding one status file per mirror
+pub fn status_dir(home_dir: &Path) -> PathBuf {
+    home_dir.join("status")
+}
+fn status_file(home_dir: &Path, source: &Path) -> PathBuf {
+    let key = format!("{:x}", md5::compute(source.to_string_lossy().as_bytes()));
+    status_dir(home_dir).join(format!("{}.json", key))
+}
+impl MirrorStatus {
+    pub fn load(home_dir: &Path, source: &Path) -> Option<Self> {
+        let data = fs::read_to_string(status_file(home_dir, source)).ok()?;
+        serde_json::from_str(&data).ok()
+    }
+    /// Every status file, skipping unreadable ones
+    pub fn load_all(home_dir: &Path) -> Vec<Self> {
+        let Ok(entries) = fs::read_dir(status_dir(home_dir)) else {
+            return Vec::new();
+        };
+        entries
+            .flatten()
+            .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "json"))
+            .filter_map(|entry| fs::read_to_string(entry.path()).ok())
+            .filter_map(|data| serde_json::from_str(&data).ok())
+            .collect()
+    }
+    pub fn save(&self, home_dir: &Path) -> Result<()> {
+        let path = status_file(home_dir, &self.source);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_string_pretty(self)?)?;
+        fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+    /// True while the process that wrote this status is still running
+    pub fn is_live(&self) -> bool {
+        self.pid != 0 && crate::daemon::process_alive(self.pid)
+    }
+}
+/// Updates the status of the mirror for `source` as seen by this process; failures
+/// are logged rather than returned so that they never stop the mirror
+pub fn update(home_dir: &Path, source: &Path, update: impl FnOnce(&mut MirrorStatus)) {
+    let mut status = MirrorStatus::load(home_dir, source)
+        .unwrap_or_else(|| MirrorStatus {
+            source: source.to_path_buf(),
+            ..MirrorStatus::default()
+        });
+    status.pid = std::process::id();
+    update(&mut status);
+    if let Err(e) = status.save(home_dir) {
+        warn!("Could not save mirror status for {:?}: {}", source, e);
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+    #[test]
+    fn test_mirror_status_round_trip() {
+        let temp_dir = tempdir().unwrap();
+        let source = Path::new("/data/a.txt");
+        assert!(MirrorStatus::load(temp_dir.path(), source).is_none());
+        update(temp_dir.path(), source, |s| s.queued = 3);
+        update(
+            temp_dir.path(),
+            source,
+            |s| {
+                s.queued = 0;
+                s.syncs += 1;
+            },
+        );
+        update(temp_dir.path(), Path::new("/data/b"), |s| s.errors += 1);
+        let status = MirrorStatus::load(temp_dir.path(), source).unwrap();
+        assert_eq!((status.queued, status.syncs, status.pid), (0, 1, std::process::id()));
+        assert!(status.is_live());
+        assert_eq!(MirrorStatus::load_all(temp_dir.path()).len(), 2);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,320 @@
+use crate::{
+    errors::{Context, ErrorCode, Result, SymorError},
+    events::SyncEvent,
+    hooks::{VersionAction, VersionEvent},
+    tui::format::{format_bytes, format_utc},
+    SymorManager,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::VecDeque, fmt, fs, path::{Path, PathBuf}, str::FromStr,
+    sync::{Arc, Mutex}, time::{Duration, SystemTime},
+};
+/// Most error messages one summary lists
+const SUMMARY_ERRORS: usize = 10;
+/// How often the daemon sends an activity summary
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SummaryPeriod {
+    Daily,
+    Weekly,
+}
+impl SummaryPeriod {
+    pub fn duration(self) -> Duration {
+        match self {
+            SummaryPeriod::Daily => Duration::from_secs(86_400),
+            SummaryPeriod::Weekly => Duration::from_secs(7 * 86_400),
+        }
+    }
+}
+impl FromStr for SummaryPeriod {
+    type Err = SymorError;
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "daily" | "day" => Ok(SummaryPeriod::Daily),
+            "weekly" | "week" => Ok(SummaryPeriod::Weekly),
+            other => {
+                Err(
+                    SymorError::new(
+                            ErrorCode::InvalidConfiguration,
+                            format!("Unknown summary period: {}", other),
+                        )
+                        .with_suggestion("Use 'daily' or 'weekly'".to_string()),
+                )
+            }
+        }
+    }
+}
+impl fmt::Display for SummaryPeriod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(
+            match self {
+                SummaryPeriod::Daily => "daily",
+                SummaryPeriod::Weekly => "weekly",
+            },
+        )
+    }
+}
+/// Activity summaries the daemon sends, in the `notifications` config section
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationsConfig {
+    /// No summaries when unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<SummaryPeriod>,
+    /// Shell commands each summary is piped to, e.g. `mail -s "Symor" ops@example.com`;
+    /// without any the summary only goes to the daemon log
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub summary_commands: Vec<String>,
+}
+impl NotificationsConfig {
+    pub fn is_empty(&self) -> bool {
+        self.summary.is_none() && self.summary_commands.is_empty()
+    }
+}
+/// What the daemon did over one summary period
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActivitySummary {
+    pub period: SummaryPeriod,
+    pub from: SystemTime,
+    pub to: SystemTime,
+    pub host: String,
+    pub versions_created: usize,
+    /// Size of the versions created
+    pub bytes_stored: u64,
+    pub versions_pruned: usize,
+    pub syncs: u64,
+    pub failed_syncs: u64,
+    /// Watched items, other than paused ones, without a backup during the period
+    pub stale_items: Vec<PathBuf>,
+    pub error_count: usize,
+    /// The latest errors, oldest first
+    pub errors: Vec<String>,
+}
+impl ActivitySummary {
+    /// One line fit for a mail subject
+    pub fn title(&self) -> String {
+        let problems = self.error_count + self.stale_items.len();
+        let verdict = if problems == 0 { "all good".to_string() } else {
+            format!("{} problem{}", problems, if problems == 1 { "" } else { "s" })
+        };
+        format!("Symor {} summary for {}: {}", self.period, self.host, verdict)
+    }
+    /// Environment summary commands run with
+    pub fn env(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("SYMOR_EVENT", "summary".to_string()), ("SYMOR_SUMMARY_TITLE", self.title()),
+            ("SYMOR_SUMMARY_PERIOD", self.period.to_string()),
+            ("SYMOR_SUMMARY_VERSIONS", self.versions_created.to_string()),
+            ("SYMOR_SUMMARY_BYTES", self.bytes_stored.to_string()),
+            ("SYMOR_SUMMARY_SYNCS", self.syncs.to_string()),
+            ("SYMOR_SUMMARY_FAILED_SYNCS", self.failed_syncs.to_string()),
+            ("SYMOR_SUMMARY_STALE", self.stale_items.len().to_string()),
+            ("SYMOR_SUMMARY_ERRORS", self.error_count.to_string()),
+        ]
+    }
+}
+impl fmt::Display for ActivitySummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.title())?;
+        writeln!(f, "{} to {}", format_utc(self.from), format_utc(self.to))?;
+        writeln!(f)?;
+        let bytes = format_bytes(self.bytes_stored);
+        writeln!(f, "Versions created: {} ({})", self.versions_created, bytes)?;
+        writeln!(f, "Versions pruned:  {}", self.versions_pruned)?;
+        writeln!(f, "Syncs:            {} ({} failed)", self.syncs, self.failed_syncs)?;
+        writeln!(f, "Without a backup: {}", self.stale_items.len())?;
+        for path in &self.stale_items {
+            writeln!(f, "  {}", path.display())?;
+        }
+        writeln!(f, "Errors:           {}", self.error_count)?;
+        if self.error_count > self.errors.len() {
+            writeln!(f, "  (latest {})", self.errors.len())?;
+        }
+        for error in &self.errors {
+            writeln!(f, "  {}", error)?;
+        }
+        Ok(())
+    }
+}
+#[derive(Debug, Default)]
+struct Counts {
+    versions_created: usize,
+    bytes_stored: u64,
+    versions_pruned: usize,
+    syncs: u64,
+    failed_syncs: u64,
+    error_count: usize,
+    errors: VecDeque<String>,
+}
+/// Activity since the last summary, fed by the daemon from sync events, version events
+/// and its own failures; clones add to the same counts
+#[derive(Debug, Clone, Default)]
+pub struct ActivityTally {
+    counts: Arc<Mutex<Counts>>,
+}
+impl ActivityTally {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    fn counts(&self) -> std::sync::MutexGuard<'_, Counts> {
+        self.counts.lock().unwrap_or_else(|e| e.into_inner())
+    }
+    pub fn record_sync(&self, event: &SyncEvent) {
+        match event {
+            SyncEvent::SyncCompleted { .. } => self.counts().syncs += 1,
+            SyncEvent::SyncFailed { source, error } => {
+                self.counts().failed_syncs += 1;
+                self.record_error(format!("Sync of {} failed: {}", source.display(), error));
+            }
+            _ => {}
+        }
+    }
+    pub fn record_version(&self, event: &VersionEvent) {
+        let mut counts = self.counts();
+        match event.action {
+            VersionAction::Created => {
+                counts.versions_created += 1;
+                counts.bytes_stored += event.size;
+            }
+            VersionAction::Pruned => counts.versions_pruned += 1,
+            VersionAction::Restored => {}
+        }
+    }
+    pub fn record_error(&self, message: impl Into<String>) {
+        let mut counts = self.counts();
+        counts.error_count += 1;
+        counts.errors.push_back(message.into());
+        if counts.errors.len() > SUMMARY_ERRORS {
+            counts.errors.pop_front();
+        }
+    }
+    fn take(&self) -> Counts {
+        std::mem::take(&mut *self.counts())
+    }
+}
+/// When the daemon last sent a summary, kept across restarts
+fn summary_file(home_dir: &Path) -> PathBuf {
+    home_dir.join("summary.json")
+}
+#[derive(Debug, Serialize, Deserialize)]
+struct SummaryState {
+    last_sent: SystemTime,
+}
+/// When the last summary went out, if one ever did
+pub fn last_summary(home_dir: &Path) -> Option<SystemTime> {
+    let data = fs::read_to_string(summary_file(home_dir)).ok()?;
+    serde_json::from_str::<SummaryState>(&data).ok().map(|state| state.last_sent)
+}
+pub fn record_summary(home_dir: &Path, sent: SystemTime) -> Result<()> {
+    let path = summary_file(home_dir);
+    fs::write(&path, serde_json::to_string_pretty(&SummaryState { last_sent: sent })?)
+        .with_context(|| format!("cannot write {:?}", path))
+}
+impl SymorManager {
+    /// Summary of the activity `tally` counted since `from`, which it resets, and of the
+    /// watched items that went without a backup since then
+    pub fn activity_summary(
+        &self,
+        period: SummaryPeriod,
+        from: SystemTime,
+        tally: &ActivityTally,
+    ) -> Result<ActivitySummary> {
+        let to = SystemTime::now();
+        let window = to.duration_since(from).unwrap_or_default().max(Duration::from_secs(1));
+        let report = self.backup_report(window)?;
+        let counts = tally.take();
+        Ok(ActivitySummary {
+            period,
+            from,
+            to,
+            host: report.host.clone(),
+            versions_created: counts.versions_created,
+            bytes_stored: counts.bytes_stored,
+            versions_pruned: counts.versions_pruned,
+            syncs: counts.syncs,
+            failed_syncs: counts.failed_syncs,
+            stale_items: report.needing_attention().map(|item| item.path.clone()).collect(),
+            error_count: counts.error_count,
+            errors: counts.errors.into(),
+        })
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::monitoring::{CommandSubscriber, NotificationSystem};
+    use tempfile::tempdir;
+    #[test]
+    fn test_activity_summary() {
+        let temp_dir = tempdir().unwrap();
+        let mut manager = SymorManager::new().unwrap();
+        manager.config.home_dir = temp_dir.path().join("home");
+        fs::create_dir_all(&manager.config.home_dir).unwrap();
+        manager.version_storage = crate::versioning::storage::VersionStorage::with_config(
+            crate::versioning::storage::StorageConfig {
+                storage_path: temp_dir.path().join("home/versions"),
+                ..Default::default()
+            },
+        );
+        let tally = ActivityTally::new();
+        let recorder = tally.clone();
+        manager.on_version_event(move |event| recorder.record_version(event));
+        let (notes, idle) = (temp_dir.path().join("notes.txt"), temp_dir.path().join("idle"));
+        fs::write(&notes, "first").unwrap();
+        fs::write(&idle, "idle").unwrap();
+        let id = manager.watch_only(notes.clone(), false, Vec::new(), false).unwrap();
+        let idle_id = manager.watch_only(idle.clone(), false, Vec::new(), false).unwrap();
+        let long_ago = SystemTime::now() - Duration::from_secs(3 * 86_400);
+        manager.watched_item_mut(&idle_id).unwrap().versions[0].timestamp = long_ago;
+        fs::write(&notes, "second!").unwrap();
+        manager.create_backup(&id).unwrap();
+        let source = temp_dir.path().join("src");
+        tally.record_sync(&SyncEvent::SyncStarted { source: source.clone() });
+        tally.record_sync(
+            &SyncEvent::SyncCompleted {
+                source: source.clone(),
+                files: 1,
+                bytes: 3,
+                duration: Duration::ZERO,
+            },
+        );
+        tally.record_sync(&SyncEvent::SyncFailed { source, error: "disk full".to_string() });
+        for n in 0..SUMMARY_ERRORS {
+            tally.record_error(format!("Scan failed: {}", n));
+        }
+        let from = SystemTime::now() - SummaryPeriod::Daily.duration();
+        let summary = manager.activity_summary(SummaryPeriod::Daily, from, &tally).unwrap();
+        assert_eq!((summary.versions_created, summary.bytes_stored), (3, 16));
+        assert_eq!((summary.syncs, summary.failed_syncs), (1, 1));
+        assert_eq!(summary.stale_items, [idle]);
+        assert_eq!((summary.error_count, summary.errors.len()), (SUMMARY_ERRORS + 1, 10));
+        assert_eq!(summary.errors[0], "Scan failed: 0");
+        assert!(summary.title().ends_with(": 12 problems"), "{}", summary.title());
+        let text = summary.to_string();
+        assert!(text.contains("Syncs:            1 (1 failed)"), "{}", text);
+        assert!(text.contains("(latest 10)"), "{}", text);
+        if cfg!(unix) {
+            let out = temp_dir.path().join("mail.txt");
+            let command = format!("cat > {0:?}; echo \"$SYMOR_SUMMARY_VERSIONS\" >> {0:?}", out);
+            let mut notifications = NotificationSystem::new();
+            notifications.subscribe(Box::new(CommandSubscriber::new(command, "/")));
+            notifications.notify_summary(&summary).unwrap();
+            for _ in 0..50 {
+                if fs::read_to_string(&out).is_ok_and(|mail| mail.ends_with("\n3\n")) {
+                    break;
+                }
+                std::thread::sleep(Duration::from_millis(100));
+            }
+            assert_eq!(fs::read_to_string(&out).unwrap(), format!("{}3\n", text));
+        }
+        let again = manager.activity_summary(SummaryPeriod::Daily, from, &tally).unwrap();
+        assert_eq!((again.versions_created, again.error_count), (0, 0));
+        let home_dir = &manager.config.home_dir;
+        assert!(last_summary(home_dir).is_none());
+        record_summary(home_dir, summary.to).unwrap();
+        assert_eq!(last_summary(home_dir), Some(summary.to));
+        assert_eq!("Week".parse::<SummaryPeriod>().unwrap(), SummaryPeriod::Weekly);
+        assert!("hourly".parse::<SummaryPeriod>().is_err());
+    }
+}
\ No newline at end of file
@@ -0,0 +1,93 @@
+use std::{
+    borrow::Cow, collections::HashMap, ffi::{OsStr, OsString}, fs, path::{Path, PathBuf},
+};
+use unicode_normalization::{is_nfc_quick, IsNormalized, UnicodeNormalization};
+/// `path` with its names in Unicode NFC, the form Linux and Windows programs usually
+/// write, so a name macOS stored decomposed (NFD) compares equal to it. Paths that are
+/// already NFC, or not UTF-8, come back as they are.
+pub fn key(path: &Path) -> Cow<'_, Path> {
+    match path.to_str() {
+        Some(text) if is_nfc_quick(text.chars()) != IsNormalized::Yes => {
+            Cow::Owned(PathBuf::from(text.nfc().collect::<String>()))
+        }
+        _ => Cow::Borrowed(path),
+    }
+}
+/// Whether `name` could be spelled in another normal form; ASCII names cannot
+pub fn may_differ(name: &OsStr) -> bool {
+    name.to_str().is_none_or(|text| !text.is_ascii())
+}
+/// Names of the entries of `dir` that `may_differ`, by their `key`
+pub fn names_by_key(dir: &Path) -> HashMap<PathBuf, OsString> {
+    let Ok(entries) = fs::read_dir(crate::long_path::extend(dir)) else {
+        return HashMap::new();
+    };
+    entries
+        .flatten()
+        .map(|entry| entry.file_name())
+        .filter(|name| may_differ(name))
+        .map(|name| (key(Path::new(&name)).into_owned(), name))
+        .collect()
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_unicode_keys() {
+        let composed = "caf\u{e9}/r\u{e9}sum\u{e9}.txt";
+        let decomposed = "cafe\u{301}/re\u{301}sume\u{301}.txt";
+        assert_eq!(key(Path::new(decomposed)), Path::new(composed));
+        assert!(matches!(key(Path::new(composed)), Cow::Borrowed(_)));
+        assert!(!may_differ(OsStr::new("plain.txt")));
+        assert!(may_differ(OsStr::new(decomposed)));
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs::write(temp_dir.path().join("Cafe\u{301}.txt"), "").unwrap();
+        fs::write(temp_dir.path().join("plain.txt"), "").unwrap();
+        let names = names_by_key(temp_dir.path());
+        assert_eq!(names.len(), 1);
+        assert_eq!(names[Path::new("Caf\u{e9}.txt")], "Cafe\u{301}.txt");
+        let (nfc, nfd) = ("caf\u{e9}.txt", "cafe\u{301}.txt");
+        let source = temp_dir.path().join("src");
+        fs::create_dir_all(&source).unwrap();
+        fs::write(source.join(nfc), "new").unwrap();
+        let mut listed = Vec::new();
+        for normalize in [true, false] {
+            let target = temp_dir.path().join(format!("dst-{}", normalize));
+            fs::create_dir_all(&target).unwrap();
+            fs::write(target.join(nfd), "new").unwrap();
+            let spec = crate::MirrorSpec {
+                source: source.clone(),
+                targets: vec![target.clone()],
+                bidirectional: false,
+                only: Vec::new(),
+                skip_hidden: false,
+                paused: false,
+                storm_threshold: None,
+                rollover: false,
+                keep_rotations: None,
+                link_rotations: false,
+                symlinks: Default::default(),
+            };
+            assert!(crate::conflicts::mirror_conflicts(&spec, None).is_empty());
+            fs::write(target.join(nfd), "old").unwrap();
+            let mirror = crate::Mirror::builder(&source)
+                .target(&target)
+                .normalize_unicode(normalize)
+                .build()
+                .unwrap();
+            mirror.sync_once().unwrap();
+            let names: Vec<_> = fs::read_dir(&target)
+                .unwrap()
+                .map(|entry| entry.unwrap().file_name())
+                .collect();
+            listed.push(names);
+        }
+        assert_eq!(listed, [[nfd], [nfc]]);
+        let target = temp_dir.path().join("dst-true");
+        assert_eq!(fs::read_to_string(target.join(nfd)).unwrap(), "new");
+        let mut detector = crate::versioning::detector::ChangeDetector::new();
+        let hash = detector.calculate_file_hash(&source.join(nfc)).unwrap();
+        detector.set_hashes(HashMap::from([(source.join(nfd), hash)]));
+        assert!(detector.scan_file(&source.join(nfc)).unwrap().is_none());
+    }
+}
\ No newline at end of file
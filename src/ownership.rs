@@ -0,0 +1,158 @@
+use crate::errors::{ErrorCode, Result, SymorError};
+use std::{io, path::Path};
+/// Which IDs of an owner a rule maps
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Side {
+    User,
+    Group,
+}
+/// Rules that change the owners restores and mirrors give files when ownership is
+/// preserved, for copies made as another user or inside a container. Each rule reads
+/// `FROM->TO`, like `1000->33` or `root->current`, and maps user and group IDs alike;
+/// `uid:` or `gid:` in front maps only one of them. Sides are IDs, user or group names,
+/// or `current` for the running process, and `*` as FROM matches any ID. The first rule
+/// matching an ID wins; IDs no rule matches stay as they are.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OwnerMap {
+    /// User ID rules as (FROM, TO), FROM `None` for any
+    users: Vec<(Option<u32>, u32)>,
+    groups: Vec<(Option<u32>, u32)>,
+}
+impl OwnerMap {
+    /// Parses `rules`, looking up the names they use
+    pub fn parse<S: AsRef<str>>(rules: &[S]) -> Result<Self> {
+        let mut map = OwnerMap::default();
+        for rule in rules {
+            map.add_rule(rule.as_ref().trim())?;
+        }
+        Ok(map)
+    }
+    fn add_rule(&mut self, rule: &str) -> Result<()> {
+        let (sides, body) = match rule.split_once(':') {
+            Some(("uid", body)) => (&[Side::User][..], body),
+            Some(("gid", body)) => (&[Side::Group][..], body),
+            _ => (&[Side::User, Side::Group][..], rule),
+        };
+        let invalid = |message: String| {
+            SymorError::new(ErrorCode::InvalidConfiguration, message)
+                .with_suggestion(
+                    "Write rules like '1000->33', 'root->current' or 'uid:*->www-data'"
+                        .to_string(),
+                )
+        };
+        let Some((from, to)) = body.split_once("->") else {
+            return Err(invalid(format!("Invalid owner rule: {}", rule)));
+        };
+        let (from, to) = (from.trim(), to.trim());
+        let mut mapped = false;
+        for &side in sides {
+            let from = if from == "*" { Some(None) } else { resolve(from, side).map(Some) };
+            if let (Some(from), Some(to)) = (from, resolve(to, side)) {
+                match side {
+                    Side::User => self.users.push((from, to)),
+                    Side::Group => self.groups.push((from, to)),
+                }
+                mapped = true;
+            }
+        }
+        if mapped {
+            Ok(())
+        } else {
+            Err(invalid(format!("Unknown user or group in owner rule: {}", rule)))
+        }
+    }
+    /// Owner `(uid, gid)` becomes under these rules
+    pub fn map(&self, (uid, gid): (u32, u32)) -> (u32, u32) {
+        let apply = |rules: &[(Option<u32>, u32)], id: u32| {
+            rules
+                .iter()
+                .find(|(from, _)| from.is_none_or(|from| from == id))
+                .map_or(id, |&(_, to)| to)
+        };
+        (apply(&self.users, uid), apply(&self.groups, gid))
+    }
+}
+/// `text` as an ID on `side`: a number, `current` or a name
+fn resolve(text: &str, side: Side) -> Option<u32> {
+    match text.parse() {
+        Ok(id) => Some(id),
+        Err(_) if text == "current" => current_id(side),
+        Err(_) => lookup(text, side),
+    }
+}
+#[cfg(unix)]
+fn current_id(side: Side) -> Option<u32> {
+    Some(
+        match side {
+            Side::User => unsafe { libc::getuid() },
+            Side::Group => unsafe { libc::getgid() },
+        },
+    )
+}
+#[cfg(unix)]
+fn lookup(name: &str, side: Side) -> Option<u32> {
+    let name = std::ffi::CString::new(name).ok()?;
+    // Both return null or an entry in static storage, read before any other lookup
+    match side {
+        Side::User => unsafe { libc::getpwnam(name.as_ptr()).as_ref() }.map(|pw| pw.pw_uid),
+        Side::Group => unsafe { libc::getgrnam(name.as_ptr()).as_ref() }.map(|gr| gr.gr_gid),
+    }
+}
+#[cfg(not(unix))]
+fn current_id(_side: Side) -> Option<u32> {
+    None
+}
+#[cfg(not(unix))]
+fn lookup(_name: &str, _side: Side) -> Option<u32> {
+    None
+}
+/// Gives `path`, or the link itself when it is a symlink, the owner `(uid, gid)` unless
+/// it already has it
+#[cfg(unix)]
+pub fn set_owner(path: &Path, (uid, gid): (u32, u32)) -> io::Result<()> {
+    use std::os::unix::fs::MetadataExt;
+    let metadata = std::fs::symlink_metadata(path)?;
+    if metadata.uid() == uid && metadata.gid() == gid {
+        return Ok(());
+    }
+    std::os::unix::fs::lchown(path, Some(uid), Some(gid))
+}
+#[cfg(not(unix))]
+pub fn set_owner(_path: &Path, _owner: (u32, u32)) -> io::Result<()> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::fs;
+    #[test]
+    fn test_owner_map() {
+        let current = unsafe { (libc::getuid(), libc::getgid()) };
+        let map = OwnerMap::parse(&["1000->33", "uid:root->current", "gid:*->0"]).unwrap();
+        assert_eq!(map.map((1000, 1000)), (33, 33));
+        assert_eq!(map.map((0, 5)), (current.0, 0));
+        assert_eq!(map.map((7, 7)), (7, 0));
+        assert_eq!(OwnerMap::default().map((7, 8)), (7, 8));
+        let map = OwnerMap::parse(&[" root -> 1 "]).unwrap();
+        assert_eq!(map.map((0, 0)), (1, 1));
+        for rule in ["1000", "1000->*", "no-such-user-here->0", "1000->-1"] {
+            assert!(OwnerMap::parse(&[rule]).is_err(), "{} parsed", rule);
+        }
+        let temp_dir = tempfile::tempdir().unwrap();
+        let source = temp_dir.path().join("src");
+        fs::create_dir_all(source.join("sub")).unwrap();
+        fs::write(source.join("sub/file.txt"), "content").unwrap();
+        let target = temp_dir.path().join("dst");
+        let owner = (if current.0 == 0 { 33 } else { current.0 }, current.1);
+        let rule = format!("uid:*->{}", owner.0);
+        let mirror = crate::Mirror::builder(&source)
+            .target(&target)
+            .owners(Some(OwnerMap::parse(&[rule]).unwrap()))
+            .build()
+            .unwrap();
+        mirror.sync_once().unwrap();
+        for path in [target.join("sub"), target.join("sub/file.txt")] {
+            assert_eq!(crate::versioning::storage::file_owner(&path), Some(owner));
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,105 @@
+use crate::errors::{ErrorCode, Result, SymorError};
+use std::path::{Component, Path};
+/// Whether any component of `relative` is a dot-file or dot-directory
+pub fn is_hidden(relative: &Path) -> bool {
+    relative
+        .components()
+        .any(|c| {
+            matches!(c, Component::Normal(name) if name.to_string_lossy().starts_with('.'))
+        })
+}
+/// Which files inside a watched or mirrored directory take part, from `--only` patterns
+/// and the `skip_hidden` option
+#[derive(Debug, Clone, Default)]
+pub struct PathFilter {
+    only: Vec<glob::Pattern>,
+    skip_hidden: bool,
+}
+impl PathFilter {
+    /// Compiles `only` globs; an empty list lets every file through
+    pub fn new(only: &[String]) -> Result<Self> {
+        let only = only
+            .iter()
+            .map(|pattern| {
+                glob::Pattern::new(pattern)
+                    .map_err(|e| {
+                        SymorError::new(
+                                ErrorCode::InvalidConfiguration,
+                                format!("Invalid --only pattern {:?}: {}", pattern, e),
+                            )
+                            .with_suggestion(
+                                "Use globs or extensions such as '*.conf,*.toml' or 'conf,toml'"
+                                    .to_string(),
+                            )
+                    })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { only, skip_hidden: false })
+    }
+    /// Also leave out hidden files and everything under hidden directories
+    pub fn skip_hidden(mut self, skip_hidden: bool) -> Self {
+        self.skip_hidden = skip_hidden;
+        self
+    }
+    /// Splits a comma-separated `--only` list; bare extensions (`toml`, `.toml`) become
+    /// `*.toml`
+    pub fn split_list(list: &str) -> Vec<String> {
+        list.split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                if entry.contains(['*', '?', '[', '/']) {
+                    entry.to_string()
+                } else {
+                    format!("*.{}", entry.trim_start_matches('.'))
+                }
+            })
+            .collect()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.only.is_empty() && !self.skip_hidden
+    }
+    /// Whether the directory at `relative` should be walked at all
+    pub fn walks(&self, relative: &Path) -> bool {
+        !(self.skip_hidden && is_hidden(relative))
+    }
+    /// Whether the file at `relative` (to the watched root) passes, matching patterns
+    /// against the relative path and the file name
+    pub fn includes(&self, relative: &Path) -> bool {
+        if !self.walks(relative) {
+            return false;
+        }
+        if self.only.is_empty() {
+            return true;
+        }
+        let name = relative.file_name().map(|n| n.to_string_lossy().into_owned());
+        self.only
+            .iter()
+            .any(|pattern| {
+                pattern.matches_path(relative)
+                    || name.as_deref().is_some_and(|n| pattern.matches(n))
+            })
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_only_patterns() {
+        let only = PathFilter::split_list("*.conf, toml,.ini,,etc/**");
+        assert_eq!(only, ["*.conf", "*.toml", "*.ini", "etc/**"]);
+        let filter = PathFilter::new(&only).unwrap();
+        assert!(filter.includes(Path::new("app.conf")));
+        assert!(filter.includes(Path::new("nested/deep/Cargo.toml")));
+        assert!(filter.includes(Path::new("etc/hosts")));
+        assert!(! filter.includes(Path::new("notes.txt")));
+        assert!(PathFilter::default().includes(Path::new("anything.bin")));
+        assert!(PathFilter::new(& ["[".to_string()]).is_err());
+        let hidden = PathFilter::default().skip_hidden(true);
+        assert!(! hidden.includes(Path::new(".env")));
+        assert!(! hidden.includes(Path::new(".venv/lib/site.py")));
+        assert!(! hidden.walks(Path::new("src/.cache")));
+        assert!(hidden.includes(Path::new("src/main.rs")));
+        assert!(filter.skip_hidden(true).includes(Path::new("app.conf")));
+    }
+}
\ No newline at end of file
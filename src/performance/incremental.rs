@@ -1,16 +1,96 @@
-use anyhow::Result;
-use std::{collections::HashMap, fs, path::{Path, PathBuf}};
+use crate::errors::{ErrorCode, Result, SymorError};
+use rayon::prelude::*;
+use std::{
+    collections::HashMap, fs, io::{BufWriter, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+/// Fewest blocks worth spreading over the rayon pool; smaller inputs are hashed on the
+/// calling thread, where handing out the work would cost more than it saves (see
+/// `benches/incremental.rs`)
+const PARALLEL_MIN_BLOCKS: usize = 64;
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct BlockHash {
     pub offset: u64,
     pub size: u64,
+    /// Rolling checksum, cheap to slide over the new file one byte at a time
+    pub weak: u32,
+    /// MD5 of the block, checked when the weak checksum matches
     pub hash: String,
 }
-#[derive(Debug, Clone)]
+/// One run of the new file: bytes copied from the base, or a literal range of the new
+/// file itself, so a delta stays small however much of a large file changed
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct DeltaBlock {
+    /// Where the run starts in the new file
     pub offset: u64,
     pub size: u64,
-    pub data: Option<Vec<u8>>,
+    /// Whether the run's bytes are not in the base and come from the new file; otherwise
+    /// they are `size` bytes of the base at `base_offset`
+    pub literal: bool,
+    pub base_offset: u64,
+}
+impl DeltaBlock {
+    /// The bytes of a literal run, out of `new`, the content the delta rebuilds
+    pub fn literal_data<'a>(&self, new: &'a [u8]) -> Option<&'a [u8]> {
+        let start = usize::try_from(self.offset).ok()?;
+        self.literal.then(|| new.get(start..start + self.size as usize)).flatten()
+    }
+}
+/// Adler-32 style checksum of a window, updated in constant time as the window
+/// slides, as rsync uses it
+struct Rolling {
+    a: u32,
+    b: u32,
+    len: u32,
+}
+impl Rolling {
+    fn new(window: &[u8]) -> Self {
+        let len = window.len() as u32;
+        let (mut a, mut b) = (0u32, 0u32);
+        for (i, &byte) in window.iter().enumerate() {
+            a = a.wrapping_add(byte as u32);
+            b = b.wrapping_add((len - i as u32).wrapping_mul(byte as u32));
+        }
+        Self { a, b, len }
+    }
+    /// Moves the window one byte on, dropping `out` and taking in `next`
+    fn roll(&mut self, out: u8, next: u8) {
+        self.a = self.a.wrapping_sub(out as u32).wrapping_add(next as u32);
+        self.b = self.b.wrapping_sub(self.len.wrapping_mul(out as u32)).wrapping_add(self.a);
+    }
+    fn digest(&self) -> u32 {
+        (self.a & 0xffff) | (self.b & 0xffff) << 16
+    }
+}
+/// Literal run of the new file
+fn literal(range: std::ops::Range<usize>) -> DeltaBlock {
+    DeltaBlock {
+        offset: range.start as u64,
+        size: range.len() as u64,
+        literal: true,
+        base_offset: 0,
+    }
+}
+/// Read-only view of a file, paged in by the OS as it is read rather than loaded up
+/// front, so deltas work on files larger than memory
+pub struct MappedFile(Option<memmap2::Mmap>);
+impl MappedFile {
+    pub fn open(path: &Path) -> std::io::Result<Self> {
+        let file = fs::File::open(crate::long_path::extend(path))?;
+        if file.metadata()?.len() == 0 {
+            return Ok(Self(None));
+        }
+        // SAFETY: the map is only read. As with any map, another process truncating the
+        // file meanwhile faults the read; the views live for one delta at most.
+        let map = unsafe { memmap2::Mmap::map(&file)? };
+        Ok(Self(Some(map)))
+    }
+}
+impl std::ops::Deref for MappedFile {
+    type Target = [u8];
+    fn deref(&self) -> &[u8] {
+        self.0.as_deref().unwrap_or_default()
+    }
 }
 pub struct IncrementalSync {
     block_size: usize,
@@ -28,87 +108,176 @@ impl IncrementalSync {
         old_path: &Path,
         new_path: &Path,
     ) -> Result<Vec<DeltaBlock>> {
-        let old_content = fs::read(old_path)?;
-        let new_content = fs::read(new_path)?;
-        let old_blocks = self.calculate_blocks(&old_content);
-        let new_blocks = self.calculate_blocks(&new_content);
+        let old_content = MappedFile::open(old_path)?;
+        let new_content = MappedFile::open(new_path)?;
+        Ok(self.delta_against(&self.calculate_blocks(&old_content), &new_content))
+    }
+    /// Runs that rebuild `content` from the base whose blocks are `signature`
+    pub fn delta_against(&self, signature: &[BlockHash], content: &[u8]) -> Vec<DeltaBlock> {
         let mut deltas = Vec::new();
-        let max_len = old_blocks.len().max(new_blocks.len());
-        for i in 0..max_len {
-            let old_block = old_blocks.get(i);
-            let new_block = new_blocks.get(i);
-            match (old_block, new_block) {
-                (Some(old), Some(new)) if old.hash == new.hash => {
-                    deltas
-                        .push(DeltaBlock {
-                            offset: (i * self.block_size) as u64,
-                            size: old.size,
-                            data: None,
-                        });
-                }
-                (_, Some(new)) => {
-                    let data_start = (i * self.block_size) as usize;
-                    let data_end = (data_start + new.size as usize)
-                        .min(new_content.len());
-                    let data = new_content[data_start..data_end].to_vec();
-                    deltas
-                        .push(DeltaBlock {
-                            offset: (i * self.block_size) as u64,
-                            size: new.size,
-                            data: Some(data),
-                        });
-                }
-                (Some(old), None) => {
-                    deltas
-                        .push(DeltaBlock {
-                            offset: (i * self.block_size) as u64,
-                            size: old.size,
-                            data: Some(Vec::new()),
-                        });
+        let mut at = 0;
+        for copy in self.matches(signature, content) {
+            if at < copy.offset as usize {
+                deltas.push(literal(at..copy.offset as usize));
+            }
+            at = (copy.offset + copy.size) as usize;
+            deltas.push(copy);
+        }
+        if at < content.len() {
+            deltas.push(literal(at..content.len()));
+        }
+        deltas
+    }
+    /// The runs of `content` found in the base, in order; everything between them is
+    /// new. The weak checksum of every window of `content` is looked up among the
+    /// base's full-size blocks, so content that moved because bytes were inserted or
+    /// removed before it is still found. Blocks still at their old offset are preferred
+    /// over matches elsewhere, so an edit amid repeated content (zeroed regions) leaves
+    /// the rest of the file unchanged rather than moved.
+    fn matches(&self, signature: &[BlockHash], content: &[u8]) -> Vec<DeltaBlock> {
+        let block_size = self.block_size.max(1);
+        let mut index: HashMap<u32, Vec<&BlockHash>> = HashMap::new();
+        for block in signature.iter().filter(|block| block.size == block_size as u64) {
+            index.entry(block.weak).or_default().push(block);
+        }
+        let in_place = |at: usize| {
+            signature
+                .get(at / block_size)
+                .filter(|block| block.offset == at as u64 && block.size == block_size as u64)
+                .is_some_and(|block| {
+                    block.hash == format!("{:x}", md5::compute(&content[at..at + block_size]))
+                })
+        };
+        let mut copies: Vec<DeltaBlock> = Vec::new();
+        let mut at = 0;
+        let mut rolling = (content.len() >= block_size)
+            .then(|| Rolling::new(&content[..block_size]));
+        while let Some(window) = rolling.as_mut() {
+            let found = index
+                .get(&window.digest())
+                .and_then(|candidates| {
+                    let hash = format!("{:x}", md5::compute(&content[at..at + block_size]));
+                    let mut matching = candidates.iter().filter(|block| block.hash == hash);
+                    let first = matching.next()?;
+                    let in_place = std::iter::once(first)
+                        .chain(matching)
+                        .find(|block| block.offset == at as u64);
+                    Some(in_place.unwrap_or(first))
+                });
+            let aligned = at.next_multiple_of(block_size);
+            let moved = found.is_some_and(|block| block.offset != at as u64);
+            if moved && aligned + block_size <= content.len() && in_place(aligned) {
+                at = aligned;
+                rolling = Some(Rolling::new(&content[at..at + block_size]));
+            } else if let Some(block) = found {
+                let extends = |last: &&mut DeltaBlock| {
+                    last.offset + last.size == at as u64
+                        && last.base_offset + last.size == block.offset
+                };
+                match copies.last_mut().filter(extends) {
+                    Some(last) => last.size += block.size,
+                    _ => {
+                        copies
+                            .push(DeltaBlock {
+                                offset: at as u64,
+                                size: block.size,
+                                literal: false,
+                                base_offset: block.offset,
+                            })
+                    }
                 }
-                (None, None) => unreachable!(),
+                at += block_size;
+                rolling = (at + block_size <= content.len())
+                    .then(|| Rolling::new(&content[at..at + block_size]));
+            } else if at + block_size < content.len() {
+                window.roll(content[at], content[at + block_size]);
+                at += 1;
+            } else {
+                rolling = None;
             }
         }
-        Ok(deltas)
+        copies
     }
+    /// Writes the file `deltas` describe, copying runs from `base_path` and literal runs
+    /// from `new_path`, the file the delta was calculated for
     pub fn apply_delta(
         &self,
         base_path: &Path,
+        new_path: &Path,
         deltas: &[DeltaBlock],
         output_path: &Path,
     ) -> Result<()> {
-        let base_content = fs::read(base_path)?;
-        let mut result = Vec::new();
-        let mut current_offset = 0;
+        let base_content = MappedFile::open(base_path)?;
+        let new_content = MappedFile::open(new_path)?;
+        let mut output = BufWriter::new(fs::File::create(output_path)?);
         for delta in deltas {
-            if current_offset < delta.offset as usize {
-                let gap_size = delta.offset as usize - current_offset;
-                if current_offset + gap_size <= base_content.len() {
-                    result
-                        .extend_from_slice(
-                            &base_content[current_offset..current_offset + gap_size],
-                        );
-                }
-                current_offset = delta.offset as usize;
-            }
-            if let Some(data) = &delta.data {
-                result.extend(data);
+            let (source, path, start) = if delta.literal {
+                (&new_content, new_path, delta.offset as usize)
             } else {
-                let copy_size = delta.size as usize;
-                if current_offset + copy_size <= base_content.len() {
-                    result
-                        .extend_from_slice(
-                            &base_content[current_offset..current_offset + copy_size],
-                        );
-                }
+                (&base_content, base_path, delta.base_offset as usize)
+            };
+            let Some(run) = source.get(start..start + delta.size as usize) else {
+                return Err(
+                    SymorError::new(
+                        ErrorCode::VersionCorrupted,
+                        format!("Delta copies bytes past the end of {:?}", path),
+                    ),
+                );
+            };
+            output.write_all(run)?;
+        }
+        output.flush()?;
+        Ok(())
+    }
+    /// Turns the file at `path` into `content`. Only runs that are new or moved are
+    /// written, into a copy of `path` that then replaces it, so readers never see a
+    /// half-patched file. Both files are read through memory maps, so neither has to
+    /// fit in memory. Returns the bytes written.
+    pub fn patch_file(&self, path: &Path, content: &[u8]) -> Result<u64> {
+        let path = &*crate::long_path::extend(path);
+        let tmp = path.with_extension("tmp-sync");
+        let patched = {
+            let base = MappedFile::open(path)?;
+            if base[..] == *content {
+                return Ok(0);
+            }
+            fs::copy(path, &tmp)?;
+            self.write_runs(&tmp, &base, content)
+        };
+        match patched {
+            Ok(written) => {
+                fs::rename(&tmp, path)?;
+                Ok(written)
+            }
+            Err(e) => {
+                let _ = fs::remove_file(&tmp);
+                Err(e)
             }
-            current_offset = (delta.offset + delta.size) as usize;
         }
-        if current_offset < base_content.len() {
-            result.extend_from_slice(&base_content[current_offset..]);
+    }
+    /// Writes into `file`, a copy of `base`, the runs of `content` not already in place
+    fn write_runs(&self, file: &Path, base: &[u8], content: &[u8]) -> Result<u64> {
+        let mut file = fs::OpenOptions::new().write(true).open(file)?;
+        let mut written = 0;
+        let mut write_at = |offset: u64, run: &[u8]| -> Result<()> {
+            file.seek(SeekFrom::Start(offset))?;
+            file.write_all(run)?;
+            written += run.len() as u64;
+            Ok(())
+        };
+        let mut at = 0;
+        for copy in self.matches(&self.calculate_blocks(base), content) {
+            write_at(at as u64, &content[at..copy.offset as usize])?;
+            if copy.base_offset != copy.offset {
+                let start = copy.base_offset as usize;
+                write_at(copy.offset, &base[start..start + copy.size as usize])?;
+            }
+            at = (copy.offset + copy.size) as usize;
         }
-        fs::write(output_path, result)?;
-        Ok(())
+        write_at(at as u64, &content[at..])?;
+        file.set_len(content.len() as u64)?;
+        file.sync_all()?;
+        Ok(written)
     }
     pub fn store_blocks(&mut self, path: PathBuf, content: &[u8]) {
         let blocks = self.calculate_blocks(content);
@@ -117,22 +286,20 @@ impl IncrementalSync {
     pub fn get_blocks(&self, path: &Path) -> Option<&Vec<BlockHash>> {
         self.file_blocks.get(path)
     }
-    fn calculate_blocks(&self, content: &[u8]) -> Vec<BlockHash> {
-        let mut blocks = Vec::new();
-        let mut offset = 0;
-        while offset < content.len() {
-            let size = (self.block_size).min(content.len() - offset);
-            let block_data = &content[offset..offset + size];
-            let hash = format!("{:x}", md5::compute(block_data));
-            blocks
-                .push(BlockHash {
-                    offset: offset as u64,
-                    size: size as u64,
-                    hash,
-                });
-            offset += size;
+    /// Hashes of each `block_size` chunk of `content`, on all cores for large inputs
+    pub fn calculate_blocks(&self, content: &[u8]) -> Vec<BlockHash> {
+        let block_size = self.block_size.max(1);
+        let hash = |(i, block): (usize, &[u8])| BlockHash {
+            offset: (i * block_size) as u64,
+            size: block.len() as u64,
+            weak: Rolling::new(block).digest(),
+            hash: format!("{:x}", md5::compute(block)),
+        };
+        if content.len() / block_size < PARALLEL_MIN_BLOCKS {
+            content.chunks(block_size).enumerate().map(hash).collect()
+        } else {
+            content.par_chunks(block_size).enumerate().map(hash).collect()
         }
-        blocks
     }
     pub fn get_stats(&self) -> IncrementalStats {
         let total_files = self.file_blocks.len();
@@ -177,8 +344,55 @@ mod tests {
         let sync = IncrementalSync::new(4);
         let deltas = sync.calculate_delta(&old_file, &new_file).unwrap();
         assert!(! deltas.is_empty());
-        let has_changed = deltas.iter().any(|d| d.data.is_some());
-        let has_unchanged = deltas.iter().any(|d| d.data.is_none());
+        let has_changed = deltas.iter().any(|d| d.literal);
+        let has_unchanged = deltas.iter().any(|d| !d.literal);
         assert!(has_changed || has_unchanged);
     }
+    #[test]
+    fn test_parallel_block_calculation() {
+        let sync = IncrementalSync::new(16);
+        let content: Vec<u8> = (0..16 * PARALLEL_MIN_BLOCKS * 4 + 5).map(|i| i as u8).collect();
+        let blocks = sync.calculate_blocks(&content);
+        assert_eq!(blocks.len(), PARALLEL_MIN_BLOCKS * 4 + 1);
+        for (block, chunk) in blocks.iter().zip(content.chunks(16)) {
+            assert_eq!(&content[block.offset as usize..][..block.size as usize], chunk);
+            assert_eq!(block.hash, format!("{:x}", md5::compute(chunk)));
+        }
+        assert_eq!(blocks.last().unwrap().size, 5);
+    }
+    #[test]
+    fn test_delta_finds_shifted_blocks() {
+        let temp_dir = tempdir().unwrap();
+        let (old_file, new_file, out_file) = (
+            temp_dir.path().join("old.bin"),
+            temp_dir.path().join("new.bin"),
+            temp_dir.path().join("out.bin"),
+        );
+        let old: Vec<u8> = (0..4096u32).map(|i| (i * 7 % 251) as u8).collect();
+        let mut new = b"inserted".to_vec();
+        new.extend_from_slice(&old[..2000]);
+        new.extend_from_slice(&old[2100..]);
+        fs::write(&old_file, &old).unwrap();
+        fs::write(&new_file, &new).unwrap();
+        let sync = IncrementalSync::new(64);
+        let deltas = sync.calculate_delta(&old_file, &new_file).unwrap();
+        let literal: u64 = deltas.iter().filter(|d| d.literal).map(|d| d.size).sum();
+        assert!(literal < 3 * 64, "{} literal bytes", literal);
+        assert_eq!(deltas.iter().map(|d| d.size).sum::<u64>(), new.len() as u64);
+        assert_eq!(deltas[0].literal_data(&new), Some(&b"inserted"[..]));
+        sync.apply_delta(&old_file, &new_file, &deltas, &out_file).unwrap();
+        assert_eq!(fs::read(&out_file).unwrap(), new);
+        sync.patch_file(&old_file, &new).unwrap();
+        assert_eq!(fs::read(&old_file).unwrap(), new);
+        assert_eq!(sync.patch_file(&old_file, &new).unwrap(), 0);
+        let empty = temp_dir.path().join("empty.bin");
+        fs::write(&empty, b"").unwrap();
+        assert!(MappedFile::open(&empty).unwrap().is_empty());
+        let deltas = sync.calculate_delta(&empty, &new_file).unwrap();
+        assert_eq!(deltas.len(), 1);
+        assert_eq!(deltas[0].literal_data(&new), Some(&new[..]));
+        let mut window = Rolling::new(&new[..64]);
+        window.roll(new[0], new[64]);
+        assert_eq!(window.digest(), Rolling::new(&new[1..65]).digest());
+    }
 }
\ No newline at end of file
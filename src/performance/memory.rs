@@ -0,0 +1,171 @@
+use std::{
+    fs::File, io::{self, Read}, path::Path, sync::{Condvar, Mutex},
+};
+/// Read buffer for streaming work when there is no memory limit
+const DEFAULT_BUFFER: usize = 1024 * 1024;
+const MIN_BUFFER: usize = 64 * 1024;
+/// Approximate size of one block hash in a delta signature
+const SIGNATURE_ENTRY: u64 = 128;
+#[derive(Debug, Default)]
+struct Usage {
+    /// 0 for no limit
+    limit: u64,
+    used: u64,
+}
+/// Memory that hashing, compression, deltas and parallel backups may use, shared by the
+/// work running at once; `budget()` is the one set from `performance.max_memory`
+#[derive(Debug, Default)]
+pub struct MemoryBudget {
+    usage: Mutex<Usage>,
+    released: Condvar,
+}
+static BUDGET: MemoryBudget = MemoryBudget::unlimited();
+/// The process-wide memory budget
+pub fn budget() -> &'static MemoryBudget {
+    &BUDGET
+}
+/// MD5 of the file at `path` in hex, read through a buffer of the budget's size
+pub fn hash_file(path: &Path) -> io::Result<String> {
+    let mut file = File::open(crate::long_path::extend(path))?;
+    let mut context = md5::Context::new();
+    let mut buffer = vec![0; budget().buffer_size()];
+    loop {
+        match file.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => context.consume(&buffer[..read]),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(format!("{:x}", context.compute()))
+}
+/// Part of a budget held until dropped
+#[derive(Debug)]
+pub struct Reservation<'a> {
+    budget: &'a MemoryBudget,
+    bytes: u64,
+}
+impl Drop for Reservation<'_> {
+    fn drop(&mut self) {
+        if self.bytes == 0 {
+            return;
+        }
+        let mut usage = self.budget.lock();
+        usage.used = usage.used.saturating_sub(self.bytes);
+        self.budget.released.notify_all();
+    }
+}
+impl MemoryBudget {
+    pub const fn unlimited() -> Self {
+        Self { usage: Mutex::new(Usage { limit: 0, used: 0 }), released: Condvar::new() }
+    }
+    fn lock(&self) -> std::sync::MutexGuard<'_, Usage> {
+        self.usage.lock().unwrap_or_else(|e| e.into_inner())
+    }
+    /// Sets the limit in bytes; 0 removes it
+    pub fn set_limit(&self, bytes: u64) {
+        self.lock().limit = bytes;
+        self.released.notify_all();
+    }
+    /// The limit, `None` without one
+    pub fn limit(&self) -> Option<u64> {
+        let limit = self.lock().limit;
+        (limit > 0).then_some(limit)
+    }
+    /// Waits until `bytes` fit beside the work already holding part of the budget and
+    /// holds them; work larger than the whole budget waits until it runs alone. Returns
+    /// at once without a limit.
+    pub fn reserve(&self, bytes: u64) -> Reservation<'_> {
+        let mut usage = self.lock();
+        if usage.limit == 0 {
+            return Reservation { budget: self, bytes: 0 };
+        }
+        let bytes = bytes.clamp(1, usage.limit);
+        while usage.limit > 0 && usage.used + bytes > usage.limit {
+            usage = self.released.wait(usage).unwrap_or_else(|e| e.into_inner());
+        }
+        if usage.limit == 0 {
+            return Reservation { budget: self, bytes: 0 };
+        }
+        usage.used += bytes;
+        Reservation { budget: self, bytes }
+    }
+    /// Holds what versioning the file at `path` takes: its content and compressed copy
+    pub fn reserve_file(&self, path: &Path) -> Reservation<'_> {
+        let len = path.metadata().map(|m| m.len()).unwrap_or(0);
+        self.reserve(len.saturating_mul(2))
+    }
+    /// How many of `wanted` workers fit when each needs `per_worker` bytes, at least one
+    pub fn concurrency(&self, wanted: usize, per_worker: u64) -> usize {
+        match self.limit() {
+            Some(limit) => wanted.min((limit / per_worker.max(1)) as usize).max(1),
+            None => wanted.max(1),
+        }
+    }
+    /// Read buffer for streaming hashes: 1 MiB, or a 64th of the limit
+    pub fn buffer_size(&self) -> usize {
+        match self.limit() {
+            Some(limit) => ((limit / 64) as usize).clamp(MIN_BUFFER, DEFAULT_BUFFER),
+            None => DEFAULT_BUFFER,
+        }
+    }
+    /// Block size for a delta of a `len` byte file: `block_size`, or larger when its
+    /// signature would take more than an eighth of the limit
+    pub fn delta_block_size(&self, len: u64, block_size: usize) -> usize {
+        let Some(limit) = self.limit() else {
+            return block_size;
+        };
+        let most_blocks = (limit / 8 / SIGNATURE_ENTRY).max(1);
+        let needed = len.div_ceil(most_blocks) as usize;
+        if needed <= block_size { block_size } else { needed.next_power_of_two() }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{sync::{atomic::{AtomicU64, Ordering}, Arc}, thread, time::Duration};
+    #[test]
+    fn test_memory_budget() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("data.bin");
+        let content: Vec<u8> = (0..3 * MIN_BUFFER as u32).map(|i| i as u8).collect();
+        std::fs::write(&path, &content).unwrap();
+        assert_eq!(hash_file(&path).unwrap(), format!("{:x}", md5::compute(&content)));
+        let budget = Arc::new(MemoryBudget::unlimited());
+        assert_eq!(budget.delta_block_size(1 << 40, 4096), 4096);
+        assert_eq!(budget.concurrency(8, u64::MAX), 8);
+        budget.set_limit(1 << 20);
+        assert_eq!(budget.buffer_size(), MIN_BUFFER);
+        assert_eq!(budget.concurrency(8, 300 * 1024), 3);
+        assert_eq!(budget.concurrency(8, 4 << 20), 1);
+        assert_eq!(budget.delta_block_size(1 << 30, 4096), 1 << 20);
+        assert_eq!(budget.delta_block_size(1 << 20, 4096), 4096);
+        let (held, peak) = (Arc::new(AtomicU64::new(0)), Arc::new(AtomicU64::new(0)));
+        let workers: Vec<_> = (0..4)
+            .map(|_| {
+                let (budget, held, peak) = (budget.clone(), held.clone(), peak.clone());
+                thread::spawn(move || {
+                    let _reservation = budget.reserve(400 * 1024);
+                    let now = held.fetch_add(400 * 1024, Ordering::SeqCst) + 400 * 1024;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    thread::sleep(Duration::from_millis(20));
+                    held.fetch_sub(400 * 1024, Ordering::SeqCst);
+                })
+            })
+            .collect();
+        drop(budget.reserve(10 << 20));
+        for worker in workers {
+            worker.join().unwrap();
+        }
+        assert!(peak.load(Ordering::SeqCst) <= 800 * 1024);
+        assert_eq!(budget.lock().used, 0);
+        let _all = budget.reserve(1 << 20);
+        let waiting = {
+            let budget = budget.clone();
+            thread::spawn(move || budget.reserve(1).bytes)
+        };
+        thread::sleep(Duration::from_millis(20));
+        budget.set_limit(0);
+        assert_eq!(waiting.join().unwrap(), 0);
+    }
+}
\ No newline at end of file
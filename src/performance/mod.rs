@@ -1,4 +1,44 @@
 pub mod incremental;
+pub mod memory;
 pub mod parallel;
-pub use incremental::{IncrementalSync, DeltaBlock, BlockHash};
-pub use parallel::{ParallelProcessor, ProcessResult, WorkQueue};
\ No newline at end of file
+pub use incremental::{IncrementalSync, DeltaBlock, BlockHash, MappedFile};
+pub use parallel::{ParallelProcessor, ProcessResult, WorkQueue};
+use crate::{
+    errors::{ErrorCode, Result, SymorError}, versioning::filter::{parse_quantity, SIZE_UNITS},
+};
+use serde::{Deserialize, Serialize};
+/// Tuning for CPU-heavy work, in the `performance` config section
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PerformanceConfig {
+    /// Threads that compress the chunks of a large version; 0 picks one from the CPU count
+    pub compression_threads: usize,
+    /// Run the daemon's syncs and backups at low CPU and IO priority
+    pub background_priority: bool,
+    /// Memory that hashing, compression, deltas and parallel backups may use together,
+    /// like `512M`; unset for no limit
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_memory: Option<String>,
+}
+impl PerformanceConfig {
+    pub fn compression_threads(&self) -> usize {
+        match self.compression_threads {
+            0 => ParallelProcessor::get_optimal_concurrency(),
+            threads => threads,
+        }
+    }
+    /// `max_memory` in bytes, 0 without a limit
+    pub fn max_memory_bytes(&self) -> Result<u64> {
+        let Some(text) = &self.max_memory else {
+            return Ok(0);
+        };
+        parse_quantity(text.trim(), SIZE_UNITS)
+            .ok_or_else(|| {
+                SymorError::new(
+                    ErrorCode::InvalidConfiguration,
+                    format!("Invalid max_memory: {:?}", text),
+                )
+                    .with_suggestion("Use a size like '256M' or '1G'".to_string())
+            })
+    }
+}
\ No newline at end of file
@@ -1,8 +1,11 @@
-use anyhow::Result;
+use crate::errors::{ErrorCode, Result, SymorError};
 use std::{
     path::PathBuf, sync::mpsc::{self, Receiver, Sender},
     time::{Duration, Instant},
 };
+fn internal_error(message: impl Into<String>) -> SymorError {
+    SymorError::new(ErrorCode::InternalError, message.into())
+}
 #[derive(Debug, Clone)]
 pub struct ProcessResult {
     pub path: PathBuf,
@@ -19,7 +22,7 @@ impl<T: Send + Sync + 'static> WorkQueue<T> {
         (Self { sender }, receiver)
     }
     pub fn enqueue(&self, item: T) -> Result<()> {
-        self.sender.send(item)?;
+        self.sender.send(item).map_err(|_| internal_error("Work queue is closed"))?;
         Ok(())
     }
 }
@@ -91,7 +94,9 @@ impl AdvancedParallelProcessor {
                 path,
                 _processor_id: i % self.thread_pool.len(),
             };
-            self.work_sender.send(work_item)?;
+            self.work_sender
+                .send(work_item)
+                .map_err(|_| internal_error("Worker threads have stopped"))?;
         }
         Ok(())
     }
@@ -107,7 +112,7 @@ impl AdvancedParallelProcessor {
     pub fn wait_and_collect(&mut self) -> Result<Vec<ProcessResult>> {
         drop(self.work_sender.clone());
         for handle in self.thread_pool.drain(..) {
-            handle.join().map_err(|_| anyhow::anyhow!("Worker thread panicked"))?;
+            handle.join().map_err(|_| internal_error("Worker thread panicked"))?;
         }
         let mut results = Vec::new();
         while let Ok(result) = self.result_receiver.try_recv() {
@@ -295,6 +300,7 @@ impl ParallelProcessor {
         }
         Ok(results)
     }
+    #[cfg(feature = "tokio")]
     pub async fn process_files_async<F, Fut>(
         &self,
         files: Vec<PathBuf>,
@@ -332,10 +338,39 @@ impl ParallelProcessor {
         }
         let mut results = Vec::new();
         for task in tasks {
-            results.push(task.await?);
+            results.push(task.await.map_err(|e| internal_error(e.to_string()))?);
         }
         Ok(results)
     }
+    /// Runs `work` on every item using up to `max_concurrent` threads, returning the
+    /// outputs in the order of `items`
+    pub fn map_parallel<T, R, F>(&self, items: &[T], work: F) -> Vec<R>
+    where
+        T: Sync,
+        R: Send,
+        F: Fn(&T) -> R + Sync,
+    {
+        use std::sync::{atomic::{AtomicUsize, Ordering}, Mutex};
+        let next = AtomicUsize::new(0);
+        let outputs: Mutex<Vec<Option<R>>> = Mutex::new(
+            items.iter().map(|_| None).collect(),
+        );
+        let workers = self.max_concurrent.clamp(1, items.len().max(1));
+        std::thread::scope(|scope| {
+            for _ in 0..workers {
+                scope
+                    .spawn(|| loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(item) = items.get(index) else {
+                            break;
+                        };
+                        let output = work(item);
+                        outputs.lock().unwrap()[index] = Some(output);
+                    });
+            }
+        });
+        outputs.into_inner().unwrap().into_iter().map(|output| output.unwrap()).collect()
+    }
     pub fn get_optimal_concurrency() -> usize {
         let num_cpus = num_cpus::get();
         (num_cpus * 3 / 4).max(1)
@@ -364,15 +399,57 @@ pub struct ParallelStats {
     pub max_concurrent: usize,
     pub queue_length: usize,
 }
+/// Tuning parameters for adaptive batch sizing
+#[derive(Debug, Clone)]
+pub struct BatchConfig {
+    pub min_batch_size: usize,
+    pub max_batch_size: usize,
+    pub target_item_latency: Duration,
+    pub max_error_rate: f64,
+    pub adaptive: bool,
+}
+impl Default for BatchConfig {
+    fn default() -> Self {
+        Self {
+            min_batch_size: 1,
+            max_batch_size: 1024,
+            target_item_latency: Duration::from_millis(20),
+            max_error_rate: 0.1,
+            adaptive: true,
+        }
+    }
+}
+#[derive(Debug, Clone, Default)]
+struct BatchState {
+    batch_size: usize,
+    batches_processed: usize,
+    items_processed: usize,
+    last_item_latency: Duration,
+    last_error_rate: f64,
+}
 /// Batch processor for grouping operations
 pub struct BatchProcessor {
-    batch_size: usize,
+    config: BatchConfig,
+    state: std::sync::Mutex<BatchState>,
     processor: ParallelProcessor,
 }
 impl BatchProcessor {
     pub fn new(batch_size: usize, max_concurrent: usize) -> Self {
+        Self::with_config(batch_size, max_concurrent, BatchConfig::default())
+    }
+    pub fn with_config(
+        batch_size: usize,
+        max_concurrent: usize,
+        config: BatchConfig,
+    ) -> Self {
+        let min = config.min_batch_size.max(1);
+        let batch_size = batch_size.clamp(min, config.max_batch_size.max(min));
         Self {
-            batch_size,
+            config,
+            state: std::sync::Mutex::new(BatchState {
+                batch_size,
+                ..Default::default()
+            }),
             processor: ParallelProcessor::new(max_concurrent),
         }
     }
@@ -385,15 +462,83 @@ impl BatchProcessor {
         F: Fn(PathBuf) -> Result<()> + Send + Sync + 'static + Clone,
     {
         let mut all_results = Vec::new();
-        for batch in files.chunks(self.batch_size) {
-            let batch_vec = batch.to_vec();
+        let mut remaining = files.into_iter().peekable();
+        while remaining.peek().is_some() {
+            let batch_size = self.current_batch_size();
+            let batch_vec: Vec<PathBuf> = remaining.by_ref().take(batch_size).collect();
             let batch_results = self
                 .processor
                 .process_files_parallel(batch_vec, processor.clone())?;
+            self.record_batch(&batch_results);
             all_results.extend(batch_results);
         }
         Ok(all_results)
     }
+    pub fn current_batch_size(&self) -> usize {
+        self.state
+            .lock()
+            .map(|s| s.batch_size)
+            .unwrap_or(self.config.min_batch_size)
+            .max(1)
+    }
+    /// Shrink the batch when items are slow or failing, grow it when they are fast
+    fn record_batch(&self, results: &[ProcessResult]) {
+        if results.is_empty() {
+            return;
+        }
+        let total_time: Duration = results.iter().map(|r| r.duration).sum();
+        let failures = results.iter().filter(|r| !r.success).count();
+        let item_latency = total_time / results.len() as u32;
+        let error_rate = failures as f64 / results.len() as f64;
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        state.batches_processed += 1;
+        state.items_processed += results.len();
+        state.last_item_latency = item_latency;
+        state.last_error_rate = error_rate;
+        if !self.config.adaptive {
+            return;
+        }
+        let min = self.config.min_batch_size.max(1);
+        let max = self.config.max_batch_size.max(min);
+        if error_rate > self.config.max_error_rate
+            || item_latency > self.config.target_item_latency * 2
+        {
+            state.batch_size = (state.batch_size / 2).max(min);
+        } else if failures == 0 && item_latency < self.config.target_item_latency / 2 {
+            state.batch_size = state.batch_size.saturating_mul(2).min(max);
+        }
+    }
+    pub fn get_stats(&self) -> BatchStats {
+        let state = self.state.lock().map(|s| s.clone()).unwrap_or_default();
+        BatchStats {
+            current_batch_size: state.batch_size,
+            min_batch_size: self.config.min_batch_size,
+            max_batch_size: self.config.max_batch_size,
+            target_item_latency: self.config.target_item_latency,
+            adaptive: self.config.adaptive,
+            batches_processed: state.batches_processed,
+            items_processed: state.items_processed,
+            last_item_latency: state.last_item_latency,
+            last_error_rate: state.last_error_rate,
+            max_concurrent: self.processor.get_stats().max_concurrent,
+        }
+    }
+}
+/// Batch processing statistics, including the currently chosen batch size
+#[derive(Debug, Clone)]
+pub struct BatchStats {
+    pub current_batch_size: usize,
+    pub min_batch_size: usize,
+    pub max_batch_size: usize,
+    pub target_item_latency: Duration,
+    pub adaptive: bool,
+    pub batches_processed: usize,
+    pub items_processed: usize,
+    pub last_item_latency: Duration,
+    pub last_error_rate: f64,
+    pub max_concurrent: usize,
 }
 #[cfg(test)]
 mod tests {
@@ -408,6 +553,21 @@ mod tests {
         assert_eq!(receiver.recv().unwrap(), "item2");
     }
     #[test]
+    fn test_map_parallel() {
+        let processor = ParallelProcessor::new(4);
+        let items: Vec<u64> = (0..50).collect();
+        let doubled = processor
+            .map_parallel(
+                &items,
+                |n| {
+                    std::thread::sleep(Duration::from_millis(n % 3));
+                    n * 2
+                },
+            );
+        assert_eq!(doubled, items.iter().map(| n | n * 2).collect::< Vec < _ >> ());
+        assert!(processor.map_parallel(& Vec::<u64>::new(), | n | * n).is_empty());
+    }
+    #[test]
     fn test_parallel_processing() {
         let temp_dir = tempdir().unwrap();
         let files = vec![
@@ -430,6 +590,7 @@ mod tests {
         assert_eq!(results.len(), 3);
         assert!(results.iter().all(| r | r.success));
     }
+    #[cfg(feature = "tokio")]
     #[tokio::test]
     async fn test_async_processing() {
         let temp_dir = tempdir().unwrap();
@@ -453,4 +614,32 @@ mod tests {
         assert_eq!(results.len(), 2);
         assert!(results.iter().all(| r | r.success));
     }
+    #[test]
+    fn test_adaptive_batch_sizing() {
+        let files: Vec<PathBuf> = (0..40)
+            .map(|i| PathBuf::from(format!("file{}.txt", i)))
+            .collect();
+        let fast = BatchProcessor::with_config(
+            2,
+            2,
+            BatchConfig {
+                target_item_latency: Duration::from_secs(1),
+                ..Default::default()
+            },
+        );
+        let results = fast.process_in_batches(files.clone(), |_| Ok(())).unwrap();
+        assert_eq!(results.len(), 40);
+        assert!(fast.get_stats().current_batch_size > 2);
+        let failing = BatchProcessor::new(16, 2);
+        failing
+            .process_in_batches(
+                files,
+                |_| Err(SymorError::new(ErrorCode::NetworkError, "unreachable".to_string())),
+            )
+            .unwrap();
+        let stats = failing.get_stats();
+        assert_eq!(stats.current_batch_size, stats.min_batch_size);
+        assert_eq!(stats.items_processed, 40);
+        assert!(stats.last_error_rate > 0.9);
+    }
 }
\ No newline at end of file
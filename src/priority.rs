@@ -0,0 +1,66 @@
+use std::io;
+/// Nice value background work runs at
+#[cfg(unix)]
+const BACKGROUND_NICE: libc::c_int = 10;
+/// Lowers the CPU and IO priority of the calling thread and of the threads it starts
+/// afterwards, so their syncs and backups yield to interactive programs: nice 10 and
+/// the lowest best-effort IO priority (`ionice -c2 -n7`) on Linux, nice 10 on other
+/// unixes, and background processing mode on Windows. A priority that is already lower
+/// is kept.
+#[cfg(unix)]
+pub fn enter_background() -> io::Result<()> {
+    // -1 is also what getpriority returns on failure, which only lowers the nice value
+    let current = unsafe { libc::getpriority(libc::PRIO_PROCESS, 0) };
+    if current < BACKGROUND_NICE
+        && unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, BACKGROUND_NICE) } != 0
+    {
+        return Err(io::Error::last_os_error());
+    }
+    #[cfg(target_os = "linux")]
+    {
+        const IOPRIO_WHO_PROCESS: libc::c_long = 1;
+        const IOPRIO_CLASS_SHIFT: libc::c_long = 13;
+        const IOPRIO_CLASS_IDLE: libc::c_long = 3;
+        let lowest_best_effort = (2 << IOPRIO_CLASS_SHIFT) | 7;
+        let current = unsafe { libc::syscall(libc::SYS_ioprio_get, IOPRIO_WHO_PROCESS, 0) };
+        let lower = current == lowest_best_effort
+            || current >> IOPRIO_CLASS_SHIFT == IOPRIO_CLASS_IDLE;
+        if !lower
+            && unsafe {
+                libc::syscall(libc::SYS_ioprio_set, IOPRIO_WHO_PROCESS, 0, lowest_best_effort)
+            } != 0
+        {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+#[cfg(windows)]
+pub fn enter_background() -> io::Result<()> {
+    use windows_sys::Win32::System::Threading::{
+        GetCurrentProcess, SetPriorityClass, PROCESS_MODE_BACKGROUND_BEGIN,
+    };
+    if unsafe { SetPriorityClass(GetCurrentProcess(), PROCESS_MODE_BACKGROUND_BEGIN) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+#[cfg(not(any(unix, windows)))]
+pub fn enter_background() -> io::Result<()> {
+    Ok(())
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_enter_background() {
+        std::thread::spawn(|| {
+                enter_background().unwrap();
+                enter_background().unwrap();
+                #[cfg(unix)]
+                assert!(unsafe { libc::getpriority(libc::PRIO_PROCESS, 0) } >= BACKGROUND_NICE);
+            })
+            .join()
+            .unwrap();
+    }
+}
\ No newline at end of file
@@ -0,0 +1,230 @@
+use crate::{
+    conflicts::{Conflict, ConflictKind, EditedSide, Resolution},
+    errors::{Context, ErrorCode, Result, SymorError},
+    SymorManager,
+};
+use serde::{Deserialize, Serialize};
+use std::{fs, path::{Path, PathBuf}, time::{SystemTime, UNIX_EPOCH}};
+/// What a quarantine entry holds, saved as `manifest.json` beside the copies
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuarantineManifest {
+    pub source: PathBuf,
+    pub target: PathBuf,
+    pub quarantined_at: SystemTime,
+    pub source_hash: String,
+    pub target_hash: String,
+    /// Set once `sym conflicts resolve` applied a choice
+    #[serde(default)]
+    pub resolution: Option<Resolution>,
+}
+/// Both competing versions of a file a two-way mirror could not merge, kept under
+/// `conflicts/<item>/<timestamp>/` in the home directory
+#[derive(Debug, Clone)]
+pub struct QuarantinedConflict {
+    /// `<item>/<timestamp>`, as `sym conflicts resolve` takes it
+    pub id: String,
+    pub dir: PathBuf,
+    pub manifest: QuarantineManifest,
+}
+impl QuarantinedConflict {
+    fn copy_name(side: &str, path: &Path) -> String {
+        let name = path.file_name().map(|n| n.to_string_lossy().into_owned());
+        format!("{}-{}", side, name.unwrap_or_default())
+    }
+    /// Quarantined copy of the source file
+    pub fn source_copy(&self) -> PathBuf {
+        self.dir.join(Self::copy_name("source", &self.manifest.source))
+    }
+    /// Quarantined copy of the target file
+    pub fn target_copy(&self) -> PathBuf {
+        self.dir.join(Self::copy_name("target", &self.manifest.target))
+    }
+    /// The live conflict this entry was made for
+    pub fn conflict(&self) -> Conflict {
+        Conflict {
+            kind: ConflictKind::Diverged,
+            source: self.manifest.source.clone(),
+            target: Some(self.manifest.target.clone()),
+            item_id: None,
+            edited: Some(EditedSide::Both),
+        }
+    }
+    fn save(&self) -> Result<()> {
+        let manifest = serde_json::to_string_pretty(&self.manifest)?;
+        fs::write(self.dir.join("manifest.json"), manifest)?;
+        Ok(())
+    }
+}
+pub fn conflicts_dir(home_dir: &Path) -> PathBuf {
+    home_dir.join("conflicts")
+}
+fn hash_file(path: &Path) -> Result<String> {
+    crate::performance::memory::hash_file(path).with_context(|| format!("cannot read {:?}", path))
+}
+/// Directory name for a source file: its name and a short hash of its path
+fn item_name(source: &Path) -> String {
+    let hash = format!("{:x}", md5::compute(source.to_string_lossy().as_bytes()));
+    let name = source.file_name().map(|n| n.to_string_lossy().into_owned());
+    format!("{}-{}", name.unwrap_or_default(), &hash[..8])
+}
+/// Every quarantine entry under `home_dir`, oldest first within each item
+pub fn list(home_dir: &Path) -> Result<Vec<QuarantinedConflict>> {
+    let mut entries = Vec::new();
+    for item in fs::read_dir(conflicts_dir(home_dir)).into_iter().flatten().flatten() {
+        for entry in fs::read_dir(item.path()).into_iter().flatten().flatten() {
+            let Ok(data) = fs::read_to_string(entry.path().join("manifest.json")) else {
+                continue;
+            };
+            entries.push(QuarantinedConflict {
+                id: format!(
+                    "{}/{}", item.file_name().to_string_lossy(), entry.file_name()
+                    .to_string_lossy()
+                ),
+                dir: entry.path(),
+                manifest: serde_json::from_str(&data)?,
+            });
+        }
+    }
+    entries.sort_by(|a, b| a.id.cmp(&b.id));
+    Ok(entries)
+}
+/// Copies `source` and `target` into a new quarantine entry, unless the latest one
+/// for the pair already holds the same contents
+pub fn quarantine(
+    home_dir: &Path,
+    source: &Path,
+    target: &Path,
+) -> Result<Option<QuarantinedConflict>> {
+    let (source_hash, target_hash) = (hash_file(source)?, hash_file(target)?);
+    let latest = list(home_dir)?
+        .into_iter()
+        .filter(|entry| entry.manifest.source == source && entry.manifest.target == target)
+        .max_by_key(|entry| entry.manifest.quarantined_at);
+    if latest.is_some_and(|entry| {
+        entry.manifest.resolution.is_none() && entry.manifest.source_hash == source_hash
+            && entry.manifest.target_hash == target_hash
+    }) {
+        return Ok(None);
+    }
+    let quarantined_at = SystemTime::now();
+    let secs = quarantined_at.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let item_dir = conflicts_dir(home_dir).join(item_name(source));
+    let mut stamp = secs.to_string();
+    let mut n = 1;
+    while item_dir.join(&stamp).exists() {
+        stamp = format!("{}-{}", secs, n);
+        n += 1;
+    }
+    let dir = item_dir.join(&stamp);
+    fs::create_dir_all(&dir).with_context(|| format!("cannot create directory {:?}", dir))?;
+    let entry = QuarantinedConflict {
+        id: format!("{}/{}", item_name(source), stamp),
+        dir,
+        manifest: QuarantineManifest {
+            source: source.to_path_buf(),
+            target: target.to_path_buf(),
+            quarantined_at,
+            source_hash,
+            target_hash,
+            resolution: None,
+        },
+    };
+    fs::copy(source, entry.source_copy())
+        .with_context(|| format!("cannot quarantine {:?}", source))?;
+    fs::copy(target, entry.target_copy())
+        .with_context(|| format!("cannot quarantine {:?}", target))?;
+    entry.save()?;
+    Ok(Some(entry))
+}
+impl SymorManager {
+    /// Quarantine entries under the home directory, oldest first within each item
+    pub fn quarantined_conflicts(&self) -> Result<Vec<QuarantinedConflict>> {
+        list(&self.config.home_dir)
+    }
+    /// Applies `resolution` to a quarantined conflict, given as `<item>/<timestamp>`
+    /// or as `<item>` for its latest unresolved entry. A side deleted since it was
+    /// quarantined is put back from its copy first; a side edited since then is left
+    /// alone and the resolution refused.
+    pub fn resolve_quarantined(&mut self, id: &str, resolution: Resolution) -> Result<String> {
+        self.ensure_writable("resolve conflicts")?;
+        let entries = self.quarantined_conflicts()?;
+        let entry = entries
+            .iter()
+            .filter(|entry| entry.manifest.resolution.is_none())
+            .rfind(|entry| entry.id == id || entry.id.split('/').next() == Some(id))
+            .or_else(|| entries.iter().find(|entry| entry.id == id));
+        let Some(entry) = entry else {
+            return Err(
+                SymorError::new(
+                    ErrorCode::FileNotFound,
+                    format!("No unresolved quarantined conflict {}", id),
+                )
+                    .with_suggestion("Run 'sym conflicts' to list them".to_string()),
+            );
+        };
+        if let Some(resolved) = entry.manifest.resolution {
+            return Err(
+                SymorError::new(
+                    ErrorCode::InvalidPath,
+                    format!("Conflict {} was already resolved ({:?})", entry.id, resolved),
+                ),
+            );
+        }
+        let sides = [
+            (&entry.manifest.source, entry.source_copy(), &entry.manifest.source_hash),
+            (&entry.manifest.target, entry.target_copy(), &entry.manifest.target_hash),
+        ];
+        for (live, copy, hash) in &sides {
+            if !live.exists() {
+                fs::copy(copy, live).with_context(|| format!("cannot restore {:?}", live))?;
+            } else if &hash_file(live)? != *hash {
+                return Err(
+                    SymorError::new(
+                        ErrorCode::InvalidPath,
+                        format!("{:?} changed after conflict {} was quarantined", live, entry.id),
+                    )
+                        .with_suggestion(
+                            format!(
+                                "Resolve it from 'sym conflicts', or copy the version you want from {}",
+                                entry.dir.display()
+                            ),
+                        ),
+                );
+            }
+        }
+        let message = self.resolve_conflict(&entry.conflict(), resolution)?;
+        let mut resolved = entry.clone();
+        resolved.manifest.resolution = Some(resolution);
+        resolved.save()?;
+        Ok(message)
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+    #[test]
+    fn test_quarantine_and_resolve() {
+        let temp_dir = tempdir().unwrap();
+        let home = temp_dir.path().join("home");
+        let mut manager = SymorManager::new().unwrap();
+        manager.config.home_dir = home.clone();
+        let (source, target) = (temp_dir.path().join("a.txt"), temp_dir.path().join("b.txt"));
+        fs::write(&source, "source edit").unwrap();
+        fs::write(&target, "target edit").unwrap();
+        let entry = quarantine(&home, &source, &target).unwrap().unwrap();
+        assert!(quarantine(&home, &source, &target).unwrap().is_none());
+        assert_eq!(fs::read_to_string(entry.target_copy()).unwrap(), "target edit");
+        fs::remove_file(&target).unwrap();
+        let item = entry.id.split('/').next().unwrap();
+        manager.resolve_quarantined(item, Resolution::KeepTarget).unwrap();
+        assert_eq!(fs::read_to_string(&source).unwrap(), "target edit");
+        let entries = manager.quarantined_conflicts().unwrap();
+        assert_eq!(entries[0].manifest.resolution, Some(Resolution::KeepTarget));
+        assert!(manager.resolve_quarantined(&entry.id, Resolution::KeepSource).is_err());
+        fs::write(&target, "edited again").unwrap();
+        let entry = quarantine(&home, &source, &target).unwrap().unwrap();
+        fs::write(&target, "and again").unwrap();
+        assert!(manager.resolve_quarantined(&entry.id, Resolution::KeepSource).is_err());
+    }
+}
\ No newline at end of file
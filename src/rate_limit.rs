@@ -0,0 +1,56 @@
+use std::{collections::VecDeque, time::{Duration, Instant}};
+const WINDOW: Duration = Duration::from_secs(60);
+/// Caps how many syncs a mirror runs in any sliding minute, so a source that never
+/// settles (a log file, build output) cannot keep it busy forever
+#[derive(Debug, Clone, Default)]
+pub struct SyncRateLimiter {
+    /// 0 means unlimited
+    max_per_minute: u32,
+    recent: VecDeque<Instant>,
+}
+impl SyncRateLimiter {
+    pub fn new(max_per_minute: u32) -> Self {
+        Self {
+            max_per_minute,
+            recent: VecDeque::new(),
+        }
+    }
+    pub fn max_per_minute(&self) -> u32 {
+        self.max_per_minute
+    }
+    /// Records a sync at `now` if the limit allows one, otherwise returns when the next
+    /// sync may run
+    pub fn acquire(&mut self, now: Instant) -> Result<(), Instant> {
+        if self.max_per_minute == 0 {
+            return Ok(());
+        }
+        while self.recent.front().is_some_and(|at| now.duration_since(*at) >= WINDOW) {
+            self.recent.pop_front();
+        }
+        if self.recent.len() >= self.max_per_minute as usize {
+            return Err(self.recent[0] + WINDOW);
+        }
+        self.recent.push_back(now);
+        Ok(())
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_sync_rate_limiter() {
+        let start = Instant::now();
+        let mut unlimited = SyncRateLimiter::new(0);
+        assert!((0..1000).all(|_| unlimited.acquire(start).is_ok()));
+        let mut limiter = SyncRateLimiter::new(3);
+        for secs in [0, 10, 20] {
+            assert!(limiter.acquire(start + Duration::from_secs(secs)).is_ok());
+        }
+        assert_eq!(limiter.acquire(start + Duration::from_secs(30)), Err(start + WINDOW));
+        assert!(limiter.acquire(start + Duration::from_secs(60)).is_ok());
+        assert_eq!(
+            limiter.acquire(start + Duration::from_secs(61)),
+            Err(start + Duration::from_secs(70))
+        );
+    }
+}
\ No newline at end of file
@@ -0,0 +1,454 @@
+use crate::{
+    daemon,
+    errors::{ErrorCode, Result, SymorError},
+    monitoring::status::MirrorStatus,
+    tui::{format::{format_age, format_bytes, format_utc}, log_tail::LogTail},
+    versioning::filter::{parse_quantity, AGE_UNITS},
+    SymorManager,
+};
+use std::{fmt, fmt::Write, path::PathBuf, str::FromStr, time::{Duration, SystemTime}};
+/// Most daemon log errors and warnings a report lists
+const REPORT_LOG_LINES: usize = 10;
+/// Output format of `sym report`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReportFormat {
+    #[default]
+    Markdown,
+    Csv,
+    Html,
+}
+impl FromStr for ReportFormat {
+    type Err = SymorError;
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "md" | "markdown" => Ok(ReportFormat::Markdown),
+            "csv" => Ok(ReportFormat::Csv),
+            "html" => Ok(ReportFormat::Html),
+            other => {
+                Err(
+                    SymorError::new(
+                            ErrorCode::InvalidConfiguration,
+                            format!("Unknown report format: {}", other),
+                        )
+                        .with_suggestion("Use 'md', 'csv' or 'html'".to_string()),
+                )
+            }
+        }
+    }
+}
+impl fmt::Display for ReportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(
+            match self {
+                ReportFormat::Markdown => "md",
+                ReportFormat::Csv => "csv",
+                ReportFormat::Html => "html",
+            },
+        )
+    }
+}
+/// How a watched item's backups look in a report
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ItemHealth {
+    Ok,
+    /// Last backed up longer ago than the report's `stale_after`
+    Stale,
+    NeverBackedUp,
+    Paused,
+}
+impl fmt::Display for ItemHealth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(
+            match self {
+                ItemHealth::Ok => "ok",
+                ItemHealth::Stale => "stale",
+                ItemHealth::NeverBackedUp => "never backed up",
+                ItemHealth::Paused => "paused",
+            },
+        )
+    }
+}
+#[derive(Debug, Clone, PartialEq)]
+pub struct ItemReport {
+    pub id: String,
+    pub name: Option<String>,
+    pub path: PathBuf,
+    pub description: Option<String>,
+    pub versions: usize,
+    pub last_backup: Option<SystemTime>,
+    pub stored_bytes: u64,
+    pub original_bytes: u64,
+    pub health: ItemHealth,
+}
+impl ItemReport {
+    /// The item's name, or its ID when it has none
+    pub fn label(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.id)
+    }
+}
+/// An error a mirror recorded, or an error or warning line of the daemon log
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReportError {
+    pub origin: String,
+    pub at: Option<SystemTime>,
+    pub message: String,
+}
+/// Health of every watched item for `sym report`, to attach to tickets or mail out
+#[derive(Debug, Clone, PartialEq)]
+pub struct BackupReport {
+    pub generated: SystemTime,
+    pub host: String,
+    pub stale_after: Duration,
+    /// Sorted by path
+    pub items: Vec<ItemReport>,
+    pub errors: Vec<ReportError>,
+}
+/// Parses a `--stale` age such as `36h`, `7d` or `2w`
+pub fn parse_stale_after(text: &str) -> Result<Duration> {
+    parse_quantity(text.trim(), AGE_UNITS)
+        .filter(|secs| *secs > 0)
+        .map(Duration::from_secs)
+        .ok_or_else(|| {
+            SymorError::new(ErrorCode::InvalidConfiguration, format!("Invalid age: {}", text))
+                .with_suggestion("Use s, m, h, d, w or y, e.g. 36h or 7d".to_string())
+        })
+}
+/// `7 days`, `36 hours` or `90 minutes`
+fn describe(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let (count, unit) = match secs {
+        _ if secs.is_multiple_of(86_400) => (secs / 86_400, "day"),
+        _ if secs.is_multiple_of(3600) => (secs / 3600, "hour"),
+        _ => (secs.div_ceil(60), "minute"),
+    };
+    format!("{} {}{}", count, unit, if count == 1 { "" } else { "s" })
+}
+fn when(time: Option<SystemTime>) -> String {
+    time.map_or_else(
+        || "never".to_string(), |time| format!("{} ({})", format_utc(time), format_age(time))
+    )
+}
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+fn markdown_cell(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', " ")
+}
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+impl BackupReport {
+    /// Items that are not paused but have no backup within `stale_after`
+    pub fn needing_attention(&self) -> impl Iterator<Item = &ItemReport> {
+        self.items
+            .iter()
+            .filter(|item| matches!(item.health, ItemHealth::Stale | ItemHealth::NeverBackedUp))
+    }
+    fn summary(&self) -> Vec<String> {
+        let paused = self.items.iter().filter(|i| i.health == ItemHealth::Paused).count();
+        let versions: usize = self.items.iter().map(|item| item.versions).sum();
+        let stored: u64 = self.items.iter().map(|item| item.stored_bytes).sum();
+        let original: u64 = self.items.iter().map(|item| item.original_bytes).sum();
+        vec![
+            format!("Watched items: {} ({} paused)", self.items.len(), paused),
+            format!("Versions: {}", versions),
+            format!(
+                "Storage used: {} ({} original)", format_bytes(stored), format_bytes(original)
+            ),
+            format!(
+                "Without a backup in the last {}: {}", describe(self.stale_after),
+                self.needing_attention().count()
+            ),
+            format!("Recent errors: {}", self.errors.len()),
+        ]
+    }
+    fn attention_line(item: &ItemReport) -> String {
+        match item.last_backup {
+            Some(time) => format!("last backup {}", when(Some(time))),
+            None => "never backed up".to_string(),
+        }
+    }
+    fn error_line(error: &ReportError) -> String {
+        match error.at {
+            Some(at) => format!("{} ({}): {}", error.origin, format_utc(at), error.message),
+            None => format!("{}: {}", error.origin, error.message),
+        }
+    }
+    pub fn render(&self, format: ReportFormat) -> String {
+        match format {
+            ReportFormat::Markdown => self.markdown(),
+            ReportFormat::Csv => self.csv(),
+            ReportFormat::Html => self.html(),
+        }
+    }
+    fn markdown(&self) -> String {
+        let mut out = String::from("# Symor backup report\n\n");
+        let _ = writeln!(out, "Generated {} on `{}`.\n", format_utc(self.generated), self.host);
+        out.push_str("## Summary\n\n");
+        for line in self.summary() {
+            let _ = writeln!(out, "- {}", line);
+        }
+        out.push_str("\n## Items\n\n");
+        out.push_str("| Item | Path | Versions | Last backup | Stored | Status |\n");
+        out.push_str("|---|---|---:|---|---:|---|\n");
+        for item in &self.items {
+            let _ = writeln!(
+                out, "| {} | `{}` | {} | {} | {} | {} |", markdown_cell(item.label()),
+                markdown_cell(&item.path.display().to_string()), item.versions,
+                when(item.last_backup), format_bytes(item.stored_bytes), item.health
+            );
+        }
+        let _ = writeln!(out, "\n## Items without a backup in the last {}\n", describe(
+            self.stale_after
+        ));
+        let mut none = true;
+        for item in self.needing_attention() {
+            none = false;
+            let _ = writeln!(
+                out, "- `{}` ({}): {}", item.path.display(), item.label(),
+                Self::attention_line(item)
+            );
+        }
+        if none {
+            out.push_str("None.\n");
+        }
+        out.push_str("\n## Recent errors\n\n");
+        for error in &self.errors {
+            let _ = writeln!(out, "- {}", Self::error_line(error));
+        }
+        if self.errors.is_empty() {
+            out.push_str("None.\n");
+        }
+        out
+    }
+    /// One row per item, with raw byte counts and UTC times for spreadsheets
+    fn csv(&self) -> String {
+        let mut out = String::from(
+            "id,name,path,description,status,versions,last_backup,stored_bytes,original_bytes\n",
+        );
+        for item in &self.items {
+            let fields = [
+                csv_field(&item.id), csv_field(item.name.as_deref().unwrap_or_default()),
+                csv_field(&item.path.display().to_string()),
+                csv_field(item.description.as_deref().unwrap_or_default()),
+                item.health.to_string(), item.versions.to_string(),
+                item.last_backup.map(format_utc).unwrap_or_default(),
+                item.stored_bytes.to_string(), item.original_bytes.to_string(),
+            ];
+            let _ = writeln!(out, "{}", fields.join(","));
+        }
+        out
+    }
+    fn html(&self) -> String {
+        let mut out = String::from(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+             <title>Symor backup report</title>\n<style>\n\
+             body { font-family: sans-serif; }\n\
+             table { border-collapse: collapse; }\n\
+             th, td { border: 1px solid #ccc; padding: 4px 8px; text-align: left; }\n\
+             .stale, .never { color: #b00; }\n.paused { color: #888; }\n\
+             </style>\n</head>\n<body>\n<h1>Symor backup report</h1>\n",
+        );
+        let _ = writeln!(
+            out, "<p>Generated {} on <code>{}</code>.</p>", format_utc(self.generated),
+            html_escape(&self.host)
+        );
+        out.push_str("<h2>Summary</h2>\n<ul>\n");
+        for line in self.summary() {
+            let _ = writeln!(out, "<li>{}</li>", html_escape(&line));
+        }
+        out.push_str("</ul>\n<h2>Items</h2>\n<table>\n");
+        out.push_str(
+            "<tr><th>Item</th><th>Path</th><th>Versions</th><th>Last backup</th>\
+             <th>Stored</th><th>Status</th></tr>\n",
+        );
+        for item in &self.items {
+            let class = match item.health {
+                ItemHealth::Ok => "ok",
+                ItemHealth::Stale => "stale",
+                ItemHealth::NeverBackedUp => "never",
+                ItemHealth::Paused => "paused",
+            };
+            let _ = writeln!(
+                out,
+                "<tr class=\"{}\"><td>{}</td><td><code>{}</code></td><td>{}</td><td>{}</td>\
+                 <td>{}</td><td>{}</td></tr>", class, html_escape(item.label()),
+                html_escape(&item.path.display().to_string()), item.versions,
+                html_escape(&when(item.last_backup)), format_bytes(item.stored_bytes),
+                item.health
+            );
+        }
+        out.push_str("</table>\n");
+        let _ = writeln!(
+            out, "<h2>Items without a backup in the last {}</h2>", describe(self.stale_after)
+        );
+        let attention: Vec<String> = self
+            .needing_attention()
+            .map(|item| {
+                let path = html_escape(&item.path.display().to_string());
+                let line = html_escape(&Self::attention_line(item));
+                format!("<li><code>{}</code> ({}): {}</li>", path, html_escape(item.label()), line)
+            })
+            .collect();
+        if attention.is_empty() {
+            out.push_str("<p>None.</p>\n");
+        } else {
+            let _ = writeln!(out, "<ul>\n{}\n</ul>", attention.join("\n"));
+        }
+        out.push_str("<h2>Recent errors</h2>\n");
+        if self.errors.is_empty() {
+            out.push_str("<p>None.</p>\n");
+        } else {
+            out.push_str("<ul>\n");
+            for error in &self.errors {
+                let _ = writeln!(out, "<li>{}</li>", html_escape(&Self::error_line(error)));
+            }
+            out.push_str("</ul>\n");
+        }
+        out.push_str("</body>\n</html>\n");
+        out
+    }
+}
+impl SymorManager {
+    /// Report on every watched item; items without a backup in `stale_after` are
+    /// flagged, and errors are those mirrors recorded within `stale_after` plus the
+    /// last error and warning lines of the daemon log
+    pub fn backup_report(&self, stale_after: Duration) -> Result<BackupReport> {
+        let now = SystemTime::now();
+        let recent = |time: SystemTime| {
+            now.duration_since(time).map_or(true, |age| age <= stale_after)
+        };
+        let mut items = Vec::new();
+        for item in self.watched_items.values() {
+            let stats = self.item_stats(item)?;
+            let health = match stats.last_backup {
+                _ if item.paused => ItemHealth::Paused,
+                None => ItemHealth::NeverBackedUp,
+                Some(time) if !recent(time) => ItemHealth::Stale,
+                Some(_) => ItemHealth::Ok,
+            };
+            items.push(ItemReport {
+                id: item.id.clone(),
+                name: item.name.clone(),
+                path: item.path.clone(),
+                description: item.description.clone(),
+                versions: stats.versions,
+                last_backup: stats.last_backup,
+                stored_bytes: stats.stored_bytes,
+                original_bytes: stats.original_bytes,
+                health,
+            });
+        }
+        items.sort_by(|a, b| a.path.cmp(&b.path));
+        let home_dir = &self.config.home_dir;
+        let mut errors: Vec<ReportError> = MirrorStatus::load_all(home_dir)
+            .into_iter()
+            .filter(|status| status.last_error_at.is_none_or(recent))
+            .filter_map(|status| {
+                Some(ReportError {
+                    origin: format!("mirror {}", status.source.display()),
+                    at: status.last_error_at,
+                    message: status.last_error?,
+                })
+            })
+            .collect();
+        errors.sort_by_key(|error| error.at);
+        let mut log = LogTail::new(daemon::log_file(home_dir));
+        log.poll()?;
+        let problems: Vec<&String> = log
+            .lines()
+            .filter(|line| line.contains(" ERROR ") || line.contains(" WARN "))
+            .collect();
+        errors.extend(
+            problems[problems.len().saturating_sub(REPORT_LOG_LINES)..]
+                .iter()
+                .map(|line| ReportError {
+                    origin: "daemon log".to_string(),
+                    at: None,
+                    message: line.to_string(),
+                }),
+        );
+        Ok(BackupReport {
+            generated: now,
+            host: hostname::get()
+                .map(|host| host.to_string_lossy().into_owned())
+                .unwrap_or_else(|_| "unknown".to_string()),
+            stale_after,
+            items,
+            errors,
+        })
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+    #[test]
+    fn test_backup_report() {
+        let temp_dir = tempdir().unwrap();
+        let mut manager = SymorManager::new().unwrap();
+        manager.config.home_dir = temp_dir.path().join("home");
+        fs::create_dir_all(manager.config.home_dir.join("logs")).unwrap();
+        manager.version_storage = crate::versioning::storage::VersionStorage::with_config(
+            crate::versioning::storage::StorageConfig {
+                storage_path: temp_dir.path().join("home/versions"),
+                ..Default::default()
+            },
+        );
+        let (notes, old, held) = (
+            temp_dir.path().join("a, notes.txt"), temp_dir.path().join("b.txt"),
+            temp_dir.path().join("c.txt"),
+        );
+        for path in [&notes, &old, &held] {
+            fs::write(path, "data").unwrap();
+        }
+        let notes_id = manager.watch_named(notes, false, Vec::new(), false, None).unwrap();
+        manager.annotate(&notes_id, Some("say \"hi\"".to_string())).unwrap();
+        let old_id = manager.watch_only(old, false, Vec::new(), false).unwrap();
+        let held_id = manager.watch_only(held, false, Vec::new(), false).unwrap();
+        manager.set_paused(&held_id, true).unwrap();
+        let week_ago = SystemTime::now() - Duration::from_secs(8 * 86_400);
+        for version in &mut manager.watched_item_mut(&old_id).unwrap().versions {
+            version.timestamp = week_ago;
+        }
+        MirrorStatus {
+            source: temp_dir.path().join("src"),
+            last_error: Some("disk <full>".to_string()),
+            last_error_at: Some(SystemTime::now()),
+            ..Default::default()
+        }
+            .save(&manager.config.home_dir)
+            .unwrap();
+        fs::write(
+                daemon::log_file(&manager.config.home_dir),
+                "[2026-10-16T10:00:00Z INFO  symor] started\n\
+                 [2026-10-16T10:01:00Z ERROR symor] Backup failed\n",
+            )
+            .unwrap();
+        let stale_after = parse_stale_after("7d").unwrap();
+        assert!(parse_stale_after("soon").is_err() && parse_stale_after("0d").is_err());
+        let report = manager.backup_report(stale_after).unwrap();
+        let health: Vec<_> = report.items.iter().map(|item| item.health).collect();
+        assert_eq!(health, [ItemHealth::Ok, ItemHealth::Stale, ItemHealth::Paused]);
+        assert_eq!(report.needing_attention().count(), 1);
+        assert_eq!(report.errors.len(), 2);
+        assert!(report.errors[1].message.ends_with("Backup failed"));
+        let markdown = report.render(ReportFormat::Markdown);
+        assert!(markdown.contains("Without a backup in the last 7 days: 1"), "{}", markdown);
+        assert!(markdown.contains("b.txt` ("), "{}", markdown);
+        let csv = report.render(ReportFormat::Csv);
+        let row = csv.lines().nth(1).unwrap();
+        assert!(row.contains(",\"say \"\"hi\"\"\",ok,1,"), "{}", row);
+        assert!(row.contains("\"") && row.contains("a, notes.txt\""), "{}", row);
+        let html = report.render(ReportFormat::Html);
+        assert!(html.contains("disk &lt;full&gt;") && html.contains("<tr class=\"stale\">"));
+        assert!(html.contains("b.txt</code> ("), "{}", html);
+        assert_eq!("HTML".parse::<ReportFormat>().unwrap(), ReportFormat::Html);
+        assert!("pdf".parse::<ReportFormat>().is_err());
+    }
+}
\ No newline at end of file
@@ -0,0 +1,162 @@
+use crate::{
+    errors::{ErrorCode, Result, SymorError},
+    tui::format::format_utc,
+    FileVersion, SymorManager,
+};
+use std::time::{Duration, SystemTime};
+/// When `version` leaves a retention `window`; `None` when it is already outside
+pub fn retained_until(version: &FileVersion, window: Option<Duration>) -> Option<SystemTime> {
+    let until = version.timestamp.checked_add(window?)?;
+    (until > SystemTime::now()).then_some(until)
+}
+pub fn is_retained(version: &FileVersion, window: Option<Duration>) -> bool {
+    retained_until(version, window).is_some()
+}
+/// Removes up to `count` of the oldest `versions` outside the retention `window`
+pub(crate) fn take_oldest(
+    versions: &mut Vec<FileVersion>,
+    count: usize,
+    window: Option<Duration>,
+) -> Vec<FileVersion> {
+    let mut removed = Vec::new();
+    let mut index = 0;
+    while removed.len() < count && index < versions.len() {
+        if is_retained(&versions[index], window) {
+            index += 1;
+        } else {
+            removed.push(versions.remove(index));
+        }
+    }
+    removed
+}
+fn locked(message: String) -> SymorError {
+    SymorError::new(ErrorCode::RetentionLocked, message)
+        .with_suggestion(
+            "Versions under the retention lock can be deleted once they are older than \
+             versioning.retention_lock_days"
+                .to_string(),
+        )
+}
+/// The compliance retention lock (write once, read many): with
+/// `versioning.retention_lock_days` set, no version younger than that is deleted by
+/// `sym clean`, the version limit, reclaiming disk space or `sym unwatch --purge`, and
+/// the window cannot be shortened while it still holds versions
+impl SymorManager {
+    /// The retention window, `None` while the lock is off
+    pub fn retention_window(&self) -> Option<Duration> {
+        let days = self.config.versioning.retention_lock_days;
+        (days > 0).then(|| Duration::from_secs(days * 86_400))
+    }
+    pub fn is_retained(&self, version: &FileVersion) -> bool {
+        is_retained(version, self.retention_window())
+    }
+    /// Fails with `RetentionLocked` when any of `versions` is under the retention lock,
+    /// naming `operation` and when the last of them is released
+    pub fn ensure_deletable<'a>(
+        &self,
+        versions: impl IntoIterator<Item = &'a FileVersion>,
+        operation: &str,
+    ) -> Result<()> {
+        let window = self.retention_window();
+        let held: Vec<SystemTime> = versions
+            .into_iter()
+            .filter_map(|version| retained_until(version, window))
+            .collect();
+        let Some(last) = held.iter().max() else {
+            return Ok(());
+        };
+        Err(
+            locked(
+                format!(
+                    "Cannot {}: {} version(s) are under the {}-day retention lock until {}",
+                    operation, held.len(), self.config.versioning.retention_lock_days,
+                    format_utc(*last)
+                ),
+            ),
+        )
+    }
+    /// Refuses to shorten or switch off the retention lock to `days` while that would
+    /// release versions it holds now
+    pub(crate) fn check_retention_change(&self, days: u64) -> Result<()> {
+        let current = self.config.versioning.retention_lock_days;
+        if current == 0 || (days != 0 && days >= current) {
+            return Ok(());
+        }
+        let shorter = (days > 0).then(|| Duration::from_secs(days * 86_400));
+        let window = self.retention_window();
+        let released = self
+            .watched_items
+            .values()
+            .flat_map(|item| &item.versions)
+            .filter(|version| is_retained(version, window) && !is_retained(version, shorter))
+            .count();
+        if released == 0 {
+            return Ok(());
+        }
+        Err(
+            locked(
+                format!(
+                    "Cannot shorten the {}-day retention lock to {} days: it still holds {} \
+                     version(s) that would be released",
+                    current, days, released
+                ),
+            ),
+        )
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+    #[test]
+    fn test_retention_lock() {
+        let temp_dir = tempdir().unwrap();
+        let mut manager = SymorManager::new().unwrap();
+        manager.config.home_dir = temp_dir.path().join("home");
+        fs::create_dir_all(&manager.config.home_dir).unwrap();
+        manager.version_storage = crate::versioning::storage::VersionStorage::with_config(
+            crate::versioning::storage::StorageConfig {
+                storage_path: temp_dir.path().join("home/versions"),
+                ..Default::default()
+            },
+        );
+        manager.config.versioning.max_versions = 2;
+        let path = temp_dir.path().join("ledger.csv");
+        fs::write(&path, "v0").unwrap();
+        let id = manager.watch_only(path.clone(), false, Vec::new(), false).unwrap();
+        let old = SystemTime::now() - Duration::from_secs(40 * 86_400);
+        manager.watched_item_mut(&id).unwrap().versions[0].timestamp = old;
+        manager.update_config(|config| config.versioning.retention_lock_days = 30).unwrap();
+        for n in 1..4 {
+            fs::write(&path, format!("v{}", n)).unwrap();
+            manager.create_backup(&id).unwrap();
+        }
+        let versions = manager.watched_item(&id).unwrap().versions.to_vec();
+        assert_eq!(versions.len(), 3);
+        assert!(versions.iter().all(|version| manager.is_retained(version)));
+        assert!(versions.iter().all(|version| manager.version_storage.contains(&version.id)));
+        assert_eq!(manager.reclaim_version_space().unwrap(), 0);
+        let error = manager.purge_versions(manager.watched_item(&id).unwrap()).unwrap_err();
+        assert_eq!(error.code, ErrorCode::RetentionLocked);
+        assert!(error.message.contains("3 version(s)"), "{}", error.message);
+        let days = |n: u64| SystemTime::now() - Duration::from_secs(n * 86_400);
+        manager.watched_item_mut(&id).unwrap().versions[0].timestamp = days(10);
+        let shorten = manager.update_config(|config| config.versioning.retention_lock_days = 7);
+        assert_eq!(shorten.unwrap_err().code, ErrorCode::RetentionLocked);
+        assert_eq!(manager.config().versioning.retention_lock_days, 30);
+        manager.update_config(|config| config.versioning.retention_lock_days = 60).unwrap();
+        let mut aged = versions.clone();
+        aged[0].timestamp = days(100);
+        aged[1].timestamp = days(40);
+        assert_eq!(take_oldest(&mut aged.clone(), 2, manager.retention_window()).len(), 1);
+        assert_eq!(take_oldest(&mut aged.clone(), 2, None).len(), 2);
+        let off = manager.update_config(|config| config.versioning.retention_lock_days = 0);
+        assert!(off.is_err());
+        let expired = crate::WatchedItem {
+            versions: aged[..1].to_vec().into(),
+            ..manager.watched_item(&id).unwrap().clone()
+        };
+        assert_eq!(manager.purge_versions(&expired).unwrap(), 1);
+    }
+}
\ No newline at end of file
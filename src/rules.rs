@@ -0,0 +1,197 @@
+use crate::{
+    conflicts::{Conflict, Resolution},
+    errors::{Context, ErrorCode, Result, SymorError},
+    SymorManager,
+};
+use log::warn;
+use rhai::{Dynamic, Engine, Map, Scope, AST};
+use std::{fs, path::{Path, PathBuf}, time::UNIX_EPOCH};
+/// Most operations one rule call may run, so a runaway loop cannot stall a sync
+const MAX_OPERATIONS: u64 = 1_000_000;
+pub fn rules_file(home_dir: &Path) -> PathBuf {
+    home_dir.join("rules.rhai")
+}
+/// Sync rules written in Rhai, from `rules.rhai` in the home directory. A script may
+/// define `include(file)`, returning false for files that mirrors skip and the daemon
+/// does not version, and `resolve(conflict)`, returning `"keep-source"`,
+/// `"keep-target"` or `"keep-both"` for a two-way edit conflict, or nothing to
+/// quarantine it as usual. A rule that fails is logged and treated as undefined.
+pub struct SyncRules {
+    engine: Engine,
+    ast: AST,
+    include: bool,
+    resolve: bool,
+}
+impl std::fmt::Debug for SyncRules {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SyncRules")
+            .field("include", &self.include)
+            .field("resolve", &self.resolve)
+            .finish()
+    }
+}
+/// Size and modification time (seconds since the epoch) of `path`, 0 when missing
+fn stat(path: &Path) -> (i64, i64) {
+    let Ok(metadata) = fs::metadata(path) else {
+        return (0, 0);
+    };
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |age| age.as_secs() as i64);
+    (metadata.len() as i64, modified)
+}
+fn text(path: &Path) -> Dynamic {
+    path.to_string_lossy().into_owned().into()
+}
+impl SyncRules {
+    pub fn compile(script: &str) -> Result<Self> {
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        let ast = engine
+            .compile(script)
+            .map_err(|e| {
+                let message = format!("Invalid sync rules: {}", e);
+                SymorError::new(ErrorCode::InvalidConfiguration, message)
+            })?;
+        let defines = |name: &str| {
+            ast.iter_functions().any(|f| f.name == name && f.params.len() == 1)
+        };
+        let (include, resolve) = (defines("include"), defines("resolve"));
+        if !include && !resolve {
+            return Err(
+                SymorError::new(
+                        ErrorCode::InvalidConfiguration,
+                        "Sync rules define neither include(file) nor resolve(conflict)"
+                            .to_string(),
+                    )
+                    .with_suggestion("Define fn include(file) { ... } in the script".to_string()),
+            );
+        }
+        Ok(Self { engine, ast, include, resolve })
+    }
+    pub fn load(path: &Path) -> Result<Self> {
+        let script = fs::read_to_string(path)
+            .with_context(|| format!("cannot read sync rules {:?}", path))?;
+        Self::compile(&script)
+            .map_err(|e| {
+                let message = format!("{:?}: {}", path, e.message);
+                SymorError::new(ErrorCode::InvalidConfiguration, message)
+            })
+    }
+    fn call(&self, name: &str, arg: Map) -> Option<Dynamic> {
+        let result = self
+            .engine
+            .call_fn::<Dynamic>(&mut Scope::new(), &self.ast, name, (Dynamic::from_map(arg),));
+        result.map_err(|e| warn!("Sync rule {}() failed: {}", name, e)).ok()
+    }
+    /// Whether `include(file)` lets `path` through; `relative` is its path inside the
+    /// mirrored or watched root
+    pub fn includes(&self, path: &Path, relative: &Path) -> bool {
+        if !self.include {
+            return true;
+        }
+        let (size, modified) = stat(path);
+        let name = path.file_name().map(|n| n.to_string_lossy().into_owned());
+        let ext = path.extension().map(|e| e.to_string_lossy().to_lowercase());
+        let file = Map::from_iter([
+            ("path".into(), text(path)), ("relative".into(), text(relative)),
+            ("name".into(), name.unwrap_or_default().into()),
+            ("ext".into(), ext.unwrap_or_default().into()), ("size".into(), size.into()),
+            ("modified".into(), modified.into()),
+        ]);
+        match self.call("include", file).map(|result| result.as_bool()) {
+            Some(Ok(include)) => include,
+            Some(Err(kind)) => {
+                warn!("Sync rule include() returned {} instead of a bool", kind);
+                true
+            }
+            None => true,
+        }
+    }
+    /// The resolution `resolve(conflict)` picks for a conflict with a target, if any
+    pub fn resolution(&self, conflict: &Conflict) -> Option<Resolution> {
+        let target = conflict.target.as_deref().filter(|_| self.resolve)?;
+        let ((source_size, source_modified), (target_size, target_modified)) = (
+            stat(&conflict.source),
+            stat(target),
+        );
+        let arg = Map::from_iter([
+            ("source".into(), text(&conflict.source)), ("target".into(), text(target)),
+            ("source_size".into(), source_size.into()), ("target_size".into(), target_size
+            .into()), ("source_modified".into(), source_modified.into()), ("target_modified"
+            .into(), target_modified.into()),
+        ]);
+        let result = self.call("resolve", arg)?;
+        if result.is_unit() {
+            return None;
+        }
+        let choice = result.into_string().unwrap_or_default();
+        serde_json::from_value(serde_json::Value::String(choice.clone()))
+            .map_err(|_| warn!("Sync rule resolve() returned unknown resolution {:?}", choice))
+            .ok()
+    }
+}
+impl SymorManager {
+    /// The sync rules in the home directory, or `None` when there is no rules file
+    pub fn load_sync_rules(&self) -> Result<Option<SyncRules>> {
+        let path = rules_file(&self.config.home_dir);
+        if !path.exists() {
+            return Ok(None);
+        }
+        SyncRules::load(&path).map(Some)
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conflicts::ConflictKind;
+    use tempfile::tempdir;
+    #[test]
+    fn test_sync_rules() {
+        let temp_dir = tempdir().unwrap();
+        let (big, dump) = (temp_dir.path().join("big.iso"), temp_dir.path().join("db.SQL"));
+        for path in [&big, &dump] {
+            fs::File::create(path).unwrap().set_len(200).unwrap();
+        }
+        let rules = SyncRules::compile(
+                r#"
+fn include(file) { file.size <= 100 || file.ext == "sql" }
+fn resolve(conflict) {
+    if conflict.source_size > conflict.target_size { "keep-source" }
+    else if conflict.target_size == 0 { "nonsense" }
+}
+"#,
+            )
+            .unwrap();
+        assert!(!rules.includes(&big, Path::new("big.iso")));
+        assert!(rules.includes(&dump, Path::new("db.SQL")));
+        assert!(rules.includes(&temp_dir.path().join("missing"), Path::new("missing")));
+        let mut conflict = Conflict {
+            kind: ConflictKind::Diverged,
+            source: big.clone(),
+            target: Some(temp_dir.path().join("missing")),
+            item_id: None,
+            edited: None,
+        };
+        assert_eq!(rules.resolution(&conflict), Some(Resolution::KeepSource));
+        conflict.source = temp_dir.path().join("missing");
+        assert_eq!(rules.resolution(&conflict), None);
+        conflict.target = Some(dump);
+        assert_eq!(rules.resolution(&conflict), None);
+        assert!(SyncRules::compile("fn other(x) { true }").is_err());
+        assert!(SyncRules::compile("fn include(file) {").is_err());
+        let looping = SyncRules::compile("fn include(file) { loop {} }").unwrap();
+        assert!(looping.includes(&big, Path::new("big.iso")));
+        let target = temp_dir.path().join("copy");
+        let mirror = crate::Mirror::builder(temp_dir.path())
+            .target(&target)
+            .exclude("copy")
+            .rules(std::sync::Arc::new(rules))
+            .build()
+            .unwrap();
+        mirror.sync_once().unwrap();
+        assert!(target.join("db.SQL").exists() && !target.join("big.iso").exists());
+    }
+}
\ No newline at end of file
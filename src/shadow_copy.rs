@@ -0,0 +1,130 @@
+use crate::errors::{Context, Result};
+use log::warn;
+use std::{fs, io, path::Path};
+/// Windows errors for a file another process holds open exclusively
+const ERROR_SHARING_VIOLATION: i32 = 32;
+const ERROR_LOCK_VIOLATION: i32 = 33;
+pub fn is_sharing_violation(error: &io::Error) -> bool {
+    cfg!(windows)
+        && matches!(
+            error.raw_os_error(), Some(ERROR_SHARING_VIOLATION) |
+            Some(ERROR_LOCK_VIOLATION)
+        )
+}
+/// Reads a file, going through a Volume Shadow Copy snapshot when `use_shadow_copy` is set
+/// and another process has it locked (Outlook PSTs, open databases)
+pub fn read_file(path: &Path, use_shadow_copy: bool) -> Result<Vec<u8>> {
+    match fs::read(crate::long_path::extend(path)) {
+        Ok(data) => Ok(data),
+        Err(e) if use_shadow_copy && is_sharing_violation(&e) => {
+            warn!("{:?} is locked by another process; reading it from a shadow copy", path);
+            read_from_snapshot(path)
+        }
+        Err(e) => Err(e).with_context(|| format!("cannot read {:?}", path)),
+    }
+}
+#[cfg(windows)]
+fn read_from_snapshot(path: &Path) -> Result<Vec<u8>> {
+    let path = std::path::absolute(path)?;
+    let mut components = path.components();
+    let volume: std::path::PathBuf = components.by_ref().take(2).collect();
+    let snapshot = windows::Snapshot::create(&volume)?;
+    let shadow_path = snapshot.device_object.join(components.as_path());
+    fs::read(&shadow_path)
+        .with_context(|| format!("cannot read {:?} from shadow copy", path))
+}
+#[cfg(not(windows))]
+fn read_from_snapshot(path: &Path) -> Result<Vec<u8>> {
+    Err(io::Error::from(io::ErrorKind::Unsupported))
+        .with_context(|| format!("shadow copies are only available on Windows ({:?})", path))
+}
+#[cfg(windows)]
+mod windows {
+    use crate::errors::{ErrorCode, Result, SymorError};
+    use log::{debug, warn};
+    use std::{path::{Path, PathBuf}, process::Command};
+    /// A client-accessible shadow copy, deleted when dropped
+    pub struct Snapshot {
+        id: String,
+        pub device_object: PathBuf,
+    }
+    fn powershell(script: &str) -> Result<String> {
+        let output = Command::new("powershell")
+            .args(["-NoProfile", "-NonInteractive", "-Command", script])
+            .output()?;
+        if !output.status.success() {
+            return Err(
+                SymorError::new(
+                        ErrorCode::PermissionDenied,
+                        format!(
+                            "Creating a shadow copy failed: {}",
+                            String::from_utf8_lossy(&output.stderr).trim()
+                        ),
+                    )
+                    .with_suggestion(
+                        "Shadow copies need administrator rights; run symor (or its service) elevated"
+                            .to_string(),
+                    ),
+            );
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+    impl Snapshot {
+        pub fn create(volume: &Path) -> Result<Self> {
+            let volume = volume.display().to_string().replace('\'', "''");
+            let script = format!(
+                "$r = (Get-WmiObject -List Win32_ShadowCopy).Create('{}', 'ClientAccessible'); \
+                 if ($r.ReturnValue -ne 0) {{ exit $r.ReturnValue }}; \
+                 $s = Get-WmiObject Win32_ShadowCopy | Where-Object {{ $_.ID -eq $r.ShadowID }}; \
+                 Write-Output $s.ID; Write-Output $s.DeviceObject",
+                volume
+            );
+            let output = powershell(&script)?;
+            let mut lines = output.lines().map(str::trim).filter(|l| !l.is_empty());
+            match (lines.next(), lines.next()) {
+                (Some(id), Some(device)) => {
+                    debug!("Created shadow copy {} at {}", id, device);
+                    Ok(Self {
+                        id: id.to_string(),
+                        device_object: PathBuf::from(format!("{}\\", device)),
+                    })
+                }
+                _ => {
+                    Err(
+                        SymorError::new(
+                            ErrorCode::InternalError,
+                            "Shadow copy was created but could not be located".to_string(),
+                        ),
+                    )
+                }
+            }
+        }
+    }
+    impl Drop for Snapshot {
+        fn drop(&mut self) {
+            let result = Command::new("vssadmin")
+                .args(["delete", "shadows", &format!("/Shadow={}", self.id), "/Quiet"])
+                .output();
+            if !result.is_ok_and(|output| output.status.success()) {
+                warn!("Could not delete shadow copy {}", self.id);
+            }
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::ErrorCode;
+    use tempfile::tempdir;
+    #[test]
+    fn test_read_file_without_lock() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("data.pst");
+        fs::write(&path, b"mail").unwrap();
+        assert_eq!(read_file(&path, true).unwrap(), b"mail");
+        let err = read_file(&temp_dir.path().join("missing.pst"), true).unwrap_err();
+        assert_eq!(err.code(), & ErrorCode::FileNotFound);
+        let locked = io::Error::from_raw_os_error(ERROR_SHARING_VIOLATION);
+        assert_eq!(is_sharing_violation(& locked), cfg!(windows));
+    }
+}
@@ -0,0 +1,75 @@
+use std::time::{Duration, Instant};
+const WINDOW: Duration = Duration::from_secs(1);
+/// How long a storm must stay quiet before the one rescan that replaces its events
+pub const SETTLE: Duration = Duration::from_secs(2);
+/// Change events per second a mirror takes one by one before treating them as a storm
+pub const DEFAULT_THRESHOLD: u32 = 200;
+/// Notices bursts of change events, such as a `git checkout` or `npm install` inside a
+/// mirrored tree, so the mirror can wait them out and rescan once instead
+#[derive(Debug, Clone, Default)]
+pub struct StormDetector {
+    /// 0 never detects a storm
+    threshold: u32,
+    window_start: Option<Instant>,
+    in_window: u32,
+    /// Events since the storm began, while one is going on
+    storm: Option<u64>,
+}
+impl StormDetector {
+    pub fn new(threshold: u32) -> Self {
+        Self {
+            threshold,
+            ..Self::default()
+        }
+    }
+    pub fn threshold(&self) -> u32 {
+        self.threshold
+    }
+    pub fn in_storm(&self) -> bool {
+        self.storm.is_some()
+    }
+    /// Counts an event at `now`; true for the event that starts a storm
+    pub fn record(&mut self, now: Instant) -> bool {
+        if let Some(events) = &mut self.storm {
+            *events += 1;
+            return false;
+        }
+        if self.window_start.is_none_or(|start| now.duration_since(start) >= WINDOW) {
+            self.window_start = Some(now);
+            self.in_window = 0;
+        }
+        self.in_window += 1;
+        if self.threshold == 0 || self.in_window <= self.threshold {
+            return false;
+        }
+        self.storm = Some(u64::from(self.in_window));
+        true
+    }
+    /// Ends the storm once it settled; returns how many events it had
+    pub fn finish(&mut self) -> Option<u64> {
+        self.window_start = None;
+        self.in_window = 0;
+        self.storm.take()
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_storm_detector() {
+        let start = Instant::now();
+        let mut never = StormDetector::new(0);
+        assert!(!(0..1000).any(|_| never.record(start)));
+        let mut detector = StormDetector::new(3);
+        for millis in [0, 400, 800, 1200, 1300, 1400] {
+            assert!(!detector.record(start + Duration::from_millis(millis)));
+        }
+        assert!(!detector.in_storm());
+        assert!(detector.record(start + Duration::from_millis(1500)));
+        assert!(!detector.record(start + Duration::from_millis(1600)));
+        assert!(detector.in_storm());
+        assert_eq!(detector.finish(), Some(5));
+        assert!(!detector.in_storm() && detector.finish().is_none());
+        assert!(!detector.record(start + Duration::from_millis(1700)));
+    }
+}
\ No newline at end of file
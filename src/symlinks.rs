@@ -0,0 +1,156 @@
+use crate::{
+    errors::{ErrorCode, Result, SymorError},
+    long_path::extend,
+};
+use serde::{Deserialize, Serialize};
+use std::{fmt, fs, path::{Path, PathBuf}, str::FromStr};
+/// What mirrors and versions do with symlinks (and, on Windows, junctions)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SymlinkMode {
+    /// Copy or version what the link points to, as if it were there
+    #[default]
+    Follow,
+    /// Recreate the link itself, pointing where the original does
+    Preserve,
+    /// Leave links out
+    Skip,
+}
+impl SymlinkMode {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+impl FromStr for SymlinkMode {
+    type Err = SymorError;
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "follow" => Ok(SymlinkMode::Follow),
+            "preserve" => Ok(SymlinkMode::Preserve),
+            "skip" => Ok(SymlinkMode::Skip),
+            other => {
+                Err(
+                    SymorError::new(
+                        ErrorCode::InvalidConfiguration,
+                        format!("Unknown symlink mode: {}", other),
+                    )
+                        .with_suggestion("Use 'follow', 'preserve' or 'skip'".to_string()),
+                )
+            }
+        }
+    }
+}
+impl fmt::Display for SymlinkMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SymlinkMode::Follow => write!(f, "follow"),
+            SymlinkMode::Preserve => write!(f, "preserve"),
+            SymlinkMode::Skip => write!(f, "skip"),
+        }
+    }
+}
+/// Where the symlink at `path` points, as written in the link; `None` when `path` is not
+/// a symlink
+pub fn link_target(path: &Path) -> Option<PathBuf> {
+    let metadata = fs::symlink_metadata(extend(path)).ok()?;
+    if !metadata.file_type().is_symlink() {
+        return None;
+    }
+    fs::read_link(extend(path)).ok()
+}
+/// Content a version of a preserved link stores: the path it points to
+pub fn target_content(target: &Path) -> &[u8] {
+    target.as_os_str().as_encoded_bytes()
+}
+/// Hash of a version of a preserved link pointing at `target`
+pub fn target_hash(target: &Path) -> String {
+    format!("{:x}", md5::compute(target_content(target)))
+}
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::symlink;
+    #[test]
+    fn test_symlink_modes() {
+        assert_eq!("Skip".parse::<SymlinkMode>().unwrap(), SymlinkMode::Skip);
+        assert!("copy".parse::<SymlinkMode>().is_err());
+        assert!(SymlinkMode::Follow.is_default());
+        let temp_dir = tempfile::tempdir().unwrap();
+        let source = temp_dir.path().join("src");
+        fs::create_dir_all(source.join("dir")).unwrap();
+        fs::write(source.join("dir/file.txt"), "data").unwrap();
+        symlink("dir/file.txt", source.join("file-link")).unwrap();
+        symlink("dir", source.join("dir-link")).unwrap();
+        symlink(".", source.join("loop")).unwrap();
+        symlink("missing", source.join("dangling")).unwrap();
+        assert_eq!(link_target(&source.join("file-link")).unwrap(), Path::new("dir/file.txt"));
+        assert_eq!(link_target(&source.join("dir")), None);
+        for mode in [SymlinkMode::Follow, SymlinkMode::Preserve, SymlinkMode::Skip] {
+            let target = temp_dir.path().join(mode.to_string());
+            let mirror = crate::Mirror::builder(&source)
+                .target(&target)
+                .symlinks(mode)
+                .build()
+                .unwrap();
+            mirror.sync_once().unwrap();
+            mirror.sync_once().unwrap();
+            let mut names: Vec<_> = fs::read_dir(&target)
+                .unwrap()
+                .map(|entry| entry.unwrap().file_name())
+                .collect();
+            names.sort();
+            match mode {
+                SymlinkMode::Follow => {
+                    assert_eq!(names, ["dir", "dir-link", "file-link"]);
+                    assert!(link_target(&target.join("file-link")).is_none());
+                    let followed = fs::read_to_string(target.join("dir-link/file.txt"));
+                    assert_eq!(followed.unwrap(), "data");
+                }
+                SymlinkMode::Preserve => {
+                    assert_eq!(names, ["dangling", "dir", "dir-link", "file-link", "loop"]);
+                    assert_eq!(link_target(&target.join("dir-link")).unwrap(), Path::new("dir"));
+                    assert_eq!(fs::read_to_string(target.join("file-link")).unwrap(), "data");
+                }
+                SymlinkMode::Skip => assert_eq!(names, ["dir"]),
+            }
+        }
+        for (name, mode) in [("follow", SymlinkMode::Skip), ("preserve", SymlinkMode::Follow)] {
+            let target = temp_dir.path().join(name);
+            let mirror = crate::Mirror::builder(&source)
+                .target(&target)
+                .symlinks(mode)
+                .build()
+                .unwrap();
+            mirror.sync_once().unwrap();
+            assert_eq!(target.join("file-link").exists(), mode == SymlinkMode::Follow);
+            assert!(link_target(&target.join("file-link")).is_none());
+        }
+        assert_eq!(fs::read_to_string(source.join("dir/file.txt")).unwrap(), "data");
+        let mut manager = crate::SymorManager::new().unwrap();
+        manager.config.home_dir = temp_dir.path().join("home");
+        manager.version_storage = crate::versioning::storage::VersionStorage::with_config(
+            crate::versioning::storage::StorageConfig {
+                storage_path: temp_dir.path().join("home/versions"),
+                ..Default::default()
+            },
+        );
+        manager.config.versioning.symlinks = SymlinkMode::Preserve;
+        let link = source.join("file-link");
+        let id = manager.watch_only(link.clone(), false, Vec::new(), false).unwrap();
+        let version = manager.watched_item(&id).unwrap().versions[0].clone();
+        assert_eq!(version.hash, target_hash(Path::new("dir/file.txt")));
+        let metadata = manager.version_storage.load_metadata(&version.id).unwrap();
+        assert_eq!(metadata.link_target.as_deref(), Some(Path::new("dir/file.txt")));
+        let restored = source.join("restored");
+        fs::write(&restored, "old").unwrap();
+        let result = manager.restore_file(&id, &version.id, &restored).unwrap();
+        assert!(result.verified && result.backup_created);
+        assert_eq!(fs::read_to_string(&restored).unwrap(), "data");
+        assert_eq!(link_target(&restored).unwrap(), Path::new("dir/file.txt"));
+        let backup = restored.with_extension(".pre-restore");
+        assert_eq!(fs::read_to_string(backup).unwrap(), "old");
+        manager.config.versioning.symlinks = SymlinkMode::Skip;
+        manager.create_backup(&id).unwrap();
+        assert_eq!(manager.watched_item(&id).unwrap().versions.len(), 1);
+    }
+}
\ No newline at end of file
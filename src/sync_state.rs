@@ -0,0 +1,177 @@
+use crate::{
+    errors::Result, merge::{self, LineMerge},
+    versioning::storage::{StorageConfig, VersionStorage},
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs, path::{Path, PathBuf}, time::SystemTime};
+/// Largest text file whose synced contents are kept as a merge base
+const MAX_BASE_SIZE: u64 = 1 << 20;
+/// Size and modification time of a file, enough to tell whether it was edited
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileStamp {
+    pub size: u64,
+    pub modified: SystemTime,
+}
+impl FileStamp {
+    pub fn of(path: &Path) -> Option<Self> {
+        let metadata = fs::metadata(path).ok()?;
+        Some(Self {
+            size: metadata.len(),
+            modified: metadata.modified().ok()?,
+        })
+    }
+}
+/// Both sides of a mirrored file right after they were last synced
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncedPair {
+    pub source: PathBuf,
+    pub source_stamp: FileStamp,
+    pub target_stamp: FileStamp,
+}
+/// What a mirror's files looked like after its last successful sync, so edits made
+/// since then can be told apart from ones the next sync will carry over
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SyncState {
+    pub source: PathBuf,
+    pub synced_at: Option<SystemTime>,
+    /// Keyed by target file
+    pub files: BTreeMap<PathBuf, SyncedPair>,
+    /// Home the state was loaded from, where merge bases are kept
+    #[serde(skip)]
+    home_dir: Option<PathBuf>,
+}
+pub fn state_dir(home_dir: &Path) -> PathBuf {
+    home_dir.join("sync-state")
+}
+fn path_key(path: &Path) -> String {
+    format!("{:x}", md5::compute(path.to_string_lossy().as_bytes()))
+}
+fn state_file(home_dir: &Path, source: &Path) -> PathBuf {
+    state_dir(home_dir).join(format!("{}.json", path_key(source)))
+}
+/// Version store holding the last synced contents of two-way mirror files, one
+/// version per target file
+fn base_storage(home_dir: &Path) -> VersionStorage {
+    VersionStorage::with_config(StorageConfig {
+        compression_level: 6,
+        max_versions_per_file: 1,
+        storage_path: state_dir(home_dir).join("bases"),
+        ..Default::default()
+    })
+}
+/// Contents of `path` if it is a text file small enough to merge
+fn read_text(path: &Path) -> Option<String> {
+    if fs::metadata(path).ok()?.len() > MAX_BASE_SIZE {
+        return None;
+    }
+    fs::read_to_string(path).ok().filter(|text| !text.contains('\0'))
+}
+/// Keeps what `target` holds right after a sync as the base for merging later edits;
+/// files that are not text have none
+pub fn save_base(home_dir: &Path, target: &Path) -> Result<()> {
+    let storage = base_storage(home_dir);
+    match read_text(target) {
+        Some(text) => storage.store_version(target, text.as_bytes(), &path_key(target)).map(drop),
+        None => storage.delete_version(&path_key(target)),
+    }
+}
+impl SyncState {
+    pub fn new(source: &Path) -> Self {
+        Self {
+            source: source.to_path_buf(),
+            ..Self::default()
+        }
+    }
+    /// State of the mirror with `source`, if it has synced since state was kept
+    pub fn load(home_dir: &Path, source: &Path) -> Option<Self> {
+        let data = fs::read_to_string(state_file(home_dir, source)).ok()?;
+        let state: Self = serde_json::from_str(&data).ok()?;
+        Some(Self {
+            home_dir: Some(home_dir.to_path_buf()),
+            ..state
+        })
+    }
+    pub fn save(&self, home_dir: &Path) -> Result<()> {
+        fs::create_dir_all(state_dir(home_dir))?;
+        fs::write(state_file(home_dir, &self.source), serde_json::to_string(self)?)?;
+        Ok(())
+    }
+    /// Notes that `source` and `target` hold the same content as of now
+    pub fn record(&mut self, source: &Path, target: &Path) {
+        if let (Some(source_stamp), Some(target_stamp)) = (
+            FileStamp::of(source),
+            FileStamp::of(target),
+        ) {
+            let pair = SyncedPair {
+                source: source.to_path_buf(),
+                source_stamp,
+                target_stamp,
+            };
+            self.files.insert(target.to_path_buf(), pair);
+        }
+    }
+    /// Whether the source and the target were edited since they were last synced, or
+    /// `None` when the pair was never recorded
+    pub fn edits(&self, source: &Path, target: &Path) -> Option<(bool, bool)> {
+        let pair = self.files.get(target).filter(|pair| pair.source == source)?;
+        Some((
+            FileStamp::of(source) != Some(pair.source_stamp),
+            FileStamp::of(target) != Some(pair.target_stamp),
+        ))
+    }
+    /// Three-way merge of the current `source` and `target` text against what they
+    /// held at the last sync, or `None` without a text base for the pair
+    pub fn merge(&self, source: &Path, target: &Path) -> Option<LineMerge> {
+        let home_dir = self.home_dir.as_deref()?;
+        self.files.get(target).filter(|pair| pair.source == source)?;
+        let (base, _) = base_storage(home_dir).retrieve_version(&path_key(target)).ok()?;
+        let base = String::from_utf8(base).ok()?;
+        merge::merge_lines(&base, &read_text(source)?, &read_text(target)?)
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tempfile::tempdir;
+    #[test]
+    fn test_sync_state() {
+        let temp_dir = tempdir().unwrap();
+        let (source, target) = (temp_dir.path().join("a.txt"), temp_dir.path().join("b.txt"));
+        fs::write(&source, "same").unwrap();
+        fs::write(&target, "same").unwrap();
+        let mut state = SyncState::new(temp_dir.path());
+        assert_eq!(state.edits(&source, &target), None);
+        state.record(&source, &target);
+        state.save(temp_dir.path()).unwrap();
+        let state = SyncState::load(temp_dir.path(), temp_dir.path()).unwrap();
+        assert_eq!(state.edits(&source, &target), Some((false, false)));
+        assert_eq!(state.edits(&target, &target), None);
+        fs::write(&target, "edited").unwrap();
+        assert_eq!(state.edits(&source, &target), Some((false, true)));
+        let later = SystemTime::now() + Duration::from_secs(5);
+        fs::File::options().write(true).open(&source).unwrap().set_modified(later).unwrap();
+        assert_eq!(state.edits(&source, &target), Some((true, true)));
+    }
+    #[test]
+    fn test_merge_against_base() {
+        let temp_dir = tempdir().unwrap();
+        let (source, target) = (temp_dir.path().join("a.txt"), temp_dir.path().join("b.txt"));
+        fs::write(&source, "one\ntwo\nthree\n").unwrap();
+        fs::write(&target, "one\ntwo\nthree\n").unwrap();
+        let mut state = SyncState::new(&source);
+        state.record(&source, &target);
+        state.save(temp_dir.path()).unwrap();
+        save_base(temp_dir.path(), &target).unwrap();
+        assert_eq!(state.merge(&source, &target), None);
+        let state = SyncState::load(temp_dir.path(), &source).unwrap();
+        fs::write(&source, "ONE\ntwo\nthree\n").unwrap();
+        fs::write(&target, "one\ntwo\nTHREE\n").unwrap();
+        assert_eq!(
+            state.merge(&source, &target),
+            Some(LineMerge::Clean("ONE\ntwo\nTHREE\n".to_string()))
+        );
+        fs::write(&target, "uno\ntwo\nthree\n").unwrap();
+        assert_eq!(state.merge(&source, &target), Some(LineMerge::Overlapping(1)));
+    }
+}
\ No newline at end of file
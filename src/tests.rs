@@ -56,9 +56,12 @@ mod tests {
         let mut manager = SymorManager::new().unwrap();
         let options = versioning::restore::RestoreOptions {
             preserve_permissions: false,
+            preserve_ownership: false,
+            owner: None,
             create_backup: true,
             backup_suffix: ".bak".to_string(),
             atomic_restore: true,
+            expected_hash: None,
         };
         manager.restore_engine.restore_file(&backup_file, content, &options).unwrap();
         let restored_content = fs::read(&backup_file).unwrap();
@@ -66,17 +69,17 @@ mod tests {
     }
     #[test]
     fn test_error_recovery_integration() {
-        use crate::errors::recovery::ErrorRecovery;
-        let recovery = ErrorRecovery::new();
+        use crate::errors::{recovery::ErrorRecovery, ErrorCode, SymorError};
+        let recovery = ErrorRecovery::default();
         let mut attempt_count = 0;
-        let result: Result<String, _> = tokio_test::block_on(
+        let result: crate::errors::Result<String> = tokio_test::block_on(
             recovery
                 .execute_recovery(
                     "FileNotFound",
                     || {
                         attempt_count += 1;
                         if attempt_count < 2 {
-                            Err(anyhow::anyhow!("File not found"))
+                            Err(SymorError::new(ErrorCode::FileNotFound, "missing".to_string()))
                         } else {
                             Ok("success".to_string())
                         }
@@ -114,6 +117,7 @@ mod tests {
             max_versions: Some(50),
             compression: Some(6),
             link_type: Some("copy".to_string()),
+            ..Default::default()
         };
         let config = template_manager
             .create_from_template("development", &overrides)
@@ -176,4 +180,314 @@ mod tests {
         manager.get_info(&source_file).unwrap();
         manager.list_watched(false).unwrap();
     }
+    #[test]
+    fn test_reclaim_version_space() {
+        let temp_dir = tempdir().unwrap();
+        let test_file = temp_dir.path().join("reclaim.txt");
+        fs::write(&test_file, "v1").unwrap();
+        let mut manager = SymorManager::new().unwrap();
+        manager.config.home_dir = temp_dir.path().join("home");
+        fs::create_dir_all(&manager.config.home_dir).unwrap();
+        manager.version_storage = versioning::storage::VersionStorage::with_config(
+            versioning::storage::StorageConfig {
+                storage_path: temp_dir.path().join("versions"),
+                ..Default::default()
+            },
+        );
+        let file_id = manager.watch(test_file.clone(), false).unwrap();
+        fs::write(&test_file, "v2").unwrap();
+        manager.create_backup(&file_id).unwrap();
+        fs::write(&test_file, "v3").unwrap();
+        manager.create_backup(&file_id).unwrap();
+        assert_eq!(manager.watched_items[& file_id].versions.len(), 3);
+        manager.config.versioning.max_versions = 2;
+        assert_eq!(manager.reclaim_version_space().unwrap(), 1);
+        assert_eq!(manager.reclaim_version_space().unwrap(), 1);
+        assert_eq!(manager.watched_items[& file_id].versions.len(), 1);
+        assert_eq!(manager.reclaim_version_space().unwrap(), 0);
+    }
+    #[test]
+    fn test_parallel_backup_reclaims_full_disk() {
+        use std::sync::atomic::Ordering;
+        let temp_dir = tempdir().unwrap();
+        let mut manager = SymorManager::new().unwrap();
+        manager.config.home_dir = temp_dir.path().join("home");
+        fs::create_dir_all(&manager.config.home_dir).unwrap();
+        manager.version_storage = versioning::storage::VersionStorage::with_config(
+            versioning::storage::StorageConfig {
+                storage_path: temp_dir.path().join("versions"),
+                ..Default::default()
+            },
+        );
+        let mut ids = Vec::new();
+        for name in ["a.txt", "b.txt"] {
+            let path = temp_dir.path().join(name);
+            fs::write(&path, "v1").unwrap();
+            ids.push(manager.watch(path.clone(), false).unwrap());
+            fs::write(&path, "v2").unwrap();
+        }
+        let versions = |manager: &SymorManager| {
+            ids.iter().map(|id| manager.watched_items[id].versions.len()).sum::<usize>()
+        };
+        let full_disk = manager.version_storage.full_disk.clone();
+        full_disk.store(1, Ordering::SeqCst);
+        let results = manager.backup_items(&ids, 2).unwrap();
+        assert!(results.iter().all(|(_, result)| result.is_ok()));
+        assert_eq!(versions(&manager), 3);
+        for id in &ids {
+            fs::write(&manager.watched_items[id].path, "v3").unwrap();
+        }
+        full_disk.store(10, Ordering::SeqCst);
+        let results = manager.backup_items(&ids, 2).unwrap();
+        assert!(
+            results
+                .iter()
+                .all(|(_, result)| {
+                    result.as_ref().unwrap_err().code() == &crate::errors::ErrorCode::DiskFull
+                })
+        );
+        assert_eq!(full_disk.load(Ordering::SeqCst), 6);
+        assert_eq!(versions(&manager), 2);
+    }
+    #[test]
+    fn test_resolve_item_and_version_content() {
+        let temp_dir = tempdir().unwrap();
+        let test_file = temp_dir.path().join("resolve.txt");
+        fs::write(&test_file, "first").unwrap();
+        let mut manager = SymorManager::new().unwrap();
+        manager.config.home_dir = temp_dir.path().join("home");
+        fs::create_dir_all(&manager.config.home_dir).unwrap();
+        manager.version_storage = versioning::storage::VersionStorage::with_config(
+            versioning::storage::StorageConfig {
+                storage_path: temp_dir.path().join("versions"),
+                ..Default::default()
+            },
+        );
+        let file_id = manager.watch(test_file.clone(), false).unwrap();
+        let by_path = manager.resolve_item(test_file.to_str().unwrap()).unwrap();
+        assert_eq!(by_path.id, file_id);
+        assert_eq!(manager.resolve_item(& file_id).unwrap().path, test_file);
+        assert!(manager.resolve_item("missing.txt").is_none());
+        let version_id = manager.watched_items[&file_id].versions[0].id.clone();
+        fs::write(&test_file, "second").unwrap();
+        assert_eq!(manager.version_content(& file_id, & version_id).unwrap(), b"first");
+        assert!(manager.version_content(& file_id, "nope").is_err());
+    }
+    #[test]
+    fn test_item_names() {
+        let temp_dir = tempdir().unwrap();
+        let (conf, other) = (temp_dir.path().join("nginx.conf"), temp_dir.path().join("b.txt"));
+        fs::write(&conf, "server {}").unwrap();
+        fs::write(&other, "other").unwrap();
+        let mut manager = SymorManager::new().unwrap();
+        manager.config.home_dir = temp_dir.path().join("home");
+        fs::create_dir_all(&manager.config.home_dir).unwrap();
+        manager.config.versioning.enabled = false;
+        let name = Some("nginx-conf".to_string());
+        let id = manager.watch_named(conf.clone(), false, Vec::new(), false, name).unwrap();
+        assert_eq!(manager.resolve_item("nginx-conf").unwrap().path, conf);
+        for taken in ["nginx-conf", id.as_str(), "etc/nginx", " ", ".hidden"] {
+            let named = Some(taken.to_string());
+            assert!(manager.watch_named(other.clone(), false, Vec::new(), false, named).is_err());
+        }
+        assert_eq!(manager.watched_index().len(), 1);
+        manager.watched_items.clear();
+        manager.load_watched_items().unwrap();
+        assert_eq!(manager.resolve_item("nginx-conf").unwrap().id, id);
+        assert!(manager.unwatch(&id).unwrap().is_some());
+        let named = Some("nginx-conf".to_string());
+        manager.watch_named(other, false, Vec::new(), false, named).unwrap();
+    }
+    #[test]
+    fn test_annotate() {
+        let temp_dir = tempdir().unwrap();
+        let test_file = temp_dir.path().join("nginx.conf");
+        fs::write(&test_file, "server {}").unwrap();
+        let mut manager = SymorManager::new().unwrap();
+        manager.config.home_dir = temp_dir.path().join("home");
+        fs::create_dir_all(&manager.config.home_dir).unwrap();
+        manager.config.versioning.enabled = false;
+        let id = manager.watch(test_file.clone(), false).unwrap();
+        let note = Some(" prod nginx config, owned by ops ".to_string());
+        assert_eq!(manager.annotate(test_file.to_str().unwrap(), note).unwrap(), test_file);
+        manager.watched_items.clear();
+        manager.load_watched_items().unwrap();
+        let description = manager.watched_items[&id].description.as_deref();
+        assert_eq!(description, Some("prod nginx config, owned by ops"));
+        manager.annotate(&id, Some("  ".to_string())).unwrap();
+        assert!(manager.watched_items[&id].description.is_none());
+        assert!(manager.annotate("missing", None).is_err());
+    }
+    #[test]
+    fn test_unwatch_persists() {
+        let temp_dir = tempdir().unwrap();
+        let test_file = temp_dir.path().join("unwatch.txt");
+        fs::write(&test_file, "data").unwrap();
+        let mut manager = SymorManager::new().unwrap();
+        manager.config.home_dir = temp_dir.path().join("home");
+        manager.config.versioning.enabled = false;
+        fs::create_dir_all(&manager.config.home_dir).unwrap();
+        let file_id = manager.watch(test_file.clone(), false).unwrap();
+        assert_eq!(manager.unwatch(&file_id).unwrap().unwrap().path, test_file);
+        assert!(manager.unwatch(&file_id).unwrap().is_none());
+        manager.watched_items.insert("stale".to_string(), crate::WatchedItem {
+            id: "stale".to_string(),
+            path: test_file,
+            is_directory: false,
+            recursive: false,
+            versions: Default::default(),
+            created_at: std::time::SystemTime::now(),
+            last_modified: std::time::SystemTime::now(),
+            only: Vec::new(),
+            skip_hidden: false,
+            paused: false,
+            name: None,
+            description: None,
+        });
+        manager.load_watched_items().unwrap();
+        assert!(manager.watched_index().len() == 0);
+    }
+    #[test]
+    fn test_watch_many() {
+        let temp_dir = tempdir().unwrap();
+        let paths: Vec<_> = (0..20).map(|i| temp_dir.path().join(format!("f{}.txt", i))).collect();
+        for (i, path) in paths.iter().enumerate() {
+            fs::write(path, format!("file {}", i)).unwrap();
+        }
+        let mut manager = SymorManager::new().unwrap();
+        manager.config.home_dir = temp_dir.path().join("home");
+        fs::create_dir_all(&manager.config.home_dir).unwrap();
+        manager.version_storage = versioning::storage::VersionStorage::with_config(
+            versioning::storage::StorageConfig {
+                storage_path: temp_dir.path().join("versions"),
+                ..Default::default()
+            },
+        );
+        let mut list = paths.clone();
+        list.push(paths[0].clone());
+        list.push(temp_dir.path().join("missing.txt"));
+        let results = manager.watch_many(list, false, Vec::new(), false).unwrap();
+        assert_eq!(results.iter().filter(|(_, r)| r.is_ok()).count(), 20);
+        assert!(results[20].1.is_err() && results[21].1.is_err());
+        manager.load_watched_items().unwrap();
+        assert_eq!(manager.watched_index().len(), 20);
+        for (path, result) in &results[..20] {
+            let id = result.as_ref().unwrap();
+            let version = &manager.watched_items[id].versions[0];
+            assert_eq!(&version.path, path);
+            assert_eq!(manager.version_content(id, &version.id).unwrap(), fs::read(path).unwrap());
+        }
+    }
+    #[test]
+    fn test_config_validated_on_load() {
+        let temp_dir = tempdir().unwrap();
+        let config_file = temp_dir.path().join("config.yaml");
+        let mut config = crate::SymorConfig::default();
+        config.linking.link_type = "bogus".to_string();
+        config.versioning.compression = 12;
+        config.write_to_file(&config_file).unwrap();
+        let mut manager = SymorManager::new().unwrap();
+        let err = manager.load_config_from(&config_file).unwrap_err();
+        assert_eq!(err.code(), & crate::errors::ErrorCode::InvalidConfiguration);
+        manager.set_validate_on_load(false);
+        manager.load_config_from(&config_file).unwrap();
+        assert_eq!(manager.config().versioning.compression, 12);
+        config.linking.link_type = "copy".to_string();
+        config.write_to_file(&config_file).unwrap();
+        manager.set_validate_on_load(true);
+        manager.load_config_from(&config_file).unwrap();
+        assert_eq!(manager.config().versioning.compression, 9);
+    }
+    #[test]
+    fn test_daemon_api() {
+        use crate::daemon::{api::ApiCommand, Daemon, DaemonOptions};
+        let temp_dir = tempdir().unwrap();
+        let file = temp_dir.path().join("notes.txt");
+        fs::write(&file, "first").unwrap();
+        let mut manager = SymorManager::new().unwrap();
+        manager.config.home_dir = temp_dir.path().join("home");
+        fs::create_dir_all(&manager.config.home_dir).unwrap();
+        manager.version_storage = versioning::storage::VersionStorage::with_config(
+            versioning::storage::StorageConfig {
+                storage_path: temp_dir.path().join("versions"),
+                ..Default::default()
+            },
+        );
+        manager.watch_many(vec![file.clone()], false, Vec::new(), false).unwrap();
+        let mut daemon = Daemon::new(manager, DaemonOptions::default());
+        let (code, status) = daemon.handle_api(ApiCommand::Status);
+        assert_eq!((code, status["paused"].as_bool()), (200, Some(false)));
+        assert_eq!(daemon.handle_api(ApiCommand::Pause(None)).1["paused"], true);
+        assert_eq!(daemon.handle_api(ApiCommand::Status).1["paused"], true);
+        let (code, health) = daemon.handle_api(ApiCommand::Health);
+        assert_eq!((code, health["watchers"]["watched_items"].as_u64()), (200, Some(1)));
+        assert_eq!(daemon.handle_api(ApiCommand::Ready).0, 503);
+        daemon.handle_api(ApiCommand::Resume(None));
+        let (code, ready) = daemon.handle_api(ApiCommand::Ready);
+        assert_eq!((code, ready["queue_depth"].as_u64()), (200, Some(0)));
+        fs::write(&file, "second").unwrap();
+        let item = file.to_string_lossy().into_owned();
+        let sync = ApiCommand::Sync { item: Some(item.clone()), force: false };
+        let marker = crate::maintenance::maintenance_file(&temp_dir.path().join("home"));
+        fs::write(&marker, "").unwrap();
+        let (code, ready) = daemon.handle_api(ApiCommand::Ready);
+        assert!(code == 503 && ready["maintenance"].is_object());
+        let (code, refused) = daemon.handle_api(ApiCommand::Sync { item: None, force: true });
+        assert_eq!((code, refused["code"].as_str()), (503, Some("MaintenanceMode")));
+        fs::remove_file(&marker).unwrap();
+        let (code, synced) = daemon.handle_api(sync);
+        assert_eq!((code, synced["versioned"].as_array().map(Vec::len)), (200, Some(1)));
+        let (_, items) = daemon.handle_api(ApiCommand::Items);
+        assert_eq!(items["items"][0]["versions"], 2);
+        let restore = ApiCommand::Restore {
+            item: item.clone(),
+            version: "@latest~1".to_string(),
+            target: None,
+        };
+        assert_eq!(daemon.handle_api(restore).0, 200);
+        assert_eq!(fs::read_to_string(&file).unwrap(), "first");
+        let missing = ApiCommand::Sync { item: Some("nope".to_string()), force: false };
+        assert_eq!(daemon.handle_api(missing).0, 404);
+        let (_, synced) = daemon.handle_api(ApiCommand::Sync { item: None, force: false });
+        assert_eq!(synced["versioned"].as_array().map(Vec::len), Some(1));
+        let other = temp_dir.path().join("other.txt");
+        fs::write(&other, "other").unwrap();
+        let watch = |path: &std::path::Path| ApiCommand::Watch {
+            path: path.to_path_buf(),
+            recursive: false,
+            only: Vec::new(),
+            skip_hidden: None,
+            name: None,
+        };
+        assert_eq!(daemon.handle_api(watch(&other)).0, 200);
+        assert_eq!(daemon.handle_api(watch(&other)).0, 400);
+        assert_eq!(daemon.handle_api(watch(&temp_dir.path().join("gone"))).0, 404);
+        assert_eq!(daemon.handle_api(ApiCommand::Items).1["items"].as_array().map(Vec::len), Some(2));
+    }
+    #[test]
+    fn test_pause_and_resume() {
+        let temp_dir = tempdir().unwrap();
+        let file = temp_dir.path().join("a.txt");
+        fs::write(&file, "a").unwrap();
+        let mut manager = SymorManager::new().unwrap();
+        manager.config.home_dir = temp_dir.path().join("home");
+        manager.config.versioning.enabled = false;
+        fs::create_dir_all(&manager.config.home_dir).unwrap();
+        let id = manager.watch_only(file.clone(), false, Vec::new(), false).unwrap();
+        let source = temp_dir.path().join("src");
+        fs::create_dir_all(&source).unwrap();
+        manager.add_mirror(&source, &[temp_dir.path().join("dst")], false, &[], None, None).unwrap();
+        assert_eq!(manager.set_paused(&id, true).unwrap(), file);
+        assert_eq!(manager.set_paused(&source.to_string_lossy(), true).unwrap(), source);
+        assert!(manager.mirrors()[0].paused);
+        manager.load_watched_items().unwrap();
+        assert!(manager.watched_item(&id).unwrap().paused);
+        assert!(manager.set_paused("/no/such/item", true).is_err());
+        manager.set_all_paused(true).unwrap();
+        assert!(manager.is_paused());
+        manager.set_all_paused(false).unwrap();
+        assert!(! manager.is_paused() && ! manager.watched_item(&id).unwrap().paused);
+        manager.load_mirrors().unwrap();
+        assert!(! manager.mirrors()[0].paused);
+    }
 }
\ No newline at end of file
@@ -1,32 +1,125 @@
-use anyhow::Result;
+use crate::errors::Result;
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
+    event::{
+        self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, MouseButton,
+        MouseEvent, MouseEventKind,
+    },
     execute,
     terminal::{
         disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
     },
 };
 use ratatui::{backend::CrosstermBackend, Terminal, Frame, prelude::Rect};
-use std::{io, time::Duration};
+use super::{
+    handlers::InputHandler, keys::{Action, KeyMap},
+    log_tail::LogTail, preview, theme::Theme,
+};
+use crate::{
+    conflicts::{Conflict, ConflictKind, Resolution}, monitoring::metrics::MetricsStore,
+    versioning::storage::{VersionMetadata, VersionStorage},
+};
+use std::{collections::HashMap, io, time::{Duration, Instant}};
 #[derive(Debug, Clone)]
 pub struct AppState {
     pub watched_items: Vec<crate::WatchedItem>,
     pub current_view: ViewType,
     pub selected_item: Option<usize>,
+    /// Row selected in the version history, newest version first
+    pub selected_version: Option<usize>,
+    /// Stored metadata for the versions shown, keyed by version id
+    pub version_metadata: HashMap<String, VersionMetadata>,
+    /// Conflicts found when the Conflicts view was last loaded
+    pub conflicts: Vec<Conflict>,
+    pub selected_conflict: Option<usize>,
     pub filter: String,
+    /// True while `/` search input is being typed
+    pub searching: bool,
+    /// Lines scrolled back from the newest log line
+    pub log_scroll: usize,
+    /// Keep the newest log lines in view as they arrive
+    pub follow_logs: bool,
+    /// Show the content preview pane beside the file list or version history
+    pub show_preview: bool,
+    /// Question being asked in the footer, if any
+    pub prompt: Option<Prompt>,
+    /// Outcome of the last action, shown until the next key press
+    pub status: Option<String>,
+    /// Pid of the background daemon, if it is running
+    pub daemon_pid: Option<u32>,
     pub running: bool,
 }
+impl AppState {
+    /// Case-insensitive match against the filter; everything matches an empty filter
+    pub fn matches(&self, text: &str) -> bool {
+        super::views::matches_filter(text, &self.filter)
+    }
+    /// Indices of watched items whose id or path matches the filter
+    pub fn visible_items(&self) -> Vec<usize> {
+        self.watched_items
+            .iter()
+            .enumerate()
+            .filter(|(_, item)| {
+                self.current_view != ViewType::FileList || self.matches(&item.id)
+                    || self.matches(&item.path.to_string_lossy())
+                    || item.name.as_deref().is_some_and(|name| self.matches(name))
+                    || item.description.as_deref().is_some_and(|text| self.matches(text))
+            })
+            .map(|(i, _)| i)
+            .collect()
+    }
+    /// Versions of the selected file whose id or hash matches the filter, newest first
+    pub fn visible_versions(&self) -> Vec<&crate::FileVersion> {
+        let Some(item) = self.selected_item.and_then(|i| self.watched_items.get(i)) else {
+            return Vec::new();
+        };
+        item.versions
+            .iter()
+            .rev()
+            .filter(|version| {
+                self.current_view != ViewType::VersionHistory || self.matches(&version.id)
+                    || self.matches(&version.hash)
+            })
+            .collect()
+    }
+}
+/// Footer input that takes over the keyboard until answered
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Prompt {
+    /// Typing a path to start watching
+    AddPath,
+    /// Waiting for y/n before unwatching the item with this id
+    ConfirmUnwatch(String),
+    /// Waiting for y/n before resolving the conflict at this index
+    ConfirmResolve(usize, Resolution),
+}
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ViewType {
     FileList,
     VersionHistory,
     Settings,
+    Stats,
     Logs,
+    Conflicts,
     Help,
 }
 pub struct SymorTUI {
     terminal: Terminal<CrosstermBackend<io::Stdout>>,
     state: AppState,
+    storage: Option<VersionStorage>,
+    /// Manager used to watch and unwatch paths; without one the list is read-only
+    manager: Option<crate::SymorManager>,
+    input: InputHandler,
+    log_tail: Option<LogTail>,
+    /// Title and lines of the last preview, keyed by what it shows
+    preview: Option<(String, String, Vec<String>)>,
+    refresh_interval: Duration,
+    last_refresh: Instant,
+    metrics: MetricsStore,
+    /// Where the list and footer were last drawn, for mapping mouse clicks
+    list_area: Rect,
+    footer_area: Rect,
+    keys: KeyMap,
+    theme: Theme,
 }
 impl SymorTUI {
     pub fn new() -> Result<Self> {
@@ -39,22 +132,346 @@ impl SymorTUI {
             watched_items: Vec::new(),
             current_view: ViewType::FileList,
             selected_item: None,
+            selected_version: None,
+            version_metadata: HashMap::new(),
+            conflicts: Vec::new(),
+            selected_conflict: None,
             filter: String::new(),
+            searching: false,
+            log_scroll: 0,
+            follow_logs: true,
+            show_preview: false,
+            prompt: None,
+            status: None,
+            daemon_pid: None,
             running: true,
         };
-        Ok(Self { terminal, state })
+        Ok(Self {
+            terminal,
+            state,
+            storage: None,
+            manager: None,
+            input: InputHandler::new(),
+            log_tail: None,
+            preview: None,
+            refresh_interval: Duration::from_secs(2),
+            last_refresh: Instant::now(),
+            metrics: MetricsStore::default(),
+            list_area: Rect::default(),
+            footer_area: Rect::default(),
+            keys: KeyMap::default(),
+            theme: Theme::default(),
+        })
+    }
+    /// Storage used to look up compressed sizes for the version history
+    pub fn set_version_storage(&mut self, storage: VersionStorage) {
+        self.storage = Some(storage);
+    }
+    /// Shows and edits the manager's watched items; changes are saved as they are made
+    pub fn set_manager(&mut self, manager: crate::SymorManager) {
+        match KeyMap::from_config(&manager.config().tui.keys) {
+            Ok(keys) => self.keys = keys,
+            Err(e) => self.state.status = Some(format!("❌ {}; using the default keys", e)),
+        }
+        match Theme::from_config(&manager.config().tui) {
+            Ok(theme) => self.theme = theme,
+            Err(e) => self.state.status = Some(format!("❌ {}; using the default theme", e)),
+        }
+        self.storage = Some(manager.version_storage().clone());
+        self.manager = Some(manager);
+        self.refresh();
+    }
+    /// Key bindings, normally built from the `tui.keys` config section
+    pub fn set_keymap(&mut self, keys: KeyMap) {
+        self.keys = keys;
+    }
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+    }
+    /// How often watched items, versions and daemon status are reloaded from disk
+    pub fn set_refresh_interval(&mut self, interval: Duration) {
+        self.refresh_interval = interval;
+    }
+    /// Reloads state written by other processes (the daemon, other `sym` commands),
+    /// keeping the selected file and version where they are
+    fn refresh(&mut self) {
+        self.last_refresh = Instant::now();
+        let Some(manager) = &mut self.manager else {
+            return;
+        };
+        self.state.daemon_pid = crate::daemon::running_pid(&manager.config().home_dir);
+        self.metrics = MetricsStore::load(&manager.config().home_dir);
+        if let Err(e) = manager.load_watched_items() {
+            self.state.status = Some(format!("❌ Refresh failed: {}", e));
+            return;
+        }
+        let selected_version = self
+            .state
+            .selected_version
+            .and_then(|i| self.state.visible_versions().get(i).map(|v| v.id.clone()));
+        self.reload_items();
+        if let Some(id) = selected_version {
+            self.state.selected_version = self
+                .state
+                .visible_versions()
+                .iter()
+                .position(|v| v.id == id)
+                .or(self.state.selected_version);
+        }
+        self.load_version_metadata();
+        if self.state.current_view == ViewType::Conflicts {
+            self.load_conflicts();
+        }
+    }
+    /// Refreshes the watched items from the manager, sorted by path, following the
+    /// selected item to its new position
+    fn reload_items(&mut self) {
+        let Some(manager) = &self.manager else {
+            return;
+        };
+        let selected_id = self
+            .state
+            .selected_item
+            .and_then(|i| self.state.watched_items.get(i))
+            .map(|item| item.id.clone());
+        let mut items: Vec<crate::WatchedItem> = manager
+            .watched_index()
+            .cloned()
+            .collect();
+        items.sort_by(|a, b| a.path.cmp(&b.path));
+        let count = items.len();
+        let moved = selected_id.and_then(|id| items.iter().position(|item| item.id == id));
+        self.state.watched_items = items;
+        if moved.is_some() {
+            self.state.selected_item = moved;
+        } else if self.state.selected_item.is_some_and(|i| i >= count) {
+            self.state.selected_item = count.checked_sub(1);
+        }
+        self.preview = None;
+    }
+    /// Log file shown in the Logs view, usually the daemon log
+    pub fn set_log_file(&mut self, path: impl Into<std::path::PathBuf>) {
+        self.log_tail = Some(LogTail::new(path));
+    }
+    /// Picks up new log lines, keeping a scrolled-back view in place
+    fn poll_logs(&mut self) {
+        let Some(tail) = &mut self.log_tail else {
+            return;
+        };
+        if let Ok(added) = tail.poll() {
+            if !self.state.follow_logs {
+                self.state.log_scroll += added;
+            }
+        }
+    }
+    /// Loads the preview for the selected file or version when the selection changed
+    fn refresh_preview(&mut self) {
+        let (key, title) = match self.state.current_view {
+            ViewType::FileList => {
+                let Some(item) = self
+                    .state
+                    .selected_item
+                    .filter(|i| self.state.visible_items().contains(i))
+                    .and_then(|i| self.state.watched_items.get(i)) else {
+                    self.preview = None;
+                    return;
+                };
+                (format!("file:{}", item.path.display()), item.path.display().to_string())
+            }
+            ViewType::VersionHistory => {
+                let versions = self.state.visible_versions();
+                let Some(version) = self.state.selected_version.and_then(|i| versions.get(i))
+                else {
+                    self.preview = None;
+                    return;
+                };
+                (format!("version:{}", version.id), format!("version {}", version.id))
+            }
+            ViewType::Conflicts => {
+                let Some(conflict) = self
+                    .state
+                    .selected_conflict
+                    .and_then(|i| self.state.conflicts.get(i))
+                    .cloned() else {
+                    self.preview = None;
+                    return;
+                };
+                let key = format!(
+                    "conflict:{:?}:{}:{:?}", conflict.kind, conflict.source.display(),
+                    conflict.target
+                );
+                if self.preview.as_ref().is_none_or(|(cached, _, _)| *cached != key) {
+                    let (title, lines) = self.conflict_preview(&conflict);
+                    self.preview = Some((key, title, lines));
+                }
+                return;
+            }
+            _ => return,
+        };
+        if self.preview.as_ref().is_some_and(|(cached, _, _)| *cached == key) {
+            return;
+        }
+        let lines = if let Some(path) = key.strip_prefix("file:") {
+            preview::preview_path(std::path::Path::new(path))
+                .unwrap_or_else(|e| vec![format!("Cannot read file: {}", e)])
+        } else {
+            let id = &key["version:".len()..];
+            match self.storage.as_ref().map(|storage| storage.retrieve_version(id)) {
+                Some(Ok((data, _))) => preview::preview_lines(&data, preview::PREVIEW_LINES),
+                Some(Err(e)) => vec![format!("Cannot read version: {}", e)],
+                None => vec!["Version storage is not available".to_string()],
+            }
+        };
+        self.preview = Some((key, title, lines));
+    }
+    /// Title and lines for the Conflicts view's preview pane: a diff from target to
+    /// source, or whichever side still exists
+    fn conflict_preview(&self, conflict: &Conflict) -> (String, Vec<String>) {
+        match (conflict.kind, &conflict.target) {
+            (ConflictKind::Diverged, Some(target)) => {
+                let title = format!(
+                    "diff {} → {}", target.display(), conflict.source.display()
+                );
+                let sides = (preview::read_head(target), preview::read_head(&conflict.source));
+                let lines = match sides {
+                    (Ok(old), Ok(new)) => {
+                        let mut lines = vec![
+                            "- target".to_string(), "+ source".to_string(), String::new(),
+                        ];
+                        lines.extend(preview::diff(&old, &new, preview::DiffMode::Auto, 3));
+                        lines
+                    }
+                    (Err(e), _) | (_, Err(e)) => vec![format!("Cannot read file: {}", e)],
+                };
+                (title, lines)
+            }
+            (ConflictKind::MissingSource, None) => {
+                let latest = conflict
+                    .item_id
+                    .as_ref()
+                    .and_then(|id| self.state.watched_items.iter().find(|item| item.id == *id))
+                    .and_then(|item| item.versions.last());
+                let Some(version) = latest else {
+                    return (
+                        conflict.source.display().to_string(),
+                        vec!["No versions stored".to_string()],
+                    );
+                };
+                let stored = self
+                    .storage
+                    .as_ref()
+                    .map(|storage| storage.retrieve_version(&version.id));
+                let lines = match stored {
+                    Some(Ok((data, _))) => {
+                        preview::preview_lines(&data, preview::PREVIEW_LINES)
+                    }
+                    Some(Err(e)) => vec![format!("Cannot read version: {}", e)],
+                    None => vec!["Version storage is not available".to_string()],
+                };
+                (format!("latest version {}", version.id), lines)
+            }
+            (_, target) => {
+                let path = target.as_ref().unwrap_or(&conflict.source);
+                let lines = preview::preview_path(path)
+                    .unwrap_or_else(|e| vec![format!("Cannot read file: {}", e)]);
+                (path.display().to_string(), lines)
+            }
+        }
+    }
+    /// Rescans for conflicts, keeping the cursor in range
+    fn load_conflicts(&mut self) {
+        let Some(manager) = &self.manager else {
+            return;
+        };
+        self.state.conflicts = manager.find_conflicts();
+        self.state.selected_conflict = match self.state.conflicts.len() {
+            0 => None,
+            count => Some(self.state.selected_conflict.unwrap_or(0).min(count - 1)),
+        };
+        self.preview = None;
     }
     pub fn run(&mut self) -> Result<()> {
         while self.state.running {
+            if self.last_refresh.elapsed() >= self.refresh_interval {
+                self.refresh();
+            }
+            self.poll_logs();
             self.draw()?;
             self.handle_events()?;
         }
         Ok(())
     }
     fn draw(&mut self) -> Result<()> {
+        let show_preview = self.state.show_preview
+            && matches!(
+                self.state.current_view, ViewType::FileList | ViewType::VersionHistory |
+                ViewType::Conflicts
+            );
+        if show_preview {
+            self.refresh_preview();
+        }
+        let preview = if show_preview {
+            Some(
+                self
+                    .preview
+                    .as_ref()
+                    .map(|(_, title, lines)| (title.clone(), lines.clone()))
+                    .unwrap_or_else(|| ("nothing selected".to_string(), Vec::new())),
+            )
+        } else {
+            None
+        };
         let current_view = self.state.current_view.clone();
-        let watched_items = self.state.watched_items.clone();
-        let selected_item = self.state.selected_item;
+        let visible = self.state.visible_items();
+        let watched_items: Vec<crate::WatchedItem> = visible
+            .iter()
+            .map(|&i| self.state.watched_items[i].clone())
+            .collect();
+        let selected_item = self
+            .state
+            .selected_item
+            .and_then(|selected| visible.iter().position(|&i| i == selected));
+        let history_item = self
+            .state
+            .selected_item
+            .and_then(|i| self.state.watched_items.get(i))
+            .cloned();
+        let selected_version = self.state.selected_version;
+        let version_metadata = self.state.version_metadata.clone();
+        let filter = self.state.filter.clone();
+        let footer_text = self.footer_text();
+        let metrics = &self.metrics;
+        let conflicts = &self.state.conflicts;
+        let selected_conflict = self.state.selected_conflict;
+        let keys = &self.keys;
+        let theme = self.theme;
+        let footer_color = match &self.state.status {
+            _ if self.state.prompt.is_some() => theme.warning,
+            Some(status) if status.starts_with('❌') => theme.error,
+            Some(_) => theme.success,
+            None => theme.footer,
+        };
+        let mut list_area = Rect::default();
+        let mut footer_area = Rect::default();
+        let header_text = match self.state.daemon_pid {
+            Some(pid) => {
+                format!(
+                    "Symor TUI - File Mirroring & Version Control | Daemon: running (pid {})",
+                    pid
+                )
+            }
+            None if self.manager.is_some() => {
+                "Symor TUI - File Mirroring & Version Control | Daemon: stopped".to_string()
+            }
+            None => "Symor TUI - File Mirroring & Version Control".to_string(),
+        };
+        let logs: Vec<String> = self
+            .log_tail
+            .as_ref()
+            .map(|tail| tail.lines().cloned().collect())
+            .unwrap_or_default();
+        let log_source = self.log_tail.as_ref().map(|tail| tail.path().to_path_buf());
+        let log_scroll = if self.state.follow_logs { 0 } else { self.state.log_scroll };
         self.terminal
             .draw(|f| {
                 use ratatui::layout::{Constraint, Direction, Layout};
@@ -67,122 +484,615 @@ impl SymorTUI {
                         Constraint::Length(1),
                     ])
                     .split(size);
-                let header = ratatui::widgets::Paragraph::new(
-                        "Symor TUI - File Mirroring & Version Control",
-                    )
+                let header = ratatui::widgets::Paragraph::new(header_text)
                     .style(
                         ratatui::style::Style::default()
-                            .fg(ratatui::style::Color::Cyan)
+                            .fg(theme.header)
                             .add_modifier(ratatui::style::Modifier::BOLD),
                     )
-                    .block(
-                        ratatui::widgets::Block::default()
-                            .borders(ratatui::widgets::Borders::ALL)
-                            .title("Symor"),
-                    );
+                    .block(theme.block("Symor"));
                 f.render_widget(header, chunks[0]);
+                let mut main = chunks[1];
+                if let Some((title, lines)) = &preview {
+                    let panes = Layout::default()
+                        .direction(Direction::Horizontal)
+                        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+                        .split(chunks[1]);
+                    main = panes[0];
+                    crate::tui::views::PreviewView {
+                        theme,
+                        diff: current_view == ViewType::Conflicts,
+                    }
+                        .render(f, panes[1], title, lines);
+                }
+                list_area = main;
+                footer_area = chunks[2];
                 match current_view {
                     ViewType::FileList => {
                         Self::draw_file_list_static(
                             f,
-                            chunks[1],
+                            main,
                             &watched_items,
                             selected_item,
+                            &filter,
+                            theme,
                         )
                     }
                     ViewType::VersionHistory => {
-                        Self::draw_version_history_static(f, chunks[1])
+                        Self::draw_version_history_static(
+                            f,
+                            main,
+                            history_item.as_ref(),
+                            &version_metadata,
+                            selected_version,
+                            &filter,
+                            theme,
+                        )
                     }
-                    ViewType::Settings => Self::draw_settings_static(f, chunks[1]),
-                    ViewType::Logs => Self::draw_logs_static(f, chunks[1]),
-                    ViewType::Help => Self::draw_help_static(f, chunks[1]),
-                }
-                let footer_text = match current_view {
-                    ViewType::FileList => {
-                        "↑↓ Navigate | Enter Select | h Help | q Quit"
+                    ViewType::Settings => Self::draw_settings_static(f, main, theme),
+                    ViewType::Stats => {
+                        crate::tui::views::StatsView { theme }.render(f, main, metrics)
                     }
-                    ViewType::VersionHistory => {
-                        "↑↓ Navigate | Enter Restore | h Help | q Quit"
+                    ViewType::Logs => {
+                        Self::draw_logs_static(
+                            f,
+                            main,
+                            &logs,
+                            log_source.as_deref(),
+                            log_scroll,
+                            theme,
+                        )
                     }
-                    ViewType::Settings => "h Help | q Quit",
-                    ViewType::Logs => "↑↓ Scroll | h Help | q Quit",
-                    ViewType::Help => "q Quit",
-                };
+                    ViewType::Conflicts => {
+                        crate::tui::views::ConflictsView { theme }
+                            .render(f, main, conflicts, selected_conflict)
+                    }
+                    ViewType::Help => Self::draw_help_static(f, main, keys, theme),
+                }
                 let footer = ratatui::widgets::Paragraph::new(footer_text)
-                    .style(
-                        ratatui::style::Style::default().fg(ratatui::style::Color::White),
-                    );
+                    .style(ratatui::style::Style::default().fg(footer_color));
                 f.render_widget(footer, chunks[2]);
             })?;
+        self.list_area = list_area;
+        self.footer_area = footer_area;
         Ok(())
     }
+    fn footer_text(&self) -> String {
+        let hints: Vec<String> = self.footer_hints().into_iter().map(|(_, text)| text).collect();
+        hints.join(" | ")
+    }
+    /// Footer segments, each with the action performed when it is clicked
+    fn footer_hints(&self) -> Vec<(Option<Action>, String)> {
+        match &self.state.prompt {
+            Some(Prompt::AddPath) => {
+                return vec![
+                    (None, format!("Watch path: {}▏", self.input.buffer)), (None,
+                    "Enter Add".to_string()), (None, "Esc Cancel".to_string())
+                ];
+            }
+            Some(Prompt::ConfirmUnwatch(id)) => {
+                let path = self
+                    .state
+                    .watched_items
+                    .iter()
+                    .find(|item| item.id == *id)
+                    .map(|item| item.path.display().to_string())
+                    .unwrap_or_else(|| id.clone());
+                return vec![
+                    (None, format!("Stop watching {}? y Yes", path)), (None,
+                    "any other key No".to_string())
+                ];
+            }
+            Some(Prompt::ConfirmResolve(index, resolution)) => {
+                let question = self
+                    .state
+                    .conflicts
+                    .get(*index)
+                    .and_then(|conflict| {
+                        conflict
+                            .resolutions()
+                            .into_iter()
+                            .find(|(r, _)| r == resolution)
+                            .map(|(_, label)| {
+                                format!("{} for {}?", label, conflict.source.display())
+                            })
+                    })
+                    .unwrap_or_else(|| "Resolve?".to_string());
+                return vec![
+                    (None, format!("{} y Yes", question)), (None, "any other key No"
+                    .to_string())
+                ];
+            }
+            None => {}
+        }
+        if let Some(status) = &self.state.status {
+            return vec![(None, status.clone())];
+        }
+        if self.state.searching {
+            return vec![
+                (None, format!("/{}▏", self.state.filter)), (None, "Enter Keep filter"
+                .to_string()), (None, "Esc Clear".to_string())
+            ];
+        }
+        let key = |action: Action, text: &str| {
+            (Some(action), format!("{} {}", self.keys.label(action), text))
+        };
+        let arrows = |text: &str| {
+            (
+                None,
+                format!(
+                    "{}{} {}", self.keys.label(Action::Up), self.keys.label(Action::Down),
+                    text
+                ),
+            )
+        };
+        let mut hints = Vec::new();
+        if !self.state.filter.is_empty() {
+            hints.push((None, format!("Filter: {} (Esc Clear)", self.state.filter)));
+        }
+        match self.state.current_view {
+            ViewType::FileList => {
+                hints.extend([
+                    arrows("Navigate"),
+                    key(Action::Select, "Select"),
+                    key(Action::Search, "Search"),
+                    key(Action::Add, "Add"),
+                    key(Action::Unwatch, "Unwatch"),
+                    key(Action::Preview, "Preview"),
+                ]);
+            }
+            ViewType::VersionHistory => {
+                hints.extend([
+                    arrows("Navigate"),
+                    (
+                        None,
+                        format!(
+                            "{}{} Switch file", self.keys.label(Action::PrevFile), self
+                            .keys.label(Action::NextFile)
+                        ),
+                    ),
+                    key(Action::Search, "Search"),
+                    key(Action::Preview, "Preview"),
+                    key(Action::Files, "Files"),
+                ]);
+            }
+            ViewType::Logs if self.state.follow_logs => {
+                hints.extend([arrows("Scroll"), key(Action::Follow, "Follow (on)")]);
+            }
+            ViewType::Logs => {
+                hints.extend([arrows("Scroll"), key(Action::Follow, "Follow (paused)")]);
+            }
+            ViewType::Conflicts => {
+                hints.push(arrows("Navigate"));
+                if let Some(conflict) = self
+                    .state
+                    .selected_conflict
+                    .and_then(|i| self.state.conflicts.get(i))
+                {
+                    for (resolution, label) in conflict.resolutions() {
+                        hints.push(key(resolution_action(resolution), label));
+                    }
+                }
+                hints.push(key(Action::Select, "Diff"));
+            }
+            ViewType::Settings | ViewType::Stats | ViewType::Help => {}
+        }
+        if self.state.current_view != ViewType::Help {
+            hints.push(key(Action::Help, "Help"));
+        }
+        hints.push(key(Action::Quit, "Quit"));
+        hints
+    }
     fn handle_events(&mut self) -> Result<()> {
         if event::poll(Duration::from_millis(100))? {
-            if let Event::Key(key) = event::read()? {
-                match key.code {
-                    KeyCode::Char('q') | KeyCode::Esc => {
-                        self.state.running = false;
-                    }
-                    KeyCode::Char('h') => {
-                        self.state.current_view = ViewType::Help;
-                    }
-                    KeyCode::Char('f') => {
-                        self.state.current_view = ViewType::FileList;
-                    }
-                    KeyCode::Char('v') => {
-                        self.state.current_view = ViewType::VersionHistory;
-                    }
-                    KeyCode::Char('s') => {
-                        self.state.current_view = ViewType::Settings;
-                    }
-                    KeyCode::Char('l') => {
-                        self.state.current_view = ViewType::Logs;
-                    }
-                    KeyCode::Up => {
-                        self.handle_navigation(-1);
-                    }
-                    KeyCode::Down => {
-                        self.handle_navigation(1);
-                    }
+            match event::read()? {
+                Event::Key(key) => self.handle_key(key.code),
+                Event::Mouse(mouse) => self.handle_mouse(mouse),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+    fn handle_key(&mut self, code: KeyCode) {
+        self.state.status = None;
+        if self.state.prompt.is_some() {
+            self.handle_prompt_key(code);
+            return;
+        }
+        if self.state.searching {
+            self.handle_search_key(code);
+            return;
+        }
+        if code == KeyCode::Esc && !self.state.filter.is_empty() {
+            self.clear_filter();
+        } else if let Some(action) = self.keys.action(code) {
+            self.perform(action);
+        }
+    }
+    fn perform(&mut self, action: Action) {
+        let view = self.state.current_view.clone();
+        let list_view = matches!(view, ViewType::FileList | ViewType::VersionHistory);
+        match action {
+            Action::Quit => self.state.running = false,
+            Action::Search if list_view => self.state.searching = true,
+            Action::Add if view == ViewType::FileList => {
+                self.input.clear();
+                self.state.prompt = Some(Prompt::AddPath);
+            }
+            Action::Unwatch if list_view => self.confirm_unwatch(),
+            Action::Preview => self.state.show_preview = !self.state.show_preview,
+            Action::Help => self.set_view(ViewType::Help),
+            Action::Files => self.set_view(ViewType::FileList),
+            Action::Versions => {
+                self.set_view(ViewType::VersionHistory);
+                self.load_versions();
+            }
+            Action::Settings => self.set_view(ViewType::Settings),
+            Action::Stats => self.set_view(ViewType::Stats),
+            Action::Logs => self.set_view(ViewType::Logs),
+            Action::Conflicts => {
+                self.set_view(ViewType::Conflicts);
+                self.load_conflicts();
+            }
+            Action::KeepSource if view == ViewType::Conflicts => {
+                self.confirm_resolve(Resolution::KeepSource)
+            }
+            Action::KeepTarget if view == ViewType::Conflicts => {
+                self.confirm_resolve(Resolution::KeepTarget)
+            }
+            Action::KeepBoth if view == ViewType::Conflicts => {
+                self.confirm_resolve(Resolution::KeepBoth)
+            }
+            Action::Up => self.handle_navigation(-1),
+            Action::Down => self.handle_navigation(1),
+            Action::PageUp => self.handle_page_navigation(-10),
+            Action::PageDown => self.handle_page_navigation(10),
+            Action::PrevFile if view == ViewType::VersionHistory => {
+                self.switch_history_file(-1)
+            }
+            Action::NextFile if view == ViewType::VersionHistory => {
+                self.switch_history_file(1)
+            }
+            Action::Select => self.handle_selection(),
+            Action::Follow if view == ViewType::Logs => {
+                self.state.follow_logs = true;
+                self.state.log_scroll = 0;
+            }
+            _ => {}
+        }
+    }
+    /// Clicks select list rows (a second click opens them), the wheel scrolls, and
+    /// clicking a footer hint acts as its key
+    fn handle_mouse(&mut self, mouse: MouseEvent) {
+        if self.state.prompt.is_some() {
+            return;
+        }
+        let inside = |area: Rect| {
+            mouse.column >= area.x && mouse.column < area.x + area.width && mouse.row >= area.y
+                && mouse.row < area.y + area.height
+        };
+        match mouse.kind {
+            MouseEventKind::ScrollUp if inside(self.list_area) => self.handle_navigation(-1),
+            MouseEventKind::ScrollDown if inside(self.list_area) => self.handle_navigation(1),
+            MouseEventKind::Down(MouseButton::Left) if inside(self.footer_area) => {
+                let column = (mouse.column - self.footer_area.x) as usize;
+                if let Some(action) = footer_hint_at(&self.footer_hints(), column) {
+                    self.perform(action);
+                }
+            }
+            MouseEventKind::Down(MouseButton::Left) if inside(self.list_area) => {
+                let row = mouse.row.saturating_sub(self.list_area.y + 1) as usize;
+                self.click_row(row);
+            }
+            _ => {}
+        }
+    }
+    /// Selects the `row`th visible entry of the current list, opening it if already selected
+    fn click_row(&mut self, row: usize) {
+        match self.state.current_view {
+            ViewType::FileList => {
+                let Some(&index) = self.state.visible_items().get(row) else {
+                    return;
+                };
+                if self.state.selected_item == Some(index) {
+                    self.handle_selection();
+                } else {
+                    self.state.selected_item = Some(index);
+                }
+            }
+            ViewType::VersionHistory if row < self.version_count() => {
+                self.state.selected_version = Some(row);
+            }
+            ViewType::Conflicts if row < self.state.conflicts.len() => {
+                if self.state.selected_conflict == Some(row) {
+                    self.handle_selection();
+                } else {
+                    self.state.selected_conflict = Some(row);
+                }
+            }
+            _ => {}
+        }
+    }
+    /// Switches view; the filter belongs to the view it was typed in
+    fn set_view(&mut self, view: ViewType) {
+        if self.state.current_view != view {
+            self.clear_filter();
+        }
+        self.state.current_view = view;
+    }
+    fn handle_search_key(&mut self, code: KeyCode) {
+        match code {
+            KeyCode::Esc => self.clear_filter(),
+            KeyCode::Enter => self.state.searching = false,
+            KeyCode::Backspace => self.input.delete_char(),
+            KeyCode::Left => self.input.move_cursor_left(),
+            KeyCode::Right => self.input.move_cursor_right(),
+            KeyCode::Char(c) => self.input.insert_char(c),
+            _ => {}
+        }
+        if self.state.filter != self.input.buffer {
+            self.state.filter = self.input.buffer.clone();
+            self.reselect();
+        }
+    }
+    fn handle_prompt_key(&mut self, code: KeyCode) {
+        match self.state.prompt.clone() {
+            Some(Prompt::AddPath) => {
+                match code {
+                    KeyCode::Esc => self.end_prompt(),
                     KeyCode::Enter => {
-                        self.handle_selection();
-                    }
-                    KeyCode::PageUp => {
-                        self.handle_page_navigation(-10);
-                    }
-                    KeyCode::PageDown => {
-                        self.handle_page_navigation(10);
+                        let path = self.input.buffer.trim().to_string();
+                        self.end_prompt();
+                        self.watch_path(&path);
                     }
+                    KeyCode::Backspace => self.input.delete_char(),
+                    KeyCode::Left => self.input.move_cursor_left(),
+                    KeyCode::Right => self.input.move_cursor_right(),
+                    KeyCode::Char(c) => self.input.insert_char(c),
                     _ => {}
                 }
             }
+            Some(Prompt::ConfirmUnwatch(id)) => {
+                self.end_prompt();
+                if matches!(code, KeyCode::Char('y') | KeyCode::Char('Y')) {
+                    self.unwatch(&id);
+                } else {
+                    self.state.status = Some("Unwatch cancelled".to_string());
+                }
+            }
+            Some(Prompt::ConfirmResolve(index, resolution)) => {
+                self.end_prompt();
+                if matches!(code, KeyCode::Char('y') | KeyCode::Char('Y')) {
+                    self.resolve(index, resolution);
+                } else {
+                    self.state.status = Some("Resolution cancelled".to_string());
+                }
+            }
+            None => {}
         }
-        Ok(())
     }
-    fn handle_navigation(&mut self, direction: i32) {
-        let max_items = match self.state.current_view {
-            ViewType::FileList => self.state.watched_items.len(),
-            _ => 0,
+    /// Closes the prompt, handing the input line back to the search filter
+    fn end_prompt(&mut self) {
+        self.state.prompt = None;
+        self.input.clear();
+        for c in self.state.filter.chars() {
+            self.input.insert_char(c);
+        }
+    }
+    /// Asks before unwatching the selected item
+    fn confirm_unwatch(&mut self) {
+        let visible = self.state.visible_items();
+        match self.state.selected_item.filter(|i| visible.contains(i)) {
+            Some(index) => {
+                let id = self.state.watched_items[index].id.clone();
+                self.state.prompt = Some(Prompt::ConfirmUnwatch(id));
+            }
+            None => self.state.status = Some("Select an item to unwatch".to_string()),
+        }
+    }
+    fn watch_path(&mut self, input: &str) {
+        let Some(manager) = &mut self.manager else {
+            self.state.status = Some("Watching is not available here".to_string());
+            return;
+        };
+        if input.is_empty() {
+            return;
+        }
+        let path = match std::path::absolute(input) {
+            Ok(path) if path.exists() => path,
+            _ => {
+                self.state.status = Some(format!("❌ No such file or directory: {}", input));
+                return;
+            }
+        };
+        if manager.watched_index().any(|item| item.path == path) {
+            self.state.status = Some(format!("{} is already watched", path.display()));
+            return;
+        }
+        self.state.status = Some(
+            match manager.watch(path.clone(), false) {
+                Ok(id) => {
+                    self.reload_items();
+                    self.state.selected_item = self
+                        .state
+                        .watched_items
+                        .iter()
+                        .position(|item| item.id == id);
+                    format!("✅ Now watching {}", path.display())
+                }
+                Err(e) => format!("❌ Could not watch {}: {}", path.display(), e),
+            },
+        );
+    }
+    fn unwatch(&mut self, id: &str) {
+        let Some(manager) = &mut self.manager else {
+            self.state.status = Some("Unwatching is not available here".to_string());
+            return;
         };
-        if max_items > 0 {
-            let current = self.state.selected_item.unwrap_or(0) as i32;
-            let new_index = (current + direction).max(0).min(max_items as i32 - 1)
-                as usize;
-            self.state.selected_item = Some(new_index);
+        self.state.status = Some(
+            match manager.unwatch(id) {
+                Ok(Some(item)) => {
+                    self.reload_items();
+                    self.reselect();
+                    if self.state.current_view == ViewType::VersionHistory {
+                        self.load_versions();
+                    }
+                    format!("✅ Stopped watching {}", item.path.display())
+                }
+                Ok(None) => format!("{} is no longer watched", id),
+                Err(e) => format!("❌ Could not unwatch: {}", e),
+            },
+        );
+    }
+    /// Asks before applying `resolution` to the selected conflict
+    fn confirm_resolve(&mut self, resolution: Resolution) {
+        let Some(index) = self.state.selected_conflict else {
+            self.state.status = Some("Select a conflict to resolve".to_string());
+            return;
+        };
+        let applies = self
+            .state
+            .conflicts
+            .get(index)
+            .is_some_and(|conflict| {
+                conflict.resolutions().iter().any(|(r, _)| *r == resolution)
+            });
+        if applies {
+            self.state.prompt = Some(Prompt::ConfirmResolve(index, resolution));
+        } else {
+            self.state.status = Some("That resolution does not apply here".to_string());
+        }
+    }
+    fn resolve(&mut self, index: usize, resolution: Resolution) {
+        let (Some(manager), Some(conflict)) = (
+            &mut self.manager,
+            self.state.conflicts.get(index),
+        ) else {
+            self.state.status = Some("Resolving conflicts is not available here".to_string());
+            return;
+        };
+        self.state.status = Some(
+            match manager.resolve_conflict(conflict, resolution) {
+                Ok(outcome) => format!("✅ {}", outcome),
+                Err(e) => format!("❌ Could not resolve: {}", e),
+            },
+        );
+        self.reload_items();
+        self.load_conflicts();
+    }
+    fn clear_filter(&mut self) {
+        self.input.clear();
+        self.state.searching = false;
+        if !self.state.filter.is_empty() {
+            self.state.filter.clear();
+            self.reselect();
+        }
+    }
+    /// Keeps the selection on a row that is still visible after the filter changed
+    fn reselect(&mut self) {
+        match self.state.current_view {
+            ViewType::FileList => {
+                let visible = self.state.visible_items();
+                if !self.state.selected_item.is_some_and(|i| visible.contains(&i)) {
+                    self.state.selected_item = visible.first().copied();
+                }
+            }
+            ViewType::VersionHistory => {
+                self.state.selected_version = if self.version_count() > 0 {
+                    Some(0)
+                } else {
+                    None
+                };
+            }
+            _ => {}
+        }
+    }
+    /// Number of versions shown for the selected file
+    fn version_count(&self) -> usize {
+        self.state.visible_versions().len()
+    }
+    fn handle_navigation(&mut self, direction: i32) {
+        match self.state.current_view {
+            ViewType::FileList => {
+                let visible = self.state.visible_items();
+                if visible.is_empty() {
+                    return;
+                }
+                let current = self
+                    .state
+                    .selected_item
+                    .and_then(|selected| visible.iter().position(|&i| i == selected))
+                    .unwrap_or(0) as i32;
+                let new_index = (current + direction)
+                    .max(0)
+                    .min(visible.len() as i32 - 1) as usize;
+                self.state.selected_item = Some(visible[new_index]);
+            }
+            ViewType::Logs => {
+                let lines = self.log_tail.as_ref().map(|t| t.lines().count()).unwrap_or(0);
+                let back = self.state.log_scroll as i32 - direction;
+                self.state.log_scroll = back.max(0).min(lines.saturating_sub(1) as i32)
+                    as usize;
+                self.state.follow_logs = self.state.log_scroll == 0;
+            }
+            ViewType::VersionHistory => {
+                let max_items = self.version_count();
+                if max_items > 0 {
+                    let current = self.state.selected_version.unwrap_or(0) as i32;
+                    let new_index = (current + direction)
+                        .max(0)
+                        .min(max_items as i32 - 1) as usize;
+                    self.state.selected_version = Some(new_index);
+                }
+            }
+            ViewType::Conflicts => {
+                let count = self.state.conflicts.len();
+                if count > 0 {
+                    let current = self.state.selected_conflict.unwrap_or(0) as i32;
+                    let new_index = (current + direction).max(0).min(count as i32 - 1)
+                        as usize;
+                    self.state.selected_conflict = Some(new_index);
+                }
+            }
+            _ => {}
         }
     }
     fn handle_page_navigation(&mut self, direction: i32) {
         let page_size = 10;
-        let max_items = match self.state.current_view {
-            ViewType::FileList => self.state.watched_items.len(),
-            _ => 0,
+        self.handle_navigation(direction * page_size);
+    }
+    /// Shows the history of the previous or next watched file
+    fn switch_history_file(&mut self, direction: i32) {
+        let count = self.state.watched_items.len();
+        if count == 0 {
+            return;
+        }
+        let current = self.state.selected_item.unwrap_or(0) as i32;
+        self.state.selected_item = Some((current + direction).rem_euclid(count as i32) as usize);
+        self.load_versions();
+    }
+    /// Resets the version cursor and loads metadata for the selected file's versions
+    fn load_versions(&mut self) {
+        if self.state.selected_item.is_none() && !self.state.watched_items.is_empty() {
+            self.state.selected_item = Some(0);
+        }
+        self.state.selected_version = if self.version_count() > 0 { Some(0) } else { None };
+        self.load_version_metadata();
+    }
+    /// Loads stored metadata for any of the selected file's versions not seen yet
+    fn load_version_metadata(&mut self) {
+        let (Some(storage), Some(item)) = (
+            &self.storage,
+            self.state.selected_item.and_then(|i| self.state.watched_items.get(i)),
+        ) else {
+            return;
         };
-        if max_items > 0 {
-            let current = self.state.selected_item.unwrap_or(0) as i32;
-            let new_index = (current + direction * page_size)
-                .max(0)
-                .min(max_items as i32 - 1) as usize;
-            self.state.selected_item = Some(new_index);
+        for version in &item.versions {
+            if !self.state.version_metadata.contains_key(&version.id) {
+                if let Ok(metadata) = storage.load_metadata(&version.id) {
+                    self.state.version_metadata.insert(version.id.clone(), metadata);
+                }
+            }
         }
     }
     fn handle_selection(&mut self) {
@@ -190,10 +1100,12 @@ impl SymorTUI {
             ViewType::FileList => {
                 if let Some(index) = self.state.selected_item {
                     if index < self.state.watched_items.len() {
-                        self.state.current_view = ViewType::VersionHistory;
+                        self.set_view(ViewType::VersionHistory);
+                        self.load_versions();
                     }
                 }
             }
+            ViewType::Conflicts => self.state.show_preview = !self.state.show_preview,
             ViewType::VersionHistory => {}
             _ => {}
         }
@@ -220,35 +1132,70 @@ impl SymorTUI {
         area: Rect,
         watched_items: &[crate::WatchedItem],
         selected_item: Option<usize>,
+        filter: &str,
+        theme: Theme,
     ) {
         use crate::tui::views::FileListView;
-        let view = FileListView;
-        view.render(f, area, watched_items, selected_item);
+        let view = FileListView { theme };
+        view.render(f, area, watched_items, selected_item, filter);
     }
-    fn draw_version_history_static(f: &mut Frame, area: Rect) {
+    fn draw_version_history_static(
+        f: &mut Frame,
+        area: Rect,
+        item: Option<&crate::WatchedItem>,
+        metadata: &HashMap<String, VersionMetadata>,
+        selected_version: Option<usize>,
+        filter: &str,
+        theme: Theme,
+    ) {
         use crate::tui::views::VersionHistoryView;
-        let view = VersionHistoryView;
-        let versions: Vec<crate::FileVersion> = Vec::new();
-        view.render(f, area, &versions);
+        let view = VersionHistoryView { theme };
+        view.render(f, area, item, metadata, selected_version, filter);
     }
-    fn draw_settings_static(f: &mut Frame, area: Rect) {
+    fn draw_settings_static(f: &mut Frame, area: Rect, theme: Theme) {
         use crate::tui::views::SettingsView;
-        let view = SettingsView;
+        let view = SettingsView { theme };
         let config = crate::SymorConfig::default();
         view.render(f, area, &config);
     }
-    fn draw_logs_static(f: &mut Frame, area: Rect) {
+    fn draw_logs_static(
+        f: &mut Frame,
+        area: Rect,
+        logs: &[String],
+        source: Option<&std::path::Path>,
+        scroll: usize,
+        theme: Theme,
+    ) {
         use crate::tui::views::LogsView;
-        let view = LogsView;
-        let logs: Vec<String> = vec!["TUI initialized".to_string()];
-        view.render(f, area, &logs);
+        let view = LogsView { theme };
+        view.render(f, area, logs, source, scroll);
     }
-    fn draw_help_static(f: &mut Frame, area: Rect) {
+    fn draw_help_static(f: &mut Frame, area: Rect, keys: &KeyMap, theme: Theme) {
         use crate::tui::views::HelpView;
-        let view = HelpView;
-        view.render(f, area);
+        let view = HelpView { theme };
+        view.render(f, area, keys);
     }
 }
+/// Key action that applies `resolution` in the Conflicts view
+fn resolution_action(resolution: Resolution) -> Action {
+    match resolution {
+        Resolution::KeepSource => Action::KeepSource,
+        Resolution::KeepTarget => Action::KeepTarget,
+        Resolution::KeepBoth => Action::KeepBoth,
+    }
+}
+/// Action of the footer segment (segments are joined by `" | "`) at `column`
+fn footer_hint_at(hints: &[(Option<Action>, String)], column: usize) -> Option<Action> {
+    let mut start = 0;
+    for (action, text) in hints {
+        let width = text.chars().count();
+        if column >= start && column < start + width {
+            return *action;
+        }
+        start += width + 3;
+    }
+    None
+}
 impl Drop for SymorTUI {
     fn drop(&mut self) {
         let _ = self.shutdown();
@@ -263,10 +1210,73 @@ mod tests {
             watched_items: Vec::new(),
             current_view: ViewType::FileList,
             selected_item: None,
+            selected_version: None,
+            version_metadata: HashMap::new(),
+            conflicts: Vec::new(),
+            selected_conflict: None,
             filter: String::new(),
+            searching: false,
+            log_scroll: 0,
+            follow_logs: true,
+            show_preview: false,
+            prompt: None,
+            status: None,
+            daemon_pid: None,
             running: true,
         };
         assert_eq!(state.current_view, ViewType::FileList);
         assert!(state.running);
     }
+    #[test]
+    fn test_footer_hint_at() {
+        let hints = vec![
+            (None, "↑↓ Navigate".to_string()), (Some(Action::Select), "Enter Select"
+            .to_string()), (Some(Action::Quit), "q Quit".to_string())
+        ];
+        assert_eq!(footer_hint_at(& hints, 0), None);
+        assert_eq!(footer_hint_at(& hints, 14), Some(Action::Select));
+        assert_eq!(footer_hint_at(& hints, 27), None);
+        assert_eq!(footer_hint_at(& hints, 29), Some(Action::Quit));
+        assert_eq!(footer_hint_at(& hints, 40), None);
+    }
+    #[test]
+    fn test_filter_visible_items() {
+        let item = |id: &str, path: &str| crate::WatchedItem {
+            id: id.to_string(),
+            path: std::path::PathBuf::from(path),
+            is_directory: false,
+            recursive: false,
+            versions: Default::default(),
+            created_at: std::time::SystemTime::now(),
+            last_modified: std::time::SystemTime::now(),
+            only: Vec::new(),
+            skip_hidden: false,
+            paused: false,
+            name: None,
+            description: None,
+        };
+        let mut state = AppState {
+            watched_items: vec![item("a1", "/src/main.rs"), item("b2", "/docs/README.md")],
+            current_view: ViewType::FileList,
+            selected_item: None,
+            selected_version: None,
+            version_metadata: HashMap::new(),
+            conflicts: Vec::new(),
+            selected_conflict: None,
+            filter: "readme".to_string(),
+            searching: true,
+            log_scroll: 0,
+            follow_logs: true,
+            show_preview: false,
+            prompt: None,
+            status: None,
+            daemon_pid: None,
+            running: true,
+        };
+        assert_eq!(state.visible_items(), vec![1]);
+        state.filter = "A1".to_string();
+        assert_eq!(state.visible_items(), vec![0]);
+        state.filter.clear();
+        assert_eq!(state.visible_items(), vec![0, 1]);
+    }
 }
\ No newline at end of file
@@ -40,6 +40,7 @@ impl NavigationHandler {
 }
 pub struct InputHandler {
     pub buffer: String,
+    /// Byte offset into `buffer`, always on a char boundary
     pub cursor_position: usize,
 }
 impl InputHandler {
@@ -49,24 +50,27 @@ impl InputHandler {
             cursor_position: 0,
         }
     }
+    fn previous_boundary(&self) -> Option<usize> {
+        self.buffer[..self.cursor_position].char_indices().next_back().map(|(i, _)| i)
+    }
     pub fn insert_char(&mut self, c: char) {
         self.buffer.insert(self.cursor_position, c);
-        self.cursor_position += 1;
+        self.cursor_position += c.len_utf8();
     }
     pub fn delete_char(&mut self) {
-        if self.cursor_position > 0 {
-            self.buffer.remove(self.cursor_position - 1);
-            self.cursor_position -= 1;
+        if let Some(previous) = self.previous_boundary() {
+            self.buffer.remove(previous);
+            self.cursor_position = previous;
         }
     }
     pub fn move_cursor_left(&mut self) {
-        if self.cursor_position > 0 {
-            self.cursor_position -= 1;
+        if let Some(previous) = self.previous_boundary() {
+            self.cursor_position = previous;
         }
     }
     pub fn move_cursor_right(&mut self) {
-        if self.cursor_position < self.buffer.len() {
-            self.cursor_position += 1;
+        if let Some(c) = self.buffer[self.cursor_position..].chars().next() {
+            self.cursor_position += c.len_utf8();
         }
     }
     pub fn clear(&mut self) {
@@ -0,0 +1,282 @@
+use crate::errors::{ErrorCode, Result, SymorError};
+use crossterm::event::KeyCode;
+pub use super::KeySpec;
+use std::{collections::HashMap, str::FromStr};
+/// Something a key can be bound to in the TUI
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Quit,
+    Search,
+    Add,
+    Unwatch,
+    Preview,
+    Help,
+    Files,
+    Versions,
+    Settings,
+    Stats,
+    Logs,
+    Conflicts,
+    KeepSource,
+    KeepTarget,
+    KeepBoth,
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    PrevFile,
+    NextFile,
+    Select,
+    Follow,
+}
+impl Action {
+    pub const ALL: [Action; 23] = [
+        Action::Quit,
+        Action::Search,
+        Action::Add,
+        Action::Unwatch,
+        Action::Preview,
+        Action::Help,
+        Action::Files,
+        Action::Versions,
+        Action::Settings,
+        Action::Stats,
+        Action::Logs,
+        Action::Conflicts,
+        Action::KeepSource,
+        Action::KeepTarget,
+        Action::KeepBoth,
+        Action::Up,
+        Action::Down,
+        Action::PageUp,
+        Action::PageDown,
+        Action::PrevFile,
+        Action::NextFile,
+        Action::Select,
+        Action::Follow,
+    ];
+    /// Name used in the `tui.keys` config section
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::Quit => "quit",
+            Action::Search => "search",
+            Action::Add => "add",
+            Action::Unwatch => "unwatch",
+            Action::Preview => "preview",
+            Action::Help => "help",
+            Action::Files => "files",
+            Action::Versions => "versions",
+            Action::Settings => "settings",
+            Action::Stats => "stats",
+            Action::Logs => "logs",
+            Action::Conflicts => "conflicts",
+            Action::KeepSource => "keep_source",
+            Action::KeepTarget => "keep_target",
+            Action::KeepBoth => "keep_both",
+            Action::Up => "up",
+            Action::Down => "down",
+            Action::PageUp => "page_up",
+            Action::PageDown => "page_down",
+            Action::PrevFile => "prev_file",
+            Action::NextFile => "next_file",
+            Action::Select => "select",
+            Action::Follow => "follow",
+        }
+    }
+    fn default_keys(&self) -> &'static [&'static str] {
+        match self {
+            Action::Quit => &["q", "Esc"],
+            Action::Search => &["/"],
+            Action::Add => &["a"],
+            Action::Unwatch => &["d", "u"],
+            Action::Preview => &["p"],
+            Action::Help => &["h"],
+            Action::Files => &["f"],
+            Action::Versions => &["v"],
+            Action::Settings => &["s"],
+            Action::Stats => &["t"],
+            Action::Logs => &["l"],
+            Action::Conflicts => &["c"],
+            Action::KeepSource => &["<"],
+            Action::KeepTarget => &[">"],
+            Action::KeepBoth => &["b"],
+            Action::Up => &["Up"],
+            Action::Down => &["Down"],
+            Action::PageUp => &["PageUp"],
+            Action::PageDown => &["PageDown"],
+            Action::PrevFile => &["Left"],
+            Action::NextFile => &["Right"],
+            Action::Select => &["Enter"],
+            Action::Follow => &["End"],
+        }
+    }
+}
+impl FromStr for Action {
+    type Err = SymorError;
+    fn from_str(s: &str) -> Result<Self> {
+        Action::ALL
+            .into_iter()
+            .find(|action| action.name() == s)
+            .ok_or_else(|| {
+                SymorError::new(
+                        ErrorCode::InvalidConfiguration,
+                        format!("Unknown TUI action: {}", s),
+                    )
+                    .with_suggestion(
+                        format!(
+                            "Use one of: {}", Action::ALL.map(| a | a.name()).join(", ")
+                        ),
+                    )
+            })
+    }
+}
+/// Parses a key name such as `j`, `Esc`, `PageDown` or `F5`
+pub fn parse_key(name: &str) -> Result<KeyCode> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Ok(KeyCode::Char(c));
+    }
+    let code = match name.to_ascii_lowercase().as_str() {
+        "esc" | "escape" => KeyCode::Esc,
+        "enter" | "return" => KeyCode::Enter,
+        "space" => KeyCode::Char(' '),
+        "tab" => KeyCode::Tab,
+        "backspace" => KeyCode::Backspace,
+        "delete" | "del" => KeyCode::Delete,
+        "insert" | "ins" => KeyCode::Insert,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" | "pgup" => KeyCode::PageUp,
+        "pagedown" | "pgdn" => KeyCode::PageDown,
+        other => {
+            match other.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+                Some(n @ 1..=12) => KeyCode::F(n),
+                _ => {
+                    return Err(
+                        SymorError::new(
+                                ErrorCode::InvalidConfiguration,
+                                format!("Unknown key: {}", name),
+                            )
+                            .with_suggestion(
+                                "Use a single character or a name like Esc, Enter, Up, PageDown, F5"
+                                    .to_string(),
+                            ),
+                    );
+                }
+            }
+        }
+    };
+    Ok(code)
+}
+/// Short label for a key, the inverse of `parse_key`
+pub fn key_label(code: KeyCode) -> String {
+    match code {
+        KeyCode::Char(' ') => "Space".to_string(),
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::Up => "↑".to_string(),
+        KeyCode::Down => "↓".to_string(),
+        KeyCode::Left => "←".to_string(),
+        KeyCode::Right => "→".to_string(),
+        KeyCode::PageUp => "PgUp".to_string(),
+        KeyCode::PageDown => "PgDn".to_string(),
+        KeyCode::F(n) => format!("F{}", n),
+        other => format!("{:?}", other),
+    }
+}
+/// Key to action lookup built from the defaults and `tui.keys` overrides
+#[derive(Debug, Clone)]
+pub struct KeyMap {
+    bindings: HashMap<KeyCode, Action>,
+}
+impl Default for KeyMap {
+    fn default() -> Self {
+        let mut bindings = HashMap::new();
+        for action in Action::ALL {
+            for name in action.default_keys() {
+                if let Ok(code) = parse_key(name) {
+                    bindings.insert(code, action);
+                }
+            }
+        }
+        Self { bindings }
+    }
+}
+impl KeyMap {
+    /// Applies `overrides`; a remapped action loses its default keys, and a key bound
+    /// here is taken away from whatever action had it by default
+    pub fn from_config(overrides: &HashMap<String, KeySpec>) -> Result<Self> {
+        let mut keymap = Self::default();
+        let mut remapped = Vec::new();
+        for (name, spec) in overrides {
+            let action: Action = name.parse()?;
+            let codes = spec
+                .names()
+                .into_iter()
+                .map(parse_key)
+                .collect::<Result<Vec<_>>>()?;
+            remapped.push((action, codes));
+        }
+        for (action, _) in &remapped {
+            keymap.bindings.retain(|_, bound| bound != action);
+        }
+        for (action, codes) in remapped {
+            for code in codes {
+                keymap.bindings.insert(code, action);
+            }
+        }
+        Ok(keymap)
+    }
+    pub fn action(&self, code: KeyCode) -> Option<Action> {
+        self.bindings.get(&code).copied()
+    }
+    /// Keys bound to `action`, in a stable order
+    pub fn keys(&self, action: Action) -> Vec<KeyCode> {
+        let defaults: Vec<KeyCode> = action
+            .default_keys()
+            .iter()
+            .filter_map(|name| parse_key(name).ok())
+            .collect();
+        let mut keys: Vec<KeyCode> = self
+            .bindings
+            .iter()
+            .filter(|(_, bound)| **bound == action)
+            .map(|(code, _)| *code)
+            .collect();
+        keys.sort_by_key(|code| {
+            (defaults.iter().position(|d| d == code).unwrap_or(usize::MAX), key_label(*code))
+        });
+        keys
+    }
+    /// Label of the first key bound to `action`, or `-` when it has none
+    pub fn label(&self, action: Action) -> String {
+        self.keys(action).first().map(|code| key_label(*code)).unwrap_or_else(|| "-".to_string())
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_keymap_overrides() {
+        let keymap = KeyMap::default();
+        assert_eq!(keymap.action(KeyCode::Char('q')), Some(Action::Quit));
+        assert_eq!(keymap.action(KeyCode::Up), Some(Action::Up));
+        let mut overrides = HashMap::new();
+        overrides.insert("up".to_string(), KeySpec::Many(vec!["k".into(), "Up".into()]));
+        overrides.insert("down".to_string(), KeySpec::One("j".into()));
+        overrides.insert("quit".to_string(), KeySpec::One("F10".into()));
+        let keymap = KeyMap::from_config(&overrides).unwrap();
+        assert_eq!(keymap.action(KeyCode::Char('k')), Some(Action::Up));
+        assert_eq!(keymap.action(KeyCode::Down), None);
+        assert_eq!(keymap.action(KeyCode::Char('q')), None);
+        assert_eq!(keymap.action(KeyCode::F(10)), Some(Action::Quit));
+        assert_eq!(keymap.label(Action::Up), "↑");
+        assert_eq!(keymap.label(Action::Down), "j");
+        overrides.insert("jump".to_string(), KeySpec::One("x".into()));
+        assert!(KeyMap::from_config(& overrides).is_err());
+        assert!(parse_key("Hyper").is_err());
+    }
+}
@@ -0,0 +1,83 @@
+use std::{
+    collections::VecDeque, fs, io::{Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+/// Lines kept in memory for the Logs view
+pub const MAX_LOG_LINES: usize = 1000;
+/// Follows a log file, picking up lines appended since the last poll
+#[derive(Debug)]
+pub struct LogTail {
+    path: PathBuf,
+    offset: u64,
+    partial: String,
+    lines: VecDeque<String>,
+}
+impl LogTail {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            offset: 0,
+            partial: String::new(),
+            lines: VecDeque::new(),
+        }
+    }
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+    pub fn lines(&self) -> impl Iterator<Item = &String> {
+        self.lines.iter()
+    }
+    /// Reads anything appended since the last call; returns the number of new lines.
+    /// A file that shrank (rotated or truncated) is read again from the start.
+    pub fn poll(&mut self) -> std::io::Result<usize> {
+        let Ok(mut file) = fs::File::open(&self.path) else {
+            return Ok(0);
+        };
+        let len = file.metadata()?.len();
+        if len < self.offset {
+            self.offset = 0;
+            self.partial.clear();
+        }
+        if len == self.offset {
+            return Ok(0);
+        }
+        file.seek(SeekFrom::Start(self.offset))?;
+        let mut buffer = Vec::new();
+        file.read_to_end(&mut buffer)?;
+        self.offset += buffer.len() as u64;
+        self.partial.push_str(&String::from_utf8_lossy(&buffer));
+        let mut added = 0;
+        while let Some(end) = self.partial.find('\n') {
+            let line: String = self.partial.drain(..=end).collect();
+            self.lines.push_back(line.trim_end().to_string());
+            added += 1;
+        }
+        while self.lines.len() > MAX_LOG_LINES {
+            self.lines.pop_front();
+        }
+        Ok(added)
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::tempdir;
+    #[test]
+    fn test_log_tail_follows_appends_and_truncation() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("daemon.log");
+        let mut tail = LogTail::new(&path);
+        assert_eq!(tail.poll().unwrap(), 0);
+        fs::write(&path, "[INFO] one\n[WARN] tw").unwrap();
+        assert_eq!(tail.poll().unwrap(), 1);
+        assert_eq!(tail.lines().collect::< Vec < _ >> (), vec!["[INFO] one"]);
+        let mut file = fs::OpenOptions::new().append(true).open(&path).unwrap();
+        writeln!(file, "o").unwrap();
+        assert_eq!(tail.poll().unwrap(), 1);
+        assert_eq!(tail.lines().last().unwrap(), "[WARN] two");
+        fs::write(&path, "[ERROR] three\n").unwrap();
+        assert_eq!(tail.poll().unwrap(), 1);
+        assert_eq!(tail.lines().count(), 3);
+    }
+}
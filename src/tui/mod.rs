@@ -1,6 +1,60 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+#[cfg(feature = "tui")]
 pub mod app;
+#[cfg(feature = "tui")]
 pub mod views;
+#[cfg(feature = "tui")]
 pub mod handlers;
-pub use app::{SymorTUI, AppState, ViewType};
+pub mod log_tail;
+pub mod format;
+#[cfg(feature = "tui")]
+pub mod keys;
+#[cfg(feature = "tui")]
+pub mod theme;
+#[cfg(feature = "tui")]
+pub mod preview;
+#[cfg(feature = "tui")]
+pub mod timeline;
+#[cfg(feature = "tui")]
+pub use app::{SymorTUI, AppState, Prompt, ViewType};
+#[cfg(feature = "tui")]
 pub use views::{FileListView, VersionHistoryView, SettingsView};
-pub use handlers::{FileAction, NavigationHandler, InputHandler};
\ No newline at end of file
+#[cfg(feature = "tui")]
+pub use handlers::{FileAction, NavigationHandler, InputHandler};
+/// One key or several for an action in the `tui.keys` config section
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum KeySpec {
+    One(String),
+    Many(Vec<String>),
+}
+impl KeySpec {
+    pub fn names(&self) -> Vec<&str> {
+        match self {
+            KeySpec::One(name) => vec![name.as_str()],
+            KeySpec::Many(names) => names.iter().map(String::as_str).collect(),
+        }
+    }
+}
+/// Settings for `sym tui`, stored in the `tui` section of the config; kept without
+/// the `tui` feature so that config files round-trip
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TuiConfig {
+    /// Action name to key(s); actions not listed keep their default keys
+    pub keys: HashMap<String, KeySpec>,
+    /// One of `theme::Theme::NAMES`
+    pub theme: String,
+    /// Theme colour overrides, role name to colour
+    pub colors: HashMap<String, String>,
+}
+impl Default for TuiConfig {
+    fn default() -> Self {
+        Self {
+            keys: HashMap::new(),
+            theme: "default".to_string(),
+            colors: HashMap::new(),
+        }
+    }
+}
\ No newline at end of file
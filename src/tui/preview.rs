@@ -0,0 +1,384 @@
+use crate::errors::{ErrorCode, SymorError};
+use std::{fmt, fs, io::{self, Read}, path::Path, str::FromStr};
+/// Bytes read from the start of a file for the preview pane
+pub const PREVIEW_BYTES: usize = 64 * 1024;
+/// Lines shown in the preview pane
+pub const PREVIEW_LINES: usize = 200;
+/// Treats data with NUL bytes or invalid UTF-8 near the start as binary
+pub fn is_binary(data: &[u8]) -> bool {
+    let head = &data[..data.len().min(8192)];
+    if head.contains(&0) {
+        return true;
+    }
+    match std::str::from_utf8(head) {
+        Ok(_) => false,
+        Err(e) => e.error_len().is_some(),
+    }
+}
+/// One `hexdump` line for the (up to sixteen) bytes of `chunk` at `offset`
+fn hex_row(offset: usize, chunk: &[u8]) -> String {
+    let hex: Vec<String> = chunk.iter().map(|b| format!("{:02x}", b)).collect();
+    let ascii: String = chunk
+        .iter()
+        .map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' })
+        .collect();
+    format!("{:08x}  {:<47}  |{}|", offset, hex.join(" "), ascii)
+}
+/// `xxd`-style dump: offset, sixteen hex bytes and their printable characters
+pub fn hexdump(data: &[u8], max_lines: usize) -> Vec<String> {
+    data.chunks(16)
+        .take(max_lines)
+        .enumerate()
+        .map(|(i, chunk)| hex_row(i * 16, chunk))
+        .collect()
+}
+/// First `max_lines` lines of text, or a hexdump for binary data
+pub fn preview_lines(data: &[u8], max_lines: usize) -> Vec<String> {
+    if data.is_empty() {
+        return vec!["(empty)".to_string()];
+    }
+    if is_binary(data) {
+        return hexdump(data, max_lines);
+    }
+    String::from_utf8_lossy(data)
+        .lines()
+        .take(max_lines)
+        .map(|line| line.replace('\t', "    "))
+        .collect()
+}
+/// Lines of each side compared by `diff_lines`, and hexdump rows by `diff_binary`
+pub const DIFF_MAX_LINES: usize = 1000;
+/// Words and spaces of one changed line that `diff_words` compares; longer lines are
+/// shown as a removed and an added line
+const DIFF_MAX_WORDS: usize = 2000;
+/// How `diff` shows changes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DiffMode {
+    /// `Binary` for binary content, `Word` for prose and config files, else `Line`
+    #[default]
+    Auto,
+    Line,
+    Word,
+    Binary,
+}
+impl DiffMode {
+    /// The mode `Auto` picks for these two sides
+    pub fn detect(old: &[u8], new: &[u8]) -> Self {
+        if is_binary(old) || is_binary(new) {
+            return DiffMode::Binary;
+        }
+        let text = String::from_utf8_lossy(if new.is_empty() { old } else { new });
+        if is_prose_or_config(&text) { DiffMode::Word } else { DiffMode::Line }
+    }
+}
+impl FromStr for DiffMode {
+    type Err = SymorError;
+    fn from_str(s: &str) -> Result<Self, SymorError> {
+        match s.to_lowercase().as_str() {
+            "auto" => Ok(DiffMode::Auto),
+            "line" | "lines" => Ok(DiffMode::Line),
+            "word" | "words" => Ok(DiffMode::Word),
+            "binary" | "hex" => Ok(DiffMode::Binary),
+            other => {
+                Err(
+                    SymorError::new(
+                            ErrorCode::InvalidConfiguration,
+                            format!("Unknown diff mode: {}", other),
+                        )
+                        .with_suggestion("Use 'auto', 'line', 'word' or 'binary'".to_string()),
+                )
+            }
+        }
+    }
+}
+impl fmt::Display for DiffMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(
+            match self {
+                DiffMode::Auto => "auto",
+                DiffMode::Line => "line",
+                DiffMode::Word => "word",
+                DiffMode::Binary => "binary",
+            },
+        )
+    }
+}
+/// Whether most lines of `text` are settings (`key = value`, `key: value`, `[section]`,
+/// comments) or sentences, where the changed words matter more than the whole line
+fn is_prose_or_config(text: &str) -> bool {
+    let lines: Vec<&str> = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .take(DIFF_MAX_LINES)
+        .collect();
+    let wordy = lines
+        .iter()
+        .filter(|line| {
+            let setting = !line.ends_with(';')
+                && line
+                    .split_once(['=', ':'])
+                    .is_some_and(|(key, _)| {
+                        let key = key.trim();
+                        !key.is_empty()
+                            && key.chars().all(|c| c.is_alphanumeric() || "_-.\"' ".contains(c))
+                    });
+            let sentence = line.split_whitespace().count() >= 6
+                && !line.ends_with(['{', '}', ';', '(', ')']);
+            setting || sentence || line.starts_with(['#', '[', ';'])
+        })
+        .count();
+    !lines.is_empty() && wordy * 2 >= lines.len()
+}
+/// Diff from `old` to `new` in `mode`, keeping `context` unchanged lines (or hexdump
+/// rows) around each change
+pub fn diff(old: &[u8], new: &[u8], mode: DiffMode, context: usize) -> Vec<String> {
+    let text = |data| String::from_utf8_lossy(data).into_owned();
+    match mode {
+        DiffMode::Auto => diff(old, new, DiffMode::detect(old, new), context),
+        DiffMode::Line => diff_lines(&text(old), &text(new), context),
+        DiffMode::Word => diff_words(&text(old), &text(new), context),
+        DiffMode::Binary => diff_binary(old, new, context),
+    }
+}
+/// Edit script turning `a` into `b`: `' '` kept, `'-'` removed, `'+'` added
+fn edit_script<T: PartialEq + Copy>(a: &[T], b: &[T]) -> Vec<(char, T)> {
+    let mut lcs = vec![vec![0u32; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    let mut ops = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            ops.push((' ', a[i]));
+            i += 1;
+            j += 1;
+        } else if i < a.len() && (j == b.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            ops.push(('-', a[i]));
+            i += 1;
+        } else {
+            ops.push(('+', b[j]));
+            j += 1;
+        }
+    }
+    ops
+}
+/// Line diff from `old` to `new` (`- ` removed, `+ ` added), keeping `context`
+/// unchanged lines around each change and `…` where the rest were skipped
+pub fn diff_lines(old: &str, new: &str, context: usize) -> Vec<String> {
+    let a: Vec<&str> = old.lines().take(DIFF_MAX_LINES).collect();
+    let b: Vec<&str> = new.lines().take(DIFF_MAX_LINES).collect();
+    let ops = edit_script(&a, &b)
+        .into_iter()
+        .map(|(op, line)| (op, line.replace('\t', "    ")))
+        .collect();
+    with_context(ops, context)
+}
+/// `diff_lines`, but a changed line that replaces another is shown once (`~ `) with
+/// the removed words in `[-…-]` and the added ones in `{+…+}`
+pub fn diff_words(old: &str, new: &str, context: usize) -> Vec<String> {
+    let a: Vec<&str> = old.lines().take(DIFF_MAX_LINES).collect();
+    let b: Vec<&str> = new.lines().take(DIFF_MAX_LINES).collect();
+    let script = edit_script(&a, &b);
+    let mut ops = Vec::with_capacity(script.len());
+    let mut k = 0;
+    while k < script.len() {
+        if script[k].0 == ' ' {
+            ops.push((' ', script[k].1.replace('\t', "    ")));
+            k += 1;
+            continue;
+        }
+        let removed_end = k + script[k..].iter().take_while(|(op, _)| *op == '-').count();
+        let added_end = removed_end
+            + script[removed_end..].iter().take_while(|(op, _)| *op == '+').count();
+        let (removed, added) = (&script[k..removed_end], &script[removed_end..added_end]);
+        for pair in 0..removed.len().max(added.len()) {
+            match (removed.get(pair), added.get(pair)) {
+                (Some((_, old)), Some((_, new))) => ops.extend(diff_line_words(old, new)),
+                (Some((_, old)), None) => ops.push(('-', old.replace('\t', "    "))),
+                (None, Some((_, new))) => ops.push(('+', new.replace('\t', "    "))),
+                (None, None) => {}
+            }
+        }
+        k = added_end;
+    }
+    with_context(ops, context)
+}
+/// Words and the whitespace between them, so that joining them gives `line` back
+fn split_words(line: &str) -> Vec<&str> {
+    let mut words = Vec::new();
+    let mut start = 0;
+    let mut chars = line.char_indices().peekable();
+    while let Some((_, c)) = chars.next() {
+        let space = c.is_whitespace();
+        if chars.peek().is_none_or(|(_, next)| next.is_whitespace() != space) {
+            let end = chars.peek().map_or(line.len(), |(i, _)| *i);
+            words.push(&line[start..end]);
+            start = end;
+        }
+    }
+    words
+}
+/// One `~ ` line marking the words that changed from `old` to `new`
+fn diff_line_words(old: &str, new: &str) -> Vec<(char, String)> {
+    let (a, b) = (split_words(old), split_words(new));
+    if a.len() + b.len() > DIFF_MAX_WORDS {
+        return vec![('-', old.replace('\t', "    ")), ('+', new.replace('\t', "    "))];
+    }
+    let mut line = String::new();
+    let mut run: Option<char> = None;
+    for (op, word) in edit_script(&a, &b) {
+        if run != Some(op) {
+            match run {
+                Some('-') => line.push_str("-]"),
+                Some('+') => line.push_str("+}"),
+                _ => {}
+            }
+            match op {
+                '-' => line.push_str("[-"),
+                '+' => line.push_str("{+"),
+                _ => {}
+            }
+            run = Some(op);
+        }
+        line.push_str(word);
+    }
+    match run {
+        Some('-') => line.push_str("-]"),
+        Some('+') => line.push_str("+}"),
+        _ => {}
+    }
+    vec![('~', line.replace('\t', "    "))]
+}
+/// Hexdump diff from `old` to `new`: rows of sixteen bytes compared at the same
+/// offset, so an insertion shows as every later row changing
+pub fn diff_binary(old: &[u8], new: &[u8], context: usize) -> Vec<String> {
+    let rows = old.len().max(new.len()).div_ceil(16).min(DIFF_MAX_LINES);
+    let mut ops = Vec::with_capacity(rows);
+    fn chunk(data: &[u8], row: usize) -> Option<&[u8]> {
+        data.get(row * 16..data.len().min(row * 16 + 16)).filter(|c| !c.is_empty())
+    }
+    for row in 0..rows {
+        match (chunk(old, row), chunk(new, row)) {
+            (Some(a), Some(b)) if a == b => ops.push((' ', hex_row(row * 16, a))),
+            (a, b) => {
+                ops.extend(a.map(|a| ('-', hex_row(row * 16, a))));
+                ops.extend(b.map(|b| ('+', hex_row(row * 16, b))));
+            }
+        }
+    }
+    with_context(ops, context)
+}
+/// Renders an edit script, keeping `context` unchanged lines around each change and
+/// `…` where the rest were skipped
+fn with_context(ops: Vec<(char, String)>, context: usize) -> Vec<String> {
+    let changed: Vec<usize> = ops
+        .iter()
+        .enumerate()
+        .filter(|(_, (op, _))| *op != ' ')
+        .map(|(k, _)| k)
+        .collect();
+    if changed.is_empty() {
+        return vec!["(no differences)".to_string()];
+    }
+    let mut lines = Vec::new();
+    let mut skipping = false;
+    for (k, (op, line)) in ops.iter().enumerate() {
+        if changed.iter().any(|&c| k + context >= c && k <= c + context) {
+            lines.push(format!("{} {}", op, line));
+            skipping = false;
+        } else if !skipping {
+            lines.push("…".to_string());
+            skipping = true;
+        }
+    }
+    lines
+}
+/// Preview of a path on disk: file contents, or the entries of a directory
+pub fn preview_path(path: &Path) -> io::Result<Vec<String>> {
+    if path.is_dir() {
+        let mut names: Vec<String> = fs::read_dir(path)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                if entry.path().is_dir() { format!("{}/", name) } else { name }
+            })
+            .collect();
+        names.sort();
+        names.truncate(PREVIEW_LINES);
+        return Ok(names);
+    }
+    Ok(preview_lines(&read_head(path)?, PREVIEW_LINES))
+}
+/// The first `PREVIEW_BYTES` of a file
+pub fn read_head(path: &Path) -> io::Result<Vec<u8>> {
+    let mut data = Vec::new();
+    fs::File::open(path)?.take(PREVIEW_BYTES as u64).read_to_end(&mut data)?;
+    Ok(data)
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+    #[test]
+    fn test_preview_text_and_binary() {
+        assert_eq!(preview_lines(b"one\ntwo\nthree\n", 2), vec!["one", "two"]);
+        assert!(! is_binary("héllo".as_bytes()));
+        assert!(is_binary(b"\x7fELF\x00\x01"));
+        let dump = preview_lines(b"\x00\x01AB", 10);
+        assert_eq!(dump.len(), 1);
+        assert!(dump[0].starts_with("00000000  00 01 41 42"));
+        assert!(dump[0].ends_with("|..AB|"));
+        let temp_dir = tempdir().unwrap();
+        fs::write(temp_dir.path().join("b.txt"), "b").unwrap();
+        fs::create_dir(temp_dir.path().join("a")).unwrap();
+        assert_eq!(preview_path(temp_dir.path()).unwrap(), vec!["a/", "b.txt"]);
+    }
+    #[test]
+    fn test_diff_lines() {
+        let old = "one\ntwo\nthree\nfour\nfive\nsix\n";
+        let new = "one\ntwo\nthree\nfour\nfive\nSIX\nseven\n";
+        assert_eq!(
+            diff_lines(old, new, 1), vec!["…", "  five", "- six", "+ SIX", "+ seven"]
+        );
+        assert_eq!(diff_lines("a\nb", "a\nb", 2), vec!["(no differences)"]);
+        assert_eq!(diff_lines("", "x", 0), vec!["+ x"]);
+    }
+    #[test]
+    fn test_diff_modes() {
+        let old = "port = 80\nhost = old.example.com\nroot = /srv\n";
+        let new = "port = 8080\nhost = new.example.com\nroot = /srv\nindex = a\n";
+        assert_eq!(
+            diff_words(old, new, 0),
+            vec![
+                "~ port = [-80-]{+8080+}",
+                "~ host = [-old.example.com-]{+new.example.com+}",
+                "…",
+                "+ index = a",
+            ]
+        );
+        assert_eq!(diff_words("a b c", "a c", 0), vec!["~ a [-b -]c"]);
+        assert_eq!(DiffMode::detect(old.as_bytes(), new.as_bytes()), DiffMode::Word);
+        let code = b"fn main() {\n    run();\n}\n";
+        assert_eq!(DiffMode::detect(code, code), DiffMode::Line);
+        assert_eq!(DiffMode::detect(b"\x00\x01", code), DiffMode::Binary);
+        let (mut before, mut after) = (vec![0u8; 40], vec![0u8; 40]);
+        before[20] = 1;
+        after.extend_from_slice(b"AB");
+        let lines = diff(&before, &after, DiffMode::Auto, 0);
+        assert_eq!(lines.len(), 5, "{:?}", lines);
+        assert_eq!(lines[0], "…");
+        assert!(lines[1].starts_with("- 00000010  00 00 00 00 01"));
+        assert!(lines[2].starts_with("+ 00000010  00 00 00 00 00"));
+        assert!(lines[4].starts_with("+ 00000020  00 00 00 00 00 00 00 00 41 42"));
+        assert_eq!("hex".parse::<DiffMode>().unwrap(), DiffMode::Binary);
+        assert!("colour".parse::<DiffMode>().is_err());
+    }
+}
@@ -0,0 +1,176 @@
+use crate::errors::{ErrorCode, Result, SymorError};
+use ratatui::{
+    style::{Color, Modifier, Style},
+    widgets::{block::Title, Block, Borders},
+};
+use std::collections::HashMap;
+/// Colours for every part of the TUI
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    pub header: Color,
+    pub text: Color,
+    pub border: Color,
+    pub selected: Color,
+    pub match_fg: Color,
+    pub match_bg: Color,
+    pub muted: Color,
+    pub accent: Color,
+    pub success: Color,
+    pub warning: Color,
+    pub error: Color,
+    pub footer: Color,
+}
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            header: Color::Cyan,
+            text: Color::Reset,
+            border: Color::Reset,
+            selected: Color::Yellow,
+            match_fg: Color::Black,
+            match_bg: Color::Yellow,
+            muted: Color::DarkGray,
+            accent: Color::Cyan,
+            success: Color::Green,
+            warning: Color::Yellow,
+            error: Color::Red,
+            footer: Color::White,
+        }
+    }
+}
+impl Theme {
+    pub const NAMES: [&'static str; 3] = ["default", "light", "high-contrast"];
+    /// Dark text for terminals with a light background
+    pub fn light() -> Self {
+        Self {
+            header: Color::Blue,
+            text: Color::Black,
+            border: Color::DarkGray,
+            selected: Color::Magenta,
+            match_fg: Color::White,
+            match_bg: Color::Blue,
+            muted: Color::Gray,
+            accent: Color::Blue,
+            success: Color::Green,
+            warning: Color::Rgb(175, 95, 0),
+            error: Color::Red,
+            footer: Color::Black,
+        }
+    }
+    /// Bright colours only, for low-vision use and washed-out displays
+    pub fn high_contrast() -> Self {
+        Self {
+            header: Color::LightYellow,
+            text: Color::White,
+            border: Color::White,
+            selected: Color::LightYellow,
+            match_fg: Color::Black,
+            match_bg: Color::LightCyan,
+            muted: Color::Gray,
+            accent: Color::LightCyan,
+            success: Color::LightGreen,
+            warning: Color::LightYellow,
+            error: Color::LightRed,
+            footer: Color::White,
+        }
+    }
+    pub fn named(name: &str) -> Result<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "default" | "dark" => Ok(Self::default()),
+            "light" => Ok(Self::light()),
+            "high-contrast" | "high_contrast" => Ok(Self::high_contrast()),
+            other => {
+                Err(
+                    SymorError::new(
+                            ErrorCode::InvalidConfiguration,
+                            format!("Unknown TUI theme: {}", other),
+                        )
+                        .with_suggestion(format!("Use one of: {:?}", Self::NAMES)),
+                )
+            }
+        }
+    }
+    /// Replaces the colours named in `palette` (role → colour name, index or `#rrggbb`)
+    pub fn with_palette(mut self, palette: &HashMap<String, String>) -> Result<Self> {
+        for (role, value) in palette {
+            let color: Color = value
+                .parse()
+                .map_err(|_| {
+                    SymorError::new(
+                            ErrorCode::InvalidConfiguration,
+                            format!("Invalid colour for {}: {}", role, value),
+                        )
+                        .with_suggestion(
+                            "Use a colour name (red, lightblue), an index (0-255) or #rrggbb"
+                                .to_string(),
+                        )
+                })?;
+            let slot = match role.as_str() {
+                "header" => &mut self.header,
+                "text" => &mut self.text,
+                "border" => &mut self.border,
+                "selected" => &mut self.selected,
+                "match_fg" => &mut self.match_fg,
+                "match_bg" => &mut self.match_bg,
+                "muted" => &mut self.muted,
+                "accent" => &mut self.accent,
+                "success" => &mut self.success,
+                "warning" => &mut self.warning,
+                "error" => &mut self.error,
+                "footer" => &mut self.footer,
+                other => {
+                    return Err(
+                        SymorError::new(
+                                ErrorCode::InvalidConfiguration,
+                                format!("Unknown theme colour: {}", other),
+                            )
+                            .with_suggestion(
+                                "Use header, text, border, selected, match_fg, match_bg, muted, accent, success, warning, error or footer"
+                                    .to_string(),
+                            ),
+                    );
+                }
+            };
+            *slot = color;
+        }
+        Ok(self)
+    }
+    /// The theme chosen in the `tui` config section, with its palette applied
+    pub fn from_config(config: &super::TuiConfig) -> Result<Self> {
+        Self::named(&config.theme)?.with_palette(&config.colors)
+    }
+    pub fn text_style(&self) -> Style {
+        Style::default().fg(self.text)
+    }
+    pub fn selected_style(&self) -> Style {
+        Style::default().fg(self.selected).add_modifier(Modifier::BOLD)
+    }
+    pub fn match_style(&self, base: Style) -> Style {
+        base.fg(self.match_fg).bg(self.match_bg)
+    }
+    /// Bordered block with the theme's border colour
+    pub fn block<'a>(&self, title: impl Into<Title<'a>>) -> Block<'a> {
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(self.border))
+            .title(title)
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_theme_palette() {
+        assert_eq!(Theme::named("light").unwrap(), Theme::light());
+        assert!(Theme::named("neon").is_err());
+        let mut palette = HashMap::new();
+        palette.insert("selected".to_string(), "#ff8800".to_string());
+        palette.insert("error".to_string(), "magenta".to_string());
+        let theme = Theme::default().with_palette(&palette).unwrap();
+        assert_eq!(theme.selected, Color::Rgb(0xff, 0x88, 0x00));
+        assert_eq!(theme.error, Color::Magenta);
+        assert_eq!(theme.header, Theme::default().header);
+        palette.insert("sidebar".to_string(), "red".to_string());
+        assert!(Theme::default().with_palette(& palette).is_err());
+    }
+}
@@ -0,0 +1,154 @@
+use super::format::{civil_from_days, epoch_secs, format_bytes};
+use crate::FileVersion;
+use std::collections::BTreeMap;
+/// Widest bar `timeline` draws
+pub const TIMELINE_WIDTH: usize = 40;
+/// How much time one timeline row covers, from finest to coarsest
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Bucket {
+    Hour,
+    SixHours,
+    Day,
+    /// Monday to Sunday
+    Week,
+    Month,
+    Year,
+}
+impl Bucket {
+    const ALL: [Bucket; 6] = [
+        Bucket::Hour, Bucket::SixHours, Bucket::Day, Bucket::Week, Bucket::Month, Bucket::Year,
+    ];
+    fn name(self) -> &'static str {
+        match self {
+            Bucket::Hour => "hour",
+            Bucket::SixHours => "6 hours",
+            Bucket::Day => "day",
+            Bucket::Week => "week",
+            Bucket::Month => "month",
+            Bucket::Year => "year",
+        }
+    }
+    /// Index of the row holding `secs` since the epoch; consecutive rows differ by one
+    fn key(self, secs: i64) -> i64 {
+        let days = secs.div_euclid(86_400);
+        let (year, month, _) = civil_from_days(days);
+        match self {
+            Bucket::Hour => secs.div_euclid(3600),
+            Bucket::SixHours => secs.div_euclid(6 * 3600),
+            Bucket::Day => days,
+            Bucket::Week => (days + 3).div_euclid(7),
+            Bucket::Month => year * 12 + month - 1,
+            Bucket::Year => year,
+        }
+    }
+    /// Start of row `key`, in UTC
+    fn label(self, key: i64) -> String {
+        let date = |days: i64| {
+            let (year, month, day) = civil_from_days(days);
+            format!("{:04}-{:02}-{:02}", year, month, day)
+        };
+        match self {
+            Bucket::Hour => format!("{} {:02}:00", date(key.div_euclid(24)), key.rem_euclid(24)),
+            Bucket::SixHours => {
+                format!("{} {:02}:00", date(key.div_euclid(4)), key.rem_euclid(4) * 6)
+            }
+            Bucket::Day => date(key),
+            Bucket::Week => date(key * 7 - 3),
+            Bucket::Month => format!("{:04}-{:02}", key.div_euclid(12), key.rem_euclid(12) + 1),
+            Bucket::Year => format!("{:04}", key),
+        }
+    }
+}
+/// Timeline of when `versions` were created, in UTC: one row per hour, 6 hours, day,
+/// week, month or year (the finest that fits in `max_rows`, which empty rows count
+/// towards so gaps in the backup cadence show), each with a bar for the versions
+/// stored then, their number, and the size of the newest with its change from the
+/// row before. Returns the row length's name and the rows.
+pub fn timeline<'a>(
+    versions: impl IntoIterator<Item = &'a FileVersion>,
+    max_rows: usize,
+) -> (&'static str, Vec<String>) {
+    let versions: Vec<&FileVersion> = versions.into_iter().collect();
+    let secs: Vec<i64> = versions.iter().map(|v| epoch_secs(v.timestamp)).collect();
+    let (Some(&first), Some(&last)) = (secs.iter().min(), secs.iter().max()) else {
+        return ("day", Vec::new());
+    };
+    let bucket = Bucket::ALL
+        .into_iter()
+        .find(|bucket| bucket.key(last) - bucket.key(first) < max_rows.max(1) as i64)
+        .unwrap_or(Bucket::Year);
+    let mut rows: BTreeMap<i64, (usize, &FileVersion)> = BTreeMap::new();
+    for (&version, &secs) in versions.iter().zip(&secs) {
+        let row = rows.entry(bucket.key(secs)).or_insert((0, version));
+        row.0 += 1;
+        if version.timestamp >= row.1.timestamp {
+            row.1 = version;
+        }
+    }
+    let most = rows.values().map(|(count, _)| *count).max().unwrap_or(1);
+    let mut previous: Option<u64> = None;
+    let lines = (bucket.key(first)..=bucket.key(last))
+        .map(|key| {
+            let label = bucket.label(key);
+            let Some((count, newest)) = rows.get(&key) else {
+                return format!("{:<16} │", label);
+            };
+            let bar = "█".repeat((count * TIMELINE_WIDTH).div_ceil(most));
+            let change = match previous {
+                Some(size) if size < newest.size => {
+                    format!(" (+{})", format_bytes(newest.size - size))
+                }
+                Some(size) if size > newest.size => {
+                    format!(" (-{})", format_bytes(size - newest.size))
+                }
+                _ => String::new(),
+            };
+            previous = Some(newest.size);
+            format!(
+                "{:<16} │{:<width$} {:>3}  {}{}", label, bar, count, format_bytes(newest.size),
+                change, width = TIMELINE_WIDTH
+            )
+        })
+        .collect();
+    (bucket.name(), lines)
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{path::PathBuf, time::{Duration, UNIX_EPOCH}};
+    fn version(secs: u64, size: u64) -> FileVersion {
+        FileVersion {
+            id: secs.to_string(),
+            path: PathBuf::from("notes.txt"),
+            timestamp: UNIX_EPOCH + Duration::from_secs(secs),
+            hash: String::new(),
+            size,
+            backup_path: None,
+        }
+    }
+    #[test]
+    fn test_timeline() {
+        assert_eq!(civil_from_days(20_742), (2026, 10, 16));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+        let day = 86_400;
+        let start = 20_742 * day;
+        let versions = [
+            version(start + 60, 1000), version(start + 120, 1500),
+            version(start + 2 * day, 1200),
+        ];
+        let (unit, rows) = timeline(&versions, 10);
+        assert_eq!(unit, "6 hours");
+        assert_eq!(rows.len(), 9);
+        assert!(rows[0].starts_with("2026-10-16 00:00 │████"), "{}", rows[0]);
+        assert!(rows[0].ends_with("  2  1.5 KiB"), "{}", rows[0]);
+        assert_eq!(rows[1], "2026-10-16 06:00 │");
+        assert!(rows[8].ends_with("  1  1.2 KiB (-300 B)"), "{}", rows[8]);
+        let (unit, rows) = timeline(&versions, 3);
+        assert_eq!((unit, rows.len()), ("day", 3));
+        let (unit, rows) = timeline(&[version(0, 1), version(400 * day, 2)], 20);
+        assert_eq!(unit, "month");
+        assert!(rows[0].starts_with("1970-01 ") && rows[13].starts_with("1971-02 "));
+        assert!(timeline(&[], 10).1.is_empty());
+        assert_eq!(timeline(&versions[2..], 10).1.len(), 1);
+    }
+}
\ No newline at end of file
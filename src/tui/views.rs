@@ -1,9 +1,44 @@
+use crate::{
+    monitoring::metrics::{MetricsBucket, MetricsStore},
+    tui::{keys::{key_label, Action, KeyMap}, theme::Theme},
+    versioning::storage::VersionMetadata,
+};
 use ratatui::{
-    layout::Rect, style::{Color, Modifier, Style},
-    text::Span, widgets::{Block, Borders, List, ListItem, Paragraph},
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span}, widgets::{List, ListItem, Paragraph, Sparkline},
     Frame,
 };
-pub struct FileListView;
+use std::collections::HashMap;
+pub use super::format::{format_age, format_bytes};
+/// Case-insensitive substring match; everything matches an empty filter
+pub fn matches_filter(text: &str, filter: &str) -> bool {
+    filter.is_empty() || text.to_lowercase().contains(&filter.to_lowercase())
+}
+/// Splits `text` into spans, drawing case-insensitive matches of `filter` in `matched`
+pub fn highlight_matches(
+    text: &str,
+    filter: &str,
+    base: Style,
+    matched: Style,
+) -> Line<'static> {
+    let lower = text.to_lowercase();
+    if filter.is_empty() || lower.len() != text.len() {
+        return Line::from(Span::styled(text.to_string(), base));
+    }
+    let mut spans = Vec::new();
+    let mut last = 0;
+    for (start, m) in lower.match_indices(&filter.to_lowercase()) {
+        spans.push(Span::styled(text[last..start].to_string(), base));
+        spans.push(Span::styled(text[start..start + m.len()].to_string(), matched));
+        last = start + m.len();
+    }
+    spans.push(Span::styled(text[last..].to_string(), base));
+    Line::from(spans)
+}
+pub struct FileListView {
+    pub theme: Theme,
+}
 impl FileListView {
     pub fn render(
         &self,
@@ -11,50 +46,123 @@ impl FileListView {
         area: Rect,
         items: &[crate::WatchedItem],
         selected: Option<usize>,
+        filter: &str,
     ) {
         let items: Vec<ListItem> = items
             .iter()
             .enumerate()
             .map(|(i, item)| {
                 let style = if Some(i) == selected {
-                    Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+                    self.theme.selected_style()
                 } else {
-                    Style::default()
+                    self.theme.text_style()
                 };
+                let mut line = format!("{}: {}", item.id, item.path.display());
+                if let Some(description) = &item.description {
+                    line.push_str(&format!("  # {}", description));
+                }
                 ListItem::new(
-                    Span::styled(format!("{}: {}", item.id, item.path.display()), style),
+                    highlight_matches(
+                        &line,
+                        filter,
+                        style,
+                        self.theme.match_style(style),
+                    ),
                 )
             })
             .collect();
+        let title = if filter.is_empty() {
+            "Watched Files".to_string()
+        } else {
+            format!("Watched Files ({} matching)", items.len())
+        };
         let list = List::new(items)
-            .block(Block::default().borders(Borders::ALL).title("Watched Files"))
+            .block(self.theme.block(title))
             .highlight_style(Style::default().add_modifier(Modifier::BOLD))
             .highlight_symbol("> ");
         f.render_widget(list, area);
     }
 }
-pub struct VersionHistoryView;
+pub struct VersionHistoryView {
+    pub theme: Theme,
+}
 impl VersionHistoryView {
-    pub fn render(&self, f: &mut Frame, area: Rect, versions: &[crate::FileVersion]) {
+    /// Lists `item`'s versions matching `filter` newest first, with stored size and
+    /// compression when known
+    pub fn render(
+        &self,
+        f: &mut Frame,
+        area: Rect,
+        item: Option<&crate::WatchedItem>,
+        metadata: &HashMap<String, VersionMetadata>,
+        selected: Option<usize>,
+        filter: &str,
+    ) {
+        let Some(item) = item else {
+            let paragraph = Paragraph::new("No file selected")
+                .style(self.theme.text_style())
+                .block(self.theme.block("Version History"));
+            f.render_widget(paragraph, area);
+            return;
+        };
+        let versions: Vec<&crate::FileVersion> = item
+            .versions
+            .iter()
+            .rev()
+            .filter(|v| matches_filter(&v.id, filter) || matches_filter(&v.hash, filter))
+            .collect();
         let items: Vec<ListItem> = versions
             .iter()
-            .map(|version| {
+            .enumerate()
+            .map(|(i, version)| {
+                let style = if Some(i) == selected {
+                    self.theme.selected_style()
+                } else {
+                    self.theme.text_style()
+                };
+                let stored = metadata
+                    .get(&version.id)
+                    .map(|m| {
+                        format!(
+                            " → {} bytes stored (level {})", m.compressed_size, m
+                            .compression_level
+                        )
+                    })
+                    .unwrap_or_default();
                 ListItem::new(
-                    format!(
-                        "{}: {} bytes ({})", version.id, version.size, version.timestamp
-                        .duration_since(std::time::UNIX_EPOCH).unwrap_or_default()
-                        .as_secs()
+                    highlight_matches(
+                        &format!(
+                            "{}  {:>8}  {} bytes{}  {}", version.id,
+                            format_age(version.timestamp), version.size, stored, & version
+                            .hash[..8.min(version.hash.len())]
+                        ),
+                        filter,
+                        style,
+                        self.theme.match_style(style),
                     ),
                 )
             })
             .collect();
+        let title = if filter.is_empty() {
+            format!(
+                "Version History: {} ({} versions)", item.path.display(), item.versions
+                .len()
+            )
+        } else {
+            format!(
+                "Version History: {} ({} of {} versions)", item.path.display(), versions
+                .len(), item.versions.len()
+            )
+        };
         let list = List::new(items)
-            .block(Block::default().borders(Borders::ALL).title("Version History"))
+            .block(self.theme.block(title))
             .highlight_style(Style::default().add_modifier(Modifier::BOLD));
         f.render_widget(list, area);
     }
 }
-pub struct SettingsView;
+pub struct SettingsView {
+    pub theme: Theme,
+}
 impl SettingsView {
     pub fn render(&self, f: &mut Frame, area: Rect, config: &crate::SymorConfig) {
         let text = format!(
@@ -69,39 +177,256 @@ impl SettingsView {
             config.linking.preserve_permissions
         );
         let paragraph = Paragraph::new(text)
-            .block(Block::default().borders(Borders::ALL).title("Settings"));
+            .style(self.theme.text_style())
+            .block(self.theme.block("Settings"));
         f.render_widget(paragraph, area);
     }
 }
-pub struct LogsView;
+pub struct LogsView {
+    pub theme: Theme,
+}
 impl LogsView {
-    pub fn render(&self, f: &mut Frame, area: Rect, logs: &[String]) {
-        let items: Vec<ListItem> = logs
+    /// Colour for a log line from its level, as written by env_logger
+    fn level_style(&self, line: &str) -> Style {
+        if line.contains(" ERROR ") || line.contains("[ERROR") {
+            Style::default().fg(self.theme.error)
+        } else if line.contains(" WARN ") || line.contains("[WARN") {
+            Style::default().fg(self.theme.warning)
+        } else if line.contains(" DEBUG ") || line.contains(" TRACE ") {
+            Style::default().fg(self.theme.muted)
+        } else {
+            self.theme.text_style()
+        }
+    }
+    /// Shows the page of `logs` ending `scroll` lines before the newest one
+    pub fn render(
+        &self,
+        f: &mut Frame,
+        area: Rect,
+        logs: &[String],
+        source: Option<&std::path::Path>,
+        scroll: usize,
+    ) {
+        let height = area.height.saturating_sub(2) as usize;
+        let end = logs.len().saturating_sub(scroll);
+        let start = end.saturating_sub(height);
+        let items: Vec<ListItem> = logs[start..end]
             .iter()
-            .map(|log| ListItem::new(log.as_str()))
+            .map(|log| ListItem::new(Span::styled(log.as_str(), self.level_style(log))))
             .collect();
+        let title = match source {
+            Some(path) if logs.is_empty() => format!("Logs: {} (empty)", path.display()),
+            Some(path) => format!("Logs: {}", path.display()),
+            None => "Logs".to_string(),
+        };
         let list = List::new(items)
-            .block(Block::default().borders(Borders::ALL).title("Logs"));
+            .block(self.theme.block(title));
         f.render_widget(list, area);
     }
 }
-pub struct HelpView;
+pub struct PreviewView {
+    pub theme: Theme,
+    /// Colour `+ ` and `- ` lines as added and removed
+    pub diff: bool,
+}
+impl PreviewView {
+    fn line_style(&self, line: &str) -> Style {
+        if self.diff && line.starts_with("+ ") {
+            Style::default().fg(self.theme.success)
+        } else if self.diff && line.starts_with("- ") {
+            Style::default().fg(self.theme.error)
+        } else {
+            self.theme.text_style()
+        }
+    }
+    pub fn render(&self, f: &mut Frame, area: Rect, title: &str, lines: &[String]) {
+        let items: Vec<ListItem> = lines
+            .iter()
+            .map(|line| ListItem::new(Span::styled(line.as_str(), self.line_style(line))))
+            .collect();
+        let list = List::new(items).block(self.theme.block(format!("Preview: {}", title)));
+        f.render_widget(list, area);
+    }
+}
+pub struct ConflictsView {
+    pub theme: Theme,
+}
+impl ConflictsView {
+    /// Lists conflicts, each tagged with its kind
+    pub fn render(
+        &self,
+        f: &mut Frame,
+        area: Rect,
+        conflicts: &[crate::conflicts::Conflict],
+        selected: Option<usize>,
+    ) {
+        use crate::conflicts::ConflictKind;
+        if conflicts.is_empty() {
+            let paragraph = Paragraph::new("No conflicts detected")
+                .style(Style::default().fg(self.theme.success))
+                .block(self.theme.block("Conflicts"));
+            f.render_widget(paragraph, area);
+            return;
+        }
+        let items: Vec<ListItem> = conflicts
+            .iter()
+            .enumerate()
+            .map(|(i, conflict)| {
+                let (label, color) = match conflict.kind {
+                    ConflictKind::Diverged => ("diverged", self.theme.error),
+                    ConflictKind::MissingSource => ("missing", self.theme.warning),
+                    ConflictKind::NoVersions => ("unversioned", self.theme.muted),
+                    ConflictKind::CaseCollision => ("case", self.theme.warning),
+                };
+                let style = if Some(i) == selected {
+                    self.theme.selected_style()
+                } else {
+                    self.theme.text_style()
+                };
+                ListItem::new(
+                    Line::from(
+                        vec![
+                            Span::styled(format!("{:<12}", label), Style::default().fg(color)),
+                            Span::styled(conflict.summary(), style),
+                        ],
+                    ),
+                )
+            })
+            .collect();
+        let list = List::new(items)
+            .block(self.theme.block(format!("Conflicts ({})", conflicts.len())));
+        f.render_widget(list, area);
+    }
+}
+pub struct StatsView {
+    pub theme: Theme,
+}
+impl StatsView {
+    fn sparkline<'a>(&self, title: String, data: &'a [u64], color: Color) -> Sparkline<'a> {
+        Sparkline::default()
+            .block(self.theme.block(title))
+            .data(data)
+            .style(Style::default().fg(color))
+    }
+    /// Hourly activity from the metrics store, one column per hour up to now
+    pub fn render(&self, f: &mut Frame, area: Rect, metrics: &MetricsStore) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Ratio(1, 4),
+                Constraint::Ratio(1, 4),
+                Constraint::Ratio(1, 4),
+                Constraint::Ratio(1, 4),
+            ])
+            .split(area);
+        let hours = (area.width.saturating_sub(2) as usize)
+            .clamp(1, crate::monitoring::metrics::RETENTION_BUCKETS);
+        let now = std::time::SystemTime::now();
+        let series = |field: fn(&MetricsBucket) -> u64| metrics.series(now, hours, field);
+        let versions = series(|b| b.versions_created);
+        let stored = series(|b| b.bytes_stored);
+        let growth: Vec<u64> = stored
+            .iter()
+            .scan(0, |total, &bytes| {
+                *total += bytes;
+                Some(*total)
+            })
+            .collect();
+        let synced = series(|b| b.bytes_synced);
+        let errors = series(|b| b.errors);
+        let totals = metrics.totals();
+        let summary = Paragraph::new(
+                format!(
+                    "All time: {} versions, {} stored, {} syncs ({}), {} errors", totals
+                    .versions_created, format_bytes(totals.bytes_stored), totals.syncs,
+                    format_bytes(totals.bytes_synced), totals.errors
+                ),
+            )
+            .style(self.theme.text_style())
+            .block(self.theme.block(format!("Statistics (last {}h)", hours)));
+        f.render_widget(summary, chunks[0]);
+        let sum = |data: &[u64]| data.iter().sum::<u64>();
+        f.render_widget(
+            self.sparkline(
+                format!("Versions created: {}", sum(&versions)),
+                &versions,
+                self.theme.success,
+            ),
+            chunks[1],
+        );
+        f.render_widget(
+            self.sparkline(
+                format!("Storage growth: +{}", format_bytes(sum(&stored))),
+                &growth,
+                self.theme.accent,
+            ),
+            chunks[2],
+        );
+        f.render_widget(
+            self.sparkline(
+                format!("Sync throughput: {} per hour (peak)", format_bytes(
+                    synced.iter().copied().max().unwrap_or(0)
+                )),
+                &synced,
+                self.theme.header,
+            ),
+            chunks[3],
+        );
+        f.render_widget(
+            self.sparkline(format!("Errors: {}", sum(&errors)), &errors, self.theme.error),
+            chunks[4],
+        );
+    }
+}
+pub struct HelpView {
+    pub theme: Theme,
+}
 impl HelpView {
-    pub fn render(&self, f: &mut Frame, area: Rect) {
-        let help_text = "Symor TUI Help\n\
-                        ==============\n\
-                        \n\
-                        Navigation:\n\
-                        h - Help\n\
-                        f - File List\n\
-                        v - Version History\n\
-                        s - Settings\n\
-                        l - Logs\n\
-                        q - Quit\n\
-                        \n\
-                        Use arrow keys to navigate lists";
-        let paragraph = Paragraph::new(help_text)
-            .block(Block::default().borders(Borders::ALL).title("Help"));
+    fn describe(action: Action) -> &'static str {
+        match action {
+            Action::Quit => "Quit",
+            Action::Search => "Filter files or versions as you type (Esc clears)",
+            Action::Add => "Watch a new path",
+            Action::Unwatch => "Unwatch the selected file (asks first)",
+            Action::Preview => "Toggle preview of the selected file or version",
+            Action::Help => "Help",
+            Action::Files => "File List",
+            Action::Versions => "Version History",
+            Action::Settings => "Settings",
+            Action::Stats => "Statistics",
+            Action::Logs => "Logs",
+            Action::Conflicts => "Conflicts between mirrors and watched files",
+            Action::KeepSource => "Resolve the selected conflict with the source",
+            Action::KeepTarget => "Resolve the selected conflict with the target",
+            Action::KeepBoth => "Keep both sides, saving the target as a copy",
+            Action::Up => "Move up",
+            Action::Down => "Move down",
+            Action::PageUp => "Page up",
+            Action::PageDown => "Page down",
+            Action::PrevFile => "Previous file while viewing history",
+            Action::NextFile => "Next file while viewing history",
+            Action::Select => "Open the selected file's version history",
+            Action::Follow => "Follow new log lines",
+        }
+    }
+    /// Lists every action with the keys currently bound to it
+    pub fn render(&self, f: &mut Frame, area: Rect, keys: &KeyMap) {
+        let mut lines = vec![
+            "Symor TUI Help".to_string(), "==============".to_string(), String::new(),
+        ];
+        for action in Action::ALL {
+            let bound: Vec<String> = keys.keys(action).into_iter().map(key_label).collect();
+            let bound = if bound.is_empty() { "-".to_string() } else { bound.join("/") };
+            lines.push(format!("{:>10} - {}", bound, Self::describe(action)));
+        }
+        lines.push(String::new());
+        lines.push("Click a row to select it, click again to open it;".to_string());
+        lines.push("the mouse wheel scrolls and footer hints can be clicked.".to_string());
+        lines.push("Keys can be remapped in the tui.keys section of the config.".to_string());
+        let paragraph = Paragraph::new(lines.join("\n"))
+            .style(self.theme.text_style())
+            .block(self.theme.block("Help"));
         f.render_widget(paragraph, area);
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,204 @@
+use crate::{
+    errors::{Context, ErrorCode, Result, SymorError},
+    generate_id, secrets, shadow_copy, SymorManager,
+};
+use super::{restore::RestoreResult, storage::{VersionMetadata, VersionStorage}};
+use log::{info, warn};
+use std::{path::Path, time::SystemTime};
+/// Runs blocking work (compression, shadow copies, restores) off the async executor
+async fn blocking<T, F>(work: F) -> Result<T>
+where
+    F: FnOnce() -> Result<T> + Send + 'static,
+    T: Send + 'static,
+{
+    tokio::task::spawn_blocking(work)
+        .await
+        .map_err(|e| {
+            SymorError::new(ErrorCode::InternalError, format!("Blocking task failed: {}", e))
+        })?
+}
+impl VersionStorage {
+    /// `store_version` for async callers: compresses on the blocking pool and writes
+    /// with `tokio::fs`
+    pub async fn store_version_async(
+        &self,
+        file_path: &Path,
+        content: Vec<u8>,
+        version_id: &str,
+    ) -> Result<VersionMetadata> {
+        let level = self.config.compression_level;
+        self.store_version_async_at(file_path, content, version_id, level).await
+    }
+    /// `store_version_at` for async callers
+    pub async fn store_version_async_at(
+        &self,
+        file_path: &Path,
+        content: Vec<u8>,
+        version_id: &str,
+        level: u8,
+    ) -> Result<VersionMetadata> {
+        let storage = self.clone();
+        let (content, compressed_data) = blocking(move || {
+                let compressed = storage.compress_data(&content, level)?;
+                Ok((content, compressed))
+            })
+            .await?;
+        let storage_path = self.get_storage_path(version_id);
+        let temp_path = storage_path.with_extension("tmp");
+        if let Some(parent) = temp_path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        if let Err(e) = tokio::fs::write(&temp_path, &compressed_data).await {
+            let _ = tokio::fs::remove_file(&temp_path).await;
+            return Err(e.into());
+        }
+        tokio::fs::rename(&temp_path, &storage_path).await?;
+        let metadata = VersionMetadata {
+            id: version_id.to_string(),
+            original_path: file_path.to_path_buf(),
+            timestamp: SystemTime::now(),
+            size: content.len() as u64,
+            compressed_size: compressed_data.len() as u64,
+            hash: format!("{:x}", md5::compute(& content)),
+            compression_level: level,
+            owner: super::storage::file_owner(file_path),
+            dictionary: super::dictionary::dictionary_id(&compressed_data).map(String::from),
+            link_target: None,
+        };
+        let metadata_path = self.get_metadata_path(version_id);
+        if let Some(parent) = metadata_path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(&metadata_path, serde_json::to_string_pretty(&metadata)?).await?;
+        Ok(metadata)
+    }
+    /// `retrieve_version` for async callers
+    pub async fn retrieve_version_async(
+        &self,
+        version_id: &str,
+    ) -> Result<(Vec<u8>, VersionMetadata)> {
+        let storage_path = self.get_storage_path(version_id);
+        let compressed_data = tokio::fs::read(&storage_path)
+            .await
+            .with_context(|| {
+                format!("Failed to read version file: {:?}", storage_path)
+            })?;
+        let storage = self.clone();
+        let decompressed_data = blocking(move || storage.decompress_data(&compressed_data))
+            .await?;
+        let json_data = tokio::fs::read_to_string(self.get_metadata_path(version_id)).await?;
+        let metadata: VersionMetadata = serde_json::from_str(&json_data)?;
+        Ok((decompressed_data, metadata))
+    }
+}
+impl SymorManager {
+    /// `create_backup` for async callers
+    pub async fn create_backup_async(&mut self, item_id: &str) -> Result<()> {
+        self.ensure_writable("create backups")?;
+        let storage = self.version_storage.clone();
+        let lock = blocking(move || storage.lock_shared()).await?;
+        self.reload_after_cleanup()?;
+        let Some((path, level)) = self.backup_path(item_id)? else {
+            return Ok(());
+        };
+        let content = if self.config.versioning.shadow_copy {
+            let source = path.clone();
+            blocking(move || shadow_copy::read_file(&source, true)).await?
+        } else {
+            tokio::fs::read(&path).await?
+        };
+        let findings = secrets::screen(&path, &content, self.config.versioning.secret_scan)?;
+        let version_id = generate_id();
+        let (metadata, lock) = match self
+            .version_storage
+            .store_version_async_at(&path, content.clone(), &version_id, level)
+            .await
+        {
+            Err(e) if e.code() == &ErrorCode::DiskFull => {
+                warn!("Disk full while storing version for {:?}, reclaiming space", path);
+                drop(lock);
+                if self.reclaim_version_space()? == 0 {
+                    return Err(e);
+                }
+                let storage = self.version_storage.clone();
+                let lock = blocking(move || storage.lock_shared()).await?;
+                self.reload_after_cleanup()?;
+                let metadata = self
+                    .version_storage
+                    .store_version_async_at(&path, content, &version_id, level)
+                    .await?;
+                (metadata, lock)
+            }
+            result => (result?, lock),
+        };
+        self.warn_secrets(item_id, &path, findings);
+        self.record_backup(item_id, metadata)?;
+        drop(lock);
+        if !self.pruned.is_empty() {
+            let storage = self.version_storage.clone();
+            let lock = blocking(move || storage.lock_exclusive()).await?;
+            self.delete_pruned_locked(lock);
+        }
+        Ok(())
+    }
+    /// `restore_file` for async callers
+    pub async fn restore_file_async(
+        &self,
+        file_id: &str,
+        version_id: &str,
+        target_path: &Path,
+    ) -> Result<RestoreResult> {
+        self.ensure_writable("restore files")?;
+        let storage = self.version_storage.clone();
+        let _lock = blocking(move || storage.lock_shared()).await?;
+        let version = self.find_version(file_id, version_id)?;
+        let storage = &self.version_storage;
+        let (content, hash, owner) = match storage.retrieve_version_async(version_id).await {
+            Ok((content, metadata)) => (content, metadata.hash, metadata.owner),
+            Err(_) => {
+                let content = tokio::fs::read(Self::legacy_backup_path(version)?).await?;
+                (content, version.hash.clone(), None)
+            }
+        };
+        let engine = self.restore_engine.clone();
+        let options = self.restore_options(&hash, owner)?;
+        let target = target_path.to_path_buf();
+        let result = blocking(move || engine.restore_file(&target, &content, &options)).await?;
+        info!("Restored {:?} to {:?}", version.path, target_path);
+        Ok(result)
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::versioning::storage::StorageConfig;
+    use tempfile::tempdir;
+    #[tokio::test]
+    async fn test_async_store_and_retrieve() {
+        let temp_dir = tempdir().unwrap();
+        let storage = VersionStorage::with_config(StorageConfig {
+            storage_path: temp_dir.path().to_path_buf(),
+            ..StorageConfig::default()
+        });
+        let file = temp_dir.path().join("notes.txt");
+        let metadata = storage
+            .store_version_async(&file, b"async content".to_vec(), "v1")
+            .await
+            .unwrap();
+        assert_eq!(metadata.size, 13);
+        let (content, loaded) = storage.retrieve_version("v1").unwrap();
+        assert_eq!(content, b"async content");
+        assert_eq!(loaded.hash, metadata.hash);
+        let (content, _) = storage.retrieve_version_async("v1").await.unwrap();
+        assert_eq!(content, b"async content");
+        assert!(storage.retrieve_version_async("missing").await.is_err());
+    }
+    #[test]
+    fn test_manager_futures_are_send() {
+        fn assert_send<T: Send>(_: &T) {}
+        let mut manager = SymorManager::new().unwrap();
+        let target = Path::new("restored.txt");
+        assert_send(&manager.restore_file_async("id", "v1", target));
+        assert_send(&manager.create_backup_async("id"));
+    }
+}
\ No newline at end of file
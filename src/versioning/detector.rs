@@ -1,7 +1,6 @@
-use anyhow::{Context, Result};
-use md5;
+use crate::errors::{Context, Result};
 use std::{
-    collections::HashMap, path::{Path, PathBuf},
+    borrow::Cow, collections::HashMap, path::{Path, PathBuf},
     time::{Duration, SystemTime},
 };
 #[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
@@ -25,6 +24,11 @@ pub struct ChangeDetectorConfig {
     pub debounce_delay: Duration,
     pub hash_algorithm: HashAlgorithm,
     pub ignore_patterns: Vec<String>,
+    /// Ignore changes to dot-files such as `.DS_Store` or `.envrc`
+    pub skip_hidden: bool,
+    /// Track paths by their Unicode NFC form, so a name spelled decomposed (NFD) by
+    /// macOS is the same file as its composed form
+    pub normalize_unicode: bool,
 }
 impl Default for ChangeDetectorConfig {
     fn default() -> Self {
@@ -35,6 +39,8 @@ impl Default for ChangeDetectorConfig {
                 "*.tmp".to_string(), "*.swp".to_string(), ".git/**".to_string(),
                 "target/**".to_string(),
             ],
+            skip_hidden: false,
+            normalize_unicode: true,
         }
     }
 }
@@ -64,12 +70,13 @@ impl ChangeDetector {
         if !self.should_process_file(path) {
             return Ok(None);
         }
+        let key = self.key(path).into_owned();
         if path.is_dir() {
             let exists = path.exists();
-            let was_tracked = self.last_hashes.contains_key(path);
+            let was_tracked = self.last_hashes.contains_key(&key);
             match (was_tracked, exists) {
                 (false, true) => {
-                    self.last_hashes.insert(path.to_path_buf(), "directory".to_string());
+                    self.last_hashes.insert(key, "directory".to_string());
                     return Ok(
                         Some(FileChangeEvent {
                             path: path.to_path_buf(),
@@ -82,7 +89,7 @@ impl ChangeDetector {
                     );
                 }
                 (true, false) => {
-                    self.last_hashes.remove(path);
+                    self.last_hashes.remove(&key);
                     return Ok(
                         Some(FileChangeEvent {
                             path: path.to_path_buf(),
@@ -98,10 +105,10 @@ impl ChangeDetector {
             }
         }
         let current_hash = self.calculate_file_hash(path)?;
-        let previous_hash = self.last_hashes.get(path);
+        let previous_hash = self.last_hashes.get(&key);
         let change_event = match (previous_hash, path.exists()) {
             (None, true) => {
-                self.last_hashes.insert(path.to_path_buf(), current_hash.clone());
+                self.last_hashes.insert(key, current_hash.clone());
                 Some(FileChangeEvent {
                     path: path.to_path_buf(),
                     change_type: ChangeType::Created,
@@ -113,7 +120,7 @@ impl ChangeDetector {
             }
             (Some(old_hash), true) if old_hash != &current_hash => {
                 let old_hash_clone = old_hash.clone();
-                self.last_hashes.insert(path.to_path_buf(), current_hash.clone());
+                self.last_hashes.insert(key, current_hash.clone());
                 Some(FileChangeEvent {
                     path: path.to_path_buf(),
                     change_type: ChangeType::Modified,
@@ -124,7 +131,7 @@ impl ChangeDetector {
                 })
             }
             (Some(_), false) => {
-                self.last_hashes.remove(path);
+                self.last_hashes.remove(&key);
                 Some(FileChangeEvent {
                     path: path.to_path_buf(),
                     change_type: ChangeType::Deleted,
@@ -141,6 +148,20 @@ impl ChangeDetector {
         }
         Ok(change_event)
     }
+    pub fn set_skip_hidden(&mut self, skip_hidden: bool) {
+        self.config.skip_hidden = skip_hidden;
+    }
+    pub fn set_normalize_unicode(&mut self, normalize: bool) {
+        self.config.normalize_unicode = normalize;
+    }
+    /// What `path` is tracked under: its NFC form when normalizing Unicode names
+    pub fn key<'a>(&self, path: &'a Path) -> Cow<'a, Path> {
+        if self.config.normalize_unicode {
+            crate::normalize::key(path)
+        } else {
+            Cow::Borrowed(path)
+        }
+    }
     pub fn scan_files(&mut self, paths: &[PathBuf]) -> Result<Vec<FileChangeEvent>> {
         let mut changes = Vec::new();
         for path in paths {
@@ -151,6 +172,11 @@ impl ChangeDetector {
         Ok(changes)
     }
     fn should_process_file(&self, path: &Path) -> bool {
+        if self.config.skip_hidden
+            && path.file_name().is_some_and(|n| n.to_string_lossy().starts_with('.'))
+        {
+            return false;
+        }
         let path_str = path.to_string_lossy();
         for pattern in &self.config.ignore_patterns {
             if self.matches_pattern(&path_str, pattern) {
@@ -186,12 +212,12 @@ impl ChangeDetector {
             path.contains(pattern)
         }
     }
-    fn calculate_file_hash(&self, path: &Path) -> Result<String> {
+    /// Hash of the file at `path` in the configured algorithm, streamed from disk
+    pub fn calculate_file_hash(&self, path: &Path) -> Result<String> {
         match self.config.hash_algorithm {
             HashAlgorithm::MD5 => {
-                let content = std::fs::read(path)
-                    .with_context(|| format!("Failed to read file: {:?}", path))?;
-                Ok(format!("{:x}", md5::compute(& content)))
+                crate::performance::memory::hash_file(path)
+                    .with_context(|| format!("Failed to read file: {:?}", path))
             }
         }
     }
@@ -201,6 +227,22 @@ impl ChangeDetector {
     pub fn clear_hashes(&mut self) {
         self.last_hashes.clear();
     }
+    /// Content hash of every path scanned so far, by `key`
+    pub fn hashes(&self) -> &HashMap<PathBuf, String> {
+        &self.last_hashes
+    }
+    /// Drops the hash of `path`, so its next scan reports it again
+    pub fn forget(&mut self, path: &Path) {
+        let key = self.key(path).into_owned();
+        self.last_hashes.remove(&key);
+    }
+    /// Picks up hashes saved by an earlier run, so unchanged files are not reported
+    pub fn set_hashes(&mut self, hashes: HashMap<PathBuf, String>) {
+        self.last_hashes = hashes
+            .into_iter()
+            .map(|(path, hash)| (self.key(&path).into_owned(), hash))
+            .collect();
+    }
     pub fn stats(&self) -> ChangeDetectorStats {
         ChangeDetectorStats {
             tracked_files: self.last_hashes.len(),
@@ -250,5 +292,9 @@ mod tests {
         assert!(! detector.should_process_file(Path::new("target/debug/binary")));
         assert!(! detector.should_process_file(Path::new("file.tmp")));
         assert!(detector.should_process_file(Path::new("src/main.rs")));
+        assert!(detector.should_process_file(Path::new("src/.envrc")));
+        detector.set_skip_hidden(true);
+        assert!(! detector.should_process_file(Path::new("src/.envrc")));
+        assert!(detector.should_process_file(Path::new("src/main.rs")));
     }
 }
\ No newline at end of file
@@ -0,0 +1,382 @@
+use super::storage::VersionStorage;
+use crate::{
+    errors::{Context, ErrorCode, Result, SymorError},
+    sync_state::FileStamp,
+};
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compress, Compression, Decompress};
+use log::warn;
+use std::{
+    cmp::Reverse, collections::{BinaryHeap, HashMap, HashSet},
+    fs, io::{Read, Write}, path::PathBuf, sync::{Arc, Mutex},
+};
+/// Starts version data compressed against a dictionary, followed by the dictionary ID
+const MAGIC: &[u8; 4] = b"SYD1";
+const ID_LEN: usize = 16;
+/// Largest useful dictionary, the deflate window
+pub const MAX_DICTIONARY_SIZE: usize = 32 * 1024;
+/// Only files up to this size are trained on and compressed with the dictionary; larger
+/// ones gain little from it
+pub const SMALL_FILE_LIMIT: u64 = 128 * 1024;
+/// Most sample bytes `train_dictionary` reads from the store
+const MAX_SAMPLE_BYTES: usize = 4 * 1024 * 1024;
+/// Length of the byte strings whose spread across samples scores a segment
+const KMER: usize = 8;
+const SEGMENT: usize = 64;
+/// Dictionary built from byte `samples`: the segments whose 8-byte strings occur in the
+/// most samples, picked greedily so each adds content not yet covered, with the best
+/// last, where deflate reaches it with the shortest distances
+pub fn train(samples: &[Vec<u8>], max_size: usize) -> Vec<u8> {
+    let mut frequency: HashMap<&[u8], u32> = HashMap::new();
+    for sample in samples {
+        let distinct: HashSet<&[u8]> = sample.windows(KMER).collect();
+        for kmer in distinct {
+            *frequency.entry(kmer).or_default() += 1;
+        }
+    }
+    let segments: Vec<&[u8]> = samples
+        .iter()
+        .flat_map(|sample| {
+            (0..sample.len().saturating_sub(KMER - 1))
+                .step_by(SEGMENT / 2)
+                .map(|start| &sample[start..sample.len().min(start + SEGMENT)])
+        })
+        .collect();
+    let mut covered: HashSet<&[u8]> = HashSet::new();
+    let score = |segment: &[u8], covered: &HashSet<&[u8]>| -> u32 {
+        let distinct: HashSet<&[u8]> = segment.windows(KMER).collect();
+        distinct
+            .into_iter()
+            .filter(|kmer| !covered.contains(kmer))
+            .map(|kmer| frequency[kmer] - 1)
+            .sum()
+    };
+    let mut heap: BinaryHeap<(u32, Reverse<usize>)> = segments
+        .iter()
+        .enumerate()
+        .map(|(index, segment)| (score(segment, &covered), Reverse(index)))
+        .filter(|(score, _)| *score > 0)
+        .collect();
+    let mut picked: Vec<&[u8]> = Vec::new();
+    let mut size = 0;
+    while let Some((stale, Reverse(index))) = heap.pop() {
+        if size >= max_size {
+            break;
+        }
+        let fresh = score(segments[index], &covered);
+        if fresh == 0 {
+            continue;
+        }
+        if fresh < stale && heap.peek().is_some_and(|(next, _)| *next > fresh) {
+            heap.push((fresh, Reverse(index)));
+            continue;
+        }
+        covered.extend(segments[index].windows(KMER));
+        picked.push(segments[index]);
+        size += segments[index].len();
+    }
+    let dictionary: Vec<u8> = picked.into_iter().rev().flatten().copied().collect();
+    dictionary[dictionary.len().saturating_sub(max_size)..].to_vec()
+}
+/// Outcome of `VersionStorage::train_dictionary`
+#[derive(Debug, Clone)]
+pub struct DictionaryReport {
+    pub id: String,
+    /// Files sampled, the newest small version of each
+    pub samples: usize,
+    pub size: usize,
+    /// Compressed size of the samples without and with the dictionary
+    pub before: u64,
+    pub after: u64,
+    /// Unused older dictionaries deleted
+    pub removed: usize,
+}
+/// The active dictionary as last read, so storing many small versions reads it once.
+/// Shared by clones of a `VersionStorage`; the stamp of the `active` file tells when
+/// another process switched dictionaries.
+#[derive(Debug, Clone, Default)]
+pub(super) struct DictionaryCache(Arc<Mutex<Option<CachedDictionary>>>);
+#[derive(Debug)]
+struct CachedDictionary {
+    stamp: Option<FileStamp>,
+    /// ID and content of the active dictionary; `None` when there is none to use
+    active: Option<(String, Arc<[u8]>)>,
+}
+impl DictionaryCache {
+    fn lock(&self) -> std::sync::MutexGuard<'_, Option<CachedDictionary>> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+fn dictionary_error(message: String) -> SymorError {
+    SymorError::new(ErrorCode::VersionCorrupted, message)
+}
+impl VersionStorage {
+    fn dictionaries_dir(&self) -> PathBuf {
+        self.config.storage_path.join("dictionaries")
+    }
+    fn dictionary_path(&self, id: &str) -> PathBuf {
+        self.dictionaries_dir().join(format!("{}.dict", id))
+    }
+    /// ID of the dictionary new small versions are compressed with, if any
+    pub fn active_dictionary(&self) -> Option<String> {
+        let id = fs::read_to_string(self.dictionaries_dir().join("active")).ok()?;
+        let id = id.trim();
+        (id.len() == ID_LEN).then(|| id.to_string())
+    }
+    /// Compresses new versions with dictionary `id`, or without one for `None`; versions
+    /// already stored keep theirs
+    pub fn set_active_dictionary(&self, id: Option<&str>) -> Result<()> {
+        let active = self.dictionaries_dir().join("active");
+        match id {
+            Some(id) => {
+                fs::create_dir_all(self.dictionaries_dir())?;
+                fs::write(&active, id)?;
+            }
+            None if active.exists() => fs::remove_file(&active)?,
+            None => {}
+        }
+        *self.dictionary_cache.lock() = None;
+        Ok(())
+    }
+    /// ID and content of the active dictionary, read from disk only when the cache does
+    /// not hold it yet or the `active` file changed since
+    fn cached_dictionary(&self) -> Option<(String, Arc<[u8]>)> {
+        let stamp = FileStamp::of(&self.dictionaries_dir().join("active"));
+        let mut cache = self.dictionary_cache.lock();
+        if let Some(cached) = cache.as_ref().filter(|cached| cached.stamp == stamp) {
+            return cached.active.clone();
+        }
+        let active = self.active_dictionary().and_then(|id| {
+            match self.read_dictionary(&id) {
+                Ok(dictionary) => Some((id, dictionary)),
+                Err(e) => {
+                    warn!("Cannot compress with dictionary {}, compressing without: {}", id, e);
+                    None
+                }
+            }
+        });
+        *cache = Some(CachedDictionary { stamp, active: active.clone() });
+        active
+    }
+    /// Content of dictionary `id`, from the cache when it is the active one; IDs are
+    /// content hashes, so cached content never goes stale
+    fn load_dictionary(&self, id: &str) -> Result<Arc<[u8]>> {
+        let cached = self.dictionary_cache.lock().as_ref().and_then(|cached| {
+            cached.active.as_ref().filter(|(active, _)| active == id).map(|(_, d)| d.clone())
+        });
+        match cached {
+            Some(dictionary) => Ok(dictionary),
+            None => self.read_dictionary(id),
+        }
+    }
+    fn read_dictionary(&self, id: &str) -> Result<Arc<[u8]>> {
+        let path = self.dictionary_path(id);
+        let dictionary = fs::read(&path)
+            .with_context(|| format!("Cannot read compression dictionary {:?}", path))?;
+        Ok(Arc::from(dictionary))
+    }
+    /// IDs of the stored dictionaries
+    pub fn dictionaries(&self) -> Vec<String> {
+        let Ok(entries) = fs::read_dir(self.dictionaries_dir()) else {
+            return Vec::new();
+        };
+        let mut ids: Vec<String> = entries
+            .flatten()
+            .filter_map(|entry| {
+                let path = entry.path();
+                (path.extension()? == "dict").then_some(path.file_stem()?.to_str()?.to_string())
+            })
+            .collect();
+        ids.sort();
+        ids
+    }
+    /// `data` compressed at `level` against the active dictionary, when there is one and
+    /// `data` is small; `None` to compress it without
+    pub(super) fn compress_with_dictionary(&self, data: &[u8], level: u8) -> Option<Vec<u8>> {
+        if level == 0 || data.len() as u64 > SMALL_FILE_LIMIT {
+            return None;
+        }
+        let (id, dictionary) = self.cached_dictionary()?;
+        match deflate(&dictionary, &id, data, level) {
+            Ok(compressed) => Some(compressed),
+            Err(e) => {
+                warn!("Cannot compress with dictionary {}, compressing without: {}", id, e);
+                None
+            }
+        }
+    }
+    /// Inflates `data` when it was compressed against a dictionary; `None` when not
+    pub(super) fn decompress_with_dictionary(&self, data: &[u8]) -> Option<Result<Vec<u8>>> {
+        let id = dictionary_id(data)?;
+        Some(self.load_dictionary(id).and_then(|dictionary| {
+            let mut decompress = Decompress::new(false);
+            decompress.set_dictionary(&dictionary).map_err(|e| {
+                dictionary_error(format!("Cannot use compression dictionary {}: {}", id, e))
+            })?;
+            let mut decoder =
+                ZlibDecoder::new_with_decompress(&data[MAGIC.len() + ID_LEN..], decompress);
+            let mut decompressed = Vec::new();
+            decoder.read_to_end(&mut decompressed).map_err(|e| {
+                dictionary_error(format!("Failed to decompress version data: {}", e))
+            })?;
+            Ok(decompressed)
+        }))
+    }
+    /// Trains a dictionary on the newest version of each small file in the store and
+    /// makes it the active one, deleting older dictionaries no version uses
+    pub fn train_dictionary(&self) -> Result<DictionaryReport> {
+        let mut newest: HashMap<PathBuf, super::VersionMetadata> = HashMap::new();
+        let mut used: HashSet<String> = HashSet::new();
+        for metadata in self.list_metadata()? {
+            if let Some(id) = &metadata.dictionary {
+                used.insert(id.clone());
+            }
+            if metadata.size == 0 || metadata.size > SMALL_FILE_LIMIT {
+                continue;
+            }
+            match newest.get(&metadata.original_path) {
+                Some(kept) if kept.timestamp >= metadata.timestamp => {}
+                _ => {
+                    newest.insert(metadata.original_path.clone(), metadata);
+                }
+            }
+        }
+        let mut newest: Vec<_> = newest.into_values().collect();
+        newest.sort_by_key(|metadata| Reverse(metadata.timestamp));
+        let mut samples = Vec::new();
+        let mut total = 0;
+        for metadata in newest {
+            if total + metadata.size as usize > MAX_SAMPLE_BYTES {
+                continue;
+            }
+            match self.retrieve_version(&metadata.id) {
+                Ok((content, _)) => {
+                    total += content.len();
+                    samples.push(content);
+                }
+                Err(e) => warn!("Skipping version {} for the dictionary: {}", metadata.id, e),
+            }
+        }
+        if samples.len() < 2 {
+            return Err(
+                SymorError::new(
+                    ErrorCode::VersionNotFound,
+                    "A dictionary needs versions of at least two small files".to_string(),
+                )
+                    .with_suggestion(
+                        format!(
+                            "Watch more files of up to {} KiB before training",
+                            SMALL_FILE_LIMIT / 1024
+                        ),
+                    ),
+            );
+        }
+        let dictionary = train(&samples, MAX_DICTIONARY_SIZE);
+        let id = format!("{:x}", md5::compute(&dictionary))[..ID_LEN].to_string();
+        let level = self.config.compression_level.max(1);
+        let (mut before, mut after) = (0, 0);
+        for sample in &samples {
+            before += self.compress_data_plain(sample, level)?.len() as u64;
+            after += deflate(&dictionary, &id, sample, level)?.len() as u64;
+        }
+        fs::create_dir_all(self.dictionaries_dir())?;
+        fs::write(self.dictionary_path(&id), &dictionary)?;
+        self.set_active_dictionary(Some(&id))?;
+        let mut removed = 0;
+        for old in self.dictionaries() {
+            if old != id && !used.contains(&old) && fs::remove_file(self.dictionary_path(&old))
+                .is_ok()
+            {
+                removed += 1;
+            }
+        }
+        Ok(
+            DictionaryReport {
+                id,
+                samples: samples.len(),
+                size: dictionary.len(),
+                before,
+                after,
+                removed,
+            },
+        )
+    }
+}
+/// ID of the dictionary version `data` was compressed against
+pub(super) fn dictionary_id(data: &[u8]) -> Option<&str> {
+    let id = data.strip_prefix(MAGIC)?.get(..ID_LEN)?;
+    std::str::from_utf8(id).ok()
+}
+fn deflate(dictionary: &[u8], id: &str, data: &[u8], level: u8) -> Result<Vec<u8>> {
+    let mut compress = Compress::new(Compression::new(level as u32), false);
+    compress
+        .set_dictionary(dictionary)
+        .map_err(|e| dictionary_error(format!("Cannot use compression dictionary: {}", e)))?;
+    let mut output = MAGIC.to_vec();
+    output.extend_from_slice(id.as_bytes());
+    let mut encoder = ZlibEncoder::new_with_compress(output, compress);
+    encoder.write_all(data)?;
+    encoder.finish().context("Failed to compress data")
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::versioning::storage::StorageConfig;
+    use std::path::Path;
+    use tempfile::tempdir;
+    fn config_file(n: usize) -> Vec<u8> {
+        format!(
+            "[server]\nhost = \"10.0.0.{n}\"\nport = {}\nworkers = 4\n\n[logging]\n\
+             level = \"info\"\nformat = \"json\"\nrotate_after_days = 14\n\n[database]\n\
+             url = \"postgres://db-{n}.internal:5432/app\"\npool_size = 16\n",
+            8000 + n
+        )
+            .into_bytes()
+    }
+    #[test]
+    fn test_dictionary_compression() {
+        let samples: Vec<Vec<u8>> = (0..6).map(config_file).collect();
+        let dictionary = train(&samples, 1024);
+        assert!(!dictionary.is_empty() && dictionary.len() <= 1024);
+        assert!(dictionary.windows(10).any(|w| w == b"pool_size "));
+        assert!(train(&samples[..1], 1024).is_empty());
+        let temp_dir = tempdir().unwrap();
+        let storage = VersionStorage::with_config(StorageConfig {
+            storage_path: temp_dir.path().join("versions"),
+            ..Default::default()
+        });
+        let path = Path::new("app.toml");
+        let plain = storage.store_version(path, &config_file(1), "plain").unwrap();
+        assert!(plain.dictionary.is_none());
+        let error = storage.train_dictionary().unwrap_err();
+        assert_eq!(error.code, ErrorCode::VersionNotFound);
+        for n in 2..6 {
+            let other = format!("app-{}.toml", n);
+            storage.store_version(Path::new(&other), &config_file(n), &other).unwrap();
+        }
+        let report = storage.train_dictionary().unwrap();
+        assert_eq!(report.samples, 5);
+        assert!(report.after * 2 < report.before, "{:?}", report);
+        assert_eq!(storage.active_dictionary(), Some(report.id.clone()));
+        let content = config_file(9);
+        let small = storage.store_version(path, &content, "small").unwrap();
+        assert_eq!(small.dictionary.as_deref(), Some(report.id.as_str()));
+        assert!(small.compressed_size < plain.compressed_size);
+        assert_eq!(storage.retrieve_version("small").unwrap().0, content);
+        assert_eq!(storage.retrieve_version("plain").unwrap().0, config_file(1));
+        let stats = storage.get_stats().unwrap();
+        assert_eq!(stats.dictionary_versions, 1);
+        let dictionary_path = storage.dictionary_path(&report.id);
+        let dictionary = fs::read(&dictionary_path).unwrap();
+        fs::remove_file(&dictionary_path).unwrap();
+        let cached = storage.store_version(path, &content, "cached").unwrap();
+        assert_eq!(cached.dictionary.as_deref(), Some(report.id.as_str()));
+        assert_eq!(storage.retrieve_version("cached").unwrap().0, content);
+        fs::write(&dictionary_path, dictionary).unwrap();
+        storage.set_active_dictionary(None).unwrap();
+        let retrained = storage.train_dictionary().unwrap();
+        assert_eq!(retrained.removed, 0);
+        assert!(storage.dictionaries().contains(&report.id));
+        storage.set_active_dictionary(None).unwrap();
+        assert!(storage.store_version(path, &content, "after").unwrap().dictionary.is_none());
+    }
+}
\ No newline at end of file
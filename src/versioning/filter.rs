@@ -0,0 +1,176 @@
+use crate::{errors::{ErrorCode, Result, SymorError}, FileVersion};
+use std::{str::FromStr, time::{Duration, SystemTime}};
+const SYNTAX: &str = "Combine terms with &&, e.g. 'size>1MB && age<7d'; fields are size \
+                      (B, KB, MB, GB), age (s, m, h, d, w, y) and hash (prefix, = or !=)";
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+}
+impl Op {
+    fn test<T: PartialOrd>(self, left: T, right: T) -> bool {
+        match self {
+            Op::Lt => left < right,
+            Op::Le => left <= right,
+            Op::Gt => left > right,
+            Op::Ge => left >= right,
+            Op::Eq => left == right,
+            Op::Ne => left != right,
+        }
+    }
+}
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Term {
+    Size(Op, u64),
+    Age(Op, Duration),
+    Hash(Op, String),
+}
+/// Versions selected by a `--where` expression: terms such as `size>1MB` or `age<7d`
+/// that must all hold
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VersionFilter {
+    terms: Vec<Term>,
+}
+fn invalid(message: String) -> SymorError {
+    SymorError::new(ErrorCode::InvalidConfiguration, message)
+        .with_suggestion(SYNTAX.to_string())
+}
+/// Splits `number unit` and scales the number by the unit's multiplier
+pub(crate) fn parse_quantity(value: &str, units: &[(&str, u64)]) -> Option<u64> {
+    let split = value.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: f64 = number.parse().ok()?;
+    let unit = unit.trim().to_ascii_lowercase();
+    let (_, scale) = units.iter().find(|(name, _)| *name == unit)?;
+    Some((number * *scale as f64) as u64)
+}
+pub(crate) const SIZE_UNITS: &[(&str, u64)] = &[
+    ("", 1),
+    ("b", 1),
+    ("kb", 1 << 10),
+    ("k", 1 << 10),
+    ("mb", 1 << 20),
+    ("m", 1 << 20),
+    ("gb", 1 << 30),
+    ("g", 1 << 30),
+];
+pub(crate) const AGE_UNITS: &[(&str, u64)] = &[
+    ("s", 1),
+    ("m", 60),
+    ("min", 60),
+    ("h", 3600),
+    ("d", 86_400),
+    ("w", 7 * 86_400),
+    ("y", 365 * 86_400),
+];
+fn parse_term(text: &str) -> Result<Term> {
+    let Some(at) = text.find(['<', '>', '=', '!']) else {
+        return Err(invalid(format!("Missing comparison in filter term: {}", text)));
+    };
+    let (field, rest) = text.split_at(at);
+    let operators = [
+        ("<=", Op::Le),
+        (">=", Op::Ge),
+        ("!=", Op::Ne),
+        ("==", Op::Eq),
+        ("<", Op::Lt),
+        (">", Op::Gt),
+        ("=", Op::Eq),
+    ];
+    let (op, value) = operators
+        .iter()
+        .find_map(|(symbol, op)| rest.strip_prefix(symbol).map(|value| (*op, value.trim())))
+        .ok_or_else(|| invalid(format!("Unknown comparison in filter term: {}", text)))?;
+    let bad_value = || invalid(format!("Invalid value in filter term: {}", text));
+    match field.trim().to_ascii_lowercase().as_str() {
+        "size" => {
+            parse_quantity(value, SIZE_UNITS)
+                .map(|bytes| Term::Size(op, bytes))
+                .ok_or_else(bad_value)
+        }
+        "age" => {
+            parse_quantity(value, AGE_UNITS)
+                .map(|secs| Term::Age(op, Duration::from_secs(secs)))
+                .ok_or_else(bad_value)
+        }
+        "hash" if matches!(op, Op::Eq | Op::Ne) && !value.is_empty()
+            && value.chars().all(|c| c.is_ascii_hexdigit()) => {
+            Ok(Term::Hash(op, value.to_ascii_lowercase()))
+        }
+        "hash" => Err(bad_value()),
+        other => Err(invalid(format!("Unknown filter field: {}", other))),
+    }
+}
+impl VersionFilter {
+    /// Whether `version` satisfies every term, with ages measured from `now`
+    pub fn matches_at(&self, version: &FileVersion, now: SystemTime) -> bool {
+        let age = now.duration_since(version.timestamp).unwrap_or_default();
+        self.terms
+            .iter()
+            .all(|term| match term {
+                Term::Size(op, bytes) => op.test(version.size, *bytes),
+                Term::Age(op, limit) => op.test(age, *limit),
+                Term::Hash(op, prefix) => {
+                    (*op == Op::Eq) == version.hash.starts_with(prefix.as_str())
+                }
+            })
+    }
+    pub fn matches(&self, version: &FileVersion) -> bool {
+        self.matches_at(version, SystemTime::now())
+    }
+}
+impl FromStr for VersionFilter {
+    type Err = SymorError;
+    fn from_str(expression: &str) -> Result<Self> {
+        let terms = expression
+            .split("&&")
+            .map(str::trim)
+            .filter(|term| !term.is_empty())
+            .map(parse_term)
+            .collect::<Result<Vec<_>>>()?;
+        if terms.is_empty() {
+            return Err(invalid("Empty version filter".to_string()));
+        }
+        Ok(Self { terms })
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{path::PathBuf, time::UNIX_EPOCH};
+    fn version(size: u64, secs: u64, hash: &str) -> FileVersion {
+        FileVersion {
+            id: "v".to_string(),
+            timestamp: UNIX_EPOCH + Duration::from_secs(secs),
+            size,
+            hash: hash.to_string(),
+            path: PathBuf::from("f"),
+            backup_path: None,
+        }
+    }
+    #[test]
+    fn test_version_filter() {
+        let now = UNIX_EPOCH + Duration::from_secs(30 * 86_400);
+        let big_old = version(3 << 20, 86_400, "abc123");
+        let small_new = version(512, 29 * 86_400, "def456");
+        let filter: VersionFilter = "size>1MB && age<7d".parse().unwrap();
+        assert!(! filter.matches_at(& big_old, now));
+        assert!(! filter.matches_at(& small_new, now));
+        let filter: VersionFilter = "size >= 1.5mb && age > 2w".parse().unwrap();
+        assert!(filter.matches_at(& big_old, now));
+        let filter: VersionFilter = "size<1k&&age<=1d".parse().unwrap();
+        assert!(filter.matches_at(& small_new, now));
+        let filter: VersionFilter = "hash!=abc".parse().unwrap();
+        assert!(! filter.matches_at(& big_old, now));
+        assert!(filter.matches_at(& small_new, now));
+        assert!("hash=DEF".parse::<VersionFilter>().unwrap().matches_at(& small_new, now));
+        let bad = ["", "size", "size>lots", "colour=red", "age<7 fortnights", "hash>ab", "size=>1"];
+        for bad in bad {
+            assert!(bad.parse::< VersionFilter > ().is_err(), "{}", bad);
+        }
+    }
+}
\ No newline at end of file
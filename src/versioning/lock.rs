@@ -0,0 +1,147 @@
+use super::storage::VersionStorage;
+use crate::errors::{Context, Result};
+use log::warn;
+use std::{fs::{self, File, TryLockError}, path::PathBuf};
+/// A hold on a version store across processes, released when dropped. Backups and
+/// restores hold it shared while they write or read versions and save the watched
+/// items; cleanups that delete versions hold it exclusively, so they wait for those to
+/// finish and never delete a version that is being written, read or recorded
+#[derive(Debug)]
+pub struct StoreLock {
+    _file: File,
+    /// Where an exclusive hold bumps the cleanup epoch when it is released
+    epoch: Option<PathBuf>,
+}
+impl Drop for StoreLock {
+    fn drop(&mut self) {
+        let Some(path) = &self.epoch else {
+            return;
+        };
+        let next = read_epoch(path) + 1;
+        if let Err(e) = fs::write(path, next.to_string()) {
+            warn!("Cannot update the store epoch {:?}: {}", path, e);
+        }
+    }
+}
+fn read_epoch(path: &std::path::Path) -> u64 {
+    fs::read_to_string(path).ok().and_then(|epoch| epoch.trim().parse().ok()).unwrap_or(0)
+}
+impl VersionStorage {
+    fn lock_file(&self) -> Result<File> {
+        fs::create_dir_all(&self.config.storage_path)?;
+        let path = self.config.storage_path.join("store.lock");
+        File::options()
+            .create(true)
+            .truncate(false)
+            .read(true)
+            .write(true)
+            .open(&path)
+            .with_context(|| format!("cannot open the store lock {:?}", path))
+    }
+    fn epoch_path(&self) -> PathBuf {
+        self.config.storage_path.join("store.epoch")
+    }
+    /// How many cleanups finished on this store; watched items loaded at an older epoch
+    /// may still list versions a cleanup deleted
+    pub fn epoch(&self) -> u64 {
+        read_epoch(&self.epoch_path())
+    }
+    /// Shared hold for writing or reading versions, waiting while a cleanup runs
+    pub fn lock_shared(&self) -> Result<StoreLock> {
+        let file = self.lock_file()?;
+        file.lock_shared().context("Failed to lock the version store")?;
+        Ok(StoreLock { _file: file, epoch: None })
+    }
+    /// Exclusive hold for deleting versions, waiting for running backups and restores
+    pub fn lock_exclusive(&self) -> Result<StoreLock> {
+        let file = self.lock_file()?;
+        file.lock().context("Failed to lock the version store")?;
+        Ok(StoreLock { _file: file, epoch: Some(self.epoch_path()) })
+    }
+    /// `lock_exclusive` without waiting; `None` while backups or restores hold the store
+    pub fn try_lock_exclusive(&self) -> Result<Option<StoreLock>> {
+        let file = self.lock_file()?;
+        match file.try_lock() {
+            Ok(()) => Ok(Some(StoreLock { _file: file, epoch: Some(self.epoch_path()) })),
+            Err(TryLockError::WouldBlock) => Ok(None),
+            Err(TryLockError::Error(e)) => Err(e).context("Failed to lock the version store"),
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{versioning::storage::StorageConfig, SymorManager};
+    use tempfile::tempdir;
+    #[test]
+    fn test_store_lock() {
+        let temp_dir = tempdir().unwrap();
+        let storage = VersionStorage::with_config(StorageConfig {
+            storage_path: temp_dir.path().join("versions"),
+            ..Default::default()
+        });
+        let (first, second) = (storage.lock_shared().unwrap(), storage.lock_shared().unwrap());
+        assert!(storage.try_lock_exclusive().unwrap().is_none());
+        drop((first, second));
+        let cleanup = storage.try_lock_exclusive().unwrap().unwrap();
+        assert_eq!(storage.epoch(), 0);
+        drop(cleanup);
+        assert_eq!(storage.epoch(), 1);
+        drop(storage.lock_shared().unwrap());
+        assert_eq!(storage.epoch(), 1);
+        let manager = || {
+            let mut manager = SymorManager::new().unwrap();
+            manager.config.home_dir = temp_dir.path().join("home");
+            manager.version_storage = storage.clone();
+            manager.load_watched_items().unwrap();
+            manager
+        };
+        let (mut daemon, mut cleaner) = (manager(), manager());
+        let path = temp_dir.path().join("notes.txt");
+        fs::write(&path, "first").unwrap();
+        let id = daemon.watch_only(path.clone(), false, Vec::new(), false).unwrap();
+        fs::write(&path, "second").unwrap();
+        daemon.create_backup(&id).unwrap();
+        let lock = cleaner.version_storage.lock_exclusive().unwrap();
+        cleaner.load_watched_items().unwrap();
+        let pruned = cleaner.watched_item_mut(&id).unwrap().versions.remove(0);
+        cleaner.version_storage.delete_version(&pruned.id).unwrap();
+        cleaner.save_watched_items_public().unwrap();
+        drop(lock);
+        fs::write(&path, "third").unwrap();
+        daemon.create_backup(&id).unwrap();
+        let versions = &daemon.watched_item(&id).unwrap().versions;
+        assert_eq!(versions.len(), 2);
+        assert!(versions.iter().all(|version| version.id != pruned.id));
+    }
+    #[test]
+    fn test_pruning_waits_for_restores() {
+        let temp_dir = tempdir().unwrap();
+        let storage = VersionStorage::with_config(StorageConfig {
+            storage_path: temp_dir.path().join("versions"),
+            ..Default::default()
+        });
+        let mut manager = SymorManager::new().unwrap();
+        manager.config.home_dir = temp_dir.path().join("home");
+        manager.config.versioning.max_versions = 1;
+        manager.version_storage = storage.clone();
+        manager.load_watched_items().unwrap();
+        let path = temp_dir.path().join("notes.txt");
+        fs::write(&path, "first").unwrap();
+        let id = manager.watch_only(path.clone(), false, Vec::new(), false).unwrap();
+        let first = manager.watched_item(&id).unwrap().versions[0].id.clone();
+        let restore = storage.lock_shared().unwrap();
+        fs::write(&path, "second").unwrap();
+        let backup = std::thread::spawn(move || {
+            manager.create_backup(&id).unwrap();
+            (manager, id)
+        });
+        std::thread::sleep(std::time::Duration::from_millis(300));
+        assert!(!backup.is_finished());
+        assert!(storage.retrieve_version(&first).is_ok());
+        drop(restore);
+        let (manager, id) = backup.join().unwrap();
+        assert!(storage.retrieve_version(&first).is_err());
+        assert_eq!(manager.watched_item(&id).unwrap().versions.len(), 1);
+    }
+}
\ No newline at end of file
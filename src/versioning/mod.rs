@@ -1,6 +1,17 @@
 pub mod detector;
 pub mod storage;
 pub mod restore;
+pub mod selector;
+pub mod search;
+pub mod filter;
+pub mod lock;
+pub mod dictionary;
+#[cfg(feature = "tokio")]
+pub mod async_io;
 pub use detector::{ChangeDetector, ChangeDetectorConfig, FileChangeEvent, ChangeType};
 pub use storage::{VersionStorage, VersionMetadata};
-pub use restore::{RestoreEngine, RestoreOptions};
\ No newline at end of file
+pub use restore::{RestoreEngine, RestoreOptions};
+pub use selector::VersionSelector;
+pub use search::{SearchMatch, SearchOptions};
+pub use filter::VersionFilter;
+pub use lock::StoreLock;
\ No newline at end of file
@@ -1,35 +1,100 @@
-use anyhow::Result;
+use crate::errors::{ErrorCode, Result, SymorError};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
 use std::{
     fs, path::{Path, PathBuf},
-    time::SystemTime,
+    time::{Duration, SystemTime},
 };
 #[cfg(unix)]
 use std::os::unix::fs::PermissionsExt;
 #[derive(Debug, Clone)]
 pub struct RestoreOptions {
     pub preserve_permissions: bool,
+    /// Set the restored file's owner to `owner`; only root can give files to others
+    pub preserve_ownership: bool,
+    /// Owner (uid, gid) recorded when the version was stored
+    pub owner: Option<(u32, u32)>,
     pub create_backup: bool,
     pub backup_suffix: String,
     pub atomic_restore: bool,
+    /// MD5 of the version being restored; when set, the written file is read back
+    /// and must match it
+    pub expected_hash: Option<String>,
 }
 impl Default for RestoreOptions {
     fn default() -> Self {
         Self {
             preserve_permissions: true,
+            preserve_ownership: false,
+            owner: None,
             create_backup: false,
             backup_suffix: ".backup".to_string(),
             atomic_restore: true,
+            expected_hash: None,
         }
     }
 }
+/// A copy of a file made by `restore_file` before overwriting it, such as `.pre-restore`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RestoreBackup {
+    pub path: PathBuf,
+    pub created: SystemTime,
+}
+#[derive(Debug, Clone)]
 pub struct RestoreEngine {
     temp_dir: PathBuf,
+    /// File listing the backups made so far, when they are tracked
+    backup_log: Option<PathBuf>,
+    /// Age after which `cleanup_temp_files` deletes tracked backups
+    backup_retention: Option<Duration>,
 }
 impl RestoreEngine {
     pub fn new() -> Result<Self> {
         let temp_dir = std::env::temp_dir().join("symor-restore");
         fs::create_dir_all(&temp_dir)?;
-        Ok(Self { temp_dir })
+        Ok(Self {
+            temp_dir,
+            backup_log: None,
+            backup_retention: None,
+        })
+    }
+    /// Records backups made before overwriting files in `log`, so `cleanup_temp_files`
+    /// can delete them once older than `retention` (`None` keeps them)
+    pub fn track_backups(&mut self, log: PathBuf, retention: Option<Duration>) {
+        self.backup_log = Some(log);
+        self.backup_retention = retention;
+    }
+    /// Backups recorded in the log that still exist, oldest first
+    pub fn tracked_backups(&self) -> Vec<RestoreBackup> {
+        let Some(log) = &self.backup_log else {
+            return Vec::new();
+        };
+        let mut backups: Vec<RestoreBackup> = fs::read_to_string(log)
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default();
+        backups.retain(|backup| backup.path.is_file());
+        backups.sort_by_key(|backup| backup.created);
+        backups
+    }
+    fn save_backups(&self, backups: &[RestoreBackup]) -> Result<()> {
+        if let Some(log) = &self.backup_log {
+            if let Some(parent) = log.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(log, serde_json::to_string_pretty(backups)?)?;
+        }
+        Ok(())
+    }
+    fn record_backup(&self, path: &Path) -> Result<()> {
+        if self.backup_log.is_none() {
+            return Ok(());
+        }
+        let path = std::path::absolute(path)?;
+        let mut backups = self.tracked_backups();
+        backups.retain(|backup| backup.path != path);
+        backups.push(RestoreBackup { path, created: SystemTime::now() });
+        self.save_backups(&backups)
     }
     pub fn restore_file(
         &self,
@@ -49,12 +114,14 @@ impl RestoreEngine {
         };
         if let Some(ref backup_path) = backup_path {
             fs::copy(target_path, backup_path)?;
+            self.record_backup(backup_path)?;
         }
-        let result = if options.atomic_restore {
+        let mut result = if options.atomic_restore {
             self.atomic_restore(target_path, content)?
         } else {
             self.direct_restore(target_path, content)?
         };
+        result.backup_created = backup_path.is_some();
         if let (Some(metadata), true) = (
             original_metadata,
             options.preserve_permissions,
@@ -67,8 +134,69 @@ impl RestoreEngine {
                 let _ = fs::set_permissions(target_path, perms);
             }
         }
+        if let (Some(owner), true) = (options.owner, options.preserve_ownership) {
+            result.ownership_restored = Self::restore_owner(target_path, owner)?;
+        }
+        if let Some(expected) = &options.expected_hash {
+            Self::verify_hash(target_path, expected, backup_path.as_deref())?;
+            result.verified = true;
+        }
         Ok(result)
     }
+    /// Gives `target_path` to `owner`; returns false when not permitted to
+    #[cfg(unix)]
+    fn restore_owner(target_path: &Path, (uid, gid): (u32, u32)) -> Result<bool> {
+        use std::os::unix::fs::MetadataExt;
+        let current = fs::metadata(target_path)?;
+        if current.uid() == uid && current.gid() == gid {
+            return Ok(true);
+        }
+        match std::os::unix::fs::chown(target_path, Some(uid), Some(gid)) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => {
+                warn!(
+                    "Cannot give {:?} back to uid {} gid {} without root: {}; map owners with \
+                     'sym settings linking --owner-map'", target_path, uid, gid, e
+                );
+                Ok(false)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+    #[cfg(not(unix))]
+    fn restore_owner(_target_path: &Path, _owner: (u32, u32)) -> Result<bool> {
+        Ok(false)
+    }
+    /// Reads `target_path` back and fails when its MD5 is not `expected`
+    fn verify_hash(
+        target_path: &Path,
+        expected: &str,
+        backup_path: Option<&Path>,
+    ) -> Result<()> {
+        let actual = crate::performance::memory::hash_file(target_path)?;
+        if actual == expected {
+            return Ok(());
+        }
+        let suggestion = match backup_path {
+            Some(backup) => {
+                format!(
+                    "The previous content is saved at {:?}; run 'sym check' to find damaged versions",
+                    backup
+                )
+            }
+            None => "Run 'sym check' to find damaged versions".to_string(),
+        };
+        Err(
+            SymorError::new(
+                    ErrorCode::VersionCorrupted,
+                    format!(
+                        "Restored file {:?} failed verification: expected hash {}, found {}",
+                        target_path, expected, actual
+                    ),
+                )
+                .with_suggestion(suggestion),
+        )
+    }
     fn atomic_restore(
         &self,
         target_path: &Path,
@@ -89,6 +217,8 @@ impl RestoreEngine {
             bytes_written: content.len() as u64,
             temp_file_used: true,
             backup_created: false,
+            verified: false,
+            ownership_restored: false,
         })
     }
     fn direct_restore(
@@ -105,6 +235,41 @@ impl RestoreEngine {
             bytes_written: content.len() as u64,
             temp_file_used: false,
             backup_created: false,
+            verified: false,
+            ownership_restored: false,
+        })
+    }
+    /// Recreates at `target_path` a symlink pointing at `link`, as a version of the link
+    /// recorded it. What `target_path` held is moved aside to the backup when
+    /// `create_backup` is set, and removed otherwise.
+    pub fn restore_link(
+        &self,
+        target_path: &Path,
+        link: &Path,
+        options: &RestoreOptions,
+    ) -> Result<RestoreResult> {
+        let backup_path = if options.create_backup && fs::symlink_metadata(target_path).is_ok() {
+            Some(target_path.with_extension(&options.backup_suffix))
+        } else {
+            None
+        };
+        if let Some(parent) = target_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        if let Some(ref backup_path) = backup_path {
+            crate::linking::remove_path(backup_path)?;
+            fs::rename(target_path, backup_path)?;
+            self.record_backup(backup_path)?;
+        }
+        crate::linking::remove_path(target_path)?;
+        crate::linking::symlink(link, target_path)?;
+        Ok(RestoreResult {
+            success: true,
+            bytes_written: 0,
+            temp_file_used: false,
+            backup_created: backup_path.is_some(),
+            verified: fs::read_link(target_path).is_ok_and(|target| target == link),
+            ownership_restored: false,
         })
     }
     pub fn batch_restore(
@@ -174,6 +339,8 @@ impl RestoreEngine {
             estimated_space_required: required_space,
         })
     }
+    /// Deletes abandoned temporary files over an hour old, and tracked backups past
+    /// their retention
     pub fn cleanup_temp_files(&self) -> Result<usize> {
         let mut cleaned_count = 0;
         if self.temp_dir.exists() {
@@ -190,6 +357,25 @@ impl RestoreEngine {
                 }
             }
         }
+        if self.backup_log.is_some() {
+            let mut backups = self.tracked_backups();
+            if let Some(retention) = self.backup_retention {
+                let mut expired = Vec::new();
+                backups.retain(|backup| {
+                    let keep = backup.created.elapsed().unwrap_or_default() <= retention;
+                    if !keep {
+                        expired.push(backup.path.clone());
+                    }
+                    keep
+                });
+                for path in expired {
+                    fs::remove_file(&path)?;
+                    info!("Removed old restore backup {:?}", path);
+                    cleaned_count += 1;
+                }
+            }
+            self.save_backups(&backups)?;
+        }
         Ok(cleaned_count)
     }
 }
@@ -204,13 +390,17 @@ pub struct RestoreResult {
     pub bytes_written: u64,
     pub temp_file_used: bool,
     pub backup_created: bool,
+    /// The written file was read back and matched `RestoreOptions::expected_hash`
+    pub verified: bool,
+    /// The file's owner was set back to `RestoreOptions::owner`
+    pub ownership_restored: bool,
 }
 #[derive(Debug)]
 pub struct BatchRestoreResult {
     pub total_operations: usize,
     pub success_count: usize,
     pub failure_count: usize,
-    pub results: Vec<Result<RestoreResult, anyhow::Error>>,
+    pub results: Vec<Result<RestoreResult>>,
 }
 #[derive(Debug, Clone)]
 pub struct RestoreValidation {
@@ -244,6 +434,63 @@ mod tests {
         assert_eq!(restored_content, content);
     }
     #[test]
+    fn test_restore_hash_verification() {
+        let temp_dir = tempdir().unwrap();
+        let target_path = temp_dir.path().join("verified.txt");
+        let content = b"checked content";
+        let engine = RestoreEngine::new().unwrap();
+        let mut options = RestoreOptions {
+            expected_hash: Some(format!("{:x}", md5::compute(content))),
+            ..RestoreOptions::default()
+        };
+        assert!(engine.restore_file(& target_path, content, & options).unwrap().verified);
+        options.expected_hash = Some("0".repeat(32));
+        let err = engine.restore_file(&target_path, content, &options).unwrap_err();
+        assert_eq!(err.code(), & ErrorCode::VersionCorrupted);
+        options.expected_hash = None;
+        assert!(! engine.restore_file(& target_path, content, & options).unwrap().verified);
+    }
+    #[cfg(unix)]
+    #[test]
+    fn test_restore_ownership() {
+        let temp_dir = tempdir().unwrap();
+        let target_path = temp_dir.path().join("owned.txt");
+        let engine = RestoreEngine::new().unwrap();
+        let owner = crate::versioning::storage::file_owner(temp_dir.path());
+        let mut options = RestoreOptions {
+            preserve_ownership: true,
+            owner,
+            ..RestoreOptions::default()
+        };
+        assert!(engine.restore_file(& target_path, b"data", & options).unwrap().ownership_restored);
+        options.preserve_ownership = false;
+        assert!(! engine.restore_file(& target_path, b"data", & options).unwrap().ownership_restored);
+    }
+    #[test]
+    fn test_restore_backup_retention() {
+        let temp_dir = tempdir().unwrap();
+        let target_path = temp_dir.path().join("config.yaml");
+        fs::write(&target_path, "before").unwrap();
+        let mut engine = RestoreEngine::new().unwrap();
+        engine.track_backups(temp_dir.path().join("backups.json"), None);
+        let options = RestoreOptions {
+            create_backup: true,
+            backup_suffix: ".pre-restore".to_string(),
+            ..RestoreOptions::default()
+        };
+        assert!(engine.restore_file(& target_path, b"after", & options).unwrap().backup_created);
+        let backups = engine.tracked_backups();
+        assert_eq!(backups.len(), 1);
+        assert_eq!(fs::read_to_string(& backups[0].path).unwrap(), "before");
+        engine.cleanup_temp_files().unwrap();
+        assert!(backups[0].path.exists());
+        engine.track_backups(temp_dir.path().join("backups.json"), Some(Duration::ZERO));
+        std::thread::sleep(Duration::from_millis(10));
+        assert!(engine.cleanup_temp_files().unwrap() >= 1);
+        assert!(! backups[0].path.exists());
+        assert!(engine.tracked_backups().is_empty());
+    }
+    #[test]
     fn test_restore_validation() {
         let temp_dir = tempdir().unwrap();
         let target_path = temp_dir.path().join("test.txt");
@@ -0,0 +1,164 @@
+use crate::{errors::{ErrorCode, Result, SymorError}, FileVersion, SymorManager, WatchedItem};
+use regex::Regex;
+use std::{path::PathBuf, time::SystemTime};
+/// Filters for `SymorManager::search_versions`
+#[derive(Debug, Clone, Default)]
+pub struct SearchOptions {
+    /// Only items whose path matches this glob
+    pub path: Option<glob::Pattern>,
+    /// Only versions stored at or after this time
+    pub since: Option<SystemTime>,
+}
+impl SearchOptions {
+    /// Options from the `--path` glob and `--since` time given on the command line
+    pub fn parse(path: Option<&str>, since: Option<&str>) -> Result<Self> {
+        let path = path
+            .map(|glob| {
+                glob::Pattern::new(glob)
+                    .map_err(|e| {
+                        SymorError::new(
+                            ErrorCode::InvalidPath,
+                            format!("Invalid path pattern {:?}: {}", glob, e),
+                        )
+                    })
+            })
+            .transpose()?;
+        let since = since
+            .map(|when| {
+                super::selector::parse_time(when, SystemTime::now())
+                    .ok_or_else(|| {
+                        SymorError::new(
+                                ErrorCode::InvalidConfiguration,
+                                format!("Invalid time: {}", when),
+                            )
+                            .with_suggestion(
+                                "Use yesterday, '3 days ago' or a date such as 2026-01-31"
+                                    .to_string(),
+                            )
+                    })
+            })
+            .transpose()?;
+        Ok(Self { path, since })
+    }
+}
+/// A line of a stored version that matched
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchMatch {
+    pub item_id: String,
+    pub path: PathBuf,
+    pub version_id: String,
+    pub timestamp: SystemTime,
+    /// 1-based
+    pub line_number: usize,
+    pub line: String,
+}
+impl SymorManager {
+    /// Lines matching `pattern` in every stored version of the watched files, oldest
+    /// version first per item; binary and unreadable versions are skipped
+    pub fn search_versions(
+        &self,
+        pattern: &Regex,
+        options: &SearchOptions,
+    ) -> Vec<SearchMatch> {
+        let mut items: Vec<_> = self
+            .watched_items
+            .values()
+            .filter(|item| !item.is_directory)
+            .filter(|item| options.path.as_ref().is_none_or(|glob| glob.matches_path(&item.path)))
+            .collect();
+        items.sort_by(|a, b| a.path.cmp(&b.path));
+        let mut matches = Vec::new();
+        for item in items {
+            let mut versions: Vec<_> = item
+                .versions
+                .iter()
+                .filter(|v| options.since.is_none_or(|since| v.timestamp >= since))
+                .collect();
+            versions.sort_by_key(|v| v.timestamp);
+            for version in versions {
+                let Ok(content) = self.version_content(&item.id, &version.id) else {
+                    continue;
+                };
+                if content[..content.len().min(8192)].contains(&0) {
+                    continue;
+                }
+                let text = String::from_utf8_lossy(&content);
+                for (index, line) in text.lines().enumerate() {
+                    if pattern.is_match(line) {
+                        matches
+                            .push(SearchMatch {
+                                item_id: item.id.clone(),
+                                path: item.path.clone(),
+                                version_id: version.id.clone(),
+                                timestamp: version.timestamp,
+                                line_number: index + 1,
+                                line: line.to_string(),
+                            });
+                    }
+                }
+            }
+        }
+        matches
+    }
+    /// Versions of any item whose content hash starts with `hash` (hex, any case),
+    /// ordered by path and then age
+    pub fn find_by_hash(&self, hash: &str) -> Vec<(&WatchedItem, &FileVersion)> {
+        let hash = hash.to_ascii_lowercase();
+        let mut found: Vec<_> = self
+            .watched_items
+            .values()
+            .flat_map(|item| item.versions.iter().map(move |version| (item, version)))
+            .filter(|(_, version)| version.hash.starts_with(&hash))
+            .collect();
+        found.sort_by(|a, b| (&a.0.path, a.1.timestamp).cmp(&(&b.0.path, b.1.timestamp)));
+        found
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::versioning::storage::{StorageConfig, VersionStorage};
+    use std::fs;
+    use tempfile::tempdir;
+    #[test]
+    fn test_search_versions() {
+        let temp_dir = tempdir().unwrap();
+        let conf = temp_dir.path().join("app.conf");
+        let notes = temp_dir.path().join("notes.txt");
+        fs::write(&conf, "port = 80\nmax_connections = 10\n").unwrap();
+        fs::write(&notes, "max_connections is tuned in app.conf").unwrap();
+        let mut manager = SymorManager::new().unwrap();
+        manager.config.home_dir = temp_dir.path().join("home");
+        fs::create_dir_all(&manager.config.home_dir).unwrap();
+        manager.version_storage = VersionStorage::with_config(StorageConfig {
+            storage_path: temp_dir.path().join("versions"),
+            ..Default::default()
+        });
+        let conf_id = manager.watch(conf.clone(), false).unwrap();
+        manager.watch(notes.clone(), false).unwrap();
+        fs::write(&conf, "port = 8080\n").unwrap();
+        manager.create_backup(&conf_id).unwrap();
+        let pattern = Regex::new(r"^max_connections\s*=").unwrap();
+        let found = manager.search_versions(&pattern, &SearchOptions::default());
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].item_id, conf_id);
+        assert_eq!(found[0].version_id, manager.watched_items[&conf_id].versions[0].id);
+        assert_eq!((found[0].line_number, found[0].line.as_str()), (2, "max_connections = 10"));
+        let port = Regex::new("port").unwrap();
+        assert_eq!(manager.search_versions(&port, &SearchOptions::default()).len(), 2);
+        let glob = format!("{}/*.txt", temp_dir.path().display());
+        let options = SearchOptions::parse(Some(&glob), None).unwrap();
+        let found = manager.search_versions(&Regex::new("max_conn").unwrap(), &options);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].path, notes);
+        let options = SearchOptions::parse(None, Some("2100-01-01")).unwrap();
+        assert!(manager.search_versions(&port, &options).is_empty());
+        assert!(SearchOptions::parse(Some("["), None).is_err());
+        assert!(SearchOptions::parse(None, Some("someday")).is_err());
+        let version = &manager.watched_items[&conf_id].versions[1];
+        let found = manager.find_by_hash(&version.hash[..8].to_ascii_uppercase());
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].1.id, version.id);
+        assert!(manager.find_by_hash("ffffffffffffffffffffffffffffffff").is_empty());
+    }
+}
\ No newline at end of file
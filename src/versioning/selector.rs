@@ -0,0 +1,151 @@
+use crate::{errors::{ErrorCode, Result, SymorError}, FileVersion};
+use std::{str::FromStr, time::{Duration, SystemTime, UNIX_EPOCH}};
+const SYNTAX: &str = "Use a version ID, @latest, @latest~N, or @{yesterday}, @{2 days ago}, \
+                      @{2026-01-31 12:00} (UTC)";
+/// Which version of an item to use, as given wherever a version ID is accepted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VersionSelector {
+    Id(String),
+    /// `@latest~N`: N versions before the newest
+    Latest(usize),
+    /// `@{when}`: the newest version stored at or before this time
+    AsOf(SystemTime),
+}
+fn invalid(spec: &str) -> SymorError {
+    SymorError::new(ErrorCode::VersionNotFound, format!("Invalid version selector: {}", spec))
+        .with_suggestion(SYNTAX.to_string())
+}
+/// Days since the epoch of a civil date
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+/// `YYYY-MM-DD`, optionally followed by ` HH:MM[:SS]` or `THH:MM[:SS]`, in UTC
+fn parse_date(text: &str) -> Option<SystemTime> {
+    let (date, time) = match text.split_once([' ', 'T', 't']) {
+        Some((date, time)) => (date, time),
+        None => (text, "00:00"),
+    };
+    let mut date_parts = date.split('-').map(str::parse::<i64>);
+    let (year, month, day) = (
+        date_parts.next()?.ok()?,
+        date_parts.next()?.ok()?,
+        date_parts.next()?.ok()?,
+    );
+    let mut time_parts = time.split(':').map(str::parse::<i64>);
+    let hour = time_parts.next()?.ok()?;
+    let minute = time_parts.next()?.ok()?;
+    let second = time_parts.next().transpose().ok()?.unwrap_or(0);
+    if date_parts.next().is_some() || time_parts.next().is_some() || !(1..=12).contains(&month)
+        || !(1..=31).contains(&day) || hour > 23 || minute > 59 || second > 59
+    {
+        return None;
+    }
+    let secs = days_from_civil(year, month, day) * 86_400 + hour * 3600 + minute * 60 + second;
+    Some(UNIX_EPOCH + Duration::from_secs(u64::try_from(secs).ok()?))
+}
+/// `now`, `yesterday`, `<n> <unit>s ago` (units up to years) or a date
+pub(crate) fn parse_time(text: &str, now: SystemTime) -> Option<SystemTime> {
+    let text = text.trim().to_ascii_lowercase();
+    match text.as_str() {
+        "now" => return Some(now),
+        "yesterday" => return now.checked_sub(Duration::from_secs(86_400)),
+        _ => {}
+    }
+    let words: Vec<&str> = text.split([' ', '.']).filter(|w| !w.is_empty()).collect();
+    if let [count, unit, "ago"] = words.as_slice() {
+        let count: u64 = count.parse().ok()?;
+        let unit_secs = match unit.trim_end_matches('s') {
+            "second" | "sec" => 1,
+            "minute" | "min" => 60,
+            "hour" => 3600,
+            "day" => 86_400,
+            "week" => 7 * 86_400,
+            "month" => 30 * 86_400,
+            "year" => 365 * 86_400,
+            _ => return None,
+        };
+        return now.checked_sub(Duration::from_secs(count.checked_mul(unit_secs)?));
+    }
+    parse_date(&text)
+}
+impl VersionSelector {
+    /// Parses `spec` with relative times measured from `now`
+    pub fn parse_at(spec: &str, now: SystemTime) -> Result<Self> {
+        let Some(rest) = spec.strip_prefix('@') else {
+            return Ok(Self::Id(spec.to_string()));
+        };
+        if let Some(when) = rest.strip_prefix('{').and_then(|r| r.strip_suffix('}')) {
+            return parse_time(when, now).map(Self::AsOf).ok_or_else(|| invalid(spec));
+        }
+        let rest = rest.strip_prefix("latest").unwrap_or(rest);
+        match rest.strip_prefix('~') {
+            None if rest.is_empty() => Ok(Self::Latest(0)),
+            Some("") => Ok(Self::Latest(1)),
+            Some(n) => n.parse().map(Self::Latest).map_err(|_| invalid(spec)),
+            None => Err(invalid(spec)),
+        }
+    }
+    /// The selected version from `versions`, which are oldest first
+    pub fn select<'a>(&self, versions: &'a [FileVersion]) -> Option<&'a FileVersion> {
+        match self {
+            Self::Id(id) => versions.iter().find(|v| &v.id == id),
+            Self::Latest(back) => versions.iter().rev().nth(*back),
+            Self::AsOf(when) => versions.iter().rev().find(|v| v.timestamp <= *when),
+        }
+    }
+}
+impl FromStr for VersionSelector {
+    type Err = SymorError;
+    fn from_str(spec: &str) -> Result<Self> {
+        Self::parse_at(spec, SystemTime::now())
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    fn version(id: &str, secs: u64) -> FileVersion {
+        FileVersion {
+            id: id.to_string(),
+            timestamp: UNIX_EPOCH + Duration::from_secs(secs),
+            size: 0,
+            hash: String::new(),
+            path: PathBuf::from("f"),
+            backup_path: None,
+        }
+    }
+    #[test]
+    fn test_version_selectors() {
+        let day = 86_400;
+        let now = UNIX_EPOCH + Duration::from_secs(10 * day);
+        let versions = vec![version("a", day), version("b", 8 * day), version("c", 10 * day)];
+        let pick = |spec: &str| {
+            VersionSelector::parse_at(spec, now)
+                .unwrap()
+                .select(&versions)
+                .map(|v| v.id.clone())
+        };
+        assert_eq!(pick("b").as_deref(), Some("b"));
+        assert_eq!(pick("@latest").as_deref(), Some("c"));
+        assert_eq!(pick("@latest~2").as_deref(), Some("a"));
+        assert_eq!(pick("@~").as_deref(), Some("b"));
+        assert_eq!(pick("@latest~3"), None);
+        assert_eq!(pick("@{yesterday}").as_deref(), Some("b"));
+        assert_eq!(pick("@{3 days ago}").as_deref(), Some("a"));
+        assert_eq!(pick("@{2.weeks.ago}"), None);
+        assert_eq!(pick("@{1970-01-09 12:00}").as_deref(), Some("b"));
+        assert_eq!(pick("@{1970-01-08T00:00:01}").as_deref(), Some("a"));
+        assert_eq!(
+            VersionSelector::parse_at("@{1970-01-02}", now).unwrap(),
+            VersionSelector::AsOf(UNIX_EPOCH + Duration::from_secs(day))
+        );
+        for bad in ["@head", "@latest~x", "@{someday}", "@{2026-13-01}"] {
+            assert!(VersionSelector::parse_at(bad, now).is_err(), "{}", bad);
+        }
+    }
+}
\ No newline at end of file
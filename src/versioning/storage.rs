@@ -1,5 +1,6 @@
-use anyhow::{Context, Result};
-use flate2::{write::GzEncoder, read::GzDecoder, Compression};
+use crate::errors::{Context, ErrorCode, Result, SymorError};
+use crate::performance::{memory, ParallelProcessor};
+use flate2::{write::GzEncoder, read::MultiGzDecoder, Compression};
 use serde::{Deserialize, Serialize};
 use std::{
     fs, path::{Path, PathBuf},
@@ -14,31 +15,74 @@ pub struct VersionMetadata {
     pub compressed_size: u64,
     pub hash: String,
     pub compression_level: u8,
+    /// Owner (uid, gid) of the file when it was backed up; unix only
+    pub owner: Option<(u32, u32)>,
+    /// Dictionary the data was compressed against, needed to read it back
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dictionary: Option<String>,
+    /// Where the symlink pointed, for a version of the link itself; restores recreate it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub link_target: Option<PathBuf>,
+}
+/// Owner (uid, gid) of `path`, on platforms that have one
+#[cfg(unix)]
+pub fn file_owner(path: &Path) -> Option<(u32, u32)> {
+    use std::os::unix::fs::MetadataExt;
+    fs::metadata(path).ok().map(|m| (m.uid(), m.gid()))
+}
+#[cfg(not(unix))]
+pub fn file_owner(_path: &Path) -> Option<(u32, u32)> {
+    None
 }
 #[derive(Debug, Clone)]
 pub struct StorageConfig {
     pub compression_level: u8,
     pub max_versions_per_file: usize,
     pub storage_path: PathBuf,
+    /// Threads compressing the chunks of versions larger than `PARALLEL_CHUNK`
+    pub compression_threads: usize,
 }
+/// Versions larger than this are split into chunks of this size, compressed in parallel
+/// as the members of one gzip file
+pub const PARALLEL_CHUNK: usize = 4 * 1024 * 1024;
 impl Default for StorageConfig {
     fn default() -> Self {
         Self {
             compression_level: 6,
             max_versions_per_file: 10,
             storage_path: PathBuf::from(".symor/versions"),
+            compression_threads: 1,
         }
     }
 }
+#[derive(Debug, Clone)]
 pub struct VersionStorage {
-    config: StorageConfig,
+    pub(super) config: StorageConfig,
+    pub(super) dictionary_cache: super::dictionary::DictionaryCache,
+    /// Stores left to fail as if the disk were full
+    #[cfg(test)]
+    pub(crate) full_disk: std::sync::Arc<std::sync::atomic::AtomicUsize>,
 }
 impl VersionStorage {
     pub fn new() -> Self {
         Self::with_config(StorageConfig::default())
     }
     pub fn with_config(config: StorageConfig) -> Self {
-        Self { config }
+        Self {
+            config,
+            dictionary_cache: Default::default(),
+            #[cfg(test)]
+            full_disk: Default::default(),
+        }
+    }
+    pub fn compression_level(&self) -> u8 {
+        self.config.compression_level
+    }
+    pub fn storage_path(&self) -> &Path {
+        &self.config.storage_path
+    }
+    pub fn set_compression_threads(&mut self, threads: usize) {
+        self.config.compression_threads = threads.max(1);
     }
     pub fn store_version(
         &self,
@@ -46,14 +90,38 @@ impl VersionStorage {
         content: &[u8],
         version_id: &str,
     ) -> Result<VersionMetadata> {
+        self.store_version_at(file_path, content, version_id, self.config.compression_level)
+    }
+    /// `store_version` at gzip `level` instead of the configured one; 0 stores the
+    /// content uncompressed
+    pub fn store_version_at(
+        &self,
+        file_path: &Path,
+        content: &[u8],
+        version_id: &str,
+        level: u8,
+    ) -> Result<VersionMetadata> {
+        #[cfg(test)]
+        {
+            use std::sync::atomic::Ordering;
+            let full = self.full_disk.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |left| {
+                left.checked_sub(1)
+            });
+            if full.is_ok() {
+                return Err(std::io::Error::from_raw_os_error(28).into());
+            }
+        }
         fs::create_dir_all(&self.config.storage_path)?;
         let storage_path = self.get_storage_path(version_id);
-        let compressed_data = self.compress_data(content)?;
+        let compressed_data = self.compress_data(content, level)?;
         let temp_path = storage_path.with_extension("tmp");
         if let Some(parent) = temp_path.parent() {
             fs::create_dir_all(parent)?;
         }
-        fs::write(&temp_path, &compressed_data)?;
+        if let Err(e) = fs::write(&temp_path, &compressed_data) {
+            let _ = fs::remove_file(&temp_path);
+            return Err(e.into());
+        }
         fs::rename(&temp_path, &storage_path)?;
         let metadata = VersionMetadata {
             id: version_id.to_string(),
@@ -62,11 +130,27 @@ impl VersionStorage {
             size: content.len() as u64,
             compressed_size: compressed_data.len() as u64,
             hash: format!("{:x}", md5::compute(content)),
-            compression_level: self.config.compression_level,
+            compression_level: level,
+            owner: file_owner(file_path),
+            dictionary: super::dictionary::dictionary_id(&compressed_data).map(String::from),
+            link_target: None,
         };
         self.save_metadata(&metadata)?;
         Ok(metadata)
     }
+    /// Stores a version of the symlink at `file_path` itself, pointing at `target`
+    pub fn store_link(
+        &self,
+        file_path: &Path,
+        target: &Path,
+        version_id: &str,
+    ) -> Result<VersionMetadata> {
+        let content = crate::symlinks::target_content(target);
+        let mut metadata = self.store_version_at(file_path, content, version_id, 0)?;
+        metadata.link_target = Some(target.to_path_buf());
+        self.save_metadata(&metadata)?;
+        Ok(metadata)
+    }
     pub fn retrieve_version(
         &self,
         version_id: &str,
@@ -85,9 +169,53 @@ impl VersionStorage {
         let metadata_path = self.get_metadata_path(version_id);
         let _ = fs::remove_file(&storage_path);
         let _ = fs::remove_file(&metadata_path);
+        self.remove_empty_shards(&storage_path);
         Ok(())
     }
+    /// `delete_version`, optionally moving the stored files to the OS trash instead
+    pub fn discard_version(&self, version_id: &str, to_trash: bool) -> Result<()> {
+        if !to_trash {
+            return self.delete_version(version_id);
+        }
+        let storage_path = self.get_storage_path(version_id);
+        crate::linking::discard_path(&storage_path, true)?;
+        self.remove_empty_shards(&storage_path);
+        crate::linking::discard_path(&self.get_metadata_path(version_id), true)
+    }
+    /// Moves data files from the flat `data/` layout of older versions into their
+    /// shard directories, returning how many were moved
+    pub fn migrate_flat_blobs(&self) -> Result<usize> {
+        let data_dir = self.data_dir();
+        let mut moved = 0;
+        for entry in fs::read_dir(&data_dir).into_iter().flatten() {
+            let path = entry?.path();
+            if !path.is_file() || path.extension().is_none_or(|ext| ext != "gz") {
+                continue;
+            }
+            let Some(version_id) = path.file_stem().map(|s| s.to_string_lossy().into_owned()) else {
+                continue;
+            };
+            let sharded = self.sharded_path(&version_id);
+            if sharded.exists() {
+                continue;
+            }
+            if let Some(parent) = sharded.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::rename(&path, &sharded)
+                .with_context(|| format!("Failed to move {:?} to {:?}", path, sharded))?;
+            moved += 1;
+        }
+        Ok(moved)
+    }
     pub fn list_versions(&self, file_path: &Path) -> Result<Vec<VersionMetadata>> {
+        let mut versions = self.list_metadata()?;
+        versions.retain(|metadata| metadata.original_path == file_path);
+        versions.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+        Ok(versions)
+    }
+    /// Metadata of every version in the store that can be read
+    pub fn list_metadata(&self) -> Result<Vec<VersionMetadata>> {
         let mut versions = Vec::new();
         let metadata_dir = self.config.storage_path.join("metadata");
         if !metadata_dir.exists() {
@@ -95,16 +223,34 @@ impl VersionStorage {
         }
         for entry in fs::read_dir(&metadata_dir)? {
             let entry = entry?;
-            let metadata_path = entry.path();
-            if let Ok(metadata) = self.load_metadata_from_path(&metadata_path) {
-                if metadata.original_path == file_path {
-                    versions.push(metadata);
-                }
+            if let Ok(metadata) = self.load_metadata_from_path(&entry.path()) {
+                versions.push(metadata);
             }
         }
-        versions.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
         Ok(versions)
     }
+    /// Ids of every version with stored metadata
+    pub fn version_ids(&self) -> Result<Vec<String>> {
+        let metadata_dir = self.config.storage_path.join("metadata");
+        if !metadata_dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut ids = Vec::new();
+        for entry in fs::read_dir(&metadata_dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                if let Some(stem) = path.file_stem() {
+                    ids.push(stem.to_string_lossy().into_owned());
+                }
+            }
+        }
+        Ok(ids)
+    }
+    /// True when both the data and metadata files of a version exist
+    pub fn contains(&self, version_id: &str) -> bool {
+        self.get_storage_path(version_id).is_file()
+            && self.get_metadata_path(version_id).is_file()
+    }
     pub fn cleanup_old_versions(&self, file_path: &Path) -> Result<usize> {
         let versions = self.list_versions(file_path)?;
         let mut deleted_count = 0;
@@ -121,21 +267,18 @@ impl VersionStorage {
         let mut total_versions = 0;
         let mut total_original_size = 0;
         let mut total_compressed_size = 0;
-        let metadata_dir = self.config.storage_path.join("metadata");
-        if metadata_dir.exists() {
-            for entry in fs::read_dir(&metadata_dir)? {
-                let entry = entry?;
-                if let Ok(metadata) = self.load_metadata_from_path(&entry.path()) {
-                    total_versions += 1;
-                    total_original_size += metadata.size;
-                    total_compressed_size += metadata.compressed_size;
-                }
-            }
+        let mut dictionary_versions = 0;
+        for metadata in self.list_metadata()? {
+            total_versions += 1;
+            total_original_size += metadata.size;
+            total_compressed_size += metadata.compressed_size;
+            dictionary_versions += usize::from(metadata.dictionary.is_some());
         }
         Ok(StorageStats {
             total_versions,
             total_original_size,
             total_compressed_size,
+            dictionary_versions,
             compression_ratio: if total_original_size > 0 {
                 total_compressed_size as f64 / total_original_size as f64
             } else {
@@ -143,24 +286,81 @@ impl VersionStorage {
             },
         })
     }
-    fn compress_data(&self, data: &[u8]) -> Result<Vec<u8>> {
-        let mut encoder = GzEncoder::new(
-            Vec::new(),
-            Compression::new(self.config.compression_level as u32),
-        );
-        encoder.write_all(data)?;
-        encoder.finish().context("Failed to compress data")
+    /// Compresses `data` at `level`, against the active dictionary when it is small
+    pub(super) fn compress_data(&self, data: &[u8], level: u8) -> Result<Vec<u8>> {
+        match self.compress_with_dictionary(data, level) {
+            Some(compressed) => Ok(compressed),
+            None => self.compress_data_plain(data, level),
+        }
+    }
+    /// Gzip of `data` at `level`; with more than one compression thread, data larger
+    /// than `PARALLEL_CHUNK` is compressed chunk by chunk in parallel
+    pub(super) fn compress_data_plain(&self, data: &[u8], level: u8) -> Result<Vec<u8>> {
+        let threads = memory::budget()
+            .concurrency(self.config.compression_threads, 2 * PARALLEL_CHUNK as u64);
+        if threads <= 1 || data.len() <= PARALLEL_CHUNK {
+            return gzip(data, level);
+        }
+        let chunks: Vec<&[u8]> = data.chunks(PARALLEL_CHUNK).collect();
+        let members = ParallelProcessor::new(threads)
+            .map_parallel(&chunks, |chunk| gzip(chunk, level));
+        let mut compressed = Vec::with_capacity(data.len() / 2);
+        for member in members {
+            compressed.extend_from_slice(&member?);
+        }
+        Ok(compressed)
     }
-    fn decompress_data(&self, data: &[u8]) -> Result<Vec<u8>> {
-        let mut decoder = GzDecoder::new(data);
+    pub(super) fn decompress_data(&self, data: &[u8]) -> Result<Vec<u8>> {
+        if let Some(decompressed) = self.decompress_with_dictionary(data) {
+            return decompressed;
+        }
+        let mut decoder = MultiGzDecoder::new(data);
         let mut decompressed = Vec::new();
-        decoder.read_to_end(&mut decompressed)?;
+        decoder
+            .read_to_end(&mut decompressed)
+            .map_err(|e| {
+                SymorError::new(
+                    ErrorCode::VersionCorrupted,
+                    format!("Failed to decompress version data: {}", e),
+                )
+            })?;
         Ok(decompressed)
     }
-    fn get_storage_path(&self, version_id: &str) -> PathBuf {
-        self.config.storage_path.join("data").join(format!("{}.gz", version_id))
+    fn data_dir(&self) -> PathBuf {
+        self.config.storage_path.join("data")
     }
-    fn get_metadata_path(&self, version_id: &str) -> PathBuf {
+    /// `data/<ab>/<cd>/<id>.gz`, where `abcd` starts the MD5 of the version id, so no
+    /// directory grows past a few hundred entries
+    fn sharded_path(&self, version_id: &str) -> PathBuf {
+        let hash = format!("{:x}", md5::compute(version_id));
+        self.data_dir().join(&hash[..2]).join(&hash[2..4]).join(format!("{}.gz", version_id))
+    }
+    /// Where the data of `version_id` lives, moving it there first if it is still in
+    /// the flat layout
+    pub(super) fn get_storage_path(&self, version_id: &str) -> PathBuf {
+        let sharded = self.sharded_path(version_id);
+        let flat = self.data_dir().join(format!("{}.gz", version_id));
+        if !sharded.exists() && flat.is_file() {
+            let moved = sharded
+                .parent()
+                .is_some_and(|parent| fs::create_dir_all(parent).is_ok())
+                && fs::rename(&flat, &sharded).is_ok();
+            if !moved {
+                return flat;
+            }
+        }
+        sharded
+    }
+    /// Removes the shard directories above a deleted data file once they are empty
+    fn remove_empty_shards(&self, storage_path: &Path) {
+        let data_dir = self.data_dir();
+        for dir in storage_path.ancestors().skip(1).take(2) {
+            if dir == data_dir || fs::remove_dir(dir).is_err() {
+                break;
+            }
+        }
+    }
+    pub(super) fn get_metadata_path(&self, version_id: &str) -> PathBuf {
         self.config.storage_path.join("metadata").join(format!("{}.json", version_id))
     }
     fn save_metadata(&self, metadata: &VersionMetadata) -> Result<()> {
@@ -171,7 +371,7 @@ impl VersionStorage {
         fs::write(&metadata_path, json_data)?;
         Ok(())
     }
-    fn load_metadata(&self, version_id: &str) -> Result<VersionMetadata> {
+    pub fn load_metadata(&self, version_id: &str) -> Result<VersionMetadata> {
         let metadata_path = self.get_metadata_path(version_id);
         let json_data = fs::read_to_string(&metadata_path)?;
         let metadata: VersionMetadata = serde_json::from_str(&json_data)?;
@@ -183,12 +383,19 @@ impl VersionStorage {
         Ok(metadata)
     }
 }
+fn gzip(data: &[u8], level: u8) -> Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::new(level as u32));
+    encoder.write_all(data)?;
+    encoder.finish().context("Failed to compress data")
+}
 #[derive(Debug, Clone)]
 pub struct StorageStats {
     pub total_versions: usize,
     pub total_original_size: u64,
     pub total_compressed_size: u64,
     pub compression_ratio: f64,
+    /// Versions compressed against a trained dictionary
+    pub dictionary_versions: usize,
 }
 #[cfg(test)]
 mod tests {
@@ -240,4 +447,49 @@ mod tests {
         assert!(metadata.compressed_size < metadata.size);
         assert!(metadata.compression_level == 9);
     }
+    #[test]
+    fn test_parallel_compression() {
+        let temp_dir = tempdir().unwrap();
+        let mut storage = VersionStorage::with_config(StorageConfig {
+            storage_path: temp_dir.path().join("versions"),
+            compression_level: 1,
+            ..Default::default()
+        });
+        let content: Vec<u8> = (0..PARALLEL_CHUNK * 2 + 1000).map(|i| (i % 251) as u8).collect();
+        let single = storage.compress_data(&content, 1).unwrap();
+        storage.set_compression_threads(3);
+        let parallel = storage.compress_data(&content, 1).unwrap();
+        assert_ne!(single, parallel);
+        assert_eq!(parallel.windows(3).filter(|w| w == &[0x1f, 0x8b, 8]).count(), 3);
+        assert_eq!(storage.decompress_data(&parallel).unwrap(), content);
+        let path = PathBuf::from("disk.img");
+        storage.store_version(&path, &content, "large").unwrap();
+        assert_eq!(storage.retrieve_version("large").unwrap().0, content);
+        storage.set_compression_threads(0);
+        assert_eq!(storage.compress_data(&content, 1).unwrap(), single);
+    }
+    #[test]
+    fn test_sharded_data_and_migration() {
+        let temp_dir = tempdir().unwrap();
+        let config = StorageConfig {
+            storage_path: temp_dir.path().join("versions"),
+            ..Default::default()
+        };
+        let storage = VersionStorage::with_config(config);
+        let path = PathBuf::from("a.txt");
+        storage.store_version(&path, b"new", "new-version").unwrap();
+        let data_dir = temp_dir.path().join("versions/data");
+        let sharded = storage.get_storage_path("new-version");
+        assert_eq!(sharded.strip_prefix(&data_dir).unwrap().components().count(), 3);
+        for id in ["old-1", "old-2"] {
+            storage.store_version(&path, id.as_bytes(), id).unwrap();
+            fs::rename(storage.get_storage_path(id), data_dir.join(format!("{}.gz", id))).unwrap();
+        }
+        assert_eq!(storage.retrieve_version("old-1").unwrap().0, b"old-1");
+        assert!(!data_dir.join("old-1.gz").exists());
+        assert_eq!(storage.migrate_flat_blobs().unwrap(), 1);
+        assert!(storage.contains("old-2") && !data_dir.join("old-2.gz").exists());
+        storage.delete_version("new-version").unwrap();
+        assert!(!sharded.parent().unwrap().exists());
+    }
 }
\ No newline at end of file